@@ -1,16 +1,28 @@
 //! Defines the `FrameBuilder` trait and some default implementations of the trait.
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::frame::pack_header;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FRAME_HEADER_LEN;
+use alloc::vec::Vec;
+
+use bytes::Bytes;
+
+use crate::frame::pack_header;
+use crate::frame::FrameHeader;
+use crate::frame::FRAME_HEADER_LEN;
 
 /// A trait that provides additional methods for serializing HTTP/2 frames.
-pub(crate) trait FrameBuilder {
+pub trait FrameBuilder {
+    /// Reserve space for at least `additional` more bytes.
     fn reserve(&mut self, _additional: usize) {}
 
+    /// Write the given bytes.
     fn write_slice(&mut self, bytes: &[u8]);
 
+    /// Write the given bytes.
+    ///
+    /// Implementations may store the `Bytes` without copying.
+    fn write_bytes(&mut self, bytes: Bytes) {
+        self.write_slice(&bytes);
+    }
+
     /// Write the given frame header as the next octets (i.e. without moving the cursor to the
     /// beginning of the buffer).
     fn write_header(&mut self, header: FrameHeader) {
@@ -44,16 +56,16 @@ pub(crate) trait FrameBuilder {
     }
 }
 
-impl FrameBuilder for WriteBuffer {
-    fn write_slice(&mut self, bytes: &[u8]) {
-        self.extend_from_slice(bytes);
+impl FrameBuilder for Vec<u8> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
     }
 
-    fn write_header(&mut self, header: FrameHeader) {
-        self.extend_frame_header_buffer(pack_header(&header));
+    fn write_slice(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
     }
 
     fn write_padding(&mut self, padding_length: u8) {
-        self.extend_with_zeroes(padding_length as usize);
+        self.resize(self.len() + padding_length as usize, 0);
     }
 }
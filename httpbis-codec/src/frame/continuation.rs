@@ -1,17 +1,16 @@
 use bytes::Bytes;
 
-use crate::solicit::frame::builder::FrameBuilder;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
+use crate::frame::builder::FrameBuilder;
+use crate::frame::Frame;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
 
 use super::flags::Flag;
 use super::flags::Flags;
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::stream_id::StreamId;
+use crate::StreamId;
 
 pub const CONTINUATION_FRAME_TYPE: u8 = 0x9;
 
@@ -130,8 +129,8 @@ impl Frame for ContinuationFrame {
 }
 
 impl FrameIR for ContinuationFrame {
-    fn serialize_into(self, b: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, b: &mut B) {
         b.write_header(self.get_header());
-        b.extend_from_bytes(self.header_fragment);
+        b.write_bytes(self.header_fragment);
     }
 }
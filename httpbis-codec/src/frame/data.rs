@@ -1,20 +1,19 @@
 //! The module contains the implementation of the `DATA` frame and associated flags.
 
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::parse_padded_payload;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-
-use crate::codec::write_buffer::WriteBuffer;
-use crate::misc::BsDebug;
-use crate::solicit::stream_id::StreamId;
+use crate::frame::flags::*;
+use crate::frame::parse_padded_payload;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+
+use crate::BsDebug;
+use crate::StreamId;
 use bytes::Bytes;
-use std::fmt;
+use core::fmt;
 
 pub const DATA_FRAME_TYPE: u8 = 0x0;
 
@@ -215,15 +214,15 @@ impl Frame for DataFrame {
 }
 
 impl FrameIR for DataFrame {
-    fn serialize_into(self, b: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, b: &mut B) {
         b.write_header(self.get_header());
         if self.is_padded() {
             let pad_len: u8 = self.padding_len;
-            b.extend_from_slice(&[pad_len]);
-            b.extend_from_bytes(self.data);
+            b.write_slice(&[pad_len]);
+            b.write_bytes(self.data);
             b.write_padding(pad_len);
         } else {
-            b.extend_from_bytes(self.data);
+            b.write_bytes(self.data);
         }
     }
 }
@@ -232,12 +231,12 @@ impl FrameIR for DataFrame {
 mod tests {
     use super::DataFlag;
     use super::DataFrame;
-    use crate::solicit::frame::pack_header;
-    use crate::solicit::frame::tests::build_padded_frame_payload;
-    use crate::solicit::frame::Frame;
-    use crate::solicit::frame::FrameHeader;
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::frame::pack_header;
+    use crate::frame::tests::build_padded_frame_payload;
+    use crate::frame::tests::raw_frame_from_parts;
+    use crate::frame::Frame;
+    use crate::frame::FrameHeader;
+    use crate::frame::FrameIR;
     use bytes::Bytes;
 
     /// Tests that the `DataFrame` struct correctly interprets a DATA frame
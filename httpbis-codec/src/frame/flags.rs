@@ -1,5 +1,5 @@
-use std::fmt;
-use std::marker;
+use core::fmt;
+use core::marker;
 
 /// A trait that all HTTP/2 frame header flags need to implement.
 pub trait Flag: fmt::Debug + Copy + Clone + Sized {
@@ -1,16 +1,17 @@
 //! HTTP/2 frame type utilities.
 
-use crate::solicit::frame::continuation::CONTINUATION_FRAME_TYPE;
-use crate::solicit::frame::data::DATA_FRAME_TYPE;
-use crate::solicit::frame::goaway::GOAWAY_FRAME_TYPE;
-use crate::solicit::frame::headers::HEADERS_FRAME_TYPE;
-use crate::solicit::frame::ping::PING_FRAME_TYPE;
-use crate::solicit::frame::priority::PRIORITY_FRAME_TYPE;
-use crate::solicit::frame::push_promise::PUSH_PROMISE_FRAME_TYPE;
-use crate::solicit::frame::rst_stream::RST_STREAM_FRAME_TYPE;
-use crate::solicit::frame::settings::SETTINGS_FRAME_TYPE;
-use crate::solicit::frame::window_update::WINDOW_UPDATE_FRAME_TYPE;
-use std::fmt;
+use crate::frame::continuation::CONTINUATION_FRAME_TYPE;
+use crate::frame::data::DATA_FRAME_TYPE;
+use crate::frame::goaway::GOAWAY_FRAME_TYPE;
+use crate::frame::headers::HEADERS_FRAME_TYPE;
+use crate::frame::ping::PING_FRAME_TYPE;
+use crate::frame::priority::PRIORITY_FRAME_TYPE;
+use crate::frame::priority_update::PRIORITY_UPDATE_FRAME_TYPE;
+use crate::frame::push_promise::PUSH_PROMISE_FRAME_TYPE;
+use crate::frame::rst_stream::RST_STREAM_FRAME_TYPE;
+use crate::frame::settings::SETTINGS_FRAME_TYPE;
+use crate::frame::window_update::WINDOW_UPDATE_FRAME_TYPE;
+use core::fmt;
 
 /// All known frame types.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -35,6 +36,8 @@ pub enum HttpFrameType {
     WindowUpdate,
     /// `CONTINUATION`
     Continuation,
+    /// `PRIORITY_UPDATE`
+    PriorityUpdate,
 }
 
 impl HttpFrameType {
@@ -50,6 +53,7 @@ impl HttpFrameType {
         HttpFrameType::Goaway,
         HttpFrameType::WindowUpdate,
         HttpFrameType::Continuation,
+        HttpFrameType::PriorityUpdate,
     ];
 }
 
@@ -68,6 +72,7 @@ impl RawHttpFrameType {
     pub const GOAWAY: RawHttpFrameType = RawHttpFrameType(GOAWAY_FRAME_TYPE);
     pub const WINDOW_UPDATE: RawHttpFrameType = RawHttpFrameType(WINDOW_UPDATE_FRAME_TYPE);
     pub const CONTINUATION: RawHttpFrameType = RawHttpFrameType(CONTINUATION_FRAME_TYPE);
+    pub const PRIORITY_UPDATE: RawHttpFrameType = RawHttpFrameType(PRIORITY_UPDATE_FRAME_TYPE);
 
     fn known(&self) -> Result<HttpFrameType, u8> {
         HttpFrameType::ALL
@@ -92,6 +97,7 @@ impl HttpFrameType {
             HttpFrameType::Goaway => GOAWAY_FRAME_TYPE,
             HttpFrameType::WindowUpdate => WINDOW_UPDATE_FRAME_TYPE,
             HttpFrameType::Continuation => CONTINUATION_FRAME_TYPE,
+            HttpFrameType::PriorityUpdate => PRIORITY_UPDATE_FRAME_TYPE,
         }
     }
 }
@@ -109,6 +115,7 @@ impl fmt::Display for HttpFrameType {
             HttpFrameType::Goaway => write!(f, "GOAWAY"),
             HttpFrameType::WindowUpdate => write!(f, "WINDOW_UPDATE"),
             HttpFrameType::Continuation => write!(f, "CONTINUATION"),
+            HttpFrameType::PriorityUpdate => write!(f, "PRIORITY_UPDATE"),
         }
     }
 }
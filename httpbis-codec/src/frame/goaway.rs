@@ -2,19 +2,18 @@
 
 use bytes::Bytes;
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::error_code::ErrorCodeOrUnknown;
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::parse_stream_id;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-use crate::solicit::stream_id::StreamId;
+use crate::error_code::ErrorCodeOrUnknown;
+use crate::frame::flags::*;
+use crate::frame::parse_stream_id;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
 use crate::ErrorCode;
+use crate::StreamId;
 
 /// The minimum size for the `GOAWAY` frame payload.
 /// It is 8 octets, as the last stream id and error code are required parts of the GOAWAY frame.
@@ -134,11 +133,11 @@ impl Frame for GoawayFrame {
 }
 
 impl FrameIR for GoawayFrame {
-    fn serialize_into(self, builder: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
         builder.write_header(self.get_header());
         builder.write_u32(self.last_stream_id);
         builder.write_u32(self.error_code.0);
-        builder.extend_from_bytes(self.debug_data);
+        builder.write_bytes(self.debug_data);
     }
 }
 
@@ -146,10 +145,10 @@ impl FrameIR for GoawayFrame {
 mod tests {
     use super::GoawayFrame;
 
-    use crate::solicit::frame::Frame;
-    use crate::solicit::frame::FrameHeader;
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::frame::tests::raw_frame_from_parts;
+    use crate::frame::Frame;
+    use crate::frame::FrameHeader;
+    use crate::frame::FrameIR;
 
     use crate::ErrorCode;
     use bytes::Bytes;
@@ -1,15 +1,14 @@
 //! Implements the `PING` HTTP/2 frame.
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-use crate::solicit::stream_id::StreamId;
+use crate::frame::flags::*;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+use crate::StreamId;
 
 /// Ping frames are always 8 bytes
 pub const PING_FRAME_LEN: u32 = 8;
@@ -125,7 +124,7 @@ impl Frame for PingFrame {
 }
 
 impl FrameIR for PingFrame {
-    fn serialize_into(self, builder: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
         builder.write_header(self.get_header());
         builder.write_u32((self.opaque_data >> 32) as u32);
         builder.write_u32(self.opaque_data as u32);
@@ -136,10 +135,10 @@ impl FrameIR for PingFrame {
 mod tests {
     use super::PingFrame;
 
-    use crate::solicit::frame::Frame;
-    use crate::solicit::frame::FrameHeader;
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::frame::tests::raw_frame_from_parts;
+    use crate::frame::Frame;
+    use crate::frame::FrameHeader;
+    use crate::frame::FrameIR;
 
     #[test]
     fn test_parse_not_ack() {
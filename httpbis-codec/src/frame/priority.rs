@@ -1,15 +1,15 @@
 use bytes::Buf;
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::frame::flags::Flags;
-use crate::solicit::frame::flags::NoFlag;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-use crate::solicit::stream_id::StreamId;
+use crate::frame::flags::Flags;
+use crate::frame::flags::NoFlag;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+use crate::StreamId;
 
 /// `PRIORITY` frame.
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -28,6 +28,19 @@ pub struct PriorityFrame {
 
 pub const PRIORITY_FRAME_TYPE: u8 = 0x2;
 
+impl PriorityFrame {
+    /// Construct a `PRIORITY` frame.
+    pub fn new(stream_id: StreamId, exclusive: bool, stream_dep: StreamId, weight: u8) -> Self {
+        PriorityFrame {
+            flags: Flags::new(0),
+            stream_id,
+            exclusive,
+            stream_dep,
+            weight,
+        }
+    }
+}
+
 impl Frame for PriorityFrame {
     type FlagType = NoFlag;
 
@@ -90,7 +103,30 @@ impl Frame for PriorityFrame {
 }
 
 impl FrameIR for PriorityFrame {
-    fn serialize_into(self, _builder: &mut WriteBuffer) {
-        unimplemented!()
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
+        builder.write_header(self.get_header());
+        let exclusive = if self.exclusive { 0x80000000 } else { 0 };
+        builder.write_u32(exclusive | self.stream_dep);
+        builder.write_slice(&[self.weight]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::frame::tests::raw_frame_from_parts;
+
+    #[test]
+    fn test_serialize() {
+        let frame = PriorityFrame::new(3, true, 1, 255);
+        let expected =
+            raw_frame_from_parts(FrameHeader::new(5, 0x2, 0, 3), vec![0x80, 0, 0, 1, 255])
+                .as_ref()
+                .to_owned();
+        assert_eq!(expected, frame.clone().serialize_into_vec());
+
+        let raw = raw_frame_from_parts(FrameHeader::new(5, 0x2, 0, 3), expected[9..].to_vec());
+        assert_eq!(frame, PriorityFrame::from_raw(&raw).expect("parse"));
     }
 }
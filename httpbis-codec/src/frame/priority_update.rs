@@ -0,0 +1,154 @@
+//! Implements the `PRIORITY_UPDATE` frame (RFC 9218 section 7.1).
+
+use bytes::Bytes;
+
+use crate::frame::flags::*;
+use crate::frame::parse_stream_id;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+use crate::StreamId;
+
+/// The frame type of the `PRIORITY_UPDATE` frame.
+pub const PRIORITY_UPDATE_FRAME_TYPE: u8 = 0x10;
+
+/// `PRIORITY_UPDATE` frame, sent by client on stream zero
+/// to change priority of a request stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriorityUpdateFrame {
+    /// Frame field
+    pub prioritized_stream_id: StreamId,
+    /// Priority field value
+    pub field_value: Bytes,
+    /// Frame field
+    flags: Flags<NoFlag>,
+}
+
+impl PriorityUpdateFrame {
+    /// Construct a frame from priority field value.
+    pub fn new<B: Into<Bytes>>(
+        prioritized_stream_id: StreamId,
+        field_value: B,
+    ) -> PriorityUpdateFrame {
+        PriorityUpdateFrame {
+            prioritized_stream_id,
+            field_value: field_value.into(),
+            flags: Flags::default(),
+        }
+    }
+
+    fn payload_len(&self) -> u32 {
+        4 + self.field_value.len() as u32
+    }
+}
+
+impl Frame for PriorityUpdateFrame {
+    type FlagType = NoFlag;
+
+    fn from_raw(raw_frame: &RawFrame) -> ParseFrameResult<Self> {
+        let FrameHeader {
+            payload_len,
+            frame_type,
+            flags,
+            stream_id,
+        } = raw_frame.header();
+        if payload_len < 4 {
+            return Err(ParseFrameError::IncorrectFrameLength(payload_len));
+        }
+        if frame_type != PRIORITY_UPDATE_FRAME_TYPE {
+            return Err(ParseFrameError::InternalError);
+        }
+        if stream_id != 0 {
+            return Err(ParseFrameError::StreamIdMustBeZero(stream_id));
+        }
+
+        let prioritized_stream_id = parse_stream_id(&raw_frame.payload());
+        if prioritized_stream_id == 0 {
+            return Err(ParseFrameError::StreamIdMustBeNonZero);
+        }
+
+        Ok(PriorityUpdateFrame {
+            prioritized_stream_id,
+            field_value: raw_frame.payload().slice(4..),
+            flags: Flags::new(flags),
+        })
+    }
+
+    fn flags(&self) -> Flags<NoFlag> {
+        self.flags
+    }
+
+    fn get_stream_id(&self) -> StreamId {
+        0
+    }
+
+    fn get_header(&self) -> FrameHeader {
+        FrameHeader {
+            payload_len: self.payload_len(),
+            frame_type: PRIORITY_UPDATE_FRAME_TYPE,
+            flags: self.flags.0,
+            stream_id: 0,
+        }
+    }
+}
+
+impl FrameIR for PriorityUpdateFrame {
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
+        builder.write_header(self.get_header());
+        builder.write_u32(self.prioritized_stream_id);
+        builder.write_bytes(self.field_value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::frame::tests::raw_frame_from_parts;
+
+    #[test]
+    fn test_parse() {
+        let raw = raw_frame_from_parts(
+            FrameHeader::new(7, 0x10, 0, 0),
+            vec![0x80, 0, 0, 5, b'u', b'=', b'1'],
+        );
+        let frame = PriorityUpdateFrame::from_raw(&raw).expect("parse");
+        assert_eq!(5, frame.prioritized_stream_id);
+        assert_eq!(&b"u=1"[..], &frame.field_value[..]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let raw = raw_frame_from_parts(FrameHeader::new(4, 0x10, 0, 1), vec![0, 0, 0, 5]);
+        assert!(matches!(
+            PriorityUpdateFrame::from_raw(&raw),
+            Err(ParseFrameError::StreamIdMustBeZero(1))
+        ));
+        let raw = raw_frame_from_parts(FrameHeader::new(4, 0x10, 0, 0), vec![0, 0, 0, 0]);
+        assert!(matches!(
+            PriorityUpdateFrame::from_raw(&raw),
+            Err(ParseFrameError::StreamIdMustBeNonZero)
+        ));
+        let raw = raw_frame_from_parts(FrameHeader::new(3, 0x10, 0, 0), vec![0, 0, 0]);
+        assert!(matches!(
+            PriorityUpdateFrame::from_raw(&raw),
+            Err(ParseFrameError::IncorrectFrameLength(3))
+        ));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let frame = PriorityUpdateFrame::new(3, &b"u=0, i"[..]);
+        let expected = raw_frame_from_parts(
+            FrameHeader::new(10, 0x10, 0, 0),
+            vec![0, 0, 0, 3, b'u', b'=', b'0', b',', b' ', b'i'],
+        )
+        .as_ref()
+        .to_owned();
+        assert_eq!(expected, frame.serialize_into_vec());
+    }
+}
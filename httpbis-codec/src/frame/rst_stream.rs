@@ -1,18 +1,17 @@
 //! The module contains the implementation of the `RST_STREAM` frame.
 
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::error_code::ErrorCodeOrUnknown;
-use crate::solicit::stream_id::StreamId;
+use crate::frame::flags::*;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+
+use crate::error_code::ErrorCodeOrUnknown;
 use crate::ErrorCode;
+use crate::StreamId;
 
 /// The total allowed size for the `RST_STREAM` frame payload.
 pub const RST_STREAM_FRAME_LEN: u32 = 4;
@@ -108,7 +107,7 @@ impl Frame for RstStreamFrame {
 }
 
 impl FrameIR for RstStreamFrame {
-    fn serialize_into(self, builder: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
         builder.write_header(self.get_header());
         builder.write_u32(self.error_code.0);
     }
@@ -118,8 +117,8 @@ impl FrameIR for RstStreamFrame {
 mod tests {
     use super::RstStreamFrame;
 
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::frame::{pack_header, Frame, FrameHeader};
+    use crate::frame::FrameIR;
+    use crate::frame::{pack_header, Frame, FrameHeader};
     use crate::ErrorCode;
 
     /// A helper function that creates a new Vec containing the serialized representation of the
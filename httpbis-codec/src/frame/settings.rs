@@ -1,16 +1,16 @@
 //! The module contains the implementation of the `SETTINGS` frame and associated flags.
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-use crate::solicit::stream_id::StreamId;
-use crate::solicit::window_size::MAX_WINDOW_SIZE;
+use crate::frame::flags::*;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+use crate::window_size::MAX_WINDOW_SIZE;
+use crate::StreamId;
+use alloc::vec::Vec;
 
 pub const SETTINGS_FRAME_TYPE: u8 = 0x4;
 
@@ -32,6 +32,8 @@ pub enum HttpSetting {
     MaxFrameSize(u32),
     /// Setting
     MaxHeaderListSize(u32),
+    /// `SETTINGS_ENABLE_CONNECT_PROTOCOL` (RFC 8441)
+    EnableConnectProtocol(bool),
 }
 
 impl HttpSetting {
@@ -73,6 +75,18 @@ impl HttpSetting {
                 HttpSetting::MaxFrameSize(val)
             }
             6 => HttpSetting::MaxHeaderListSize(val),
+            8 => {
+                let b = match val {
+                    0 => false,
+                    1 => true,
+                    _ => {
+                        return Err(
+                            ParseFrameError::IncorrectSettingsEnableConnectProtocolValue(val),
+                        )
+                    }
+                };
+                HttpSetting::EnableConnectProtocol(b)
+            }
             _ => return Ok(None),
         }))
     }
@@ -104,6 +118,7 @@ impl HttpSetting {
             HttpSetting::InitialWindowSize(_) => 4,
             HttpSetting::MaxFrameSize(_) => 5,
             HttpSetting::MaxHeaderListSize(_) => 6,
+            HttpSetting::EnableConnectProtocol(_) => 8,
         }
     }
 
@@ -115,8 +130,8 @@ impl HttpSetting {
             | HttpSetting::InitialWindowSize(val)
             | HttpSetting::MaxFrameSize(val)
             | HttpSetting::MaxHeaderListSize(val) => val,
-            HttpSetting::EnablePush(true) => 1,
-            HttpSetting::EnablePush(false) => 0,
+            HttpSetting::EnablePush(true) | HttpSetting::EnableConnectProtocol(true) => 1,
+            HttpSetting::EnablePush(false) | HttpSetting::EnableConnectProtocol(false) => 0,
         }
     }
 
@@ -150,6 +165,8 @@ pub struct HttpSettings {
     pub max_frame_size: u32,
     /// Setting
     pub max_header_list_size: u32,
+    /// Setting
+    pub enable_connect_protocol: bool,
 }
 
 impl HttpSettings {
@@ -162,6 +179,7 @@ impl HttpSettings {
             HttpSetting::InitialWindowSize(s) => self.initial_window_size = s,
             HttpSetting::MaxFrameSize(s) => self.max_frame_size = s,
             HttpSetting::MaxHeaderListSize(s) => self.max_header_list_size = s,
+            HttpSetting::EnableConnectProtocol(e) => self.enable_connect_protocol = e,
         }
     }
 
@@ -284,7 +302,7 @@ impl SettingsFrame {
     ///
     /// If the frame is invalid (i.e. the length of the payload is not a
     /// multiple of 6) it returns `None`.
-    fn parse_payload(payload: &[u8]) -> ParseFrameResult<Vec<HttpSetting>> {
+    pub fn parse_payload(payload: &[u8]) -> ParseFrameResult<Vec<HttpSetting>> {
         if payload.len() % 6 != 0 {
             return Err(ParseFrameError::ProtocolError);
         }
@@ -388,10 +406,10 @@ impl Frame for SettingsFrame {
 }
 
 impl FrameIR for SettingsFrame {
-    fn serialize_into(self, b: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, b: &mut B) {
         b.write_header(self.get_header());
         for setting in &self.settings {
-            b.extend_from_slice(&setting.serialize());
+            b.write_slice(&setting.serialize());
         }
     }
 }
@@ -399,9 +417,18 @@ impl FrameIR for SettingsFrame {
 #[cfg(test)]
 mod tests {
     use super::{HttpSetting, SettingsFrame};
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::frame::{pack_header, Frame, FrameHeader};
-    use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::frame::tests::raw_frame_from_parts;
+    use crate::frame::FrameIR;
+    use crate::frame::{pack_header, Frame, FrameHeader};
+
+    #[test]
+    fn test_setting_enable_connect_protocol() {
+        let setting = HttpSetting::from_id(8, 1).unwrap().unwrap();
+        assert_eq!(HttpSetting::EnableConnectProtocol(true), setting);
+        assert_eq!(8, setting.get_id());
+        assert_eq!(1, setting.get_val());
+        assert!(HttpSetting::from_id(8, 2).is_err());
+    }
 
     /// Tests that a `SettingsFrame` correctly handles a SETTINGS frame with
     /// no ACK flag and only a single setting.
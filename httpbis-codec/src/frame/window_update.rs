@@ -1,15 +1,14 @@
 //! Implements the `WINDOW_UPDATE` HTTP/2 frame.
 
-use crate::codec::write_buffer::WriteBuffer;
-use crate::solicit::frame::flags::*;
-use crate::solicit::frame::Frame;
-use crate::solicit::frame::FrameBuilder;
-use crate::solicit::frame::FrameHeader;
-use crate::solicit::frame::FrameIR;
-use crate::solicit::frame::ParseFrameError;
-use crate::solicit::frame::ParseFrameResult;
-use crate::solicit::frame::RawFrame;
-use crate::solicit::stream_id::StreamId;
+use crate::frame::flags::*;
+use crate::frame::Frame;
+use crate::frame::FrameBuilder;
+use crate::frame::FrameHeader;
+use crate::frame::FrameIR;
+use crate::frame::ParseFrameError;
+use crate::frame::ParseFrameResult;
+use crate::frame::RawFrame;
+use crate::StreamId;
 
 /// The minimum size for the `WINDOW_UPDATE` frame payload.
 pub const WINDOW_UPDATE_FRAME_LEN: u32 = 4;
@@ -105,7 +104,7 @@ impl Frame for WindowUpdateFrame {
 }
 
 impl FrameIR for WindowUpdateFrame {
-    fn serialize_into(self, builder: &mut WriteBuffer) {
+    fn serialize_into<B: FrameBuilder>(self, builder: &mut B) {
         builder.write_header(self.get_header());
         builder.write_u32(self.increment);
     }
@@ -115,10 +114,10 @@ impl FrameIR for WindowUpdateFrame {
 mod tests {
     use super::WindowUpdateFrame;
 
-    use crate::solicit::frame::Frame;
-    use crate::solicit::frame::FrameHeader;
-    use crate::solicit::frame::FrameIR;
-    use crate::solicit::tests::common::raw_frame_from_parts;
+    use crate::frame::tests::raw_frame_from_parts;
+    use crate::frame::Frame;
+    use crate::frame::FrameHeader;
+    use crate::frame::FrameIR;
 
     #[test]
     fn test_parse_valid_connection_level() {
@@ -4,7 +4,8 @@
 //! The decoder only follows HPACK rules, without performing any additional
 //! (semantic) checks on the header name/value pairs, i.e. it considers the
 //! headers as opaque octets.
-use std::num::Wrapping;
+use alloc::vec::Vec;
+use core::num::Wrapping;
 
 use bytes::Buf;
 use bytes::Bytes;
@@ -237,11 +238,18 @@ impl Decoder {
     }
 
     /// Sets a new maximum dynamic table size for the decoder.
+    ///
+    /// This is the limit for size updates sent by the encoder
+    /// (our acknowledged `SETTINGS_HEADER_TABLE_SIZE`). The table itself
+    /// is resized by size updates, here it is only shrunk if it exceeds
+    /// the new limit, because encoder must not use larger table anyway.
     pub fn set_max_table_size(&mut self, new_max_size: usize) {
         self.max_size = new_max_size as u32;
-        self.header_table
-            .dynamic_table
-            .set_max_table_size(new_max_size);
+        if self.header_table.dynamic_table.get_max_table_size() > new_max_size {
+            self.header_table
+                .dynamic_table
+                .set_max_table_size(new_max_size);
+        }
     }
 
     /// Decodes the headers found in the given buffer `buf`. Invokes the callback `cb` for each
@@ -1089,6 +1097,31 @@ mod tests {
         }
     }
 
+    /// Tests that raising the limit does not grow the table
+    /// until encoder sends a size update.
+    #[test]
+    fn test_max_table_size_limit() {
+        let mut decoder = Decoder::new();
+        decoder.set_max_table_size(8192);
+        assert_eq!(
+            4096,
+            decoder.header_table.dynamic_table.get_max_table_size()
+        );
+
+        // size update to 8192 followed by indexed `:method: GET`
+        decoder
+            .decode_for_test(&[0x3f, 0xe1, 0x3f, 0x82][..])
+            .unwrap();
+        assert_eq!(
+            8192,
+            decoder.header_table.dynamic_table.get_max_table_size()
+        );
+
+        decoder.set_max_table_size(100);
+        assert_eq!(100, decoder.header_table.dynamic_table.get_max_table_size());
+        assert!(decoder.decode_for_test(&[0x3f, 0xe1, 0x01][..]).is_err());
+    }
+
     /// Tests that when the decoder receives an update of the max dynamic table
     /// size as 0, all entries are cleared from the dynamic table.
     #[test]
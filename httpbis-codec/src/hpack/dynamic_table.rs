@@ -1,6 +1,6 @@
+use alloc::collections::VecDeque;
 use bytes::Bytes;
-use std::collections::VecDeque;
-use std::fmt;
+use core::fmt;
 
 /// A struct representing the dynamic table that needs to be maintained by the
 /// coder.
@@ -27,6 +27,10 @@ use std::fmt;
 /// only cares about the maximum size as set by the HPACK {en,de}coder and lets
 /// *it* worry about making certain that the changes are valid according to
 /// the (current) constraints of the protocol.
+/// Default table size, corresponds to the initial value
+/// of HTTP/2 `SETTINGS_HEADER_TABLE_SIZE`.
+pub(crate) const DEFAULT_MAX_TABLE_SIZE: usize = 4096;
+
 pub(crate) struct DynamicTable {
     table: VecDeque<(Bytes, Bytes)>,
     size: usize,
@@ -36,9 +40,7 @@ pub(crate) struct DynamicTable {
 impl DynamicTable {
     /// Creates a new empty dynamic table with a default size.
     pub fn new() -> DynamicTable {
-        // The default maximum size corresponds to the default HTTP/2
-        // setting
-        DynamicTable::with_size(4096)
+        DynamicTable::with_size(DEFAULT_MAX_TABLE_SIZE)
     }
 
     /// Creates a new empty dynamic table with the given maximum size.
@@ -79,7 +81,6 @@ impl DynamicTable {
     }
 
     /// Returns the maximum size of the table in octets.
-    #[cfg(test)]
     pub fn get_max_table_size(&self) -> usize {
         self.max_size
     }
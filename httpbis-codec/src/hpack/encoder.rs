@@ -3,13 +3,18 @@
 //!
 //! Clients should use the `Encoder` struct as the API for performing HPACK
 //! encoding.
-use std::num::Wrapping;
+use core::cmp;
+use core::num::Wrapping;
 
 use bytes::Bytes;
 
 use super::HeaderTable;
+use crate::hpack::dynamic_table::DEFAULT_MAX_TABLE_SIZE;
+use crate::hpack::huffman::huffman_encode_into;
+use crate::hpack::huffman::huffman_encoded_len;
 use crate::hpack::static_table::StaticTable;
 use crate::hpack::HeaderValueFound;
+use alloc::vec::Vec;
 use bytes::BytesMut;
 
 pub trait EncodeBuf {
@@ -103,6 +108,15 @@ pub fn encode_integer(value: usize, prefix_size: u8) -> Vec<u8> {
 pub struct Encoder {
     /// The header table represents the encoder's context
     header_table: HeaderTable,
+    /// Add headers to the dynamic table
+    use_dynamic_table: bool,
+    /// Smallest and last table size set since last header block,
+    /// emitted as size updates at the beginning of the next block
+    pending_size_update: Option<(usize, usize)>,
+    /// Names of headers always encoded as never-indexed literals
+    sensitive_names: Vec<Bytes>,
+    /// Huffman-encode strings when it makes them shorter
+    use_huffman: bool,
 }
 
 impl Encoder {
@@ -111,9 +125,60 @@ impl Encoder {
     pub fn new() -> Encoder {
         Encoder {
             header_table: HeaderTable::with_static_table(StaticTable::new()),
+            use_dynamic_table: true,
+            pending_size_update: None,
+            sensitive_names: Vec::new(),
+            use_huffman: false,
         }
     }
 
+    /// Creates an `Encoder` which never adds headers to the dynamic table,
+    /// so it keeps no per-connection state.
+    pub fn without_dynamic_table() -> Encoder {
+        Encoder {
+            use_dynamic_table: false,
+            ..Encoder::new()
+        }
+    }
+
+    /// Apply `SETTINGS_HEADER_TABLE_SIZE` received from the peer.
+    ///
+    /// Encoder uses at most default 4096 bytes even if peer allows more.
+    /// When table size changes, dynamic table size update is emitted
+    /// at the beginning of the next header block (RFC 7541 section 4.2).
+    pub fn set_max_table_size(&mut self, peer_max_size: usize) {
+        let new_size = cmp::min(peer_max_size, DEFAULT_MAX_TABLE_SIZE);
+        if new_size == self.header_table.dynamic_table.get_max_table_size() {
+            return;
+        }
+        self.header_table.dynamic_table.set_max_table_size(new_size);
+        self.pending_size_update = Some(match self.pending_size_update {
+            Some((smallest, _)) => (cmp::min(smallest, new_size), new_size),
+            None => (new_size, new_size),
+        });
+    }
+
+    /// Huffman-encode string literals when the encoded string is shorter,
+    /// default is `false`.
+    pub fn set_huffman(&mut self, use_huffman: bool) {
+        self.use_huffman = use_huffman;
+    }
+
+    /// Names of headers which are always encoded as never-indexed literals
+    /// (RFC 7541 section 6.2.3), in addition to headers marked sensitive.
+    ///
+    /// Names are matched case-insensitively.
+    pub fn set_sensitive_names<S: AsRef<str>>(&mut self, names: &[S]) {
+        self.sensitive_names = names
+            .iter()
+            .map(|n| Bytes::from(n.as_ref().to_ascii_lowercase()))
+            .collect();
+    }
+
+    fn is_sensitive_name(&self, name: &[u8]) -> bool {
+        self.sensitive_names.iter().any(|n| &n[..] == name)
+    }
+
     /// Encodes the given headers using the HPACK rules and returns a newly
     /// allocated `Vec` containing the bytes representing the encoded header
     /// set.
@@ -143,6 +208,31 @@ impl Encoder {
         encoded.freeze()
     }
 
+    /// Encodes `(name, value, sensitive)` triples,
+    /// sensitive headers are encoded as never-indexed literals.
+    pub fn encode_headers<'b, I>(&mut self, headers: I) -> Bytes
+    where
+        I: IntoIterator<Item = (&'b [u8], &'b [u8], bool)>,
+    {
+        let mut encoded = BytesMut::new();
+        self.encode_headers_into(headers, &mut encoded);
+        encoded.freeze()
+    }
+
+    /// Like `encode_into`, but also respects the per-header sensitive flag.
+    pub fn encode_headers_into<'b, I, W>(&mut self, headers: I, writer: &mut W)
+    where
+        I: IntoIterator<Item = (&'b [u8], &'b [u8], bool)>,
+        W: EncodeBuf,
+    {
+        self.encode_pending_size_update(writer);
+
+        for (name, value, sensitive) in headers {
+            let sensitive = sensitive || self.is_sensitive_name(name);
+            self.encode_header_into((name, value), sensitive, writer);
+        }
+    }
+
     /// Encodes the given headers into the given `io::Write` instance. If the io::Write raises an
     /// Error at any point, this error is propagated out. Any changes to the internal state of the
     /// encoder will not be rolled back, though, so care should be taken to ensure that the paired
@@ -152,25 +242,57 @@ impl Encoder {
         I: IntoIterator<Item = (&'b [u8], &'b [u8])>,
         W: EncodeBuf,
     {
+        self.encode_pending_size_update(writer);
+
         for header in headers {
-            self.encode_header_into(header, writer);
+            let sensitive = self.is_sensitive_name(header.0);
+            self.encode_header_into(header, sensitive, writer);
         }
     }
 
+    fn encode_pending_size_update<W: EncodeBuf>(&mut self, writer: &mut W) {
+        if let Some((smallest, last)) = self.pending_size_update.take() {
+            if smallest < last {
+                self.encode_size_update(smallest, writer);
+            }
+            self.encode_size_update(last, writer);
+        }
+    }
+
+    /// Encodes dynamic table size update (RFC 7541 section 6.3).
+    fn encode_size_update<W: EncodeBuf>(&self, size: usize, buf: &mut W) {
+        encode_integer_into(size, 5, 0x20, buf);
+    }
+
     /// Encodes a single given header into the given `io::Write` instance.
     ///
     /// Any errors are propagated, similarly to the `encode_into` method, and it is the callers
     /// responsiblity to make sure that the paired encoder sees them too.
-    fn encode_header_into<W: EncodeBuf>(&mut self, header: (&[u8], &[u8]), writer: &mut W) {
+    ///
+    /// Sensitive headers are encoded as never-indexed literals even if
+    /// the same header is already present in the table.
+    fn encode_header_into<W: EncodeBuf>(
+        &mut self,
+        header: (&[u8], &[u8]),
+        sensitive: bool,
+        writer: &mut W,
+    ) {
+        if sensitive {
+            self.encode_never_indexed(header, writer);
+            return;
+        }
+
         match self.header_table.find_header(header) {
             None => {
                 // The name of the header is in no tables: need to encode
                 // it with both a literal name and value.
-                self.encode_literal(&header, true, writer);
-                self.header_table.add_header(
-                    Bytes::copy_from_slice(header.0),
-                    Bytes::copy_from_slice(header.1),
-                );
+                self.encode_literal(&header, self.use_dynamic_table, writer);
+                if self.use_dynamic_table {
+                    self.header_table.add_header(
+                        Bytes::copy_from_slice(header.0),
+                        Bytes::copy_from_slice(header.1),
+                    );
+                }
             }
             Some((index, HeaderValueFound::NameOnlyFound)) => {
                 // The name of the header is at the given index, but the
@@ -210,13 +332,38 @@ impl Encoder {
         self.encode_string_literal(&header.1, buf);
     }
 
+    /// Encodes a header as a literal never indexed (RFC 7541 section 6.2.3),
+    /// referencing the name from the header table if possible.
+    fn encode_never_indexed<W: EncodeBuf>(&mut self, header: (&[u8], &[u8]), buf: &mut W) {
+        match self.header_table.find_header(header) {
+            Some((index, _)) => {
+                encode_integer_into(index, 4, 0x10, buf);
+            }
+            None => {
+                buf.write_u8(0x10);
+                self.encode_string_literal(header.0, buf);
+            }
+        }
+        self.encode_string_literal(header.1, buf);
+    }
+
     /// Encodes a string literal and places the result in the given buffer
     /// `buf`.
     ///
-    /// The function does not consider Huffman encoding for now, but always
-    /// produces a string literal representations, according to the HPACK spec
-    /// section 5.2.
+    /// If Huffman encoding is enabled and makes the string shorter, the string
+    /// is Huffman-encoded directly into `buf`, otherwise raw octets are written,
+    /// according to the HPACK spec section 5.2.
     fn encode_string_literal<W: EncodeBuf>(&mut self, octet_str: &[u8], buf: &mut W) {
+        if self.use_huffman {
+            let huffman_len = huffman_encoded_len(octet_str);
+            if huffman_len < octet_str.len() {
+                buf.reserve(huffman_len + 1);
+                encode_integer_into(huffman_len, 7, 0x80, buf);
+                huffman_encode_into(octet_str, buf);
+                return;
+            }
+        }
+
         buf.reserve(octet_str.len() + 1);
         encode_integer_into(octet_str.len(), 7, 0, buf);
         buf.write_all(octet_str);
@@ -323,6 +470,33 @@ mod tests {
         debug!("{:?}", result);
     }
 
+    /// Tests that encoder without dynamic table does not index headers
+    /// and output can be decoded with a decoder with zero table size.
+    #[test]
+    fn test_without_dynamic_table() {
+        let mut encoder: Encoder = Encoder::without_dynamic_table();
+        let headers = vec![
+            (b":method".to_vec(), b"GET".to_vec()),
+            (b"custom-key".to_vec(), b"custom-value".to_vec()),
+        ];
+
+        let first = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        let second = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        assert_eq!(first, second);
+        assert_eq!(0, encoder.header_table.dynamic_table.len());
+
+        let mut decoder = Decoder::new();
+        decoder.set_max_table_size(0);
+        let decoded = decoder.decode_for_test(&second[..]).unwrap();
+        assert_eq!(
+            headers,
+            decoded
+                .into_iter()
+                .map(|(k, v)| (k.to_vec(), v.to_vec()))
+                .collect::<Vec<_>>()
+        );
+    }
+
     /// Tests that when a header gets added to the dynamic table, the encoder
     /// will use the index, instead of the literal representation on the next
     /// encoding of the same header.
@@ -402,4 +576,96 @@ mod tests {
 
         assert!(is_decodable(&result, &headers));
     }
+
+    /// Tests that table size change is emitted as size update
+    /// at the beginning of the next header block only.
+    #[test]
+    fn test_size_update() {
+        let mut encoder = Encoder::new();
+        let mut decoder = Decoder::new();
+        decoder.set_max_table_size(256);
+        let headers = vec![(b"custom-key".to_vec(), b"custom-value".to_vec())];
+
+        encoder.set_max_table_size(256);
+        let first = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        // 001 prefix, 256 = 31 + 225
+        assert_eq!(&[0x3f, 0xe1, 0x01], &first[..3]);
+        decoder.decode_for_test(&first[..]).unwrap();
+
+        let second = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        assert_eq!(&[0xbe], &second[..]);
+        decoder.decode_for_test(&second[..]).unwrap();
+
+        // smallest size is emitted before the final size
+        encoder.set_max_table_size(0);
+        encoder.set_max_table_size(100);
+        let third = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        assert_eq!(&[0x20, 0x3f, 0x45], &third[..3]);
+        decoder.decode_for_test(&third[..]).unwrap();
+
+        // encoder does not grow above default size
+        encoder.set_max_table_size(100_000);
+        let fourth = encoder.encode_for_test(headers.iter().map(|h| (&h.0[..], &h.1[..])));
+        assert_eq!(&[0x3f, 0xe1, 0x1f], &fourth[..3]);
+    }
+
+    #[test]
+    fn test_huffman() {
+        let mut encoder = Encoder::new();
+        encoder.set_huffman(true);
+        let mut decoder = Decoder::new();
+
+        let headers: Vec<(&[u8], &[u8])> = vec![
+            (b":authority", b"www.example.com"),
+            // Huffman encoding is longer
+            (b"x-binary", b"\xff\xfe"),
+        ];
+        let encoded = encoder.encode_for_test(headers.iter().cloned());
+        // RFC 7541 C.4.1, but not indexed
+        assert_eq!(
+            &b"\x01\x8c\xf1\xe3\xc2\xe5\xf2\x3a\x6b\xa0\xab\x90\xf4\xff"[..],
+            &encoded[..14]
+        );
+        // new name is Huffman-encoded, value is raw
+        assert_eq!(&[0x40, 0x86], &encoded[14..16]);
+        assert_eq!(&b"\x02\xff\xfe"[..], &encoded[22..]);
+
+        let decoded = decoder.decode_for_test(&encoded).unwrap();
+        assert_eq!(
+            headers,
+            decoded
+                .iter()
+                .map(|(n, v)| (&n[..], &v[..]))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_sensitive_never_indexed() {
+        let mut encoder = Encoder::new();
+        encoder.set_sensitive_names(&["Cookie"]);
+        let mut decoder = Decoder::new();
+
+        let headers: Vec<(&[u8], &[u8], bool)> = vec![
+            (b"authorization", b"secret", true),
+            (b"cookie", b"a=b", false),
+            (b"x-token", b"t", true),
+            (b"x-custom", b"c", false),
+        ];
+
+        for _ in 0..2 {
+            let encoded = encoder.encode_headers(headers.iter().cloned());
+            // `authorization` name is static table entry 23
+            assert_eq!(0x1f, encoded[0]);
+            assert_eq!(8, encoded[1]);
+            let decoded = decoder.decode(encoded).unwrap();
+            assert_eq!(4, decoded.len());
+        }
+
+        // only the non-sensitive header is indexed
+        let encoded = encoder.encode_headers(vec![(&b"x-custom"[..], &b"c"[..], false)]);
+        assert_eq!(&[0xbe], &encoded[..]);
+        let encoded = encoder.encode_headers(vec![(&b"x-token"[..], &b"t"[..], false)]);
+        assert_eq!(0x40, encoded[0]);
+    }
 }
@@ -2,7 +2,10 @@
 //! strings, under the Huffman code defined by HPACK.
 //! (HPACK-draft-10, Appendix B)
 
-use std::collections::HashMap;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::hpack::encoder::EncodeBuf;
 
 /// Represents a symbol that can be inserted into a Huffman-encoded octet
 /// string.
@@ -43,7 +46,7 @@ pub type HuffmanDecoderResult = Result<Vec<u8>, HuffmanDecoderError>;
 
 /// A simple implementation of a Huffman code decoder.
 pub struct HuffmanDecoder {
-    table: HashMap<u8, HashMap<u32, HuffmanCodeSymbol>>,
+    table: BTreeMap<u8, BTreeMap<u32, HuffmanCodeSymbol>>,
     // The representation of the EOS: the left-aligned code representation and
     // the actual length of the codepoint, as a tuple.
     eos_codepoint: (u32, u8),
@@ -57,12 +60,12 @@ impl HuffmanDecoder {
             panic!("Invalid Huffman code table. It must define exactly 257 symbols.");
         }
 
-        let mut decoder_table: HashMap<u8, HashMap<u32, HuffmanCodeSymbol>> = HashMap::new();
+        let mut decoder_table: BTreeMap<u8, BTreeMap<u32, HuffmanCodeSymbol>> = BTreeMap::new();
         let mut eos_codepoint: Option<(u32, u8)> = None;
 
         for (symbol, &(code, code_len)) in table.iter().enumerate() {
             if !decoder_table.contains_key(&code_len) {
-                decoder_table.insert(code_len, HashMap::new());
+                decoder_table.insert(code_len, BTreeMap::new());
             }
             let subtable = decoder_table.get_mut(&code_len).unwrap();
             let huff_symbol = HuffmanCodeSymbol::new(symbol);
@@ -163,6 +166,45 @@ impl HuffmanDecoder {
     }
 }
 
+/// Length of the octet string `buf` encoded with the HPACK Huffman code.
+pub fn huffman_encoded_len(buf: &[u8]) -> usize {
+    let bits: usize = buf
+        .iter()
+        .map(|&b| HUFFMAN_CODE_TABLE[b as usize].1 as usize)
+        .sum();
+    bits.div_ceil(8)
+}
+
+/// Encodes the octet string `buf` with the HPACK Huffman code into `writer`,
+/// padding the last octet with the most significant bits of EOS.
+///
+/// Exactly `huffman_encoded_len(buf)` bytes are written. Codes are
+/// accumulated in a 64-bit register and flushed 32 bits at a time,
+/// so no intermediate buffer is allocated.
+pub fn huffman_encode_into<W: EncodeBuf>(buf: &[u8], writer: &mut W) {
+    let mut bits: u64 = 0;
+    let mut bits_len: u32 = 0;
+
+    for &b in buf {
+        let (code, code_len) = HUFFMAN_CODE_TABLE[b as usize];
+        // At most 31 bits are pending and codes are at most 30 bits long
+        bits = (bits << code_len) | code as u64;
+        bits_len += code_len as u32;
+        if bits_len >= 32 {
+            bits_len -= 32;
+            writer.write_all(&((bits >> bits_len) as u32).to_be_bytes());
+        }
+    }
+
+    if bits_len != 0 {
+        let pad_len = (8 - bits_len % 8) % 8;
+        bits = (bits << pad_len) | ((1 << pad_len) - 1);
+        bits_len += pad_len;
+        let bytes = (bits << (64 - bits_len)).to_be_bytes();
+        writer.write_all(&bytes[..bits_len as usize / 8]);
+    }
+}
+
 /// A helper struct that represents an iterator over individual bits of all
 /// bytes found in a wrapped Iterator over bytes.
 /// Bits are represented as `bool`s, where `true` corresponds to a set bit and
@@ -485,6 +527,8 @@ static HUFFMAN_CODE_TABLE: &'static [(u32, u8)] = &[
 
 #[cfg(test)]
 mod tests {
+    use super::huffman_encode_into;
+    use super::huffman_encoded_len;
     use super::BitIterator;
     use super::HuffmanDecoder;
     use super::HuffmanDecoderError;
@@ -708,4 +752,39 @@ mod tests {
             );
         }
     }
+
+    /// Tests encoding with examples from HPACK spec Appendix C.4 and C.6.
+    #[test]
+    fn test_huffman_encode() {
+        let examples: &[(&[u8], &[u8])] = &[
+            (b"", b""),
+            (
+                b"www.example.com",
+                b"\xf1\xe3\xc2\xe5\xf2\x3a\x6b\xa0\xab\x90\xf4\xff",
+            ),
+            (b"no-cache", b"\xa8\xeb\x10\x64\x9c\xbf"),
+            (b"custom-key", b"\x25\xa8\x49\xe9\x5b\xa9\x7d\x7f"),
+            (b"302", b"\x64\x02"),
+            (
+                b"Mon, 21 Oct 2013 20:13:21 GMT",
+                b"\xd0\x7a\xbe\x94\x10\x54\xd4\x44\xa8\x20\x05\x95\x04\x0b\x81\x66\xe0\x82\xa6\x2d\x1b\xff",
+            ),
+        ];
+        for &(plain, encoded) in examples {
+            let mut buf = Vec::new();
+            huffman_encode_into(plain, &mut buf);
+            assert_eq!(encoded, &buf[..]);
+            assert_eq!(encoded.len(), huffman_encoded_len(plain));
+        }
+    }
+
+    /// Tests that all octets survive encoding and decoding.
+    #[test]
+    fn test_huffman_encode_decode_all_octets() {
+        let plain: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        let mut buf = Vec::new();
+        huffman_encode_into(&plain, &mut buf);
+        assert_eq!(huffman_encoded_len(&plain), buf.len());
+        assert_eq!(plain, HuffmanDecoder::new().decode(&buf).unwrap());
+    }
 }
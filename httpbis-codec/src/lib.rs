@@ -0,0 +1,61 @@
+//! HTTP/2 wire layer: frame parsing/serialization and HPACK.
+//!
+//! The crate only needs `alloc`: nothing here depends on tokio, sockets
+//! or the connection machinery of `httpbis`, so it can be reused
+//! with other IO implementations.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate log;
+
+mod bs_debug;
+mod error_code;
+pub mod frame;
+pub mod hpack;
+mod stream_id;
+pub mod window_size;
+
+pub use crate::bs_debug::BsDebug;
+pub use crate::error_code::ErrorCode;
+pub use crate::stream_id::StreamId;
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use crate::frame::*;
+    use crate::hpack;
+
+    #[test]
+    fn frame_round_trip() {
+        let mut frame = DataFrame::with_data(3, Bytes::from_static(b"hello"));
+        frame.set_flag(DataFlag::EndStream);
+
+        let raw = RawFrame::parse(frame.clone().serialize_into_vec()).unwrap();
+        match HttpFrame::from_raw(&raw).unwrap() {
+            HttpFrame::Data(parsed) => assert_eq!(frame, parsed),
+            f => panic!("wrong frame: {:?}", f),
+        }
+    }
+
+    #[test]
+    fn hpack_round_trip() {
+        let headers: Vec<(&[u8], &[u8])> = vec![(b":path", b"/"), (b"x-a", b"b")];
+        let encoded = hpack::Encoder::new().encode(headers.iter().cloned());
+        let decoded = hpack::Decoder::new().decode(encoded).unwrap();
+        let expected: Vec<(Vec<u8>, Vec<u8>)> = headers
+            .into_iter()
+            .map(|(n, v)| (n.to_vec(), v.to_vec()))
+            .collect();
+        assert_eq!(
+            expected,
+            decoded
+                .into_iter()
+                .map(|(n, v)| (n.to_vec(), v.to_vec()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
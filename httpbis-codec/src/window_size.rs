@@ -0,0 +1,14 @@
+//! Window size constants
+
+/// A sender MUST NOT allow a flow-control window to exceed 231-1 octets. If a sender receives
+/// a WINDOW_UPDATE that causes a flow-control window to exceed this maximum,
+/// it MUST terminate either the stream or the connection, as appropriate. For streams,
+/// the sender sends a RST_STREAM with an error code of FLOW_CONTROL_ERROR; for the connection,
+/// a GOAWAY frame with an error code of FLOW_CONTROL_ERROR is sent.
+pub const MAX_WINDOW_SIZE: u32 = 0x7fffffff;
+
+#[test]
+fn test_max_window_size_is_i32_max() {
+    use core::i32;
+    assert_eq!(i32::max_value(), MAX_WINDOW_SIZE as i32);
+}
@@ -0,0 +1,133 @@
+//! HTTP/1.1 `CONNECT` proxy for client tests.
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::BIND_HOST;
+
+/// Proxy listening on a local port and tunneling connections
+/// to authorities requested with `CONNECT`.
+///
+/// Proxy threads run until the test process exits.
+pub struct ConnectProxy {
+    port: u16,
+    authorities: Arc<Mutex<Vec<String>>>,
+}
+
+impl ConnectProxy {
+    /// Requests without `Proxy-Authorization` equal to `proxy_authorization`
+    /// are rejected with `407`.
+    pub fn new(proxy_authorization: Option<&str>) -> ConnectProxy {
+        let listener = TcpListener::bind((BIND_HOST, 0)).expect("bind");
+        let port = listener.local_addr().expect("local_addr").port();
+        let authorities = Arc::new(Mutex::new(Vec::new()));
+
+        let proxy_authorization = proxy_authorization.map(|s| s.to_owned());
+        let authorities_copy = authorities.clone();
+        thread::Builder::new()
+            .name("connect-proxy".to_owned())
+            .spawn(move || {
+                for client in listener.incoming() {
+                    let client = match client {
+                        Ok(client) => client,
+                        Err(e) => {
+                            warn!("connect proxy accept failed: {:?}", e);
+                            return;
+                        }
+                    };
+                    if let Err(e) =
+                        tunnel(client, proxy_authorization.as_deref(), &authorities_copy)
+                    {
+                        debug!("connect proxy tunnel failed: {:?}", e);
+                    }
+                }
+            })
+            .expect("spawn");
+
+        ConnectProxy { port, authorities }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Authorities requested so far.
+    pub fn authorities(&self) -> Vec<String> {
+        self.authorities.lock().unwrap().clone()
+    }
+}
+
+fn read_head(mut stream: &TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        stream.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn tunnel(
+    mut client: TcpStream,
+    proxy_authorization: Option<&str>,
+    authorities: &Mutex<Vec<String>>,
+) -> io::Result<()> {
+    let head = read_head(&client)?;
+    let mut lines = head.split("\r\n");
+    let authority = match lines.next().unwrap().split(' ').collect::<Vec<_>>()[..] {
+        ["CONNECT", authority, "HTTP/1.1"] => authority.to_owned(),
+        _ => {
+            client.write_all(b"HTTP/1.1 405 Method Not Allowed\r\n\r\n")?;
+            return Ok(());
+        }
+    };
+
+    if let Some(expected) = proxy_authorization {
+        let authorized = lines.any(|line| match line.find(':') {
+            Some(colon) => {
+                line[..colon].eq_ignore_ascii_case("proxy-authorization")
+                    && line[colon + 1..].trim() == expected
+            }
+            None => false,
+        });
+        if !authorized {
+            client.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")?;
+            return Ok(());
+        }
+    }
+
+    authorities.lock().unwrap().push(authority.clone());
+
+    let server = match TcpStream::connect(&authority) {
+        Ok(server) => server,
+        Err(e) => {
+            client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n")?;
+            return Err(e);
+        }
+    };
+    client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+
+    spawn_copy(client.try_clone()?, server.try_clone()?);
+    spawn_copy(server, client);
+    Ok(())
+}
+
+pub(crate) fn spawn_copy(mut from: TcpStream, mut to: TcpStream) {
+    thread::Builder::new()
+        .name("connect-proxy-copy".to_owned())
+        .spawn(move || {
+            if let Err(e) = io::copy(&mut from, &mut to) {
+                debug!("connect proxy copy end: {:?}", e);
+            }
+            drop(from.shutdown(Shutdown::Both));
+            drop(to.shutdown(Shutdown::Both));
+        })
+        .expect("spawn");
+}
@@ -0,0 +1,238 @@
+//! TCP proxy injecting faults into HTTP/2 frame stream.
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::Shutdown;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use httpbis::for_test::solicit::frame::RawHttpFrameType;
+use httpbis::for_test::solicit::frame::FRAME_HEADER_LEN;
+
+use crate::BIND_HOST;
+
+const PREFACE_LEN: usize = 24;
+
+/// Direction of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultDirection {
+    ClientToServer,
+    ServerToClient,
+}
+
+/// What to do with the matched frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Forward the frame after a delay.
+    Delay(Duration),
+    /// Do not forward the frame.
+    Drop,
+    /// Forward the frame twice.
+    Duplicate,
+    /// Forward only first given number of bytes of the frame, then close the connection.
+    Truncate(usize),
+    /// Flip all bits of the frame payload.
+    Corrupt,
+    /// Close the connection instead of forwarding the frame.
+    Close,
+}
+
+/// Apply `fault` to `nth` (zero-based) frame matching direction and type.
+///
+/// Frames are counted across all connections made through the proxy,
+/// so each rule fires at most once.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    pub direction: FaultDirection,
+    /// Match any frame type if `None`.
+    pub frame_type: Option<RawHttpFrameType>,
+    pub nth: usize,
+    pub fault: Fault,
+}
+
+impl FaultRule {
+    pub fn new(
+        direction: FaultDirection,
+        frame_type: Option<RawHttpFrameType>,
+        nth: usize,
+        fault: Fault,
+    ) -> FaultRule {
+        FaultRule {
+            direction,
+            frame_type,
+            nth,
+            fault,
+        }
+    }
+}
+
+struct RuleState {
+    rule: FaultRule,
+    seen: usize,
+}
+
+struct Script {
+    rules: Vec<RuleState>,
+}
+
+impl Script {
+    fn fault_for(
+        &mut self,
+        direction: FaultDirection,
+        frame_type: RawHttpFrameType,
+    ) -> Option<Fault> {
+        let mut fault = None;
+        for state in &mut self.rules {
+            if state.rule.direction != direction {
+                continue;
+            }
+            if let Some(t) = state.rule.frame_type {
+                if t != frame_type {
+                    continue;
+                }
+            }
+            if state.seen == state.rule.nth && fault.is_none() {
+                fault = Some(state.rule.fault);
+            }
+            state.seen += 1;
+        }
+        fault
+    }
+}
+
+/// Proxy listening on a local port and forwarding connections to `target_port`.
+///
+/// Proxy threads run until the test process exits.
+pub struct FaultProxy {
+    port: u16,
+}
+
+impl FaultProxy {
+    pub fn new(target_port: u16, rules: Vec<FaultRule>) -> FaultProxy {
+        let listener = TcpListener::bind((BIND_HOST, 0)).expect("bind");
+        let port = listener.local_addr().expect("local_addr").port();
+
+        let script = Arc::new(Mutex::new(Script {
+            rules: rules
+                .into_iter()
+                .map(|rule| RuleState { rule, seen: 0 })
+                .collect(),
+        }));
+
+        thread::Builder::new()
+            .name("fault-proxy".to_owned())
+            .spawn(move || {
+                for client in listener.incoming() {
+                    let client = match client {
+                        Ok(client) => client,
+                        Err(e) => {
+                            warn!("fault proxy accept failed: {:?}", e);
+                            return;
+                        }
+                    };
+                    let server = match TcpStream::connect((BIND_HOST, target_port)) {
+                        Ok(server) => server,
+                        Err(e) => {
+                            warn!("fault proxy failed to connect to target: {:?}", e);
+                            continue;
+                        }
+                    };
+                    spawn_pipe(
+                        client.try_clone().expect("clone"),
+                        server.try_clone().expect("clone"),
+                        FaultDirection::ClientToServer,
+                        script.clone(),
+                    );
+                    spawn_pipe(
+                        server,
+                        client,
+                        FaultDirection::ServerToClient,
+                        script.clone(),
+                    );
+                }
+            })
+            .expect("spawn");
+
+        FaultProxy { port }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+fn spawn_pipe(
+    from: TcpStream,
+    to: TcpStream,
+    direction: FaultDirection,
+    script: Arc<Mutex<Script>>,
+) {
+    thread::Builder::new()
+        .name(format!("fault-proxy-{:?}", direction))
+        .spawn(move || {
+            if let Err(e) = pipe(&from, &to, direction, &script) {
+                debug!("fault proxy {:?} pipe end: {:?}", direction, e);
+            }
+            drop(from.shutdown(Shutdown::Both));
+            drop(to.shutdown(Shutdown::Both));
+        })
+        .expect("spawn");
+}
+
+fn pipe(
+    mut from: &TcpStream,
+    mut to: &TcpStream,
+    direction: FaultDirection,
+    script: &Mutex<Script>,
+) -> io::Result<()> {
+    if direction == FaultDirection::ClientToServer {
+        let mut preface = [0; PREFACE_LEN];
+        from.read_exact(&mut preface)?;
+        to.write_all(&preface)?;
+    }
+
+    loop {
+        let mut frame = vec![0; FRAME_HEADER_LEN];
+        from.read_exact(&mut frame)?;
+        let payload_len =
+            ((frame[0] as usize) << 16) | ((frame[1] as usize) << 8) | (frame[2] as usize);
+        let frame_type = RawHttpFrameType(frame[3]);
+        frame.resize(FRAME_HEADER_LEN + payload_len, 0);
+        from.read_exact(&mut frame[FRAME_HEADER_LEN..])?;
+
+        let fault = script.lock().unwrap().fault_for(direction, frame_type);
+        match fault {
+            None => to.write_all(&frame)?,
+            Some(fault) => {
+                debug!("fault proxy {:?} {:?}: {:?}", direction, frame_type, fault);
+                match fault {
+                    Fault::Delay(delay) => {
+                        thread::sleep(delay);
+                        to.write_all(&frame)?;
+                    }
+                    Fault::Drop => {}
+                    Fault::Duplicate => {
+                        to.write_all(&frame)?;
+                        to.write_all(&frame)?;
+                    }
+                    Fault::Truncate(len) => {
+                        to.write_all(&frame[..len.min(frame.len())])?;
+                        return Ok(());
+                    }
+                    Fault::Corrupt => {
+                        for b in &mut frame[FRAME_HEADER_LEN..] {
+                            *b = !*b;
+                        }
+                        to.write_all(&frame)?;
+                    }
+                    Fault::Close => return Ok(()),
+                }
+            }
+        }
+    }
+}
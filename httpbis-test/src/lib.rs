@@ -18,16 +18,24 @@ mod t;
 mod assert_types;
 mod bytes_ext;
 mod client;
+mod connect_proxy;
+mod fault_proxy;
+mod metrics;
 #[path = "../../src/misc.rs"]
 mod misc;
 pub mod openssl_test_key_gen;
 mod server_one_conn;
 mod server_test;
+mod socks5_proxy;
 mod task;
 mod tester;
 
+pub use self::connect_proxy::*;
+pub use self::fault_proxy::*;
+pub use self::metrics::*;
 pub use self::server_one_conn::*;
 pub use self::server_test::*;
+pub use self::socks5_proxy::*;
 pub use self::tester::*;
 pub use client::*;
 pub use misc::*;
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use httpbis::Metric;
+use httpbis::MetricLabel;
+use httpbis::Metrics;
+
+/// `Metrics` sink storing counter and gauge values,
+/// and number of samples for histograms.
+#[derive(Default)]
+pub struct TestMetrics {
+    values: Mutex<HashMap<(Metric, Vec<MetricLabel>), i64>>,
+}
+
+impl TestMetrics {
+    pub fn get(&self, metric: Metric, labels: &[MetricLabel]) -> i64 {
+        let values = self.values.lock().unwrap();
+        values.get(&(metric, labels.to_vec())).cloned().unwrap_or(0)
+    }
+
+    fn add(&self, metric: Metric, labels: &[MetricLabel], value: i64) {
+        let mut values = self.values.lock().unwrap();
+        *values.entry((metric, labels.to_vec())).or_insert(0) += value;
+    }
+
+    /// Wait until metric reaches value, metrics of stream
+    /// and connection close are reported asynchronously.
+    pub fn wait(&self, metric: Metric, labels: &[MetricLabel], value: i64) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while self.get(metric, labels) != value {
+            assert!(
+                Instant::now() < deadline,
+                "{} {:?}: {}",
+                metric,
+                labels,
+                self.get(metric, labels)
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl Metrics for TestMetrics {
+    fn counter(&self, metric: Metric, labels: &[MetricLabel], value: u64) {
+        self.add(metric, labels, value as i64);
+    }
+
+    fn gauge(&self, metric: Metric, labels: &[MetricLabel], delta: i64) {
+        self.add(metric, labels, delta);
+    }
+
+    fn histogram(&self, metric: Metric, labels: &[MetricLabel], _value: f64) {
+        self.add(metric, labels, 1);
+    }
+}
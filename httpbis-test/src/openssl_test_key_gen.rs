@@ -20,6 +20,10 @@ pub struct ServerKeys {
     pub pkcs12_password: String,
 
     pub pem: Vec<u8>,
+
+    pub cert_der: Vec<u8>,
+    /// PKCS#8 DER-encoded private key
+    pub key_der: Vec<u8>,
 }
 
 pub struct Keys {
@@ -100,14 +104,27 @@ pub fn keys() -> &'static Keys {
             .output());
         assert!(pkcs12out.status.success());
 
+        let keyout = t!(Command::new("openssl")
+            .arg("pkcs8")
+            .arg("-topk8")
+            .arg("-nocrypt")
+            .arg("-outform")
+            .arg("der")
+            .arg("-in")
+            .arg(&keyfile)
+            .output());
+        assert!(keyout.status.success());
+
         let pem = pkcs12_to_pem(&pkcs12out.stdout, "foobar");
 
         let keys = Box::new(Keys {
             client: ClientKeys {
-                cert_der: crtout.stdout,
+                cert_der: crtout.stdout.clone(),
             },
             server: ServerKeys {
                 pem,
+                cert_der: crtout.stdout,
+                key_der: keyout.stdout,
                 pkcs12: pkcs12out.stdout,
                 pkcs12_password: "foobar".to_owned(),
             },
@@ -136,3 +153,99 @@ fn pkcs12_to_pem(pkcs12: &[u8], passin: &str) -> Vec<u8> {
 
     pem
 }
+
+/// DER-encoded self-signed certificate and PKCS#8 private key.
+pub struct HostKeys {
+    pub cert_der: Vec<u8>,
+    pub key_der: Vec<u8>,
+}
+
+/// Generate new self-signed certificate for given DNS name.
+pub fn keys_for_host(host: &str) -> HostKeys {
+    keys_for_hosts(&[host])
+}
+
+/// Generate new self-signed certificate for given DNS names,
+/// the first one is used as common name.
+pub fn keys_for_hosts(hosts: &[&str]) -> HostKeys {
+    let host = hosts[0];
+    let alt_names: String = hosts
+        .iter()
+        .enumerate()
+        .map(|(i, host)| format!("DNS.{} = {}\n", i + 1, host))
+        .collect();
+    let path = t!(env::current_exe());
+    let path = path.parent().unwrap();
+    let keyfile = path.join(format!("{}.key", host));
+    let certfile = path.join(format!("{}.crt", host));
+    let config = path.join(format!("{}.openssl.config", host));
+
+    File::create(&config)
+        .unwrap()
+        .write_all(
+            format!(
+                "\
+                [req]\n\
+                distinguished_name=dn\n\
+                [dn]\n\
+                CN={host}\n\
+                [ext]\n\
+                basicConstraints=CA:FALSE,pathlen:0\n\
+                subjectAltName = @alt_names\n\
+                extendedKeyUsage=serverAuth,clientAuth\n\
+                [alt_names]\n\
+                {alt_names}\
+                ",
+                host = host,
+                alt_names = alt_names
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+    let output = t!(Command::new("openssl")
+        .arg("req")
+        .arg("-nodes")
+        .arg("-x509")
+        .arg("-newkey")
+        .arg("rsa:2048")
+        .arg("-config")
+        .arg(&config)
+        .arg("-extensions")
+        .arg("ext")
+        .arg("-subj")
+        .arg(format!("/CN={}", host))
+        .arg("-keyout")
+        .arg(&keyfile)
+        .arg("-out")
+        .arg(&certfile)
+        .arg("-days")
+        .arg("1")
+        .output());
+    assert!(output.status.success());
+
+    let crtout = t!(Command::new("openssl")
+        .arg("x509")
+        .arg("-outform")
+        .arg("der")
+        .arg("-in")
+        .arg(&certfile)
+        .output());
+    assert!(crtout.status.success());
+
+    let keyout = t!(Command::new("openssl")
+        .arg("pkcs8")
+        .arg("-topk8")
+        .arg("-nocrypt")
+        .arg("-outform")
+        .arg("der")
+        .arg("-in")
+        .arg(&keyfile)
+        .output());
+    assert!(keyout.status.success());
+
+    HostKeys {
+        cert_der: crtout.stdout,
+        key_der: keyout.stdout,
+    }
+}
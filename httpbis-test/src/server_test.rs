@@ -83,7 +83,7 @@ impl ServerTest {
     #[cfg(unix)]
     pub fn new_unix(addr: String) -> ServerTest {
         let mut server = ServerBuilder::new_plain_unix();
-        server.set_unix_addr(addr).unwrap();
+        server.bind_unix(addr);
 
         server.service.set_service("/blocks", Arc::new(Blocks {}));
         server.service.set_service("/echo", Arc::new(Echo {}));
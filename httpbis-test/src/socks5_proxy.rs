@@ -0,0 +1,140 @@
+//! SOCKS5 proxy for client tests.
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::connect_proxy::spawn_copy;
+use crate::BIND_HOST;
+
+/// Proxy listening on a local port and connecting to requested targets.
+///
+/// Proxy threads run until the test process exits.
+pub struct Socks5Proxy {
+    port: u16,
+    targets: Arc<Mutex<Vec<String>>>,
+}
+
+impl Socks5Proxy {
+    /// Require username/password authentication if `credentials` are specified.
+    pub fn new(credentials: Option<(&str, &str)>) -> Socks5Proxy {
+        let listener = TcpListener::bind((BIND_HOST, 0)).expect("bind");
+        let port = listener.local_addr().expect("local_addr").port();
+        let targets = Arc::new(Mutex::new(Vec::new()));
+
+        let credentials = credentials.map(|(u, p)| (u.to_owned(), p.to_owned()));
+        let targets_copy = targets.clone();
+        thread::Builder::new()
+            .name("socks5-proxy".to_owned())
+            .spawn(move || {
+                for client in listener.incoming() {
+                    let client = match client {
+                        Ok(client) => client,
+                        Err(e) => {
+                            warn!("socks5 proxy accept failed: {:?}", e);
+                            return;
+                        }
+                    };
+                    if let Err(e) = serve(client, credentials.as_ref(), &targets_copy) {
+                        debug!("socks5 proxy failed: {:?}", e);
+                    }
+                }
+            })
+            .expect("spawn");
+
+        Socks5Proxy { port, targets }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// `host:port` targets connected to so far.
+    pub fn targets(&self) -> Vec<String> {
+        self.targets.lock().unwrap().clone()
+    }
+}
+
+fn read_vec(mut stream: &TcpStream, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u8(stream: &TcpStream) -> io::Result<u8> {
+    Ok(read_vec(stream, 1)?[0])
+}
+
+fn serve(
+    mut client: TcpStream,
+    credentials: Option<&(String, String)>,
+    targets: &Mutex<Vec<String>>,
+) -> io::Result<()> {
+    assert_eq!(5, read_u8(&client)?);
+    let methods_len = read_u8(&client)? as usize;
+    let methods = read_vec(&client, methods_len)?;
+
+    let method = if credentials.is_some() { 2 } else { 0 };
+    if !methods.contains(&method) {
+        client.write_all(&[5, 0xff])?;
+        return Ok(());
+    }
+    client.write_all(&[5, method])?;
+
+    if let Some((username, password)) = credentials {
+        assert_eq!(1, read_u8(&client)?);
+        let len = read_u8(&client)? as usize;
+        let u = read_vec(&client, len)?;
+        let len = read_u8(&client)? as usize;
+        let p = read_vec(&client, len)?;
+        if u != username.as_bytes() || p != password.as_bytes() {
+            client.write_all(&[1, 1])?;
+            return Ok(());
+        }
+        client.write_all(&[1, 0])?;
+    }
+
+    let request = read_vec(&client, 4)?;
+    assert_eq!(&[5, 1, 0], &request[..3]);
+    let host = match request[3] {
+        1 => {
+            let ip = read_vec(&client, 4)?;
+            Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]).to_string()
+        }
+        3 => {
+            let len = read_u8(&client)? as usize;
+            String::from_utf8(read_vec(&client, len)?).expect("utf-8")
+        }
+        4 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&read_vec(&client, 16)?);
+            format!("[{}]", Ipv6Addr::from(ip))
+        }
+        atyp => panic!("unknown address type {}", atyp),
+    };
+    let port = read_vec(&client, 2)?;
+    let target = format!("{}:{}", host, u16::from_be_bytes([port[0], port[1]]));
+
+    targets.lock().unwrap().push(target.clone());
+
+    let server = match TcpStream::connect(&target) {
+        Ok(server) => server,
+        Err(e) => {
+            // connection refused
+            client.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])?;
+            return Err(e);
+        }
+    };
+    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+
+    spawn_copy(client.try_clone()?, server.try_clone()?);
+    spawn_copy(server, client);
+    Ok(())
+}
@@ -22,6 +22,7 @@ use httpbis::for_test::solicit::frame::GoawayFrame;
 use httpbis::for_test::solicit::frame::HeadersFlag;
 use httpbis::for_test::solicit::frame::HeadersFrame;
 use httpbis::for_test::solicit::frame::HttpFrame;
+use httpbis::for_test::solicit::frame::PushPromiseFrame;
 use httpbis::for_test::solicit::frame::RawFrame;
 use httpbis::for_test::solicit::frame::RstStreamFrame;
 use httpbis::for_test::solicit::frame::SettingsFrame;
@@ -193,6 +194,22 @@ impl HttpConnTester {
         self.send_frame(headers_frame);
     }
 
+    pub fn send_push_promise(
+        &mut self,
+        stream_id: StreamId,
+        promised_stream_id: StreamId,
+        headers: Headers,
+    ) {
+        let fragment = self
+            .encoder
+            .encode_for_test(headers.iter().map(|h| (h.name().as_bytes(), h.value())));
+        self.send_frame(PushPromiseFrame::new(
+            stream_id,
+            promised_stream_id,
+            Bytes::from(fragment),
+        ));
+    }
+
     pub fn send_get(&mut self, stream_id: StreamId, path: &str) {
         let mut headers = Headers::new();
         headers.add(":method", "GET");
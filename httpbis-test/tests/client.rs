@@ -1,8 +1,11 @@
 //! Tests for client.
 
+use std::convert::TryFrom;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
 extern crate bytes;
 extern crate futures;
@@ -17,10 +20,18 @@ use bytes::Bytes;
 
 use futures::channel::oneshot;
 use futures::stream::StreamExt;
+use futures::stream::TryStreamExt;
 
 use futures::future;
 use futures::future::TryFutureExt;
 
+use httpbis::for_test::solicit::frame::DataFrame;
+use httpbis::for_test::solicit::frame::HttpFrame;
+use httpbis::for_test::solicit::frame::HttpSetting;
+use httpbis::for_test::solicit::frame::PingFrame;
+use httpbis::for_test::solicit::frame::PriorityUpdateFrame;
+use httpbis::for_test::solicit::frame::RawHttpFrameType;
+use httpbis::for_test::solicit::frame::SettingsFrame;
 use httpbis::for_test::solicit::DEFAULT_SETTINGS;
 use httpbis::for_test::*;
 use httpbis::ErrorCode;
@@ -456,3 +467,2736 @@ fn sink_reset_by_peer() {
     assert_eq!(0, client.conn_state().out_window_size);
     assert_eq!(0, client.conn_state().pump_out_window_size);
 }
+
+#[test]
+fn sink_data_end_of_stream_single_frame() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let (mut sender, response) = rt
+        .block_on(client.start_post_sink("/foo", "sink"))
+        .expect("start_post_sink");
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    sender
+        .send_data(Bytes::from_static(b"abc"))
+        .expect("send_data");
+    assert_eq!(b"abc", &server_tester.recv_frame_data_check(1, false)[..]);
+
+    sender
+        .send_data_end_of_stream(Bytes::from_static(b"def"))
+        .expect("send_data_end_of_stream");
+    assert_eq!(b"def", &server_tester.recv_frame_data_check(1, true)[..]);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(response.collect()).expect("collect");
+    assert_eq!(200, resp.headers.status());
+
+    // next frame must be the next request, not an empty `DATA` frame
+    let _req = client.start_get("/bar", "localhost");
+    server_tester.recv_frame_headers_check(3, true);
+}
+
+#[test]
+fn sink_trailers() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let (mut sender, response) = rt
+        .block_on(client.start_post_sink("/foo", "sink"))
+        .expect("start_post_sink");
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    sender
+        .send_data(Bytes::from_static(b"abc"))
+        .expect("send_data");
+    assert_eq!(b"abc", &server_tester.recv_frame_data_check(1, false)[..]);
+
+    match sender.send_trailers(Headers::new_get("/")) {
+        Err(SendError::InvalidHeaders(HeaderError::PseudoHeadersInTrailers)) => {}
+        r => panic!("expecting InvalidHeaders, got: {:?}", r),
+    }
+
+    let mut trailers = Headers::new();
+    trailers.add("grpc-status", "0");
+    sender.send_trailers(trailers).expect("send_trailers");
+    assert_eq!(SenderState::Done, sender.state());
+    let trailers = server_tester.recv_frame_headers_check(1, true);
+    assert_eq!("0", trailers.get("grpc-status"));
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(response.collect()).expect("collect");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn response_trailers() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let req = client.start_get("/fgfg", "localhost");
+    server_tester.recv_frame_headers_check(1, true);
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"ab", false);
+    server_tester.send_data(1, b"cd", false);
+    let mut trailers = Headers::new();
+    trailers.add("grpc-status", "0");
+    server_tester.send_headers(1, trailers.clone(), true);
+
+    let (headers, stream) = rt.block_on(req.0).expect("headers");
+    assert_eq!(200, headers.status());
+    let parts = rt
+        .block_on(stream.try_collect::<Vec<_>>())
+        .expect("collect");
+    assert_eq!(
+        vec![
+            DataOrTrailers::Data(Bytes::from_static(b"ab"), EndStream::No),
+            DataOrTrailers::Data(Bytes::from_static(b"cd"), EndStream::No),
+            DataOrTrailers::Trailers(trailers),
+        ],
+        parts
+    );
+}
+
+fn expect_continue_request(client: &Client) -> Response {
+    let mut headers = Headers::new_post("/fgfg");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    headers.add("expect", "100-continue");
+    client.start_request_end_stream(headers, Some(Bytes::from_static(b"abc")), None)
+}
+
+/// Next frame sent by client is the ack of our `PING`, not request body
+fn assert_body_held(server_tester: &mut HttpConnTester) {
+    server_tester.send_frame(PingFrame::new());
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(ping) => assert!(ping.is_ack()),
+        frame => panic!("expecting PING ack, got: {:?}", frame),
+    }
+}
+
+#[test]
+fn expect_continue() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let req = expect_continue_request(&client).collect();
+    server_tester.recv_frame_headers_check(1, false);
+    assert_body_held(&mut server_tester);
+
+    // 103 does not release the body
+    server_tester.send_headers(1, Headers::new_status(103), false);
+    assert_body_held(&mut server_tester);
+
+    server_tester.send_headers(1, Headers::new_status(100), false);
+    assert_eq!(b"abc", &server_tester.recv_frame_data_check(1, true)[..]);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn expect_continue_timeout() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.expect_continue_timeout = Some(Duration::from_millis(100));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let start = Instant::now();
+    let req = expect_continue_request(&client).collect();
+    server_tester.recv_frame_headers_check(1, false);
+
+    assert_eq!(b"abc", &server_tester.recv_frame_data_check(1, true)[..]);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn informational_responses() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.informational_responses = Some(true);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let mut parts = client
+        .start_get("/fgfg", "localhost")
+        .into_informational_stream();
+    server_tester.recv_frame_headers_check(1, true);
+
+    let mut early_hints = Headers::new_status(103);
+    early_hints.add("link", "</style.css>; rel=preload");
+    server_tester.send_headers(1, early_hints, false);
+
+    // Interim response is yielded before final headers are sent
+    match rt.block_on(parts.next()).expect("part").expect("ok") {
+        ResponsePart::Informational(headers) => {
+            assert_eq!(103, headers.status());
+            assert_eq!("</style.css>; rel=preload", headers.get("link"));
+        }
+        ResponsePart::Final(..) => panic!("expecting informational"),
+    }
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", true);
+
+    match rt.block_on(parts.next()).expect("part").expect("ok") {
+        ResponsePart::Final(headers, rem) => {
+            assert_eq!(200, headers.status());
+            let body: Vec<Bytes> = rt.block_on(rem.filter_data().try_collect()).expect("body");
+            assert_eq!(vec![Bytes::from_static(b"abc")], body);
+        }
+        ResponsePart::Informational(..) => panic!("expecting final"),
+    }
+    assert!(rt.block_on(parts.next()).is_none());
+}
+
+#[test]
+fn traffic_accounting() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let before = rt
+        .block_on(client.dump_state())
+        .expect("dump_state")
+        .traffic;
+
+    let response = client.start_post("/foo", "localhost", Bytes::from_static(b"abcdef"));
+    server_tester.recv_frame_headers_check(1, false);
+    assert_eq!(b"abcdef", &server_tester.recv_frame_data_check(1, true)[..]);
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"hello", true);
+    let resp = rt.block_on(response.collect()).expect("collect");
+    assert_eq!(200, resp.headers.status());
+
+    let after = rt
+        .block_on(client.dump_state())
+        .expect("dump_state")
+        .traffic;
+    assert_eq!(6, after.sent.data_bytes - before.sent.data_bytes);
+    assert_eq!(5, after.received.data_bytes - before.received.data_bytes);
+    assert!(after.sent.header_bytes > before.sent.header_bytes);
+    assert!(after.received.header_bytes > before.received.header_bytes);
+    assert!(after.sent.frames >= before.sent.frames + 2);
+    assert_eq!(2, after.received.frames - before.received.frames);
+}
+
+#[test]
+fn sink_poll_capacity() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let (mut sender, _response) = rt
+        .block_on(client.start_post_sink("/foo", "sink"))
+        .expect("start_post_sink");
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    assert_eq!(0, sender.capacity());
+    assert_eq!(
+        Poll::Ready(Ok(0)),
+        sender.poll_capacity(&mut NopRuntime::new().context())
+    );
+
+    sender.reserve_capacity(100_000);
+    assert_eq!(65535, sender.capacity());
+    assert_eq!(
+        Poll::Ready(Ok(65535)),
+        sender.poll_capacity(&mut NopRuntime::new().context())
+    );
+
+    let b = Bytes::from(vec![1; 65_535]);
+    sender.send_data(b.clone()).expect("send_data");
+    assert_eq!(
+        b,
+        Bytes::from(server_tester.recv_frames_data_check(1, 16_384, 65_535, false))
+    );
+
+    assert_eq!(0, sender.capacity());
+    assert_eq!(
+        Poll::Pending,
+        sender.poll_capacity(&mut NopRuntime::new().context())
+    );
+
+    server_tester.send_window_update_conn(3);
+    server_tester.send_window_update_stream(1, 5);
+
+    let capacity = rt
+        .block_on(future::poll_fn(|cx| sender.poll_capacity(cx)))
+        .unwrap();
+    assert_eq!(3, capacity);
+
+    sender
+        .send_data(Bytes::from(vec![2; capacity]))
+        .expect("send_data");
+    assert_eq!(vec![2; 3], server_tester.recv_frame_data_check(1, false));
+
+    // window is exhausted, but not overdrawn
+    assert_eq!(0, client.conn_state().pump_out_window_size);
+    assert_eq!(2, client.stream_state(1).pump_out_window_size);
+}
+
+#[test]
+fn sink_poll_capacity_after_reset() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let (mut sender, _response) = rt
+        .block_on(client.start_post_sink("/foo", "sink"))
+        .expect("start_post_sink");
+
+    server_tester.recv_frame_headers_check(1, false);
+
+    sender.reserve_capacity(100);
+    assert_eq!(
+        Poll::Ready(Ok(100)),
+        sender.poll_capacity(&mut NopRuntime::new().context())
+    );
+
+    sender.reset(ErrorCode::Cancel).expect("reset");
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+
+    assert_eq!(
+        Poll::Ready(Err(StreamDead::Stream)),
+        sender.poll_capacity(&mut NopRuntime::new().context())
+    );
+}
+
+#[test]
+fn collect_with_limit_exceeded() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let resp = client.start_get("/fgfg", "localhost").collect_with_limit(5);
+
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", false);
+    server_tester.send_data(1, b"def", false);
+
+    match rt.block_on(resp) {
+        Err(httpbis::Error::BodyLimitExceeded(5)) => {}
+        r => panic!("expecting BodyLimitExceeded: {:?}", r.map(|m| m.dump())),
+    }
+
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+}
+
+#[test]
+fn collect_with_limit_content_length() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    {
+        let resp = client
+            .start_get("/small", "localhost")
+            .collect_with_limit(5);
+
+        server_tester.recv_message(1);
+        server_tester.send_headers(1, Headers::ok_200(), false);
+        server_tester.send_data(1, b"abcde", true);
+
+        let message = rt.block_on(resp).expect("collect_with_limit");
+        assert_eq!(200, message.headers.status());
+        assert_eq!(&b"abcde"[..], &message.body.get_bytes()[..]);
+    }
+
+    {
+        let resp = client
+            .start_get("/large", "localhost")
+            .collect_with_limit(5);
+
+        server_tester.recv_message(3);
+        let mut headers = Headers::ok_200();
+        headers.add("content-length", "1000");
+        server_tester.send_headers(3, headers, false);
+
+        match rt.block_on(resp) {
+            Err(httpbis::Error::BodyLimitExceeded(5)) => {}
+            r => panic!("expecting BodyLimitExceeded: {:?}", r.map(|m| m.dump())),
+        }
+
+        server_tester.recv_rst_frame_check(3, ErrorCode::Cancel);
+    }
+}
+
+#[test]
+fn prewarm() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let prewarm = client.prewarm(true);
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    // PING may be received before SETTINGS ack
+    server_tester.settings_xchg_but_ack();
+
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(ping) => {
+            assert!(!ping.is_ack());
+            server_tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+        }
+        frame => panic!("expecting PING, got: {:?}", frame),
+    }
+
+    rt.block_on(prewarm).expect("prewarm");
+
+    // connection is reused by the following request
+    let req = client.start_get("/111", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn ping() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let mut server_tester = server.accept_xchg();
+
+    assert_eq!(None, client.conn_state().rtt);
+
+    let ping = client.ping();
+
+    match server_tester.recv_frame() {
+        HttpFrame::Ping(ping) => {
+            assert!(!ping.is_ack());
+            thread::sleep(Duration::from_millis(20));
+            server_tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+        }
+        frame => panic!("expecting PING, got: {:?}", frame),
+    }
+
+    let rtt = rt.block_on(ping).expect("ping");
+    assert!(rtt >= Duration::from_millis(20), "{:?}", rtt);
+    assert_eq!(Some(rtt), client.conn_state().rtt);
+}
+
+#[test]
+fn update_settings() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let mut server_tester = server.accept_xchg();
+
+    let update = client.update_settings(vec![
+        HttpSetting::InitialWindowSize(100_000),
+        HttpSetting::MaxFrameSize(0x8000),
+    ]);
+
+    let settings = server_tester.recv_frame_settings_set();
+    assert_eq!(
+        vec![
+            HttpSetting::InitialWindowSize(100_000),
+            HttpSetting::MaxFrameSize(0x8000),
+        ],
+        settings.settings
+    );
+
+    // not applied until ack
+    let req = client.start_get("/fgfg", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+    assert_eq!(65535, client.stream_state(1).in_window_size);
+
+    server_tester.send_frame(SettingsFrame::new_ack());
+    rt.block_on(update).expect("update");
+
+    assert_eq!(100_000, client.stream_state(1).in_window_size);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+
+    match rt.block_on(client.update_settings(vec![HttpSetting::MaxFrameSize(100)])) {
+        Err(Error::InvalidSetting(HttpSetting::MaxFrameSize(100))) => {}
+        r => panic!("expecting InvalidSetting: {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn header_table_size() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.common.header_table_size = Some(8192);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::HeaderTableSize(256),
+    ]));
+    let settings = server_tester.recv_frame_settings_set();
+    assert!(settings
+        .settings
+        .contains(&HttpSetting::HeaderTableSize(8192)));
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let req = client.start_get("/fgfg", "localhost").collect();
+
+    // dynamic table size update to 256 starts the header block
+    let (headers, _) = server_tester.recv_frame_headers_continuation();
+    assert_eq!(&[0x3f, 0xe1, 0x01], &headers.header_fragment[..3]);
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn sensitive_headers() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.common.sensitive_headers = Some(vec!["x-api-key".to_owned()]);
+    conf.common.hpack_huffman = Some(false);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let mut headers = Headers::new_get("/fgfg");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    headers.add_sensitive("authorization", "secret");
+    headers.add("x-api-key", "key");
+    let req = client
+        .start_request_end_stream(headers, None, None)
+        .collect();
+
+    let (frame, _) = server_tester.recv_frame_headers_continuation();
+    let fragment = &frame.header_fragment[..];
+    let contains = |needle: &[u8]| fragment.windows(needle.len()).any(|w| w == needle);
+    // never-indexed literal with `authorization` name from static table
+    assert!(contains(b"\x1f\x08\x06secret"));
+    // never-indexed literal with new name
+    assert!(contains(b"\x10\x09x-api-key\x03key"));
+    let headers = server_tester
+        .decoder
+        .decode(frame.header_fragment.clone())
+        .expect("decode");
+    assert!(headers.contains(&(
+        Bytes::from_static(b"authorization"),
+        Bytes::from_static(b"secret")
+    )));
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn padding() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.common.padding = Some(Padding::Fixed(10));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept_xchg();
+
+    let req = client
+        .start_post("/fgfg", "localhost", Bytes::from_static(b"abcd"))
+        .collect();
+
+    let (headers, _, _) = server_tester.recv_frame_headers_decode();
+    assert_eq!(10, headers.padding_len);
+
+    let data = server_tester.recv_frame_data();
+    assert!(data.is_padded());
+    assert!(data.is_end_of_stream());
+    assert_eq!(&b"abcd"[..], &data.data[..]);
+    assert_eq!(1 + 4 + 10, data.payload_len());
+
+    server_tester.send_headers(1, Headers::ok_200(), false);
+
+    let mut data = DataFrame::with_data(1, Bytes::from_static(b"xyz"));
+    data.set_padding(100);
+    server_tester.send_frame(data);
+
+    // padding is returned to the stream window right away
+    match server_tester.fn_recv_frame_no_check_ack() {
+        HttpFrame::WindowUpdate(f) => {
+            assert_eq!(1, f.stream_id);
+            assert_eq!(1 + 100, f.increment);
+        }
+        f => panic!("expecting WINDOW_UPDATE, got: {:?}", f),
+    }
+
+    server_tester.send_data(1, b"", true);
+
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"xyz"[..], &resp.body.get_bytes()[..]);
+}
+
+#[test]
+fn send_goaway_graceful() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let mut server_tester = server.accept_xchg();
+
+    let req = client.start_get("/111", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+
+    client
+        .send_goaway(ErrorCode::NoError, Bytes::from_static(b"bye"), true)
+        .expect("send_goaway");
+
+    let goaway = server_tester.recv_goaway_frame();
+    assert_eq!(ErrorCode::NoError, goaway.error_code());
+    assert_eq!(&b"bye"[..], &goaway.debug_data()[..]);
+
+    // existing stream is completed
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+
+    server_tester.recv_eof();
+
+    // new request is sent over new connection
+    let mut server_tester = server.accept_xchg();
+    let req = client.start_get("/222", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn drain() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let mut server_tester = server.accept_xchg();
+
+    let req = client.start_get("/111", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+
+    let drain = client.drain();
+
+    let goaway = server_tester.recv_goaway_frame();
+    assert_eq!(ErrorCode::NoError, goaway.error_code());
+
+    // new request is sent over new connection while old one is draining
+    let mut server_tester2 = server.accept_xchg();
+    let req2 = client.start_get("/222", "localhost").collect();
+    server_tester2.recv_message(1);
+    server_tester2.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req2).expect("OK");
+    assert_eq!(200, resp.headers.status());
+
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+
+    rt.block_on(drain).expect("drain");
+    server_tester.recv_eof();
+}
+
+#[test]
+fn fault_close_then_reconnect() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+
+    let proxy = FaultProxy::new(
+        server.port,
+        vec![FaultRule::new(
+            FaultDirection::ServerToClient,
+            Some(RawHttpFrameType::HEADERS),
+            0,
+            Fault::Close,
+        )],
+    );
+
+    let client = Client::new_plain(BIND_HOST, proxy.port(), ClientConf::new()).expect("client");
+
+    let r = rt.block_on(client.start_get("/blocks/1/1", "localhost").collect());
+    assert!(r.is_err());
+
+    let r = rt
+        .block_on(client.start_get("/blocks/1/1", "localhost").collect())
+        .expect("get after reconnect");
+    assert_eq!(200, r.headers.status());
+}
+
+#[test]
+fn fault_delay_and_corrupt() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+
+    let proxy = FaultProxy::new(
+        server.port,
+        vec![
+            FaultRule::new(
+                FaultDirection::ServerToClient,
+                Some(RawHttpFrameType::HEADERS),
+                0,
+                Fault::Delay(Duration::from_millis(100)),
+            ),
+            FaultRule::new(
+                FaultDirection::ClientToServer,
+                Some(RawHttpFrameType::HEADERS),
+                1,
+                Fault::Corrupt,
+            ),
+        ],
+    );
+
+    let client = Client::new_plain(BIND_HOST, proxy.port(), ClientConf::new()).expect("client");
+
+    let start = Instant::now();
+    let r = rt
+        .block_on(client.start_get("/blocks/1/1", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // corrupted header block is a connection error
+    let r = rt.block_on(client.start_get("/blocks/1/1", "localhost").collect());
+    assert!(r.is_err());
+}
+
+#[test]
+fn hpack_without_dynamic_table() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.hpack_dynamic_table = Some(false);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let mut headers = Headers::ok_200();
+            headers.add("x-echo", req.headers.get("x-custom").to_owned());
+            resp.send_headers_end_of_stream(headers)?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut conf = ClientConf::new();
+    conf.common.hpack_dynamic_table = Some(false);
+    let client =
+        Client::new_plain(BIND_HOST, server.local_addr().port().unwrap(), conf).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut header_bytes = Vec::new();
+    for i in 0..4 {
+        let before = rt.block_on(client.dump_state()).expect("dump_state");
+
+        let mut headers = Headers::new_get("/");
+        headers.add(":authority", "localhost");
+        headers.add(":scheme", "http");
+        headers.add("x-custom", "some-long-custom-value");
+        let resp = rt
+            .block_on(
+                client
+                    .start_request_end_stream(headers, None, None)
+                    .collect(),
+            )
+            .expect("collect");
+        assert_eq!(200, resp.headers.status());
+        assert_eq!("some-long-custom-value", resp.headers.get("x-echo"));
+
+        let after = rt.block_on(client.dump_state()).expect("dump_state");
+        if i > 0 {
+            assert_eq!(0, after.peer_settings.header_table_size);
+        }
+        header_bytes.push((
+            after.traffic.sent.header_bytes - before.traffic.sent.header_bytes,
+            after.traffic.received.header_bytes - before.traffic.received.header_bytes,
+        ));
+    }
+
+    // nothing is indexed, so repeated header blocks are not smaller;
+    // first two blocks are skipped because one of them also carries
+    // dynamic table size update to zero
+    assert_eq!(header_bytes[2], header_bytes[3]);
+}
+
+#[test]
+fn cookie_jar() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let mut headers = Headers::ok_200();
+            if req.headers.path() == "/login" {
+                headers.add("set-cookie", "sid=abc; Path=/");
+            }
+            let cookie = req.headers.get_opt("cookie").unwrap_or("").to_owned();
+            resp.send_headers(headers)?;
+            resp.send_data_end_of_stream(Bytes::from(cookie))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.cookie_jar = Some(Arc::new(CookieJar::new()));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut get = |path| {
+        rt.block_on(client.start_get(path, "localhost").collect())
+            .expect("get")
+    };
+
+    assert_eq!(&b""[..], get("/").body.get_bytes());
+    assert_eq!(&b""[..], get("/login").body.get_bytes());
+    assert_eq!(&b"sid=abc"[..], get("/").body.get_bytes());
+
+    let cookie_jar = client.cookie_jar().expect("cookie_jar");
+    assert_eq!(1, cookie_jar.cookies().len());
+    cookie_jar.clear();
+    assert_eq!(&b""[..], get("/").body.get_bytes());
+}
+
+#[test]
+fn http_cache() {
+    init_logger();
+
+    let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let requests_copy = requests.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            let n = requests_copy.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            let mut headers = Headers::ok_200();
+            match req.headers.path() {
+                "/fresh" => headers.add("cache-control", "max-age=60"),
+                "/etag" => {
+                    if req.headers.get_opt("if-none-match") == Some("\"v1\"") {
+                        resp.send_headers_end_of_stream(Headers::new_status(304))?;
+                        return Ok(());
+                    }
+                    headers.add("cache-control", "no-cache");
+                    headers.add("etag", "\"v1\"");
+                }
+                _ => {}
+            }
+            resp.send_headers(headers)?;
+            resp.send_data_end_of_stream(Bytes::from(format!("{}", n)))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.cache = Some(Arc::new(HttpCache::new_memory(10)));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut get = |path| {
+        let resp = if path == "/post" {
+            client.start_post("/fresh", "localhost", Bytes::new())
+        } else {
+            client.start_get(path, "localhost")
+        };
+        let message = rt.block_on(resp.collect()).expect("get");
+        assert_eq!(200, message.headers.status());
+        String::from_utf8(message.body.get_bytes().to_vec()).unwrap()
+    };
+
+    // fresh response is served from cache
+    assert_eq!("1", get("/fresh"));
+    assert_eq!("1", get("/fresh"));
+    assert_eq!(1, requests.load(std::sync::atomic::Ordering::SeqCst));
+
+    // response is not cacheable
+    assert_eq!("2", get("/other"));
+    assert_eq!("3", get("/other"));
+
+    // response is revalidated with `if-none-match`
+    assert_eq!("4", get("/etag"));
+    assert_eq!("4", get("/etag"));
+    assert_eq!(5, requests.load(std::sync::atomic::Ordering::SeqCst));
+
+    // POST invalidates cached response
+    assert_eq!("6", get("/post"));
+    assert_eq!("7", get("/fresh"));
+}
+
+#[test]
+fn http_cache_max_entry_size() {
+    init_logger();
+
+    let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let requests_copy = requests.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            requests_copy.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let size: usize = req.headers.path()[1..].parse().unwrap();
+            let mut headers = Headers::ok_200();
+            headers.add("cache-control", "max-age=60");
+            resp.send_headers(headers)?;
+            resp.send_data(Bytes::from(vec![b'a'; size / 2]))?;
+            resp.send_data_end_of_stream(Bytes::from(vec![b'b'; size - size / 2]))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut cache = HttpCache::new_memory(10);
+    cache.set_max_entry_size(100);
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.cache = Some(Arc::new(cache));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut get = |path| {
+        let message = rt
+            .block_on(client.start_get(path, "localhost").collect())
+            .expect("get");
+        assert_eq!(200, message.headers.status());
+        message.body.get_bytes().len()
+    };
+
+    // small response is cached
+    assert_eq!(100, get("/100"));
+    assert_eq!(100, get("/100"));
+    assert_eq!(1, requests.load(std::sync::atomic::Ordering::SeqCst));
+
+    // large response is streamed through
+    assert_eq!(1000, get("/1000"));
+    assert_eq!(1000, get("/1000"));
+    assert_eq!(3, requests.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[test]
+fn retry_refused_stream() {
+    init_logger();
+
+    let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let requests_copy = requests.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            let n = requests_copy.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            let refuse: usize = req.headers.path()[1..].parse().unwrap();
+            if n <= refuse {
+                resp.reset(ErrorCode::RefusedStream)?;
+            } else {
+                resp.send_message(SimpleHttpMessage::found_200_plain_text("ok"))?;
+            }
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.conf.retry_policy = Some(Arc::new(DefaultRetryPolicy {
+        max_attempts: 3,
+        backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(10),
+        budget: None,
+    }));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let message = rt
+        .block_on(client.start_get("/2", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, message.headers.status());
+    assert_eq!(3, requests.load(std::sync::atomic::Ordering::SeqCst));
+
+    requests.store(0, std::sync::atomic::Ordering::SeqCst);
+    match rt.block_on(client.start_get("/3", "localhost").collect()) {
+        Err(Error::RstStreamReceived(ErrorCode::RefusedStream)) => {}
+        r => panic!("{:?}", r.map(|m| m.dump())),
+    }
+    assert_eq!(3, requests.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[test]
+fn retry_above_goaway_last_stream() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.retry_policy = Some(Arc::new(DefaultRetryPolicy {
+        max_attempts: 2,
+        backoff: Duration::from_millis(10),
+        max_backoff: Duration::from_millis(10),
+        budget: None,
+    }));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+    let mut conn1 = server.accept_xchg();
+
+    // request with retry policy is started when polled
+    let a = rt.spawn(client.start_get("/a", "localhost").collect());
+    conn1.recv_frame_headers_check(1, true);
+
+    // stream 1 is not processed
+    conn1.send_goaway(0);
+    conn1.recv_eof();
+
+    // so it is retried on a new connection
+    let mut conn2 = accept_busy(&server);
+    conn2.recv_frame_headers_check(1, true);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    let a = rt.block_on(a).unwrap().expect("a");
+    assert_eq!(200, a.headers.status());
+}
+
+#[test]
+fn connect_error_is_unprocessed() {
+    init_logger();
+
+    // bind and close to get a port nobody listens on
+    let port = std::net::TcpListener::bind((BIND_HOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+    let mut rt = Runtime::new().unwrap();
+    let e = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .err()
+        .expect("error");
+    assert!(is_unprocessed_error(&e), "{:?}", e);
+}
+
+#[test]
+fn mirror() {
+    init_logger();
+
+    let mut primary = ServerBuilder::new_plain();
+    primary.set_port(0);
+    primary
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_message(SimpleHttpMessage::found_200_plain_text("primary"))?;
+            Ok(())
+        });
+    let primary = primary.build().expect("server");
+
+    let (mirrored_tx, mirrored_rx) = mpsc::channel();
+    let mirrored_tx = std::sync::Mutex::new(mirrored_tx);
+
+    let mut shadow = ServerBuilder::new_plain();
+    shadow.set_port(0);
+    shadow
+        .service
+        .set_service_fn("/", move |context, req, mut resp| {
+            let mirrored_tx = mirrored_tx.lock().unwrap().clone();
+            let message = req.collect_with_limit(1000);
+            context.loop_remote().spawn(async move {
+                mirrored_tx.send(message.await.expect("body")).unwrap();
+            });
+            resp.send_message(SimpleHttpMessage::found_200_plain_text("shadow"))?;
+            Ok(())
+        });
+    let shadow = shadow.build().expect("server");
+
+    let shadow_client = Client::new_plain(
+        BIND_HOST,
+        shadow.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+    let mut mirror = RequestMirror::new(Arc::new(shadow_client));
+    mirror.authority = Some("shadow".to_owned());
+    mirror.filter = Some(Arc::new(|headers: &Headers| headers.path() != "/skip"));
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, primary.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.mirror = Some(Arc::new(mirror));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let message = rt
+        .block_on(
+            client
+                .start_post("/skip", "localhost", Bytes::from("a"))
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(&b"primary"[..], message.body.get_bytes());
+    let message = rt
+        .block_on(
+            client
+                .start_post("/x", "localhost", Bytes::from("hello"))
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(&b"primary"[..], message.body.get_bytes());
+
+    let mirrored = mirrored_rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("mirrored");
+    assert_eq!("/x", mirrored.headers.path());
+    assert_eq!("shadow", mirrored.headers.get(":authority"));
+    assert_eq!(&b"hello"[..], mirrored.body.get_bytes());
+    assert!(mirrored_rx.try_recv().is_err());
+}
+
+#[test]
+fn headers_hook() {
+    init_logger();
+
+    let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let requests_copy = requests.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            if requests_copy.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                resp.reset(ErrorCode::RefusedStream)?;
+                return Ok(());
+            }
+            let authorization = req.headers.get("authorization");
+            resp.send_message(SimpleHttpMessage::found_200_plain_text(authorization))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let tokens = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.conf.retry_policy = Some(Arc::new(DefaultRetryPolicy {
+        max_attempts: 2,
+        backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(1),
+        budget: None,
+    }));
+    client.headers_hook = Some(Arc::new(move |mut headers: Headers| {
+        let tokens = tokens.clone();
+        async move {
+            if headers.path() == "/fail" {
+                return Err(Error::User("no token".to_owned()));
+            }
+            tokio::time::delay_for(Duration::from_millis(1)).await;
+            let token = tokens.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            headers.add("authorization", format!("Bearer {}", token));
+            Ok(headers)
+        }
+    }));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    // token is computed again for the retry
+    let message = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(&b"Bearer 2"[..], message.body.get_bytes());
+    assert_eq!(2, requests.load(std::sync::atomic::Ordering::SeqCst));
+
+    match rt.block_on(client.start_get("/fail", "localhost").collect()) {
+        Err(Error::User(..)) => {}
+        r => panic!("{:?}", r.map(|m| m.dump())),
+    }
+    assert_eq!(2, requests.load(std::sync::atomic::Ordering::SeqCst));
+}
+
+#[test]
+fn default_headers() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let body = format!(
+                "{} {}",
+                req.headers.get("user-agent"),
+                req.headers.get("accept")
+            );
+            resp.send_message(SimpleHttpMessage::found_200_plain_text(&body))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut default_headers = Headers::new();
+    default_headers.add("user-agent", "httpbis-test");
+    default_headers.add("accept", "*/*");
+
+    let mut conf = ClientConf::new();
+    conf.default_headers = Some(default_headers);
+    let client =
+        Client::new_plain(BIND_HOST, server.local_addr().port().unwrap(), conf).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let message = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(&b"httpbis-test */*"[..], message.body.get_bytes());
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":scheme", "http");
+    headers.add(":authority", "localhost");
+    headers.add("accept", "text/plain");
+    let message = rt
+        .block_on(
+            client
+                .start_request_end_stream(headers, None, None)
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(&b"httpbis-test text/plain"[..], message.body.get_bytes());
+}
+
+#[test]
+fn alt_svc() {
+    init_logger();
+
+    let mut alternative = ServerBuilder::new_plain();
+    alternative.set_port(0);
+    alternative
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_message(SimpleHttpMessage::found_200_plain_text("alternative"))?;
+            Ok(())
+        });
+    let alternative = alternative.build().expect("server");
+    let alternative_port = alternative.local_addr().port().unwrap();
+
+    // port nobody listens on
+    let closed_port = std::net::TcpListener::bind((BIND_HOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let mut origin = ServerBuilder::new_plain();
+    origin.set_port(0);
+    origin
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            let mut message = SimpleHttpMessage::found_200_plain_text("origin");
+            let port = match req.headers.path() {
+                "/good" => alternative_port,
+                "/bad" => closed_port,
+                _ => 0,
+            };
+            if port != 0 {
+                message
+                    .headers
+                    .add("alt-svc", format!("h2c=\":{}\"; ma=60, h3=\":1\"", port));
+            }
+            resp.send_message(message)?;
+            Ok(())
+        });
+    let origin = origin.build().expect("server");
+
+    let (events_tx, events_rx) = mpsc::channel();
+    let events_tx = std::sync::Mutex::new(events_tx);
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, origin.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.conf.alt_svc = Some(true);
+    client.alt_svc_observer = Some(Arc::new(move |event: AltSvcEvent| {
+        let event = match event {
+            AltSvcEvent::Advertised(..) => "advertised",
+            AltSvcEvent::Cleared => "cleared",
+            AltSvcEvent::Switched(..) => "switched",
+            AltSvcEvent::Failed(..) => "failed",
+        };
+        events_tx.lock().unwrap().send(event).unwrap();
+    }));
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut get = |path: &str| {
+        let message = rt
+            .block_on(client.start_get(path, "localhost").collect())
+            .expect("get");
+        String::from_utf8(message.body.get_bytes().to_vec()).unwrap()
+    };
+
+    assert_eq!("origin", get("/bad"));
+    // alternative is not reachable, request falls back to origin
+    assert_eq!("origin", get("/"));
+    assert_eq!("origin", get("/good"));
+    assert_eq!("alternative", get("/"));
+    assert_eq!("alternative", get("/"));
+
+    let events: Vec<&str> = events_rx.try_iter().collect();
+    assert_eq!(
+        vec!["advertised", "switched", "failed", "advertised", "switched"],
+        events
+    );
+}
+
+#[test]
+fn alt_svc_same_host() {
+    init_logger();
+
+    let mut alternative = ServerBuilder::new_plain();
+    alternative.set_port(0);
+    alternative
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_message(SimpleHttpMessage::found_200_plain_text("alternative"))?;
+            Ok(())
+        });
+    let alternative = alternative.build().expect("server");
+    let alternative_port = alternative.local_addr().port().unwrap();
+
+    let mut origin = ServerBuilder::new_plain();
+    origin.set_port(0);
+    origin
+        .service
+        .set_service_fn("/", move |_context, _req, mut resp| {
+            let mut message = SimpleHttpMessage::found_200_plain_text("origin");
+            message
+                .headers
+                .add("alt-svc", format!("h2c=\":{}\"", alternative_port));
+            resp.send_message(message)?;
+            Ok(())
+        });
+    let origin = origin.build().expect("server");
+    let origin_port = origin.local_addr().port().unwrap();
+
+    let resolved = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let resolved_copy = resolved.clone();
+
+    let mut client = ClientBuilder::new_plain();
+    client.set_host("origin.test", origin_port);
+    client.set_resolver(move |host: &str, port: u16| {
+        resolved_copy.lock().unwrap().push((host.to_owned(), port));
+        let addr: std::net::SocketAddr = format!("{}:{}", BIND_HOST, port).parse().unwrap();
+        future::ok(vec![addr])
+    });
+    client.conf.alt_svc = Some(true);
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut get = |path: &str| {
+        let message = rt
+            .block_on(client.start_get(path, "origin.test").collect())
+            .expect("get");
+        String::from_utf8(message.body.get_bytes().to_vec()).unwrap()
+    };
+
+    assert_eq!("origin", get("/"));
+    assert_eq!("alternative", get("/"));
+
+    // alternative without host is on the configured origin host name
+    assert_eq!(
+        vec![
+            ("origin.test".to_owned(), origin_port),
+            ("origin.test".to_owned(), alternative_port),
+        ],
+        *resolved.lock().unwrap()
+    );
+}
+
+#[test]
+fn length_prefixed_messages() {
+    init_logger();
+
+    let codec = LengthPrefixedCodec::grpc();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    let server_codec = codec.clone();
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            // respond with message lengths
+            let lengths = server_codec
+                .decode_stream(req.make_stream().filter_data())
+                .map_ok(|m| Bytes::from(m.len().to_string()));
+            resp.send_headers(Headers::ok_200())?;
+            resp.pull_bytes_from_stream(server_codec.encode_stream(lengths))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let (mut req, resp) = rt
+        .block_on(client.start_post_sink("/", "localhost"))
+        .expect("request");
+    let mut body = Vec::new();
+    for message in &[&b"hello"[..], b"", b"abc"] {
+        body.extend_from_slice(&codec.encode(message).unwrap());
+    }
+    // split messages between frames
+    req.send_data(Bytes::copy_from_slice(&body[..7]))
+        .expect("send");
+    req.send_data_end_of_stream(Bytes::copy_from_slice(&body[7..]))
+        .expect("send");
+
+    let (_headers, stream) = rt.block_on(resp.0).expect("headers");
+    let messages: Vec<Bytes> = rt
+        .block_on(
+            codec
+                .decode_stream(stream.filter_data())
+                .try_collect::<Vec<_>>(),
+        )
+        .expect("messages");
+    assert_eq!(
+        vec![Bytes::from("5"), Bytes::from("0"), Bytes::from("3")],
+        messages
+    );
+}
+
+#[test]
+fn send_raw_frame() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+
+    let connect = client.wait_for_connect();
+    let mut server_tester = server.accept_xchg();
+    rt.block_on(connect).expect("connect");
+
+    // unknown frame type 0xfa on stream 0 with payload "abc"
+    let raw =
+        wire::frame::RawFrame::parse(&b"\x00\x00\x03\xfa\x00\x00\x00\x00\x00abc"[..]).unwrap();
+    client
+        .send_raw_frame(HttpFrame::Unknown(raw))
+        .expect("send_raw_frame");
+
+    let frame = server_tester.recv_raw_frame();
+    assert_eq!(0xfa, frame.frame_type());
+    assert_eq!(&b"abc"[..], &frame.payload()[..]);
+
+    // connection is still usable
+    let req = client.start_get("/111", "localhost").collect();
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn push_promise() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let (tx, rx) = mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    let mut conf = ClientConf::new();
+    conf.push_handler = Some(Arc::new(move |request: Headers, response: Response| {
+        tx.lock().unwrap().send((request, response)).unwrap();
+    }));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::new());
+    let settings = server_tester.recv_frame_settings_set();
+    assert!(settings.settings.contains(&HttpSetting::EnablePush(true)));
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let req = client.start_get("/index.html", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+
+    let mut promised = Headers::new_get("/style.css");
+    promised.add(":scheme", "http");
+    promised.add(":authority", "localhost");
+    server_tester.send_push_promise(1, 2, promised);
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_headers(2, Headers::ok_200(), false);
+    server_tester.send_data(2, b"css", true);
+    server_tester.send_data(1, b"index", true);
+
+    let resp = rt.block_on(req).expect("response");
+    assert_eq!(&b"index"[..], &resp.body.get_bytes()[..]);
+
+    let (request, response) = rx.recv_timeout(Duration::from_secs(5)).expect("push");
+    assert_eq!("/style.css", request.path());
+    let pushed = rt.block_on(response.collect()).expect("pushed");
+    assert_eq!(200, pushed.headers.status());
+    assert_eq!(&b"css"[..], &pushed.body.get_bytes()[..]);
+
+    // unsafe method cannot be promised
+    let req = client.start_get("/index.html", "localhost").collect();
+    server_tester.recv_frame_headers_check(3, true);
+    let mut promised = Headers::new_post("/form");
+    promised.add(":scheme", "http");
+    promised.add(":authority", "localhost");
+    server_tester.send_push_promise(3, 4, promised);
+    server_tester.recv_rst_frame_check(4, ErrorCode::ProtocolError);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    rt.block_on(req).expect("response");
+
+    assert_eq!(0, client.conn_state().streams.len());
+}
+
+#[test]
+fn push_promise_disabled() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let _req = client.start_get("/index.html", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+
+    let mut promised = Headers::new_get("/style.css");
+    promised.add(":scheme", "http");
+    promised.add(":authority", "localhost");
+    server_tester.send_push_promise(1, 2, promised);
+    server_tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn extended_connect() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let extended_connect = |client: &Client| {
+        let headers = Headers::from_vec(vec![
+            Header::new(":method", "CONNECT"),
+            Header::new(":protocol", "websocket"),
+            Header::new(":scheme", "http"),
+            Header::new(":path", "/chat"),
+            Header::new(":authority", "localhost"),
+        ]);
+        client.start_request(headers, None, None, false)
+    };
+
+    // request waits until server settings are received
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client();
+    server_tester.recv_preface();
+    let start = extended_connect(&client);
+    let mut settings = SettingsFrame::new();
+    settings.add_setting(HttpSetting::EnableConnectProtocol(true));
+    server_tester.send_settings(settings);
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+    rt.block_on(start).expect("start");
+    let headers = server_tester.recv_frame_headers_check(1, false);
+    assert_eq!("websocket", headers.get(":protocol"));
+
+    // server did not enable extended CONNECT
+    let (_server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+    match rt.block_on(extended_connect(&client)) {
+        Err(Error::ExtendedConnectNotSupported) => {}
+        r => panic!("expecting ExtendedConnectNotSupported: {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn start_connect_rejected() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let connect = client.start_connect("example.com:443");
+    let headers = server_tester.recv_frame_headers_check(1, false);
+    assert_eq!("CONNECT", headers.method());
+    assert_eq!("example.com:443", headers.get(":authority"));
+    assert_eq!(None, headers.get_opt(":path"));
+    server_tester.send_headers(1, Headers::new_status(403), true);
+
+    match rt.block_on(connect) {
+        Err(Error::ConnectRejected(403)) => {}
+        r => panic!("expecting ConnectRejected: {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn priority() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let mut headers = Headers::new_post("/upload");
+    headers.add(":scheme", "http");
+    headers.add(":authority", "localhost");
+    Priority::new(1, false).set_header(&mut headers);
+    let (mut req, _resp) = rt
+        .block_on(client.start_request(headers, None, None, false))
+        .expect("start");
+    let headers = server_tester.recv_frame_headers_check(1, false);
+    assert_eq!("u=1", headers.get("priority"));
+
+    req.set_priority(Priority::new(5, true))
+        .expect("set_priority");
+    match server_tester.recv_frame() {
+        HttpFrame::PriorityUpdate(f) => {
+            assert_eq!(1, f.prioritized_stream_id);
+            assert_eq!(&b"u=5, i"[..], &f.field_value[..]);
+        }
+        f => panic!("expecting PRIORITY_UPDATE, got: {:?}", f),
+    }
+    req.close().expect("close");
+}
+
+#[test]
+fn priority_update_from_server() {
+    init_logger();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+    let _resp = client.start_get("/", "localhost");
+    server_tester.recv_frame_headers_check(1, true);
+
+    // only clients send `PRIORITY_UPDATE`
+    server_tester.send_frame(PriorityUpdateFrame::new(
+        1,
+        Priority::new(0, false).to_string(),
+    ));
+    server_tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn h2c_upgrade() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let body = format!("{} {}", req.headers.method(), req.headers.path());
+            resp.send_message(SimpleHttpMessage::found_200_plain_text(&body))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.set_h2c_upgrade();
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    for _ in 0..2 {
+        let message = rt
+            .block_on(client.start_get("/foo", "localhost").collect())
+            .expect("get");
+        assert_eq!(&b"GET /foo"[..], message.body.get_bytes());
+    }
+
+    // stream 1 of the upgrade request is closed after its response
+    let state = rt.block_on(client.dump_state()).expect("state");
+    assert!(state.streams.is_empty(), "{:?}", state.streams);
+}
+
+#[test]
+fn max_header_list_size() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.common.max_header_list_size = Some(100);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::new());
+    let settings = server_tester.recv_frame_settings_set();
+    assert!(settings
+        .settings
+        .contains(&HttpSetting::MaxHeaderListSize(100)));
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let req = client.start_get("/111", "localhost").collect();
+    server_tester.recv_frame_headers_check(1, true);
+
+    let mut headers = Headers::ok_200();
+    headers.add("x-large", "a".repeat(100));
+    server_tester.send_headers(1, headers, true);
+
+    match rt.block_on(req) {
+        Err(Error::HeaderListTooLarge(..)) => {}
+        Err(e) => panic!("expecting HeaderListTooLarge: {:?}", e),
+        Ok(_) => panic!("expecting error"),
+    }
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+
+    // connection is still usable
+    let req = client.start_get("/222", "localhost").collect();
+    server_tester.recv_frame_headers_check(3, true);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    let resp = rt.block_on(req).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn queue_at_max_concurrent_streams() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.max_queued_requests = Some(1);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+
+    let mut server_tester = server.accept();
+    server_tester.recv_preface();
+    server_tester.send_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::MaxConcurrentStreams(1),
+    ]));
+    server_tester.recv_frame_settings_set();
+    server_tester.send_frame(SettingsFrame::new_ack());
+    server_tester.recv_frame_settings_ack();
+
+    let req1 = client.start_get("/1", "localhost").collect();
+    let req2 = client.start_get("/2", "localhost").collect();
+    let req3 = client.start_get("/3", "localhost").collect();
+
+    match rt.block_on(req3) {
+        Err(Error::RequestQueueFull) => {}
+        Err(e) => panic!("expecting RequestQueueFull: {:?}", e),
+        Ok(_) => panic!("expecting error"),
+    }
+
+    // second request waits for the first one
+    assert_eq!(1, client.conn_state().streams.len());
+    server_tester.recv_frame_headers_check(1, true);
+    server_tester.send_headers(1, Headers::ok_200(), true);
+    let resp = rt.block_on(req1).expect("OK");
+    assert_eq!(200, resp.headers.status());
+
+    server_tester.recv_frame_headers_check(3, true);
+    server_tester.send_headers(3, Headers::ok_200(), true);
+    let resp = rt.block_on(req2).expect("OK");
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn http_proxy() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let authorization = basic_proxy_authorization("user", "secret");
+    let proxy = ConnectProxy::new(Some(&authorization));
+    let authority = format!("{}:{}", BIND_HOST, server.port);
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_http_proxy((BIND_HOST, proxy.port()), &authority, Some(authorization))
+        .expect("set_http_proxy");
+    let client = client.build().expect("client");
+
+    let r = rt
+        .block_on(client.start_get("/blocks/3/2", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(&[0, 0, 0, 1, 1, 1][..], &r.body.get_bytes()[..]);
+    assert_eq!(vec![authority], proxy.authorities());
+}
+
+#[test]
+fn http_proxy_auth_rejected() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let proxy = ConnectProxy::new(Some(&basic_proxy_authorization("user", "secret")));
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_http_proxy(
+            (BIND_HOST, proxy.port()),
+            &format!("{}:{}", BIND_HOST, server.port),
+            Some(basic_proxy_authorization("user", "wrong")),
+        )
+        .expect("set_http_proxy");
+    let client = client.build().expect("client");
+
+    match rt.block_on(client.start_get("/blocks/1/1", "localhost").collect()) {
+        Err(e) => assert!(format!("{:?}", e).contains("status 407"), "{:?}", e),
+        Ok(r) => panic!("expecting error, got {}", r.headers.status()),
+    }
+    assert!(proxy.authorities().is_empty());
+}
+
+#[test]
+fn socks5_proxy() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let proxy = Socks5Proxy::new(None);
+    let authority = format!("{}:{}", BIND_HOST, server.port);
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_socks5_proxy((BIND_HOST, proxy.port()), &authority, None)
+        .expect("set_socks5_proxy");
+    let client = client.build().expect("client");
+
+    let r = rt
+        .block_on(client.start_get("/blocks/3/2", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(&[0, 0, 0, 1, 1, 1][..], &r.body.get_bytes()[..]);
+    assert_eq!(vec![authority], proxy.targets());
+}
+
+#[test]
+fn socks5_proxy_username_password() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let proxy = Socks5Proxy::new(Some(("user", "secret")));
+    // host name is resolved by the proxy
+    let authority = format!("localhost:{}", server.port);
+
+    let client = |password: &str| {
+        let mut client = ClientBuilder::new_plain();
+        client
+            .set_socks5_proxy(
+                (BIND_HOST, proxy.port()),
+                &authority,
+                Some(("user".to_owned(), password.to_owned())),
+            )
+            .expect("set_socks5_proxy");
+        client.build().expect("client")
+    };
+
+    match rt.block_on(
+        client("wrong")
+            .start_get("/blocks/1/1", "localhost")
+            .collect(),
+    ) {
+        Err(e) => assert!(
+            format!("{:?}", e).contains("authentication failed"),
+            "{:?}",
+            e
+        ),
+        Ok(r) => panic!("expecting error, got {}", r.headers.status()),
+    }
+    assert!(proxy.targets().is_empty());
+
+    let r = rt
+        .block_on(
+            client("secret")
+                .start_get("/blocks/1/1", "localhost")
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(vec![authority], proxy.targets());
+}
+
+/// Accept connection which sends request before `SETTINGS` ack.
+fn accept_busy(server: &HttpServerTester) -> HttpConnTester {
+    let mut conn = server.accept();
+    conn.recv_preface();
+    conn.settings_xchg_but_ack();
+    conn
+}
+
+#[test]
+fn pool_least_loaded() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.max_connections = Some(2);
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+    let mut conn1 = server.accept_xchg();
+
+    let a = client.start_get("/a", "localhost").collect();
+    conn1.recv_frame_headers_check(1, true);
+
+    // first connection is busy, so second one is opened
+    let b = client.start_get("/b", "localhost").collect();
+    let mut conn2 = accept_busy(&server);
+    conn2.recv_frame_headers_check(1, true);
+
+    conn1.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(a).expect("a").headers.status());
+
+    // pool is full, request goes to the least loaded connection
+    let c = client.start_get("/c", "localhost").collect();
+    conn1.recv_frame_headers_check(3, true);
+
+    conn1.send_headers(3, Headers::ok_200(), true);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(b).expect("b").headers.status());
+    assert_eq!(200, rt.block_on(c).expect("c").headers.status());
+}
+
+#[test]
+fn pool_retires_goaway_conn() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (server, client) = HttpServerTester::new_with_client();
+    let mut conn1 = server.accept_xchg();
+
+    let a = client.start_get("/a", "localhost").collect();
+    conn1.recv_frame_headers_check(1, true);
+
+    conn1.send_goaway(1);
+    // GOAWAY is processed when PING is acknowledged
+    conn1.send_frame(PingFrame::new());
+    match conn1.recv_frame() {
+        HttpFrame::Ping(ping) => assert!(ping.is_ack()),
+        frame => panic!("expecting PING ack, got: {:?}", frame),
+    }
+
+    // new request is sent to a new connection,
+    // stream started before GOAWAY is completed on the old one
+    let b = client.start_get("/b", "localhost").collect();
+    let mut conn2 = accept_busy(&server);
+    conn2.recv_frame_headers_check(1, true);
+
+    conn2.send_headers(1, Headers::ok_200(), true);
+    conn1.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(b).expect("b").headers.status());
+    assert_eq!(200, rt.block_on(a).expect("a").headers.status());
+}
+
+#[test]
+fn idle_timeout_reconnect() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = HttpServerTester::new();
+    let mut conf = ClientConf::new();
+    conf.idle_timeout = Some(Duration::from_millis(100));
+    let client = Client::new_plain(BIND_HOST, server.port(), conf).expect("client");
+    let mut conn1 = server.accept_xchg();
+
+    let a = client.start_get("/a", "localhost").collect();
+    conn1.recv_frame_headers_check(1, true);
+    conn1.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(a).expect("a").headers.status());
+
+    // connection without streams is closed
+    conn1.recv_goaway_frame_check(ErrorCode::NoError);
+    conn1.recv_eof();
+
+    // and the next request opens a new one
+    let b = client.start_get("/b", "localhost").collect();
+    let mut conn2 = accept_busy(&server);
+    conn2.recv_frame_headers_check(1, true);
+    conn2.send_headers(1, Headers::ok_200(), true);
+    assert_eq!(200, rt.block_on(b).expect("b").headers.status());
+}
+
+/// Responds with request method and `authorization` header.
+fn echo_method_server() -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let authorization = req.headers.get_opt("authorization").unwrap_or("-");
+            resp.send_found_200_plain_text(&format!("{} {}", req.headers.method(), authorization))?;
+            Ok(())
+        });
+    server.build().expect("server")
+}
+
+/// Redirects `/hops/N` to `/hops/N-1`, `/post` to `/method` with `303`
+/// and `/away` to `/method` of server on `away_port`.
+fn redirect_server(away_port: u16) -> Server {
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            let path = req.headers.path();
+            if let Some(n) = path.strip_prefix("/hops/") {
+                match n.parse::<u32>().unwrap() {
+                    0 => resp.send_found_200_plain_text("done")?,
+                    n => resp.send_redirect_302(&format!("/hops/{}", n - 1))?,
+                }
+            } else if path == "/post" {
+                let mut headers = Headers::new_status(303);
+                headers.add("location", "method");
+                resp.send_headers_end_of_stream(headers)?;
+            } else if path == "/away" {
+                resp.send_redirect_302(&format!("http://{}:{}/method", BIND_HOST, away_port))?;
+            } else {
+                resp.send_found_200_plain_text(&format!("{} -", req.headers.method()))?;
+            }
+            Ok(())
+        });
+    server.build().expect("server")
+}
+
+#[test]
+fn follow_redirects() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let away = echo_method_server();
+    let server = redirect_server(away.local_addr().port().unwrap());
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.conf.max_redirects = Some(2);
+    let client = client.build().expect("client");
+
+    let mut get = |path: &str| {
+        rt.block_on(client.start_get(path, "localhost").collect())
+            .expect("get")
+    };
+
+    let r = get("/hops/2");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(b"done", &r.body.get_bytes()[..]);
+
+    // too many redirects
+    let r = get("/hops/3");
+    assert_eq!(302, r.headers.status());
+    assert_eq!("/hops/0", r.headers.get("location"));
+
+    // client does not connect to other origins
+    assert_eq!(302, get("/away").headers.status());
+
+    let mut headers = Headers::new_post("/post");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    let r = rt
+        .block_on(
+            client
+                .start_request_end_stream(headers, Some(Bytes::from_static(b"body")), None)
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(b"GET -", &r.body.get_bytes()[..]);
+}
+
+#[test]
+fn multi_client_follow_redirects() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let away = echo_method_server();
+    let server = redirect_server(away.local_addr().port().unwrap());
+
+    let mut conf = ClientConf::new();
+    conf.max_redirects = Some(1);
+    let client = MultiClient::new_plain(conf);
+
+    let mut headers = Headers::from_vec(vec![
+        Header::new(":method", "GET"),
+        Header::new(":path", "/away"),
+        Header::new(
+            ":authority",
+            format!("{}:{}", BIND_HOST, server.local_addr().port().unwrap()),
+        ),
+        Header::new(":scheme", "http"),
+    ]);
+    headers.add("authorization", "Bearer secret");
+    let r = rt
+        .block_on(
+            client
+                .start_request_end_stream(headers, None, None)
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    // credentials are not sent to another origin
+    assert_eq!(b"GET -", &r.body.get_bytes()[..]);
+    assert_eq!(2, client.client_count());
+}
+
+#[test]
+fn http_request() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = echo_method_server();
+    let port = server.local_addr().port().unwrap();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+    let request = http::Request::put("/")
+        .header("host", "localhost")
+        .header("authorization", "Bearer t")
+        .body(Bytes::from_static(b"body"))
+        .unwrap();
+    let r = rt
+        .block_on(client.start_http_request(request).collect())
+        .expect("put");
+    let r = http::Response::<Bytes>::try_from(r).expect("response");
+    assert_eq!(http::StatusCode::OK, r.status());
+    assert!(r.headers().contains_key("date"));
+    assert_eq!(&b"PUT Bearer t"[..], &r.body()[..]);
+
+    let request = http::Request::get("/").body(Bytes::new()).unwrap();
+    assert!(rt.block_on(client.start_http_request(request)).is_err());
+
+    let client = MultiClient::new_plain(Default::default());
+    let request = http::Request::get(format!("http://{}:{}/", BIND_HOST, port))
+        .body(Bytes::new())
+        .unwrap();
+    let r = rt
+        .block_on(client.start_http_request(request).collect())
+        .expect("get");
+    assert_eq!(b"GET -", &r.body.get_bytes()[..]);
+}
+
+#[test]
+fn request_builder() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let headers = req.headers.clone();
+            let body = req.make_stream().filter_data();
+            resp.send_headers(Headers::ok_200())?;
+            let prefix = format!(
+                "{} {} {} {} ",
+                headers.method(),
+                headers.get(":authority"),
+                headers.path(),
+                headers.get_opt("x-a").unwrap_or("-"),
+            );
+            resp.pull_bytes_from_stream(
+                futures::stream::once(async move { Ok(Bytes::from(prefix)) }).chain(body),
+            )?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let r = rt
+        .block_on(
+            client
+                .request(Method::PUT, "/path?a=1")
+                .header("x-a", "b")
+                .query("k", "v w")
+                .body("body")
+                .send()
+                .collect(),
+        )
+        .expect("put");
+    assert_eq!(
+        format!("PUT {}:{} /path?a=1&k=v%20w b body", BIND_HOST, port).as_bytes(),
+        &r.body.get_bytes()[..]
+    );
+
+    let r = rt
+        .block_on(
+            client
+                .request(Method::from("PROPFIND"), "/")
+                .authority("example.com")
+                .send()
+                .collect(),
+        )
+        .expect("propfind");
+    assert_eq!(&b"PROPFIND example.com / - "[..], &r.body.get_bytes()[..]);
+}
+
+#[test]
+fn interceptors() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = echo_method_server();
+
+    struct Recorder {
+        name: &'static str,
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl ClientInterceptor for Recorder {
+        fn before_send(&self, request: &mut Headers) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{} before", self.name));
+            request.add("authorization", self.name);
+        }
+
+        fn after_response_headers(&self, _request: &Headers, response: &Headers) {
+            self.events.lock().unwrap().push(format!(
+                "{} headers {}",
+                self.name,
+                response.status()
+            ));
+        }
+
+        fn after_response(&self, request: &Headers, error: Option<&Error>) {
+            self.events.lock().unwrap().push(format!(
+                "{} end {} {:?}",
+                self.name,
+                request.path(),
+                error.map(|e| e.to_string())
+            ));
+        }
+    }
+
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_addr((BIND_HOST, server.local_addr().port().unwrap()))
+        .expect("set_addr");
+    client.add_interceptor(Recorder {
+        name: "a",
+        events: events.clone(),
+    });
+    client.add_interceptor(Recorder {
+        name: "b",
+        events: events.clone(),
+    });
+    let client = client.build().expect("client");
+
+    let r = rt
+        .block_on(client.start_get("/x", "localhost").collect())
+        .expect("get");
+    // the first header added by interceptor is used
+    assert_eq!(b"GET a", &r.body.get_bytes()[..]);
+    assert_eq!(
+        vec![
+            "a before",
+            "b before",
+            "b headers 200",
+            "a headers 200",
+            "b end /x None",
+            "a end /x None",
+        ],
+        *events.lock().unwrap()
+    );
+
+    // response body is dropped
+    events.lock().unwrap().clear();
+    let (_, body) = rt
+        .block_on(client.start_get("/x", "localhost").0)
+        .expect("get");
+    drop(body);
+    assert_eq!(
+        Some(&"a end /x Some(\"Request caller died\")".to_owned()),
+        events.lock().unwrap().last()
+    );
+}
+
+#[test]
+fn multi_client_races_resolved_addresses() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_found_200_plain_text("hello")?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let mut client = MultiClient::new_plain(ClientConf::new());
+    // nobody listens on the first address
+    client.set_resolver(|_host: &str, port: u16| {
+        future::ok(vec![
+            format!("127.0.0.2:{}", port).parse().unwrap(),
+            format!("{}:{}", BIND_HOST, port).parse().unwrap(),
+        ])
+    });
+
+    let r = rt
+        .block_on(
+            client
+                .start_get(&format!("http://service.test:{}/", port))
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(&b"hello"[..], r.body.get_bytes());
+}
+
+#[test]
+fn multi_client_origins() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server1 = ServerTest::new();
+    let server2 = ServerTest::new();
+
+    let client = MultiClient::new_plain(ClientConf::new());
+
+    for &(port, path, expected) in &[
+        (server1.port, "/blocks/1/2", &[0, 1][..]),
+        (server2.port, "/blocks/2/1", &[0, 0][..]),
+        (server1.port, "/blocks/1/1", &[0][..]),
+    ] {
+        let r = rt
+            .block_on(
+                client
+                    .start_get(&format!("http://{}:{}{}", BIND_HOST, port, path))
+                    .collect(),
+            )
+            .expect("get");
+        assert_eq!(200, r.headers.status());
+        assert_eq!(expected, &r.body.get_bytes()[..]);
+    }
+    assert_eq!(2, client.client_count());
+
+    // plain connections are not shared between hosts
+    let headers = Headers::from_vec(vec![
+        Header::new(":method", "GET"),
+        Header::new(":path", "/blocks/1/1"),
+        Header::new(":authority", format!("localhost:{}", server1.port)),
+        Header::new(":scheme", "http"),
+    ]);
+    let r = rt
+        .block_on(
+            client
+                .start_request_end_stream(headers, None, None)
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(3, client.client_count());
+
+    match rt.block_on(client.start_get("/relative").collect()) {
+        Err(Error::InvalidRequestTarget(_)) => {}
+        r => panic!("{:?}", r.map(|r| r.headers)),
+    }
+}
+
+#[test]
+fn happy_eyeballs_fallback() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+
+    // port nobody listens on
+    let closed = std::net::TcpListener::bind((BIND_HOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let addrs = [
+        closed,
+        format!("{}:{}", BIND_HOST, server.port).parse().unwrap(),
+    ];
+
+    let mut client = ClientBuilder::new_plain();
+    client.conf.happy_eyeballs_delay = Some(Duration::from_secs(10));
+    client.set_addr(&addrs[..]).expect("set_addr");
+    let client = client.build().expect("client");
+
+    let start = Instant::now();
+    let r = rt
+        .block_on(client.start_get("/blocks/1/1", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    // refused attempt does not wait for delay
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn custom_resolver() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let server_addr: std::net::SocketAddr =
+        format!("{}:{}", BIND_HOST, server.port).parse().unwrap();
+
+    let resolved = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let resolved_copy = resolved.clone();
+
+    let mut client = ClientBuilder::new_plain();
+    client.set_host("service.test", 1234);
+    client.set_resolver(move |host: &str, port: u16| {
+        resolved_copy.lock().unwrap().push((host.to_owned(), port));
+        future::ok(vec![server_addr])
+    });
+    let client = client.build().expect("client");
+
+    let r = rt
+        .block_on(client.start_get("/blocks/1/1", "service.test").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+    assert_eq!(
+        vec![("service.test".to_owned(), 1234)],
+        *resolved.lock().unwrap()
+    );
+
+    // system resolver
+    let mut client = ClientBuilder::new_plain();
+    client.set_host("localhost", server.port);
+    let client = client.build().expect("client");
+    let r = rt
+        .block_on(client.start_get("/blocks/1/1", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, r.headers.status());
+}
+
+#[cfg(windows)]
+#[test]
+fn named_pipe() {
+    init_logger();
+
+    let path = format!(r"\\.\pipe\httpbis-test-{}", std::process::id());
+
+    let mut server = ServerBuilder::new_plain();
+    server
+        .set_named_pipe_addr(&path[..])
+        .expect("set_named_pipe_addr");
+    server
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_message(SimpleHttpMessage::found_200_plain_text("named pipe"))?;
+            Ok(())
+        });
+    let _server = server.build().expect("server");
+
+    let mut client = ClientBuilder::new_plain();
+    client
+        .set_named_pipe_addr(&path[..])
+        .expect("set_named_pipe_addr");
+    let client = client.build().expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    for _ in 0..2 {
+        let r = rt
+            .block_on(client.start_get("/", "localhost").collect())
+            .expect("get");
+        assert_eq!(&b"named pipe"[..], r.body.get_bytes());
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn named_pipe_unsupported() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server
+        .set_named_pipe_addr(r"\\.\pipe\httpbis-test")
+        .expect("set_named_pipe_addr");
+    assert!(server.build().is_err());
+}
+
+#[test]
+fn connection_timeout_without_settings() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    // accepts connections but never sends SETTINGS
+    let listener = std::net::TcpListener::bind((BIND_HOST, 0)).unwrap();
+
+    let mut client = ClientBuilder::new_plain();
+    client.conf.connection_timeout = Some(Duration::from_millis(200));
+    client
+        .set_addr(listener.local_addr().unwrap())
+        .expect("set_addr");
+    let client = client.build().expect("client");
+
+    let start = Instant::now();
+    let e = rt
+        .block_on(client.start_get("/blocks/1/1", "localhost").collect())
+        .err()
+        .expect("error");
+    assert!(format!("{:?}", e).contains("ConnectionTimeout"), "{:?}", e);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    drop(listener);
+}
+
+#[test]
+fn deadline_before_headers() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let resp = client.start_get_with_deadline("/slow", "localhost", Duration::from_millis(100));
+    server_tester.recv_message(1);
+
+    match rt.block_on(resp.collect()) {
+        Err(httpbis::Error::DeadlineExceeded) => {}
+        r => panic!("expecting DeadlineExceeded: {:?}", r.map(|m| m.dump())),
+    }
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+
+    let state = rt.block_on(client.dump_state()).expect("dump_state");
+    assert_eq!(0, state.streams.len());
+}
+
+#[test]
+fn deadline_during_body() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (mut server_tester, client) = HttpConnTester::new_server_with_client_xchg();
+
+    let resp = client.start_get_with_deadline("/slow", "localhost", Duration::from_millis(200));
+    server_tester.recv_message(1);
+    server_tester.send_headers(1, Headers::ok_200(), false);
+    server_tester.send_data(1, b"abc", false);
+
+    match rt.block_on(resp.collect()) {
+        Err(httpbis::Error::DeadlineExceeded) => {}
+        r => panic!("expecting DeadlineExceeded: {:?}", r.map(|m| m.dump())),
+    }
+    server_tester.recv_rst_frame_check(1, ErrorCode::Cancel);
+
+    let state = rt.block_on(client.dump_state()).expect("dump_state");
+    assert_eq!(0, state.streams.len());
+
+    // deadline does not affect fast requests
+    let resp = client.start_get_with_deadline("/fast", "localhost", Duration::from_secs(10));
+    server_tester.recv_message(3);
+    server_tester.send_headers(3, Headers::ok_200(), false);
+    server_tester.send_data(3, b"abc", true);
+    let message = rt.block_on(resp.collect()).expect("collect");
+    assert_eq!(&b"abc"[..], &message.body.get_bytes()[..]);
+}
+
+#[test]
+fn post_stream() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = ServerTest::new();
+    let client = Client::new_plain(BIND_HOST, server.port, Default::default()).expect("client");
+
+    // larger than initial window, so sending waits for window updates
+    let body: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+
+    let chunks: Vec<_> = body
+        .chunks(10_000)
+        .map(|c| Ok(Bytes::copy_from_slice(c)))
+        .collect();
+    let resp = client.start_post_stream("/echo", "localhost", futures::stream::iter(chunks));
+    let message = rt.block_on(resp.collect()).expect("collect");
+    assert_eq!(200, message.headers.status());
+    assert_eq!(&body[..], &message.body.get_bytes()[..]);
+
+    let resp = client.start_post_read("/echo", "localhost", &b"read"[..]);
+    let message = rt.block_on(resp.collect()).expect("collect");
+    assert_eq!(&b"read"[..], &message.body.get_bytes()[..]);
+}
+
+#[test]
+fn metrics() {
+    init_logger();
+
+    let metrics = Arc::new(TestMetrics::default());
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.metrics = Some(metrics.clone());
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            if req.headers.path() == "/reset" {
+                resp.reset(ErrorCode::Cancel)?;
+                return Ok(());
+            }
+            resp.send_found_200_plain_text("world")?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut conf = ClientConf::new();
+    conf.common.metrics = Some(metrics.clone());
+    let client =
+        Client::new_plain(BIND_HOST, server.local_addr().port().unwrap(), conf).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let resp = rt
+        .block_on(
+            client
+                .start_post("/", "localhost", Bytes::from_static(b"hello"))
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(&b"world"[..], resp.body.get_bytes());
+
+    for side in &["client", "server"] {
+        let side = [("side", *side)];
+        assert_eq!(1, metrics.get(Metric::ConnectionsOpened, &side));
+        assert_eq!(1, metrics.get(Metric::HandshakeDuration, &side));
+        assert_eq!(1, metrics.get(Metric::StreamsStarted, &side));
+        metrics.wait(Metric::StreamsActive, &side, 0);
+        assert_eq!(1, metrics.get(Metric::StreamDuration, &side));
+    }
+
+    let client_sent = [("side", "client"), ("direction", "sent")];
+    let server_sent = [("side", "server"), ("direction", "sent")];
+    let server_received = [("side", "server"), ("direction", "received")];
+    metrics.wait(Metric::DataBytes, &client_sent, 5);
+    metrics.wait(Metric::DataBytes, &server_received, 5);
+    metrics.wait(Metric::DataBytes, &server_sent, 5);
+    let headers_sent = [client_sent[0], client_sent[1], ("type", "HEADERS")];
+    assert_eq!(1, metrics.get(Metric::Frames, &headers_sent));
+    assert_eq!(0, metrics.get(Metric::StreamsReset, &client_sent));
+
+    match rt.block_on(client.start_get("/reset", "localhost").collect()) {
+        Err(Error::RstStreamReceived(ErrorCode::Cancel)) => {}
+        r => panic!("{:?}", r.map(|m| m.dump())),
+    }
+    let client_received = [("side", "client"), ("direction", "received")];
+    metrics.wait(Metric::StreamsReset, &server_sent, 1);
+    metrics.wait(Metric::StreamsReset, &client_received, 1);
+
+    rt.block_on(client.ping()).expect("ping");
+    assert!(metrics.get(Metric::Rtt, &[("side", "client")]) >= 1);
+
+    drop(client);
+    for side in &["client", "server"] {
+        let side = [("side", *side)];
+        metrics.wait(Metric::ConnectionsClosed, &side, 1);
+        assert_eq!(0, metrics.get(Metric::ConnectionsActive, &side));
+    }
+}
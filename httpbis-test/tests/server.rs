@@ -11,26 +11,36 @@ extern crate regex;
 extern crate httpbis_test;
 use httpbis_test::*;
 
+use std::path::Path;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use bytes::Bytes;
 
 use std::io::Read as _Read;
 use std::io::Write as _Write;
 use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
 use futures::stream;
 
 use futures::channel::oneshot;
 use futures::stream::Stream;
 use futures::stream::StreamExt;
+use futures::stream::TryStreamExt;
+use std::future::Future;
 
 use std::task::Poll;
 
 use httpbis::for_test::solicit::frame::HeadersFlag;
+use httpbis::for_test::solicit::frame::HttpFrame;
 use httpbis::for_test::solicit::frame::HttpSetting;
+use httpbis::for_test::solicit::frame::PingFrame;
+use httpbis::for_test::solicit::frame::PriorityFrame;
+use httpbis::for_test::solicit::frame::PriorityUpdateFrame;
 use httpbis::for_test::solicit::frame::SettingsFrame;
 use httpbis::for_test::solicit::DEFAULT_SETTINGS;
 use httpbis::*;
@@ -51,6 +61,289 @@ use tokio::runtime::Runtime;
 #[cfg(unix)]
 use unix_socket::UnixStream;
 
+fn post_headers(path: &str) -> Headers {
+    let mut headers = Headers::new_post(path);
+    headers.add(":scheme", "http");
+    headers
+}
+
+#[test]
+fn collect_request_with_limit() {
+    init_logger();
+
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+
+    let server = ServerOneConn::new_fn(0, move |context, req, mut resp| {
+        let tx = tx.lock().unwrap().clone();
+        let collect = req.collect_with_limit(5);
+        context.loop_remote().spawn(async move {
+            let r = collect.await;
+            if r.is_ok() {
+                resp.send_message(SimpleHttpMessage::found_200_plain_text("ok"))
+                    .unwrap();
+            }
+            tx.send(r.map(|m| m.body.get_bytes())).unwrap();
+        });
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_headers(1, post_headers("/small"), false);
+    tester.send_data(1, b"abcde", true);
+    assert_eq!(&b"abcde"[..], &rx.recv().unwrap().expect("ok")[..]);
+    assert_eq!(200, tester.recv_message(1).headers.status());
+
+    tester.send_headers(3, post_headers("/large"), false);
+    tester.send_data(3, b"abc", false);
+    tester.send_data(3, b"def", false);
+    match rx.recv().unwrap() {
+        Err(httpbis::Error::BodyLimitExceeded(5)) => {}
+        r => panic!("expecting BodyLimitExceeded: {:?}", r),
+    }
+    tester.recv_rst_frame_check(3, ErrorCode::Cancel);
+}
+
+#[test]
+fn ping_rtt() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.ping_interval = Some(Duration::from_millis(10));
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg_but_ack();
+
+    assert_eq!(None, server.dump_state().rtt);
+
+    let ping = loop {
+        match tester.recv_frame() {
+            HttpFrame::Ping(ping) => break ping,
+            HttpFrame::Settings(..) => {}
+            frame => panic!("expecting PING, got: {:?}", frame),
+        }
+    };
+    assert!(!ping.is_ack());
+    thread::sleep(Duration::from_millis(20));
+    tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+
+    let rtt = loop {
+        if let Some(rtt) = server.dump_state().rtt {
+            break rtt;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    assert!(rtt >= Duration::from_millis(20), "{:?}", rtt);
+}
+
+#[test]
+fn adaptive_window() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.adaptive_window = Some(true);
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_headers(1, post_headers("/"), false);
+    // first frame starts measurement, the rest is received during round trip
+    for _ in 0..5 {
+        tester.send_data(1, &[17; 12000], false);
+    }
+
+    let ping = loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::Ping(ping) => break ping,
+            _ => {}
+        }
+    };
+    assert!(!ping.is_ack());
+    tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+
+    // window is grown to twice the data received during round trip
+    let expected = 2 * 4 * 12000 - DEFAULT_SETTINGS.initial_window_size;
+    loop {
+        match tester.fn_recv_frame_no_check_ack() {
+            HttpFrame::WindowUpdate(f) if f.stream_id == 0 && f.increment == expected => break,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn keepalive_timeout() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.keepalive_interval = Some(Duration::from_millis(20));
+    conf.keepalive_timeout = Some(Duration::from_millis(50));
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    fn recv_ping(tester: &mut HttpConnTester) -> PingFrame {
+        loop {
+            match tester.recv_frame() {
+                HttpFrame::Ping(ping) => return ping,
+                HttpFrame::Settings(..) => {}
+                frame => panic!("expecting PING, got: {:?}", frame),
+            }
+        }
+    }
+
+    // acknowledged PING keeps connection alive
+    let ping = recv_ping(&mut tester);
+    assert!(!ping.is_ack());
+    tester.send_frame(PingFrame::new_ack(ping.opaque_data()));
+
+    // connection is closed if the next one is not acknowledged
+    let ping = recv_ping(&mut tester);
+    assert!(!ping.is_ack());
+    tester.recv_eof();
+}
+
+#[test]
+fn idle_timeout() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.idle_timeout = Some(Duration::from_millis(100));
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let resp = tester.get(1, "/hi");
+    assert_eq!(200, resp.headers.status());
+
+    let goaway = tester.recv_goaway_frame();
+    assert_eq!(ErrorCode::NoError, goaway.error_code());
+    assert_eq!(1, goaway.last_stream_id);
+    tester.recv_eof();
+}
+
+#[test]
+fn conn_id() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |context, _req, mut resp| {
+        resp.send_found_200_plain_text(&format!("{}", context.conn_id().get()))?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let resp = tester.get(1, "/foo");
+    let conn_id = server.dump_state().conn_id;
+    assert_eq!(
+        format!("{}", conn_id.get()).as_bytes(),
+        &resp.body.get_bytes()[..]
+    );
+}
+
+#[test]
+fn misbehavior_ping_flood() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.common.misbehavior_limits.max_ping_per_sec = Some(2);
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    for i in 0..3 {
+        tester.send_frame(PingFrame::with_data(i));
+    }
+
+    let goaway = loop {
+        match tester.recv_frame() {
+            HttpFrame::Goaway(goaway) => break goaway,
+            HttpFrame::Ping(ping) => assert!(ping.is_ack()),
+            frame => panic!("expecting GOAWAY, got: {:?}", frame),
+        }
+    };
+    assert_eq!(ErrorCode::EnhanceYourCalm, goaway.error_code());
+}
+
+#[test]
+fn misbehavior_ping_flood_with_open_stream() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.common.misbehavior_limits.max_ping_per_sec = Some(2);
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        resp.pull_from_stream(req.make_stream())?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    // stream is kept open by peer
+    let mut headers = Headers::new_post("/echo");
+    headers.add(":scheme", "http");
+    tester.send_headers(1, headers, false);
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+
+    for i in 0..3 {
+        tester.send_frame(PingFrame::with_data(i));
+    }
+
+    let mut acked = Vec::new();
+    let goaway = loop {
+        match tester.recv_frame() {
+            HttpFrame::Goaway(goaway) => break goaway,
+            HttpFrame::Ping(ping) => {
+                assert!(ping.is_ack());
+                acked.push(ping.opaque_data);
+            }
+            frame => panic!("expecting GOAWAY, got: {:?}", frame),
+        }
+    };
+    // `PING` exceeding the limit is acknowledged too
+    assert_eq!(vec![0, 1, 2], acked);
+    assert_eq!(ErrorCode::EnhanceYourCalm, goaway.error_code());
+    tester.recv_eof();
+}
+
 #[test]
 fn simple_new() {
     init_logger();
@@ -460,6 +753,72 @@ fn do_not_poll_when_not_enough_window() {
     assert_eq!(2, polls.load(Ordering::SeqCst));
 }
 
+#[test]
+fn trailers_after_flow_controlled_data() {
+    init_logger();
+
+    let w = DEFAULT_SETTINGS.initial_window_size as usize;
+
+    let server = ServerOneConn::new_fn(0, move |_, _, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        resp.send_data(Bytes::from(vec![1; w + 100]))?;
+        let mut trailers = Headers::new();
+        trailers.add("grpc-status", "0");
+        resp.send_trailers(trailers)?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/fgfg");
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    tester.recv_frames_data_check(1, 16_384, w, false);
+
+    // trailers are queued behind data until window is increased
+    tester.send_window_update_conn(100);
+    tester.send_window_update_stream(1, 100);
+
+    assert_eq!(vec![1; 100], tester.recv_frame_data_check(1, false));
+    let trailers = tester.recv_frame_headers_check(1, true);
+    assert_eq!("0", trailers.get("grpc-status"));
+}
+
+#[test]
+fn informational_response() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _, mut resp| {
+        match resp.send_informational(200, Headers::new()) {
+            Err(SendError::NotInformationalStatus(200)) => {}
+            r => panic!("expecting NotInformationalStatus, got: {:?}", r),
+        }
+        for link in &["</a.css>; rel=preload", "</b.js>; rel=preload"] {
+            let mut headers = Headers::new();
+            headers.add("link", *link);
+            resp.send_informational(103, headers)?;
+        }
+        resp.send_headers_end_of_stream(Headers::ok_200())?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_get(1, "/fgfg");
+    let headers = tester.recv_frame_headers_check(1, false);
+    assert_eq!(103, headers.status());
+    assert_eq!("</a.css>; rel=preload", headers.get("link"));
+    // informational responses have no default headers
+    assert_eq!(None, headers.get_opt("date"));
+    let headers = tester.recv_frame_headers_check(1, false);
+    assert_eq!(103, headers.status());
+    assert_eq!("</b.js>; rel=preload", headers.get("link"));
+    assert_eq!(200, tester.recv_frame_headers_check(1, true).status());
+}
+
 #[test]
 pub fn server_sends_continuation_frame() {
     init_logger();
@@ -487,9 +846,11 @@ pub fn server_sends_continuation_frame() {
     tester.settings_xchg();
 
     tester.send_get(1, "/long-header-list");
-    let (headers_frame, headers_recv, cont_count) = tester.recv_frame_headers_decode();
+    let (headers_frame, mut headers_recv, cont_count) = tester.recv_frame_headers_decode();
     assert!(headers_frame.flags.is_set(HeadersFlag::EndHeaders));
     assert!(cont_count > 0);
+    assert!(headers_recv.get_opt("date").is_some());
+    headers_recv.remove("date");
     assert_eq!(headers, headers_recv);
 
     assert_eq!(&b"there"[..], &tester.recv_frame_data_tail(1)[..]);
@@ -536,6 +897,87 @@ pub fn http_1_1_unix() {
     );
 }
 
+#[cfg(unix)]
+#[test]
+pub fn http2_unix() {
+    init_logger();
+
+    let tempdir = tempdir::TempDir::new("rust_http2_test").unwrap();
+    let socket_path = tempdir.path().join("test_socket");
+    let _server = ServerTest::new_unix(socket_path.to_str().unwrap().to_owned());
+
+    let client =
+        Client::new_plain_unix(socket_path.to_str().unwrap(), ClientConf::new()).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let resp = rt
+        .block_on(client.start_get("/blocks/3/2", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&[0, 0, 0, 1, 1, 1][..], resp.body.get_bytes());
+}
+
+#[test]
+fn h2c_upgrade() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        resp.send_data_end_of_stream(Bytes::from(format!(
+            "{} {} {}",
+            req.headers.method(),
+            req.headers.path(),
+            req.headers.get_opt("accept").unwrap_or("-"),
+        )))?;
+        Ok(())
+    });
+
+    let mut tcp_stream = TcpStream::connect((BIND_HOST, server.port())).expect("connect");
+
+    // `HTTP2-Settings` is INITIAL_WINDOW_SIZE 5
+    tcp_stream
+        .write_all(
+            b"GET /foo HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Connection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: AAQAAAAF\r\n\
+            Accept: text/plain\r\n\r\n",
+        )
+        .expect("write");
+
+    let mut read = Vec::new();
+    while !read.ends_with(b"\r\n\r\n") {
+        let mut byte = [0];
+        tcp_stream.read_exact(&mut byte).expect("read");
+        read.push(byte[0]);
+    }
+    assert!(
+        read.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"),
+        "{:?}",
+        BsDebug(&read)
+    );
+
+    let mut tester = HttpConnTester::with_tcp(tcp_stream);
+    tester.send_preface();
+    // response may be sent before our SETTINGS is acknowledged
+    tester.settings_xchg_but_ack();
+
+    // upgraded request is stream 1 and settings from the header are applied
+    assert_eq!(200, tester.recv_frame_headers_check(1, false).status());
+    assert_eq!(b"GET /", &tester.recv_frame_data_check(1, false)[..]);
+    tester.send_window_update_stream(1, 100);
+    assert_eq!(
+        b"foo text/plain",
+        &tester.recv_frame_data_check(1, true)[..]
+    );
+
+    // settings apply to later streams too
+    tester.send_get(3, "/bar");
+    assert_eq!(200, tester.recv_frame_headers_check(3, false).status());
+    assert_eq!(b"GET /", &tester.recv_frame_data_check(3, false)[..]);
+}
+
 #[test]
 fn external_event_loop() {
     init_logger();
@@ -598,3 +1040,1494 @@ fn external_event_loop() {
 
     info!("last line of test");
 }
+
+fn proxy_server(upstream_port: u16, configure: impl FnOnce(&mut ProxyHandler)) -> Server {
+    let upstream = Client::new_plain(BIND_HOST, upstream_port, ClientConf::new()).expect("client");
+    let mut proxy = ProxyHandler::new(Arc::new(upstream));
+    configure(&mut proxy);
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_service("/", Arc::new(proxy));
+    server.build().expect("server")
+}
+
+#[test]
+fn proxy() {
+    init_logger();
+
+    let upstream = ServerTest::new();
+    let proxy = proxy_server(upstream.port, |proxy| {
+        proxy.via = Some("test-proxy".to_owned());
+        proxy.response_hook = Some(Arc::new(|headers: &mut Headers| {
+            headers.add("x-proxied", "1");
+        }));
+    });
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        proxy.local_addr().port().unwrap(),
+        ClientConf::new(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = rt
+        .block_on(client.start_get("/blocks/1000/100", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!("2 test-proxy", resp.headers.get("via"));
+    assert_eq!("1", resp.headers.get("x-proxied"));
+    assert_eq!(1000 * 100, resp.body.len());
+
+    let body = Bytes::from(vec![17; 200000]);
+    let resp = rt
+        .block_on(
+            client
+                .start_post("/echo", "localhost", body.clone())
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(body, resp.body.get_bytes());
+}
+
+#[test]
+fn proxy_upstream_timeout() {
+    init_logger();
+
+    struct Hang {
+        responses: Mutex<Vec<ServerResponse>>,
+    }
+
+    impl ServerHandler for Hang {
+        fn start_request(
+            &self,
+            _context: ServerHandlerContext,
+            _req: ServerRequest,
+            resp: ServerResponse,
+        ) -> httpbis::Result<()> {
+            self.responses.lock().unwrap().push(resp);
+            Ok(())
+        }
+    }
+
+    let mut upstream = ServerBuilder::new_plain();
+    upstream.set_port(0);
+    upstream.service.set_service(
+        "/",
+        Arc::new(Hang {
+            responses: Mutex::new(Vec::new()),
+        }),
+    );
+    let upstream = upstream.build().expect("server");
+
+    let proxy = proxy_server(upstream.local_addr().port().unwrap(), |proxy| {
+        proxy.upstream_timeout = Some(Duration::from_millis(100));
+    });
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        proxy.local_addr().port().unwrap(),
+        ClientConf::new(),
+    )
+    .expect("client");
+
+    let resp = Runtime::new()
+        .unwrap()
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(504, resp.headers.status());
+}
+
+#[test]
+fn connect_gateway() {
+    init_logger();
+
+    // TCP echo server
+    let listener = std::net::TcpListener::bind((BIND_HOST, 0)).expect("bind");
+    let target_port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut socket, _) = listener.accept().expect("accept");
+        let mut buf = [0; 1000];
+        loop {
+            let n = socket.read(&mut buf).expect("read");
+            if n == 0 {
+                break;
+            }
+            socket.write_all(&buf[..n]).expect("write");
+        }
+    });
+
+    let gateway = ConnectGateway::new(Arc::new(|headers: &Headers| {
+        headers.get_opt("proxy-authorization") == Some("secret")
+    }));
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_connect_service(Arc::new(gateway));
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        ClientConf::new(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let connect_headers = |auth: &str| {
+        Headers::from_vec(vec![
+            Header::new(":method", "CONNECT"),
+            Header::new(":authority", format!("{}:{}", BIND_HOST, target_port)),
+            Header::new("proxy-authorization", auth.to_owned()),
+        ])
+    };
+
+    let resp = rt
+        .block_on(
+            client
+                .start_request_end_stream(connect_headers("wrong"), None, None)
+                .collect(),
+        )
+        .expect("connect");
+    assert_eq!(407, resp.headers.status());
+
+    let (mut req, resp) = rt
+        .block_on(client.start_request(connect_headers("secret"), None, None, false))
+        .expect("connect");
+    let (headers, resp) = rt.block_on(resp.0).expect("headers");
+    assert_eq!(200, headers.status());
+    let mut resp = resp.filter_data();
+
+    for i in 1..10 {
+        let data = Bytes::from(vec![i as u8; i * 100]);
+        req.send_data(data.clone()).expect("send");
+        let mut received = Vec::new();
+        while received.len() < data.len() {
+            received.extend(rt.block_on(resp.next()).unwrap().unwrap());
+        }
+        assert_eq!(&data[..], &received[..]);
+    }
+
+    // tunnel is closed when target closes the socket after client EOF
+    req.close().expect("close");
+    let rem: Vec<_> = rt.block_on(resp.collect::<Vec<_>>());
+    assert!(rem.into_iter().all(|b| b.unwrap().is_empty()));
+}
+
+#[test]
+fn connect_without_gateway() {
+    init_logger();
+
+    let server = ServerTest::new();
+    let client = Client::new_plain(BIND_HOST, server.port, ClientConf::new()).expect("client");
+    let headers = Headers::from_vec(vec![
+        Header::new(":method", "CONNECT"),
+        Header::new(":authority", "example.com:443"),
+    ]);
+    let resp = Runtime::new()
+        .unwrap()
+        .block_on(
+            client
+                .start_request_end_stream(headers, None, None)
+                .collect(),
+        )
+        .expect("connect");
+    assert_eq!(405, resp.headers.status());
+}
+
+#[test]
+fn request_context() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.common.context_propagation = Some(ContextPropagation::default());
+    server
+        .service
+        .set_service_fn("/", |context, _req, mut resp| {
+            let request_context = context.request_context();
+            let body = format!(
+                "{:x} {} {}",
+                request_context.trace.map(|t| t.trace_id).unwrap_or(0),
+                request_context.baggage_item("tenant").unwrap_or(""),
+                request_context.deadline.is_some(),
+            );
+            resp.send_message(SimpleHttpMessage::found_200_plain_text(&body))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut context = RequestContext::new();
+    let trace = TraceContext::new_root();
+    context.trace = Some(trace);
+    context.deadline = Some(std::time::Instant::now() + Duration::from_secs(30));
+    context
+        .baggage
+        .push(("tenant".to_owned(), "acme".to_owned()));
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":scheme", "http");
+    headers.add(":authority", "localhost");
+
+    let mut rt = Runtime::new().unwrap();
+    let message = client
+        .start_request_end_stream_with_context(&context, headers, None, None)
+        .collect();
+    let message = rt.block_on(message).expect("get");
+    assert_eq!(
+        format!("{:x} acme true", trace.trace_id),
+        String::from_utf8(message.body.get_bytes().to_vec()).unwrap()
+    );
+
+    let message = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(&b"0  false"[..], message.body.get_bytes());
+}
+
+#[test]
+fn default_headers() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    let mut default_headers = Headers::new();
+    default_headers.add("server", "httpbis");
+    default_headers.add("strict-transport-security", "max-age=63072000");
+    server.conf.default_headers = Some(default_headers);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            let mut headers = Headers::ok_200();
+            if req.headers.path() == "/own" {
+                headers.add("server", "handler");
+            }
+            resp.send_headers_end_of_stream(headers)?;
+            Ok(())
+        });
+    server
+        .service
+        .set_service_fn("/static", |_context, _req, mut resp| {
+            resp.send_found_200_plain_text("static")?;
+            Ok(())
+        });
+    let mut static_headers = Headers::new();
+    static_headers.add("cache-control", "max-age=60");
+    static_headers.add("server", "static");
+    server
+        .service
+        .set_default_headers("/static", static_headers);
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let message = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!("httpbis", message.headers.get("server"));
+    assert_eq!(
+        "max-age=63072000",
+        message.headers.get("strict-transport-security")
+    );
+    assert_eq!(None, message.headers.get_opt("cache-control"));
+    assert!(message.headers.get("date").ends_with(" GMT"));
+
+    let message = rt
+        .block_on(client.start_get("/static/a.txt", "localhost").collect())
+        .expect("get");
+    assert_eq!("static", message.headers.get("server"));
+    assert_eq!("max-age=60", message.headers.get("cache-control"));
+    assert_eq!(
+        "max-age=63072000",
+        message.headers.get("strict-transport-security")
+    );
+
+    let message = rt
+        .block_on(client.start_get("/own", "localhost").collect())
+        .expect("get");
+    assert_eq!("handler", message.headers.get("server"));
+}
+
+#[test]
+fn middleware() {
+    init_logger();
+
+    /// Rejects requests without `authorization`, passes user to handler.
+    struct Auth;
+
+    impl ServerMiddleware for Auth {
+        fn start_request(
+            &self,
+            context: ServerHandlerContext,
+            mut req: ServerRequest,
+            mut resp: ServerResponse,
+            next: Next,
+        ) -> httpbis::Result<()> {
+            let user = match req.headers.get_opt("authorization") {
+                Some(user) => user.to_owned(),
+                None => {
+                    resp.send_headers_end_of_stream(Headers::new_status(401))?;
+                    return Ok(());
+                }
+            };
+            req.headers.add("x-user", user);
+            next.start_request(context, req, resp)
+        }
+    }
+
+    /// Adds header with the middleware order to responses.
+    struct Layer(&'static str);
+
+    impl ServerMiddleware for Layer {
+        fn start_request(
+            &self,
+            context: ServerHandlerContext,
+            req: ServerRequest,
+            mut resp: ServerResponse,
+            next: Next,
+        ) -> httpbis::Result<()> {
+            let name = self.0;
+            resp.add_headers_filter(move |headers| {
+                let layers = match headers.get_opt("x-layers") {
+                    Some(layers) => format!("{},{}", layers, name),
+                    None => name.to_owned(),
+                };
+                headers.remove("x-layers");
+                headers.add("x-layers", layers);
+            });
+            next.start_request(context, req, resp)
+        }
+    }
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.add_middleware(Layer("outer"));
+    server.add_middleware(Auth);
+    server.add_middleware(Layer("inner"));
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            resp.send_found_200_plain_text(req.headers.get("x-user"))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":authority", "localhost");
+    headers.add(":scheme", "http");
+    headers.add("authorization", "alice");
+    let message = rt
+        .block_on(
+            client
+                .start_request_end_stream(headers, None, None)
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(200, message.headers.status());
+    assert_eq!(&b"alice"[..], message.body.get_bytes());
+    assert_eq!("inner,outer", message.headers.get("x-layers"));
+
+    let message = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(401, message.headers.status());
+    assert_eq!("outer", message.headers.get("x-layers"));
+}
+
+#[test]
+fn grpc() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            if !grpc::is_grpc_request(&req.headers) {
+                resp.send_headers_end_of_stream(Headers::new_status(415))?;
+                return Ok(());
+            }
+            if req.headers.path() != "/test.Echo/Upper" {
+                let status = grpc::GrpcStatus::new(grpc::GrpcCode::Unimplemented, "no method");
+                resp.send_headers_end_of_stream(grpc::trailers_only(&status))?;
+                return Ok(());
+            }
+            let codec = LengthPrefixedCodec::grpc();
+            let messages = codec
+                .decode_stream(Box::pin(req.make_stream().filter_data()))
+                .map_ok(|m| Bytes::from(m.to_ascii_uppercase()));
+            resp.send_headers(grpc::response_headers())?;
+            resp.pull_from_stream(grpc::response_body(&codec, messages))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let codec = LengthPrefixedCodec::grpc();
+    let mut call = |path: &str| {
+        let headers = grpc::request_headers(HttpScheme::Http, "localhost", path);
+        let mut body = Vec::new();
+        body.extend_from_slice(&codec.encode(b"hello").unwrap());
+        body.extend_from_slice(&codec.encode(b"world").unwrap());
+        let response = client.start_request_end_stream(headers, Some(Bytes::from(body)), None);
+        rt.block_on(async {
+            let (headers, body) = response.await?;
+            grpc::response_messages(&codec, &headers, body)
+                .try_collect::<Vec<Bytes>>()
+                .await
+        })
+    };
+
+    assert_eq!(
+        vec![Bytes::from_static(b"HELLO"), Bytes::from_static(b"WORLD")],
+        call("/test.Echo/Upper").expect("call")
+    );
+    match call("/test.Echo/Lower") {
+        Err(httpbis::Error::GrpcStatus(status)) => {
+            assert_eq!(grpc::GrpcCode::Unimplemented, status.code);
+            assert_eq!("no method", status.message);
+        }
+        r => panic!("{:?}", r),
+    }
+}
+
+#[test]
+fn tower_service() {
+    init_logger();
+
+    /// Responds with request method, path and body.
+    #[derive(Clone)]
+    struct Echo;
+
+    impl tower_service::Service<http::Request<HttpStreamAfterHeaders>> for Echo {
+        type Response = http::Response<Bytes>;
+        type Error = httpbis::Error;
+        type Future = Pin<Box<dyn Future<Output = httpbis::Result<http::Response<Bytes>>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<httpbis::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<HttpStreamAfterHeaders>) -> Self::Future {
+            Box::pin(async move {
+                let (parts, body) = request.into_parts();
+                let body: Vec<Bytes> = body.filter_data().try_collect().await?;
+                let body = format!("{} {} {}", parts.method, parts.uri, body.concat().len());
+                Ok(http::Response::builder()
+                    .status(201)
+                    .header("x-host", parts.headers["host"].clone())
+                    .body(Bytes::from(body))
+                    .unwrap())
+            })
+        }
+    }
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service("/", Arc::new(TowerHandler::new(Echo)));
+    let server = server.build().expect("server");
+
+    let mut client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let request = http::Request::post("/a?b")
+        .header("host", "localhost")
+        .body(Bytes::from_static(b"hello"))
+        .unwrap();
+    let response = rt
+        .block_on(tower_service::Service::call(&mut client, request))
+        .expect("response");
+    assert_eq!(http::StatusCode::CREATED, response.status());
+    assert_eq!("localhost", response.headers()["x-host"]);
+    let body: Vec<Bytes> = rt
+        .block_on(response.into_body().filter_data().try_collect())
+        .expect("body");
+    assert_eq!(&b"POST http://localhost/a?b 5"[..], &body.concat()[..]);
+}
+
+#[test]
+fn websocket() {
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.conf.enable_connect_protocol = Some(true);
+    server
+        .service
+        .set_service_fn("/chat", |context, req, resp| {
+            let mut ws = WebSocketStream::accept(req, resp, |protocols| {
+                protocols.iter().find(|p| *p == "chat").cloned()
+            })?;
+            context.loop_remote().spawn(async move {
+                // echo until client shuts down
+                let mut buf = [0; 100];
+                loop {
+                    let n = ws.read(&mut buf).await.expect("read");
+                    if n == 0 {
+                        break;
+                    }
+                    ws.write_all(&buf[..n]).await.expect("write");
+                }
+                ws.shutdown().await.expect("shutdown");
+            });
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        ClientConf::new(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(async {
+        let mut ws =
+            WebSocketStream::connect(&client, "/chat", "localhost", &["superchat", "chat"])
+                .await
+                .expect("connect");
+        assert_eq!(Some("chat"), ws.protocol());
+
+        ws.write_all(b"hello").await.expect("write");
+        let mut buf = [0; 5];
+        ws.read_exact(&mut buf).await.expect("read");
+        assert_eq!(b"hello", &buf);
+
+        ws.shutdown().await.expect("shutdown");
+        assert_eq!(0, ws.read(&mut buf).await.expect("read"));
+    });
+
+    let mut get = Headers::new_get("/chat");
+    get.add(":scheme", "http");
+    get.add(":authority", "localhost");
+    let resp = rt
+        .block_on(client.start_request_end_stream(get, None, None).collect())
+        .expect("get");
+    assert_eq!(400, resp.headers.status());
+}
+
+#[test]
+fn websocket_not_enabled() {
+    init_logger();
+
+    let server = ServerTest::new();
+    let client = Client::new_plain(BIND_HOST, server.port, ClientConf::new()).expect("client");
+    let r =
+        Runtime::new()
+            .unwrap()
+            .block_on(WebSocketStream::connect(&client, "/", "localhost", &[]));
+    // client does not send `:protocol` unless server enabled it
+    match r {
+        Err(httpbis::Error::ExtendedConnectNotSupported) => {}
+        r => panic!("{:?}", r.map(|_| ())),
+    }
+
+    // server resets extended CONNECT if it is sent anyway
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+    let mut headers = Headers::from_vec(vec![
+        Header::new(":method", "CONNECT"),
+        Header::new(":protocol", "websocket"),
+    ]);
+    headers.add(":scheme", "http");
+    headers.add(":path", "/");
+    headers.add(":authority", "localhost");
+    tester.send_headers(1, headers, false);
+    tester.recv_rst_frame_check(1, ErrorCode::ProtocolError);
+}
+
+#[test]
+fn send_raw_frame() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |_context, _req, mut resp| {
+            resp.send_found_200_plain_text("hi")?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut tester = HttpConnTester::connect(server.local_addr().port().unwrap());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut rt = Runtime::new().unwrap();
+    let state = rt.block_on(server.dump_state()).expect("dump_state");
+    let (_, conn) = state.single_conn();
+
+    server
+        .send_raw_frame(conn.conn_id, HttpFrame::Ping(PingFrame::with_data(17)))
+        .expect("send_raw_frame");
+    match tester.recv_frame() {
+        HttpFrame::Ping(ping) => {
+            assert!(!ping.is_ack());
+            assert_eq!(17, ping.opaque_data());
+        }
+        frame => panic!("expecting PING, got: {:?}", frame),
+    }
+}
+
+#[test]
+fn admission_control() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.admission_control = Some(Arc::new(DefaultAdmissionControl {
+        max_active_streams: 1,
+        max_write_queue_bytes: 1 << 20,
+        retry_after: Some(Duration::from_secs(3)),
+    }));
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let handled_copy = handled.clone();
+    let server = ServerOneConn::new_fn_conf(0, conf, move |_, req, mut resp| {
+        handled_copy.fetch_add(1, Ordering::SeqCst);
+        resp.send_headers(Headers::ok_200())?;
+        resp.pull_from_stream(req.make_stream())?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    // stream 1 stays open until request body is finished
+    tester.send_headers(1, post_headers("/"), false);
+    assert_eq!(
+        "200",
+        tester.recv_frame_headers_check(1, false).get(":status")
+    );
+
+    tester.send_headers(3, post_headers("/"), true);
+    let headers = tester.recv_frame_headers_check(3, true);
+    assert_eq!("503", headers.get(":status"));
+    assert_eq!("3", headers.get("retry-after"));
+    assert_eq!(1, handled.load(Ordering::SeqCst));
+
+    tester.send_data(1, b"ab", true);
+    assert_eq!(&b"ab"[..], &tester.recv_frame_data_check(1, true)[..]);
+
+    tester.send_headers(5, post_headers("/"), true);
+    assert_eq!(
+        "200",
+        tester.recv_frame_headers_check(5, false).get(":status")
+    );
+    assert_eq!(2, handled.load(Ordering::SeqCst));
+}
+
+#[test]
+fn push() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _req, mut resp| {
+        let mut headers = Headers::new();
+        headers.add("accept", "text/css");
+        let mut pushed = resp.push("/style.css", headers)?;
+        resp.send_found_200_plain_text("index")?;
+        pushed.send_found_200_plain_text("css")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_get("/");
+    headers.add(":scheme", "http");
+    headers.add(":authority", "example.com");
+    tester.send_headers(1, headers, true);
+
+    let push_promise = match tester.recv_frame() {
+        HttpFrame::PushPromise(push_promise) => push_promise,
+        frame => panic!("expecting PUSH_PROMISE, got: {:?}", frame),
+    };
+    assert_eq!(1, push_promise.stream_id);
+    assert_eq!(2, push_promise.promised_stream_id);
+    let promised = tester
+        .decoder
+        .decode(push_promise.header_fragment)
+        .expect("decode");
+    let promised = Headers::from_vec(
+        promised
+            .into_iter()
+            .map(|(n, v)| Header::new(n, v))
+            .collect(),
+    );
+    assert_eq!("GET", promised.method());
+    assert_eq!("/style.css", promised.path());
+    assert_eq!("http", promised.get(":scheme"));
+    assert_eq!("example.com", promised.get(":authority"));
+    assert_eq!("text/css", promised.get("accept"));
+
+    // frames of the two streams may interleave
+    let mut bodies = vec![Vec::new(), Vec::new()];
+    let mut open = 2;
+    while open > 0 {
+        let (stream_id, end_of_stream) = match tester.recv_frame() {
+            HttpFrame::Headers(headers_frame) => {
+                let headers = tester
+                    .decoder
+                    .decode(headers_frame.header_fragment.clone())
+                    .expect("decode");
+                assert!(headers
+                    .iter()
+                    .any(|(n, v)| &n[..] == b":status" && &v[..] == b"200"));
+                (headers_frame.stream_id, headers_frame.is_end_of_stream())
+            }
+            HttpFrame::Data(data_frame) => {
+                let i = data_frame.stream_id as usize / 2;
+                bodies[i].extend_from_slice(&data_frame.data);
+                (data_frame.stream_id, data_frame.is_end_of_stream())
+            }
+            frame => panic!("unexpected frame: {:?}", frame),
+        };
+        assert!(stream_id == 1 || stream_id == 2, "{}", stream_id);
+        if end_of_stream {
+            open -= 1;
+        }
+    }
+    assert_eq!(&b"index"[..], &bodies[0][..]);
+    assert_eq!(&b"css"[..], &bodies[1][..]);
+}
+
+#[test]
+fn connect_tunnel() {
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+
+    init_logger();
+
+    struct Echo;
+
+    impl ServerHandler for Echo {
+        fn start_request(
+            &self,
+            context: ServerHandlerContext,
+            req: ServerRequest,
+            resp: ServerResponse,
+        ) -> httpbis::Result<()> {
+            assert_eq!("example.com:443", req.headers.get(":authority"));
+            let mut tunnel = Tunnel::accept(req, resp)?;
+            context.loop_remote().spawn(async move {
+                // echo until client shuts down
+                let mut buf = [0; 100];
+                loop {
+                    let n = tunnel.read(&mut buf).await.expect("read");
+                    if n == 0 {
+                        break;
+                    }
+                    tunnel.write_all(&buf[..n]).await.expect("write");
+                }
+                tunnel.shutdown().await.expect("shutdown");
+            });
+            Ok(())
+        }
+    }
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_connect_service(Arc::new(Echo));
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        ClientConf::new(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    rt.block_on(async {
+        let mut tunnel = client
+            .start_connect("example.com:443")
+            .await
+            .expect("connect");
+
+        tunnel.write_all(b"hello").await.expect("write");
+        let mut buf = [0; 5];
+        tunnel.read_exact(&mut buf).await.expect("read");
+        assert_eq!(b"hello", &buf);
+
+        tunnel.write_all(b"bye").await.expect("write");
+        tunnel.shutdown().await.expect("shutdown");
+        let mut rem = Vec::new();
+        tunnel.read_to_end(&mut rem).await.expect("read");
+        assert_eq!(b"bye", &rem[..]);
+    });
+}
+
+/// Send responses of given requests while stream windows are zero,
+/// then open windows and return stream ids of received `DATA` frames.
+fn priority_data_order(
+    tester: &mut HttpConnTester,
+    server: &ServerOneConn,
+    requests: &[StreamId],
+    window: u32,
+) -> Vec<StreamId> {
+    // wait for all responses to be queued
+    while server.dump_state().streams.len() != requests.len() {
+        thread::sleep(Duration::from_millis(1));
+    }
+    for _ in requests {
+        tester.recv_frame_headers_decode();
+    }
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(window),
+    ]));
+
+    let mut order = Vec::new();
+    let mut ended = 0;
+    while ended != requests.len() {
+        let data = tester.recv_frame_data();
+        if !data.data.is_empty() {
+            order.push(data.stream_id);
+        }
+        if data.is_end_of_stream() {
+            ended += 1;
+        }
+    }
+    order
+}
+
+#[test]
+fn priority() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, req, mut resp| {
+        resp.send_found_200_plain_text(req.headers.path())?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(0),
+    ]));
+
+    let get = |path: &str, priority: Priority| {
+        let mut headers = Headers::new_get(path);
+        headers.add(":scheme", "http");
+        priority.set_header(&mut headers);
+        headers
+    };
+
+    tester.send_headers(1, get("/default", Priority::default()), true);
+    tester.send_headers(3, get("/u1", Priority::new(1, false)), true);
+    // update before request
+    tester.send_frame(PriorityUpdateFrame::new(
+        5,
+        Priority::new(0, false).to_string(),
+    ));
+    tester.send_headers(5, get("/u0", Priority::new(1, false)), true);
+    tester.send_headers(7, get("/u6", Priority::new(1, false)), true);
+    tester.send_frame(PriorityUpdateFrame::new(
+        7,
+        Priority::new(6, false).to_string(),
+    ));
+
+    assert_eq!(
+        vec![5, 3, 1, 7],
+        priority_data_order(&mut tester, &server, &[1, 3, 5, 7], 100)
+    );
+}
+
+#[test]
+fn priority_incremental() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        resp.send_data_end_of_stream(Bytes::from(vec![1; 20000]))?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(0),
+    ]));
+
+    for &stream_id in &[1, 3] {
+        let mut headers = Headers::new_get("/");
+        headers.add(":scheme", "http");
+        headers.add("priority", "i");
+        tester.send_headers(stream_id, headers, true);
+    }
+
+    // frames are limited by default max frame size
+    assert_eq!(
+        vec![1, 3, 1, 3],
+        priority_data_order(&mut tester, &server, &[1, 3], 20000)
+    );
+}
+
+#[test]
+fn priority_update_of_server_stream() {
+    init_logger();
+
+    let server = ServerTest::new();
+
+    let mut tester = HttpConnTester::connect(server.port);
+    tester.send_preface();
+    tester.settings_xchg();
+
+    tester.send_frame(PriorityUpdateFrame::new(
+        2,
+        Priority::new(0, false).to_string(),
+    ));
+    tester.recv_goaway_frame_check(ErrorCode::ProtocolError);
+}
+
+#[test]
+fn priority_weights() {
+    init_logger();
+
+    let server = ServerOneConn::new_fn(0, |_, _req, mut resp| {
+        resp.send_headers(Headers::ok_200())?;
+        resp.send_data_end_of_stream(Bytes::from(vec![1; 60000]))?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(0),
+        HttpSetting::MaxFrameSize(0x10000),
+    ]));
+
+    // weights 64 and 192
+    tester.send_frame(PriorityFrame::new(1, false, 0, 63));
+    tester.send_frame(PriorityFrame::new(3, false, 0, 191));
+    tester.send_get(1, "/");
+    tester.send_get(3, "/");
+
+    while server.dump_state().streams.len() != 2 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    tester.recv_frame_headers_decode();
+    tester.recv_frame_headers_decode();
+
+    tester.send_recv_settings(SettingsFrame::from_settings(vec![
+        HttpSetting::InitialWindowSize(60000),
+    ]));
+
+    // connection window is divided according to weights
+    let conn_window = DEFAULT_SETTINGS.initial_window_size as usize;
+    let mut received = [0, 0];
+    while received[0] + received[1] != conn_window {
+        let data = tester.recv_frame_data();
+        received[(data.stream_id / 2) as usize] += data.data.len();
+    }
+    assert_eq!([conn_window / 4 + 1, conn_window * 3 / 4], received);
+}
+
+#[test]
+fn max_header_list_size() {
+    init_logger();
+
+    let mut conf = ServerConf::new();
+    conf.common.max_header_list_size = Some(200);
+
+    let server = ServerOneConn::new_fn_conf(0, conf, |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hi")?;
+        Ok(())
+    });
+
+    let mut tester = HttpConnTester::connect(server.port());
+    tester.send_preface();
+    tester.settings_xchg();
+
+    let mut headers = Headers::new_get("/large");
+    headers.add("x-large", "a".repeat(200));
+    tester.send_headers(1, headers, false);
+    let headers = tester.recv_frame_headers_check(1, true);
+    assert_eq!(431, headers.status());
+    tester.recv_rst_frame_check(1, ErrorCode::NoError);
+
+    // following requests are served
+    assert_eq!(200, tester.get(3, "/small").headers.status());
+}
+
+#[test]
+fn serve_connection_over_io() {
+    init_logger();
+
+    struct Hello;
+
+    impl ServerHandler for Hello {
+        fn start_request(
+            &self,
+            _context: ServerHandlerContext,
+            req: ServerRequest,
+            mut resp: ServerResponse,
+        ) -> httpbis::Result<()> {
+            resp.send_found_200_plain_text(&format!("hello {}", req.headers.path()))?;
+            Ok(())
+        }
+    }
+
+    let mut rt = Runtime::new().unwrap();
+
+    let (client_io, server_io) = httpbis::mem::duplex(64 * 1024);
+    let served = rt.spawn(Server::serve_connection(
+        server_io,
+        ServerConf::new(),
+        Hello,
+    ));
+
+    let client = Client::new_from_io(client_io, ClientConf::new()).expect("client");
+    for path in &["/a", "/b"] {
+        let resp = rt
+            .block_on(client.start_get(path, "io").collect())
+            .expect("response");
+        assert_eq!(200, resp.headers.status());
+        assert_eq!(
+            format!("hello {}", path).as_bytes(),
+            &resp.body.get_bytes()[..]
+        );
+    }
+
+    // server connection future completes when client is dropped
+    drop(client);
+    let _ = rt.block_on(served).expect("join");
+}
+
+#[test]
+fn send_file() {
+    init_logger();
+
+    let tempdir = tempdir::TempDir::new("rust_http2_test").unwrap();
+    let file_path = tempdir.path().join("file");
+    // larger than initial window, so sending waits for window updates
+    let content: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&file_path, &content).unwrap();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            let range = match req.headers.path() {
+                "/range" => Some(ByteRange { start: 10, end: 19 }),
+                _ => None,
+            };
+            let file_path = match req.headers.path() {
+                "/missing" => Path::new("/nonexistent/file"),
+                _ => &file_path,
+            };
+            resp.send_headers(Headers::ok_200())?;
+            resp.send_file(file_path, range)
+        });
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&content[..], &resp.body.get_bytes()[..]);
+
+    let resp = rt
+        .block_on(client.start_get("/range", "localhost").collect())
+        .expect("get");
+    assert_eq!(&content[10..20], &resp.body.get_bytes()[..]);
+
+    // file is opened after headers are sent, so the stream is reset
+    match rt.block_on(client.start_get("/missing", "localhost").collect()) {
+        Err(Error::RstStreamReceived(ErrorCode::InternalError)) => {}
+        r => panic!("{:?}", r.map(|m| m.dump())),
+    }
+}
+
+#[test]
+fn static_files() {
+    init_logger();
+
+    let tempdir = tempdir::TempDir::new("rust_http2_test").unwrap();
+    std::fs::write(tempdir.path().join("a.txt"), b"hello world").unwrap();
+    std::fs::write(tempdir.path().join("index.html"), b"<html></html>").unwrap();
+
+    let mut files = httpbis::static_files::StaticFiles::new(tempdir.path());
+    files.set_prefix("/static");
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_service("/static", Arc::new(files));
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+
+    let resp = rt
+        .block_on(
+            client
+                .request(Method::GET, "/static/a.txt")
+                .send()
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(
+        Some("text/plain; charset=utf-8"),
+        resp.headers.content_type()
+    );
+    assert_eq!("11", resp.headers.get("content-length"));
+    assert_eq!(&b"hello world"[..], &resp.body.get_bytes()[..]);
+    let etag = resp.headers.get("etag").to_owned();
+
+    let resp = rt
+        .block_on(
+            client
+                .request(Method::GET, "/static/a.txt")
+                .header("if-none-match", etag)
+                .send()
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(304, resp.headers.status());
+    assert!(resp.body.get_bytes().is_empty());
+
+    let resp = rt
+        .block_on(
+            client
+                .request(Method::GET, "/static/a.txt")
+                .header("range", "bytes=6-")
+                .send()
+                .collect(),
+        )
+        .expect("get");
+    assert_eq!(206, resp.headers.status());
+    assert_eq!("bytes 6-10/11", resp.headers.get("content-range"));
+    assert_eq!(&b"world"[..], &resp.body.get_bytes()[..]);
+
+    let resp = rt
+        .block_on(
+            client
+                .request(Method::HEAD, "/static/a.txt")
+                .send()
+                .collect(),
+        )
+        .expect("head");
+    assert_eq!(200, resp.headers.status());
+    assert!(resp.body.get_bytes().is_empty());
+
+    let resp = rt
+        .block_on(client.request(Method::GET, "/static/").send().collect())
+        .expect("get");
+    assert_eq!(
+        Some("text/html; charset=utf-8"),
+        resp.headers.content_type()
+    );
+    assert_eq!(&b"<html></html>"[..], &resp.body.get_bytes()[..]);
+
+    for path in &["/static/../a.txt", "/static/%2e%2e/a.txt", "/static/b.txt"] {
+        let resp = rt
+            .block_on(client.request(Method::GET, path).send().collect())
+            .expect("get");
+        assert_eq!(404, resp.headers.status(), "{}", path);
+    }
+
+    let resp = rt
+        .block_on(
+            client
+                .request(Method::POST, "/static/a.txt")
+                .send()
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(405, resp.headers.status());
+}
+
+#[test]
+fn router() {
+    init_logger();
+
+    let mut posts = Router::new();
+    posts.add_route_fn(Method::GET, "/{post}", |_context, req, mut resp| {
+        let params: Vec<String> = req
+            .params
+            .iter()
+            .map(|(n, v)| format!("{}={}", n, v))
+            .collect();
+        resp.send_found_200_plain_text(&params.join(","))?;
+        Ok(())
+    });
+
+    let mut users = Router::new();
+    users.add_route_fn(Method::GET, "/{id}", |_context, req, mut resp| {
+        resp.send_found_200_plain_text(&format!("get {}", req.params.get("id").unwrap()))?;
+        Ok(())
+    });
+    users.add_route_fn(Method::DELETE, "/{id}", |_context, req, mut resp| {
+        resp.send_found_200_plain_text(&format!("delete {}", req.params.get("id").unwrap()))?;
+        Ok(())
+    });
+    users.mount("/{id}/posts", posts);
+
+    let mut router = Router::new();
+    router.add_route_fn(Method::GET, "/users/me", |_context, _req, mut resp| {
+        resp.send_found_200_plain_text("me")?;
+        Ok(())
+    });
+    router.mount("/users", users);
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.service.set_service("/", Arc::new(router));
+    let server = server.build().expect("server");
+
+    let client = Client::new_plain(
+        BIND_HOST,
+        server.local_addr().port().unwrap(),
+        Default::default(),
+    )
+    .expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let mut call = |method: Method, path: &str| {
+        let resp = rt
+            .block_on(client.request(method, path).send().collect())
+            .expect("request");
+        (
+            resp.headers.status(),
+            String::from_utf8(resp.body.get_bytes().to_vec()).unwrap(),
+            resp.headers.get_opt("allow").map(|a| a.to_owned()),
+        )
+    };
+
+    assert_eq!((200, "me".to_owned(), None), call(Method::GET, "/users/me"));
+    assert_eq!(
+        (200, "get 42".to_owned(), None),
+        call(Method::GET, "/users/42?x=y")
+    );
+    assert_eq!(
+        (200, "delete a b".to_owned(), None),
+        call(Method::DELETE, "/users/a%20b")
+    );
+    assert_eq!(
+        (200, "id=42,post=7".to_owned(), None),
+        call(Method::GET, "/users/42/posts/7")
+    );
+    let (status, _, allow) = call(Method::PUT, "/users/42");
+    assert_eq!((405, Some("GET, DELETE".to_owned())), (status, allow));
+    assert_eq!(404, call(Method::GET, "/users/42/comments").0);
+    assert_eq!(404, call(Method::GET, "/").0);
+}
+
+#[test]
+fn shutdown_handle() {
+    init_logger();
+
+    let hanging = Arc::new(Mutex::new(Vec::new()));
+    let hanging_copy = hanging.clone();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", move |_context, req, mut resp| {
+            if req.headers.path() == "/hang" {
+                resp.send_headers(Headers::ok_200())?;
+                hanging_copy.lock().unwrap().push(resp);
+            } else if req.headers.path() == "/slow" {
+                resp.send_headers(Headers::ok_200())?;
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(100));
+                    resp.send_data_end_of_stream(Bytes::from_static(b"slow"))
+                        .expect("send");
+                });
+            } else {
+                resp.send_found_200_plain_text("fast")?;
+            }
+            Ok(())
+        });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+    let handle = server.shutdown_handle();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+    let (headers, hang_body) = rt
+        .block_on(client.start_get("/hang", "localhost"))
+        .expect("hang headers");
+    assert_eq!(200, headers.status());
+    let (headers, slow_body) = rt
+        .block_on(client.start_get("/slow", "localhost"))
+        .expect("slow headers");
+    assert_eq!(200, headers.status());
+
+    // stream completed during drain is served,
+    // connection with unfinished stream is closed after deadline
+    let start = Instant::now();
+    rt.block_on(handle.drain(Duration::from_millis(300)))
+        .expect("drain");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    let slow_body: Vec<Bytes> = rt
+        .block_on(slow_body.filter_data().try_collect())
+        .expect("slow body");
+    assert_eq!(vec![Bytes::from_static(b"slow")], slow_body);
+    let hang_body: httpbis::Result<Vec<Bytes>> = rt.block_on(hang_body.filter_data().try_collect());
+    assert!(hang_body.is_err());
+    assert_eq!(1, hanging.lock().unwrap().len());
+
+    // listener is closed, even though server is not dropped
+    assert!(TcpStream::connect((BIND_HOST, port)).is_err());
+
+    // nothing left to drain
+    let start = Instant::now();
+    rt.block_on(handle.drain(Duration::from_secs(10)))
+        .expect("drain");
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    drop(server);
+}
+
+#[test]
+fn peer_and_local_addr() {
+    init_logger();
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server
+        .service
+        .set_service_fn("/", |context, req, mut resp| {
+            assert_eq!(context.peer_addr(), req.peer_addr());
+            assert_eq!(context.local_addr(), req.local_addr());
+            let mut headers = Headers::ok_200();
+            headers.add("x-peer", req.peer_addr().to_string());
+            headers.add("x-local", req.local_addr().to_string());
+            resp.send_headers_end_of_stream(headers)?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let resp = rt
+        .block_on(client.start_get("/", "localhost").collect())
+        .expect("get");
+
+    let peer: std::net::SocketAddr = resp.headers.get("x-peer").parse().unwrap();
+    let local: std::net::SocketAddr = resp.headers.get("x-local").parse().unwrap();
+    // both ends are on loopback
+    assert_eq!(peer.ip(), local.ip());
+    assert_ne!(port, peer.port());
+    assert_eq!(port, local.port());
+}
+
+#[test]
+fn access_log() {
+    init_logger();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = Mutex::new(tx);
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_port(0);
+    server.set_access_log(move |entry: &AccessLogEntry| {
+        tx.lock().unwrap().send(entry.clone()).unwrap();
+    });
+    server
+        .service
+        .set_service_fn("/", |_context, req, mut resp| {
+            resp.send_headers(Headers::ok_200())?;
+            resp.pull_from_stream(req.make_stream())?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let client = Client::new_plain(BIND_HOST, port, Default::default()).expect("client");
+
+    let mut rt = Runtime::new().unwrap();
+    let resp = rt
+        .block_on(
+            client
+                .start_post("/echo?a=b", "localhost", Bytes::from_static(b"hello"))
+                .collect(),
+        )
+        .expect("post");
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+
+    let entry = rx.recv_timeout(Duration::from_secs(5)).expect("entry");
+    assert_eq!("POST", entry.method);
+    assert_eq!("/echo?a=b", entry.path);
+    assert_eq!(Some(200), entry.status);
+    assert_eq!(5, entry.traffic.received.data_bytes);
+    assert_eq!(5, entry.traffic.sent.data_bytes);
+    assert_ne!(0, entry.traffic.received.header_bytes);
+    assert_ne!(0, entry.traffic.sent.header_bytes);
+    assert!(entry.traffic.sent.frames >= 2);
+    assert_ne!(port, entry.peer_addr.port().unwrap());
+}
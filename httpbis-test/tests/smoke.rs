@@ -204,14 +204,5 @@ fn seq_slow() {
 
     drop(tx);
 
-    if false {
-        // TODO
-        assert_eq!(None, rt.block_on(resp.next()).map(|e| format!("{:?}", e)));
-    } else {
-        assert_eq!(
-            Some(Ok(Bytes::new())),
-            rt.block_on(resp.next())
-                .map(|r| r.map_err(|e| format!("{:?}", e)))
-        );
-    }
+    assert_eq!(None, rt.block_on(resp.next()).map(|e| format!("{:?}", e)));
 }
@@ -10,11 +10,24 @@ extern crate tls_api_native_tls;
 extern crate httpbis_test;
 use httpbis_test::*;
 
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use futures::future;
+
 use httpbis::SimpleHttpMessage;
 use httpbis::*;
 
+use httpbis::rustls;
+use httpbis::tls::spki_sha256;
+use httpbis::tls::ClientCertificate;
+use httpbis::tls::ClientCertificateResolver;
+use httpbis::tls::TlsAcceptorLike;
+use httpbis::tls::TlsConnectorLike;
+use httpbis::tls::TlsHandshakeFuture;
+use httpbis::tls::TlsIo;
 use httpbis::AnySocketAddr;
 
 use tls_api::Certificate;
@@ -88,3 +101,706 @@ fn tls() {
     assert_eq!(200, resp.headers.status());
     assert_eq!(&b"hello"[..], resp.body.get_bytes());
 }
+
+#[test]
+fn rustls() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut server = ServerBuilder::new_rustls(&[&server_keys.cert_der], &server_keys.key_der)
+        .expect("new_rustls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(server_keys.cert_der.clone()))
+        .expect("add_root_certificate");
+    connector.set_alpn_protocols(&[b"h2"]).expect("alpn");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(connector.build().unwrap()));
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+}
+
+#[test]
+fn rustls_session_info() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut server = ServerBuilder::new_rustls(&[&server_keys.cert_der], &server_keys.key_der)
+        .expect("new_rustls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server
+        .service
+        .set_service_fn("/", |context, req, mut resp| {
+            let session = req.tls_session().expect("tls_session");
+            assert_eq!(Some(session), context.tls_session());
+            resp.send_found_200_plain_text(&format!(
+                "{} {}",
+                session.sni.as_deref().unwrap_or("-"),
+                session.version.as_deref().unwrap_or("-"),
+            ))?;
+            Ok(())
+        });
+    let server = server.build().expect("server");
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(server_keys.cert_der.clone()))
+        .expect("add_root_certificate");
+    connector.set_alpn_protocols(&[b"h2"]).expect("alpn");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.set_tls_connector("localhost", connector.build().unwrap());
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"localhost TLSv1_3"[..], resp.body.get_bytes());
+
+    let session = rt
+        .block_on(client.tls_session())
+        .unwrap()
+        .expect("tls_session");
+    assert_eq!(Some(&b"h2"[..]), session.alpn_protocol.as_deref());
+    assert_eq!(Some("TLSv1_3"), session.version.as_deref());
+    assert!(session.cipher_suite.unwrap().starts_with("TLS13_"));
+    assert_eq!(Some("localhost"), session.sni.as_deref());
+}
+
+#[test]
+fn rustls_through_http_proxy() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut server = ServerBuilder::new_rustls(&[&server_keys.cert_der], &server_keys.key_der)
+        .expect("new_rustls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let proxy = ConnectProxy::new(None);
+    let authority = format!("{}:{}", BIND_HOST, server.local_addr().port().unwrap());
+
+    // TLS is negotiated with the server, proxy only sees encrypted bytes
+    let mut client = ClientBuilder::new_rustls();
+    client
+        .set_http_proxy((BIND_HOST, proxy.port()), &authority, None)
+        .expect("set_http_proxy");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(rustls_test_connector()));
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+    assert_eq!(vec![authority], proxy.authorities());
+}
+
+#[test]
+fn rustls_sni() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let localhost_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+    let other_keys = httpbis_test::openssl_test_key_gen::keys_for_host("other.localhost");
+
+    let mut server = ServerBuilder::new_rustls_sni(&[
+        (
+            "localhost",
+            &[&localhost_keys.cert_der],
+            &localhost_keys.key_der,
+        ),
+        (
+            "other.localhost",
+            &[&other_keys.cert_der],
+            &other_keys.key_der,
+        ),
+    ])
+    .expect("new_rustls_sni");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    for &(host, cert_der) in &[
+        ("localhost", &localhost_keys.cert_der),
+        ("other.localhost", &other_keys.cert_der),
+    ] {
+        // Client trusts only certificate of the host it connects to
+        let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+        connector
+            .add_root_certificate(Certificate::from_der(cert_der.clone()))
+            .expect("add_root_certificate");
+        connector.set_alpn_protocols(&[b"h2"]).expect("alpn");
+
+        let mut client = ClientBuilder::new_rustls();
+        client.set_addr(socket_addr).expect("set_addr");
+        client.tls = ClientTlsOption::Tls(host.to_owned(), Arc::new(connector.build().unwrap()));
+        let client = client.build().expect("client");
+
+        let resp: SimpleHttpMessage = rt
+            .block_on(client.start_get("/hi", host).collect())
+            .unwrap();
+        assert_eq!(200, resp.headers.status());
+        assert_eq!(&b"hello"[..], resp.body.get_bytes());
+    }
+}
+
+#[test]
+fn native_tls() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut server =
+        ServerBuilder::new_native_tls(&server_keys.pkcs12, &server_keys.pkcs12_password)
+            .expect("new_native_tls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut client = ClientBuilder::new_native_tls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(test_tls_connector()));
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+}
+
+#[test]
+fn multi_client_coalescing() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let keys = httpbis_test::openssl_test_key_gen::keys_for_hosts(&["www.localhost", "localhost"]);
+
+    let mut server =
+        ServerBuilder::new_rustls(&[&keys.cert_der], &keys.key_der).expect("new_rustls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, req, mut resp| {
+        resp.send_found_200_plain_text(req.headers.get(":authority"))?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+    let port = server.local_addr().port().unwrap();
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(keys.cert_der.clone()))
+        .expect("add_root_certificate");
+    connector.set_alpn_protocols(&[b"h2"]).expect("alpn");
+
+    let mut client = MultiClient::<httpbis::tls_api_rustls::TlsConnector>::new(ClientConf::new());
+    client.set_tls_connector(connector.build().unwrap());
+    client.set_resolver(|_host: &str, port: u16| {
+        future::ok(vec![format!("{}:{}", BIND_HOST, port).parse().unwrap()])
+    });
+
+    for host in &["localhost", "www.localhost"] {
+        let authority = format!("{}:{}", host, port);
+        let resp: SimpleHttpMessage = rt
+            .block_on(
+                client
+                    .start_get(&format!("https://{}/hi", authority))
+                    .collect(),
+            )
+            .unwrap();
+        assert_eq!(200, resp.headers.status());
+        assert_eq!(authority.as_bytes(), resp.body.get_bytes());
+    }
+    assert_eq!(1, client.client_count());
+    let state = rt.block_on(server.dump_state()).expect("dump_state");
+    assert_eq!(1, state.conns.len());
+
+    // certificate does not cover the host, so a new connection is made,
+    // which fails verification
+    let r = rt.block_on(
+        client
+            .start_get(&format!("https://other.localhost:{}/hi", port))
+            .collect(),
+    );
+    assert!(r.is_err());
+    assert_eq!(2, client.client_count());
+}
+
+#[test]
+fn connection_timeout_in_tls_handshake() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    // accepts connections but never responds to TLS handshake
+    let listener = std::net::TcpListener::bind((BIND_HOST, 0)).unwrap();
+
+    let mut client = ClientBuilder::new_rustls();
+    client.conf.connection_timeout = Some(std::time::Duration::from_millis(200));
+    client
+        .set_addr(listener.local_addr().unwrap())
+        .expect("set_addr");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(rustls_test_connector()));
+    let client = client.build().expect("client");
+
+    let e = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .err()
+        .expect("error");
+    assert!(format!("{:?}", e).contains("ConnectionTimeout"), "{:?}", e);
+    drop(listener);
+}
+
+fn rustls_test_connector() -> httpbis::tls_api_rustls::TlsConnector {
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(server_keys.cert_der.clone()))
+        .expect("add_root_certificate");
+    connector.set_alpn_protocols(&[b"h2"]).expect("alpn");
+    connector.build().unwrap()
+}
+
+#[test]
+fn client_alpn_require() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    // Platform TLS server does not negotiate ALPN
+    let mut server =
+        ServerBuilder::new_native_tls(&server_keys.pkcs12, &server_keys.pkcs12_password)
+            .expect("new_native_tls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.conf.alpn = Some(ClientAlpn::Require);
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(rustls_test_connector()));
+    let client = client.build().expect("client");
+
+    match rt.block_on(client.start_get("/hi", "localhost").collect()) {
+        Err(e) => assert!(format!("{:?}", e).contains("AlpnMismatch(None)"), "{:?}", e),
+        Ok(r) => panic!("expecting error, got {}", r.headers.status()),
+    }
+}
+
+#[test]
+fn server_alpn_require() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut server = ServerBuilder::new_rustls(&[&server_keys.cert_der], &server_keys.key_der)
+        .expect("new_rustls");
+    server.conf.alpn = Some(ServerAlpn::Require);
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    // Platform TLS client does not offer ALPN
+    let mut client = ClientBuilder::new_native_tls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(test_tls_connector()));
+    let client = client.build().expect("client");
+
+    assert!(rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .is_err());
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(rustls_test_connector()));
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+}
+
+#[test]
+fn rustls_pinning() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+    let other_keys = httpbis_test::openssl_test_key_gen::keys_for_host("other.localhost");
+
+    let mut server = ServerBuilder::new_rustls(&[&server_keys.cert_der], &server_keys.key_der)
+        .expect("new_rustls");
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let client = |pin: &[u8]| {
+        let mut client = ClientBuilder::new_rustls();
+        client.set_addr(socket_addr).expect("set_addr");
+        client.conf.pinned_spki_sha256 = Some(vec![spki_sha256(pin).expect("spki_sha256")]);
+        client.set_tls_connector("localhost", rustls_test_connector());
+        client.build().expect("client")
+    };
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(
+            client(&server_keys.cert_der)
+                .start_get("/hi", "localhost")
+                .collect(),
+        )
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+
+    match rt.block_on(
+        client(&other_keys.cert_der)
+            .start_get("/hi", "localhost")
+            .collect(),
+    ) {
+        Err(e) => assert!(format!("{:?}", e).contains("PinMismatch"), "{:?}", e),
+        Ok(r) => panic!("expecting error, got {}", r.headers.status()),
+    }
+}
+
+#[test]
+fn custom_tls_stack() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    // Delegates to another TLS stack and counts handshakes
+    struct Counting<T> {
+        inner: T,
+        handshakes: Arc<AtomicUsize>,
+    }
+
+    impl<T: TlsConnectorLike> TlsConnectorLike for Counting<T> {
+        fn connect<'a>(
+            &'a self,
+            domain: &'a str,
+            stream: Pin<Box<dyn TlsIo>>,
+        ) -> TlsHandshakeFuture<'a> {
+            self.handshakes.fetch_add(1, Ordering::SeqCst);
+            self.inner.connect(domain, stream)
+        }
+    }
+
+    impl<T: TlsAcceptorLike> TlsAcceptorLike for Counting<T> {
+        fn accept<'a>(&'a self, stream: Pin<Box<dyn TlsIo>>) -> TlsHandshakeFuture<'a> {
+            self.handshakes.fetch_add(1, Ordering::SeqCst);
+            self.inner.accept(stream)
+        }
+    }
+
+    let handshakes = Arc::new(AtomicUsize::new(0));
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server.set_tls_acceptor(Counting {
+        inner: test_tls_acceptor(),
+        handshakes: handshakes.clone(),
+    });
+    server.service.set_service_fn("/", |_, _req, mut resp| {
+        resp.send_found_200_plain_text("hello")?;
+        Ok(())
+    });
+    let server = server.build().expect("server");
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut client = ClientBuilder::new_plain();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.set_tls_connector(
+        "localhost",
+        Counting {
+            inner: test_tls_connector(),
+            handshakes: handshakes.clone(),
+        },
+    );
+    let client = client.build().expect("client");
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+    assert_eq!(2, handshakes.load(Ordering::SeqCst));
+}
+
+#[test]
+fn http1_selected_by_alpn() {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    // plain HTTP/1.1 server over TLS, selects `http/1.1` protocol
+    let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+    config
+        .set_single_cert(
+            vec![rustls::Certificate(server_keys.cert_der.clone())],
+            rustls::PrivateKey(server_keys.key_der.clone()),
+        )
+        .expect("set_single_cert");
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    let config = Arc::new(config);
+
+    let listener = std::net::TcpListener::bind((BIND_HOST, 0)).unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut session = rustls::ServerSession::new(&config);
+        let mut stream = BufReader::new(rustls::Stream::new(&mut session, &mut socket));
+        let mut requests = Vec::new();
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            requests.push(line.trim_end().to_owned());
+            loop {
+                line.clear();
+                stream.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            stream
+                .get_mut()
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+                )
+                .unwrap();
+            stream.get_mut().flush().unwrap();
+        }
+        requests
+    });
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(server_keys.cert_der.clone()))
+        .expect("add_root_certificate");
+    connector
+        .set_alpn_protocols(&[b"h2", b"http/1.1"])
+        .expect("alpn");
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client.set_http1_fallback();
+    client.conf.alpn = Some(ClientAlpn::Require);
+    client.tls = ClientTlsOption::Tls("localhost".to_owned(), Arc::new(connector.build().unwrap()));
+    let client = client.build().expect("client");
+
+    for path in &["/a", "/b"] {
+        let resp: SimpleHttpMessage = rt
+            .block_on(client.start_get(path, "localhost").collect())
+            .unwrap();
+        assert_eq!(200, resp.headers.status());
+        assert_eq!(&b"hello world"[..], resp.body.get_bytes());
+    }
+
+    drop(client);
+    assert_eq!(
+        vec!["GET /a HTTP/1.1", "GET /b HTTP/1.1"],
+        server.join().unwrap()
+    );
+}
+
+/// rustls server requiring client certificate signed by test key.
+fn rustls_client_auth_server() -> Server {
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots
+        .add(&rustls::Certificate(server_keys.cert_der.clone()))
+        .expect("add");
+    let mut config = rustls::ServerConfig::new(rustls::AllowAnyAuthenticatedClient::new(roots));
+    config
+        .set_single_cert(
+            vec![rustls::Certificate(server_keys.cert_der.clone())],
+            rustls::PrivateKey(server_keys.key_der.clone()),
+        )
+        .expect("set_single_cert");
+    config.alpn_protocols = vec![b"h2".to_vec()];
+
+    let mut server = ServerBuilder::new_plain();
+    server.set_rustls_config(config);
+    server.set_addr((BIND_HOST, 0)).expect("set_addr");
+    server
+        .service
+        .set_service_fn("/", move |context, _req, mut resp| {
+            match context.peer_certificates() {
+                Some(peer)
+                    if peer.verified && peer.end_entity() == Some(&server_keys.cert_der[..]) =>
+                {
+                    resp.send_found_200_plain_text("hello")?
+                }
+                _ => resp.send_headers_end_of_stream(Headers::new_status(403))?,
+            }
+            Ok(())
+        });
+    server.build().expect("server")
+}
+
+fn rustls_client_auth_client<R: ClientCertificateResolver>(server: &Server, resolver: R) -> Client {
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let socket_addr = match server.local_addr() {
+        &AnySocketAddr::Inet(ref sock_addr) => sock_addr,
+        _ => panic!("Assumed server was an inet server"),
+    };
+
+    let mut connector = httpbis::tls_api_rustls::TlsConnector::builder().unwrap();
+    connector
+        .add_root_certificate(Certificate::from_der(server_keys.cert_der.clone()))
+        .expect("add_root_certificate");
+
+    let mut client = ClientBuilder::new_rustls();
+    client.set_addr(socket_addr).expect("set_addr");
+    client
+        .set_tls_client_auth("localhost", connector, resolver)
+        .expect("set_tls_client_auth");
+    client.build().expect("client")
+}
+
+#[test]
+fn rustls_client_auth() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server_keys = &httpbis_test::openssl_test_key_gen::keys().server;
+
+    let server = rustls_client_auth_server();
+    let client = rustls_client_auth_client(
+        &server,
+        ClientCertificate {
+            cert_chain: vec![server_keys.cert_der.clone()],
+            key: server_keys.key_der.clone(),
+        },
+    );
+
+    let resp: SimpleHttpMessage = rt
+        .block_on(client.start_get("/hi", "localhost").collect())
+        .unwrap();
+    assert_eq!(200, resp.headers.status());
+    assert_eq!(&b"hello"[..], resp.body.get_bytes());
+}
+
+#[test]
+fn rustls_client_auth_rejected() {
+    init_logger();
+
+    let mut rt = Runtime::new().unwrap();
+
+    let server = rustls_client_auth_server();
+    // Resolver declines to present a certificate
+    let client = rustls_client_auth_client(&server, |_: &[&[u8]]| None);
+
+    match rt.block_on(client.start_get("/hi", "localhost").collect()) {
+        Err(e) => assert!(
+            format!("{:?}", e).contains("ClientCertificateRejected"),
+            "{:?}",
+            e
+        ),
+        Ok(r) => panic!("expecting error, got {}", r.headers.status()),
+    }
+}
@@ -43,6 +43,16 @@ impl BytesDeque {
         }
     }
 
+    /// Check if this deque contains no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of non-empty chunks stored in this deque.
+    pub fn chunk_count(&self) -> usize {
+        self.into_iter().filter(|b| !b.is_empty()).count()
+    }
+
     /// Append [`Bytes`] to this deque.
     pub fn extend(&mut self, bytes: Bytes) {
         if bytes.is_empty() {
@@ -67,7 +77,8 @@ impl BytesDeque {
 
     /// Get deque contents as [`Bytes`] object.
     ///
-    /// This operation is cheap if this deque contains only single [`Bytes`] object,
+    /// This operation is cheap (no copy) if this deque contains only single [`Bytes`] object,
+    /// e. g. when a body was received in a single `DATA` frame,
     /// otherwise it allocates memory and copies data.
     pub fn get_bytes(&self) -> Bytes {
         match &self.0 {
@@ -238,4 +249,31 @@ mod test {
             extend_iter();
         }
     }
+
+    #[test]
+    fn single_chunk_no_copy() {
+        let b = Bytes::from(vec![1, 2, 3]);
+        let mut d = BytesDeque::new();
+        d.extend(Bytes::new());
+        d.extend(b.clone());
+        d.extend(Bytes::new());
+        assert_eq!(1, d.chunk_count());
+        assert_eq!(b.as_ptr(), d.get_bytes().as_ptr());
+        assert_eq!(b.as_ptr(), d.into_bytes().as_ptr());
+    }
+
+    #[test]
+    fn multi_chunk_rope() {
+        let mut d = BytesDeque::new();
+        d.extend(Bytes::from_static(b"ab"));
+        let c = Bytes::from(vec![3, 4, 5]);
+        d.extend(c.clone());
+        assert_eq!(2, d.chunk_count());
+        assert_eq!(5, d.len());
+        assert_eq!(&b"ab\x03\x04\x05"[..], &d.get_bytes()[..]);
+
+        // after the first chunk is consumed, no copy is needed
+        d.advance(2);
+        assert_eq!(c.as_ptr(), d.get_bytes().as_ptr());
+    }
 }
@@ -54,6 +54,12 @@ impl BytesVecDeque {
     }
 
     pub fn get_bytes(&self) -> Bytes {
+        let mut iter = (&self.deque).into_iter();
+        if let (Some(one), None) = (iter.next(), iter.next()) {
+            // Deque became single-chunk after `advance`
+            return one.clone();
+        }
+
         let mut bytes_mut = BytesMut::with_capacity(self.remaining());
         for b in &self.deque {
             bytes_mut.extend_from_slice(b);
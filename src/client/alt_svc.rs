@@ -0,0 +1,381 @@
+//! Switching to alternative services (RFC 7838).
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytes::Bytes;
+
+use crate::client::retry::is_unprocessed_error;
+use crate::error::Error;
+use crate::result;
+use crate::Client;
+use crate::Headers;
+use crate::Response;
+
+/// Alternative service advertised in `alt-svc` response header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AltSvc {
+    /// ALPN protocol id, e. g. `h2`.
+    pub protocol: String,
+    /// `None` if alternative is on the origin host.
+    pub host: Option<String>,
+    pub port: u16,
+    /// Time alternative is considered fresh (`ma` parameter).
+    pub max_age: Duration,
+}
+
+/// Parsed `alt-svc` header value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AltSvcHeader {
+    /// Origin asks to forget all alternatives.
+    Clear,
+    Alternatives(Vec<AltSvc>),
+}
+
+/// Default `ma` parameter value.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+/// Split by `sep` outside of quoted strings.
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn parse_alternative(s: &str) -> Option<AltSvc> {
+    let mut params = split_unquoted(s, ';').into_iter();
+    let alternative = params.next()?;
+    let eq = alternative.find('=')?;
+    let protocol = alternative[..eq].trim();
+    let authority = alternative[eq + 1..].trim();
+    if protocol.is_empty()
+        || authority.len() < 2
+        || !authority.starts_with('"')
+        || !authority.ends_with('"')
+    {
+        return None;
+    }
+    let authority = &authority[1..authority.len() - 1];
+    let colon = authority.rfind(':')?;
+    let host = &authority[..colon];
+    let port = authority[colon + 1..].parse().ok()?;
+
+    let mut max_age = DEFAULT_MAX_AGE;
+    for param in params {
+        if let Some(eq) = param.find('=') {
+            if param[..eq].trim().eq_ignore_ascii_case("ma") {
+                let value = param[eq + 1..].trim().trim_matches('"');
+                max_age = Duration::from_secs(value.parse().ok()?);
+            }
+        }
+    }
+
+    Some(AltSvc {
+        protocol: protocol.to_owned(),
+        host: if host.is_empty() {
+            None
+        } else {
+            Some(host.to_owned())
+        },
+        port,
+        max_age,
+    })
+}
+
+/// Parse `alt-svc` header value, invalid alternatives are skipped.
+pub fn parse_alt_svc(value: &str) -> AltSvcHeader {
+    if value.trim() == "clear" {
+        return AltSvcHeader::Clear;
+    }
+    AltSvcHeader::Alternatives(
+        split_unquoted(value, ',')
+            .into_iter()
+            .filter_map(parse_alternative)
+            .collect(),
+    )
+}
+
+/// Alternative service state change reported to `AltSvcObserver`.
+#[derive(Debug)]
+pub enum AltSvcEvent<'a> {
+    /// Origin advertised alternatives.
+    Advertised(&'a [AltSvc]),
+    /// Origin asked to forget alternatives.
+    Cleared,
+    /// New requests are sent to the alternative.
+    Switched(&'a AltSvc),
+    /// Alternative failed, requests are sent to the origin.
+    Failed(&'a AltSvc, &'a Error),
+}
+
+/// Observer of alternative service switching.
+pub trait AltSvcObserver: Send + Sync + 'static {
+    fn event(&self, event: AltSvcEvent);
+}
+
+impl<F: Fn(AltSvcEvent) + Send + Sync + 'static> AltSvcObserver for F {
+    fn event(&self, event: AltSvcEvent) {
+        self(event)
+    }
+}
+
+/// Creates a client connected to the alternative.
+pub(crate) type AltClientFactory = Box<dyn Fn(&AltSvc) -> result::Result<Client> + Send + Sync>;
+
+/// Failed alternative is not used for this time.
+const BROKEN_DURATION: Duration = Duration::from_secs(300);
+
+struct Alternative {
+    alt: AltSvc,
+    expires: Instant,
+    /// Created lazily when a request is started.
+    client: Option<Arc<Client>>,
+}
+
+#[derive(Default)]
+struct AltSvcState {
+    current: Option<Alternative>,
+    broken: Vec<(AltSvc, Instant)>,
+}
+
+fn same_endpoint(a: &AltSvc, b: &AltSvc) -> bool {
+    a.protocol == b.protocol && a.host == b.host && a.port == b.port
+}
+
+/// Sends requests of a `Client` to an advertised alternative service,
+/// falling back to the origin if the alternative fails.
+pub(crate) struct AltSvcSwitch {
+    /// Protocol id of alternatives which can be used.
+    protocol: &'static str,
+    factory: AltClientFactory,
+    observer: Option<Arc<dyn AltSvcObserver>>,
+    state: Mutex<AltSvcState>,
+}
+
+impl AltSvcSwitch {
+    pub fn new(
+        protocol: &'static str,
+        factory: AltClientFactory,
+        observer: Option<Arc<dyn AltSvcObserver>>,
+    ) -> AltSvcSwitch {
+        AltSvcSwitch {
+            protocol,
+            factory,
+            observer,
+            state: Default::default(),
+        }
+    }
+
+    fn notify(&self, event: AltSvcEvent) {
+        debug!("alt-svc: {:?}", event);
+        if let Some(observer) = &self.observer {
+            observer.event(event);
+        }
+    }
+
+    /// Stop using the alternative, the caller must notify the observer
+    /// after the state lock is released.
+    fn mark_broken(state: &mut AltSvcState, alt: &AltSvc) {
+        state.current = None;
+        state
+            .broken
+            .push((alt.clone(), Instant::now() + BROKEN_DURATION));
+    }
+
+    /// Client connected to current alternative.
+    ///
+    /// The factory and the observer are called without holding the state lock.
+    fn client(&self) -> Option<Arc<Client>> {
+        let alt = {
+            let mut state = self.state.lock().unwrap();
+            let current = state.current.as_ref()?;
+            if current.expires <= Instant::now() {
+                state.current = None;
+                return None;
+            }
+            if let Some(client) = &current.client {
+                return Some(client.clone());
+            }
+            current.alt.clone()
+        };
+
+        let client = (self.factory)(&alt);
+
+        let mut state = self.state.lock().unwrap();
+        let current = match &mut state.current {
+            Some(current) if same_endpoint(&current.alt, &alt) => current,
+            // Alternative changed while the client was created
+            _ => return None,
+        };
+        if let Some(client) = &current.client {
+            // Created concurrently by another request
+            return Some(client.clone());
+        }
+        match client {
+            Ok(client) => {
+                let client = Arc::new(client);
+                current.client = Some(client.clone());
+                drop(state);
+                self.notify(AltSvcEvent::Switched(&alt));
+                Some(client)
+            }
+            Err(e) => {
+                AltSvcSwitch::mark_broken(&mut state, &alt);
+                drop(state);
+                self.notify(AltSvcEvent::Failed(&alt, &e));
+                None
+            }
+        }
+    }
+
+    /// Request to the alternative failed.
+    fn alternative_failed(&self, client: &Arc<Client>, error: &Error) {
+        let mut state = self.state.lock().unwrap();
+        let alt = match &state.current {
+            Some(Alternative {
+                alt,
+                client: Some(c),
+                ..
+            }) if Arc::ptr_eq(c, client) => alt.clone(),
+            _ => return,
+        };
+        AltSvcSwitch::mark_broken(&mut state, &alt);
+        drop(state);
+        self.notify(AltSvcEvent::Failed(&alt, error));
+    }
+
+    /// Process `alt-svc` headers of a response.
+    fn response_headers(&self, headers: &Headers) {
+        for header in headers.iter().filter(|h| h.name() == "alt-svc") {
+            let value = String::from_utf8_lossy(header.value());
+            let alternatives = match parse_alt_svc(&value) {
+                AltSvcHeader::Clear => {
+                    let cleared = self.state.lock().unwrap().current.take().is_some();
+                    if cleared {
+                        self.notify(AltSvcEvent::Cleared);
+                    }
+                    continue;
+                }
+                AltSvcHeader::Alternatives(alternatives) => alternatives,
+            };
+            self.notify(AltSvcEvent::Advertised(&alternatives));
+
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            state.broken.retain(|(_, until)| *until > now);
+            let state = &mut *state;
+            let alt = alternatives.into_iter().find(|alt| {
+                alt.protocol == self.protocol
+                    && !state.broken.iter().any(|(b, _)| same_endpoint(b, alt))
+            });
+            let alt = match alt {
+                Some(alt) => alt,
+                None => continue,
+            };
+            let expires = now + alt.max_age;
+            match &mut state.current {
+                Some(current) if same_endpoint(&current.alt, &alt) => {
+                    current.expires = expires;
+                }
+                current => {
+                    *current = Some(Alternative {
+                        alt,
+                        expires,
+                        client: None,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Start request on current alternative if any, otherwise on the origin.
+    pub fn start_request_end_stream(
+        self: &Arc<Self>,
+        origin: &Client,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        let switch = self.clone();
+        let alt_client = match self.client() {
+            Some(alt_client) => alt_client,
+            None => {
+                let response = origin.start_request_end_stream_direct(headers, body, trailers);
+                return response.map_ok(move |(headers, stream)| {
+                    switch.response_headers(&headers);
+                    (headers, stream)
+                });
+            }
+        };
+
+        let starter = origin.request_starter();
+        Response::new_with_informational(move |informational| async move {
+            let response = alt_client
+                .start_request_end_stream_direct(headers.clone(), body.clone(), trailers.clone())
+                .forward_informational(informational.clone())
+                .await;
+            let (headers, stream) = match response {
+                Ok(r) => r,
+                Err(e) if is_unprocessed_error(&e) => {
+                    switch.alternative_failed(&alt_client, &e);
+                    let (_sender, response) =
+                        starter.start_request(headers, body, trailers, true).await?;
+                    response.forward_informational(informational).await?
+                }
+                Err(e) => return Err(e),
+            };
+            switch.response_headers(&headers);
+            Ok((headers, stream))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(AltSvcHeader::Clear, parse_alt_svc(" clear"));
+        assert_eq!(
+            AltSvcHeader::Alternatives(vec![
+                AltSvc {
+                    protocol: "h2".to_owned(),
+                    host: Some("alt.example.com".to_owned()),
+                    port: 8443,
+                    max_age: Duration::from_secs(3600),
+                },
+                AltSvc {
+                    protocol: "h3".to_owned(),
+                    host: None,
+                    port: 443,
+                    max_age: DEFAULT_MAX_AGE,
+                },
+            ]),
+            parse_alt_svc(
+                r#"h2="alt.example.com:8443"; ma=3600; persist=1, h3=":443", bad, h2=":x""#
+            )
+        );
+        assert_eq!(
+            AltSvcHeader::Alternatives(vec![AltSvc {
+                protocol: "h2".to_owned(),
+                host: Some("a,b".to_owned()),
+                port: 1,
+                max_age: DEFAULT_MAX_AGE,
+            }]),
+            parse_alt_svc(r#"h2="a,b:1""#)
+        );
+    }
+}
@@ -0,0 +1,606 @@
+//! Client HTTP cache (RFC 9111 subset).
+//!
+//! Cache is private (not shared): `private` responses are stored,
+//! `s-maxage` is ignored. Heuristic freshness is not implemented,
+//! responses without explicit freshness are stored only if they have
+//! a validator and are revalidated on each use.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use bytes::Bytes;
+use futures::stream::TryStreamExt;
+
+use crate::bytes_ext::bytes_deque::BytesDeque;
+use crate::date;
+use crate::Client;
+use crate::DataOrTrailers;
+use crate::Headers;
+use crate::Response;
+use crate::SimpleHttpMessage;
+
+/// Response stored in a cache.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub headers: Headers,
+    pub body: Bytes,
+    /// Request header values selected by response `vary` header.
+    pub vary: Vec<(String, Option<String>)>,
+    /// When the response was received.
+    pub response_time: Instant,
+    /// Response age when it was received.
+    pub initial_age: Duration,
+    pub freshness_lifetime: Duration,
+}
+
+impl CachedResponse {
+    fn age(&self, now: Instant) -> Duration {
+        self.initial_age + now.saturating_duration_since(self.response_time)
+    }
+
+    fn is_fresh(&self, now: Instant) -> bool {
+        self.age(now) < self.freshness_lifetime
+    }
+
+    fn matches_vary(&self, req: &Headers) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| req.get_opt(name) == value.as_ref().map(|v| &v[..]))
+    }
+
+    fn validator(&self) -> Option<(&'static str, &str)> {
+        if let Some(etag) = self.headers.get_opt("etag") {
+            Some(("if-none-match", etag))
+        } else if let Some(last_modified) = self.headers.get_opt("last-modified") {
+            Some(("if-modified-since", last_modified))
+        } else {
+            None
+        }
+    }
+
+    /// Response to return to the caller.
+    fn to_response(&self, head: bool) -> Response {
+        let mut headers = self.headers.clone();
+        headers.remove("age");
+        headers.add("age", self.age(Instant::now()).as_secs().to_string());
+        let body = if head {
+            Bytes::new()
+        } else {
+            self.body.clone()
+        };
+        Response::headers_and_bytes(headers, body)
+    }
+}
+
+/// Storage backend of `HttpCache`.
+pub trait CacheStorage: Send + Sync + 'static {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    fn put(&self, key: String, response: CachedResponse);
+    fn remove(&self, key: &str);
+}
+
+/// In-memory storage evicting least recently used entries.
+pub struct MemoryCacheStorage {
+    capacity: usize,
+    entries: Mutex<MemoryCacheEntries>,
+}
+
+#[derive(Default)]
+struct MemoryCacheEntries {
+    map: HashMap<String, CachedResponse>,
+    lru: VecDeque<String>,
+}
+
+impl MemoryCacheEntries {
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.lru.iter().position(|k| k == key) {
+            let key = self.lru.remove(pos).unwrap();
+            self.lru.push_back(key);
+        }
+    }
+}
+
+impl MemoryCacheStorage {
+    /// Storage holding at most `capacity` responses.
+    pub fn new(capacity: usize) -> MemoryCacheStorage {
+        MemoryCacheStorage {
+            capacity,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStorage for MemoryCacheStorage {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let response = entries.map.get(key).cloned();
+        if response.is_some() {
+            entries.touch(key);
+        }
+        response
+    }
+
+    fn put(&self, key: String, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.map.insert(key.clone(), response).is_some() {
+            entries.touch(&key);
+            return;
+        }
+        if entries.lru.len() == self.capacity {
+            let remove = entries.lru.pop_front().unwrap();
+            entries.map.remove(&remove);
+        }
+        entries.lru.push_back(key);
+    }
+
+    fn remove(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.map.remove(key).is_some() {
+            entries.lru.retain(|k| k != key);
+        }
+    }
+}
+
+/// `cache-control` directives used by the cache.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    public: bool,
+    max_age: Option<u64>,
+}
+
+impl CacheControl {
+    fn parse(headers: &Headers) -> CacheControl {
+        let mut cc = CacheControl::default();
+        for header in headers.iter().filter(|h| h.name() == "cache-control") {
+            let value = String::from_utf8_lossy(header.value());
+            for directive in value.split(',') {
+                let (name, arg) = match directive.find('=') {
+                    Some(eq) => (directive[..eq].trim(), Some(directive[eq + 1..].trim())),
+                    None => (directive.trim(), None),
+                };
+                match &name.to_ascii_lowercase()[..] {
+                    "no-store" => cc.no_store = true,
+                    "no-cache" => cc.no_cache = true,
+                    "public" => cc.public = true,
+                    "max-age" => {
+                        cc.max_age = arg.and_then(|a| a.trim_matches('"').parse().ok());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        cc
+    }
+}
+
+/// Statuses cacheable by default (RFC 9110 section 15.1).
+fn is_cacheable_by_default(status: u32) -> bool {
+    matches!(
+        status,
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// Parse HTTP-date, dates before Unix epoch are invalid.
+fn parse_http_date(s: &str) -> Option<SystemTime> {
+    let unix_secs = u64::try_from(date::parse_http_date(s)?).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(unix_secs))
+}
+
+fn header_date(headers: &Headers, name: &str) -> Option<SystemTime> {
+    parse_http_date(headers.get_opt(name)?)
+}
+
+/// Response freshness lifetime (RFC 9111 section 4.2.1).
+///
+/// Invalid `expires` means the response is already expired.
+fn freshness_lifetime(headers: &Headers, cc: &CacheControl) -> Option<Duration> {
+    if let Some(max_age) = cc.max_age {
+        return Some(Duration::from_secs(max_age));
+    }
+    let expires = headers.get_opt("expires")?;
+    let expires = match parse_http_date(expires) {
+        Some(expires) => expires,
+        None => return Some(Duration::from_secs(0)),
+    };
+    let date = header_date(headers, "date").unwrap_or_else(SystemTime::now);
+    Some(expires.duration_since(date).unwrap_or_default())
+}
+
+fn is_safe_method(method: &str) -> bool {
+    matches!(method, "GET" | "HEAD" | "OPTIONS" | "TRACE")
+}
+
+/// HTTP cache for a `Client`.
+///
+/// Caches responses to `GET` and `HEAD` requests started with
+/// `Client::start_request_end_stream` and helpers built on it.
+/// Responses are keyed by method and URI, `vary` request headers
+/// are compared on lookup. Stale responses with `etag` or
+/// `last-modified` are revalidated with a conditional request.
+/// Successful requests with unsafe methods invalidate cached responses
+/// for the same URI.
+///
+/// Response bodies larger than `max_entry_size` are not cached,
+/// they are streamed to the caller as is.
+pub struct HttpCache {
+    storage: Arc<dyn CacheStorage>,
+    max_entry_size: usize,
+}
+
+const DEFAULT_MAX_ENTRY_SIZE: usize = 1 << 20;
+
+impl HttpCache {
+    /// Cache with given storage.
+    pub fn new<S: CacheStorage>(storage: S) -> HttpCache {
+        HttpCache {
+            storage: Arc::new(storage),
+            max_entry_size: DEFAULT_MAX_ENTRY_SIZE,
+        }
+    }
+
+    /// Do not cache responses with body larger than `max_entry_size` bytes.
+    ///
+    /// Default is 1 MiB.
+    pub fn set_max_entry_size(&mut self, max_entry_size: usize) {
+        self.max_entry_size = max_entry_size;
+    }
+
+    /// Cache with in-memory storage of given capacity.
+    pub fn new_memory(capacity: usize) -> HttpCache {
+        HttpCache::new(MemoryCacheStorage::new(capacity))
+    }
+
+    fn key(method: &str, req: &Headers) -> Option<String> {
+        Some(format!(
+            "{} {}://{}{}",
+            method,
+            req.get_opt(":scheme")?,
+            req.host_or_authority()?,
+            req.get_opt(":path")?
+        ))
+    }
+
+    /// Lookup a stored response for a request.
+    pub fn lookup(&self, req: &Headers) -> Option<CachedResponse> {
+        let key = HttpCache::key(req.method(), req)?;
+        self.storage.get(&key).filter(|r| r.matches_vary(req))
+    }
+
+    /// Remove stored responses for the URI of a request.
+    pub fn invalidate(&self, req: &Headers) {
+        for method in &["GET", "HEAD"] {
+            if let Some(key) = HttpCache::key(method, req) {
+                self.storage.remove(&key);
+            }
+        }
+    }
+
+    /// Store a response if it is storable (RFC 9111 section 3).
+    pub fn store(&self, req: &Headers, resp: &Headers, body: Bytes) {
+        let key = match HttpCache::key(req.method(), req) {
+            Some(key) => key,
+            None => return,
+        };
+        match HttpCache::cached_response(req, resp, body) {
+            Some(response) => self.storage.put(key, response),
+            None => self.storage.remove(&key),
+        }
+    }
+
+    fn cached_response(req: &Headers, resp: &Headers, body: Bytes) -> Option<CachedResponse> {
+        let method = req.method();
+        if method != "GET" && method != "HEAD" {
+            return None;
+        }
+        let status = resp.status_opt()?;
+        if status < 200 {
+            return None;
+        }
+        let cc = CacheControl::parse(resp);
+        if cc.no_store || CacheControl::parse(req).no_store {
+            return None;
+        }
+        if req.get_opt("authorization").is_some() && !cc.public {
+            return None;
+        }
+
+        let mut vary = Vec::new();
+        for header in resp.iter().filter(|h| h.name() == "vary") {
+            for name in String::from_utf8_lossy(header.value()).split(',') {
+                let name = name.trim().to_ascii_lowercase();
+                if name == "*" {
+                    return None;
+                }
+                let value = req.get_opt(&name).map(|v| v.to_owned());
+                vary.push((name, value));
+            }
+        }
+
+        let freshness_lifetime = freshness_lifetime(resp, &cc);
+        let has_validator =
+            resp.get_opt("etag").is_some() || resp.get_opt("last-modified").is_some();
+        if freshness_lifetime.is_none() && !(has_validator && is_cacheable_by_default(status)) {
+            return None;
+        }
+        let freshness_lifetime = if cc.no_cache {
+            Duration::from_secs(0)
+        } else {
+            freshness_lifetime.unwrap_or_default()
+        };
+        if freshness_lifetime == Duration::from_secs(0) && !has_validator {
+            return None;
+        }
+
+        let mut headers = resp.clone();
+        headers.remove("age");
+        Some(CachedResponse {
+            headers,
+            body,
+            vary,
+            response_time: Instant::now(),
+            initial_age: Duration::from_secs(resp.get_opt_parse("age").unwrap_or(0)),
+            freshness_lifetime,
+        })
+    }
+
+    /// Update stored response with headers of `304` response.
+    fn freshen(
+        &self,
+        req: &Headers,
+        mut stored: CachedResponse,
+        not_modified: &Headers,
+    ) -> CachedResponse {
+        for header in not_modified.iter() {
+            if header.is_preudo_header() || header.name() == "content-length" {
+                continue;
+            }
+            stored.headers.remove(header.name());
+        }
+        for header in not_modified.iter() {
+            if header.is_preudo_header() || header.name() == "content-length" {
+                continue;
+            }
+            stored.headers.add_header(header.clone());
+        }
+        match HttpCache::cached_response(req, &stored.headers, stored.body.clone()) {
+            Some(updated) => {
+                if let Some(key) = HttpCache::key(req.method(), req) {
+                    self.storage.put(key, updated.clone());
+                }
+                updated
+            }
+            None => {
+                self.invalidate(req);
+                stored
+            }
+        }
+    }
+
+    /// Execute request through the cache.
+    pub(crate) fn start_request_end_stream(
+        self: &Arc<Self>,
+        client: &Client,
+        mut headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        let method = headers.method().to_owned();
+        if !is_safe_method(&method) {
+            let cache = self.clone();
+            let req = headers.clone();
+            let resp = client.start_request_end_stream_uncached(headers, body, trailers);
+            return resp.map_ok(move |(headers, stream)| {
+                if matches!(headers.status_opt(), Some(s) if s < 400) {
+                    cache.invalidate(&req);
+                }
+                (headers, stream)
+            });
+        }
+        if (method != "GET" && method != "HEAD") || CacheControl::parse(&headers).no_store {
+            return client.start_request_end_stream_uncached(headers, body, trailers);
+        }
+
+        let req_cc = CacheControl::parse(&headers);
+        let stored = self.lookup(&headers);
+        let mut revalidate = None;
+        if let Some(stored) = stored {
+            let reuse = !req_cc.no_cache && req_cc.max_age != Some(0);
+            if reuse && stored.is_fresh(Instant::now()) {
+                return stored.to_response(method == "HEAD");
+            }
+            if let Some((name, value)) = stored.validator() {
+                if headers.get_opt(name).is_none() {
+                    headers.add(name, value.to_owned());
+                    revalidate = Some((name, stored));
+                }
+            }
+        }
+
+        let cache = self.clone();
+        let mut req = headers.clone();
+        let resp = client.start_request_end_stream_uncached(headers, body, trailers);
+        Response::new_with_informational(move |informational| async move {
+            let (headers, stream) = resp.forward_informational(informational).await?;
+            if let Some((validator, stored)) = revalidate {
+                req.remove(validator);
+                if headers.status_opt() == Some(304) {
+                    let stored = cache.freshen(&req, stored, &headers);
+                    return stored.to_response(req.method() == "HEAD").0.await;
+                }
+            }
+            if HttpCache::cached_response(&req, &headers, Bytes::new()).is_none() {
+                cache.invalidate_key(&req);
+                return Ok((headers, stream));
+            }
+            if headers.content_length().unwrap_or(0) > cache.max_entry_size as u64 {
+                cache.invalidate_key(&req);
+                return Ok((headers, stream));
+            }
+
+            let mut stream = stream;
+            let mut parts = Vec::new();
+            let mut size = 0usize;
+            while let Some(part) = stream.try_next().await? {
+                if let DataOrTrailers::Data(ref data, _) = part {
+                    size = size.saturating_add(data.len());
+                }
+                parts.push(part);
+                if size > cache.max_entry_size {
+                    cache.invalidate_key(&req);
+                    return Ok((headers, stream.prepend(parts)));
+                }
+            }
+
+            let mut message = SimpleHttpMessage {
+                headers,
+                body: BytesDeque::new(),
+            };
+            for part in parts {
+                message.add(part.into_part().content);
+            }
+            let body = message.body.into_bytes();
+            cache.store(&req, &message.headers, body.clone());
+            Response::headers_and_bytes(message.headers, body).0.await
+        })
+    }
+
+    fn invalidate_key(&self, req: &Headers) {
+        if let Some(key) = HttpCache::key(req.method(), req) {
+            self.storage.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(path: &str) -> Headers {
+        let mut headers = Headers::new_get(path.to_owned());
+        headers.add(":scheme", "https");
+        headers.add(":authority", "example.com");
+        headers
+    }
+
+    fn response(cache_control: &str) -> Headers {
+        let mut headers = Headers::ok_200();
+        headers.add("cache-control", cache_control.to_owned());
+        headers
+    }
+
+    #[test]
+    fn http_date() {
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(951782400)),
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT")
+        );
+        assert_eq!(
+            Some(UNIX_EPOCH + Duration::from_secs(784111777)),
+            parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT")
+        );
+        assert_eq!(None, parse_http_date("0"));
+        assert_eq!(None, parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT"));
+    }
+
+    #[test]
+    fn cache_control() {
+        let cc = CacheControl::parse(&response("public, Max-Age=\"30\", no-cache"));
+        assert_eq!(
+            CacheControl {
+                no_store: false,
+                no_cache: true,
+                public: true,
+                max_age: Some(30),
+            },
+            cc
+        );
+    }
+
+    #[test]
+    fn storable() {
+        let req = request("/");
+        let storable = |resp: &Headers| HttpCache::cached_response(&req, resp, Bytes::new());
+
+        let stored = storable(&response("max-age=10")).unwrap();
+        assert_eq!(Duration::from_secs(10), stored.freshness_lifetime);
+        assert!(stored.is_fresh(Instant::now()));
+
+        assert!(storable(&response("no-store, max-age=10")).is_none());
+        assert!(storable(&response("no-cache")).is_none());
+
+        let mut resp = response("no-cache");
+        resp.add("etag", "\"x\"");
+        let stored = storable(&resp).unwrap();
+        assert!(!stored.is_fresh(Instant::now()));
+        assert_eq!(Some(("if-none-match", "\"x\"")), stored.validator());
+
+        let mut resp = Headers::ok_200();
+        resp.add("date", "Sun, 06 Nov 1994 08:49:37 GMT");
+        resp.add("expires", "Sun, 06 Nov 1994 08:50:37 GMT");
+        resp.add("age", "20");
+        let stored = storable(&resp).unwrap();
+        assert_eq!(Duration::from_secs(60), stored.freshness_lifetime);
+        assert_eq!(Duration::from_secs(20), stored.initial_age);
+
+        let mut resp = response("max-age=10");
+        resp.add("vary", "*");
+        assert!(storable(&resp).is_none());
+    }
+
+    #[test]
+    fn vary() {
+        let cache = HttpCache::new_memory(10);
+        let mut req = request("/");
+        req.add("accept-language", "en");
+        let mut resp = response("max-age=10");
+        resp.add("vary", "Accept-Language");
+        cache.store(&req, &resp, Bytes::from_static(b"en"));
+
+        assert!(cache.lookup(&req).is_some());
+        assert!(cache.lookup(&request("/")).is_none());
+        cache.invalidate(&req);
+        assert!(cache.lookup(&req).is_none());
+    }
+
+    #[test]
+    fn memory_storage_lru() {
+        let storage = MemoryCacheStorage::new(2);
+        let entry =
+            HttpCache::cached_response(&request("/"), &response("max-age=10"), Bytes::new())
+                .unwrap();
+        storage.put("a".to_owned(), entry.clone());
+        storage.put("b".to_owned(), entry.clone());
+        assert!(storage.get("a").is_some());
+        storage.put("c".to_owned(), entry);
+        assert_eq!(2, storage.len());
+        assert!(storage.get("a").is_some());
+        assert!(storage.get("b").is_none());
+        storage.remove("a");
+        assert_eq!(1, storage.len());
+    }
+}
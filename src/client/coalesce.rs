@@ -0,0 +1,88 @@
+//! Connection reuse for different authorities (RFC 9113 section 9.1.1).
+
+use std::net::IpAddr;
+
+/// Properties of a TLS connection checked before the connection
+/// is reused for requests to a different authority.
+///
+/// DNS names from certificate subject alternative names are provided
+/// by the caller, see `tls::certificate_dns_names`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnCoverage {
+    /// Address of the server the connection is established to.
+    pub peer_ip: IpAddr,
+    /// DNS names (possibly wildcards) the server certificate is valid for.
+    pub dns_names: Vec<String>,
+}
+
+impl ConnCoverage {
+    pub fn new(peer_ip: IpAddr, dns_names: Vec<String>) -> ConnCoverage {
+        ConnCoverage { peer_ip, dns_names }
+    }
+
+    /// Server certificate is valid for given host.
+    pub fn certificate_covers(&self, host: &str) -> bool {
+        self.dns_names.iter().any(|n| dns_name_matches(n, host))
+    }
+
+    /// Connection can be used for requests to `host`
+    /// which resolves to `host_addrs`.
+    pub fn can_coalesce(&self, host: &str, host_addrs: &[IpAddr]) -> bool {
+        host_addrs.contains(&self.peer_ip) && self.certificate_covers(host)
+    }
+}
+
+/// Certificate DNS name matches host (RFC 6125 section 6.4).
+///
+/// Wildcard is allowed only as the whole leftmost label
+/// and matches exactly one label.
+fn dns_name_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.');
+    let host = host.trim_end_matches('.');
+    if host.is_empty() || host.parse::<IpAddr>().is_ok() {
+        return false;
+    }
+    if pattern.starts_with("*.") {
+        let suffix = &pattern[1..];
+        // `*.com` is too broad
+        if !suffix[1..].contains('.') {
+            return false;
+        }
+        match host.find('.') {
+            Some(dot) => dot > 0 && host[dot..].eq_ignore_ascii_case(suffix),
+            None => false,
+        }
+    } else {
+        !pattern.contains('*') && pattern.eq_ignore_ascii_case(host)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matching() {
+        assert!(dns_name_matches("example.com", "Example.COM"));
+        assert!(dns_name_matches("example.com.", "example.com"));
+        assert!(dns_name_matches("*.example.com", "www.example.com"));
+        assert!(!dns_name_matches("*.example.com", "example.com"));
+        assert!(!dns_name_matches("*.example.com", "a.b.example.com"));
+        assert!(!dns_name_matches("*.com", "example.com"));
+        assert!(!dns_name_matches("w*.example.com", "www.example.com"));
+        assert!(!dns_name_matches("127.0.0.1", "127.0.0.1"));
+    }
+
+    #[test]
+    fn coalesce() {
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+        let coverage = ConnCoverage::new(
+            ip,
+            vec!["example.com".to_owned(), "*.example.com".to_owned()],
+        );
+        assert!(coverage.can_coalesce("api.example.com", &[other, ip]));
+        assert!(!coverage.can_coalesce("api.example.com", &[other]));
+        assert!(!coverage.can_coalesce("example.org", &[ip]));
+    }
+}
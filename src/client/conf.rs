@@ -1,6 +1,25 @@
+use crate::client::http1::ALPN_HTTP_1_1;
+use crate::client::push::PushHandler;
+use crate::client::retry::RetryPolicy;
 use crate::common::conf::CommonConf;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::SettingsFrame;
+use crate::Headers;
+use std::sync::Arc;
 use std::time::Duration;
 
+/// Client reaction to protocol negotiated with TLS ALPN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAlpn {
+    /// Speak HTTP/2 if ALPN is not negotiated or selects unknown protocol.
+    Ignore,
+    /// Fail connection with `Error::AlpnMismatch` unless ALPN selects
+    /// `h2` (or `http/1.1` with `http1_fallback`).
+    ///
+    /// Platform TLS does not negotiate ALPN, so it cannot be used with it.
+    Require,
+}
+
 /// Client configuration.
 #[derive(Default, Debug, Clone)]
 pub struct ClientConf {
@@ -8,8 +27,105 @@ pub struct ClientConf {
     pub no_delay: Option<bool>,
     /// Thread name.
     pub thread_name: Option<String>,
-    /// Connection timeout.
+    /// Connection fails with `Error::ConnectionTimeout` unless connection
+    /// is established within this time, including TCP connect, TLS handshake
+    /// and receiving server `SETTINGS`. Not limited by default.
     pub connection_timeout: Option<Duration>,
+    /// Delay before connection attempt to the next address is started
+    /// while previous attempts are in progress, when server name resolves
+    /// to several addresses (Happy Eyeballs, RFC 8305). Default is 250 ms.
+    pub happy_eyeballs_delay: Option<Duration>,
+    /// Send `PING` when nothing is received from server during this interval.
+    pub keepalive_interval: Option<Duration>,
+    /// Close connection with `Error::KeepaliveTimeout` if keepalive `PING`
+    /// is not acknowledged within this time, default is 20 seconds.
+    pub keepalive_timeout: Option<Duration>,
+    /// Gracefully close connection with `GOAWAY` when it has no open streams
+    /// and nothing is received from server during this time. Not limited by default.
+    ///
+    /// Client opens a new connection for the next request.
+    pub idle_timeout: Option<Duration>,
+    /// Headers added to each request unless request has a header with the same name.
+    ///
+    /// Request header overrides all default values with that name,
+    /// except `cookie`: default cookies are sent together with request cookies.
+    pub default_headers: Option<Headers>,
+    /// Send requests to alternative services advertised by the server
+    /// in `alt-svc` response headers, falling back to the server
+    /// if the alternative fails. Default is `false`.
+    ///
+    /// Only requests started with `Client::start_request_end_stream`
+    /// (and helpers built on it) are switched.
+    pub alt_svc: Option<bool>,
+    /// Receives responses pushed by the server.
+    ///
+    /// Server push is enabled by default only if specified.
+    pub push_handler: Option<Arc<dyn PushHandler>>,
+    /// Advertise `SETTINGS_ENABLE_PUSH`, see `set_enable_push`.
+    pub enable_push: Option<bool>,
+    /// Start plain connections with HTTP/1.1 `Upgrade: h2c` request
+    /// instead of HTTP/2 preface, for servers which do not support
+    /// HTTP/2 with prior knowledge. Default is `false`.
+    pub h2c_upgrade: Option<bool>,
+    /// Offer `http/1.1` in TLS ALPN after `h2` and send requests
+    /// with HTTP/1.1 if server selects it. Default is `false`.
+    ///
+    /// HTTP/1.1 requests are sent one at a time per connection,
+    /// request body must be passed when request is started
+    /// and request trailers are not sent.
+    pub http1_fallback: Option<bool>,
+    /// Protocols offered in TLS ALPN, most preferred first.
+    ///
+    /// Default is `h2`, followed by `http/1.1` if `http1_fallback` is enabled.
+    /// Applied when TLS connector is created by `ClientBuilder`.
+    pub alpn_protocols: Option<Vec<Vec<u8>>>,
+    /// Whether connection fails if ALPN selects neither `h2`
+    /// nor `http/1.1` allowed by `http1_fallback`, default is `ClientAlpn::Ignore`.
+    pub alpn: Option<ClientAlpn>,
+    /// SHA-256 hashes of `SubjectPublicKeyInfo` of trusted keys
+    /// (see `tls::spki_sha256`), not pinned by default.
+    ///
+    /// After TLS handshake connection fails with `Error::PinMismatch`
+    /// unless server certificate chain contains a pinned key.
+    /// TLS stack must report server certificates (`TlsHandshake::peer_certificates`),
+    /// as rustls connectors do.
+    #[cfg(feature = "tls-rustls")]
+    pub pinned_spki_sha256: Option<Vec<[u8; 32]>>,
+    /// Max number of requests waiting for a stream slot when server
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS` is reached, not limited by default.
+    ///
+    /// Requests exceeding this limit fail with `Error::RequestQueueFull`.
+    pub max_queued_requests: Option<usize>,
+    /// Max number of connections to the server, default is 1.
+    ///
+    /// New requests are sent to the connection with fewest open streams,
+    /// another connection is opened while all connections have open streams.
+    /// Connections which received `GOAWAY` are not used for new requests.
+    pub max_connections: Option<usize>,
+    /// Request body with `expect: 100-continue` header is held back
+    /// until `100` or final response is received, or until this timeout
+    /// expires after the request is started. Default is 1 second.
+    pub expect_continue_timeout: Option<Duration>,
+    /// Pass interim `1xx` responses (e.g. `103 Early Hints`) to response
+    /// handler instead of dropping them, default is `false`.
+    ///
+    /// See `Response::into_informational_stream`.
+    pub informational_responses: Option<bool>,
+    /// Retry requests which failed before response headers are received,
+    /// e. g. `DefaultRetryPolicy`, requests are not retried by default.
+    ///
+    /// Requests reset with `REFUSED_STREAM` or above `GOAWAY` last stream id
+    /// are known not to be processed by the server, so they are safe to retry;
+    /// connection which received `GOAWAY` is not used for retries.
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Follow `301`, `302`, `303`, `307` and `308` responses
+    /// up to this number of redirects, redirects are not followed by default.
+    ///
+    /// `Client` follows redirects only to its origin, `MultiClient` follows
+    /// redirects to any origin, without `authorization`, `proxy-authorization`
+    /// and `cookie` request headers. Only requests started with
+    /// `start_request_end_stream` (and helpers built on it) are redirected.
+    pub max_redirects: Option<u32>,
 
     /// Common client/server conf.
     pub common: CommonConf,
@@ -20,4 +136,83 @@ impl ClientConf {
     pub fn new() -> ClientConf {
         Default::default()
     }
+
+    /// Allow server push, default is `true` if `push_handler` is specified.
+    ///
+    /// When disabled, `PUSH_PROMISE` from server is a connection error
+    /// (RFC 7540 section 8.2). When enabled without `push_handler`,
+    /// pushed streams are reset.
+    pub fn set_enable_push(&mut self, enable_push: bool) {
+        self.enable_push = Some(enable_push);
+    }
+
+    /// Protocols offered in TLS ALPN.
+    pub(crate) fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match &self.alpn_protocols {
+            Some(protocols) => protocols.clone(),
+            None if self.http1_fallback.unwrap_or(false) => {
+                vec![b"h2".to_vec(), ALPN_HTTP_1_1.to_vec()]
+            }
+            None => vec![b"h2".to_vec()],
+        }
+    }
+
+    /// Initial `SETTINGS` frame sent to server.
+    pub(crate) fn settings_frame(&self) -> SettingsFrame {
+        let mut settings_frame = self.common.settings_frame();
+        if self.enable_push.unwrap_or(self.push_handler.is_some()) {
+            settings_frame
+                .settings
+                .retain(|s| !matches!(s, HttpSetting::EnablePush(..)));
+            settings_frame.add_setting(HttpSetting::EnablePush(true));
+        }
+        settings_frame
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enable_push() {
+        let enable_push = |conf: &ClientConf| {
+            let settings = conf.settings_frame().settings;
+            assert_eq!(
+                1,
+                settings
+                    .iter()
+                    .filter(|s| matches!(s, HttpSetting::EnablePush(..)))
+                    .count()
+            );
+            settings.contains(&HttpSetting::EnablePush(true))
+        };
+
+        let mut conf = ClientConf::new();
+        assert!(!enable_push(&conf));
+        conf.set_enable_push(true);
+        assert!(enable_push(&conf));
+
+        let mut conf = ClientConf::new();
+        conf.push_handler = Some(Arc::new(|_: Headers, _: crate::Response| {}));
+        assert!(enable_push(&conf));
+        conf.set_enable_push(false);
+        assert!(!enable_push(&conf));
+    }
+
+    #[test]
+    fn alpn_protocols() {
+        let mut conf = ClientConf::new();
+        assert_eq!(vec![b"h2".to_vec()], conf.alpn_protocols());
+        conf.http1_fallback = Some(true);
+        assert_eq!(
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            conf.alpn_protocols()
+        );
+        conf.alpn_protocols = Some(vec![b"http/1.1".to_vec(), b"h2".to_vec()]);
+        assert_eq!(
+            vec![b"http/1.1".to_vec(), b"h2".to_vec()],
+            conf.alpn_protocols()
+        );
+    }
 }
@@ -1,8 +1,16 @@
 //! Single client connection
 
+use std::collections::VecDeque;
 use std::io;
+use std::mem;
 use std::result::Result as std_Result;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::error;
 use crate::error::Error;
@@ -10,34 +18,35 @@ use crate::result;
 use crate::AnySocketAddr;
 
 use crate::solicit::end_stream::EndStream;
-use crate::solicit::frame::HttpSetting;
-use crate::solicit::frame::SettingsFrame;
 use crate::solicit::header::*;
+use crate::solicit::priority::Priority;
 use crate::solicit::DEFAULT_SETTINGS;
 
 use std::future::Future;
 
-use tls_api::TlsConnector;
-
-use tls_api;
-
 use crate::solicit_async::*;
 
 use crate::assert_types::assert_send_future;
+use crate::client::http1::run_http1_conn;
+use crate::client::http1::ALPN_HTTP_1_1;
 use crate::client::req::ClientRequest;
 
+use crate::client::push::PushHandler;
 use crate::client::stream_handler::ClientStreamCreatedHandler;
 use crate::client::types::ClientTypes;
 use crate::client::ClientInterface;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::conn::Conn;
+use crate::common::conn::ConnIo;
 use crate::common::conn::ConnSpecific;
 use crate::common::conn::ConnStateSnapshot;
 use crate::common::conn_command_channel::conn_command_channel;
 use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::conn_id::ConnId;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::conn_write::ConnWriteSideCustom;
+use crate::common::init_where::InitWhere;
 use crate::common::sender::CommonSender;
 use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
@@ -45,17 +54,29 @@ use crate::common::stream::HttpStreamDataSpecific;
 use crate::common::stream::InMessageStage;
 use crate::common::stream_handler::StreamHandlerInternal;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::types::Types;
 use crate::data_or_headers::DataOrHeaders;
 use crate::headers_place::HeadersPlace;
 use crate::req_resp::RequestOrResponse;
 use crate::socket::StreamItem;
 use crate::socket::ToClientStream;
+use crate::solicit::frame::HttpFrame;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::stream_id::StreamId;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::check_pins;
+use crate::tls::PeerCertificates;
+use crate::tls::TlsConnectorLike;
+use crate::tls::TlsSessionInfo;
+use crate::ClientAlpn;
 use crate::ClientConf;
 use crate::ClientTlsOption;
 use crate::ErrorCode;
 use bytes::Bytes;
 use futures::channel::oneshot;
+use futures::future;
+use futures::future::Shared;
 use futures::FutureExt;
 use futures::TryFutureExt;
 use std::pin::Pin;
@@ -76,14 +97,57 @@ impl HttpStreamData for ClientStream {
     type Types = ClientTypes;
 }
 
+/// Load of a connection, shared between connection event loop and client pool.
+#[derive(Default)]
+pub(crate) struct ConnLoad {
+    /// Requests sent to the event loop
+    sent: AtomicUsize,
+    /// Requests received by the event loop
+    received: AtomicUsize,
+    /// Open or queued streams, updated by the event loop
+    streams: AtomicUsize,
+    /// `GOAWAY` received from server
+    goaway: AtomicBool,
+}
+
+impl ConnLoad {
+    /// Open streams and requests not yet received by the event loop.
+    fn streams(&self) -> usize {
+        let received = self.received.load(Ordering::SeqCst);
+        let sent = self.sent.load(Ordering::SeqCst);
+        self.streams.load(Ordering::SeqCst) + sent.saturating_sub(received)
+    }
+}
+
 pub struct ClientConnData {
-    _callbacks: Box<dyn ClientConnCallbacks>,
+    callbacks: Box<dyn ClientConnCallbacks>,
+    load: Arc<ConnLoad>,
+    push_handler: Option<Arc<dyn PushHandler>>,
+    /// First `SETTINGS` frame received from server
+    settings_received: bool,
+    /// Notified when first `SETTINGS` frame is received
+    settings_received_tx: Option<oneshot::Sender<()>>,
+    /// Extended `CONNECT` requests wait for server settings
+    waiting_for_settings: Vec<ClientStartRequestMessage>,
+    /// Requests waiting for server `SETTINGS_MAX_CONCURRENT_STREAMS`
+    queued: VecDeque<ClientStartRequestMessage>,
+    max_queued_requests: Option<usize>,
+    expect_continue_timeout: Duration,
+    informational_responses: bool,
 }
 
 impl ConnSpecific for ClientConnData {}
 
 pub struct ClientConn {
     write_tx: ConnCommandSender<ClientTypes>,
+    conn_id: ConnId,
+    load: Arc<ConnLoad>,
+    /// Certificates presented by server during TLS handshake
+    peer_certificates: Arc<Mutex<Option<PeerCertificates>>>,
+    /// Parameters negotiated during TLS handshake
+    tls_session: Arc<Mutex<Option<TlsSessionInfo>>>,
+    /// Resolved when connection task completes
+    closed: Shared<oneshot::Receiver<()>>,
 }
 
 unsafe impl Sync for ClientConn {}
@@ -97,13 +161,44 @@ pub(crate) struct StartRequestMessage {
 }
 
 pub struct ClientStartRequestMessage {
-    start: StartRequestMessage,
+    // `None` after the stream is created
+    start: Option<StartRequestMessage>,
     write_tx: ConnCommandSender<ClientTypes>,
 }
 
+impl Drop for ClientStartRequestMessage {
+    fn drop(&mut self) {
+        if let Some(mut start) = self.start.take() {
+            let error = self.write_tx.conn_died_error();
+            debug!("request dropped before stream is created: {}", error);
+            start.stream_handler.request_not_created(error);
+        }
+    }
+}
+
+impl ClientStartRequestMessage {
+    /// Take request to process it outside of HTTP/2 connection.
+    pub(crate) fn take(&mut self) -> StartRequestMessage {
+        self.start.take().unwrap()
+    }
+}
+
+/// Protocol spoken over established connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClientProtocol {
+    /// HTTP/2 with prior knowledge or negotiated with ALPN
+    Http2,
+    /// HTTP/2 after HTTP/1.1 `Upgrade: h2c`
+    H2cUpgrade,
+    /// ALPN selected HTTP/1.1, see `ClientConf::http1_fallback`
+    Http1,
+}
+
 pub(crate) enum ClientToWriteMessage {
     Start(ClientStartRequestMessage),
     WaitForHandshake(oneshot::Sender<result::Result<()>>),
+    /// `100 Continue` was not received in time, send request body anyway
+    ExpectContinueTimeout(StreamId),
     Common(CommonToWriteMessage),
 }
 
@@ -128,7 +223,34 @@ where
                 drop(tx.send(Ok(())));
                 Ok(())
             }
+            ClientToWriteMessage::ExpectContinueTimeout(stream_id) => {
+                if self.release_held_body(stream_id) {
+                    debug!("100 continue not received, sending body of {}", stream_id);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn process_queued(&mut self) -> result::Result<()> {
+        while !self.specific.queued.is_empty() && self.has_stream_slot() {
+            let start = self.specific.queued.pop_front().unwrap();
+            self.start_stream(start)?;
+        }
+
+        let load = &self.specific.load;
+        let streams = self.streams.local_stream_count()
+            + self.specific.queued.len()
+            + self.specific.waiting_for_settings.len();
+        load.streams.store(streams, Ordering::SeqCst);
+        if let Some(goaway) = &self.goaway_received {
+            if !load.goaway.swap(true, Ordering::SeqCst) {
+                self.specific
+                    .callbacks
+                    .goaway(goaway.last_stream_id, goaway.raw_error_code());
+            }
         }
+        Ok(())
     }
 }
 
@@ -136,28 +258,104 @@ impl<I> Conn<ClientTypes, I>
 where
     I: AsyncWrite + AsyncRead + Send + 'static,
 {
-    fn process_start(&mut self, start: ClientStartRequestMessage) -> result::Result<()> {
-        let ClientStartRequestMessage {
-            start:
-                StartRequestMessage {
-                    headers,
-                    body,
-                    trailers,
-                    end_stream,
-                    mut stream_handler,
-                },
-            write_tx,
-        } = start;
+    /// Upgrade request is stream 1, half-closed (local), its response is discarded.
+    fn process_h2c_upgraded(&mut self) {
+        let stream_id = self.next_local_stream_id();
+        let (mut stream, _out_window) = self.new_stream_data(
+            stream_id,
+            None,
+            InMessageStage::Initial,
+            ClientStreamData {},
+        );
+        stream.stream().close_local();
+    }
+
+    fn process_start(&mut self, mut start: ClientStartRequestMessage) -> result::Result<()> {
+        self.specific.load.received.fetch_add(1, Ordering::SeqCst);
+
+        // RFC 8441 section 3: `:protocol` can be sent only if
+        // server advertised `SETTINGS_ENABLE_CONNECT_PROTOCOL`
+        if start
+            .start
+            .as_ref()
+            .unwrap()
+            .headers
+            .get_opt(":protocol")
+            .is_some()
+        {
+            if !self.specific.settings_received {
+                self.specific.waiting_for_settings.push(start);
+                return Ok(());
+            }
+            if !self.peer_settings.enable_connect_protocol {
+                let mut start = start.start.take().unwrap();
+                start
+                    .stream_handler
+                    .request_not_created(error::Error::ExtendedConnectNotSupported);
+                return Ok(());
+            }
+        }
+
+        if !self.specific.queued.is_empty() || !self.has_stream_slot() {
+            if self.specific.max_queued_requests == Some(self.specific.queued.len()) {
+                let mut start = start.start.take().unwrap();
+                start
+                    .stream_handler
+                    .request_not_created(error::Error::RequestQueueFull);
+            } else {
+                debug!("max concurrent streams reached, queueing request");
+                self.specific.queued.push_back(start);
+            }
+            return Ok(());
+        }
+
+        self.start_stream(start)
+    }
+
+    /// Send request body held back for `100 Continue`,
+    /// return `false` if the body is not held.
+    fn release_held_body(&mut self, stream_id: StreamId) -> bool {
+        let mut stream = match self.streams.get_mut(stream_id) {
+            Some(stream) => stream,
+            None => return false,
+        };
+        if !stream.stream().out_held {
+            return false;
+        }
+        stream.set_out_held(false);
+        true
+    }
+
+    /// Server `SETTINGS_MAX_CONCURRENT_STREAMS` allows one more stream.
+    fn has_stream_slot(&self) -> bool {
+        self.streams.local_stream_count() < self.peer_settings.max_concurrent_streams as usize
+    }
+
+    fn start_stream(&mut self, mut start: ClientStartRequestMessage) -> result::Result<()> {
+        let write_tx = start.write_tx.clone();
+        let StartRequestMessage {
+            headers,
+            body,
+            trailers,
+            end_stream,
+            mut stream_handler,
+        } = start.start.take().unwrap();
 
         let stream_id = self.next_local_stream_id();
 
+        let expect_continue = (body.is_some() || !end_stream)
+            && headers
+                .get_opt("expect")
+                .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"));
+
         {
-            let (_, out_window) = self.new_stream_data(
+            let (mut stream, out_window) = self.new_stream_data(
                 stream_id,
                 None,
                 InMessageStage::Initial,
                 ClientStreamData {},
             );
+            stream.stream().priority = Priority::from_headers(&headers);
 
             let in_window_size = self
                 .streams
@@ -171,8 +369,9 @@ where
                 common: if end_stream {
                     CommonSender::new_done(stream_id)
                 } else {
-                    CommonSender::new(stream_id, write_tx, out_window, true)
+                    CommonSender::new(stream_id, write_tx.clone(), out_window, true)
                 },
+                write_tx,
                 drop_callback: None,
             };
 
@@ -207,10 +406,23 @@ where
                     if end_stream {
                         stream.close_outgoing(ErrorCode::NoError);
                     }
+                    stream.set_out_held(expect_continue);
                 }
             };
         }
 
+        if expect_continue {
+            let write_tx = self.to_write_tx.clone();
+            let timeout = self.specific.expect_continue_timeout;
+            self.loop_handle.spawn(async move {
+                time::delay_for(timeout).await;
+                // ignore error, connection may be closed
+                drop(
+                    write_tx.unbounded_send(ClientToWriteMessage::ExpectContinueTimeout(stream_id)),
+                );
+            });
+        }
+
         // Also opens latch if necessary
         self.buffer_outg_conn()?;
         Ok(())
@@ -225,7 +437,7 @@ pub trait ClientConnCallbacks: Send + 'static {
 impl ClientConn {
     fn spawn_connected<I, C>(
         lh: Handle,
-        connect: HttpFutureSend<I>,
+        connect: HttpFutureSend<(I, ClientProtocol)>,
         peer_addr: AnySocketAddr,
         conf: ClientConf,
         callbacks: C,
@@ -238,11 +450,22 @@ impl ClientConn {
 
         let (to_write_tx, to_write_rx) = conn_command_channel(conn_died_error_holder.clone());
 
+        let conn_id = ConnId::next();
+
+        let (closed_tx, closed_rx) = oneshot::channel();
+
+        let load = Arc::new(ConnLoad::default());
+
         let c = ClientConn {
             write_tx: to_write_tx.clone(),
+            conn_id,
+            load: load.clone(),
+            peer_certificates: Default::default(),
+            tls_session: Default::default(),
+            closed: closed_rx.shared(),
         };
 
-        let settings_frame = SettingsFrame::from_settings(vec![HttpSetting::EnablePush(false)]);
+        let settings_frame = conf.settings_frame();
         let mut settings = DEFAULT_SETTINGS;
         settings.apply_from_frame(&settings_frame);
 
@@ -250,44 +473,130 @@ impl ClientConn {
 
         let conn_died_error_holder_copy = conn_died_error_holder.clone();
 
-        let future = connect.and_then(move |mut conn| async move {
-            client_handshake(&mut conn, settings_frame).await?;
+        let authority = peer_addr.to_string();
+
+        let connect_deadline = conf
+            .connection_timeout
+            .map(|timeout| time::Instant::now() + timeout);
+        let (settings_received_tx, settings_received_rx) = oneshot::channel();
+
+        let future = async move {
+            let handshake_started = Instant::now();
+            let handshake = async {
+                let (mut conn, protocol) = connect.await?;
+                match protocol {
+                    ClientProtocol::Http2 => client_handshake(&mut conn, settings_frame).await?,
+                    ClientProtocol::H2cUpgrade => {
+                        client_h2c_handshake(&mut conn, settings_frame, &authority).await?
+                    }
+                    ClientProtocol::Http1 => {}
+                }
+                Ok((conn, protocol))
+            };
+            let handshake = async {
+                match connect_deadline {
+                    Some(deadline) => match time::timeout_at(deadline, handshake).await {
+                        Ok(r) => r,
+                        Err(_) => Err(error::Error::ConnectionTimeout),
+                    },
+                    None => handshake.await,
+                }
+            };
+            let (conn, protocol) = match handshake.await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    // record the error before queued requests are dropped
+                    conn_died_error_holder.set_once(e);
+                    return Err(conn_died_error_holder.error());
+                }
+            };
 
             debug!("handshake done");
 
-            let conn_data = Conn::<ClientTypes, _>::new(
-                lh_copy,
+            if protocol == ClientProtocol::Http1 {
+                return run_http1_conn(conn, to_write_rx, to_write_tx).await;
+            }
+
+            let io = ConnIo {
+                loop_handle: lh_copy,
+                socket: conn,
+                conn_id,
+                peer_addr,
+                to_write_tx: to_write_tx.clone(),
+                write_rx: to_write_rx,
+                conn_died_error_holder,
+            };
+            let mut conn_data = Conn::<ClientTypes, _>::new(
+                io,
                 ClientConnData {
-                    _callbacks: Box::new(callbacks),
+                    callbacks: Box::new(callbacks),
+                    load,
+                    push_handler: conf.push_handler,
+                    settings_received: false,
+                    settings_received_tx: Some(settings_received_tx),
+                    waiting_for_settings: Vec::new(),
+                    queued: VecDeque::new(),
+                    max_queued_requests: conf.max_queued_requests,
+                    expect_continue_timeout: conf
+                        .expect_continue_timeout
+                        .unwrap_or(Duration::from_secs(1)),
+                    informational_responses: conf.informational_responses.unwrap_or(false),
                 },
                 conf.common,
                 settings,
-                to_write_tx.clone(),
-                to_write_rx,
-                conn,
-                peer_addr,
-                conn_died_error_holder,
             );
-            conn_data.run().await
-        });
+            conn_data.handshake_done(handshake_started);
+            conn_data.set_keepalive(conf.keepalive_interval, conf.keepalive_timeout);
+            conn_data.set_idle_timeout(conf.idle_timeout);
+            if protocol == ClientProtocol::H2cUpgrade {
+                conn_data.process_h2c_upgraded();
+            }
+            let conn_died_error_holder = conn_data.conn_died_error_holder.clone();
+            let run = conn_data.run();
+            let deadline = match connect_deadline {
+                Some(deadline) => deadline,
+                None => return run.await,
+            };
+            // server `SETTINGS` completes connection establishment
+            let settings_timeout = async {
+                match time::timeout_at(deadline, settings_received_rx).await {
+                    Err(_) => error::Error::ConnectionTimeout,
+                    // connection loop finished or settings received
+                    Ok(_) => future::pending().await,
+                }
+            };
+            futures::pin_mut!(run);
+            futures::pin_mut!(settings_timeout);
+            match future::select(run, settings_timeout).await {
+                future::Either::Left((r, _)) => r,
+                future::Either::Right((e, _)) => {
+                    conn_died_error_holder.set_once(e);
+                    Err(conn_died_error_holder.error())
+                }
+            }
+        };
 
         let future = conn_died_error_holder_copy.wrap_future(future);
 
+        // ignore error, nobody may wait
+        let future = future.map(move |()| {
+            let _ = closed_tx.send(());
+        });
+
         lh.spawn(future);
 
         c
     }
 
-    pub fn spawn<H, C>(
+    pub fn spawn<H>(
         lh: Handle,
         addr: Pin<Box<dyn ToClientStream + Send>>,
-        tls: ClientTlsOption<C>,
+        tls: ClientTlsOption,
         conf: ClientConf,
         callbacks: H,
     ) -> Self
     where
         H: ClientConnCallbacks,
-        C: TlsConnector + Sync,
     {
         match tls {
             ClientTlsOption::Plain => ClientConn::spawn_plain(lh.clone(), addr, conf, callbacks),
@@ -322,35 +631,29 @@ impl ClientConn {
             socket
         };
 
-        let connect: Pin<
-            Box<dyn Future<Output = result::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>,
-        > = if let Some(timeout) = conf.connection_timeout {
-            Box::pin(time::timeout(timeout, connect).map(|r| match r {
-                Ok(r) => r,
-                Err(_) => Err(error::Error::ConnectionTimeout),
-            }))
-        } else {
-            Box::pin(connect)
-        };
-
         let connect = Box::pin(
             connect.map_ok(move |socket: Pin<Box<dyn StreamItem + Send>>| map_callback(socket)),
         );
 
+        let protocol = match conf.h2c_upgrade.unwrap_or(false) {
+            true => ClientProtocol::H2cUpgrade,
+            false => ClientProtocol::Http2,
+        };
+        let connect = Box::pin(connect.map_ok(move |socket| (socket, protocol)));
+
         ClientConn::spawn_connected(lh, connect, addr_struct, conf, callbacks)
     }
 
-    pub fn spawn_tls<H, C>(
+    pub fn spawn_tls<H>(
         lh: Handle,
         domain: &str,
-        connector: Arc<C>,
+        connector: Arc<dyn TlsConnectorLike>,
         addr: Pin<Box<dyn ToClientStream + Send>>,
         conf: ClientConf,
         callbacks: H,
     ) -> Self
     where
         H: ClientConnCallbacks,
-        C: TlsConnector + Sync,
     {
         let addr_struct = addr.socket_addr();
 
@@ -374,15 +677,84 @@ impl ClientConn {
         let connect = assert_send_future(connect);
 
         let tls_conn = connect
-            .and_then(move |conn| async move { Ok(connector.connect(&domain, conn).await?) });
+            .and_then(move |conn| async move { connector.connect(&domain, Box::pin(conn)).await });
 
         let tls_conn = assert_send_future(tls_conn);
 
-        let tls_conn = tls_conn.map_err(Error::from);
+        let http1_fallback = conf.http1_fallback.unwrap_or(false);
+        let alpn = conf.alpn.unwrap_or(ClientAlpn::Ignore);
+        #[cfg(feature = "tls-rustls")]
+        let pinned_spki_sha256 = conf.pinned_spki_sha256.clone();
+        let peer_certificates: Arc<Mutex<Option<PeerCertificates>>> = Default::default();
+        let peer_certificates_copy = peer_certificates.clone();
+        let tls_session: Arc<Mutex<Option<TlsSessionInfo>>> = Default::default();
+        let tls_session_copy = tls_session.clone();
+        let tls_conn = tls_conn.and_then(move |mut handshake| {
+            #[cfg(feature = "tls-rustls")]
+            {
+                if let Some(pins) = &pinned_spki_sha256 {
+                    if let Err(e) = check_pins(pins, handshake.peer_certificates.as_ref()) {
+                        return future::ready(Err(e));
+                    }
+                }
+            }
+            *tls_session_copy.lock().unwrap() = Some(handshake.session_info());
+            *peer_certificates_copy.lock().unwrap() = handshake.peer_certificates.take();
+            future::ready(match handshake.alpn_protocol.as_deref() {
+                Some(ALPN_HTTP_1_1) if http1_fallback => {
+                    info!("ALPN selected HTTP/1.1, falling back to HTTP/1.1");
+                    Ok((handshake.stream, ClientProtocol::Http1))
+                }
+                Some(b"h2") => Ok((handshake.stream, ClientProtocol::Http2)),
+                protocol => match alpn {
+                    ClientAlpn::Ignore => Ok((handshake.stream, ClientProtocol::Http2)),
+                    ClientAlpn::Require => Err(error::Error::AlpnMismatch(protocol.map(Vec::from))),
+                },
+            })
+        });
 
         let tls_conn = assert_send_future(tls_conn);
 
-        ClientConn::spawn_connected(lh, Box::pin(tls_conn), addr_struct, conf, callbacks)
+        let mut conn =
+            ClientConn::spawn_connected(lh, Box::pin(tls_conn), addr_struct, conf, callbacks);
+        conn.peer_certificates = peer_certificates;
+        conn.tls_session = tls_session;
+        conn
+    }
+
+    /// Id of this connection.
+    pub fn conn_id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    /// Number of open streams and requests waiting for a stream.
+    pub(crate) fn streams(&self) -> usize {
+        self.load.streams()
+    }
+
+    /// Certificates presented by server, available after TLS handshake.
+    pub(crate) fn peer_certificates(&self) -> Option<PeerCertificates> {
+        self.peer_certificates.lock().unwrap().clone()
+    }
+
+    /// Parameters negotiated during TLS handshake, available after it.
+    pub(crate) fn tls_session(&self) -> Option<TlsSessionInfo> {
+        self.tls_session.lock().unwrap().clone()
+    }
+
+    /// Connection event loop is finished.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.write_tx.is_closed()
+    }
+
+    /// Server sent `GOAWAY`, so new requests must not be sent to this connection.
+    pub(crate) fn goaway_received(&self) -> bool {
+        self.load.goaway.load(Ordering::SeqCst)
+    }
+
+    /// Future resolved when connection is closed.
+    pub(crate) fn closed(&self) -> impl Future<Output = ()> + Send {
+        self.closed.clone().map(|_| ())
     }
 
     pub(crate) fn start_request_with_resp_sender(
@@ -390,16 +762,18 @@ impl ClientConn {
         start: StartRequestMessage,
     ) -> Result<(), StartRequestMessage> {
         let client_start = ClientStartRequestMessage {
-            start,
+            start: Some(start),
             write_tx: self.write_tx.clone(),
         };
 
         self.write_tx
             .unbounded_send_recover(ClientToWriteMessage::Start(client_start))
             .map_err(|send_error| match send_error {
-                ClientToWriteMessage::Start(start) => start.start,
+                ClientToWriteMessage::Start(mut start) => start.start.take().unwrap(),
                 _ => unreachable!(),
-            })
+            })?;
+        self.load.sent.fetch_add(1, Ordering::SeqCst);
+        Ok(())
     }
 
     pub fn dump_state_with_resp_sender(&self, tx: oneshot::Sender<ConnStateSnapshot>) {
@@ -434,6 +808,55 @@ impl ClientConn {
     }
 }
 
+impl ClientConn {
+    pub(crate) fn ping_with_resp_sender(
+        &self,
+        tx: oneshot::Sender<result::Result<Duration>>,
+    ) -> std_Result<(), oneshot::Sender<result::Result<Duration>>> {
+        let message = ClientToWriteMessage::Common(CommonToWriteMessage::Ping(tx));
+        self.write_tx
+            .unbounded_send_recover(message)
+            .map_err(|send_error| match send_error {
+                ClientToWriteMessage::Common(CommonToWriteMessage::Ping(tx)) => tx,
+                _ => unreachable!(),
+            })
+    }
+}
+
+impl ClientConn {
+    pub(crate) fn update_settings_with_resp_sender(
+        &self,
+        settings: Vec<HttpSetting>,
+        tx: oneshot::Sender<result::Result<()>>,
+    ) -> std_Result<(), oneshot::Sender<result::Result<()>>> {
+        let message =
+            ClientToWriteMessage::Common(CommonToWriteMessage::UpdateSettings(settings, tx));
+        self.write_tx
+            .unbounded_send_recover(message)
+            .map_err(|send_error| match send_error {
+                ClientToWriteMessage::Common(CommonToWriteMessage::UpdateSettings(_, tx)) => tx,
+                _ => unreachable!(),
+            })
+    }
+}
+
+impl ClientConn {
+    pub(crate) fn send_goaway(
+        &self,
+        error_code: ErrorCode,
+        debug_data: Bytes,
+        graceful: bool,
+    ) -> result::Result<()> {
+        let message = CommonToWriteMessage::Goaway(error_code, debug_data, graceful);
+        self.write_tx.unbounded_send(message.into())
+    }
+
+    pub(crate) fn send_raw_frame(&self, frame: HttpFrame) -> result::Result<()> {
+        let message = CommonToWriteMessage::RawFrame(frame);
+        self.write_tx.unbounded_send(message.into())
+    }
+}
+
 impl ClientInterface for ClientConn {
     fn start_request_low_level(
         &self,
@@ -523,6 +946,12 @@ where
             }
         };
 
+        // 100 or final response allows to send held request body
+        if headers_place == HeadersPlace::Initial && (!status_1xx || headers.status() == 100) {
+            self.release_held_body(stream_id);
+        }
+
+        let informational_responses = self.specific.informational_responses;
         let mut stream = self.streams.get_mut(stream_id).unwrap();
         if let Some(in_rem_content_length) = headers.content_length() {
             stream.stream().in_rem_content_length = Some(in_rem_content_length);
@@ -534,8 +963,14 @@ where
             (HeadersPlace::Trailing, _) => InMessageStage::AfterTrailingHeaders,
         };
 
-        // Ignore 1xx headers
-        if !status_1xx {
+        if status_1xx {
+            // Ignore 1xx headers unless requested
+            if informational_responses {
+                if let Some(ref mut response_handler) = stream.stream().peer_tx {
+                    drop(response_handler.0.informational(headers));
+                }
+            }
+        } else {
             if let Some(ref mut response_handler) = stream.stream().peer_tx {
                 // TODO: reset stream on error
                 drop(match headers_place {
@@ -554,4 +989,94 @@ where
 
         Ok(Some(stream))
     }
+
+    fn peer_settings_applied(&mut self) -> result::Result<()> {
+        if !self.specific.settings_received {
+            self.specific.settings_received = true;
+            if let Some(tx) = self.specific.settings_received_tx.take() {
+                // ignore error, connect deadline may be not set
+                let _ = tx.send(());
+            }
+            for start in mem::take(&mut self.specific.waiting_for_settings) {
+                self.process_start(start)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_push_promise(
+        &mut self,
+        frame: PushPromiseDecodedFrame,
+    ) -> result::Result<Option<HttpStreamRef<'_, ClientTypes>>> {
+        let PushPromiseDecodedFrame {
+            stream_id,
+            promised_stream_id,
+            headers,
+        } = frame;
+
+        // 8.2: push is disabled, or promised stream id is not a new server stream id
+        if !self.our_settings_sent().enable_push
+            || ClientTypes::init_where(stream_id) != InitWhere::Locally
+            || ClientTypes::init_where(promised_stream_id) != InitWhere::Peer
+            || promised_stream_id <= self.last_peer_stream_id
+        {
+            warn!(
+                "unexpected PUSH_PROMISE {} on {}",
+                promised_stream_id, stream_id
+            );
+            self.send_goaway(ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+        self.last_peer_stream_id = promised_stream_id;
+
+        // 8.2: promised requests must be safe and must not have body
+        let method = headers.get_opt(":method");
+        if headers
+            .validate(RequestOrResponse::Request, HeadersPlace::Initial)
+            .is_err()
+            || (method != Some("GET") && method != Some("HEAD"))
+        {
+            warn!("invalid promised request headers: {:?}", headers);
+            self.send_rst_stream(promised_stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
+        let push_handler = match &self.specific.push_handler {
+            Some(push_handler) if self.streams.get_mut(stream_id).is_some() => push_handler.clone(),
+            _ => {
+                debug!("push {} on {} refused", promised_stream_id, stream_id);
+                self.send_rst_stream(promised_stream_id, ErrorCode::Cancel)?;
+                return Ok(None);
+            }
+        };
+
+        let (mut stream, _) = self.new_stream_data(
+            promised_stream_id,
+            None,
+            InMessageStage::Initial,
+            ClientStreamData {},
+        );
+        // Pushed stream is reserved (remote) until headers are received,
+        // which is equivalent to half-closed (local) for us.
+        stream.stream().close_local();
+        let in_window_size = stream.stream().in_window_size.size() as u32;
+
+        let mut handler = None;
+        let response = ClientResponse {
+            stream_handler: &mut handler,
+            in_window_size,
+            stream_id: promised_stream_id,
+            to_write_tx: &self.to_write_tx,
+        }
+        .make_stream();
+        self.streams
+            .get_mut(promised_stream_id)
+            .unwrap()
+            .stream()
+            .peer_tx = handler;
+
+        push_handler.push_promise(headers, response);
+
+        Ok(None)
+    }
 }
@@ -0,0 +1,428 @@
+//! Client cookie store (RFC 6265 subset).
+
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::date::parse_http_date;
+use crate::Headers;
+
+/// Cookie stored in a `CookieJar`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Domain without port.
+    pub domain: String,
+    /// Cookie is sent only to exactly `domain`, not to subdomains.
+    pub host_only: bool,
+    pub path: String,
+    /// Cookie is sent only over `https`.
+    pub secure: bool,
+    pub http_only: bool,
+    /// `None` for session cookies.
+    pub expires: Option<Instant>,
+}
+
+impl Cookie {
+    /// Parse `set-cookie` header value received for given request host and path.
+    ///
+    /// `Max-Age` takes precedence over `Expires`.
+    pub fn parse(set_cookie: &str, host: &str, request_path: &str) -> Option<Cookie> {
+        let mut parts = set_cookie.split(';');
+        let name_value = parts.next()?;
+        let eq = name_value.find('=')?;
+        let name = name_value[..eq].trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Cookie {
+            name: name.to_owned(),
+            value: name_value[eq + 1..].trim().to_owned(),
+            domain: host.to_ascii_lowercase(),
+            host_only: true,
+            path: default_path(request_path).to_owned(),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+
+        let mut max_age = false;
+        for attr in parts {
+            let (attr_name, attr_value) = match attr.find('=') {
+                Some(eq) => (attr[..eq].trim(), attr[eq + 1..].trim()),
+                None => (attr.trim(), ""),
+            };
+            match &attr_name.to_ascii_lowercase()[..] {
+                "domain" => {
+                    let domain = attr_value.trim_start_matches('.').to_ascii_lowercase();
+                    if domain.is_empty() {
+                        continue;
+                    }
+                    // reject cookies for unrelated domains
+                    if !domain_match(host, &domain) {
+                        return None;
+                    }
+                    // single-label domain (e. g. `com`) is treated as a public suffix,
+                    // allowed only for the host itself (RFC 6265 section 5.3 step 5)
+                    if !domain.contains('.') {
+                        if !host.eq_ignore_ascii_case(&domain) {
+                            return None;
+                        }
+                        continue;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if attr_value.starts_with('/') => cookie.path = attr_value.to_owned(),
+                "max-age" => match attr_value.parse::<i64>() {
+                    Ok(secs) if secs <= 0 => {
+                        cookie.expires = Some(Instant::now());
+                        max_age = true;
+                    }
+                    Ok(secs) => {
+                        cookie.expires = Some(Instant::now() + Duration::from_secs(secs as u64));
+                        max_age = true;
+                    }
+                    Err(_) => {}
+                },
+                "expires" if !max_age => {
+                    if let Some(unix_secs) = parse_http_date(attr_value) {
+                        cookie.expires = Some(instant_from_unix_secs(unix_secs));
+                    }
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        match self.expires {
+            Some(expires) => expires <= now,
+            None => false,
+        }
+    }
+
+    fn matches(&self, https: bool, host: &str, path: &str) -> bool {
+        if self.secure && !https {
+            return false;
+        }
+        let host_matches = if self.host_only {
+            host.eq_ignore_ascii_case(&self.domain)
+        } else {
+            domain_match(host, &self.domain)
+        };
+        host_matches && path_match(path, &self.path)
+    }
+}
+
+/// Convert absolute wall clock time to `Instant`.
+fn instant_from_unix_secs(unix_secs: i64) -> Instant {
+    let now = Instant::now();
+    let now_unix_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    if unix_secs <= now_unix_secs {
+        now
+    } else {
+        now + Duration::from_secs((unix_secs - now_unix_secs) as u64)
+    }
+}
+
+/// Host is an IP address, IPv6 address is in brackets.
+fn is_ip_address(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<IpAddr>().is_ok()
+}
+
+/// Request host matches cookie domain (RFC 6265 section 5.1.3).
+///
+/// IP addresses match only identical domain.
+fn domain_match(host: &str, domain: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == domain
+        || (!is_ip_address(&host)
+            && host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+/// Request path matches cookie path (RFC 6265 section 5.1.4).
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Default cookie path for request path (RFC 6265 section 5.1.4).
+fn default_path(request_path: &str) -> &str {
+    let path = match request_path.find('?') {
+        Some(q) => &request_path[..q],
+        None => request_path,
+    };
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(slash) => &path[..slash],
+    }
+}
+
+/// Host part of `:authority`.
+fn authority_host(authority: &str) -> &str {
+    if authority.starts_with('[') {
+        match authority.find(']') {
+            Some(end) => &authority[..end + 1],
+            None => authority,
+        }
+    } else {
+        match authority.rfind(':') {
+            Some(colon) => &authority[..colon],
+            None => authority,
+        }
+    }
+}
+
+/// Request properties cookies are matched against.
+struct RequestTarget<'a> {
+    https: bool,
+    host: &'a str,
+    path: &'a str,
+}
+
+impl<'a> RequestTarget<'a> {
+    fn from_headers(headers: &'a Headers) -> Option<RequestTarget<'a>> {
+        Some(RequestTarget {
+            https: headers.get_opt(":scheme") == Some("https"),
+            host: authority_host(headers.host_or_authority()?),
+            path: headers.get_opt(":path")?,
+        })
+    }
+}
+
+/// Cookie storage shared by requests of a client.
+///
+/// Records `set-cookie` response headers and adds matching `cookie`
+/// header to subsequent requests.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    pub fn new() -> CookieJar {
+        Default::default()
+    }
+
+    /// Stored cookies which are not expired.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = Instant::now();
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|c| !c.is_expired(now));
+        cookies.clone()
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Store a cookie, replacing a cookie with the same name, domain and path.
+    ///
+    /// Expired cookie removes the stored one.
+    pub fn insert(&self, cookie: Cookie) {
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|c| {
+            !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path)
+        });
+        if !cookie.is_expired(Instant::now()) {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Record `set-cookie` headers of a response to given request.
+    pub fn store_response(&self, req: &Headers, resp: &Headers) {
+        let target = match RequestTarget::from_headers(req) {
+            Some(target) => target,
+            None => return,
+        };
+        for header in resp.iter().filter(|h| h.name() == "set-cookie") {
+            let value = String::from_utf8_lossy(header.value());
+            if let Some(cookie) = Cookie::parse(&value, target.host, target.path) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    /// `cookie` header value for given request.
+    pub fn cookie_header(&self, req: &Headers) -> Option<String> {
+        let target = RequestTarget::from_headers(req)?;
+        let path = match target.path.find('?') {
+            Some(q) => &target.path[..q],
+            None => target.path,
+        };
+
+        let mut cookies: Vec<Cookie> = self
+            .cookies()
+            .into_iter()
+            .filter(|c| c.matches(target.https, target.host, path))
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        // cookies with longer paths are listed first
+        cookies.sort_by_key(|c| Reverse(c.path.len()));
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Add `cookie` header to a request.
+    pub(crate) fn add_cookie_header(&self, req: &mut Headers) {
+        if let Some(cookie) = self.cookie_header(req) {
+            req.add("cookie", cookie);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(scheme: &str, authority: &str, path: &str) -> Headers {
+        let mut headers = Headers::new_get(path.to_owned());
+        headers.add(":scheme", scheme.to_owned());
+        headers.add(":authority", authority.to_owned());
+        headers
+    }
+
+    fn response(set_cookies: &[&str]) -> Headers {
+        let mut headers = Headers::ok_200();
+        for set_cookie in set_cookies {
+            headers.add("set-cookie", (*set_cookie).to_owned());
+        }
+        headers
+    }
+
+    #[test]
+    fn parse() {
+        let cookie = Cookie::parse(
+            "sid=abc; Path=/app; Domain=.Example.com; Secure; HttpOnly",
+            "www.example.com",
+            "/login",
+        )
+        .unwrap();
+        assert_eq!("sid", cookie.name);
+        assert_eq!("abc", cookie.value);
+        assert_eq!("example.com", cookie.domain);
+        assert!(!cookie.host_only);
+        assert_eq!("/app", cookie.path);
+        assert!(cookie.secure);
+        assert!(cookie.http_only);
+
+        assert_eq!(
+            None,
+            Cookie::parse("a=b; Domain=other.com", "example.com", "/")
+        );
+        assert_eq!(
+            "/a/b",
+            Cookie::parse("a=b", "example.com", "/a/b/c?x=/y")
+                .unwrap()
+                .path
+        );
+        assert_eq!(None, Cookie::parse("novalue", "example.com", "/"));
+    }
+
+    #[test]
+    fn parse_single_label_domain() {
+        assert_eq!(None, Cookie::parse("a=b; Domain=com", "foo.com", "/"));
+        assert_eq!(None, Cookie::parse("a=b; Domain=.com", "foo.com", "/"));
+        let cookie = Cookie::parse("a=b; Domain=localhost", "localhost", "/").unwrap();
+        assert_eq!("localhost", cookie.domain);
+        assert!(cookie.host_only);
+    }
+
+    #[test]
+    fn parse_ip_address_host() {
+        assert_eq!(None, Cookie::parse("a=b; Domain=0.0.1", "10.0.0.1", "/"));
+        assert_eq!(None, Cookie::parse("a=b; Domain=1", "[::1]", "/"));
+        let cookie = Cookie::parse("a=b; Domain=10.0.0.1", "10.0.0.1", "/").unwrap();
+        assert_eq!("10.0.0.1", cookie.domain);
+        assert!(!domain_match("10.0.0.1", "0.0.1"));
+    }
+
+    #[test]
+    fn parse_expires() {
+        let expires = |set_cookie| {
+            Cookie::parse(set_cookie, "example.com", "/")
+                .unwrap()
+                .expires
+        };
+
+        let now = Instant::now();
+        assert!(expires("a=b; Expires=Thu, 01 Jan 1970 00:00:00 GMT").unwrap() <= Instant::now());
+        let future = expires("a=b; Expires=Fri, 01 Jan 2100 00:00:00 GMT").unwrap();
+        assert!(future > now + Duration::from_secs(86400 * 365));
+        assert_eq!(None, expires("a=b; Expires=never"));
+
+        // `Max-Age` wins regardless of attribute order
+        for set_cookie in &[
+            "a=b; Max-Age=60; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            "a=b; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=60",
+        ] {
+            assert!(expires(set_cookie).unwrap() > now + Duration::from_secs(30));
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let jar = CookieJar::new();
+        jar.store_response(
+            &request("https", "www.example.com:8443", "/app/login"),
+            &response(&[
+                "host=1",
+                "domain=2; Domain=example.com; Path=/",
+                "secure=3; Secure; Path=/",
+            ]),
+        );
+        assert_eq!(3, jar.cookies().len());
+
+        let cookie = |scheme, authority, path| jar.cookie_header(&request(scheme, authority, path));
+        assert_eq!(
+            Some("host=1; domain=2; secure=3".to_owned()),
+            cookie("https", "www.example.com", "/app/x")
+        );
+        assert_eq!(
+            Some("domain=2".to_owned()),
+            cookie("http", "api.example.com", "/app/x")
+        );
+        assert_eq!(
+            Some("domain=2".to_owned()),
+            cookie("http", "www.example.com", "/application")
+        );
+        assert_eq!(None, cookie("http", "example.org", "/"));
+
+        jar.clear();
+        assert_eq!(None, cookie("https", "www.example.com", "/app/x"));
+    }
+
+    #[test]
+    fn replace_and_expire() {
+        let jar = CookieJar::new();
+        let req = request("http", "example.com", "/");
+        jar.store_response(&req, &response(&["a=1", "b=2"]));
+        jar.store_response(&req, &response(&["a=3"]));
+        jar.store_response(&req, &response(&["b=; Max-Age=0"]));
+        let cookies = jar.cookies();
+        assert_eq!(1, cookies.len());
+        assert_eq!("3", cookies[0].value);
+    }
+}
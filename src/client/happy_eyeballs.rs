@@ -0,0 +1,213 @@
+//! Connect to one of several server addresses (Happy Eyeballs, RFC 8305).
+
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::future;
+use futures::future::Either;
+use futures::stream::FuturesUnordered;
+use futures::stream::StreamExt;
+use futures::Future;
+use tokio::runtime::Handle;
+
+use crate::display_comma_separated::DisplayCommaSeparated;
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+
+/// Recommended Connection Attempt Delay (RFC 8305 section 5).
+pub(crate) const DEFAULT_DELAY: Duration = Duration::from_millis(250);
+
+/// Alternate address families, starting with the family
+/// of the first address (RFC 8305 section 4).
+pub(crate) fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_ipv6 = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+    let (first, second): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_ipv6);
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    let mut r = Vec::new();
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return r,
+            (a, b) => r.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// Start connection attempts in order, next attempt is started
+/// when previous attempt fails or after `delay`. First successful
+/// connection is returned, other attempts are cancelled.
+pub(crate) async fn race<T, F, C>(
+    addrs: Vec<SocketAddr>,
+    delay: Duration,
+    connect: C,
+) -> io::Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+    C: Fn(SocketAddr) -> F,
+{
+    let mut addrs = addrs.into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    loop {
+        let next_started = match addrs.next() {
+            Some(addr) => {
+                debug!("connecting to {}", addr);
+                attempts.push(connect(addr));
+                true
+            }
+            None => false,
+        };
+
+        let timer = if next_started && addrs.len() != 0 {
+            Either::Left(tokio::time::delay_for(delay))
+        } else {
+            Either::Right(future::pending())
+        };
+        match future::select(attempts.next(), timer).await {
+            Either::Left((Some(Ok(stream)), _)) => return Ok(stream),
+            Either::Left((Some(Err(e)), _)) => {
+                debug!("connection attempt failed: {}", e);
+                last_error = Some(e);
+            }
+            Either::Left((None, _)) => {
+                return Err(last_error.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "no addresses to connect to")
+                }));
+            }
+            // connection attempt delay expired
+            Either::Right(((), _)) => {}
+        }
+    }
+}
+
+/// Connector racing connection attempts to server addresses.
+pub(crate) struct HappyEyeballsConnector {
+    /// Addresses in order of attempts, see `interleave`.
+    pub addrs: Vec<SocketAddr>,
+    pub delay: Duration,
+}
+
+impl fmt::Display for HappyEyeballsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&DisplayCommaSeparated(&self.addrs[..]), f)
+    }
+}
+
+impl ToClientStream for HappyEyeballsConnector {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        let addrs = self.addrs.clone();
+        let delay = self.delay;
+        let handle = handle.clone();
+        Box::pin(async move { race(addrs, delay, |addr| addr.connect(&handle)).await })
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        AnySocketAddr::Inet(self.addrs[0])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Instant;
+
+    use tokio::runtime::Runtime;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn interleave_families() {
+        assert_eq!(
+            addrs(&[
+                "[::1]:80",
+                "10.0.0.1:80",
+                "[::2]:80",
+                "10.0.0.2:80",
+                "[::3]:80"
+            ]),
+            interleave(addrs(&[
+                "[::1]:80",
+                "[::2]:80",
+                "[::3]:80",
+                "10.0.0.1:80",
+                "10.0.0.2:80"
+            ]))
+        );
+        assert_eq!(
+            addrs(&["10.0.0.1:80", "[::1]:80", "10.0.0.2:80"]),
+            interleave(addrs(&["10.0.0.1:80", "10.0.0.2:80", "[::1]:80"]))
+        );
+        assert_eq!(Vec::<SocketAddr>::new(), interleave(Vec::new()));
+    }
+
+    #[test]
+    fn next_attempt_after_delay() {
+        let mut rt = Runtime::new().unwrap();
+        let start = Instant::now();
+        let r = rt.block_on(race(
+            addrs(&["[::1]:80", "127.0.0.1:80"]),
+            Duration::from_millis(50),
+            |addr| {
+                if addr.is_ipv6() {
+                    // never completes
+                    Either::Left(future::pending())
+                } else {
+                    Either::Right(future::ok(addr))
+                }
+            },
+        ));
+        assert_eq!("127.0.0.1:80".parse::<SocketAddr>().unwrap(), r.unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn next_attempt_after_failure() {
+        let mut rt = Runtime::new().unwrap();
+        let start = Instant::now();
+        let r = rt.block_on(race(
+            addrs(&["[::1]:80", "127.0.0.1:80"]),
+            Duration::from_secs(10),
+            |addr| {
+                if addr.is_ipv6() {
+                    future::err(io::Error::from(io::ErrorKind::ConnectionRefused))
+                } else {
+                    future::ok(addr)
+                }
+            },
+        ));
+        assert_eq!("127.0.0.1:80".parse::<SocketAddr>().unwrap(), r.unwrap());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn all_failed() {
+        let mut rt = Runtime::new().unwrap();
+        let r: io::Result<()> = rt.block_on(race(
+            addrs(&["[::1]:80", "127.0.0.1:80"]),
+            Duration::from_millis(10),
+            |addr| {
+                future::err(io::Error::from(if addr.is_ipv6() {
+                    io::ErrorKind::ConnectionRefused
+                } else {
+                    io::ErrorKind::TimedOut
+                }))
+            },
+        ));
+        assert_eq!(io::ErrorKind::TimedOut, r.unwrap_err().kind());
+    }
+}
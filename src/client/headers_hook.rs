@@ -0,0 +1,29 @@
+//! Asynchronous request headers hook.
+
+use std::future::Future;
+
+use crate::solicit_async::HttpFutureSend;
+use crate::Headers;
+
+/// Computes request headers before a request is sent,
+/// e. g. to refresh an access token or sign the request.
+///
+/// Request is sent when returned future resolves, and fails with
+/// the hook error if the future fails. Hook is invoked for each
+/// attempt of a retried request. Hook is not applied to requests
+/// started with `ClientInterface::start_request_low_level`.
+///
+/// Implemented for functions `Fn(Headers) -> impl Future<Output = Result<Headers>>`.
+pub trait RequestHeadersHook: Send + Sync + 'static {
+    fn apply(&self, headers: Headers) -> HttpFutureSend<Headers>;
+}
+
+impl<F, R> RequestHeadersHook for F
+where
+    F: Fn(Headers) -> R + Send + Sync + 'static,
+    R: Future<Output = crate::Result<Headers>> + Send + 'static,
+{
+    fn apply(&self, headers: Headers) -> HttpFutureSend<Headers> {
+        Box::pin(self(headers))
+    }
+}
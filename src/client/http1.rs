@@ -0,0 +1,512 @@
+//! HTTP/1.1 transport used when TLS ALPN selects `http/1.1`,
+//! see `ClientConf::http1_fallback`.
+//!
+//! Requests are sent one by one without pipelining,
+//! request body must be complete when the request is started.
+
+use std::io;
+
+use bytes::Bytes;
+use futures::stream::StreamExt;
+use tokio::io::AsyncBufRead;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+
+use crate::client::conn::ClientToWriteMessage;
+use crate::client::conn::StartRequestMessage;
+use crate::client::req::ClientRequest;
+use crate::client::resp::ClientResponse;
+use crate::client::stream_handler::ClientResponseStreamHandlerHolder;
+use crate::client::types::ClientTypes;
+use crate::common::conn_command_channel::ConnCommandReceiver;
+use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::conn_write::CommonToWriteMessage;
+use crate::common::sender::CommonSender;
+use crate::error;
+use crate::http1::read_head;
+use crate::http1::Http1Head;
+use crate::http1::MAX_HEAD_LEN;
+use crate::result;
+use crate::solicit::end_stream::EndStream;
+use crate::solicit::header::Header;
+use crate::solicit::stream_id::StreamId;
+use crate::solicit::DEFAULT_SETTINGS;
+use crate::DataOrTrailers;
+use crate::Headers;
+
+/// ALPN protocol id of HTTP/1.1.
+pub(crate) const ALPN_HTTP_1_1: &[u8] = b"http/1.1";
+
+/// Max size of data parts passed to response handler.
+const READ_BUF_SIZE: usize = 16 * 1024;
+
+/// How response body is delimited (RFC 7230 section 3.3.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyLength {
+    Empty,
+    ContentLength(u64),
+    Chunked,
+    UntilEof,
+}
+
+struct ResponseHead {
+    headers: Headers,
+    body_length: BodyLength,
+    keep_alive: bool,
+}
+
+fn invalid_head(message: &str) -> error::Error {
+    error::Error::InvalidHttp1Head(message.to_owned())
+}
+
+fn unexpected_eof() -> error::Error {
+    let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected EOF");
+    error::Error::from(io_error)
+}
+
+/// Convert HTTP/2 request headers to HTTP/1.1 request.
+fn encode_request(headers: &Headers, body: Option<&Bytes>) -> Vec<u8> {
+    let method = headers.method();
+    let path = headers.get_opt(":path").unwrap_or("/");
+
+    let mut r = format!("{} {} HTTP/1.1\r\n", method, path).into_bytes();
+    if let Some(authority) = headers.get_opt(":authority") {
+        r.extend_from_slice(format!("Host: {}\r\n", authority).as_bytes());
+    }
+    for header in headers.iter() {
+        if header.is_preudo_header() || header.name() == "content-length" {
+            continue;
+        }
+        r.extend_from_slice(header.name().as_bytes());
+        r.extend_from_slice(b": ");
+        r.extend_from_slice(header.value());
+        r.extend_from_slice(b"\r\n");
+    }
+    let content_length = match body {
+        Some(body) => Some(body.len()),
+        None if method == "POST" || method == "PUT" || method == "PATCH" => Some(0),
+        None => None,
+    };
+    if let Some(content_length) = content_length {
+        r.extend_from_slice(format!("Content-Length: {}\r\n", content_length).as_bytes());
+    }
+    r.extend_from_slice(b"\r\n");
+    if let Some(body) = body {
+        r.extend_from_slice(body);
+    }
+    r
+}
+
+fn header_fields(head: &Http1Head, headers: &mut Headers) -> result::Result<()> {
+    for (name, value) in &head.headers {
+        if head.is_connection_specific(name) {
+            continue;
+        }
+        let name = Bytes::from(name.to_ascii_lowercase());
+        let header = Header::new_validate(name, Bytes::from(value.clone()))
+            .map_err(|_| invalid_head("invalid header"))?;
+        headers.add_header(header);
+    }
+    Ok(())
+}
+
+/// Read final response head, interim `1xx` responses are skipped.
+async fn read_response_head<I>(conn: &mut I, method: &str) -> result::Result<ResponseHead>
+where
+    I: AsyncRead + Unpin,
+{
+    let (head, status) = loop {
+        let mut buf = Vec::new();
+        read_head(conn, &mut buf).await?;
+        let head = Http1Head::parse(&buf)?;
+        match head.status() {
+            Some(status) if status < 200 => continue,
+            Some(status) => break (head, status),
+            None => return Err(invalid_head("invalid status line")),
+        }
+    };
+
+    let mut headers = Headers::new_status(status);
+    header_fields(&head, &mut headers)?;
+
+    let body_length = if method == "HEAD" || status == 204 || status == 304 {
+        BodyLength::Empty
+    } else if head.header_has_token("transfer-encoding", "chunked") {
+        BodyLength::Chunked
+    } else if let Some(content_length) = head.header("content-length") {
+        match content_length.parse() {
+            Ok(0) => BodyLength::Empty,
+            Ok(content_length) => BodyLength::ContentLength(content_length),
+            Err(_) => return Err(invalid_head("invalid content-length")),
+        }
+    } else {
+        BodyLength::UntilEof
+    };
+
+    let keep_alive = head.first_line.starts_with("HTTP/1.1 ")
+        && !head.header_has_token("connection", "close")
+        && body_length != BodyLength::UntilEof;
+
+    Ok(ResponseHead {
+        headers,
+        body_length,
+        keep_alive,
+    })
+}
+
+/// Read exactly `len` bytes, passing them to `part` in pieces.
+async fn read_exact_parts<I, F>(conn: &mut I, len: u64, part: &mut F) -> result::Result<()>
+where
+    I: AsyncRead + Unpin,
+    F: FnMut(Bytes, bool),
+{
+    let mut rem = len;
+    while rem > 0 {
+        let mut buf = vec![0; rem.min(READ_BUF_SIZE as u64) as usize];
+        let n = conn.read(&mut buf).await?;
+        if n == 0 {
+            return Err(unexpected_eof());
+        }
+        buf.truncate(n);
+        rem -= n as u64;
+        part(Bytes::from(buf), rem == 0);
+    }
+    Ok(())
+}
+
+/// Read response body, last part has `EndStream::Yes` or is trailers.
+async fn read_body<I, F>(conn: &mut I, body_length: BodyLength, mut part: F) -> result::Result<()>
+where
+    I: AsyncBufRead + Unpin,
+    F: FnMut(DataOrTrailers),
+{
+    let mut data = |data: Bytes, end_stream: bool| {
+        let end_stream = match end_stream {
+            true => EndStream::Yes,
+            false => EndStream::No,
+        };
+        part(DataOrTrailers::Data(data, end_stream))
+    };
+
+    match body_length {
+        BodyLength::Empty => {}
+        BodyLength::ContentLength(len) => read_exact_parts(conn, len, &mut data).await?,
+        BodyLength::UntilEof => loop {
+            let mut buf = vec![0; READ_BUF_SIZE];
+            let n = conn.read(&mut buf).await?;
+            buf.truncate(n);
+            data(Bytes::from(buf), n == 0);
+            if n == 0 {
+                break;
+            }
+        },
+        BodyLength::Chunked => loop {
+            let mut line = Vec::new();
+            (&mut *conn)
+                .take(MAX_HEAD_LEN as u64)
+                .read_until(b'\n', &mut line)
+                .await?;
+            if !line.ends_with(b"\n") {
+                if line.len() >= MAX_HEAD_LEN {
+                    return Err(invalid_head("chunk size line is too long"));
+                }
+                return Err(unexpected_eof());
+            }
+            let size = String::from_utf8_lossy(&line);
+            // chunk extensions are ignored
+            let size = size.split(';').next().unwrap().trim();
+            let size =
+                u64::from_str_radix(size, 16).map_err(|_| invalid_head("invalid chunk size"))?;
+
+            if size == 0 {
+                // last chunk line is followed by trailer section
+                read_head(conn, &mut line).await?;
+                let trailers = Http1Head::parse(&line)?;
+                if trailers.headers.is_empty() {
+                    data(Bytes::new(), true);
+                } else {
+                    let mut headers = Headers::new();
+                    header_fields(&trailers, &mut headers)?;
+                    part(DataOrTrailers::Trailers(headers));
+                }
+                break;
+            }
+
+            read_exact_parts(conn, size, &mut |bytes, _| data(bytes, false)).await?;
+            let mut crlf = [0; 2];
+            conn.read_exact(&mut crlf).await?;
+            if &crlf != b"\r\n" {
+                return Err(invalid_head("chunk is not terminated by CRLF"));
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Send request and pass response to handler, returns whether connection can be reused.
+async fn exchange<I>(
+    conn: &mut I,
+    headers: &Headers,
+    body: Option<&Bytes>,
+    handler: &mut Option<ClientResponseStreamHandlerHolder>,
+) -> result::Result<bool>
+where
+    I: AsyncBufRead + AsyncWrite + Unpin,
+{
+    conn.write_all(&encode_request(headers, body)).await?;
+
+    let head = read_response_head(conn, headers.method()).await?;
+    let keep_alive = head.keep_alive;
+    if let Some(handler) = handler {
+        // errors are ignored: response is read anyway to keep connection usable
+        drop(
+            handler
+                .0
+                .headers(head.headers, head.body_length == BodyLength::Empty),
+        );
+    }
+
+    read_body(conn, head.body_length, |part| {
+        if let Some(handler) = handler {
+            drop(match part {
+                DataOrTrailers::Data(data, end_stream) => {
+                    handler.0.data_frame(data, end_stream == EndStream::Yes)
+                }
+                DataOrTrailers::Trailers(trailers) => handler.0.trailers(trailers),
+            });
+        }
+    })
+    .await?;
+
+    Ok(keep_alive)
+}
+
+async fn process_start<I>(
+    conn: &mut I,
+    start: StartRequestMessage,
+    stream_id: StreamId,
+    write_tx: &ConnCommandSender<ClientTypes>,
+) -> result::Result<bool>
+where
+    I: AsyncBufRead + AsyncWrite + Unpin,
+{
+    let StartRequestMessage {
+        headers,
+        body,
+        trailers,
+        end_stream,
+        mut stream_handler,
+    } = start;
+
+    if !end_stream {
+        stream_handler.request_not_created(error::Error::StreamingRequestOverHttp1);
+        return Ok(true);
+    }
+    if trailers.is_some() {
+        debug!("request trailers are not sent over HTTP/1");
+    }
+
+    let req = ClientRequest {
+        common: CommonSender::new_done(stream_id),
+        write_tx: write_tx.clone(),
+        drop_callback: None,
+    };
+    let mut handler = None;
+    let resp = ClientResponse {
+        stream_handler: &mut handler,
+        in_window_size: DEFAULT_SETTINGS.initial_window_size,
+        stream_id,
+        to_write_tx: write_tx,
+    };
+    if let Err(e) = stream_handler.request_created(req, resp) {
+        warn!("client cancelled request: {:?}", e);
+        return Ok(true);
+    }
+
+    match exchange(conn, &headers, body.as_ref(), &mut handler).await {
+        Ok(keep_alive) => Ok(keep_alive),
+        Err(e) => {
+            warn!("HTTP/1 request failed: {:?}", e);
+            if let Some(mut handler) = handler {
+                drop(handler.0.error(e));
+            }
+            Err(error::Error::ConnDied)
+        }
+    }
+}
+
+/// Process requests sent to connection until peer closes connection.
+///
+/// Flow control, pings and other HTTP/2 commands are ignored.
+pub(crate) async fn run_http1_conn<I>(
+    conn: I,
+    mut write_rx: ConnCommandReceiver<ClientTypes>,
+    write_tx: ConnCommandSender<ClientTypes>,
+) -> result::Result<()>
+where
+    I: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut conn = BufReader::new(conn);
+    // streams ids are allocated only to identify requests
+    let mut stream_id = 1;
+    while let Some(message) = write_rx.next().await {
+        match message {
+            ClientToWriteMessage::Start(mut start) => {
+                let start = start.take();
+                if !process_start(&mut conn, start, stream_id, &write_tx).await? {
+                    debug!("HTTP/1 connection is not reusable");
+                    return Ok(());
+                }
+                stream_id += 2;
+            }
+            ClientToWriteMessage::WaitForHandshake(tx) => {
+                // ignore error
+                drop(tx.send(Ok(())));
+            }
+            ClientToWriteMessage::Common(CommonToWriteMessage::Goaway(..)) => {
+                // previous requests are complete, so connection is drained
+                debug!("closing HTTP/1 connection");
+                return Ok(());
+            }
+            ClientToWriteMessage::ExpectContinueTimeout(..) | ClientToWriteMessage::Common(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+
+    fn read_response(response: &[u8], method: &str) -> (Headers, Vec<DataOrTrailers>) {
+        executor::block_on(async {
+            let mut conn = response;
+            let head = read_response_head(&mut conn, method).await.expect("head");
+            let mut parts = Vec::new();
+            read_body(&mut conn, head.body_length, |p| parts.push(p))
+                .await
+                .expect("body");
+            assert!(conn.is_empty(), "{:?}", conn);
+            (head.headers, parts)
+        })
+    }
+
+    fn data(parts: &[DataOrTrailers]) -> (Vec<u8>, bool) {
+        let mut r = Vec::new();
+        let mut end = false;
+        for part in parts {
+            match part {
+                DataOrTrailers::Data(data, end_stream) => {
+                    assert!(!end);
+                    r.extend_from_slice(data);
+                    end = *end_stream == EndStream::Yes;
+                }
+                DataOrTrailers::Trailers(..) => end = true,
+            }
+        }
+        (r, end)
+    }
+
+    #[test]
+    fn encode() {
+        let mut headers = Headers::new_post("/upload");
+        headers.add(":scheme", "https");
+        headers.add(":authority", "example.com");
+        headers.add("content-type", "text/plain");
+        headers.add("content-length", "100");
+        let request = encode_request(&headers, Some(&Bytes::from_static(b"hello")));
+        assert_eq!(
+            &b"POST /upload HTTP/1.1\r\n\
+            Host: example.com\r\n\
+            content-type: text/plain\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            hello"[..],
+            &request[..]
+        );
+
+        let request = encode_request(&Headers::new_get("/"), None);
+        assert_eq!(&b"GET / HTTP/1.1\r\n\r\n"[..], &request[..]);
+    }
+
+    #[test]
+    fn content_length() {
+        let (headers, parts) = read_response(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+            HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: keep-alive\r\n\r\nhello",
+            "GET",
+        );
+        assert_eq!(200, headers.status());
+        assert_eq!(None, headers.get_opt("connection"));
+        assert_eq!((b"hello".to_vec(), true), data(&parts));
+    }
+
+    #[test]
+    fn chunked() {
+        let (headers, parts) = read_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+            "GET",
+        );
+        assert_eq!(None, headers.get_opt("transfer-encoding"));
+        assert_eq!((b"hello world".to_vec(), true), data(&parts));
+
+        let (_, parts) = read_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            2\r\nhi\r\n0\r\nGrpc-Status: 0\r\n\r\n",
+            "GET",
+        );
+        match parts.last() {
+            Some(DataOrTrailers::Trailers(trailers)) => {
+                assert_eq!("0", trailers.get("grpc-status"))
+            }
+            _ => panic!("expecting trailers"),
+        }
+        assert_eq!((b"hi".to_vec(), true), data(&parts));
+    }
+
+    #[test]
+    fn chunk_size_line_too_long() {
+        let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        response.extend(vec![b'0'; MAX_HEAD_LEN]);
+        response.extend_from_slice(b"5\r\nhello\r\n0\r\n\r\n");
+        executor::block_on(async {
+            let mut conn = &response[..];
+            let head = read_response_head(&mut conn, "GET").await.expect("head");
+            match read_body(&mut conn, head.body_length, drop).await {
+                Err(error::Error::InvalidHttp1Head(..)) => {}
+                r => panic!("unexpected: {:?}", r),
+            }
+        });
+    }
+
+    #[test]
+    fn no_body() {
+        for (response, method) in &[
+            (&b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n"[..], "HEAD"),
+            (b"HTTP/1.1 204 No Content\r\n\r\n", "GET"),
+            (b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", "GET"),
+        ] {
+            let (_, parts) = read_response(response, method);
+            assert!(parts.is_empty());
+        }
+    }
+
+    #[test]
+    fn until_eof() {
+        executor::block_on(async {
+            let mut conn = &b"HTTP/1.1 200 OK\r\n\r\nhello"[..];
+            let head = read_response_head(&mut conn, "GET").await.expect("head");
+            assert_eq!(BodyLength::UntilEof, head.body_length);
+            assert!(!head.keep_alive);
+        });
+        let (_, parts) = read_response(b"HTTP/1.0 200 OK\r\n\r\nhello", "GET");
+        assert_eq!((b"hello".to_vec(), true), data(&parts));
+    }
+}
@@ -0,0 +1,132 @@
+//! Client connections tunneled through HTTP/1.1 `CONNECT` proxy.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+
+use futures::Future;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Handle;
+
+use crate::error;
+use crate::h2c::base64url_encode;
+use crate::http1::read_head;
+use crate::http1::Http1Head;
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+
+/// `Proxy-Authorization` header value for `Basic` authentication scheme.
+pub fn basic_proxy_authorization(user: &str, password: &str) -> String {
+    // standard alphabet differs from URL-safe one only in two characters
+    let mut credentials: String = base64url_encode(format!("{}:{}", user, password).as_bytes())
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    while !credentials.len().is_multiple_of(4) {
+        credentials.push('=');
+    }
+    format!("Basic {}", credentials)
+}
+
+fn head_error(e: error::Error) -> io::Error {
+    match e {
+        error::Error::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, format!("proxy response: {}", e)),
+    }
+}
+
+/// Connects to proxy and asks it to open a tunnel to target `authority`.
+pub(crate) struct HttpProxyConnector {
+    pub proxy: AnySocketAddr,
+    /// `host:port` of the target server.
+    pub authority: String,
+    /// Value of `Proxy-Authorization` header.
+    pub proxy_authorization: Option<String>,
+}
+
+impl HttpProxyConnector {
+    fn connect_request(&self) -> Vec<u8> {
+        let mut request = format!(
+            "CONNECT {} HTTP/1.1\r\nHost: {}\r\n",
+            self.authority, self.authority
+        );
+        if let Some(proxy_authorization) = &self.proxy_authorization {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", proxy_authorization));
+        }
+        request.push_str("\r\n");
+        request.into_bytes()
+    }
+}
+
+impl fmt::Display for HttpProxyConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} via proxy {}", self.authority, self.proxy)
+    }
+}
+
+impl ToClientStream for HttpProxyConnector {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        let connect = self.proxy.connect(handle);
+        let request = self.connect_request();
+        Box::pin(async move {
+            let mut stream = connect.await?;
+            stream.write_all(&request).await?;
+
+            // bytes after the head belong to the tunnel, so read exactly the head
+            let mut buf = Vec::new();
+            read_head(&mut stream, &mut buf).await.map_err(head_error)?;
+            let head = Http1Head::parse(&buf).map_err(head_error)?;
+            match head.status() {
+                Some(status) if status / 100 == 2 => Ok(stream),
+                Some(status) => Err(io::Error::other(format!(
+                    "proxy rejected CONNECT with status {}",
+                    status
+                ))),
+                None => Err(head_error(error::Error::InvalidHttp1Head(
+                    "no status".to_owned(),
+                ))),
+            }
+        })
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        self.proxy.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        // RFC 7617 example
+        assert_eq!(
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+            basic_proxy_authorization("Aladdin", "open sesame")
+        );
+        assert_eq!("Basic Pz8/Pjo=", basic_proxy_authorization("???>", ""));
+    }
+
+    #[test]
+    fn request() {
+        let connector = HttpProxyConnector {
+            proxy: AnySocketAddr::Mem,
+            authority: "example.com:443".to_owned(),
+            proxy_authorization: Some("Basic eDp5".to_owned()),
+        };
+        assert_eq!(
+            &b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\
+            Proxy-Authorization: Basic eDp5\r\n\r\n"[..],
+            &connector.connect_request()[..]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Hooks applied to every client request.
+
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::Headers;
+use crate::Response;
+
+/// Observes and modifies requests sent by `Client`, e. g. to add
+/// authentication headers, trace requests or collect metrics.
+///
+/// Interceptors are registered with `ClientBuilder::add_interceptor`
+/// and form a chain: `before_send` is called in order of registration,
+/// response hooks are called in reverse order. Interceptors are invoked
+/// for each attempt of a retried request, but not for requests started
+/// with `ClientInterface::start_request_low_level`.
+///
+/// Hooks are called on the event loop, so they must not block.
+pub trait ClientInterceptor: Send + Sync + 'static {
+    /// Called before request is sent, may modify request headers.
+    fn before_send(&self, _request: &mut Headers) {}
+
+    /// Called when final response headers are received.
+    fn after_response_headers(&self, _request: &Headers, _response: &Headers) {}
+
+    /// Called once when response body is received completely (`error` is `None`),
+    /// or when request fails. If response body is dropped before the end,
+    /// `error` is `Error::CallerDied`.
+    fn after_response(&self, _request: &Headers, _error: Option<&Error>) {}
+}
+
+/// Invoke `after_response` hooks of `interceptors`.
+pub(crate) fn after_response(
+    interceptors: &[Arc<dyn ClientInterceptor>],
+    request: &Headers,
+    error: Option<&Error>,
+) {
+    for interceptor in interceptors.iter().rev() {
+        interceptor.after_response(request, error);
+    }
+}
+
+/// Invoke response hooks of `interceptors` when `response` to `request` arrives.
+pub(crate) fn intercept_response(
+    interceptors: Vec<Arc<dyn ClientInterceptor>>,
+    request: Headers,
+    response: Response,
+) -> Response {
+    response.map_result(move |r| match r {
+        Ok((headers, body)) => {
+            for interceptor in interceptors.iter().rev() {
+                interceptor.after_response_headers(&request, &headers);
+            }
+            let body = body.on_end(move |error| after_response(&interceptors, &request, error));
+            Ok((headers, body))
+        }
+        Err(e) => {
+            after_response(&interceptors, &request, Some(&e));
+            Err(e)
+        }
+    })
+}
@@ -0,0 +1,123 @@
+//! Request mirroring (shadowing) to a secondary server.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::client::increase_in_window::ClientIncreaseInWindow;
+use crate::client::req::ClientRequest;
+use crate::client::resp::ClientResponse;
+use crate::client::stream_handler::ClientResponseStreamHandler;
+use crate::client::stream_handler::ClientStreamCreatedHandler;
+use crate::error;
+use crate::result;
+use crate::Client;
+use crate::ClientInterface;
+use crate::ErrorCode;
+use crate::Headers;
+
+/// Predicate selecting requests to mirror.
+pub type MirrorFilter = Arc<dyn Fn(&Headers) -> bool + Send + Sync>;
+
+/// Sends copies of requests to a secondary client, for dark-launch
+/// testing of new backends.
+///
+/// Mirrored responses are read and discarded, mirror failures
+/// are logged and do not affect the original request.
+///
+/// Set `ClientBuilder::mirror` to mirror requests started with
+/// `Client::start_request_end_stream` and helpers built on it,
+/// or call `mirror` for individual requests.
+pub struct RequestMirror {
+    client: Arc<Client>,
+    /// Replace `:authority` of mirrored requests.
+    pub authority: Option<String>,
+    /// Mirror only requests for which filter returns `true`.
+    pub filter: Option<MirrorFilter>,
+}
+
+impl RequestMirror {
+    /// Mirror requests to given client.
+    pub fn new(client: Arc<Client>) -> RequestMirror {
+        RequestMirror {
+            client,
+            authority: None,
+            filter: None,
+        }
+    }
+
+    /// Send a copy of a request, ignoring the response.
+    pub fn mirror(&self, headers: &Headers, body: Option<Bytes>, trailers: Option<Headers>) {
+        if let Some(filter) = &self.filter {
+            if !filter(headers) {
+                return;
+            }
+        }
+
+        let mut headers = headers.clone();
+        if let Some(authority) = &self.authority {
+            headers.remove(":authority");
+            headers.add(":authority", authority.clone());
+        }
+
+        if let Err(e) = self.client.start_request_low_level(
+            headers,
+            body,
+            trailers,
+            true,
+            Box::new(MirrorStreamCreated),
+        ) {
+            debug!("failed to mirror request: {}", e);
+        }
+    }
+}
+
+struct MirrorStreamCreated;
+
+impl ClientStreamCreatedHandler for MirrorStreamCreated {
+    fn request_created(&mut self, _req: ClientRequest, resp: ClientResponse) -> result::Result<()> {
+        resp.register_stream_handler(|increase_in_window| {
+            (DiscardResponse { increase_in_window }, ())
+        });
+        Ok(())
+    }
+
+    fn request_not_created(&mut self, error: error::Error) {
+        debug!("failed to mirror request: {}", error);
+    }
+}
+
+/// Reads and drops response, keeping the window open.
+struct DiscardResponse {
+    increase_in_window: ClientIncreaseInWindow,
+}
+
+impl ClientResponseStreamHandler for DiscardResponse {
+    fn headers(&mut self, headers: Headers, _end_stream: bool) -> result::Result<()> {
+        debug!(
+            "mirrored request response status: {:?}",
+            headers.status_opt()
+        );
+        Ok(())
+    }
+
+    fn data_frame(&mut self, data: Bytes, _end_stream: bool) -> result::Result<()> {
+        self.increase_in_window
+            .data_frame_processed(data.len() as u32);
+        self.increase_in_window.increase_window_auto()
+    }
+
+    fn trailers(&mut self, _trailers: Headers) -> result::Result<()> {
+        Ok(())
+    }
+
+    fn rst(&mut self, error_code: ErrorCode) -> result::Result<()> {
+        debug!("mirrored request reset: {:?}", error_code);
+        Ok(())
+    }
+
+    fn error(&mut self, error: error::Error) -> result::Result<()> {
+        debug!("mirrored request failed: {}", error);
+        Ok(())
+    }
+}
@@ -1,16 +1,37 @@
+pub(crate) mod alt_svc;
+#[cfg(feature = "cache")]
+pub(crate) mod cache;
+pub(crate) mod coalesce;
 pub(crate) mod conf;
 pub(crate) mod conn;
+#[cfg(feature = "cookies")]
+pub(crate) mod cookie_jar;
+pub(crate) mod happy_eyeballs;
+pub(crate) mod headers_hook;
+pub(crate) mod http1;
+pub(crate) mod http_proxy;
 pub(crate) mod increase_in_window;
+pub(crate) mod interceptor;
+pub(crate) mod mirror;
+pub(crate) mod multi;
+pub(crate) mod push;
+pub(crate) mod redirect;
 pub(crate) mod req;
+pub(crate) mod request_builder;
+pub(crate) mod resolver;
 pub(crate) mod resp;
+pub(crate) mod retry;
+pub(crate) mod socks5;
 pub(crate) mod stream_handler;
 pub(crate) mod tls;
 pub(crate) mod types;
 
+use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
 use bytes::Bytes;
 
@@ -21,6 +42,7 @@ use futures::channel::oneshot;
 use futures::future;
 use futures::future::FutureExt;
 use futures::future::TryFutureExt;
+use futures::stream::Stream;
 use futures::stream::StreamExt;
 
 use tls_api::TlsConnector;
@@ -32,6 +54,18 @@ use crate::futures_misc::*;
 use crate::error;
 use crate::error::Error;
 use crate::result::Result;
+use crate::tls::connector_like;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::rustls::ClientAuthConnector;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::rustls::RustlsClientCertResolver;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::rustls::RustlsConnector;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::ClientCertificateResolver;
+use crate::tls::PeerCertificates;
+use crate::tls::TlsConnectorLike;
+use crate::tls::TlsSessionInfo;
 
 use crate::solicit::header::*;
 use crate::solicit::HttpScheme;
@@ -40,13 +74,42 @@ use crate::solicit_async::*;
 
 use crate::socket::AnySocketAddr;
 use crate::socket::ToClientStream;
+use crate::socket_io::IoConnector;
 
+use crate::client::alt_svc::AltSvcObserver;
+use crate::client::alt_svc::AltSvcSwitch;
+#[cfg(feature = "cache")]
+use crate::client::cache::HttpCache;
 use crate::client::conf::ClientConf;
 use crate::client::conn::ClientConn;
 use crate::client::conn::ClientConnCallbacks;
 use crate::client::conn::StartRequestMessage;
-
+#[cfg(feature = "cookies")]
+use crate::client::cookie_jar::CookieJar;
+use crate::solicit::frame::HttpFrame;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::window_size::MAX_WINDOW_SIZE;
+use crate::solicit::DEFAULT_SETTINGS;
+
+use crate::client::happy_eyeballs::interleave;
+use crate::client::happy_eyeballs::HappyEyeballsConnector;
+use crate::client::happy_eyeballs::DEFAULT_DELAY;
+use crate::client::headers_hook::RequestHeadersHook;
+use crate::client::http_proxy::HttpProxyConnector;
+use crate::client::interceptor::intercept_response;
+use crate::client::interceptor::ClientInterceptor;
+use crate::client::mirror::RequestMirror;
 use crate::client::req::ClientRequest;
+use crate::client::request_builder::RequestBuilder;
+use crate::client::resolver::Resolver;
+use crate::client::resolver::ResolvingConnector;
+use crate::client::resolver::SystemResolver;
+use crate::client::retry::RetryPolicy;
+use crate::client::socks5::Socks5Connector;
+use crate::context::ContextPropagation;
+use crate::context::RequestContext;
+use crate::futures_misc::read_stream::ReadStream;
+use crate::solicit::header::method::Method;
 
 use crate::client::stream_handler::ClientStreamCreatedHandler;
 pub use crate::client::tls::ClientTlsOption;
@@ -57,11 +120,17 @@ use crate::common::conn::ConnStateSnapshot;
 
 use crate::client::resp::ClientResponse;
 use crate::result;
+use crate::socket_named_pipe::SocketAddrNamedPipe;
 use crate::socket_unix::SocketAddrUnix;
 use crate::solicit::stream_id::StreamId;
+use crate::ErrorCode;
 use crate::Response;
+use crate::Tunnel;
 use std::fmt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::runtime::{Handle, Runtime};
+use tokio::time;
 
 /// Builder for HTTP/2 client.
 ///
@@ -70,8 +139,30 @@ use tokio::runtime::{Handle, Runtime};
 pub struct ClientBuilder<C: TlsConnector = tls_api_stub::TlsConnector> {
     pub event_loop: Option<Handle>,
     pub addr: Option<AnySocketAddr>,
-    pub tls: ClientTlsOption<C>,
+    /// All addresses resolved by `set_addr`, `addr` is the first of them.
+    addrs: Vec<SocketAddr>,
+    /// Resolves host specified with `set_host`, system resolver by default.
+    pub resolver: Option<Arc<dyn Resolver>>,
+    /// Custom transport used instead of `addr`.
+    pub connector: Option<Arc<dyn ToClientStream>>,
+    pub tls: ClientTlsOption,
     pub conf: ClientConf,
+    /// Send copies of requests to a secondary server.
+    pub mirror: Option<Arc<RequestMirror>>,
+    /// Compute request headers before each request is sent.
+    pub headers_hook: Option<Arc<dyn RequestHeadersHook>>,
+    /// Hooks applied to each request, see `add_interceptor`.
+    pub interceptors: Vec<Arc<dyn ClientInterceptor>>,
+    /// Notified about alternative service switching, see `ClientConf::alt_svc`.
+    pub alt_svc_observer: Option<Arc<dyn AltSvcObserver>>,
+    /// Store cookies from responses and send them with requests.
+    #[cfg(feature = "cookies")]
+    pub cookie_jar: Option<Arc<CookieJar>>,
+    /// Cache responses to `GET` and `HEAD` requests.
+    #[cfg(feature = "cache")]
+    pub cache: Option<Arc<HttpCache>>,
+    /// `tls_api` connector created by `set_tls`.
+    _tls_connector: PhantomData<fn() -> C>,
 }
 
 impl ClientBuilder<tls_api_stub::TlsConnector> {
@@ -80,18 +171,131 @@ impl ClientBuilder<tls_api_stub::TlsConnector> {
     }
 }
 
+#[cfg(feature = "tls-rustls")]
+impl ClientBuilder<tls_api_rustls::TlsConnector> {
+    /// New client builder using rustls, server certificates are verified
+    /// against Mozilla root certificates.
+    ///
+    /// TLS is enabled with `set_tls`.
+    pub fn new_rustls() -> ClientBuilder<tls_api_rustls::TlsConnector> {
+        ClientBuilder::new()
+    }
+
+    /// Enable TLS like `set_tls` with connector configured by caller
+    /// (e. g. with private root certificates), presenting certificate
+    /// selected by `resolver` when server requests client authentication.
+    ///
+    /// Requests fail with `Error::ClientCertificateRejected`
+    /// if server does not accept the certificate.
+    pub fn set_tls_client_auth<R: ClientCertificateResolver>(
+        &mut self,
+        host: &str,
+        mut tls_connector: tls_api_rustls::TlsConnectorBuilder,
+        resolver: R,
+    ) -> Result<()> {
+        self.set_alpn_protocols(&mut tls_connector)?;
+        tls_connector.config.client_auth_cert_resolver =
+            Arc::new(RustlsClientCertResolver(resolver));
+        let tls_connector = tls_connector.build()?;
+        self.set_tls_connector(
+            host,
+            ClientAuthConnector(RustlsConnector(tls_connector.config)),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tls-native")]
+impl ClientBuilder<tls_api_native_tls::TlsConnector> {
+    /// New client builder using platform TLS library, server certificates
+    /// are verified against the OS trust store.
+    ///
+    /// TLS is enabled with `set_tls`. Platform TLS does not negotiate ALPN,
+    /// so server must accept HTTP/2 without it.
+    pub fn new_native_tls() -> ClientBuilder<tls_api_native_tls::TlsConnector> {
+        ClientBuilder::new()
+    }
+}
+
+fn resolve_addr<S: ToSocketAddrs>(addr: S) -> Result<SocketAddr> {
+    // TODO: sync
+    let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+    if addrs.is_empty() {
+        return Err(Error::AddrResolvedToEmptyList);
+    } else if addrs.len() > 1 {
+        // TODO: allow multiple addresses
+        return Err(Error::AddrResolvedToMoreThanOneAddr(addrs));
+    }
+    Ok(addrs.into_iter().next().unwrap())
+}
+
 impl<C: TlsConnector> ClientBuilder<C> {
     /// Set the addr client connects to.
+    ///
+    /// If the addr resolves to several addresses, connection attempts
+    /// to them are raced, alternating IPv6 and IPv4 addresses
+    /// (see `ClientConf::happy_eyeballs_delay`).
     pub fn set_addr<S: ToSocketAddrs>(&mut self, addr: S) -> Result<()> {
         // TODO: sync
-        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        let addrs = interleave(addr.to_socket_addrs()?.collect());
         if addrs.is_empty() {
             return Err(Error::AddrResolvedToEmptyList);
-        } else if addrs.len() > 1 {
-            // TODO: allow multiple addresses
-            return Err(Error::AddrResolvedToMoreThanOneAddr(addrs));
         }
-        self.addr = Some(AnySocketAddr::Inet(addrs.into_iter().next().unwrap()));
+        self.addr = Some(AnySocketAddr::Inet(addrs[0]));
+        self.addrs = addrs;
+        Ok(())
+    }
+
+    /// Connect to `host`, which is resolved with `resolver`
+    /// before each connection. Connection attempts to resolved addresses
+    /// are raced like with `set_addr`.
+    pub fn set_host(&mut self, host: &str, port: u16) {
+        self.addr = Some(AnySocketAddr::Host(host.to_owned(), port));
+    }
+
+    /// Resolve host specified with `set_host` with given resolver.
+    pub fn set_resolver<R: Resolver>(&mut self, resolver: R) {
+        self.resolver = Some(Arc::new(resolver));
+    }
+
+    /// Connect through HTTP/1.1 proxy at `proxy_addr`, which is asked
+    /// to open a tunnel to `authority` (`host:port`) with `CONNECT` request.
+    ///
+    /// Target name is resolved by the proxy, so `set_addr` is not needed.
+    /// TLS enabled with `set_tls` is negotiated with the target through the tunnel.
+    /// `proxy_authorization` is sent as `Proxy-Authorization` header,
+    /// see `basic_proxy_authorization`.
+    pub fn set_http_proxy<S: ToSocketAddrs>(
+        &mut self,
+        proxy_addr: S,
+        authority: &str,
+        proxy_authorization: Option<String>,
+    ) -> Result<()> {
+        self.connector = Some(Arc::new(HttpProxyConnector {
+            proxy: AnySocketAddr::Inet(resolve_addr(proxy_addr)?),
+            authority: authority.to_owned(),
+            proxy_authorization,
+        }));
+        Ok(())
+    }
+
+    /// Connect through SOCKS5 proxy at `proxy_addr` to `authority` (`host:port`).
+    ///
+    /// Host names are resolved by the proxy, so `set_addr` is not needed.
+    /// Username/password authentication is offered if `credentials` are specified.
+    /// TLS enabled with `set_tls` is negotiated with the target through the proxy.
+    pub fn set_socks5_proxy<S: ToSocketAddrs>(
+        &mut self,
+        proxy_addr: S,
+        authority: &str,
+        credentials: Option<(String, String)>,
+    ) -> Result<()> {
+        let proxy = AnySocketAddr::Inet(resolve_addr(proxy_addr)?);
+        self.connector = Some(Arc::new(Socks5Connector::new(
+            proxy,
+            authority,
+            credentials,
+        )?));
         Ok(())
     }
 }
@@ -102,6 +306,14 @@ impl<C: TlsConnector> ClientBuilder<C> {
         self.addr = Some(AnySocketAddr::Unix(addr.into()));
         Ok(())
     }
+
+    /// Connect to Windows named pipe, e. g. `\\.\pipe\name`.
+    ///
+    /// Connection fails on other platforms.
+    pub fn set_named_pipe_addr<A: Into<SocketAddrNamedPipe>>(&mut self, addr: A) -> Result<()> {
+        self.addr = Some(AnySocketAddr::NamedPipe(addr.into()));
+        Ok(())
+    }
 }
 
 impl<C: TlsConnector> ClientBuilder<C> {
@@ -109,32 +321,159 @@ impl<C: TlsConnector> ClientBuilder<C> {
         ClientBuilder {
             event_loop: None,
             addr: None,
+            addrs: Vec::new(),
+            resolver: None,
+            connector: None,
             tls: ClientTlsOption::Plain,
             conf: ClientConf::new(),
+            mirror: None,
+            headers_hook: None,
+            interceptors: Vec::new(),
+            alt_svc_observer: None,
+            #[cfg(feature = "cookies")]
+            cookie_jar: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            _tls_connector: PhantomData,
         }
     }
 
     pub fn set_tls(&mut self, host: &str) -> Result<()> {
         let mut tls_connector = C::builder()?;
+        self.set_alpn_protocols(&mut tls_connector)?;
+
+        let tls_connector = tls_connector.build()?;
+        self.set_tls_connector(host, tls_connector);
+        Ok(())
+    }
 
+    fn set_alpn_protocols(&self, tls_connector: &mut C::Builder) -> Result<()> {
         if C::supports_alpn() {
-            // TODO: check negotiated protocol after connect
-            tls_connector.set_alpn_protocols(&[b"h2"])?;
+            let protocols = self.conf.alpn_protocols();
+            let protocols: Vec<&[u8]> = protocols.iter().map(Vec::as_slice).collect();
+            tls_connector.set_alpn_protocols(&protocols)?;
         }
+        Ok(())
+    }
 
-        let tls_connector = tls_connector.build()?;
+    /// Connect with TLS using any TLS stack,
+    /// `host` is used for server certificate verification.
+    ///
+    /// Connector is responsible for offering ALPN protocols.
+    pub fn set_tls_connector<T: TlsConnectorLike>(&mut self, host: &str, connector: T) {
+        self.tls = ClientTlsOption::Tls(host.to_owned(), connector_like(connector));
+    }
 
-        let tls_connector = Arc::new(tls_connector);
-        self.tls = ClientTlsOption::Tls(host.to_owned(), tls_connector);
-        Ok(())
+    /// Add interceptor to the end of the chain, see `ClientInterceptor`.
+    pub fn add_interceptor<I: ClientInterceptor>(&mut self, interceptor: I) {
+        self.interceptors.push(Arc::new(interceptor));
+    }
+
+    /// Use HTTP/1.1 if TLS server selects it, see `ClientConf::http1_fallback`.
+    ///
+    /// Must be called before `set_tls`.
+    pub fn set_http1_fallback(&mut self) {
+        self.conf.http1_fallback = Some(true);
+    }
+
+    /// Connect to plain HTTP/1.1 servers with `Upgrade: h2c`, see `ClientConf::h2c_upgrade`.
+    ///
+    /// Ignored for TLS connections.
+    pub fn set_h2c_upgrade(&mut self) {
+        self.conf.h2c_upgrade = Some(true);
+    }
+
+    /// Alternative clients share TLS and configuration of this client,
+    /// so alternatives must present certificate of the origin host.
+    ///
+    /// Alternative hosts are resolved with `resolver` when connecting.
+    fn alt_svc_switch(&self, origin_host: String) -> AltSvcSwitch {
+        let event_loop = self.event_loop.clone();
+        let resolver = self.resolver.clone();
+        let tls = self.tls.clone();
+        let mut conf = self.conf.clone();
+        conf.alt_svc = None;
+        let headers_hook = self.headers_hook.clone();
+        let interceptors = self.interceptors.clone();
+        #[cfg(feature = "cookies")]
+        let cookie_jar = self.cookie_jar.clone();
+        let protocol = match self.tls.http_scheme() {
+            HttpScheme::Https => "h2",
+            HttpScheme::Http => "h2c",
+        };
+        AltSvcSwitch::new(
+            protocol,
+            Box::new(move |alt| {
+                let mut client = ClientBuilder::<C>::new();
+                client.event_loop = event_loop.clone();
+                client.resolver = resolver.clone();
+                client.tls = tls.clone();
+                client.conf = conf.clone();
+                client.headers_hook = headers_hook.clone();
+                client.interceptors = interceptors.clone();
+                #[cfg(feature = "cookies")]
+                {
+                    client.cookie_jar = cookie_jar.clone();
+                }
+                let host = alt.host.as_ref().unwrap_or(&origin_host);
+                client.set_host(host, alt.port);
+                client.build()
+            }),
+            self.alt_svc_observer.clone(),
+        )
     }
 
     pub fn build(self) -> Result<Client> {
-        let addr = self.addr.expect("addr is not specified");
-        let addr_copy = addr.clone();
+        // Host name of the origin used for alternatives on the same host
+        let origin_host = match (&self.addr, &self.tls) {
+            (Some(AnySocketAddr::Host(host, _)), _) => Some(host.clone()),
+            (Some(AnySocketAddr::Inet(_)), ClientTlsOption::Tls(host, _)) => Some(host.clone()),
+            (Some(AnySocketAddr::Inet(origin)), ClientTlsOption::Plain) => {
+                Some(origin.ip().to_string())
+            }
+            _ => None,
+        };
+        let alt_svc = match origin_host {
+            Some(origin_host) if self.conf.alt_svc == Some(true) => {
+                Some(Arc::new(self.alt_svc_switch(origin_host)))
+            }
+            _ => None,
+        };
+
+        let connector: Arc<dyn ToClientStream> = match (self.connector, self.addr) {
+            (Some(connector), _) => connector,
+            // `addr` is not overwritten after `set_addr`
+            (None, Some(AnySocketAddr::Inet(addr)))
+                if self.addrs.len() > 1 && self.addrs[0] == addr =>
+            {
+                Arc::new(HappyEyeballsConnector {
+                    addrs: self.addrs,
+                    delay: self.conf.happy_eyeballs_delay.unwrap_or(DEFAULT_DELAY),
+                })
+            }
+            (None, Some(AnySocketAddr::Host(host, port))) => Arc::new(ResolvingConnector::new(
+                self.resolver.unwrap_or_else(|| Arc::new(SystemResolver)),
+                &host,
+                port,
+                self.conf.happy_eyeballs_delay.unwrap_or(DEFAULT_DELAY),
+            )),
+            (None, addr) => Arc::new(addr.expect("addr is not specified")),
+        };
+        let addr = connector.socket_addr();
+        let addr_copy = connector;
 
         let http_scheme = self.tls.http_scheme();
 
+        let default_headers = self.conf.default_headers.clone().map(Arc::new);
+        let max_redirects = self.conf.max_redirects;
+        let retry_policy = self.conf.retry_policy.clone();
+        let context_propagation = self
+            .conf
+            .common
+            .context_propagation
+            .clone()
+            .unwrap_or_default();
+
         // Create a channel to receive shutdown signal.
         let (shutdown_signal, shutdown_future) = shutdown_signal();
 
@@ -153,14 +492,16 @@ impl<C: TlsConnector> ClientBuilder<C> {
             remote.spawn(future::lazy(move |_cx| {
                 spawn_client_event_loop(
                     handle,
-                    shutdown_future,
                     addr_copy,
                     tls,
                     conf,
-                    done_tx,
-                    controller_tx,
-                    controller_rx,
-                    client_died_error_holder_copy,
+                    ClientLoopChannels {
+                        shutdown_future,
+                        done_tx,
+                        controller_tx,
+                        controller_rx,
+                        client_died_error_holder: client_died_error_holder_copy,
+                    },
                 )
             }));
             Completion::Rx(done_rx)
@@ -182,14 +523,16 @@ impl<C: TlsConnector> ClientBuilder<C> {
 
                     spawn_client_event_loop(
                         lp.handle().clone(),
-                        shutdown_future,
                         addr_copy,
                         tls,
                         conf,
-                        done_tx,
-                        controller_tx,
-                        controller_rx,
-                        client_died_error_holder_copy,
+                        ClientLoopChannels {
+                            shutdown_future,
+                            done_tx,
+                            controller_tx,
+                            controller_rx,
+                            client_died_error_holder: client_died_error_holder_copy,
+                        },
                     );
 
                     lp.block_on(done_rx).expect("run");
@@ -205,6 +548,18 @@ impl<C: TlsConnector> ClientBuilder<C> {
             shutdown: shutdown_signal,
             client_died_error_holder,
             addr,
+            retry_policy,
+            max_redirects,
+            mirror: self.mirror,
+            headers_hook: self.headers_hook,
+            interceptors: self.interceptors,
+            alt_svc,
+            default_headers,
+            context_propagation,
+            #[cfg(feature = "cookies")]
+            cookie_jar: self.cookie_jar,
+            #[cfg(feature = "cache")]
+            cache: self.cache,
         })
     }
 }
@@ -227,6 +582,18 @@ pub struct Client {
     shutdown: ShutdownSignal,
     client_died_error_holder: SomethingDiedErrorHolder<ClientDiedType>,
     addr: AnySocketAddr,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
+    max_redirects: Option<u32>,
+    mirror: Option<Arc<RequestMirror>>,
+    headers_hook: Option<Arc<dyn RequestHeadersHook>>,
+    interceptors: Vec<Arc<dyn ClientInterceptor>>,
+    alt_svc: Option<Arc<AltSvcSwitch>>,
+    default_headers: Option<Arc<Headers>>,
+    context_propagation: ContextPropagation,
+    #[cfg(feature = "cookies")]
+    cookie_jar: Option<Arc<CookieJar>>,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<HttpCache>>,
 }
 
 impl fmt::Debug for Client {
@@ -274,13 +641,24 @@ impl Client {
         client.build()
     }
 
+    /// Create a new client speaking HTTP/2 over an already established stream,
+    /// for example QUIC stream, SSH channel or custom tunnel.
+    ///
+    /// No TLS or protocol negotiation is performed. The stream is used for a single
+    /// connection: client does not reconnect, so requests fail after it is closed.
+    pub fn new_from_io<I>(io: I, conf: ClientConf) -> Result<Client>
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    {
+        let mut client = ClientBuilder::new_plain();
+        client.conf = conf;
+        client.connector = Some(Arc::new(IoConnector::new(io)));
+        client.build()
+    }
+
     /// Connect to server using plain or TLS protocol depending on `tls` parameter.
-    pub fn new_expl<C: TlsConnector>(
-        addr: &SocketAddr,
-        tls: ClientTlsOption<C>,
-        conf: ClientConf,
-    ) -> Result<Client> {
-        let mut client = ClientBuilder::new();
+    pub fn new_expl(addr: &SocketAddr, tls: ClientTlsOption, conf: ClientConf) -> Result<Client> {
+        let mut client = ClientBuilder::new_plain();
         client.addr = Some(AnySocketAddr::Inet(addr.clone()));
         client.tls = tls;
         client.conf = conf;
@@ -294,56 +672,162 @@ impl Client {
         trailers: Option<Headers>,
         end_stream: bool,
     ) -> HttpFutureSend<(ClientRequest, Response)> {
-        let (tx, rx) = oneshot::channel();
+        self.request_starter()
+            .start_request(headers, body, trailers, end_stream)
+    }
 
-        struct Impl {
-            tx: Option<oneshot::Sender<(ClientRequest, Response)>>,
+    /// Open a tunnel to `authority` (`host:port`) with `CONNECT` request.
+    ///
+    /// Bytes written to and read from the tunnel are carried in `DATA` frames.
+    /// Fails with `Error::ConnectRejected` if server responded with non-2xx status.
+    pub fn start_connect(&self, authority: &str) -> HttpFutureSend<Tunnel> {
+        let headers = Headers::from_vec(vec![
+            Header::new(":method", "CONNECT"),
+            Header::new(":authority", authority.to_owned()),
+        ]);
+
+        let start = self.start_request(headers, None, None, false);
+        Box::pin(async move {
+            let (sender, response) = start.await?;
+            let (headers, recv) = response.0.await?;
+            let status = headers.status();
+            if status / 100 != 2 {
+                return Err(Error::ConnectRejected(status));
+            }
+            Ok(Tunnel::new_client(sender, recv))
+        })
+    }
+
+    pub(crate) fn request_starter(&self) -> RequestStarter {
+        RequestStarter {
+            controller_tx: self.controller_tx.clone(),
+            client_died_error_holder: self.client_died_error_holder.clone(),
+            headers_hook: self.headers_hook.clone(),
+            interceptors: self.interceptors.clone(),
+            default_headers: self.default_headers.clone(),
+            #[cfg(feature = "cookies")]
+            cookie_jar: self.cookie_jar.clone(),
         }
+    }
 
-        impl ClientStreamCreatedHandler for Impl {
-            fn request_created(
-                &mut self,
-                req: ClientRequest,
-                resp: ClientResponse,
-            ) -> result::Result<()> {
-                let tx = self.tx.take().unwrap();
+    /// Start request with context propagated in request headers.
+    pub fn start_request_with_context(
+        &self,
+        context: &RequestContext,
+        mut headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+        end_stream: bool,
+    ) -> HttpFutureSend<(ClientRequest, Response)> {
+        self.context_propagation.inject(context, &mut headers);
+        self.start_request(headers, body, trailers, end_stream)
+    }
 
-                if let Err(_) = tx.send((req, resp.make_stream())) {
-                    return Err(error::Error::CallerDied);
-                }
+    /// Like `start_request_end_stream`, but with context propagated
+    /// in request headers.
+    pub fn start_request_end_stream_with_context(
+        &self,
+        context: &RequestContext,
+        mut headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        self.context_propagation.inject(context, &mut headers);
+        self.start_request_end_stream(headers, body, trailers)
+    }
 
-                Ok(())
+    /// Cookie jar configured in `ClientBuilder`.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_jar(&self) -> Option<&Arc<CookieJar>> {
+        self.cookie_jar.as_ref()
+    }
+
+    /// HTTP cache configured in `ClientBuilder`.
+    #[cfg(feature = "cache")]
+    pub fn cache(&self) -> Option<&Arc<HttpCache>> {
+        self.cache.as_ref()
+    }
+
+    pub fn start_request_end_stream(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        #[cfg(feature = "cache")]
+        {
+            if let Some(cache) = &self.cache {
+                return cache.start_request_end_stream(self, headers, body, trailers);
             }
         }
+        self.start_request_end_stream_uncached(headers, body, trailers)
+    }
 
-        if let Err(e) = self.start_request_low_level(
-            headers,
-            body,
-            trailers,
-            end_stream,
-            Box::new(Impl { tx: Some(tx) }),
-        ) {
-            return Box::pin(future::err(e));
+    pub(crate) fn start_request_end_stream_uncached(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        if let Some(mirror) = &self.mirror {
+            mirror.mirror(&headers, body.clone(), trailers.clone());
         }
 
-        let client_error = self.client_died_error_holder.clone();
-        let resp_rx = rx.map_err(move |oneshot::Canceled| client_error.error());
-
-        Box::pin(resp_rx)
+        if let Some(alt_svc) = &self.alt_svc {
+            return alt_svc.start_request_end_stream(self, headers, body, trailers);
+        }
+        self.start_request_end_stream_direct(headers, body, trailers)
     }
 
-    pub fn start_request_end_stream(
+    /// Start request on this client connection, with retries,
+    /// following same-origin redirects if enabled.
+    pub(crate) fn start_request_end_stream_direct(
         &self,
         headers: Headers,
         body: Option<Bytes>,
         trailers: Option<Headers>,
     ) -> Response {
-        Response::new(
-            self.start_request(headers, body, trailers, true)
-                .and_then(move |(_sender, response)| response),
+        let starter = self.request_starter();
+        let retry_policy = self.retry_policy.clone();
+        let max_redirects = match self.max_redirects {
+            Some(max_redirects) => max_redirects,
+            None => return start_with_retries(&starter, &retry_policy, headers, body, trailers),
+        };
+        let response = start_with_retries(
+            &starter,
+            &retry_policy,
+            headers.clone(),
+            body.clone(),
+            trailers.clone(),
+        );
+        redirect::follow(
+            response,
+            headers,
+            body,
+            trailers,
+            max_redirects,
+            move |next| {
+                // client is connected to a single origin
+                if next.cross_origin {
+                    return None;
+                }
+                Some(start_with_retries(
+                    &starter,
+                    &retry_policy,
+                    next.headers.clone(),
+                    next.body.clone(),
+                    next.trailers.clone(),
+                ))
+            },
         )
     }
 
+    /// Construct a request with given method and path (with optional query),
+    /// the request is started with `RequestBuilder::send`.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        RequestBuilder::new(self, method, path)
+    }
+
     /// Start HTTP/2 `GET` request.
     pub fn start_get(&self, path: &str, authority: &str) -> Response {
         let headers = Headers::from_vec(vec![
@@ -355,6 +839,75 @@ impl Client {
         self.start_request_end_stream(headers, None, None)
     }
 
+    /// Start request converted from `http` crate request.
+    ///
+    /// Request URI may be absolute or contain only path, then `:authority`
+    /// is taken from `host` header and `:scheme` is the client scheme.
+    #[cfg(feature = "http")]
+    pub fn start_http_request(&self, request: http::Request<Bytes>) -> Response {
+        let (parts, body) = request.into_parts();
+        let mut headers = Headers::from(&parts);
+        if headers.get_opt(":authority").is_none() {
+            return Response::err(Error::InvalidRequestTarget(parts.uri.to_string()));
+        }
+        if headers.get_opt(":scheme").is_none() && !headers.is_connect() {
+            headers.add(":scheme", self.http_scheme.as_bytes());
+        }
+        let body = if body.is_empty() { None } else { Some(body) };
+        self.start_request_end_stream(headers, body, None)
+    }
+
+    /// Like `start_request_end_stream`, but the request is reset with `CANCEL`
+    /// and fails with `Error::DeadlineExceeded` unless the response,
+    /// including body, is received within `timeout`.
+    ///
+    /// Request is sent once: retry policy, cache and alternative services
+    /// are not applied.
+    pub fn start_request_end_stream_with_deadline(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+        timeout: Duration,
+    ) -> Response {
+        let deadline = time::Instant::now() + timeout;
+        let start = self.start_request(headers, body, trailers, true);
+        Response::new_with_informational(move |informational| async move {
+            let (req, response) = match time::timeout_at(deadline, start).await {
+                Ok(r) => r?,
+                Err(_) => return Err(Error::DeadlineExceeded),
+            };
+            let reset = req.reset_fn();
+            match time::timeout_at(deadline, response.forward_informational(informational)).await {
+                Ok(r) => {
+                    let (headers, body) = r?;
+                    Ok((headers, body.with_deadline(deadline)))
+                }
+                Err(_) => {
+                    reset(ErrorCode::Cancel);
+                    Err(Error::DeadlineExceeded)
+                }
+            }
+        })
+    }
+
+    /// Start HTTP/2 `GET` request which fails unless completed within `timeout`,
+    /// see `start_request_end_stream_with_deadline`.
+    pub fn start_get_with_deadline(
+        &self,
+        path: &str,
+        authority: &str,
+        timeout: Duration,
+    ) -> Response {
+        let headers = Headers::from_vec(vec![
+            Header::new(":method", "GET"),
+            Header::new(":path", path.to_owned()),
+            Header::new(":authority", authority.to_owned()),
+            Header::new(":scheme", self.http_scheme.as_bytes()),
+        ]);
+        self.start_request_end_stream_with_deadline(headers, None, None, timeout)
+    }
+
     /// Start HTTP/2 `POST` request.
     pub fn start_post(&self, path: &str, authority: &str, body: Bytes) -> Response {
         let headers = Headers::from_vec(vec![
@@ -366,6 +919,34 @@ impl Client {
         self.start_request_end_stream(headers, Some(body), None)
     }
 
+    /// Start HTTP/2 `POST` request with body produced by `body` stream.
+    ///
+    /// The stream is polled only when stream and connection flow control
+    /// windows allow to send data. If the stream fails, the request is reset.
+    pub fn start_post_stream<S>(&self, path: &str, authority: &str, body: S) -> Response
+    where
+        S: Stream<Item = result::Result<Bytes>> + Send + 'static,
+    {
+        let start = self.start_post_sink(path, authority);
+        Response::new_with_informational(move |informational| async move {
+            let (mut req, response) = start.await?;
+            req.pull_bytes_from_stream(body)?;
+            response.forward_informational(informational).await
+        })
+    }
+
+    /// Start HTTP/2 `POST` request with body read from `body` until EOF,
+    /// like `start_post_stream`.
+    pub fn start_post_read<R>(&self, path: &str, authority: &str, body: R) -> Response
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let chunk_size = DEFAULT_SETTINGS.max_frame_size as usize;
+        self.start_post_stream(path, authority, ReadStream::new(body, chunk_size))
+    }
+
+    /// Start HTTP/2 `POST` request with body sent later with returned `ClientRequest`,
+    /// the request is finished with `send_data_end_of_stream` or `send_trailers`.
     pub fn start_post_sink(
         &self,
         path: &str,
@@ -380,6 +961,19 @@ impl Client {
         self.start_request(headers, None, None, false)
     }
 
+    pub(crate) fn http_scheme(&self) -> HttpScheme {
+        self.http_scheme
+    }
+
+    /// `:authority` of requests without explicit authority:
+    /// address the client connects to, `localhost` for non-network transports.
+    pub(crate) fn default_authority(&self) -> String {
+        match &self.addr {
+            AnySocketAddr::Inet(..) | AnySocketAddr::Host(..) => self.addr.to_string(),
+            _ => "localhost".to_owned(),
+        }
+    }
+
     /// For tests
     #[doc(hidden)]
     pub fn dump_state(&self) -> HttpFutureSend<ConnStateSnapshot> {
@@ -406,6 +1000,284 @@ impl Client {
                 .and_then(|r| future::ready(r)),
         )
     }
+
+    /// Wait for connection and return certificates presented by server
+    /// during TLS handshake.
+    ///
+    /// `None` for plain connections or if TLS implementation
+    /// does not expose certificates.
+    pub fn peer_certificates(&self) -> HttpFutureSend<Option<PeerCertificates>> {
+        let connect = self.wait_for_connect();
+        let controller_tx = self.controller_tx.clone();
+        Box::pin(async move {
+            connect.await?;
+            let (tx, rx) = oneshot::channel();
+            controller_tx
+                .unbounded_send(ControllerCommand::PeerCertificates(tx))
+                .map_err(|_| error::Error::ClientControllerDied)?;
+            rx.await.map_err(|_| error::Error::ConnDied)
+        })
+    }
+
+    /// Wait for connection and return ALPN protocol, TLS version,
+    /// cipher suite and SNI negotiated during TLS handshake.
+    ///
+    /// `None` for plain connections.
+    pub fn tls_session(&self) -> HttpFutureSend<Option<TlsSessionInfo>> {
+        let connect = self.wait_for_connect();
+        let controller_tx = self.controller_tx.clone();
+        Box::pin(async move {
+            connect.await?;
+            let (tx, rx) = oneshot::channel();
+            controller_tx
+                .unbounded_send(ControllerCommand::TlsSession(tx))
+                .map_err(|_| error::Error::ClientControllerDied)?;
+            rx.await.map_err(|_| error::Error::ConnDied)
+        })
+    }
+
+    /// Send `PING` over current connection and wait for ack,
+    /// return measured round-trip time.
+    ///
+    /// Each measurement also updates smoothed round-trip time
+    /// of the connection (see `ConnStateSnapshot::rtt`).
+    pub fn ping(&self) -> HttpFutureSend<Duration> {
+        let (tx, rx) = oneshot::channel();
+        // ignore error
+        drop(
+            self.controller_tx
+                .unbounded_send(ControllerCommand::Ping(tx)),
+        );
+        Box::pin(
+            rx.map_err(|_| error::Error::ConnDied)
+                .and_then(future::ready),
+        )
+    }
+
+    /// Change our settings on the current connection.
+    ///
+    /// `SETTINGS` frame is sent to the server, and returned future resolves
+    /// when the server acknowledges it. New values (e. g. initial window size,
+    /// max frame size or HPACK table size) are applied to the connection
+    /// and all its streams at once when ack is received.
+    ///
+    /// Settings are not preserved across reconnects, new connections
+    /// use settings from `ClientConf`.
+    pub fn update_settings(&self, settings: Vec<HttpSetting>) -> HttpFutureSend<()> {
+        for &setting in &settings {
+            let valid = match setting {
+                HttpSetting::InitialWindowSize(size) => size <= MAX_WINDOW_SIZE,
+                HttpSetting::MaxFrameSize(size) => (0x4000..0x100_0000).contains(&size),
+                _ => true,
+            };
+            if !valid {
+                return Box::pin(future::err(error::Error::InvalidSetting(setting)));
+            }
+        }
+
+        let (tx, rx) = oneshot::channel();
+        if self
+            .controller_tx
+            .unbounded_send(ControllerCommand::UpdateSettings(settings, tx))
+            .is_err()
+        {
+            return Box::pin(future::err(error::Error::ClientControllerDied));
+        }
+        Box::pin(
+            rx.map_err(|_| error::Error::ConnDied)
+                .and_then(future::ready),
+        )
+    }
+
+    /// Send `GOAWAY` frame with given error code and debug data.
+    ///
+    /// If `graceful` is true, existing streams are allowed to complete,
+    /// and connection is closed after that, otherwise connection is closed
+    /// right after `GOAWAY` is sent, and existing streams fail.
+    ///
+    /// New requests are sent over a new connection.
+    pub fn send_goaway(
+        &self,
+        error_code: ErrorCode,
+        debug_data: Bytes,
+        graceful: bool,
+    ) -> Result<()> {
+        self.controller_tx
+            .unbounded_send(ControllerCommand::SendGoaway(
+                error_code, debug_data, graceful,
+            ))
+            .map_err(|_| error::Error::ClientControllerDied)
+    }
+
+    /// Stop sending new requests over the current connection.
+    ///
+    /// Graceful `GOAWAY` is sent, returned future resolves when requests
+    /// in flight complete and the connection is closed. New requests
+    /// are sent over a new connection, so this can be used to rotate
+    /// connections, or to finish requests before the client is dropped.
+    pub fn drain(&self) -> HttpFutureSend<()> {
+        let (tx, rx) = oneshot::channel();
+        if self
+            .controller_tx
+            .unbounded_send(ControllerCommand::Drain(tx))
+            .is_err()
+        {
+            return Box::pin(future::err(error::Error::ClientControllerDied));
+        }
+        Box::pin(rx.map_err(|_| error::Error::ClientControllerDied))
+    }
+
+    /// Write a pre-built frame to the current connection.
+    ///
+    /// This is an escape hatch for protocol experiments and conformance
+    /// testing: the frame bypasses stream state, flow control and HPACK
+    /// encoder, so peer may treat it as a protocol error.
+    pub fn send_raw_frame(&self, frame: HttpFrame) -> Result<()> {
+        self.controller_tx
+            .unbounded_send(ControllerCommand::SendRawFrame(frame))
+            .map_err(|_| error::Error::ClientControllerDied)
+    }
+
+    /// Establish the connection before the first request is made.
+    ///
+    /// Returned future resolves when TCP (and TLS) connection is established
+    /// and HTTP/2 preface is sent. If `ping` is true, a `PING` frame is sent
+    /// and the future resolves only after peer acknowledges it, so the peer
+    /// is known to be alive and to have processed our preface.
+    ///
+    /// Client maintains single connection, so that connection is warmed up.
+    pub fn prewarm(&self, ping: bool) -> HttpFutureSend<()> {
+        let connect = self.wait_for_connect();
+        if !ping {
+            return connect;
+        }
+        let ping = self.ping();
+        Box::pin(connect.and_then(|()| ping.map_ok(|_rtt| ())))
+    }
+}
+
+/// Part of `Client` needed to start requests, used to start
+/// request attempts after `Client` call returned.
+#[derive(Clone)]
+pub(crate) struct RequestStarter {
+    controller_tx: UnboundedSender<ControllerCommand>,
+    client_died_error_holder: SomethingDiedErrorHolder<ClientDiedType>,
+    headers_hook: Option<Arc<dyn RequestHeadersHook>>,
+    interceptors: Vec<Arc<dyn ClientInterceptor>>,
+    default_headers: Option<Arc<Headers>>,
+    #[cfg(feature = "cookies")]
+    cookie_jar: Option<Arc<CookieJar>>,
+}
+
+impl RequestStarter {
+    pub fn start_request(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+        end_stream: bool,
+    ) -> HttpFutureSend<(ClientRequest, Response)> {
+        if let Some(headers_hook) = &self.headers_hook {
+            let headers = headers_hook.apply(headers);
+            let starter = RequestStarter {
+                headers_hook: None,
+                ..self.clone()
+            };
+            return Box::pin(async move {
+                let headers = headers.await?;
+                starter
+                    .start_request(headers, body, trailers, end_stream)
+                    .await
+            });
+        }
+
+        let mut headers = headers;
+        for interceptor in &self.interceptors {
+            interceptor.before_send(&mut headers);
+        }
+        let intercepted = if self.interceptors.is_empty() {
+            None
+        } else {
+            Some((self.interceptors.clone(), headers.clone()))
+        };
+
+        #[cfg(feature = "cookies")]
+        let cookie_jar = self.cookie_jar.clone().map(|cookie_jar| {
+            cookie_jar.add_cookie_header(&mut headers);
+            (cookie_jar, headers.clone())
+        });
+
+        let (tx, rx) = oneshot::channel();
+
+        struct Impl {
+            tx: Option<oneshot::Sender<result::Result<(ClientRequest, Response)>>>,
+        }
+
+        impl ClientStreamCreatedHandler for Impl {
+            fn request_created(
+                &mut self,
+                req: ClientRequest,
+                resp: ClientResponse,
+            ) -> result::Result<()> {
+                let tx = self.tx.take().unwrap();
+
+                if tx.send(Ok((req, resp.make_stream()))).is_err() {
+                    return Err(error::Error::CallerDied);
+                }
+
+                Ok(())
+            }
+
+            fn request_not_created(&mut self, error: error::Error) {
+                if let Some(tx) = self.tx.take() {
+                    // ignore error, caller may be dead
+                    drop(tx.send(Err(error)));
+                }
+            }
+        }
+
+        if let Err(e) = self.start_request_low_level(
+            headers,
+            body,
+            trailers,
+            end_stream,
+            Box::new(Impl { tx: Some(tx) }),
+        ) {
+            return Box::pin(future::err(e));
+        }
+
+        let client_error = self.client_died_error_holder.clone();
+        let resp_rx = rx
+            .map_err(move |oneshot::Canceled| client_error.error())
+            .and_then(future::ready);
+
+        #[cfg(feature = "cookies")]
+        let resp_rx = resp_rx.map_ok(move |(req, resp)| match cookie_jar {
+            Some((cookie_jar, req_headers)) => {
+                let resp = resp.map_ok(move |(headers, stream)| {
+                    cookie_jar.store_response(&req_headers, &headers);
+                    (headers, stream)
+                });
+                (req, resp)
+            }
+            None => (req, resp),
+        });
+
+        match intercepted {
+            Some((interceptors, req_headers)) => Box::pin(resp_rx.then(move |r| {
+                future::ready(match r {
+                    Ok((req, resp)) => {
+                        Ok((req, intercept_response(interceptors, req_headers, resp)))
+                    }
+                    Err(e) => {
+                        interceptor::after_response(&interceptors, &req_headers, Some(&e));
+                        Err(e)
+                    }
+                })
+            })),
+            None => Box::pin(resp_rx),
+        }
+    }
 }
 
 pub trait ClientInterface {
@@ -429,6 +1301,29 @@ impl ClientInterface for Client {
         end_stream: bool,
         stream_handler: Box<dyn ClientStreamCreatedHandler>,
     ) -> result::Result<()> {
+        self.request_starter().start_request_low_level(
+            headers,
+            body,
+            trailers,
+            end_stream,
+            stream_handler,
+        )
+    }
+}
+
+impl ClientInterface for RequestStarter {
+    fn start_request_low_level(
+        &self,
+        mut headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+        end_stream: bool,
+        stream_handler: Box<dyn ClientStreamCreatedHandler>,
+    ) -> result::Result<()> {
+        if let Some(default_headers) = &self.default_headers {
+            headers.add_missing(default_headers);
+        }
+
         let start = StartRequestMessage {
             headers,
             body,
@@ -449,25 +1344,82 @@ impl ClientInterface for Client {
     }
 }
 
+/// Start request, retrying it as allowed by `retry_policy`.
+fn start_with_retries(
+    starter: &RequestStarter,
+    retry_policy: &Option<Arc<dyn RetryPolicy>>,
+    headers: Headers,
+    body: Option<Bytes>,
+    trailers: Option<Headers>,
+) -> Response {
+    let retry_policy = match retry_policy {
+        Some(retry_policy) => retry_policy.clone(),
+        None => {
+            let start = starter.start_request(headers, body, trailers, true);
+            return Response::new_with_informational(move |informational| async move {
+                let (_sender, response) = start.await?;
+                response.forward_informational(informational).await
+            });
+        }
+    };
+
+    retry_policy.request_started();
+    let starter = starter.clone();
+    Response::new_with_informational(move |informational| async move {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let r = match starter
+                .start_request(headers.clone(), body.clone(), trailers.clone(), true)
+                .await
+            {
+                Ok((_sender, response)) => {
+                    response.forward_informational(informational.clone()).await
+                }
+                Err(e) => Err(e),
+            };
+            let e = match r {
+                Ok(r) => return Ok(r),
+                Err(e) => e,
+            };
+            match retry_policy.retry(&headers, attempt, &e) {
+                Some(delay) => {
+                    debug!("retrying request after attempt {}: {}", attempt, e);
+                    tokio::time::delay_for(delay).await;
+                }
+                None => return Err(e),
+            }
+        }
+    })
+}
+
 enum ControllerCommand {
     GoAway,
     StartRequest(StartRequestMessage),
     WaitForConnect(oneshot::Sender<Result<()>>),
+    PeerCertificates(oneshot::Sender<Option<PeerCertificates>>),
+    TlsSession(oneshot::Sender<Option<TlsSessionInfo>>),
     DumpState(oneshot::Sender<ConnStateSnapshot>),
+    Ping(oneshot::Sender<Result<Duration>>),
+    UpdateSettings(Vec<HttpSetting>, oneshot::Sender<Result<()>>),
+    SendGoaway(ErrorCode, Bytes, bool),
+    SendRawFrame(HttpFrame),
+    Drain(oneshot::Sender<()>),
 }
 
-struct ControllerState<T: ToClientStream, C: TlsConnector> {
+struct ControllerState<T: ToClientStream> {
     handle: Handle,
     socket_addr: T,
-    tls: ClientTlsOption<C>,
+    tls: ClientTlsOption,
     conf: ClientConf,
-    // current connection
-    conn: Arc<ClientConn>,
+    // connections new requests may be sent to, the last one is the newest
+    conns: Vec<Arc<ClientConn>>,
     tx: UnboundedSender<ControllerCommand>,
 }
 
-impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C> {
-    fn init_conn(&mut self) {
+impl<T: ToClientStream + 'static + Clone> ControllerState<T> {
+    /// Open a new connection and add it to the pool.
+    fn init_conn(&mut self) -> Arc<ClientConn> {
         let conn = ClientConn::spawn(
             self.handle.clone(),
             Box::pin(self.socket_addr.clone()),
@@ -478,27 +1430,69 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
             },
         );
 
-        self.conn = Arc::new(conn);
+        debug!("new client connection {}", conn.conn_id());
+
+        let conn = Arc::new(conn);
+        self.conns.push(conn.clone());
+        conn
+    }
+
+    /// Replace all connections with a new one.
+    fn reset_conns(&mut self) {
+        self.conns.clear();
+        self.init_conn();
+    }
+
+    fn remove_conn(&mut self, conn: &ClientConn) {
+        let conn_id = conn.conn_id();
+        self.conns.retain(|c| c.conn_id() != conn_id);
+    }
+
+    /// Connection for commands not related to particular requests.
+    fn conn(&mut self) -> Arc<ClientConn> {
+        match self.conns.last() {
+            Some(conn) => conn.clone(),
+            None => self.init_conn(),
+        }
+    }
+
+    /// Connection with fewest open streams, a new connection is opened
+    /// if all connections are busy and the pool is not full.
+    fn conn_for_request(&mut self) -> Arc<ClientConn> {
+        self.conns
+            .retain(|c| !c.is_closed() && !c.goaway_received());
+        let max_connections = self.conf.max_connections.unwrap_or(1).max(1);
+        match self.conns.iter().min_by_key(|c| c.streams()) {
+            Some(conn) if conn.streams() == 0 || self.conns.len() >= max_connections => {
+                conn.clone()
+            }
+            _ => self.init_conn(),
+        }
     }
 
-    fn iter(mut self, cmd: ControllerCommand) -> ControllerState<T, C> {
+    fn iter(mut self, cmd: ControllerCommand) -> ControllerState<T> {
         match cmd {
             ControllerCommand::GoAway => {
-                self.init_conn();
+                // new connection is opened when it is needed,
+                // so connections closed as idle are not reopened eagerly
+                self.conns.retain(|c| !c.goaway_received());
             }
             ControllerCommand::StartRequest(start) => {
-                if let Err(start) = self.conn.start_request_with_resp_sender(start) {
-                    self.init_conn();
-                    if let Err(_start) = self.conn.start_request_with_resp_sender(start) {
+                let conn = self.conn_for_request();
+                if let Err(start) = conn.start_request_with_resp_sender(start) {
+                    self.remove_conn(&conn);
+                    let conn = self.conn_for_request();
+                    if let Err(_start) = conn.start_request_with_resp_sender(start) {
                         warn!("client died and reconnect failed");
                         // TODO: invoke a callback to report about the error
                     }
                 }
             }
             ControllerCommand::WaitForConnect(tx) => {
-                if let Err(tx) = self.conn.wait_for_connect_with_resp_sender(tx) {
-                    self.init_conn();
-                    if let Err(tx) = self.conn.wait_for_connect_with_resp_sender(tx) {
+                let conn = self.conn();
+                if let Err(tx) = conn.wait_for_connect_with_resp_sender(tx) {
+                    self.remove_conn(&conn);
+                    if let Err(tx) = self.init_conn().wait_for_connect_with_resp_sender(tx) {
                         // TODO: reason
                         let err = error::Error::ClientDiedAndReconnectFailed;
                         // ignore error
@@ -507,7 +1501,61 @@ impl<T: ToClientStream + 'static + Clone, C: TlsConnector> ControllerState<T, C>
                 }
             }
             ControllerCommand::DumpState(tx) => {
-                self.conn.dump_state_with_resp_sender(tx);
+                self.conn().dump_state_with_resp_sender(tx);
+            }
+            ControllerCommand::PeerCertificates(tx) => {
+                // ignore error, caller may be dead
+                drop(tx.send(self.conn().peer_certificates()));
+            }
+            ControllerCommand::TlsSession(tx) => {
+                // ignore error, caller may be dead
+                let _ = tx.send(self.conn().tls_session());
+            }
+            ControllerCommand::SendGoaway(error_code, debug_data, graceful) => {
+                for conn in &self.conns {
+                    // ignore error, connection might be already dead
+                    drop(conn.send_goaway(error_code, debug_data.clone(), graceful));
+                }
+                self.reset_conns();
+            }
+            ControllerCommand::Drain(tx) => {
+                let closed = future::join_all(self.conns.iter().map(|c| c.closed()));
+                for conn in &self.conns {
+                    // ignore error, connection might be already dead
+                    drop(conn.send_goaway(ErrorCode::NoError, Bytes::new(), true));
+                }
+                self.reset_conns();
+                self.handle.spawn(closed.map(move |_| {
+                    // ignore error, caller may be dead
+                    let _ = tx.send(());
+                }));
+            }
+            ControllerCommand::SendRawFrame(frame) => {
+                // ignore error, connection might be already dead
+                drop(self.conn().send_raw_frame(frame));
+            }
+            ControllerCommand::UpdateSettings(settings, tx) => {
+                let conn = self.conn();
+                for other in self.conns.iter().filter(|c| c.conn_id() != conn.conn_id()) {
+                    // result is reported for the newest connection only
+                    let (other_tx, _) = oneshot::channel();
+                    drop(other.update_settings_with_resp_sender(settings.clone(), other_tx));
+                }
+                if let Err(tx) = conn.update_settings_with_resp_sender(settings, tx) {
+                    // ignore error
+                    drop(tx.send(Err(error::Error::ConnDied)));
+                }
+            }
+            ControllerCommand::Ping(tx) => {
+                let conn = self.conn();
+                if let Err(tx) = conn.ping_with_resp_sender(tx) {
+                    self.remove_conn(&conn);
+                    if let Err(tx) = self.init_conn().ping_with_resp_sender(tx) {
+                        let err = error::Error::ClientDiedAndReconnectFailed;
+                        // ignore error
+                        drop(tx.send(Err(err)));
+                    }
+                }
             }
         }
         self
@@ -531,18 +1579,32 @@ impl ClientConnCallbacks for CallbacksImpl {
     }
 }
 
-// Event loop entry point
-fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static, C: TlsConnector>(
-    handle: Handle,
+/// Shutdown and controller channels of client event loop,
+/// see `spawn_client_event_loop`.
+struct ClientLoopChannels {
     shutdown_future: ShutdownFuture,
-    socket_addr: T,
-    tls: ClientTlsOption<C>,
-    conf: ClientConf,
     done_tx: oneshot::Sender<()>,
     controller_tx: UnboundedSender<ControllerCommand>,
     controller_rx: UnboundedReceiver<ControllerCommand>,
     client_died_error_holder: SomethingDiedErrorHolder<ClientDiedType>,
+}
+
+// Event loop entry point
+fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static>(
+    handle: Handle,
+    socket_addr: T,
+    tls: ClientTlsOption,
+    conf: ClientConf,
+    channels: ClientLoopChannels,
 ) {
+    let ClientLoopChannels {
+        shutdown_future,
+        done_tx,
+        controller_tx,
+        controller_rx,
+        client_died_error_holder,
+    } = channels;
+
     let http_conn = ClientConn::spawn(
         handle.clone(),
         Box::pin(socket_addr.clone()),
@@ -553,12 +1615,14 @@ fn spawn_client_event_loop<T: ToClientStream + Send + Clone + 'static, C: TlsCon
         },
     );
 
+    debug!("new client connection {}", http_conn.conn_id());
+
     let init = ControllerState {
         handle: handle.clone(),
         socket_addr: socket_addr.clone(),
         tls: tls,
         conf: conf,
-        conn: Arc::new(http_conn),
+        conns: vec![Arc::new(http_conn)],
         tx: controller_tx,
     };
 
@@ -0,0 +1,480 @@
+//! Client for requests to multiple origins.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use futures::future;
+
+use tls_api::TlsConnector;
+
+use crate::client::coalesce::ConnCoverage;
+use crate::client::conf::ClientConf;
+use crate::client::happy_eyeballs::interleave;
+use crate::client::happy_eyeballs::DEFAULT_DELAY;
+use crate::client::redirect;
+use crate::client::req::ClientRequest;
+use crate::client::resolver::Resolver;
+use crate::client::resolver::ResolvingConnector;
+use crate::client::resolver::SystemResolver;
+use crate::client::tls::ClientTlsOption;
+use crate::client::Client;
+use crate::client::ClientBuilder;
+use crate::error::Error;
+use crate::result::Result;
+use crate::socket::AnySocketAddr;
+use crate::solicit::header::Header;
+use crate::solicit::header::Headers;
+use crate::solicit::HttpScheme;
+use crate::solicit_async::HttpFutureSend;
+use crate::tls::certificate_dns_names;
+use crate::tls::connector_like;
+use crate::tls::TlsConnectorLike;
+use crate::Response;
+
+/// Scheme, host and port of request target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Origin {
+    scheme: HttpScheme,
+    /// Lowercase host name or IP address without brackets.
+    host: String,
+    port: u16,
+}
+
+impl Origin {
+    fn new(scheme: HttpScheme, authority: &str) -> Result<Origin> {
+        let invalid = || Error::InvalidRequestTarget(authority.to_owned());
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(rest) => {
+                let close = rest.find(']').ok_or_else(invalid)?;
+                (&rest[..close], &rest[close + 1..])
+            }
+            None => match authority.rfind(':') {
+                Some(colon) => (&authority[..colon], &authority[colon..]),
+                None => (authority, ""),
+            },
+        };
+        let port = match port {
+            "" => match scheme {
+                HttpScheme::Http => 80,
+                HttpScheme::Https => 443,
+            },
+            port => match port.strip_prefix(':') {
+                Some(port) => port.parse().map_err(|_| invalid())?,
+                None => return Err(invalid()),
+            },
+        };
+        if host.is_empty() || host.contains('@') {
+            return Err(invalid());
+        }
+        Ok(Origin {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+
+    fn from_headers(headers: &Headers) -> Result<Origin> {
+        let scheme = parse_scheme(headers.get_opt(":scheme").unwrap_or("https"))?;
+        match headers.get_opt(":authority") {
+            Some(authority) => Origin::new(scheme, authority),
+            None => Err(Error::InvalidRequestTarget(
+                "no :authority header".to_owned(),
+            )),
+        }
+    }
+}
+
+fn parse_scheme(scheme: &str) -> Result<HttpScheme> {
+    if scheme.eq_ignore_ascii_case("http") {
+        Ok(HttpScheme::Http)
+    } else if scheme.eq_ignore_ascii_case("https") {
+        Ok(HttpScheme::Https)
+    } else {
+        Err(Error::InvalidRequestTarget(format!(
+            "unsupported scheme: {}",
+            scheme
+        )))
+    }
+}
+
+/// Split absolute URI into scheme, authority and path with query.
+fn parse_absolute_uri(uri: &str) -> Result<(HttpScheme, &str, String)> {
+    let invalid = || Error::InvalidRequestTarget(uri.to_owned());
+    let colon = uri.find("://").ok_or_else(invalid)?;
+    let scheme = parse_scheme(&uri[..colon])?;
+    let rest = &uri[colon + 3..];
+    let rest = match rest.find('#') {
+        Some(hash) => &rest[..hash],
+        None => rest,
+    };
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    if authority.is_empty() {
+        return Err(invalid());
+    }
+    let path = match &rest[authority_end..] {
+        "" => "/".to_owned(),
+        path if path.starts_with('?') => format!("/{}", path),
+        path => path.to_owned(),
+    };
+    Ok((scheme, authority, path))
+}
+
+/// Client for a single origin or coalesced origins.
+struct OriginClient {
+    client: Client,
+    /// Connector of the client, records connection peer address.
+    connector: Arc<ResolvingConnector>,
+}
+
+impl OriginClient {
+    /// Connection can be reused for `host` resolved to `host_addrs`
+    /// (RFC 9113 section 9.1.1): server presented verified certificate
+    /// valid for `host` and connection peer is one of `host_addrs`.
+    async fn can_coalesce(&self, host: &str, host_addrs: &[IpAddr]) -> bool {
+        // waits for connection
+        let certificates = self.client.peer_certificates().await;
+        let peer_ip = match self.connector.connected_addr() {
+            Some(addr) if host_addrs.contains(&addr.ip()) => addr.ip(),
+            _ => return false,
+        };
+        match certificates {
+            Ok(Some(certificates)) if certificates.verified => match certificates.end_entity() {
+                Some(cert) => ConnCoverage::new(peer_ip, certificate_dns_names(cert))
+                    .can_coalesce(host, host_addrs),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Client sending requests to any origin, selected by absolute URI
+/// or `:scheme` and `:authority` request headers.
+///
+/// Each origin (scheme, host and port) gets a separate `Client`
+/// with its own connection pool (see `ClientConf::max_connections`).
+/// HTTPS connection is reused for another host if the host resolves
+/// to the connection peer address and verified server certificate
+/// is valid for the host (RFC 9113 section 9.1.1), which requires TLS stack
+/// exposing peer certificates (rustls).
+///
+/// Clones share clients.
+pub struct MultiClient<C: TlsConnector = tls_api_stub::TlsConnector> {
+    conf: ClientConf,
+    tls_connector: Option<Arc<dyn TlsConnectorLike>>,
+    resolver: Arc<dyn Resolver>,
+    clients: Arc<Mutex<HashMap<Origin, Arc<OriginClient>>>>,
+    _tls_connector: PhantomData<fn() -> C>,
+}
+
+impl<C: TlsConnector> Clone for MultiClient<C> {
+    fn clone(&self) -> MultiClient<C> {
+        MultiClient {
+            conf: self.conf.clone(),
+            tls_connector: self.tls_connector.clone(),
+            resolver: self.resolver.clone(),
+            clients: self.clients.clone(),
+            _tls_connector: PhantomData,
+        }
+    }
+}
+
+impl MultiClient<tls_api_stub::TlsConnector> {
+    /// Client for `http` origins only.
+    pub fn new_plain(conf: ClientConf) -> MultiClient<tls_api_stub::TlsConnector> {
+        MultiClient::new(conf)
+    }
+}
+
+impl<C: TlsConnector> MultiClient<C> {
+    /// Clients for `https` origins are configured like `ClientBuilder::set_tls`.
+    pub fn new(conf: ClientConf) -> MultiClient<C> {
+        MultiClient {
+            conf,
+            tls_connector: None,
+            resolver: Arc::new(SystemResolver),
+            clients: Default::default(),
+            _tls_connector: PhantomData,
+        }
+    }
+
+    /// Use given TLS connector for `https` origins,
+    /// like `ClientBuilder::set_tls_connector`.
+    pub fn set_tls_connector<T: TlsConnectorLike>(&mut self, connector: T) {
+        self.tls_connector = Some(connector_like(connector));
+    }
+
+    /// Resolve origin hosts with given resolver instead of system resolver,
+    /// like `ClientBuilder::set_resolver`.
+    pub fn set_resolver<R: Resolver>(&mut self, resolver: R) {
+        self.resolver = Arc::new(resolver);
+    }
+
+    fn client_builder(
+        &self,
+        origin: &Origin,
+        connector: Arc<ResolvingConnector>,
+    ) -> Result<ClientBuilder<C>> {
+        let mut client = ClientBuilder::<C>::new();
+        client.conf = self.conf.clone();
+        // redirects to other origins are followed by this client
+        client.conf.max_redirects = None;
+        client.addr = Some(AnySocketAddr::Host(origin.host.clone(), origin.port));
+        client.resolver = Some(self.resolver.clone());
+        client.connector = Some(connector);
+        if origin.scheme == HttpScheme::Https {
+            match &self.tls_connector {
+                Some(connector) => {
+                    client.tls = ClientTlsOption::Tls(origin.host.clone(), connector.clone())
+                }
+                None => client.set_tls(&origin.host)?,
+            }
+        }
+        Ok(client)
+    }
+
+    /// Existing client for origin, coalesced client or a new one.
+    ///
+    /// Origin host is resolved to find a connection to coalesce with,
+    /// new client resolves it again before each connection
+    /// and races connections to resolved addresses.
+    fn client_for(&self, origin: Origin) -> HttpFutureSend<Arc<OriginClient>> {
+        if let Some(client) = self.clients.lock().unwrap().get(&origin) {
+            return Box::pin(future::ok(client.clone()));
+        }
+
+        let resolve = self.resolver.resolve(&origin.host, origin.port);
+        let connector = Arc::new(ResolvingConnector::new(
+            self.resolver.clone(),
+            &origin.host,
+            origin.port,
+            self.conf.happy_eyeballs_delay.unwrap_or(DEFAULT_DELAY),
+        ));
+        let client = match self.client_builder(&origin, connector.clone()) {
+            Ok(client) => client,
+            Err(e) => return Box::pin(future::err(e)),
+        };
+
+        let clients = self.clients.clone();
+        Box::pin(async move {
+            let addrs = interleave(resolve.await?);
+            if addrs.is_empty() {
+                return Err(Error::AddrResolvedToEmptyList);
+            }
+            let host_ips: Vec<IpAddr> = addrs.iter().map(SocketAddr::ip).collect();
+
+            let candidates: Vec<Arc<OriginClient>> = match origin.scheme {
+                HttpScheme::Https => {
+                    let clients = clients.lock().unwrap();
+                    let mut candidates: Vec<Arc<OriginClient>> = Vec::new();
+                    for (o, c) in clients.iter() {
+                        let peer_matches = match c.connector.connected_addr() {
+                            Some(addr) => host_ips.contains(&addr.ip()),
+                            // not connected yet
+                            None => true,
+                        };
+                        if o.scheme == HttpScheme::Https
+                            && peer_matches
+                            && !candidates.iter().any(|x| Arc::ptr_eq(x, c))
+                        {
+                            candidates.push(c.clone());
+                        }
+                    }
+                    candidates
+                }
+                // certificate does not authenticate plain connections
+                HttpScheme::Http => Vec::new(),
+            };
+
+            for candidate in candidates {
+                if candidate.can_coalesce(&origin.host, &host_ips).await {
+                    debug!(
+                        "coalescing {}:{} with connection to {}",
+                        origin.host,
+                        origin.port,
+                        candidate.connector.connected_addr().unwrap()
+                    );
+                    let mut clients = clients.lock().unwrap();
+                    return Ok(clients.entry(origin).or_insert(candidate).clone());
+                }
+            }
+
+            let mut clients = clients.lock().unwrap();
+            if let Some(client) = clients.get(&origin) {
+                return Ok(client.clone());
+            }
+            let client = Arc::new(OriginClient {
+                client: client.build()?,
+                connector,
+            });
+            clients.insert(origin, client.clone());
+            Ok(client)
+        })
+    }
+
+    /// Start request to origin specified by `:scheme` (`https` if missing)
+    /// and `:authority` headers.
+    pub fn start_request(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+        end_stream: bool,
+    ) -> HttpFutureSend<(ClientRequest, Response)> {
+        let client = match Origin::from_headers(&headers) {
+            Ok(origin) => self.client_for(origin),
+            Err(e) => return Box::pin(future::err(e)),
+        };
+        Box::pin(async move {
+            client
+                .await?
+                .client
+                .start_request(headers, body, trailers, end_stream)
+                .await
+        })
+    }
+
+    /// Like `Client::start_request_end_stream`, origin is selected
+    /// like in `start_request`.
+    ///
+    /// Redirects to any origin are followed if `ClientConf::max_redirects` is set.
+    pub fn start_request_end_stream(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        let max_redirects = match self.conf.max_redirects {
+            Some(max_redirects) => max_redirects,
+            None => return self.start_request_end_stream_to_origin(headers, body, trailers),
+        };
+        let response = self.start_request_end_stream_to_origin(
+            headers.clone(),
+            body.clone(),
+            trailers.clone(),
+        );
+        let multi = self.clone();
+        redirect::follow(
+            response,
+            headers,
+            body,
+            trailers,
+            max_redirects,
+            move |next| {
+                Some(multi.start_request_end_stream_to_origin(
+                    next.headers.clone(),
+                    next.body.clone(),
+                    next.trailers.clone(),
+                ))
+            },
+        )
+    }
+
+    fn start_request_end_stream_to_origin(
+        &self,
+        headers: Headers,
+        body: Option<Bytes>,
+        trailers: Option<Headers>,
+    ) -> Response {
+        let client = match Origin::from_headers(&headers) {
+            Ok(origin) => self.client_for(origin),
+            Err(e) => return Response::err(e),
+        };
+        Response::new_with_informational(move |informational| async move {
+            client
+                .await?
+                .client
+                .start_request_end_stream(headers, body, trailers)
+                .forward_informational(informational)
+                .await
+        })
+    }
+
+    /// Start `GET` request to absolute URI, e. g. `https://example.com/path`.
+    pub fn start_get(&self, uri: &str) -> Response {
+        let (scheme, authority, path) = match parse_absolute_uri(uri) {
+            Ok(r) => r,
+            Err(e) => return Response::err(e),
+        };
+        let headers = Headers::from_vec(vec![
+            Header::new(":method", "GET"),
+            Header::new(":path", path),
+            Header::new(":authority", authority.to_owned()),
+            Header::new(":scheme", scheme.as_bytes()),
+        ]);
+        self.start_request_end_stream(headers, None, None)
+    }
+
+    /// Start request converted from `http` crate request,
+    /// origin is selected like in `start_request`.
+    #[cfg(feature = "http")]
+    pub fn start_http_request(&self, request: http::Request<Bytes>) -> Response {
+        let (parts, body) = request.into_parts();
+        let headers = Headers::from(&parts);
+        let body = if body.is_empty() { None } else { Some(body) };
+        self.start_request_end_stream(headers, body, None)
+    }
+
+    /// Number of distinct clients, coalesced origins share a client.
+    pub fn client_count(&self) -> usize {
+        let clients = self.clients.lock().unwrap();
+        let mut distinct: Vec<&Arc<OriginClient>> = Vec::new();
+        for client in clients.values() {
+            if !distinct.iter().any(|c| Arc::ptr_eq(c, client)) {
+                distinct.push(client);
+            }
+        }
+        distinct.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn origin() {
+        assert_eq!(
+            Origin {
+                scheme: HttpScheme::Https,
+                host: "example.com".to_owned(),
+                port: 443,
+            },
+            Origin::new(HttpScheme::Https, "Example.com").unwrap()
+        );
+        assert_eq!(
+            80,
+            Origin::new(HttpScheme::Http, "example.com").unwrap().port
+        );
+        let origin = Origin::new(HttpScheme::Http, "[::1]:8080").unwrap();
+        assert_eq!(("::1", 8080), (&origin.host[..], origin.port));
+        assert!(Origin::new(HttpScheme::Http, "example.com:").is_err());
+        assert!(Origin::new(HttpScheme::Http, "[::1]8080").is_err());
+        assert!(Origin::new(HttpScheme::Http, "user@example.com").is_err());
+        assert!(Origin::new(HttpScheme::Http, ":80").is_err());
+    }
+
+    #[test]
+    fn absolute_uri() {
+        let (scheme, authority, path) =
+            parse_absolute_uri("https://example.com:8443/a/b?c=d#e").unwrap();
+        assert_eq!(HttpScheme::Https, scheme);
+        assert_eq!("example.com:8443", authority);
+        assert_eq!("/a/b?c=d", path);
+
+        let (_, authority, path) = parse_absolute_uri("HTTP://example.com?q").unwrap();
+        assert_eq!("example.com", authority);
+        assert_eq!("/?q", path);
+
+        assert_eq!("/", parse_absolute_uri("http://example.com").unwrap().2);
+        assert!(parse_absolute_uri("/path").is_err());
+        assert!(parse_absolute_uri("ftp://example.com/").is_err());
+        assert!(parse_absolute_uri("http:///path").is_err());
+    }
+}
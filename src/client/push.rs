@@ -0,0 +1,26 @@
+//! Receiving server push (RFC 7540 section 8.2).
+
+use std::fmt;
+
+use crate::Headers;
+use crate::Response;
+
+/// Receives responses pushed by the server, see `ClientConf::push_handler`.
+pub trait PushHandler: Send + Sync + 'static {
+    /// Called when server promises a response to `request`.
+    ///
+    /// Called on the connection event loop, so it must not block.
+    fn push_promise(&self, request: Headers, response: Response);
+}
+
+impl<F: Fn(Headers, Response) + Send + Sync + 'static> PushHandler for F {
+    fn push_promise(&self, request: Headers, response: Response) {
+        self(request, response)
+    }
+}
+
+impl fmt::Debug for dyn PushHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PushHandler")
+    }
+}
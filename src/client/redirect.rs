@@ -0,0 +1,273 @@
+//! Following redirect responses (RFC 9110 section 15.4).
+
+use bytes::Bytes;
+
+use crate::solicit::header::Header;
+use crate::solicit::header::Headers;
+use crate::Response;
+
+/// Request headers not sent when redirect leads to another origin.
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// Request to send to follow a redirect.
+pub(crate) struct Redirect {
+    pub headers: Headers,
+    pub body: Option<Bytes>,
+    pub trailers: Option<Headers>,
+    /// Redirect target has different scheme or authority.
+    pub cross_origin: bool,
+}
+
+/// Directory part of path, e. g. `/a/` for `/a/b?c`.
+fn base_dir(path: &str) -> &str {
+    let path = match path.find('?') {
+        Some(query) => &path[..query],
+        None => path,
+    };
+    match path.rfind('/') {
+        Some(slash) => &path[..slash + 1],
+        None => "/",
+    }
+}
+
+/// Resolve `location` header value against request scheme, authority and path.
+fn resolve_location(
+    scheme: &str,
+    authority: &str,
+    path: &str,
+    location: &str,
+) -> Option<(String, String, String)> {
+    let location = match location.find('#') {
+        Some(hash) => &location[..hash],
+        None => location,
+    };
+    let (scheme, rest) = match location.find("://") {
+        Some(colon) => {
+            let scheme = location[..colon].to_ascii_lowercase();
+            if scheme != "http" && scheme != "https" {
+                return None;
+            }
+            (scheme, &location[colon + 1..])
+        }
+        None => (scheme.to_owned(), location),
+    };
+    if let Some(rest) = rest.strip_prefix("//") {
+        let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let new_authority = &rest[..authority_end];
+        if new_authority.is_empty() {
+            return None;
+        }
+        let new_path = match &rest[authority_end..] {
+            "" => "/".to_owned(),
+            new_path if new_path.starts_with('?') => format!("/{}", new_path),
+            new_path => new_path.to_owned(),
+        };
+        return Some((scheme, new_authority.to_owned(), new_path));
+    }
+    let new_path = if rest.starts_with('/') {
+        rest.to_owned()
+    } else if rest.starts_with('?') {
+        let path = match path.find('?') {
+            Some(query) => &path[..query],
+            None => path,
+        };
+        format!("{}{}", path, rest)
+    } else if rest.is_empty() {
+        path.to_owned()
+    } else {
+        format!("{}{}", base_dir(path), rest)
+    };
+    Some((scheme, authority.to_owned(), new_path))
+}
+
+/// Request following redirect response `resp` to request `req`,
+/// `None` if response is not a redirect which can be followed.
+///
+/// `303` response, as well as `301` and `302` responses to `POST`,
+/// change request method to `GET` and drop request body.
+pub(crate) fn redirect(
+    req: &Headers,
+    body: &Option<Bytes>,
+    trailers: &Option<Headers>,
+    resp: &Headers,
+) -> Option<Redirect> {
+    let status = resp.status_opt()?;
+    let method = req.get_opt(":method")?;
+    let to_get = match status {
+        301 | 302 => method == "POST",
+        303 => method != "GET" && method != "HEAD",
+        307 | 308 => false,
+        _ => return None,
+    };
+    let scheme = req.get_opt(":scheme")?;
+    let authority = req.get_opt(":authority")?;
+    let (new_scheme, new_authority, new_path) = resolve_location(
+        scheme,
+        authority,
+        req.get_opt(":path")?,
+        resp.get_opt("location")?,
+    )?;
+    let cross_origin = !scheme.eq_ignore_ascii_case(&new_scheme)
+        || !authority.eq_ignore_ascii_case(&new_authority);
+
+    let mut headers = vec![
+        Header::new(":method", if to_get { "GET" } else { method }.to_owned()),
+        Header::new(":scheme", new_scheme),
+        Header::new(":authority", new_authority),
+        Header::new(":path", new_path),
+    ];
+    for header in req.iter() {
+        if header.is_preudo_header()
+            || (to_get && header.name().starts_with("content-"))
+            || (cross_origin && SENSITIVE_HEADERS.contains(&header.name()))
+        {
+            continue;
+        }
+        headers.push(header.clone());
+    }
+
+    Some(Redirect {
+        headers: Headers::from_vec(headers),
+        body: if to_get { None } else { body.clone() },
+        trailers: if to_get { None } else { trailers.clone() },
+        cross_origin,
+    })
+}
+
+/// Follow up to `max_redirects` redirects starting with `response`
+/// to request `headers`, redirect requests are sent with `start`.
+///
+/// `start` returns `None` if it cannot send request to the redirect target,
+/// then the redirect response is returned, like the last redirect
+/// response when the limit is reached.
+pub(crate) fn follow<S>(
+    response: Response,
+    headers: Headers,
+    body: Option<Bytes>,
+    trailers: Option<Headers>,
+    max_redirects: u32,
+    start: S,
+) -> Response
+where
+    S: Fn(&Redirect) -> Option<Response> + Send + 'static,
+{
+    Response::new_with_informational(move |informational| async move {
+        let mut headers = headers;
+        let mut body = body;
+        let mut trailers = trailers;
+        let mut response = response;
+        let mut redirects = 0;
+        loop {
+            let (resp_headers, resp_body) = response
+                .forward_informational(informational.clone())
+                .await?;
+            if redirects == max_redirects {
+                return Ok((resp_headers, resp_body));
+            }
+            let next = match redirect(&headers, &body, &trailers, &resp_headers) {
+                Some(next) => next,
+                None => return Ok((resp_headers, resp_body)),
+            };
+            response = match start(&next) {
+                Some(response) => response,
+                None => return Ok((resp_headers, resp_body)),
+            };
+            debug!(
+                "following {} redirect to {}{}",
+                resp_headers.status(),
+                next.headers.get(":authority"),
+                next.headers.path()
+            );
+            redirects += 1;
+            headers = next.headers;
+            body = next.body;
+            trailers = next.trailers;
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn req(method: &str, path: &str) -> Headers {
+        let mut headers = Headers::from_vec(vec![
+            Header::new(":method", method.to_owned()),
+            Header::new(":scheme", "https"),
+            Header::new(":authority", "example.com"),
+            Header::new(":path", path.to_owned()),
+        ]);
+        headers.add("authorization", "Bearer t");
+        headers.add("content-type", "text/plain");
+        headers
+    }
+
+    fn resp(status: u32, location: &str) -> Headers {
+        let mut headers = Headers::new_status(status);
+        headers.add("location", location.to_owned());
+        headers
+    }
+
+    #[test]
+    fn location() {
+        let resolve = |location| resolve_location("https", "example.com", "/a/b?c", location);
+        let r = |scheme: &str, authority: &str, path: &str| {
+            Some((scheme.to_owned(), authority.to_owned(), path.to_owned()))
+        };
+        assert_eq!(r("https", "example.com", "/x"), resolve("/x"));
+        assert_eq!(r("https", "example.com", "/a/x?y"), resolve("x?y#z"));
+        assert_eq!(r("https", "example.com", "/a/b?d"), resolve("?d"));
+        assert_eq!(r("https", "other.com", "/"), resolve("//other.com"));
+        assert_eq!(
+            r("http", "other.com:8080", "/?q"),
+            resolve("HTTP://other.com:8080?q")
+        );
+        assert_eq!(None, resolve("ftp://example.com/"));
+        assert_eq!(None, resolve("https:///x"));
+    }
+
+    #[test]
+    fn method_and_body() {
+        let body = Some(Bytes::from_static(b"body"));
+
+        let next = redirect(&req("POST", "/"), &body, &None, &resp(302, "/x")).unwrap();
+        assert_eq!("GET", next.headers.method());
+        assert_eq!(None, next.body);
+        assert_eq!(None, next.headers.content_type());
+
+        let next = redirect(&req("PUT", "/"), &body, &None, &resp(301, "/x")).unwrap();
+        assert_eq!("PUT", next.headers.method());
+        assert_eq!(body, next.body);
+
+        let next = redirect(&req("PUT", "/"), &body, &None, &resp(303, "/x")).unwrap();
+        assert_eq!("GET", next.headers.method());
+        assert_eq!(None, next.body);
+
+        let next = redirect(&req("POST", "/"), &body, &None, &resp(307, "/x")).unwrap();
+        assert_eq!("POST", next.headers.method());
+        assert_eq!(body, next.body);
+        assert_eq!(Some("text/plain"), next.headers.content_type());
+
+        assert!(redirect(&req("GET", "/"), &None, &None, &resp(304, "/x")).is_none());
+        assert!(redirect(&req("GET", "/"), &None, &None, &Headers::new_status(302)).is_none());
+    }
+
+    #[test]
+    fn cross_origin() {
+        let next = redirect(&req("GET", "/"), &None, &None, &resp(302, "/x")).unwrap();
+        assert!(!next.cross_origin);
+        assert_eq!("Bearer t", next.headers.get("authorization"));
+
+        let next = redirect(
+            &req("GET", "/"),
+            &None,
+            &None,
+            &resp(302, "https://other.com/x"),
+        )
+        .unwrap();
+        assert!(next.cross_origin);
+        assert_eq!("other.com", next.headers.get(":authority"));
+        assert_eq!(None, next.headers.get_opt("authorization"));
+        assert_eq!(Some("text/plain"), next.headers.content_type());
+    }
+}
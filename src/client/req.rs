@@ -1,13 +1,18 @@
 use crate::assert_types::assert_send;
 use crate::client::types::ClientTypes;
+use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::sender::CommonSender;
 use crate::common::sender::SendError;
 use crate::common::window_size::StreamDead;
+use crate::data_or_trailers::ResetStreamFn;
 
 use crate::result;
+use crate::solicit::frame::PriorityUpdateFrame;
 use crate::ErrorCode;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
+use crate::Priority;
 use crate::SenderState;
 use bytes::Bytes;
 use futures::stream::Stream;
@@ -19,6 +24,8 @@ use std::task::Poll;
 // NOTE: keep in sync with ServerResponse
 pub struct ClientRequest {
     pub(crate) common: CommonSender<ClientTypes>,
+    /// Used to send `PRIORITY_UPDATE` after the request is sent
+    pub(crate) write_tx: ConnCommandSender<ClientTypes>,
     // need to replace with FnOnce when rust allows it
     pub(crate) drop_callback:
         Option<Box<dyn FnMut(&mut ClientRequest) -> result::Result<()> + Send>>,
@@ -65,6 +72,32 @@ impl ClientRequest {
         self.common.poll(cx)
     }
 
+    /// Declare how many bytes the caller wants to send.
+    ///
+    /// Capacity is reduced by the amount of data sent.
+    pub fn reserve_capacity(&mut self, capacity: usize) {
+        self.common.reserve_capacity(capacity)
+    }
+
+    /// Number of bytes which can be sent now without exceeding
+    /// stream or connection window, at most reserved capacity.
+    pub fn capacity(&self) -> usize {
+        self.common.capacity()
+    }
+
+    /// Wait until some capacity is available.
+    ///
+    /// Returns the number of bytes (at most reserved capacity)
+    /// which can be sent without exceeding stream or connection window.
+    /// Returns zero if no capacity is reserved.
+    /// Fails if the stream is ended, reset or closed by the peer.
+    ///
+    /// Connection window is shared between streams, so the capacity
+    /// is only a hint when several streams send data concurrently.
+    pub fn poll_capacity(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, StreamDead>> {
+        self.common.poll_capacity(cx)
+    }
+
     /// Enqueue data to outgoing stream
     ///
     /// This operation fails if stream is in incorrect state.
@@ -76,11 +109,18 @@ impl ClientRequest {
     }
 
     /// Send last `DATA` frame
+    ///
+    /// `END_STREAM` flag is set on the frame carrying the last chunk of `data`,
+    /// no separate empty `DATA` frame is sent.
     pub fn send_data_end_of_stream(&mut self, data: Bytes) -> Result<(), SendError> {
         self.common.send_data_end_of_stream(data)
     }
 
-    /// Send trailing headers
+    /// Send trailing headers and finish the request.
+    ///
+    /// Trailers are sent in a `HEADERS` frame with `END_STREAM` flag
+    /// after all data enqueued before. Trailers must not contain
+    /// pseudo-headers, otherwise `SendError::InvalidHeaders` is returned.
     pub fn send_trailers(&mut self, trailers: Headers) -> Result<(), SendError> {
         self.common.send_trailers(trailers)
     }
@@ -103,4 +143,25 @@ impl ClientRequest {
     pub fn close(&mut self) -> Result<(), SendError> {
         self.common.close()
     }
+
+    /// Change priority of the request with `PRIORITY_UPDATE` frame.
+    ///
+    /// Can be called after the request is sent, until the response is received.
+    /// Initial priority is sent in the `priority` header, see `Priority::set_header`.
+    pub fn set_priority(&mut self, priority: Priority) -> result::Result<()> {
+        let frame = PriorityUpdateFrame::new(self.common.stream_id(), priority.to_string());
+        self.write_tx
+            .unbounded_send(CommonToWriteMessage::RawFrame(frame.into()).into())
+    }
+
+    /// Function sending `RST_STREAM` even after the request is sent.
+    pub(crate) fn reset_fn(&self) -> ResetStreamFn {
+        let stream_id = self.common.stream_id();
+        let write_tx = self.write_tx.clone();
+        Box::new(move |error_code| {
+            // ignore error, connection might be already dead
+            let m = CommonToWriteMessage::StreamReset(stream_id, error_code);
+            drop(write_tx.unbounded_send(m.into()));
+        })
+    }
 }
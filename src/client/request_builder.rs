@@ -0,0 +1,135 @@
+//! Fluent construction of client requests.
+
+use bytes::Bytes;
+
+use crate::client::Client;
+use crate::solicit::header::method::Method;
+use crate::solicit::header::name::HeaderName;
+use crate::solicit::header::Header;
+use crate::solicit::header::Headers;
+use crate::HeaderValue;
+use crate::Response;
+
+/// Percent-encode query component, only unreserved characters are kept.
+fn encode_query_component(s: &str, r: &mut String) {
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                r.push(b as char)
+            }
+            _ => r.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+/// Request constructed with `Client::request`, sent with `send`.
+///
+/// ```no_run
+/// # use httpbis::*;
+/// # fn f(client: &Client) -> Response {
+/// client
+///     .request(Method::PUT, "/users")
+///     .header("content-type", "application/json")
+///     .query("id", "42")
+///     .body("{}")
+///     .send()
+/// # }
+/// ```
+#[must_use = "request is not sent until `send` is called"]
+pub struct RequestBuilder<'a> {
+    client: &'a Client,
+    method: Method,
+    path: String,
+    authority: Option<HeaderValue>,
+    headers: Headers,
+    body: Option<Bytes>,
+    trailers: Option<Headers>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    pub(crate) fn new(client: &'a Client, method: Method, path: &str) -> RequestBuilder<'a> {
+        RequestBuilder {
+            client,
+            method,
+            path: path.to_owned(),
+            authority: None,
+            headers: Headers::new(),
+            body: None,
+            trailers: None,
+        }
+    }
+
+    /// Set `:authority`, by default it is the address client connects to.
+    pub fn authority(mut self, authority: impl Into<HeaderValue>) -> Self {
+        self.authority = Some(authority.into());
+        self
+    }
+
+    /// Add a request header.
+    pub fn header(mut self, name: impl Into<HeaderName>, value: impl Into<HeaderValue>) -> Self {
+        self.headers.add(name, value);
+        self
+    }
+
+    /// Add request headers.
+    pub fn headers(mut self, headers: Headers) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Append percent-encoded `key=value` pair to the query string of the path.
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.path
+            .push(if self.path.contains('?') { '&' } else { '?' });
+        encode_query_component(key, &mut self.path);
+        self.path.push('=');
+        encode_query_component(value, &mut self.path);
+        self
+    }
+
+    /// Set request body, by default request has no body.
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set request trailers.
+    pub fn trailers(mut self, trailers: Headers) -> Self {
+        self.trailers = Some(trailers);
+        self
+    }
+
+    /// Client, request headers with pseudo-headers, body and trailers.
+    fn into_parts(self) -> (&'a Client, Headers, Option<Bytes>, Option<Headers>) {
+        let authority = match self.authority {
+            Some(authority) => authority,
+            None => self.client.default_authority().into(),
+        };
+        let mut headers = Headers::from_vec(vec![
+            Header::new(":method", self.method),
+            Header::new(":path", self.path),
+            Header::new(":authority", authority),
+            Header::new(":scheme", self.client.http_scheme().as_bytes()),
+        ]);
+        headers.extend(self.headers);
+        (self.client, headers, self.body, self.trailers)
+    }
+
+    /// Send the request with `Client::start_request_end_stream`.
+    pub fn send(self) -> Response {
+        let (client, headers, body, trailers) = self.into_parts();
+        client.start_request_end_stream(headers, body, trailers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn query() {
+        let mut r = String::new();
+        encode_query_component("a b&c=d/é~", &mut r);
+        assert_eq!("a%20b%26c%3Dd%2F%C3%A9~", r);
+    }
+}
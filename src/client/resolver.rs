@@ -0,0 +1,138 @@
+//! Pluggable host name resolution.
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+
+use crate::client::happy_eyeballs;
+use crate::client::happy_eyeballs::interleave;
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+
+/// Resolves host names to addresses, e. g. with custom DNS client,
+/// caching layer or service discovery.
+///
+/// Implemented for functions
+/// `Fn(&str, u16) -> impl Future<Output = io::Result<Vec<SocketAddr>>>`.
+pub trait Resolver: Send + Sync + 'static {
+    /// Addresses of `host`, with given `port` unless resolver knows better.
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send>>;
+}
+
+impl<F, R> Resolver for F
+where
+    F: Fn(&str, u16) -> R + Send + Sync + 'static,
+    R: Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'static,
+{
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send>> {
+        Box::pin(self(host, port))
+    }
+}
+
+/// Resolver using operating system facilities (`getaddrinfo`).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send>> {
+        let host = host.to_owned();
+        Box::pin(async move { Ok(tokio::net::lookup_host((&host[..], port)).await?.collect()) })
+    }
+}
+
+/// Connector resolving host before each connection,
+/// connection attempts to resolved addresses are raced.
+pub(crate) struct ResolvingConnector {
+    resolver: Arc<dyn Resolver>,
+    host: String,
+    port: u16,
+    /// See `ClientConf::happy_eyeballs_delay`.
+    delay: Duration,
+    /// Address of the last successful connection.
+    connected: Arc<Mutex<Option<SocketAddr>>>,
+}
+
+impl ResolvingConnector {
+    pub fn new(
+        resolver: Arc<dyn Resolver>,
+        host: &str,
+        port: u16,
+        delay: Duration,
+    ) -> ResolvingConnector {
+        ResolvingConnector {
+            resolver,
+            host: host.to_owned(),
+            port,
+            delay,
+            connected: Default::default(),
+        }
+    }
+
+    /// Connector with system resolver and default settings.
+    pub fn system(host: &str, port: u16) -> ResolvingConnector {
+        ResolvingConnector::new(
+            Arc::new(SystemResolver),
+            host,
+            port,
+            happy_eyeballs::DEFAULT_DELAY,
+        )
+    }
+
+    /// Address of the last successful connection.
+    pub fn connected_addr(&self) -> Option<SocketAddr> {
+        *self.connected.lock().unwrap()
+    }
+}
+
+impl fmt::Display for ResolvingConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+impl ToClientStream for ResolvingConnector {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        let resolve = self.resolver.resolve(&self.host, self.port);
+        let delay = self.delay;
+        let handle = handle.clone();
+        let connected = self.connected.clone();
+        Box::pin(async move {
+            let addrs = interleave(resolve.await?);
+            debug!("resolved to {:?}", addrs);
+            let (stream, addr) = happy_eyeballs::race(addrs, delay, |addr| {
+                let connect = addr.connect(&handle);
+                async move { Ok((connect.await?, addr)) }
+            })
+            .await?;
+            *connected.lock().unwrap() = Some(addr);
+            Ok(stream)
+        })
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        AnySocketAddr::Host(self.host.clone(), self.port)
+    }
+}
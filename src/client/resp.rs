@@ -25,7 +25,11 @@ impl<'a> ClientResponse<'a> {
                 increase_in_window: increase_in_window.0,
             };
 
-            (inc_tx, Response::from_stream(stream_from_network))
+            let reset = stream_from_network.reset_fn();
+            (
+                inc_tx,
+                Response::from_stream_impl(stream_from_network, Some(reset)),
+            )
         })
     }
 
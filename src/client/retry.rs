@@ -0,0 +1,222 @@
+//! Client request retries.
+
+use std::cmp;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::ErrorCode;
+use crate::Headers;
+
+/// Decides whether a failed request is retried.
+///
+/// Only requests started with `Client::start_request_end_stream`
+/// (and helpers built on it) are retried, and only if they failed
+/// before response headers were received.
+pub trait RetryPolicy: Send + Sync + 'static {
+    /// Called once per request before the first attempt.
+    fn request_started(&self) {}
+
+    /// Called after failed attempt number `attempt` (starting from 1).
+    ///
+    /// Return delay before the next attempt, or `None` to fail the request.
+    fn retry(&self, req: &Headers, attempt: u32, error: &Error) -> Option<Duration>;
+}
+
+impl fmt::Debug for dyn RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("RetryPolicy")
+    }
+}
+
+fn is_connect_error(error: &Error) -> bool {
+    match error {
+        Error::ConnectionTimeout | Error::UnableToConnect => true,
+        Error::IoError(e) => matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::AddrNotAvailable
+                | io::ErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
+
+/// Request failed before the server started processing it,
+/// so it is safe to retry regardless of request method.
+///
+/// These are `REFUSED_STREAM`, streams above `GOAWAY` last stream id,
+/// and failures to connect.
+pub fn is_unprocessed_error(error: &Error) -> bool {
+    match error {
+        Error::RstStreamReceived(ErrorCode::RefusedStream) => true,
+        Error::GoawayReceived => true,
+        Error::ClientDied(Some(e)) => is_connect_error(e),
+        e => is_connect_error(e),
+    }
+}
+
+/// Limits retries to a fraction of requests, so retries cannot
+/// amplify an outage.
+///
+/// Each request deposits `ratio` to the budget, each retry
+/// withdraws one. Budget starts with and is capped at `reserve`,
+/// which is the maximum number of retries in a burst.
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    reserve: f64,
+    balance: Mutex<f64>,
+}
+
+impl RetryBudget {
+    pub fn new(ratio: f64, reserve: u32) -> RetryBudget {
+        RetryBudget {
+            ratio,
+            reserve: reserve as f64,
+            balance: Mutex::new(reserve as f64),
+        }
+    }
+
+    fn deposit(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + self.ratio).min(self.reserve);
+    }
+
+    fn withdraw(&self) -> bool {
+        let mut balance = self.balance.lock().unwrap();
+        if *balance >= 1.0 {
+            *balance -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Retry policy with exponential backoff.
+///
+/// Retries only errors for which `is_unprocessed_error` is true.
+#[derive(Debug)]
+pub struct DefaultRetryPolicy {
+    /// Max number of attempts including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each next retry.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub budget: Option<RetryBudget>,
+}
+
+impl DefaultRetryPolicy {
+    pub fn new() -> DefaultRetryPolicy {
+        DefaultRetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            budget: Some(RetryBudget::new(0.2, 10)),
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        cmp::min(
+            self.backoff.checked_mul(factor).unwrap_or(self.max_backoff),
+            self.max_backoff,
+        )
+    }
+}
+
+impl Default for DefaultRetryPolicy {
+    fn default() -> DefaultRetryPolicy {
+        DefaultRetryPolicy::new()
+    }
+}
+
+impl RetryPolicy for DefaultRetryPolicy {
+    fn request_started(&self) {
+        if let Some(budget) = &self.budget {
+            budget.deposit();
+        }
+    }
+
+    fn retry(&self, _req: &Headers, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !is_unprocessed_error(error) {
+            return None;
+        }
+        if let Some(budget) = &self.budget {
+            if !budget.withdraw() {
+                debug!("retry budget exhausted");
+                return None;
+            }
+        }
+        Some(self.backoff(attempt))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    fn refused() -> Error {
+        Error::RstStreamReceived(ErrorCode::RefusedStream)
+    }
+
+    #[test]
+    fn classify() {
+        assert!(is_unprocessed_error(&refused()));
+        assert!(is_unprocessed_error(&Error::GoawayReceived));
+        assert!(is_unprocessed_error(&Error::ClientDied(Some(Arc::new(
+            Error::IoError(io::ErrorKind::ConnectionRefused.into())
+        )))));
+        assert!(!is_unprocessed_error(&Error::RstStreamReceived(
+            ErrorCode::InternalError
+        )));
+        assert!(!is_unprocessed_error(&Error::ClientDied(None)));
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = DefaultRetryPolicy {
+            max_attempts: 100,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            budget: None,
+        };
+        let req = Headers::new_get("/");
+        assert_eq!(
+            Some(Duration::from_millis(100)),
+            policy.retry(&req, 1, &refused())
+        );
+        assert_eq!(
+            Some(Duration::from_millis(400)),
+            policy.retry(&req, 3, &refused())
+        );
+        assert_eq!(
+            Some(Duration::from_millis(500)),
+            policy.retry(&req, 40, &refused())
+        );
+        assert_eq!(None, policy.retry(&req, 100, &refused()));
+        assert_eq!(None, policy.retry(&req, 1, &Error::Shutdown));
+    }
+
+    #[test]
+    fn budget() {
+        let budget = RetryBudget::new(0.5, 2);
+        assert!(budget.withdraw());
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+        budget.deposit();
+        assert!(!budget.withdraw());
+        budget.deposit();
+        assert!(budget.withdraw());
+        for _ in 0..10 {
+            budget.deposit();
+        }
+        assert!(budget.withdraw());
+        assert!(budget.withdraw());
+        assert!(!budget.withdraw());
+    }
+}
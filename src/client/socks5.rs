@@ -0,0 +1,266 @@
+//! Client connections through SOCKS5 proxy (RFC 1928),
+//! with optional username/password authentication (RFC 1929).
+
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+
+use futures::Future;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Handle;
+
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+
+const VERSION: u8 = 5;
+
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_USERNAME_PASSWORD: u8 = 0x02;
+const METHOD_NO_ACCEPTABLE: u8 = 0xff;
+
+/// Version of username/password subnegotiation.
+const USERNAME_PASSWORD_VERSION: u8 = 1;
+
+const COMMAND_CONNECT: u8 = 1;
+
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN_NAME: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+fn socks_error(message: String) -> io::Error {
+    io::Error::other(format!("SOCKS5 proxy: {}", message))
+}
+
+fn reply_message(reply: u8) -> &'static str {
+    match reply {
+        1 => "general failure",
+        2 => "connection not allowed by ruleset",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+/// Split `host:port`, host may be bracketed IPv6 address.
+fn split_authority(authority: &str) -> Option<(&str, u16)> {
+    let colon = authority.rfind(':')?;
+    let host = &authority[..colon];
+    let port = authority[colon + 1..].parse().ok()?;
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']')?,
+        None => host,
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host, port))
+}
+
+/// Connects to proxy and asks it to connect to target host and port.
+pub(crate) struct Socks5Connector {
+    pub proxy: AnySocketAddr,
+    pub host: String,
+    pub port: u16,
+    /// Username and password, no authentication if `None`.
+    pub credentials: Option<(String, String)>,
+}
+
+impl Socks5Connector {
+    /// Connector to `authority` (`host:port`).
+    pub fn new(
+        proxy: AnySocketAddr,
+        authority: &str,
+        credentials: Option<(String, String)>,
+    ) -> io::Result<Socks5Connector> {
+        let (host, port) = split_authority(authority).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid authority: {}", authority),
+            )
+        })?;
+        if host.len() > 255 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "host name is too long for SOCKS5",
+            ));
+        }
+        if let Some((username, password)) = &credentials {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "username or password is too long for SOCKS5",
+                ));
+            }
+        }
+        Ok(Socks5Connector {
+            proxy,
+            host: host.to_owned(),
+            port,
+            credentials,
+        })
+    }
+
+    fn greeting(&self) -> Vec<u8> {
+        match self.credentials {
+            Some(_) => vec![VERSION, 2, METHOD_NO_AUTH, METHOD_USERNAME_PASSWORD],
+            None => vec![VERSION, 1, METHOD_NO_AUTH],
+        }
+    }
+
+    fn connect_request(&self) -> Vec<u8> {
+        let mut request = vec![VERSION, COMMAND_CONNECT, 0];
+        match self.host.parse() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(ATYP_IPV4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(ATYP_IPV6);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) => {
+                request.push(ATYP_DOMAIN_NAME);
+                request.push(self.host.len() as u8);
+                request.extend_from_slice(self.host.as_bytes());
+            }
+        }
+        request.extend_from_slice(&self.port.to_be_bytes());
+        request
+    }
+}
+
+async fn handshake<I>(
+    stream: &mut I,
+    greeting: Vec<u8>,
+    credentials: Option<(String, String)>,
+    connect_request: Vec<u8>,
+) -> io::Result<()>
+where
+    I: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(&greeting).await?;
+    let mut choice = [0; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != VERSION {
+        return Err(socks_error(format!("unsupported version {}", choice[0])));
+    }
+    match (choice[1], credentials) {
+        (METHOD_NO_AUTH, _) => {}
+        (METHOD_USERNAME_PASSWORD, Some((username, password))) => {
+            let mut request = vec![USERNAME_PASSWORD_VERSION, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await?;
+
+            let mut status = [0; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0 {
+                return Err(socks_error("authentication failed".to_owned()));
+            }
+        }
+        (METHOD_NO_ACCEPTABLE, _) => {
+            return Err(socks_error(
+                "no acceptable authentication method".to_owned(),
+            ))
+        }
+        (method, _) => {
+            return Err(socks_error(format!(
+                "unexpected authentication method {}",
+                method
+            )))
+        }
+    }
+
+    stream.write_all(&connect_request).await?;
+    let mut reply = [0; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(socks_error(format!(
+            "connect failed: {}",
+            reply_message(reply[1])
+        )));
+    }
+    // skip bound address and port
+    let addr_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN_NAME => stream.read_u8().await? as usize,
+        atyp => return Err(socks_error(format!("unknown address type {}", atyp))),
+    };
+    let mut bound = vec![0; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+impl fmt::Display for Socks5Connector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} via SOCKS5 proxy {}",
+            self.host, self.port, self.proxy
+        )
+    }
+}
+
+impl ToClientStream for Socks5Connector {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        let connect = self.proxy.connect(handle);
+        let greeting = self.greeting();
+        let credentials = self.credentials.clone();
+        let connect_request = self.connect_request();
+        Box::pin(async move {
+            let mut stream = connect.await?;
+            handshake(&mut stream, greeting, credentials, connect_request).await?;
+            Ok(stream)
+        })
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        self.proxy.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn authority() {
+        assert_eq!(
+            Some(("example.com", 443)),
+            split_authority("example.com:443")
+        );
+        assert_eq!(Some(("::1", 80)), split_authority("[::1]:80"));
+        assert_eq!(None, split_authority("example.com"));
+        assert_eq!(None, split_authority(":80"));
+        assert_eq!(None, split_authority("[::1:80"));
+    }
+
+    #[test]
+    fn connect_request() {
+        let connector = Socks5Connector::new(AnySocketAddr::Mem, "example.com:443", None).unwrap();
+        let mut expected = vec![5, 1, 0, 3, 11];
+        expected.extend_from_slice(b"example.com");
+        expected.extend_from_slice(&[1, 187]);
+        assert_eq!(expected, connector.connect_request());
+
+        let connector = Socks5Connector::new(AnySocketAddr::Mem, "10.0.0.1:80", None).unwrap();
+        assert_eq!(
+            vec![5, 1, 0, 1, 10, 0, 0, 1, 0, 80],
+            connector.connect_request()
+        );
+    }
+}
@@ -11,12 +11,21 @@ use bytes::Bytes;
 pub trait ClientStreamCreatedHandler: Send + 'static {
     /// Called when stream is created
     fn request_created(&mut self, req: ClientRequest, resp: ClientResponse) -> crate::Result<()>;
+
+    /// Called instead of `request_created` if connection died before
+    /// the stream was created.
+    fn request_not_created(&mut self, _error: error::Error) {}
 }
 
 /// Synchrnous callback of incoming data
 pub trait ClientResponseStreamHandler: Send + 'static {
     /// Response HEADERS frame received
     fn headers(&mut self, headers: Headers, end_stream: bool) -> result::Result<()>;
+    /// Interim `1xx` response HEADERS received,
+    /// called only if `ClientConf::informational_responses` is enabled
+    fn informational(&mut self, _headers: Headers) -> result::Result<()> {
+        Ok(())
+    }
     /// DATA frame received
     fn data_frame(&mut self, data: Bytes, end_stream: bool) -> result::Result<()>;
     /// Trailers HEADERS received
@@ -1,24 +1,15 @@
 use std::sync::Arc;
 
-use tls_api::TlsConnector;
-
 use crate::solicit::HttpScheme;
+use crate::tls::TlsConnectorLike;
 
-pub enum ClientTlsOption<C: TlsConnector> {
+#[derive(Clone)]
+pub enum ClientTlsOption {
     Plain,
-    Tls(String, Arc<C>), // domain
-}
-
-impl<C: TlsConnector> Clone for ClientTlsOption<C> {
-    fn clone(&self) -> Self {
-        match self {
-            &ClientTlsOption::Plain => ClientTlsOption::Plain,
-            &ClientTlsOption::Tls(ref d, ref c) => ClientTlsOption::Tls(d.clone(), c.clone()),
-        }
-    }
+    Tls(String, Arc<dyn TlsConnectorLike>), // domain
 }
 
-impl<C: TlsConnector> ClientTlsOption<C> {
+impl ClientTlsOption {
     pub fn http_scheme(&self) -> HttpScheme {
         match self {
             &ClientTlsOption::Plain => HttpScheme::Http,
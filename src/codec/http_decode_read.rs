@@ -1,13 +1,18 @@
 use crate::codec::http_framed_read::HttpFramedJoinContinuationRead;
-use crate::hpack;
+use crate::common::traffic::TrafficStats;
+use crate::error;
 use crate::result;
+use crate::solicit::end_stream::EndStream;
 use crate::solicit::frame::HttpFrameDecoded;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::{HeadersDecodedFrame, HttpFrame};
 use crate::solicit::stream_id::StreamId;
 use crate::ErrorCode;
 use crate::Header;
 use crate::Headers;
+use bytes::Bytes;
 use futures::task::Context;
+use httpbis_codec::hpack;
 use std::task::Poll;
 use tokio::io::AsyncRead;
 
@@ -15,19 +20,106 @@ pub struct HttpDecodeRead<R: AsyncRead + Unpin> {
     framed_read: HttpFramedJoinContinuationRead<R>,
     /// HPACK decoder used to decode incoming headers before passing them on to the session.
     decoder: hpack::Decoder,
+    /// Our `SETTINGS_MAX_HEADER_LIST_SIZE`
+    max_header_list_size: Option<u32>,
 }
 
 pub enum HttpFrameDecodedOrGoaway {
     Frame(HttpFrameDecoded),
     SendGoaway(ErrorCode),
     _SendRst(StreamId, ErrorCode),
+    /// Decoded header list of stream exceeds `SETTINGS_MAX_HEADER_LIST_SIZE`,
+    /// size of header list is up to the first header exceeding the limit.
+    HeaderListTooLarge(StreamId, EndStream, usize),
+}
+
+enum DecodeHeadersError {
+    /// Connection error
+    Conn(ErrorCode),
+    /// Header list size exceeds the limit
+    TooLarge(usize),
+}
+
+/// Header list size as defined in RFC 7540 section 6.5.2.
+fn header_list_entry_size(name: &[u8], value: &[u8]) -> usize {
+    name.len() + value.len() + 32
 }
 
 impl<R: AsyncRead + Unpin> HttpDecodeRead<R> {
-    pub fn new(read: R) -> Self {
+    pub fn new(
+        read: R,
+        max_header_block_size: Option<u32>,
+        max_header_list_size: Option<u32>,
+    ) -> Self {
         HttpDecodeRead {
-            framed_read: HttpFramedJoinContinuationRead::new(read),
+            framed_read: HttpFramedJoinContinuationRead::new(read, max_header_block_size),
             decoder: hpack::Decoder::new(),
+            max_header_list_size,
+        }
+    }
+
+    /// Limit HPACK dynamic table size after peer acknowledged our
+    /// `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn set_max_header_table_size(&mut self, size: u32) {
+        self.decoder.set_max_table_size(size as usize);
+    }
+
+    /// Frames received so far.
+    pub fn received_traffic(&self) -> &TrafficStats {
+        self.framed_read.received_traffic()
+    }
+
+    /// Decode header block.
+    fn decode_headers(
+        &mut self,
+        header_fragment: Bytes,
+        stream_id: StreamId,
+    ) -> Result<Headers, DecodeHeadersError> {
+        let max_header_list_size = self.max_header_list_size.map(|s| s as usize);
+        let mut size = 0;
+        let mut headers = Vec::new();
+        // Whole block is decoded to keep dynamic table in sync,
+        // but headers exceeding the limit are not collected
+        let r = self.decoder.decode_with_cb(header_fragment, |name, value| {
+            if max_header_list_size.is_some_and(|max| size > max) {
+                return;
+            }
+            size += header_list_entry_size(&name, &value);
+            headers.push((name, value));
+        });
+        if let Err(e) = r {
+            warn!("failed to decode headers: {:?}", e);
+            return Err(DecodeHeadersError::Conn(ErrorCode::CompressionError));
+        }
+        if let Some(max) = max_header_list_size {
+            if size > max {
+                warn!(
+                    "header list size of stream {} exceeds limit {}",
+                    stream_id, max
+                );
+                return Err(DecodeHeadersError::TooLarge(size));
+            }
+        }
+
+        match headers
+            .into_iter()
+            .map(|h| Header::new_validate(h.0, h.1))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(Headers::from_vec_pseudo_first)
+        {
+            Ok(headers) => Ok(headers),
+            Err(e) => {
+                // All pseudo-header fields MUST appear in the header block before
+                // regular header fields. Any request or response that contains
+                // a pseudo-header field that appears in a header block after
+                // a regular header field MUST be treated as malformed (Section 8.1.2.6).
+                warn!(
+                    "received incorrect headers in stream {}: {:?}",
+                    stream_id, e
+                );
+                // TODO: close connection, because decoder may be in incorrect state
+                Err(DecodeHeadersError::Conn(ErrorCode::ProtocolError))
+            }
         }
     }
 
@@ -36,42 +128,34 @@ impl<R: AsyncRead + Unpin> HttpDecodeRead<R> {
         cx: &mut Context<'_>,
         max_frame_size: u32,
     ) -> Poll<result::Result<HttpFrameDecodedOrGoaway>> {
-        let frame = match self.framed_read.poll_http_frame(cx, max_frame_size)? {
-            Poll::Ready(frame) => frame,
+        let frame = match self.framed_read.poll_http_frame(cx, max_frame_size) {
+            Poll::Ready(Ok(frame)) => frame,
+            Poll::Ready(Err(error::Error::HeaderBlockTooLarge(size, limit))) => {
+                warn!("header block size {} exceeds limit {}", size, limit);
+                return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(
+                    ErrorCode::EnhanceYourCalm,
+                )));
+            }
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
             Poll::Pending => return Poll::Pending,
         };
         Poll::Ready(Ok(HttpFrameDecodedOrGoaway::Frame(match frame {
             HttpFrame::Data(frame) => HttpFrameDecoded::Data(frame),
             HttpFrame::Headers(frame) => {
-                let headers = match self.decoder.decode(frame.header_fragment) {
-                    Err(e) => {
-                        warn!("failed to decode headers: {:?}", e);
-                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(
-                            ErrorCode::CompressionError,
-                        )));
-                    }
-                    Ok(headers) => headers,
+                let end_stream = match frame.is_end_of_stream() {
+                    true => EndStream::Yes,
+                    false => EndStream::No,
                 };
-
-                let headers = match headers
-                    .into_iter()
-                    .map(|h| Header::new_validate(h.0, h.1))
-                    .collect::<Result<Vec<_>, _>>()
-                    .and_then(Headers::from_vec_pseudo_first)
-                {
+                let headers = match self.decode_headers(frame.header_fragment, frame.stream_id) {
                     Ok(headers) => headers,
-                    Err(e) => {
-                        // All pseudo-header fields MUST appear in the header block before
-                        // regular header fields. Any request or response that contains
-                        // a pseudo-header field that appears in a header block after
-                        // a regular header field MUST be treated as malformed (Section 8.1.2.6).
-                        warn!(
-                            "received incorrect headers in stream {}: {:?}",
-                            frame.stream_id, e
-                        );
-                        // TODO: close connection, because decoder may be in incorrect state
-                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(
-                            ErrorCode::ProtocolError,
+                    Err(DecodeHeadersError::Conn(error_code)) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(error_code)))
+                    }
+                    Err(DecodeHeadersError::TooLarge(size)) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::HeaderListTooLarge(
+                            frame.stream_id,
+                            end_stream,
+                            size,
                         )));
                     }
                 };
@@ -87,10 +171,32 @@ impl<R: AsyncRead + Unpin> HttpDecodeRead<R> {
             HttpFrame::Priority(frame) => HttpFrameDecoded::Priority(frame),
             HttpFrame::RstStream(frame) => HttpFrameDecoded::RstStream(frame),
             HttpFrame::Settings(frame) => HttpFrameDecoded::Settings(frame),
-            HttpFrame::PushPromise(frame) => HttpFrameDecoded::PushPromise(frame),
+            HttpFrame::PushPromise(frame) => {
+                // Decoded even if push is not enabled to keep decoder state in sync
+                let headers = match self.decode_headers(frame.header_fragment, frame.stream_id) {
+                    Ok(headers) => headers,
+                    Err(DecodeHeadersError::Conn(error_code)) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::SendGoaway(error_code)))
+                    }
+                    Err(DecodeHeadersError::TooLarge(size)) => {
+                        return Poll::Ready(Ok(HttpFrameDecodedOrGoaway::HeaderListTooLarge(
+                            frame.promised_stream_id,
+                            EndStream::Yes,
+                            size,
+                        )));
+                    }
+                };
+
+                HttpFrameDecoded::PushPromise(PushPromiseDecodedFrame {
+                    stream_id: frame.stream_id,
+                    promised_stream_id: frame.promised_stream_id,
+                    headers,
+                })
+            }
             HttpFrame::Ping(frame) => HttpFrameDecoded::Ping(frame),
             HttpFrame::Goaway(frame) => HttpFrameDecoded::Goaway(frame),
             HttpFrame::WindowUpdate(frame) => HttpFrameDecoded::WindowUpdate(frame),
+            HttpFrame::PriorityUpdate(frame) => HttpFrameDecoded::PriorityUpdate(frame),
             HttpFrame::Continuation(_frame) => {
                 unreachable!("must be joined with HEADERS before that")
             }
@@ -1,6 +1,7 @@
 use bytes::Bytes;
 use bytes::BytesMut;
 
+use crate::common::traffic::TrafficStats;
 use crate::error;
 use crate::result;
 use crate::solicit::frame::unpack_header_from_slice;
@@ -23,6 +24,8 @@ use tokio::io::AsyncRead;
 pub struct HttpFramedRead<R: AsyncRead + Unpin> {
     read: R,
     buf: BytesMut,
+    /// Frames received so far
+    received: TrafficStats,
 }
 
 impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
@@ -30,6 +33,7 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
         HttpFramedRead {
             read,
             buf: BytesMut::new(),
+            received: TrafficStats::default(),
         }
     }
 
@@ -87,6 +91,9 @@ impl<R: AsyncRead + Unpin> HttpFramedRead<R> {
             return Poll::Pending;
         }
 
+        self.received
+            .frame(RawHttpFrameType(header.frame_type), header.payload_len);
+
         Poll::Ready(Ok(RawFrame {
             raw_content: self.buf.split_to(total_len).freeze(),
         }))
@@ -167,15 +174,30 @@ impl Continuable {
 
 pub struct HttpFramedJoinContinuationRead<R: AsyncRead + Unpin> {
     framed_read: HttpFramedRead<R>,
-    // TODO: check total size is not exceeded some limit
     header_opt: Option<Continuable>,
+    /// Max total size of header block fragments
+    max_header_block_size: Option<u32>,
 }
 
 impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
-    pub fn new(read: R) -> Self {
+    pub fn new(read: R, max_header_block_size: Option<u32>) -> Self {
         HttpFramedJoinContinuationRead {
             framed_read: HttpFramedRead::new(read),
             header_opt: None,
+            max_header_block_size,
+        }
+    }
+
+    pub fn received_traffic(&self) -> &TrafficStats {
+        &self.framed_read.received
+    }
+
+    fn check_header_block_size(&self, size: usize) -> result::Result<()> {
+        match self.max_header_block_size {
+            Some(limit) if size > limit as usize => {
+                Err(error::Error::HeaderBlockTooLarge(size, limit))
+            }
+            _ => Ok(()),
         }
     }
 
@@ -197,6 +219,7 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                             RawHttpFrameType::HEADERS,
                         )));
                     } else {
+                        self.check_header_block_size(h.header_fragment.len())?;
                         if h.flags.is_set(HeadersFlag::EndHeaders) {
                             return Poll::Ready(Ok(HttpFrame::Headers(h)));
                         } else {
@@ -211,6 +234,7 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                             RawHttpFrameType::PUSH_PROMISE,
                         )));
                     } else {
+                        self.check_header_block_size(p.header_fragment.len())?;
                         if p.flags.is_set(PushPromiseFlag::EndHeaders) {
                             return Poll::Ready(Ok(HttpFrame::PushPromise(p)));
                         } else {
@@ -230,6 +254,9 @@ impl<R: AsyncRead + Unpin> HttpFramedJoinContinuationRead<R> {
                             ));
                         } else {
                             let header_end = c.is_headers_end();
+                            self.check_header_block_size(
+                                h.header_fragment.len() + c.header_fragment.len(),
+                            )?;
                             h.extend_header_fragment(c.header_fragment);
                             if header_end {
                                 h.set_end_headers();
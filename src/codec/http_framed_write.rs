@@ -2,7 +2,9 @@ use crate::result;
 use tokio::io::AsyncWrite;
 
 use crate::codec::write_buffer::WriteBuffer;
+use crate::common::traffic::TrafficStats;
 use crate::solicit::frame::FrameIR;
+use crate::solicit::frame::HeadersMultiFrame;
 use bytes::Buf;
 use futures::task::Context;
 use std::pin::Pin;
@@ -25,12 +27,23 @@ impl<W: AsyncWrite + Unpin> HttpFramedWrite<W> {
         self.buf.remaining()
     }
 
+    /// Frames buffered for sending so far.
+    pub fn sent_traffic(&self) -> &TrafficStats {
+        self.buf.traffic()
+    }
+
     pub fn buffer_frame<F: FrameIR>(&mut self, frame: F) {
         debug!("send {:?}", frame);
 
         frame.serialize_into(&mut self.buf);
     }
 
+    pub fn buffer_headers(&mut self, frame: HeadersMultiFrame) {
+        debug!("send {:?}", frame);
+
+        frame.serialize_into(&mut self.buf);
+    }
+
     pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<result::Result<()>> {
         loop {
             if !self.buf.has_remaining() {
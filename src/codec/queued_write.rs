@@ -1,7 +1,9 @@
 use crate::codec::http_framed_write::HttpFramedWrite;
+use crate::common::traffic::TrafficStats;
 use crate::result;
 use crate::solicit::frame::FrameIR;
 use crate::solicit::frame::GoawayFrame;
+use crate::solicit::frame::HeadersMultiFrame;
 use futures::task::Context;
 use std::task::Poll;
 use tokio::io::AsyncWrite;
@@ -24,7 +26,11 @@ impl<W: AsyncWrite + Unpin> QueuedWrite<W> {
         self.framed_write.data_len()
     }
 
-    pub fn _queued_empty(&self) -> bool {
+    pub fn sent_traffic(&self) -> &TrafficStats {
+        self.framed_write.sent_traffic()
+    }
+
+    pub fn queued_empty(&self) -> bool {
         self.queued_bytes_len() == 0
     }
 
@@ -36,6 +42,14 @@ impl<W: AsyncWrite + Unpin> QueuedWrite<W> {
         self.framed_write.buffer_frame(frame)
     }
 
+    pub fn queue_headers_not_goaway(&mut self, frame: HeadersMultiFrame) {
+        if self.goaway_queued {
+            return;
+        }
+
+        self.framed_write.buffer_headers(frame)
+    }
+
     pub fn queue_goaway(&mut self, frame: GoawayFrame) {
         // If we decided to terminate, send goaway immediately
         // and discard queued frames
@@ -47,6 +61,12 @@ impl<W: AsyncWrite + Unpin> QueuedWrite<W> {
         self.framed_write.buffer_frame(frame);
     }
 
+    /// Close connection once queued frames are flushed,
+    /// used when `GOAWAY` was already sent.
+    pub fn terminate(&mut self) {
+        self.goaway_queued = true;
+    }
+
     pub fn poll(&mut self, cx: &mut Context<'_>) -> Poll<result::Result<()>> {
         self.framed_write.poll_flush(cx)
     }
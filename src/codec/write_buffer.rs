@@ -1,6 +1,11 @@
 use crate::bytes_ext::buf_vec_deque::BufVecDeque;
 use crate::codec::zeroes::Zeroes;
+use crate::common::traffic::TrafficStats;
+use crate::solicit::frame::pack_header;
+use crate::solicit::frame::FrameBuilder;
+use crate::solicit::frame::FrameHeader;
 use crate::solicit::frame::FrameHeaderBuffer;
+use crate::solicit::frame::RawHttpFrameType;
 use crate::BufGetBytes;
 use bytes::Buf;
 use bytes::Bytes;
@@ -67,6 +72,8 @@ impl BufGetBytes for Item {
 #[derive(Default)]
 pub struct WriteBuffer {
     deque: BufVecDeque<Item>,
+    /// Frames ever written into this buffer
+    traffic: TrafficStats,
 }
 
 impl Buf for WriteBuffer {
@@ -110,6 +117,16 @@ impl WriteBuffer {
         self.deque.push_back(Item::Bytes(data));
     }
 
+    /// Frames ever written into this buffer.
+    pub fn traffic(&self) -> &TrafficStats {
+        &self.traffic
+    }
+
+    pub(crate) fn account_frame(&mut self, header: &FrameHeader) {
+        self.traffic
+            .frame(RawHttpFrameType(header.frame_type), header.payload_len);
+    }
+
     pub fn extend_frame_header_buffer(&mut self, buffer: FrameHeaderBuffer) {
         self.deque
             .push_back(Item::FrameHeaderBuffer(Cursor::new(buffer)));
@@ -143,6 +160,25 @@ impl WriteBuffer {
     }
 }
 
+impl FrameBuilder for WriteBuffer {
+    fn write_slice(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+
+    fn write_bytes(&mut self, bytes: Bytes) {
+        self.extend_from_bytes(bytes);
+    }
+
+    fn write_header(&mut self, header: FrameHeader) {
+        self.account_frame(&header);
+        self.extend_frame_header_buffer(pack_header(&header));
+    }
+
+    fn write_padding(&mut self, padding_length: u8) {
+        self.extend_with_zeroes(padding_length as usize);
+    }
+}
+
 impl Into<Vec<u8>> for WriteBuffer {
     fn into(mut self) -> Vec<u8> {
         let mut v = Vec::with_capacity(self.remaining());
@@ -183,6 +219,10 @@ impl<'a> WriteBufferTailVec<'a> {
         self.data.len() - self.position
     }
 
+    pub(crate) fn account_frame(&mut self, header: &FrameHeader) {
+        self.write_buffer.account_frame(header);
+    }
+
     /// Pos is relative to "data"
     pub fn patch_buf(&mut self, pos: usize, data: &[u8]) {
         let patch_pos = self.position + pos;
@@ -0,0 +1,102 @@
+//! Growing connection receive window to bandwidth-delay product
+//! measured with `PING` frames.
+
+use std::time::Duration;
+
+use crate::solicit::DEFAULT_SETTINGS;
+
+/// Connection window is not grown above this size.
+const MAX_WINDOW: u32 = 16 << 20;
+
+/// Bandwidth-delay product estimator of a connection.
+///
+/// `DATA` bytes received while `PING` is in flight is the amount of data
+/// the peer sent in one round trip. When it is close to the window size,
+/// the window limits the throughput, so the window is grown.
+pub(crate) struct Bdp {
+    /// Connection receive window to maintain
+    window: u32,
+    /// `DATA` payload received since `PING` was sent
+    bytes: u32,
+}
+
+impl Bdp {
+    pub fn new() -> Bdp {
+        Bdp {
+            window: DEFAULT_SETTINGS.initial_window_size,
+            bytes: 0,
+        }
+    }
+
+    /// Connection receive window to maintain.
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    pub fn ping_sent(&mut self) {
+        self.bytes = 0;
+    }
+
+    pub fn data_recvd(&mut self, len: u32) {
+        self.bytes = self.bytes.saturating_add(len);
+    }
+
+    /// `PING` ack is received after `rtt`, `srtt` is smoothed RTT
+    /// including this sample.
+    ///
+    /// Return window increment if window should grow.
+    pub fn ping_acked(&mut self, rtt: Duration, srtt: Duration) -> Option<u32> {
+        let bytes = self.bytes;
+        self.bytes = 0;
+
+        if rtt == Duration::from_secs(0) {
+            return None;
+        }
+
+        // bytes received during smoothed round trip at the measured bandwidth
+        let bdp = bytes as f64 * srtt.as_secs_f64() / rtt.as_secs_f64();
+        if bdp < self.window as f64 * 2.0 / 3.0 {
+            return None;
+        }
+
+        let window = (bdp * 2.0).min(MAX_WINDOW as f64) as u32;
+        if window <= self.window {
+            return None;
+        }
+        let increment = window - self.window;
+        debug!("BDP {}, grow conn window to {}", bdp as u64, window);
+        self.window = window;
+        Some(increment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grow() {
+        let rtt = Duration::from_millis(10);
+
+        let mut bdp = Bdp::new();
+        bdp.ping_sent();
+        bdp.data_recvd(10000);
+        assert_eq!(None, bdp.ping_acked(rtt, rtt));
+
+        bdp.ping_sent();
+        bdp.data_recvd(60000);
+        assert_eq!(Some(120000 - 65535), bdp.ping_acked(rtt, rtt));
+        assert_eq!(120000, bdp.window());
+
+        // twice slower sample, but smoothed RTT is the same,
+        // so bandwidth is lower
+        bdp.ping_sent();
+        bdp.data_recvd(120000);
+        assert_eq!(None, bdp.ping_acked(rtt * 2, rtt));
+
+        bdp.ping_sent();
+        bdp.data_recvd(u32::max_value());
+        assert_eq!(Some(MAX_WINDOW - 120000), bdp.ping_acked(rtt, rtt));
+        assert_eq!(MAX_WINDOW, bdp.window());
+    }
+}
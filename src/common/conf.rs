@@ -1,8 +1,71 @@
+use std::sync::Arc;
+
+use crate::common::metrics::Metrics;
+use crate::common::misbehavior::MisbehaviorLimits;
+use crate::common::padding::Padding;
+use crate::context::ContextPropagation;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::SettingsFrame;
+
+/// Configuration common for client and server.
 #[derive(Default, Debug, Clone)]
-pub struct CommonConf {}
+pub struct CommonConf {
+    /// Flood and abuse protection thresholds.
+    pub misbehavior_limits: MisbehaviorLimits,
+    /// Use HPACK dynamic table, default `true`.
+    ///
+    /// When `false`, headers are encoded with static table and literals only,
+    /// and `SETTINGS_HEADER_TABLE_SIZE` of zero is advertised to the peer.
+    /// Useful for intermediaries with many connections where dynamic tables
+    /// dominate memory usage.
+    pub hpack_dynamic_table: Option<bool>,
+    /// Huffman-encode header names and values when it makes them shorter,
+    /// default `true`.
+    pub hpack_huffman: Option<bool>,
+    /// Codecs mapping `RequestContext` to request headers.
+    ///
+    /// Server extracts context of incoming requests only if specified,
+    /// client uses default propagation if not specified.
+    pub context_propagation: Option<ContextPropagation>,
+    /// Advertised `SETTINGS_MAX_HEADER_LIST_SIZE`, not limited by default.
+    ///
+    /// Size of decoded header list counts 32 bytes of overhead per header.
+    /// Server responds with `431` to requests with larger headers,
+    /// client fails such responses with `Error::HeaderListTooLarge`.
+    pub max_header_list_size: Option<u32>,
+    /// Pad outgoing `DATA` and `HEADERS` frames, no padding by default.
+    pub padding: Option<Padding>,
+    /// Advertised `SETTINGS_HEADER_TABLE_SIZE`, max size of HPACK dynamic table
+    /// peer may use to encode headers sent to us, default is 4096.
+    ///
+    /// Ignored if `hpack_dynamic_table` is `false`.
+    pub header_table_size: Option<u32>,
+    /// Names of headers always encoded as HPACK never-indexed literals,
+    /// for example `authorization` or `cookie`, none by default.
+    ///
+    /// Individual headers can be marked with `Headers::add_sensitive`.
+    pub sensitive_headers: Option<Vec<String>>,
+    /// Sink for connection, stream and traffic metrics, see `Metric`.
+    /// Metrics are not collected by default.
+    pub metrics: Option<Arc<dyn Metrics>>,
+}
 
 impl CommonConf {
     pub fn new() -> CommonConf {
         Default::default()
     }
+
+    /// Initial `SETTINGS` frame sent to peer.
+    pub(crate) fn settings_frame(&self) -> SettingsFrame {
+        let mut settings = vec![HttpSetting::EnablePush(false)];
+        if self.hpack_dynamic_table == Some(false) {
+            settings.push(HttpSetting::HeaderTableSize(0));
+        } else if let Some(header_table_size) = self.header_table_size {
+            settings.push(HttpSetting::HeaderTableSize(header_table_size));
+        }
+        if let Some(max_header_list_size) = self.max_header_list_size {
+            settings.push(HttpSetting::MaxHeaderListSize(max_header_list_size));
+        }
+        SettingsFrame::from_settings(settings)
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::pin::Pin;
 
 use crate::error;
@@ -6,10 +7,13 @@ use crate::result;
 use crate::AnySocketAddr;
 
 use crate::solicit::frame::GoawayFrame;
+use crate::solicit::frame::HttpFrameDecoded;
 use crate::solicit::frame::HttpFrameType;
+use crate::solicit::frame::HttpSetting;
 use crate::solicit::frame::HttpSettings;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::WindowUpdateFrame;
+use crate::solicit::priority::Priority;
 use crate::solicit::session::StreamState;
 use crate::solicit::session::StreamStateIdleOrClosed;
 use crate::solicit::DEFAULT_SETTINGS;
@@ -20,25 +24,35 @@ use super::stream::*;
 use super::stream_map::*;
 use super::types::*;
 use super::window_size;
+use crate::common::bdp::Bdp;
+use crate::common::conn_id::ConnId;
+use crate::common::idle_timeout::IdleTimeout;
+use crate::common::keepalive::Keepalive;
+use crate::common::metrics::ConnMetrics;
+use crate::common::metrics::StreamMetrics;
+use crate::common::misbehavior::MisbehaviorCounters;
+use crate::common::padding::Padding;
+use crate::common::traffic::ConnTraffic;
 
 pub use crate::resp::Response;
 
 use crate::client_died_error_holder::ConnDiedType;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::codec::http_decode_read::HttpDecodeRead;
+use crate::codec::http_decode_read::HttpFrameDecodedOrGoaway;
 use crate::codec::queued_write::QueuedWrite;
 use crate::common::conn_command_channel::ConnCommandReceiver;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::ConnWriteSideCustom;
 use crate::common::init_where::InitWhere;
-use crate::hpack;
 use crate::solicit::stream_id::StreamId;
 use crate::solicit::window_size::NonNegativeWindowSize;
 use crate::solicit::window_size::WindowSize;
 use crate::ErrorCode;
 use futures::channel::oneshot;
 use futures::future;
+use httpbis_codec::hpack;
 
 use crate::common::loop_event::LoopEvent;
 use crate::log_ndc_future::log_ndc_future;
@@ -49,18 +63,23 @@ use futures::task::Context;
 use std::mem;
 use std::sync::Arc;
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io::split;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 use tokio::io::ReadHalf;
 use tokio::io::WriteHalf;
 use tokio::runtime::Handle;
+use tokio::time;
+use tokio::time::Interval;
 
 /// Client or server fields of connection
 pub trait ConnSpecific: Send + 'static {}
 
 /// HTTP/2 connection state with socket and streams
 pub(crate) struct Conn<T: Types, I: AsyncWrite + AsyncRead + Send + 'static> {
+    pub conn_id: ConnId,
     pub peer_addr: AnySocketAddr,
 
     pub conn_died_error_holder: SomethingDiedErrorHolder<ConnDiedType>,
@@ -80,7 +99,32 @@ pub(crate) struct Conn<T: Types, I: AsyncWrite + AsyncRead + Send + 'static> {
     pub last_peer_stream_id: StreamId,
     pub goaway_sent: Option<GoawayFrame>,
     pub goaway_received: Option<GoawayFrame>,
-    pub ping_sent: Option<u64>,
+    /// Connection loop fails with this error once queued `GOAWAY` is flushed
+    pub close_error: Option<error::Error>,
+    /// Opaque data and send time of `PING` waiting for ack
+    pub ping_sent: Option<(u64, Instant)>,
+    /// Opaque data of the last `PING` frame sent
+    pub ping_last_opaque_data: u64,
+    /// Notified with round-trip time when `PING` ack is received
+    pub ping_waiters: Vec<oneshot::Sender<result::Result<Duration>>>,
+    /// Send `PING` periodically if set
+    pub ping_interval: Option<Interval>,
+    /// Send `PING` on idle connection and wait for ack if set
+    pub keepalive: Option<Keepalive>,
+    /// Send `GOAWAY` when connection has no streams and receives no frames if set
+    pub idle_timeout: Option<IdleTimeout>,
+    /// Smoothed round-trip time measured with `PING` frames
+    pub smoothed_rtt: Option<Duration>,
+    /// Grows connection receive window if set
+    pub bdp: Option<Bdp>,
+    /// Counters checked against configured misbehavior limits
+    pub misbehavior: MisbehaviorCounters,
+    /// Priorities received in `PRIORITY_UPDATE` for streams not opened yet
+    pub pending_priorities: HashMap<StreamId, Priority>,
+    /// Padding of outgoing `DATA` and `HEADERS` frames
+    pub padding: Option<Padding>,
+    /// Reports metrics if configured
+    pub metrics: Option<ConnMetrics>,
 
     /// Tracks the size of the outbound flow control window
     pub out_window_size: WindowSize,
@@ -103,21 +147,46 @@ pub(crate) struct Conn<T: Types, I: AsyncWrite + AsyncRead + Send + 'static> {
     pub our_settings_ack: HttpSettings,
     /// Last our settings sent
     pub our_settings_sent: Option<HttpSettings>,
+    /// Notified when sent settings are acknowledged
+    pub our_settings_waiter: Option<oneshot::Sender<result::Result<()>>>,
+    /// Settings updates waiting for ack of previously sent settings
+    pub our_settings_updates: VecDeque<(Vec<HttpSetting>, oneshot::Sender<result::Result<()>>)>,
 }
 
 impl<T: Types, I: AsyncWrite + AsyncRead + Send + 'static> Drop for Conn<T, I> {
     fn drop(&mut self) {
         mem::take(&mut self.streams).conn_died(|| self.conn_died_error_holder.error());
+        self.report_traffic();
+    }
+}
+
+impl<T: Types, I: AsyncWrite + AsyncRead + Send + 'static> Conn<T, I> {
+    /// Report traffic not yet reported to metrics.
+    fn report_traffic(&mut self) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.traffic(&ConnTraffic {
+                sent: *self.queued_write.sent_traffic(),
+                received: *self.framed_read.received_traffic(),
+            });
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ConnStateSnapshot {
+    pub conn_id: ConnId,
     pub peer_addr: AnySocketAddr,
     pub in_window_size: i32,
     pub out_window_size: i32,
     pub pump_out_window_size: isize,
     pub out_buf_bytes: usize,
+    /// Smoothed round-trip time of `PING` frames (RFC 6298 averaging),
+    /// `None` if no `PING` was acknowledged yet
+    pub rtt: Option<Duration>,
+    /// Frames and bytes sent and received
+    pub traffic: ConnTraffic,
+    /// Settings received from peer
+    pub peer_settings: HttpSettings,
     pub streams: HashMap<StreamId, HttpStreamStateSnapshot>,
 }
 
@@ -130,6 +199,17 @@ impl ConnStateSnapshot {
     }
 }
 
+/// Socket and channels of a connection, see `Conn::new`.
+pub(crate) struct ConnIo<T: Types, I> {
+    pub loop_handle: Handle,
+    pub socket: I,
+    pub conn_id: ConnId,
+    pub peer_addr: AnySocketAddr,
+    pub to_write_tx: ConnCommandSender<T>,
+    pub write_rx: ConnCommandReceiver<T>,
+    pub conn_died_error_holder: SomethingDiedErrorHolder<ConnDiedType>,
+}
+
 impl<T, I> Conn<T, I>
 where
     T: Types,
@@ -139,16 +219,21 @@ where
     I: AsyncWrite + AsyncRead + Send + 'static,
 {
     pub fn new(
-        loop_handle: Handle,
+        io: ConnIo<T, I>,
         specific: T::ConnSpecific,
-        _conf: CommonConf,
+        conf: CommonConf,
         sent_settings: HttpSettings,
-        to_write_tx: ConnCommandSender<T>,
-        write_rx: ConnCommandReceiver<T>,
-        socket: I,
-        peer_addr: AnySocketAddr,
-        conn_died_error_holder: SomethingDiedErrorHolder<ConnDiedType>,
     ) -> Self {
+        let ConnIo {
+            loop_handle,
+            socket,
+            conn_id,
+            peer_addr,
+            to_write_tx,
+            write_rx,
+            conn_died_error_holder,
+        } = io;
+
         let in_window_size =
             NonNegativeWindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32);
         let out_window_size = WindowSize::new(DEFAULT_SETTINGS.initial_window_size as i32);
@@ -157,10 +242,24 @@ where
 
         let (read, write) = split(socket);
 
-        let framed_read = HttpDecodeRead::new(read);
+        let framed_read = HttpDecodeRead::new(
+            read,
+            conf.misbehavior_limits.max_header_block_size,
+            conf.max_header_list_size,
+        );
         let queued_write = QueuedWrite::new(write);
 
+        let mut encoder = match conf.hpack_dynamic_table {
+            Some(false) => hpack::Encoder::without_dynamic_table(),
+            _ => hpack::Encoder::new(),
+        };
+        encoder.set_huffman(conf.hpack_huffman.unwrap_or(true));
+        if let Some(ref sensitive_headers) = conf.sensitive_headers {
+            encoder.set_sensitive_names(sensitive_headers);
+        }
+
         Conn {
+            conn_id,
             peer_addr,
             conn_died_error_holder,
             specific,
@@ -171,18 +270,34 @@ where
             loop_handle,
             goaway_sent: None,
             goaway_received: None,
+            close_error: None,
             ping_sent: None,
+            ping_last_opaque_data: 0,
+            ping_waiters: Vec::new(),
+            ping_interval: None,
+            keepalive: None,
+            idle_timeout: None,
+            smoothed_rtt: None,
+            bdp: None,
+            misbehavior: MisbehaviorCounters::new(conf.misbehavior_limits),
+            pending_priorities: HashMap::new(),
+            padding: conf.padding,
+            metrics: conf
+                .metrics
+                .map(|metrics| ConnMetrics::new(metrics, T::CLIENT_OR_SERVER)),
             pump_out_window_size: pump_window_size,
             peer_closed_streams: ClosedStreams::new(),
             framed_read,
             queued_write,
             write_rx,
-            encoder: hpack::Encoder::new(),
+            encoder,
             in_window_size,
             out_window_size,
             peer_settings: DEFAULT_SETTINGS,
             our_settings_ack: DEFAULT_SETTINGS,
             our_settings_sent: Some(sent_settings),
+            our_settings_waiter: None,
+            our_settings_updates: VecDeque::new(),
         }
     }
 
@@ -208,7 +323,7 @@ where
             .new_stream(self.peer_settings.initial_window_size as u32);
 
         let stream = HttpStreamCommon::new(
-            self.our_settings_sent().initial_window_size,
+            self.our_settings_ack.initial_window_size,
             self.peer_settings.initial_window_size,
             out_window_sender,
             in_rem_content_length,
@@ -216,22 +331,97 @@ where
             specific,
         );
 
-        let stream = self.streams.insert(stream_id, stream);
+        let metrics = self.stream_metrics();
+        let mut stream = self.streams.insert(stream_id, stream);
+        stream.stream().metrics = metrics;
 
         (stream, out_window_receiver)
     }
 
+    /// Metrics of a new stream if metrics are configured.
+    pub fn stream_metrics(&self) -> Option<StreamMetrics> {
+        self.metrics.as_ref().map(|m| m.stream())
+    }
+
+    /// Report time spent establishing the connection.
+    pub fn handshake_done(&self, started: Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics.handshake_done(started);
+        }
+    }
+
     pub fn dump_state(&self) -> ConnStateSnapshot {
         ConnStateSnapshot {
+            conn_id: self.conn_id,
             peer_addr: self.peer_addr.clone(),
             in_window_size: self.in_window_size.size(),
             out_window_size: self.out_window_size.size(),
             pump_out_window_size: self.pump_out_window_size.get(),
             out_buf_bytes: self.queued_write.queued_bytes_len(),
+            rtt: self.smoothed_rtt,
+            traffic: self.traffic(),
+            peer_settings: self.peer_settings,
             streams: self.streams.snapshot(),
         }
     }
 
+    /// Traffic counters of this connection.
+    pub fn traffic(&self) -> ConnTraffic {
+        ConnTraffic {
+            sent: *self.queued_write.sent_traffic(),
+            received: *self.framed_read.received_traffic(),
+        }
+    }
+
+    /// Send `PING` to peer every `interval` to measure round-trip time.
+    pub fn set_ping_interval(&mut self, interval: Option<Duration>) {
+        self.ping_interval =
+            interval.map(|interval| time::interval_at(time::Instant::now() + interval, interval));
+    }
+
+    /// Send `PING` when no frames are received during `interval`,
+    /// and close connection if ack is not received within `timeout`.
+    pub fn set_keepalive(&mut self, interval: Option<Duration>, timeout: Option<Duration>) {
+        self.keepalive = interval.map(|interval| Keepalive::new(interval, timeout));
+    }
+
+    /// Gracefully close connection when it has no open streams
+    /// and no frames are received during `timeout`.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout.map(IdleTimeout::new);
+    }
+
+    /// Grow connection receive window to bandwidth-delay product
+    /// measured with `PING` frames sent while `DATA` is received.
+    pub fn set_adaptive_window(&mut self, adaptive_window: bool) {
+        self.bdp = if adaptive_window {
+            Some(Bdp::new())
+        } else {
+            None
+        };
+    }
+
+    /// Connection receive window to maintain.
+    pub fn in_window_target(&self) -> u32 {
+        match &self.bdp {
+            Some(bdp) => bdp.window(),
+            None => DEFAULT_SETTINGS.initial_window_size,
+        }
+    }
+
+    /// Update smoothed round-trip time with a new sample.
+    pub fn update_rtt(&mut self, sample: Duration) {
+        // Same smoothing factor as in TCP (RFC 6298)
+        let srtt = match self.smoothed_rtt {
+            Some(srtt) => (srtt * 7 + sample) / 8,
+            None => sample,
+        };
+        self.smoothed_rtt = Some(srtt);
+        if let Some(metrics) = &self.metrics {
+            metrics.rtt(sample);
+        }
+    }
+
     pub fn our_settings_sent(&self) -> &HttpSettings {
         if let Some(ref sent) = self.our_settings_sent {
             &sent
@@ -309,7 +499,7 @@ where
         }
     }
 
-    fn stream_state(&self, stream_id: StreamId) -> StreamState {
+    pub fn stream_state(&self, stream_id: StreamId) -> StreamState {
         match self.streams.get_stream_state(stream_id) {
             Some(state) => state,
             None => self.stream_state_idle_or_closed(stream_id).into(),
@@ -325,12 +515,10 @@ where
 
         match stream_state {
             StreamState::Idle => {
-                let send_connection_error = match frame_type {
-                    HttpFrameType::Headers
-                    | HttpFrameType::Priority
-                    | HttpFrameType::PushPromise => false,
-                    _ => true,
-                };
+                let send_connection_error = !matches!(
+                    frame_type,
+                    HttpFrameType::Headers | HttpFrameType::Priority | HttpFrameType::PushPromise
+                );
 
                 if send_connection_error {
                     debug!("stream is idle: {}, sending GOAWAY", stream_id);
@@ -345,12 +533,12 @@ where
                 // WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
                 // this state, it MUST respond with a stream error (Section 5.4.2) of
                 // type STREAM_CLOSED.
-                let send_rst = match frame_type {
+                let send_rst = !matches!(
+                    frame_type,
                     HttpFrameType::WindowUpdate
-                    | HttpFrameType::Priority
-                    | HttpFrameType::RstStream => false,
-                    _ => true,
-                };
+                        | HttpFrameType::Priority
+                        | HttpFrameType::RstStream
+                );
 
                 if send_rst {
                     debug!(
@@ -380,15 +568,13 @@ where
                 // time after sending END_STREAM as a connection error
                 // (Section 5.4.1) of type PROTOCOL_ERROR.
 
-                let send_stream_closed = match frame_type {
+                let send_stream_closed = !matches!(
+                    frame_type,
                     HttpFrameType::RstStream
-                    | HttpFrameType::Priority
-                    | HttpFrameType::WindowUpdate => false,
-                    _ => true,
-                };
+                        | HttpFrameType::Priority
+                        | HttpFrameType::WindowUpdate
+                );
 
-                // TODO: http2 spec requires sending stream or connection error
-                // depending on how stream was closed
                 if send_stream_closed {
                     if self.peer_closed_streams.contains(stream_id) {
                         debug!("stream is closed by peer: {}, sending GOAWAY", stream_id);
@@ -431,14 +617,48 @@ where
     }
 
     fn poll_next_event(&mut self, cx: &mut Context<'_>) -> Poll<result::Result<LoopEvent<T>>> {
+        if let Some(ping_interval) = &mut self.ping_interval {
+            let mut tick = false;
+            // Poll until `Pending` to be woken up on next tick
+            while ping_interval.poll_tick(cx).is_ready() {
+                tick = true;
+            }
+            if tick {
+                self.send_ping(None)?;
+            }
+        }
+
+        if let Some(keepalive) = &mut self.keepalive {
+            if keepalive.poll(cx, self.ping_sent.is_some())? {
+                self.send_ping(None)?;
+            }
+        }
+
+        if let Some(idle_timeout) = &mut self.idle_timeout {
+            if !self.streams.is_empty() {
+                idle_timeout.activity();
+            } else if self.goaway_sent.is_none() && idle_timeout.poll(cx) {
+                info!("connection is idle, sending GOAWAY");
+                self.send_goaway(ErrorCode::NoError)?;
+            }
+        }
+
         // Always flush outgoing queue
         self.poll_flush(cx)?;
 
+        self.report_traffic();
+
         if self.queued_write.goaway_queued_and_flushed() {
             info!("GOAWAY written and flushed, closing connection");
             return Poll::Ready(Ok(LoopEvent::ExitLoop));
         }
 
+        if self.goaway_sent.is_some() && self.streams.is_empty() && self.queued_write.queued_empty()
+        {
+            info!("GOAWAY sent and streams is empty, closing connection");
+            return Poll::Ready(Ok(LoopEvent::ExitLoop));
+        }
+
         if self.goaway_received.is_some() && self.streams.is_empty() {
             info!("GOAWAY received and streams is empty, closing connection");
             return Poll::Ready(Ok(LoopEvent::ExitLoop));
@@ -453,8 +673,24 @@ where
             }
         };
 
+        let received_before = *self.framed_read.received_traffic();
         match self.poll_recv_http_frame(cx)? {
-            Poll::Ready(m) => return Poll::Ready(Ok(LoopEvent::Frame(m))),
+            Poll::Ready(m) => {
+                if let Some(keepalive) = &mut self.keepalive {
+                    keepalive.frame_received();
+                }
+                if let Some(idle_timeout) = &mut self.idle_timeout {
+                    // keepalive `PING` does not make connection busy
+                    if !matches!(
+                        m,
+                        HttpFrameDecodedOrGoaway::Frame(HttpFrameDecoded::Ping(..))
+                    ) {
+                        idle_timeout.activity();
+                    }
+                }
+                let traffic = self.framed_read.received_traffic().since(&received_before);
+                return Poll::Ready(Ok(LoopEvent::Frame(m, traffic)));
+            }
             Poll::Pending => {}
         }
 
@@ -466,19 +702,41 @@ where
         future::poll_fn(|cx| self.poll_next_event(cx)).await
     }
 
-    async fn run_loop(mut self) -> result::Result<()> {
+    async fn process_events(&mut self) -> result::Result<()> {
         loop {
             let event = self.next_event().await?;
             match event {
                 LoopEvent::ToWriteMessage(m) => self.process_message(m)?,
-                LoopEvent::Frame(f) => self.process_http_frame_of_goaway(f)?,
-                LoopEvent::ExitLoop => return Ok(()),
+                LoopEvent::Frame(f, traffic) => self.process_http_frame_of_goaway(f, traffic)?,
+                LoopEvent::ExitLoop => {
+                    return match self.close_error.take() {
+                        Some(e) => Err(e),
+                        None => Ok(()),
+                    }
+                }
+            }
+            self.process_queued()?;
+        }
+    }
+
+    async fn run_loop(mut self) -> result::Result<()> {
+        match self.process_events().await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                // record the error before streams are notified on drop
+                self.conn_died_error_holder.set_once(e);
+                Err(self.conn_died_error_holder.error())
             }
         }
     }
 
     pub fn run(self) -> impl Future<Output = result::Result<()>> + Send {
-        let ndc = Arc::new(format!("{} {}", T::CONN_NDC, self.peer_addr));
+        let ndc = Arc::new(format!(
+            "{} {} {}",
+            T::CONN_NDC,
+            self.conn_id,
+            self.peer_addr
+        ));
         log_ndc_future(ndc, self.run_loop())
     }
 }
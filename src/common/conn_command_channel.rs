@@ -1,6 +1,7 @@
 use crate::client_died_error_holder::ConnDiedType;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::types::Types;
+use crate::error;
 use crate::result;
 use futures::channel::mpsc;
 use futures::channel::mpsc::UnboundedReceiver;
@@ -29,6 +30,16 @@ impl<T: Types> ConnCommandSender<T> {
         self.tx.unbounded_send(msg).map_err(|e| e.into_inner())
     }
 
+    /// Error the connection died with.
+    pub fn conn_died_error(&self) -> error::Error {
+        self.conn_died_error_holder.error()
+    }
+
+    /// Connection event loop is finished.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
     pub fn unbounded_send(&self, msg: T::ToWriteMessage) -> result::Result<()> {
         match self.tx.unbounded_send(msg) {
             Ok(()) => Ok(()),
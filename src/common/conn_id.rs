@@ -0,0 +1,42 @@
+use std::fmt;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// Process-wide unique connection identifier.
+///
+/// Ids are assigned in increasing order to both client and server
+/// connections, and are included in connection log context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConnId(u64);
+
+impl ConnId {
+    /// Allocate new id.
+    pub(crate) fn next() -> ConnId {
+        static LAST_CONN_ID: AtomicU64 = AtomicU64::new(0);
+        ConnId(LAST_CONN_ID.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// Numeric value of this id.
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ConnId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn increasing() {
+        let a = ConnId::next();
+        let b = ConnId::next();
+        assert!(b > a);
+        assert_eq!(format!("#{}", a.get()), format!("{}", a));
+    }
+}
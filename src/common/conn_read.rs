@@ -1,4 +1,5 @@
 use crate::codec::http_decode_read::HttpFrameDecodedOrGoaway;
+use crate::common::client_or_server::ClientOrServer;
 use crate::common::conn::Conn;
 use crate::common::conn_write::ConnWriteSideCustom;
 use crate::common::init_where::InitWhere;
@@ -6,7 +7,9 @@ use crate::common::stream::DroppedData;
 use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::InMessageStage;
+use crate::common::stream_handler::StreamHandlerInternal;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::traffic::TrafficStats;
 use crate::common::types::Types;
 use crate::error;
 use crate::result;
@@ -20,9 +23,13 @@ use crate::solicit::frame::HttpFrameType;
 use crate::solicit::frame::HttpSetting;
 use crate::solicit::frame::PingFrame;
 use crate::solicit::frame::PriorityFrame;
+use crate::solicit::frame::PriorityUpdateFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
 use crate::solicit::frame::WindowUpdateFrame;
+use crate::solicit::priority::Priority;
+use crate::solicit::session::StreamState;
 use crate::solicit::stream_id::StreamId;
 use crate::solicit::window_size::MAX_WINDOW_SIZE;
 use crate::solicit::DEFAULT_SETTINGS;
@@ -34,6 +41,7 @@ use crate::Headers;
 
 use futures::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 
@@ -46,8 +54,21 @@ pub(crate) trait ConnReadSideCustom {
         end_stream: EndStream,
         headers: Headers,
     ) -> result::Result<Option<HttpStreamRef<Self::Types>>>;
+
+    /// Called after `SETTINGS` from peer are applied.
+    fn peer_settings_applied(&mut self) -> result::Result<()> {
+        Ok(())
+    }
+
+    fn process_push_promise(
+        &mut self,
+        frame: PushPromiseDecodedFrame,
+    ) -> result::Result<Option<HttpStreamRef<'_, Self::Types>>>;
 }
 
+/// Limit of `PRIORITY_UPDATE` frames buffered for streams not opened yet.
+const MAX_PENDING_PRIORITIES: usize = 16;
+
 impl<T, I> Conn<T, I>
 where
     T: Types,
@@ -68,13 +89,25 @@ where
 
     fn process_data_frame(&mut self, frame: DataFrame) -> result::Result<Option<HttpStreamRef<T>>> {
         let stream_id = frame.get_stream_id();
+        // Padding and pad length field are counted in flow control
+        let padding = frame.payload_len() - frame.data.len() as u32;
+        let end_of_stream = frame.is_end_of_stream();
 
         self.decrease_in_window(frame.payload_len())?;
 
+        if let Some(bdp) = &mut self.bdp {
+            bdp.data_recvd(frame.payload_len());
+            // measure how much is received during round trip
+            if self.ping_sent.is_none() {
+                self.send_ping(None)?;
+            }
+        }
+
+        let in_window_target = self.in_window_target();
         let increment_conn =
         // TODO: need something better
-            if self.in_window_size.size() < (DEFAULT_SETTINGS.initial_window_size / 2) as i32 {
-                let increment = DEFAULT_SETTINGS.initial_window_size;
+            if self.in_window_size.size() < (in_window_target / 2) as i32 {
+                let increment = in_window_target;
                 let old_in_window_size = self.in_window_size.size();
                 self.in_window_size.try_increase(increment)
                     .map_err(|()| error::Error::ConnInWindowOverflow(self.in_window_size.size(), increment))?;
@@ -132,7 +165,6 @@ where
                 new_in_window_size
             );
 
-            let end_of_stream = frame.is_end_of_stream();
             stream.stream().data_recvd(frame.data, end_of_stream);
             break;
         }
@@ -147,6 +179,12 @@ where
             return Ok(None);
         }
 
+        // Padding is discarded, so stream window is restored right away,
+        // data part is restored when it is consumed by the user
+        if padding != 0 && !end_of_stream {
+            self.increase_in_window(stream_id, padding)?;
+        }
+
         Ok(Some(
             self.streams
                 .get_mut(stream_id)
@@ -156,8 +194,16 @@ where
 
     fn process_ping(&mut self, frame: PingFrame) -> result::Result<()> {
         if frame.is_ack() {
-            if let Some(opaque_data) = self.ping_sent.take() {
+            if let Some((opaque_data, sent_at)) = self.ping_sent.take() {
                 if opaque_data == frame.opaque_data {
+                    let rtt = sent_at.elapsed();
+                    debug!("PING ack received, rtt: {:?}", rtt);
+                    self.update_rtt(rtt);
+                    self.ping_acked_grow_window(rtt)?;
+                    for tx in self.ping_waiters.drain(..) {
+                        // ignore error, caller might be not interested anymore
+                        drop(tx.send(Ok(rtt)));
+                    }
                     Ok(())
                 } else {
                     Err(error::Error::PingAckOpaqueDataMismatch(
@@ -176,13 +222,31 @@ where
         }
     }
 
+    /// Grow connection window if bandwidth-delay product
+    /// is close to the window size.
+    fn ping_acked_grow_window(&mut self, rtt: Duration) -> result::Result<()> {
+        let srtt = self.smoothed_rtt.unwrap_or(rtt);
+        let increment = match &mut self.bdp {
+            Some(bdp) => match bdp.ping_acked(rtt, srtt) {
+                Some(increment) => increment,
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        self.in_window_size.try_increase(increment).map_err(|()| {
+            error::Error::ConnInWindowOverflow(self.in_window_size.size(), increment)
+        })?;
+        self.send_frame_and_notify(WindowUpdateFrame::for_connection(increment));
+        Ok(())
+    }
+
     fn process_goaway(&mut self, frame: GoawayFrame) -> result::Result<()> {
         if let Some(..) = self.goaway_received {
             return Err(error::Error::GoawayAfterGoaway);
         }
 
         let last_stream_id = frame.last_stream_id;
-        let raw_error_code = frame.error_code.0;
+        let raw_error_code = frame.raw_error_code();
 
         self.goaway_received = Some(frame);
 
@@ -205,21 +269,79 @@ where
             EndStream::No
         };
 
+        if let Some(dep) = &frame.stream_dep {
+            self.reprioritize(frame.stream_id, dep.stream_id, dep.weight, dep.is_exclusive);
+        }
+
         self.process_headers(frame.stream_id, end_stream, frame.headers)
     }
 
+    /// Apply RFC 7540 priority, priorities of closed streams are not tracked.
+    ///
+    /// `weight` is as sent in frames, one less than actual weight.
+    fn reprioritize(&mut self, stream_id: StreamId, dep: StreamId, weight: u8, exclusive: bool) {
+        if self.stream_state(stream_id) != StreamState::Closed {
+            self.streams
+                .reprioritize(stream_id, dep, weight as u16 + 1, exclusive);
+        }
+    }
+
     fn process_priority_frame(
         &mut self,
         frame: PriorityFrame,
     ) -> result::Result<Option<HttpStreamRef<T>>> {
+        self.reprioritize(
+            frame.stream_id,
+            frame.stream_dep,
+            frame.weight,
+            frame.exclusive,
+        );
         Ok(self.streams.get_mut(frame.get_stream_id()))
     }
 
+    // RFC 9218 section 7.1
+    fn process_priority_update(&mut self, frame: PriorityUpdateFrame) -> result::Result<()> {
+        // Only clients send `PRIORITY_UPDATE`, and only for request streams
+        if T::CLIENT_OR_SERVER == ClientOrServer::Client
+            || T::init_where(frame.prioritized_stream_id) == InitWhere::Locally
+        {
+            return self.send_goaway(ErrorCode::ProtocolError);
+        }
+
+        let stream_id = frame.prioritized_stream_id;
+        if let Some(mut stream) = self.streams.get_mut(stream_id) {
+            stream.stream().priority = Priority::from_field_value(&frame.field_value);
+        } else if stream_id > self.last_peer_stream_id
+            && self.pending_priorities.len() < MAX_PENDING_PRIORITIES
+        {
+            // Frame may arrive before `HEADERS`, priority is applied when stream is opened
+            self.pending_priorities
+                .insert(stream_id, Priority::from_field_value(&frame.field_value));
+        }
+        Ok(())
+    }
+
     fn process_settings_ack(&mut self, frame: SettingsFrame) -> result::Result<()> {
         assert!(frame.is_ack());
 
         if let Some(settings) = self.our_settings_sent.take() {
+            if settings.header_table_size != self.our_settings_ack.header_table_size {
+                self.framed_read
+                    .set_max_header_table_size(settings.header_table_size);
+            }
+            let delta = settings.initial_window_size as i32
+                - self.our_settings_ack.initial_window_size as i32;
+            if delta != 0 {
+                self.streams.add_in_window(delta);
+            }
             self.our_settings_ack = settings;
+            if let Some(tx) = self.our_settings_waiter.take() {
+                // ignore error, caller might be not interested anymore
+                drop(tx.send(Ok(())));
+            }
+            if let Some((settings, tx)) = self.our_settings_updates.pop_front() {
+                self.send_settings_update(settings, tx);
+            }
             Ok(())
         } else {
             Err(error::Error::SettingsAckWithoutSettingsSent)
@@ -248,7 +370,9 @@ where
                         self.streams.add_out_window(delta);
                     }
                 }
-                HttpSetting::HeaderTableSize(_new_size) => {}
+                HttpSetting::HeaderTableSize(new_size) => {
+                    self.encoder.set_max_table_size(new_size as usize);
+                }
                 _ => {}
             }
 
@@ -257,7 +381,7 @@ where
 
         self.send_ack_settings()?;
 
-        Ok(())
+        self.peer_settings_applied()
     }
 
     fn process_settings(&mut self, frame: SettingsFrame) -> result::Result<()> {
@@ -362,12 +486,45 @@ where
         Ok(None)
     }
 
+    /// Peer exceeded one of configured `MisbehaviorLimits`.
+    ///
+    /// Connection is closed with error once `GOAWAY` is flushed,
+    /// even if streams are open.
+    fn misbehavior_limit_exceeded(&mut self, what: &str) -> result::Result<()> {
+        if self.close_error.is_none() {
+            self.close_error = Some(error::Error::CodeError(ErrorCode::EnhanceYourCalm));
+        }
+        if self.goaway_sent.is_some() {
+            // do not wait for streams of connection being drained
+            warn!("peer exceeded {} limit, closing connection", what);
+            self.queued_write.terminate();
+            return Ok(());
+        }
+        warn!("peer exceeded {} limit, sending GOAWAY", what);
+        self.send_goaway(ErrorCode::EnhanceYourCalm)
+    }
+
     fn process_conn_frame(&mut self, frame: HttpFrameConn) -> result::Result<()> {
+        let exceeded = match &frame {
+            HttpFrameConn::Settings(f) if !f.is_ack() => {
+                self.misbehavior.settings().then_some("SETTINGS")
+            }
+            HttpFrameConn::Ping(f) if !f.is_ack() => self.misbehavior.ping().then_some("PING"),
+            _ => None,
+        };
+
+        // frame is processed before `GOAWAY` is queued, so e. g. `PING` is still acknowledged
         match frame {
-            HttpFrameConn::Settings(f) => self.process_settings(f),
-            HttpFrameConn::Ping(f) => self.process_ping(f),
-            HttpFrameConn::Goaway(f) => self.process_goaway(f),
-            HttpFrameConn::WindowUpdate(f) => self.process_conn_window_update(f),
+            HttpFrameConn::Settings(f) => self.process_settings(f)?,
+            HttpFrameConn::Ping(f) => self.process_ping(f)?,
+            HttpFrameConn::Goaway(f) => self.process_goaway(f)?,
+            HttpFrameConn::WindowUpdate(f) => self.process_conn_window_update(f)?,
+            HttpFrameConn::PriorityUpdate(f) => self.process_priority_update(f)?,
+        }
+
+        match exceeded {
+            Some(what) => self.misbehavior_limit_exceeded(what),
+            None => Ok(()),
         }
     }
 
@@ -386,14 +543,24 @@ where
             }
         }
 
+        let exceeded = match &frame {
+            HttpFrameStream::Data(f) => self
+                .misbehavior
+                .data_frame(f.data.len(), end_of_stream)
+                .then_some("empty DATA"),
+            HttpFrameStream::RstStream(_) => self.misbehavior.rst_stream().then_some("RST_STREAM"),
+            _ => None,
+        };
+
+        // frame is processed before `GOAWAY` is queued, so e. g. `RST_STREAM` closes the stream
         {
             let stream = match frame {
                 HttpFrameStream::Data(data) => self.process_data_frame(data)?,
                 HttpFrameStream::Headers(headers) => self.process_headers_frame(headers)?,
                 HttpFrameStream::Priority(priority) => self.process_priority_frame(priority)?,
                 HttpFrameStream::RstStream(rst) => self.process_rst_stream_frame(rst)?,
-                HttpFrameStream::PushPromise(_f) => {
-                    return Err(error::Error::NotImplemented("PUSH_PROMISE"))
+                HttpFrameStream::PushPromise(push_promise) => {
+                    self.process_push_promise(push_promise)?
                 }
                 HttpFrameStream::WindowUpdate(window_update) => {
                     self.process_stream_window_update_frame(window_update)?
@@ -411,7 +578,10 @@ where
             self.peer_closed_streams.add(stream_id);
         }
 
-        Ok(())
+        match exceeded {
+            Some(what) => self.misbehavior_limit_exceeded(what),
+            None => Ok(()),
+        }
     }
 
     fn process_http_frame(&mut self, frame: HttpFrameDecoded) -> result::Result<()> {
@@ -442,16 +612,89 @@ where
         Ok(())
     }
 
+    /// Reject header list exceeding our `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    ///
+    /// Server responds to a new request with `431` status,
+    /// otherwise stream handler fails and stream is reset.
+    fn process_header_list_too_large(
+        &mut self,
+        stream_id: StreamId,
+        end_stream: EndStream,
+        size: usize,
+    ) -> result::Result<()> {
+        if let Some(mut stream) = self.streams.get_mut(stream_id) {
+            if let Some(handler) = stream.stream().peer_tx.take() {
+                drop(handler.error(error::Error::HeaderListTooLarge(size)));
+            }
+            return self.send_rst_stream(stream_id, ErrorCode::Cancel);
+        }
+
+        if T::CLIENT_OR_SERVER == ClientOrServer::Server
+            && T::init_where(stream_id) == InitWhere::Peer
+            && stream_id > self.last_peer_stream_id
+        {
+            self.last_peer_stream_id = stream_id;
+            self.write_part_headers(stream_id, Headers::new_status(431), EndStream::Yes);
+            if end_stream == EndStream::No {
+                // Response is complete, so the rest of request is not needed (8.1)
+                self.queued_write
+                    .queue_not_goaway(RstStreamFrame::new(stream_id, ErrorCode::NoError));
+            }
+            return Ok(());
+        }
+
+        self.process_stream_error(stream_id, ErrorCode::Cancel)
+    }
+
+    /// Account raw frames received in stream traffic.
+    ///
+    /// Return `false` if stream is not found.
+    fn stream_traffic_recvd(&mut self, stream_id: StreamId, traffic: &TrafficStats) -> bool {
+        if stream_id == 0 {
+            return true;
+        }
+        match self.streams.get_mut(stream_id) {
+            Some(mut stream) => {
+                stream.stream().traffic.received.add(traffic);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Process frame decoded from `traffic` raw frames.
     pub fn process_http_frame_of_goaway(
         &mut self,
         m: HttpFrameDecodedOrGoaway,
+        traffic: TrafficStats,
     ) -> result::Result<()> {
+        let stream_id = match &m {
+            HttpFrameDecodedOrGoaway::Frame(frame) => frame.get_stream_id(),
+            HttpFrameDecodedOrGoaway::HeaderListTooLarge(stream_id, ..) => *stream_id,
+            HttpFrameDecodedOrGoaway::SendGoaway(..) | HttpFrameDecodedOrGoaway::_SendRst(..) => 0,
+        };
+
+        // accounted before processing, because frame may close the stream,
+        // and after processing if frame opens the stream
+        if self.stream_traffic_recvd(stream_id, &traffic) {
+            self.process_frame_or_goaway(m)
+        } else {
+            self.process_frame_or_goaway(m)?;
+            self.stream_traffic_recvd(stream_id, &traffic);
+            Ok(())
+        }
+    }
+
+    fn process_frame_or_goaway(&mut self, m: HttpFrameDecodedOrGoaway) -> result::Result<()> {
         match m {
             HttpFrameDecodedOrGoaway::Frame(frame) => self.process_http_frame(frame),
             HttpFrameDecodedOrGoaway::_SendRst(stream_id, error_code) => {
                 self.process_stream_error(stream_id, error_code)
             }
             HttpFrameDecodedOrGoaway::SendGoaway(error_code) => self.send_goaway(error_code),
+            HttpFrameDecodedOrGoaway::HeaderListTooLarge(stream_id, end_stream, size) => {
+                self.process_header_list_too_large(stream_id, end_stream, size)
+            }
         }
     }
 }
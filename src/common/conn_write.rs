@@ -21,6 +21,8 @@ use crate::solicit::frame::GoawayFrame;
 use crate::solicit::frame::HeadersFlag;
 use crate::solicit::frame::HeadersMultiFrame;
 use crate::solicit::frame::HttpFrame;
+use crate::solicit::frame::HttpSetting;
+use crate::solicit::frame::PingFrame;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
 use crate::solicit::stream_id::StreamId;
@@ -33,6 +35,8 @@ use futures::task::Context;
 use std::cmp;
 
 use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 
@@ -43,6 +47,11 @@ pub(crate) trait ConnWriteSideCustom {
         &mut self,
         message: <Self::Types as Types>::ToWriteMessage,
     ) -> result::Result<()>;
+
+    /// Called after each event, e. g. to start requests waiting for a stream slot.
+    fn process_queued(&mut self) -> result::Result<()> {
+        Ok(())
+    }
 }
 
 impl<T, I> Conn<T, I>
@@ -61,6 +70,7 @@ where
         if end_stream == EndStream::Yes && data.len() == 0 {
             let mut frame = DataFrame::with_data(stream_id, Bytes::new());
             frame.set_flag(DataFlag::EndStream);
+            self.pad_data_frame(stream_id, &mut frame);
 
             debug!("sending frame {:?}", frame);
 
@@ -83,6 +93,7 @@ where
             if end_stream_in_frame == EndStream::Yes {
                 frame.set_flag(DataFlag::EndStream);
             }
+            self.pad_data_frame(stream_id, &mut frame);
 
             self.queued_write.queue_not_goaway(frame);
 
@@ -90,20 +101,65 @@ where
         }
     }
 
-    fn write_part_headers(&mut self, stream_id: StreamId, headers: Headers, end_stream: EndStream) {
+    /// Pad `DATA` frame if padding is configured.
+    ///
+    /// Padding counts in flow control, so it is limited
+    /// by max frame size and by stream and connection windows.
+    fn pad_data_frame(&mut self, stream_id: StreamId, frame: &mut DataFrame) {
+        let padding = match self.padding {
+            Some(padding) => padding,
+            None => return,
+        };
+
+        let frame_size_rem = self.peer_settings.max_frame_size as i32 - frame.payload_len() as i32;
+        let mut max = cmp::min(frame_size_rem, self.out_window_size.size());
+        if let Some(mut stream) = self.streams.get_mut(stream_id) {
+            max = cmp::min(max, stream.stream().out_window_size.size());
+        }
+
+        // Pad length field takes one byte
+        let pad_len = cmp::min(padding.pad_len() as i32, max - 1);
+        if pad_len <= 0 {
+            return;
+        }
+
+        self.out_window_size
+            .try_decrease_to_non_negative(pad_len + 1)
+            .unwrap();
+        if let Some(mut stream) = self.streams.get_mut(stream_id) {
+            stream
+                .stream()
+                .out_window_size
+                .try_decrease_to_non_negative(pad_len + 1)
+                .unwrap();
+        }
+        frame.set_padding(pad_len as u8);
+    }
+
+    pub fn write_part_headers(
+        &mut self,
+        stream_id: StreamId,
+        headers: Headers,
+        end_stream: EndStream,
+    ) {
         let mut flags = Flags::new(0);
         if end_stream == EndStream::Yes {
             flags.set(HeadersFlag::EndStream);
         }
-        self.queued_write.queue_not_goaway(HeadersMultiFrame {
-            flags,
-            stream_id,
-            headers,
-            stream_dep: None,
-            padding_len: 0,
-            encoder: &mut self.encoder,
-            max_frame_size: self.peer_settings.max_frame_size,
-        });
+        let padding_len = match self.padding {
+            Some(padding) => padding.pad_len(),
+            None => 0,
+        };
+        self.queued_write
+            .queue_headers_not_goaway(HeadersMultiFrame {
+                flags,
+                stream_id,
+                headers,
+                stream_dep: None,
+                padding_len,
+                encoder: &mut self.encoder,
+                max_frame_size: self.peer_settings.max_frame_size,
+            });
     }
 
     fn write_part_rst(&mut self, stream_id: StreamId, error_code: ErrorCode) {
@@ -130,16 +186,21 @@ where
         self.queued_write.queued_bytes_len() < 0x8000
     }
 
-    fn pop_outg_for_stream(
-        &mut self,
-        stream_id: StreamId,
-    ) -> Option<(StreamId, HttpStreamCommand, bool)> {
-        let stream = self.streams.get_mut(stream_id).unwrap();
-        if let (Some(command), stream) = stream.pop_outg_maybe_remove(&mut self.out_window_size) {
-            return Some((stream_id, command, stream.is_some()));
-        }
-
-        None
+    /// Pop outgoing command of the stream and write it.
+    ///
+    /// Return `None` if stream has nothing to write,
+    /// otherwise whether the stream is still in the map.
+    fn write_outg_for_stream(&mut self, stream_id: StreamId, max_data_len: usize) -> Option<bool> {
+        let mut stream = self.streams.get_mut(stream_id).unwrap();
+        let command = stream.pop_outg(&mut self.out_window_size, max_data_len)?;
+
+        let before = *self.queued_write.sent_traffic();
+        self.write_part(stream_id, command);
+        let sent = self.queued_write.sent_traffic().since(&before);
+
+        let mut stream = self.streams.get_mut(stream_id).unwrap();
+        stream.stream().traffic.sent.add(&sent);
+        Some(stream.remove_if_closed().is_some())
     }
 
     pub fn buffer_outg_conn(&mut self) -> result::Result<bool> {
@@ -150,24 +211,67 @@ where
             return Ok(updated);
         }
 
-        let writable_stream_ids = self.streams.writable_stream_ids();
+        if self.streams.priority_tree.is_used() {
+            return Ok(self.buffer_outg_conn_by_dependency());
+        }
+
+        // RFC 9218 section 10: streams with lower urgency go first,
+        // non-incremental streams are sent one after another in stream id order,
+        // incremental streams of the same urgency are interleaved
+        let writable_streams = self.streams.writable_stream_ids_by_priority();
+
+        let mut i = 0;
+        while i < writable_streams.len() {
+            let (priority, stream_id) = writable_streams[i];
+            if !priority.incremental {
+                i += 1;
+                loop {
+                    if !self.has_write_buffer_capacity() {
+                        return Ok(updated);
+                    }
+
+                    if let Some(cont) = self.write_outg_for_stream(stream_id, usize::MAX) {
+                        updated = true;
+
+                        // Stream is removed from map, need to continue to the next stream
+                        if !cont {
+                            break;
+                        }
+                    } else {
+                        break;
+                    }
+                }
+                continue;
+            }
 
-        for &stream_id in &writable_stream_ids {
-            loop {
+            let mut group: Vec<StreamId> = writable_streams[i..]
+                .iter()
+                .take_while(|(p, _)| *p == priority)
+                .map(|&(_, stream_id)| stream_id)
+                .collect();
+            i += group.len();
+
+            // Round-robin, one frame per stream
+            let max_frame_size = self.peer_settings.max_frame_size as usize;
+            let mut j = 0;
+            while !group.is_empty() {
                 if !self.has_write_buffer_capacity() {
                     return Ok(updated);
                 }
 
-                if let Some((stream_id, part, cont)) = self.pop_outg_for_stream(stream_id) {
-                    self.write_part(stream_id, part);
-                    updated = true;
-
-                    // Stream is removed from map, need to continue to the next stream
-                    if !cont {
-                        break;
+                j %= group.len();
+                match self.write_outg_for_stream(group[j], max_frame_size) {
+                    Some(cont) => {
+                        updated = true;
+                        if cont {
+                            j += 1;
+                        } else {
+                            group.remove(j);
+                        }
+                    }
+                    None => {
+                        group.remove(j);
                     }
-                } else {
-                    break;
                 }
             }
         }
@@ -175,6 +279,36 @@ where
         Ok(updated)
     }
 
+    /// Scheduler used after peer sent RFC 7540 priorities: connection window
+    /// is divided between streams according to dependencies and weights.
+    fn buffer_outg_conn_by_dependency(&mut self) -> bool {
+        let mut updated = false;
+        loop {
+            let shares = self.streams.writable_stream_shares();
+            let window = cmp::max(self.out_window_size.size(), 0) as f64;
+
+            let mut progress = false;
+            for (stream_id, share) in shares {
+                if !self.has_write_buffer_capacity() {
+                    return updated;
+                }
+
+                let max_data_len = cmp::max((window * share).ceil() as usize, 1);
+                if self
+                    .write_outg_for_stream(stream_id, max_data_len)
+                    .is_some()
+                {
+                    updated = true;
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                return updated;
+            }
+        }
+    }
+
     pub fn send_frame_and_notify<F: Into<HttpFrame>>(&mut self, frame: F) {
         // TODO: some of frames should not be in front of GOAWAY
         self.queued_write.queue_not_goaway(frame.into());
@@ -199,6 +333,18 @@ where
         Ok(())
     }
 
+    fn process_stream_reset(
+        &mut self,
+        stream_id: StreamId,
+        error_code: ErrorCode,
+    ) -> result::Result<()> {
+        // Stream may be already closed and removed
+        if self.streams.get_mut(stream_id).is_some() {
+            self.send_rst_stream(stream_id, error_code)?;
+        }
+        Ok(())
+    }
+
     fn process_stream_enqueue(
         &mut self,
         stream_id: StreamId,
@@ -239,6 +385,9 @@ where
             CommonToWriteMessage::StreamEnd(stream_id, error_code) => {
                 self.process_stream_end(stream_id, error_code)?;
             }
+            CommonToWriteMessage::StreamReset(stream_id, error_code) => {
+                self.process_stream_reset(stream_id, error_code)?;
+            }
             CommonToWriteMessage::StreamEnqueue(stream_id, part) => {
                 self.process_stream_enqueue(stream_id, part)?;
             }
@@ -251,6 +400,25 @@ where
             CommonToWriteMessage::DumpState(sender) => {
                 self.process_dump_state(sender)?;
             }
+            CommonToWriteMessage::Ping(sender) => {
+                self.send_ping(Some(sender))?;
+            }
+            CommonToWriteMessage::Goaway(error_code, debug_data, graceful) => {
+                self.process_send_goaway(error_code, debug_data, graceful)?;
+            }
+            CommonToWriteMessage::RawFrame(frame) => {
+                debug!("sending raw frame {:?}", frame);
+                self.send_frame_and_notify(frame);
+            }
+            CommonToWriteMessage::UpdateSettings(settings, sender) => {
+                if self.our_settings_sent.is_some() {
+                    // Acks are matched to frames in order,
+                    // so send after previous settings are acknowledged
+                    self.our_settings_updates.push_back((settings, sender));
+                } else {
+                    self.send_settings_update(settings, sender);
+                }
+            }
         }
         Ok(())
     }
@@ -258,10 +426,82 @@ where
     pub fn send_goaway(&mut self, error_code: ErrorCode) -> result::Result<()> {
         debug!("requesting to send GOAWAY with code {:?}", error_code);
         let frame = GoawayFrame::new(self.last_peer_stream_id, error_code);
+        self.goaway_sent = Some(frame.clone());
         self.queued_write.queue_goaway(frame);
         Ok(())
     }
 
+    /// Send `GOAWAY` requested by user.
+    ///
+    /// If `graceful` is true, connection is closed after all existing streams
+    /// complete, otherwise connection is closed right after `GOAWAY` is sent.
+    fn process_send_goaway(
+        &mut self,
+        error_code: ErrorCode,
+        debug_data: Bytes,
+        graceful: bool,
+    ) -> result::Result<()> {
+        debug!(
+            "requesting to send GOAWAY with code {:?}, graceful: {}",
+            error_code, graceful
+        );
+        if self.goaway_sent.is_some() {
+            if !graceful {
+                // close connection being drained after earlier graceful `GOAWAY`
+                self.queued_write.terminate();
+            }
+            return Ok(());
+        }
+        let frame = GoawayFrame::with_debug_data(self.last_peer_stream_id, error_code, debug_data);
+        self.goaway_sent = Some(frame.clone());
+        if graceful {
+            self.send_frame_and_notify(frame);
+        } else {
+            self.queued_write.queue_goaway(frame);
+        }
+        Ok(())
+    }
+
+    /// Send `PING` frame unless there's already `PING` waiting for ack.
+    ///
+    /// `tx` (if specified) is notified with round-trip time when ack is received.
+    pub fn send_ping(
+        &mut self,
+        tx: Option<oneshot::Sender<result::Result<Duration>>>,
+    ) -> result::Result<()> {
+        if let Some(tx) = tx {
+            self.ping_waiters.push(tx);
+        }
+        if self.ping_sent.is_some() {
+            return Ok(());
+        }
+        self.ping_last_opaque_data = self.ping_last_opaque_data.wrapping_add(1);
+        let opaque_data = self.ping_last_opaque_data;
+        debug!("sending PING {}", opaque_data);
+        self.ping_sent = Some((opaque_data, Instant::now()));
+        if let Some(bdp) = &mut self.bdp {
+            bdp.ping_sent();
+        }
+        self.send_frame_and_notify(PingFrame::with_data(opaque_data));
+        Ok(())
+    }
+
+    /// Send `SETTINGS` frame, new settings are applied when ack is received.
+    pub fn send_settings_update(
+        &mut self,
+        settings: Vec<HttpSetting>,
+        tx: oneshot::Sender<result::Result<()>>,
+    ) {
+        debug!("sending SETTINGS {:?}", settings);
+        let mut sent = self.our_settings_ack;
+        for setting in &settings {
+            sent.apply(*setting);
+        }
+        self.our_settings_sent = Some(sent);
+        self.our_settings_waiter = Some(tx);
+        self.send_frame_and_notify(SettingsFrame::from_settings(settings));
+    }
+
     pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> result::Result<()> {
         self.buffer_outg_conn()?;
         loop {
@@ -284,6 +524,13 @@ pub enum CommonToWriteMessage {
     IncreaseInWindow(StreamId, u32),
     StreamEnqueue(StreamId, DataOrHeadersWithFlag),
     StreamEnd(StreamId, ErrorCode), // send when user provided handler completed the stream
+    StreamReset(StreamId, ErrorCode), // send when user is no longer interested in incoming data
     Pull(StreamId, HttpStreamAfterHeaders, StreamOutWindowReceiver),
     DumpState(oneshot::Sender<ConnStateSnapshot>),
+    Ping(oneshot::Sender<result::Result<Duration>>),
+    Goaway(ErrorCode, Bytes, bool),
+    /// Frame is written as is, bypassing stream state and flow control.
+    RawFrame(HttpFrame),
+    /// Send `SETTINGS`, sender is notified when ack is received.
+    UpdateSettings(Vec<HttpSetting>, oneshot::Sender<result::Result<()>>),
 }
@@ -0,0 +1,35 @@
+//! Close connections without streams and traffic.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::time::Duration;
+
+use tokio::time;
+use tokio::time::Delay;
+
+/// Idle timeout state of a connection.
+pub(crate) struct IdleTimeout {
+    timeout: Duration,
+    /// Connection is closed when this expires
+    deadline: Delay,
+}
+
+impl IdleTimeout {
+    pub fn new(timeout: Duration) -> IdleTimeout {
+        IdleTimeout {
+            timeout,
+            deadline: time::delay_for(timeout),
+        }
+    }
+
+    /// Connection has open streams or a frame was received.
+    pub fn activity(&mut self) {
+        self.deadline.reset(time::Instant::now() + self.timeout);
+    }
+
+    /// Returns `true` if connection has been idle for the whole timeout.
+    pub fn poll(&mut self, cx: &mut Context<'_>) -> bool {
+        Pin::new(&mut self.deadline).poll(cx).is_ready()
+    }
+}
@@ -0,0 +1,74 @@
+//! `PING` on idle connections to detect dead peers.
+
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::Context;
+use std::time::Duration;
+
+use tokio::time;
+use tokio::time::Delay;
+use tokio::time::Interval;
+
+use crate::error;
+use crate::result;
+
+/// Default time to wait for `PING` ack.
+pub const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Keepalive state of a connection.
+pub(crate) struct Keepalive {
+    interval: Interval,
+    timeout: Duration,
+    /// Frame received since last tick
+    frame_received: bool,
+    /// Connection fails when this expires before `PING` ack
+    ack_deadline: Option<Delay>,
+}
+
+impl Keepalive {
+    pub fn new(interval: Duration, timeout: Option<Duration>) -> Keepalive {
+        Keepalive {
+            interval: time::interval_at(time::Instant::now() + interval, interval),
+            timeout: timeout.unwrap_or(DEFAULT_KEEPALIVE_TIMEOUT),
+            frame_received: false,
+            ack_deadline: None,
+        }
+    }
+
+    /// Any frame from peer means connection is not idle.
+    pub fn frame_received(&mut self) {
+        self.frame_received = true;
+    }
+
+    /// Returns `true` if `PING` must be sent.
+    ///
+    /// `ping_in_flight` is true if `PING` is sent and ack is not received yet.
+    pub fn poll(&mut self, cx: &mut Context<'_>, ping_in_flight: bool) -> result::Result<bool> {
+        let mut tick = false;
+        // Poll until `Pending` to be woken up on next tick
+        while self.interval.poll_tick(cx).is_ready() {
+            tick = true;
+        }
+
+        let mut send_ping = false;
+        if tick {
+            let idle = !mem::replace(&mut self.frame_received, false);
+            if idle && self.ack_deadline.is_none() {
+                debug!("connection is idle, sending keepalive PING");
+                self.ack_deadline = Some(time::delay_for(self.timeout));
+                send_ping = true;
+            }
+        }
+
+        if let Some(ack_deadline) = &mut self.ack_deadline {
+            if !ping_in_flight && !send_ping {
+                self.ack_deadline = None;
+            } else if Pin::new(ack_deadline).poll(cx).is_ready() {
+                return Err(error::Error::KeepaliveTimeout);
+            }
+        }
+
+        Ok(send_ping)
+    }
+}
@@ -1,8 +1,10 @@
 use crate::codec::http_decode_read::HttpFrameDecodedOrGoaway;
+use crate::common::traffic::TrafficStats;
 use crate::common::types::Types;
 
 pub(crate) enum LoopEvent<T: Types> {
     ToWriteMessage(T::ToWriteMessage),
-    Frame(HttpFrameDecodedOrGoaway),
+    /// Frame and raw frames it is decoded from
+    Frame(HttpFrameDecodedOrGoaway, TrafficStats),
     ExitLoop,
 }
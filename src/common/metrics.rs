@@ -0,0 +1,210 @@
+//! Reporting connection and stream metrics to a user-provided sink.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::common::client_or_server::ClientOrServer;
+use crate::common::traffic::frame_type_name;
+use crate::common::traffic::ConnTraffic;
+use crate::common::traffic::TrafficStats;
+use crate::common::traffic::FRAME_TYPE_COUNT;
+use crate::solicit::frame::RawHttpFrameType;
+
+/// Metric label, name and value.
+pub type MetricLabel = (&'static str, &'static str);
+
+/// Metrics reported to `Metrics`.
+///
+/// All metrics are labelled with `side`, `client` or `server`.
+/// Traffic metrics are also labelled with `direction`, `sent` or `received`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// Counter of connections which completed handshake.
+    ConnectionsOpened,
+    /// Counter of closed connections.
+    ConnectionsClosed,
+    /// Gauge of open connections.
+    ConnectionsActive,
+    /// Counter of streams opened by either peer, including pushed streams.
+    StreamsStarted,
+    /// Counter of streams reset with `RST_STREAM`, by `direction`.
+    StreamsReset,
+    /// Gauge of open streams.
+    StreamsActive,
+    /// Counter of frames by `direction` and frame `type`,
+    /// e. g. `HEADERS`, or `UNKNOWN` for extension frames.
+    Frames,
+    /// Counter of `DATA` frame payload bytes, by `direction`.
+    DataBytes,
+    /// Counter of header block payload bytes, by `direction`.
+    HeaderBytes,
+    /// Histogram of seconds from accepting or starting to connect
+    /// to exchanging connection preface, including TLS handshake.
+    HandshakeDuration,
+    /// Histogram of seconds streams were open.
+    StreamDuration,
+    /// Histogram of `PING` round-trip time samples in seconds.
+    Rtt,
+}
+
+impl Metric {
+    /// Conventional metric name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::ConnectionsOpened => "http2_connections_opened_total",
+            Metric::ConnectionsClosed => "http2_connections_closed_total",
+            Metric::ConnectionsActive => "http2_connections_active",
+            Metric::StreamsStarted => "http2_streams_started_total",
+            Metric::StreamsReset => "http2_streams_reset_total",
+            Metric::StreamsActive => "http2_streams_active",
+            Metric::Frames => "http2_frames_total",
+            Metric::DataBytes => "http2_data_bytes_total",
+            Metric::HeaderBytes => "http2_header_bytes_total",
+            Metric::HandshakeDuration => "http2_handshake_duration_seconds",
+            Metric::StreamDuration => "http2_stream_duration_seconds",
+            Metric::Rtt => "http2_rtt_seconds",
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Metrics sink, see `CommonConf::metrics`.
+///
+/// Called on connection event loops, so implementations must not block.
+/// Traffic counters are reported in batches, not for every frame.
+pub trait Metrics: Send + Sync + 'static {
+    /// Increment counter.
+    fn counter(&self, metric: Metric, labels: &[MetricLabel], value: u64);
+    /// Increment or decrement gauge.
+    fn gauge(&self, metric: Metric, labels: &[MetricLabel], delta: i64);
+    /// Record histogram observation.
+    fn histogram(&self, metric: Metric, labels: &[MetricLabel], value: f64);
+}
+
+impl fmt::Debug for dyn Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+fn side_label(side: ClientOrServer) -> MetricLabel {
+    match side {
+        ClientOrServer::Client => ("side", "client"),
+        ClientOrServer::Server => ("side", "server"),
+    }
+}
+
+/// Metrics of a connection, reports connection closed when dropped.
+pub(crate) struct ConnMetrics {
+    metrics: Arc<dyn Metrics>,
+    side: MetricLabel,
+    /// Traffic already reported
+    reported: ConnTraffic,
+}
+
+impl ConnMetrics {
+    pub fn new(metrics: Arc<dyn Metrics>, side: ClientOrServer) -> ConnMetrics {
+        let side = side_label(side);
+        metrics.counter(Metric::ConnectionsOpened, &[side], 1);
+        metrics.gauge(Metric::ConnectionsActive, &[side], 1);
+        ConnMetrics {
+            metrics,
+            side,
+            reported: ConnTraffic::default(),
+        }
+    }
+
+    pub fn handshake_done(&self, started: Instant) {
+        self.metrics.histogram(
+            Metric::HandshakeDuration,
+            &[self.side],
+            started.elapsed().as_secs_f64(),
+        );
+    }
+
+    pub fn rtt(&self, sample: Duration) {
+        self.metrics
+            .histogram(Metric::Rtt, &[self.side], sample.as_secs_f64());
+    }
+
+    /// Metrics of a new stream.
+    pub fn stream(&self) -> StreamMetrics {
+        self.metrics
+            .counter(Metric::StreamsStarted, &[self.side], 1);
+        self.metrics.gauge(Metric::StreamsActive, &[self.side], 1);
+        StreamMetrics {
+            metrics: self.metrics.clone(),
+            side: self.side,
+            started: Instant::now(),
+        }
+    }
+
+    /// Report traffic since previous call.
+    pub fn traffic(&mut self, traffic: &ConnTraffic) {
+        if *traffic == self.reported {
+            return;
+        }
+        self.traffic_delta("sent", &traffic.sent, &self.reported.sent);
+        self.traffic_delta("received", &traffic.received, &self.reported.received);
+        self.reported = *traffic;
+    }
+
+    fn traffic_delta(&self, direction: &'static str, now: &TrafficStats, before: &TrafficStats) {
+        let labels = [self.side, ("direction", direction)];
+        for i in 0..FRAME_TYPE_COUNT {
+            let frames = now.frames_by_type[i] - before.frames_by_type[i];
+            if frames != 0 {
+                let labels = [labels[0], labels[1], ("type", frame_type_name(i))];
+                self.metrics.counter(Metric::Frames, &labels, frames);
+            }
+        }
+        let reset_index = RawHttpFrameType::RST_STREAM.0 as usize;
+        let resets = now.frames_by_type[reset_index] - before.frames_by_type[reset_index];
+        if resets != 0 {
+            self.metrics.counter(Metric::StreamsReset, &labels, resets);
+        }
+        if now.data_bytes != before.data_bytes {
+            let bytes = now.data_bytes - before.data_bytes;
+            self.metrics.counter(Metric::DataBytes, &labels, bytes);
+        }
+        if now.header_bytes != before.header_bytes {
+            let bytes = now.header_bytes - before.header_bytes;
+            self.metrics.counter(Metric::HeaderBytes, &labels, bytes);
+        }
+    }
+}
+
+impl Drop for ConnMetrics {
+    fn drop(&mut self) {
+        self.metrics
+            .counter(Metric::ConnectionsClosed, &[self.side], 1);
+        self.metrics
+            .gauge(Metric::ConnectionsActive, &[self.side], -1);
+    }
+}
+
+/// Metrics of a stream, reports stream duration when dropped
+/// together with the stream.
+pub(crate) struct StreamMetrics {
+    metrics: Arc<dyn Metrics>,
+    side: MetricLabel,
+    started: Instant,
+}
+
+impl Drop for StreamMetrics {
+    fn drop(&mut self) {
+        self.metrics.gauge(Metric::StreamsActive, &[self.side], -1);
+        self.metrics.histogram(
+            Metric::StreamDuration,
+            &[self.side],
+            self.started.elapsed().as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::Duration;
+use std::time::Instant;
+
+/// Thresholds protecting a connection from flooding or abusive peer.
+///
+/// When a limit is exceeded, connection is closed with `GOAWAY`
+/// with `ENHANCE_YOUR_CALM` error code.
+///
+/// `None` means no limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MisbehaviorLimits {
+    /// Max number of `RST_STREAM` frames received per second.
+    pub max_rst_stream_per_sec: Option<u32>,
+    /// Max number of consecutive empty `DATA` frames without `END_STREAM` flag.
+    pub max_empty_data_frames: Option<u32>,
+    /// Max number of `SETTINGS` frames (excluding acks) received per second.
+    pub max_settings_per_sec: Option<u32>,
+    /// Max number of `PING` frames (excluding acks) received per second.
+    pub max_ping_per_sec: Option<u32>,
+    /// Max size of header block (`HEADERS` or `PUSH_PROMISE`
+    /// payload with all `CONTINUATION` frames) before HPACK decoding.
+    pub max_header_block_size: Option<u32>,
+}
+
+impl Default for MisbehaviorLimits {
+    fn default() -> Self {
+        MisbehaviorLimits {
+            max_rst_stream_per_sec: Some(200),
+            max_empty_data_frames: Some(100),
+            max_settings_per_sec: Some(100),
+            max_ping_per_sec: Some(100),
+            max_header_block_size: Some(1 << 20),
+        }
+    }
+}
+
+impl MisbehaviorLimits {
+    /// Default limits.
+    pub fn new() -> MisbehaviorLimits {
+        Default::default()
+    }
+
+    /// No limits, useful for testing.
+    pub fn off() -> MisbehaviorLimits {
+        MisbehaviorLimits {
+            max_rst_stream_per_sec: None,
+            max_empty_data_frames: None,
+            max_settings_per_sec: None,
+            max_ping_per_sec: None,
+            max_header_block_size: None,
+        }
+    }
+}
+
+/// Count events in one second windows.
+#[derive(Default)]
+struct RateCounter {
+    window_start: Option<Instant>,
+    count: u32,
+}
+
+impl RateCounter {
+    /// Register an event, return `true` if limit is exceeded.
+    fn hit(&mut self, limit: Option<u32>, now: Instant) -> bool {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => return false,
+        };
+        match self.window_start {
+            Some(start) if now.duration_since(start) < Duration::from_secs(1) => {}
+            _ => {
+                self.window_start = Some(now);
+                self.count = 0;
+            }
+        }
+        self.count = self.count.saturating_add(1);
+        self.count > limit
+    }
+}
+
+/// Counters of peer misbehavior.
+pub(crate) struct MisbehaviorCounters {
+    limits: MisbehaviorLimits,
+    rst_stream: RateCounter,
+    settings: RateCounter,
+    ping: RateCounter,
+    empty_data_frames: u32,
+}
+
+impl MisbehaviorCounters {
+    pub fn new(limits: MisbehaviorLimits) -> MisbehaviorCounters {
+        MisbehaviorCounters {
+            limits,
+            rst_stream: RateCounter::default(),
+            settings: RateCounter::default(),
+            ping: RateCounter::default(),
+            empty_data_frames: 0,
+        }
+    }
+
+    /// `RST_STREAM` received, return `true` if limit is exceeded.
+    pub fn rst_stream(&mut self) -> bool {
+        let limit = self.limits.max_rst_stream_per_sec;
+        self.rst_stream.hit(limit, Instant::now())
+    }
+
+    /// `SETTINGS` received, return `true` if limit is exceeded.
+    pub fn settings(&mut self) -> bool {
+        let limit = self.limits.max_settings_per_sec;
+        self.settings.hit(limit, Instant::now())
+    }
+
+    /// `PING` received, return `true` if limit is exceeded.
+    pub fn ping(&mut self) -> bool {
+        let limit = self.limits.max_ping_per_sec;
+        self.ping.hit(limit, Instant::now())
+    }
+
+    /// `DATA` frame received, return `true` if limit is exceeded.
+    pub fn data_frame(&mut self, len: usize, end_stream: bool) -> bool {
+        if len != 0 || end_stream {
+            self.empty_data_frames = 0;
+            return false;
+        }
+        self.empty_data_frames = self.empty_data_frames.saturating_add(1);
+        match self.limits.max_empty_data_frames {
+            Some(limit) => self.empty_data_frames > limit,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate_counter() {
+        let mut counter = RateCounter::default();
+        let start = Instant::now();
+        assert!(!counter.hit(Some(2), start));
+        assert!(!counter.hit(Some(2), start));
+        assert!(counter.hit(Some(2), start));
+        // new window
+        assert!(!counter.hit(Some(2), start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn empty_data_frames() {
+        let mut limits = MisbehaviorLimits::off();
+        limits.max_empty_data_frames = Some(2);
+        let mut counters = MisbehaviorCounters::new(limits);
+        assert!(!counters.data_frame(0, false));
+        assert!(!counters.data_frame(0, false));
+        assert!(!counters.data_frame(10, false));
+        assert!(!counters.data_frame(0, false));
+        assert!(!counters.data_frame(0, false));
+        assert!(counters.data_frame(0, false));
+    }
+
+    #[test]
+    fn off() {
+        let mut counters = MisbehaviorCounters::new(MisbehaviorLimits::off());
+        for _ in 0..1000 {
+            assert!(!counters.ping());
+            assert!(!counters.data_frame(0, false));
+        }
+    }
+}
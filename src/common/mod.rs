@@ -1,17 +1,25 @@
 //! Common code for client and server
 
 pub(crate) mod atomic_box_option;
+pub(crate) mod bdp;
 pub(crate) mod client_or_server;
 pub(crate) mod closed_streams;
 pub(crate) mod conf;
 pub(crate) mod conn;
 pub(crate) mod conn_command_channel;
+pub(crate) mod conn_id;
 pub(crate) mod conn_read;
 pub(crate) mod conn_write;
 pub(crate) mod hash_set_shallow_clone;
+pub(crate) mod idle_timeout;
 pub(crate) mod increase_in_window;
 pub(crate) mod init_where;
+pub(crate) mod keepalive;
 pub(crate) mod loop_event;
+pub(crate) mod metrics;
+pub(crate) mod misbehavior;
+pub(crate) mod padding;
+pub(crate) mod priority_tree;
 pub(crate) mod pump_stream_to_write_loop;
 pub(crate) mod sender;
 pub(crate) mod stream;
@@ -20,6 +28,7 @@ pub(crate) mod stream_handler;
 pub(crate) mod stream_map;
 pub(crate) mod stream_queue;
 pub(crate) mod stream_queue_sync;
+pub(crate) mod traffic;
 pub(crate) mod types;
 pub(crate) mod waiters;
 pub(crate) mod window_size;
@@ -0,0 +1,41 @@
+//! Padding of outgoing `DATA` and `HEADERS` frames.
+
+use rand::thread_rng;
+use rand::Rng;
+
+/// Padding added to outgoing `DATA` and `HEADERS` frames
+/// to obscure message sizes from traffic analysis (RFC 7540 section 10.7).
+///
+/// Padding of `DATA` frames counts in flow control, so it is reduced
+/// when frame size or flow control window is not large enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Pad each frame with the given number of bytes.
+    Fixed(u8),
+    /// Pad each frame with a random number of bytes from zero to given inclusive.
+    Random(u8),
+}
+
+impl Padding {
+    /// Pad length for the next frame.
+    pub(crate) fn pad_len(&self) -> u8 {
+        match *self {
+            Padding::Fixed(len) => len,
+            Padding::Random(max) => thread_rng().gen_range(0, max as u16 + 1) as u8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn random_in_range() {
+        for _ in 0..1000 {
+            assert!(Padding::Random(3).pad_len() <= 3);
+        }
+        assert_eq!(0, Padding::Random(0).pad_len());
+        assert_eq!(7, Padding::Fixed(7).pad_len());
+    }
+}
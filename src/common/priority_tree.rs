@@ -0,0 +1,291 @@
+//! Stream dependency tree (RFC 7540 section 5.3).
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use crate::solicit::stream_id::StreamId;
+
+/// Weight of streams without priority information (5.3.5).
+pub const DEFAULT_WEIGHT: u16 = 16;
+
+/// Limit of nodes for streams not known to stream map,
+/// e. g. idle streams used only for grouping.
+pub const MAX_INACTIVE_NODES: usize = 100;
+
+#[derive(Debug, Clone)]
+struct Node {
+    parent: StreamId,
+    /// `1..=256`
+    weight: u16,
+    children: Vec<StreamId>,
+}
+
+impl Node {
+    fn new(parent: StreamId, weight: u16) -> Node {
+        Node {
+            parent,
+            weight,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Stream dependencies and weights.
+///
+/// Stream zero is the root, other streams are inserted with default priority.
+pub(crate) struct PriorityTree {
+    nodes: HashMap<StreamId, Node>,
+    /// Peer sent priority information
+    used: bool,
+}
+
+impl Default for PriorityTree {
+    fn default() -> PriorityTree {
+        PriorityTree::new()
+    }
+}
+
+impl PriorityTree {
+    pub fn new() -> PriorityTree {
+        let mut nodes = HashMap::new();
+        nodes.insert(0, Node::new(0, DEFAULT_WEIGHT));
+        PriorityTree { nodes, used: false }
+    }
+
+    /// Peer sent at least one priority.
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+
+    /// Number of streams in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn contains(&self, stream_id: StreamId) -> bool {
+        stream_id != 0 && self.nodes.contains_key(&stream_id)
+    }
+
+    /// Insert stream depending on root with default weight if it is not in the tree.
+    pub fn insert_default(&mut self, stream_id: StreamId) {
+        if let Entry::Vacant(e) = self.nodes.entry(stream_id) {
+            e.insert(Node::new(0, DEFAULT_WEIGHT));
+            self.nodes.get_mut(&0).unwrap().children.push(stream_id);
+        }
+    }
+
+    fn detach(&mut self, stream_id: StreamId) {
+        let parent = self.nodes[&stream_id].parent;
+        self.nodes
+            .get_mut(&parent)
+            .unwrap()
+            .children
+            .retain(|&c| c != stream_id);
+    }
+
+    fn attach(&mut self, stream_id: StreamId, parent: StreamId) {
+        self.nodes.get_mut(&stream_id).unwrap().parent = parent;
+        self.nodes
+            .get_mut(&parent)
+            .unwrap()
+            .children
+            .push(stream_id);
+    }
+
+    fn is_descendant(&self, stream_id: StreamId, ancestor: StreamId) -> bool {
+        let mut id = stream_id;
+        while id != 0 {
+            id = self.nodes[&id].parent;
+            if id == ancestor {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Apply priority from `HEADERS` or `PRIORITY` frame (5.3.3).
+    ///
+    /// `weight` is in `1..=256` range.
+    pub fn reprioritize(
+        &mut self,
+        stream_id: StreamId,
+        dep: StreamId,
+        weight: u16,
+        exclusive: bool,
+    ) {
+        assert!(stream_id != 0);
+        self.used = true;
+
+        // Dependency on a stream not in the tree results in default priority (5.3.1)
+        let (dep, weight, exclusive) = match dep == stream_id || !self.nodes.contains_key(&dep) {
+            true => (0, DEFAULT_WEIGHT, false),
+            false => (dep, weight, exclusive),
+        };
+
+        self.insert_default(stream_id);
+
+        // If a stream is made dependent on one of its own dependencies,
+        // the formerly dependent stream is first moved to be dependent
+        // on the reprioritized stream's previous parent
+        if self.is_descendant(dep, stream_id) {
+            let parent = self.nodes[&stream_id].parent;
+            self.detach(dep);
+            self.attach(dep, parent);
+        }
+
+        self.detach(stream_id);
+        if exclusive {
+            let children = std::mem::take(&mut self.nodes.get_mut(&dep).unwrap().children);
+            for &child in &children {
+                self.nodes.get_mut(&child).unwrap().parent = stream_id;
+            }
+            self.nodes
+                .get_mut(&stream_id)
+                .unwrap()
+                .children
+                .extend(children);
+        }
+        self.attach(stream_id, dep);
+        self.nodes.get_mut(&stream_id).unwrap().weight = weight;
+    }
+
+    /// Remove stream, its dependencies become dependencies of its parent
+    /// with weight of the removed stream distributed proportionally (5.3.4).
+    pub fn remove(&mut self, stream_id: StreamId) {
+        if !self.contains(stream_id) {
+            return;
+        }
+        self.detach(stream_id);
+        let node = self.nodes.remove(&stream_id).unwrap();
+        let total: u32 = node
+            .children
+            .iter()
+            .map(|c| self.nodes[c].weight as u32)
+            .sum();
+        for &child in &node.children {
+            let weight = self.nodes[&child].weight as u32 * node.weight as u32 / total;
+            self.nodes.get_mut(&child).unwrap().weight = weight.max(1) as u16;
+            self.attach(child, node.parent);
+        }
+    }
+
+    /// Share of resources allocated to each writable stream.
+    ///
+    /// Stream gets nothing if any of its ancestors is writable, otherwise
+    /// resources of the parent are divided between siblings with writable
+    /// descendants proportionally to their weights.
+    pub fn shares<F>(&self, is_writable: F) -> Vec<(StreamId, f64)>
+    where
+        F: Fn(StreamId) -> bool,
+    {
+        let mut shares = Vec::new();
+        self.collect_shares(0, &is_writable, &mut shares);
+        shares
+    }
+
+    /// Returns false if subtree has no writable streams.
+    fn collect_shares<F>(
+        &self,
+        stream_id: StreamId,
+        is_writable: &F,
+        shares: &mut Vec<(StreamId, f64)>,
+    ) -> bool
+    where
+        F: Fn(StreamId) -> bool,
+    {
+        if stream_id != 0 && is_writable(stream_id) {
+            shares.push((stream_id, 1.0));
+            return true;
+        }
+
+        // Collect subtrees with shares relative to this node,
+        // then scale by weights of subtrees with writable streams
+        let start = shares.len();
+        let mut active = Vec::new();
+        let mut total = 0;
+        for &child in &self.nodes[&stream_id].children {
+            let child_start = shares.len();
+            if self.collect_shares(child, is_writable, shares) {
+                let weight = self.nodes[&child].weight as u32;
+                total += weight;
+                active.push((child_start, shares.len(), weight));
+            }
+        }
+        for (begin, end, weight) in active {
+            for s in &mut shares[begin..end] {
+                s.1 = s.1 * weight as f64 / total as f64;
+            }
+        }
+        shares.len() != start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parent(tree: &PriorityTree, stream_id: StreamId) -> StreamId {
+        tree.nodes[&stream_id].parent
+    }
+
+    fn children(tree: &PriorityTree, stream_id: StreamId) -> Vec<StreamId> {
+        let mut children = tree.nodes[&stream_id].children.clone();
+        children.sort();
+        children
+    }
+
+    #[test]
+    fn reprioritize_exclusive() {
+        // 5.3.3 example: A becomes exclusive dependency of D
+        let mut tree = PriorityTree::new();
+        let (a, b, c, d, e, f) = (1, 3, 5, 7, 9, 11);
+        tree.insert_default(a);
+        tree.reprioritize(b, a, 16, false);
+        tree.reprioritize(c, a, 16, false);
+        tree.reprioritize(d, c, 16, false);
+        tree.reprioritize(e, c, 16, false);
+        tree.reprioritize(f, d, 16, false);
+
+        tree.reprioritize(a, d, 16, true);
+        assert_eq!(d, parent(&tree, a));
+        assert_eq!(0, parent(&tree, d));
+        assert_eq!(vec![b, c, f], children(&tree, a));
+        assert_eq!(vec![e], children(&tree, c));
+        assert_eq!(vec![a], children(&tree, d));
+    }
+
+    #[test]
+    fn remove_redistributes_weight() {
+        let mut tree = PriorityTree::new();
+        tree.insert_default(1);
+        tree.reprioritize(1, 0, 64, false);
+        tree.reprioritize(3, 1, 10, false);
+        tree.reprioritize(5, 1, 30, false);
+
+        tree.remove(1);
+        assert_eq!(vec![3, 5], children(&tree, 0));
+        assert_eq!(16, tree.nodes[&3].weight);
+        assert_eq!(48, tree.nodes[&5].weight);
+        assert_eq!(2, tree.len());
+    }
+
+    #[test]
+    fn shares() {
+        let mut tree = PriorityTree::new();
+        tree.reprioritize(1, 0, 64, false);
+        tree.reprioritize(3, 0, 192, false);
+        tree.reprioritize(5, 3, 16, false);
+        tree.reprioritize(7, 3, 16, false);
+
+        let mut shares = tree.shares(|_| true);
+        shares.sort_by_key(|&(id, _)| id);
+        assert_eq!(vec![(1, 0.25), (3, 0.75)], shares);
+
+        // dependencies share parent resources when parent is blocked
+        let mut shares = tree.shares(|id| id != 3);
+        shares.sort_by_key(|&(id, _)| id);
+        assert_eq!(vec![(1, 0.25), (5, 0.375), (7, 0.375)], shares);
+
+        assert_eq!(vec![(7, 1.0)], tree.shares(|id| id == 7));
+    }
+}
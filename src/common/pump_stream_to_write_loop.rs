@@ -5,6 +5,7 @@ use super::*;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::types::Types;
+use crate::error;
 use crate::misc::any_to_string;
 use crate::solicit::stream_id::StreamId;
 use crate::DataOrTrailers;
@@ -36,6 +37,8 @@ impl<T: Types> PumpStreamToWrite<T> {
                 }
                 Err(window_size::StreamDead::Stream) => {
                     warn!("stream {} dead", self.stream_id);
+                    // stop the source if it is received from network
+                    self.stream.reset(ErrorCode::Cancel);
                     break;
                 }
             }
@@ -57,8 +60,12 @@ impl<T: Types> PumpStreamToWrite<T> {
                 Some(Ok(r)) => Some(r),
                 Some(Err(e)) => {
                     warn!("stream error: {:?}", e);
-                    let stream_end =
-                        CommonToWriteMessage::StreamEnd(self.stream_id, ErrorCode::InternalError);
+                    // propagate reset of the source stream
+                    let error_code = match e {
+                        error::Error::RstStreamReceived(error_code) => error_code,
+                        _ => ErrorCode::InternalError,
+                    };
+                    let stream_end = CommonToWriteMessage::StreamEnd(self.stream_id, error_code);
                     if let Err(e) = self.to_write_tx.unbounded_send(stream_end.into()) {
                         warn!(
                             "failed to write to channel, probably connection is closed: {:?}",
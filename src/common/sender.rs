@@ -2,10 +2,13 @@ use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::types::Types;
 use crate::common::window_size::StreamOutWindowReceiver;
+use crate::common::window_size::StreamOutWindowSender;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::error;
+use crate::headers_place::HeadersPlace;
 use crate::result;
+use crate::solicit::header::HeaderError;
 use crate::solicit::stream_id::StreamId;
 use crate::ErrorCode;
 use crate::Headers;
@@ -29,12 +32,19 @@ pub enum SenderState {
 pub enum SendError {
     ConnectionDied(Arc<error::Error>),
     IncorrectState(SenderState),
+    /// Push is allowed only in responses to client requests.
+    PushNotAllowed,
+    /// Headers are not valid in this place, e. g. pseudo-headers in trailers.
+    InvalidHeaders(HeaderError),
+    /// Status is not `1xx` or is `101` which is not allowed in HTTP/2.
+    NotInformationalStatus(u32),
 }
 
 struct CanSendData<T: Types> {
     write_tx: ConnCommandSender<T>,
     out_window: StreamOutWindowReceiver,
     seen_headers: bool,
+    reserved_capacity: usize,
 }
 
 /// Shared implementation of sender for client and server
@@ -55,6 +65,7 @@ impl<T: Types> CommonSender<T> {
                 write_tx,
                 out_window,
                 seen_headers,
+                reserved_capacity: 0,
             }),
             stream_id,
         }
@@ -76,6 +87,34 @@ impl<T: Types> CommonSender<T> {
         }
     }
 
+    pub fn reserve_capacity(&mut self, capacity: usize) {
+        if let Some(ref mut state) = self.state {
+            state.reserved_capacity = capacity;
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self.state {
+            Some(ref state) => {
+                let available = state.out_window.capacity();
+                if available <= 0 {
+                    0
+                } else {
+                    (available as usize).min(state.reserved_capacity)
+                }
+            }
+            None => 0,
+        }
+    }
+
+    pub fn poll_capacity(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, StreamDead>> {
+        match self.state {
+            Some(ref mut state) => state.out_window.poll_capacity(cx, state.reserved_capacity),
+            // stream is ended or reset by this side, nothing can be sent
+            None => Poll::Ready(Err(StreamDead::Stream)),
+        }
+    }
+
     fn get_can_send(&mut self) -> Result<&mut CanSendData<T>, SendError> {
         match self.state {
             Some(ref mut state) => Ok(state),
@@ -83,6 +122,10 @@ impl<T: Types> CommonSender<T> {
         }
     }
 
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
     pub fn state(&self) -> SenderState {
         match self.state {
             Some(CanSendData {
@@ -96,6 +139,27 @@ impl<T: Types> CommonSender<T> {
         }
     }
 
+    /// Sender for another stream of the same connection.
+    ///
+    /// Returned window sender must be passed to the connection
+    /// when the stream is created.
+    pub fn new_stream(
+        &mut self,
+        stream_id: StreamId,
+    ) -> Result<(CommonSender<T>, StreamOutWindowSender), SendError> {
+        let state = self.get_can_send()?;
+        let (out_window_sender, out_window) = state.out_window.new_stream();
+        let sender = CommonSender::new(stream_id, state.write_tx.clone(), out_window, false);
+        Ok((sender, out_window_sender))
+    }
+
+    pub fn send_message(&mut self, message: T::ToWriteMessage) -> Result<(), SendError> {
+        self.get_can_send()?
+            .write_tx
+            .unbounded_send(message)
+            .map_err(|e| SendError::ConnectionDied(Arc::new(e)))
+    }
+
     pub fn send_common(&mut self, message: CommonToWriteMessage) -> Result<(), SendError> {
         // TODO: why client died?
         self.get_can_send()?
@@ -109,7 +173,9 @@ impl<T: Types> CommonSender<T> {
             return Err(SendError::IncorrectState(self.state()));
         }
         let stream_id = self.stream_id;
-        self.get_can_send()?.out_window.decrease(data.len());
+        let state = self.get_can_send()?;
+        state.out_window.decrease(data.len());
+        state.reserved_capacity = state.reserved_capacity.saturating_sub(data.len());
         self.send_common(CommonToWriteMessage::StreamEnqueue(
             stream_id,
             DataOrHeadersWithFlag {
@@ -159,10 +225,28 @@ impl<T: Types> CommonSender<T> {
         Ok(())
     }
 
+    /// Send `1xx` headers, final headers are still expected after them.
+    pub fn send_informational_headers(&mut self, headers: Headers) -> Result<(), SendError> {
+        if self.state() != SenderState::ExpectingHeaders {
+            return Err(SendError::IncorrectState(self.state()));
+        }
+        let stream_id = self.stream_id;
+        self.send_common(CommonToWriteMessage::StreamEnqueue(
+            stream_id,
+            DataOrHeadersWithFlag {
+                content: DataOrHeaders::Headers(headers),
+                last: false,
+            },
+        ))
+    }
+
     pub fn send_trailers(&mut self, trailers: Headers) -> Result<(), SendError> {
         if self.state() != SenderState::ExpectingBodyOrTrailers {
             return Err(SendError::IncorrectState(self.state()));
         }
+        trailers
+            .validate(T::OUT_REQUEST_OR_RESPONSE, HeadersPlace::Trailing)
+            .map_err(SendError::InvalidHeaders)?;
         let stream_id = self.stream_id;
         self.send_common(CommonToWriteMessage::StreamEnqueue(
             stream_id,
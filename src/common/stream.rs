@@ -6,6 +6,7 @@ use crate::error;
 
 use crate::solicit::end_stream::EndStream;
 use crate::solicit::header::Headers;
+use crate::solicit::priority::Priority;
 use crate::solicit::session::StreamState;
 use crate::solicit::window_size::NonNegativeWindowSize;
 use crate::solicit::window_size::WindowSize;
@@ -14,7 +15,9 @@ use super::types::Types;
 
 use super::stream_queue::StreamQueue;
 use super::window_size;
+use crate::common::metrics::StreamMetrics;
 use crate::common::stream_handler::StreamHandlerInternal;
+use crate::common::traffic::StreamTraffic;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::ErrorCode;
@@ -51,6 +54,7 @@ pub struct HttpStreamStateSnapshot {
     pub pump_out_window_size: isize,
     pub queued_out_data_size: usize,
     pub out_data_size: usize,
+    pub traffic: StreamTraffic,
 }
 
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
@@ -75,6 +79,15 @@ pub(crate) struct HttpStreamCommon<T: Types> {
     // Incoming remaining content-length
     pub in_rem_content_length: Option<u64>,
     pub in_message_stage: InMessageStage,
+    /// Used by write scheduler
+    pub priority: Priority,
+    /// Outgoing `DATA` frames are not sent, e. g. request body
+    /// waiting for `100 Continue` response.
+    pub out_held: bool,
+    /// Reports stream metrics when stream is dropped
+    pub metrics: Option<StreamMetrics>,
+    /// Frames of this stream sent and received so far
+    pub traffic: StreamTraffic,
 }
 
 impl<T: Types> HttpStreamCommon<T> {
@@ -96,6 +109,10 @@ impl<T: Types> HttpStreamCommon<T> {
             pump_out_window,
             in_rem_content_length,
             in_message_stage,
+            priority: Priority::default(),
+            out_held: false,
+            metrics: None,
+            traffic: StreamTraffic::default(),
         }
     }
 
@@ -107,6 +124,7 @@ impl<T: Types> HttpStreamCommon<T> {
             pump_out_window_size: self.pump_out_window.get(),
             queued_out_data_size: self.outgoing.data_size(),
             out_data_size: self.outgoing.data_size(),
+            traffic: self.traffic,
         }
     }
 
@@ -137,10 +155,15 @@ impl<T: Types> HttpStreamCommon<T> {
         match self.outgoing.front() {
             Some(front) => match front {
                 DataOrHeaders::Headers(..) => true,
-                DataOrHeaders::Data(data) => data.len() == 0 || self.out_window_size.size() > 0,
+                DataOrHeaders::Data(data) => {
+                    !self.out_held && (data.is_empty() || self.out_window_size.size() > 0)
+                }
             },
             None => {
-                if let Some(_error_code) = self.outgoing.end() {
+                if let Some(error_code) = self.outgoing.end() {
+                    if self.out_held && error_code == ErrorCode::NoError {
+                        return false;
+                    }
                     if !self.state.is_closed_local() {
                         return true;
                     }
@@ -152,11 +175,15 @@ impl<T: Types> HttpStreamCommon<T> {
     }
 
     #[cfg(debug_assertions)]
-    pub fn pop_outg(&mut self, conn_out_window_size: &mut WindowSize) -> Option<HttpStreamCommand> {
+    pub fn pop_outg(
+        &mut self,
+        conn_out_window_size: &mut WindowSize,
+        max_data_len: usize,
+    ) -> Option<HttpStreamCommand> {
         let writable = self.is_writable();
         let conn_out_window_size_before = conn_out_window_size.size();
 
-        let command = self.pop_outg_impl(conn_out_window_size);
+        let command = self.pop_outg_impl(conn_out_window_size, max_data_len);
         if command.is_some() {
             assert!(writable);
         } else {
@@ -172,17 +199,24 @@ impl<T: Types> HttpStreamCommon<T> {
     }
 
     #[cfg(not(debug_assertions))]
-    pub fn pop_outg(&mut self, conn_out_window_size: &mut WindowSize) -> Option<HttpStreamCommand> {
-        self.pop_outg_impl(conn_out_window_size)
+    pub fn pop_outg(
+        &mut self,
+        conn_out_window_size: &mut WindowSize,
+        max_data_len: usize,
+    ) -> Option<HttpStreamCommand> {
+        self.pop_outg_impl(conn_out_window_size, max_data_len)
     }
 
+    /// Pop next frame, `DATA` is limited by windows and `max_data_len`.
     fn pop_outg_impl(
         &mut self,
         conn_out_window_size: &mut WindowSize,
+        max_data_len: usize,
     ) -> Option<HttpStreamCommand> {
         if self.outgoing.is_empty() {
             return if let Some(error_code) = self.outgoing.end() {
-                if self.state.is_closed_local() {
+                if self.state.is_closed_local() || self.out_held && error_code == ErrorCode::NoError
+                {
                     None
                 } else {
                     self.close_local();
@@ -213,6 +247,10 @@ impl<T: Types> HttpStreamCommon<T> {
             }));
         }
 
+        if self.out_held {
+            return None;
+        }
+
         if self.out_window_size.size() <= 0 || conn_out_window_size.size() <= 0 {
             return None;
         }
@@ -226,9 +264,11 @@ impl<T: Types> HttpStreamCommon<T> {
         // Min of connection and stream window size
         let max_window = cmp::min(self.out_window_size.size(), conn_out_window_size.size());
 
-        if data.len() as usize > max_window as usize {
-            trace!("truncating data of len {} to {}", data.len(), max_window);
-            let size = max_window as usize;
+        let max_len = cmp::min(max_window as usize, max_data_len);
+
+        if data.len() > max_len {
+            trace!("truncating data of len {} to {}", data.len(), max_len);
+            let size = max_len;
             let rem = data.split_off(size);
             self.outgoing.push_front(DataOrHeaders::Data(rem));
         };
@@ -275,7 +315,19 @@ impl<T: Types> HttpStreamCommon<T> {
     }
 }
 
-pub(crate) trait HttpStreamDataSpecific: Send + 'static {}
+impl<T: Types> Drop for HttpStreamCommon<T> {
+    fn drop(&mut self) {
+        self.specific.stream_dropped(&self.traffic);
+    }
+}
+
+pub(crate) trait HttpStreamDataSpecific: Send + 'static {
+    /// Frame of the stream is about to be written.
+    fn command_sent(&mut self, _command: &HttpStreamCommand) {}
+
+    /// Stream is closed, reset, or the connection is terminated.
+    fn stream_dropped(&mut self, _traffic: &StreamTraffic) {}
+}
 
 pub(crate) trait HttpStreamData {
     type Types: Types;
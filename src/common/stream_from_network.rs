@@ -9,9 +9,11 @@ use crate::result;
 
 use super::stream_queue_sync::StreamQueueSyncReceiver;
 use super::types::Types;
+use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::increase_in_window::IncreaseInWindow;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
+use crate::data_or_trailers::ResetStreamFn;
 use futures::task::Context;
 use std::pin::Pin;
 
@@ -22,6 +24,19 @@ pub(crate) struct StreamFromNetwork<T: Types> {
     pub increase_in_window: IncreaseInWindow<T>,
 }
 
+impl<T: Types> StreamFromNetwork<T> {
+    /// Callback to reset this stream from the user side.
+    pub fn reset_fn(&self) -> ResetStreamFn {
+        let stream_id = self.increase_in_window.stream_id;
+        let to_write_tx = self.increase_in_window.to_write_tx.clone();
+        Box::new(move |error_code| {
+            // ignore error, connection might be already dead
+            let m = CommonToWriteMessage::StreamReset(stream_id, error_code);
+            drop(to_write_tx.unbounded_send(m.into()));
+        })
+    }
+}
+
 impl<T: Types> Stream for StreamFromNetwork<T> {
     type Item = result::Result<DataOrHeadersWithFlag>;
 
@@ -7,11 +7,14 @@ use super::stream::HttpStreamCommon;
 use super::stream::HttpStreamStateSnapshot;
 use super::types::Types;
 use crate::common::hash_set_shallow_clone::HashSetShallowClone;
-use crate::common::hash_set_shallow_clone::HashSetShallowCloneItems;
 use crate::common::init_where::InitWhere;
+use crate::common::priority_tree::PriorityTree;
+use crate::common::priority_tree::MAX_INACTIVE_NODES;
 use crate::common::stream::DroppedData;
+use crate::common::stream::HttpStreamDataSpecific;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
+use crate::solicit::priority::Priority;
 use crate::solicit::session::StreamState;
 use crate::solicit::stream_id::StreamId;
 use crate::solicit::window_size::WindowSize;
@@ -22,12 +25,15 @@ pub(crate) struct StreamMap<T: Types> {
     map: HashMap<StreamId, HttpStreamCommon<T>>,
     // This field must be kept in sync with stream state.
     writable_streams: HashSetShallowClone<StreamId>,
+    /// Contains all streams of the map
+    pub priority_tree: PriorityTree,
 }
 
 /// Reference to a stream within `StreamMap`
 pub(crate) struct HttpStreamRef<'m, T: Types + 'm> {
     entry: OccupiedEntry<'m, StreamId, HttpStreamCommon<T>>,
     writable_streams: &'m mut HashSetShallowClone<StreamId>,
+    priority_tree: &'m mut PriorityTree,
 }
 
 impl<T: Types> StreamMap<T> {
@@ -35,6 +41,7 @@ impl<T: Types> StreamMap<T> {
         StreamMap {
             map: HashMap::new(),
             writable_streams: HashSetShallowClone::new(),
+            priority_tree: PriorityTree::new(),
         }
     }
 
@@ -44,6 +51,7 @@ impl<T: Types> StreamMap<T> {
             Entry::Occupied(_) => panic!("stream to insert that already exists: {}", id),
             Entry::Vacant(v) => v.insert(stream),
         };
+        self.priority_tree.insert_default(id);
 
         // unfortunately HashMap doesn't have an API to convert vacant entry into occupied
         let mut stream = self.get_mut(id).unwrap();
@@ -56,6 +64,7 @@ impl<T: Types> StreamMap<T> {
             Entry::Occupied(e) => Some(HttpStreamRef {
                 entry: e,
                 writable_streams: &mut self.writable_streams,
+                priority_tree: &mut self.priority_tree,
             }),
             Entry::Vacant(_) => None,
         }
@@ -104,6 +113,14 @@ impl<T: Types> StreamMap<T> {
         self.sync_is_writable();
     }
 
+    /// Increment or decrement each stream in window
+    /// after our `SETTINGS_INITIAL_WINDOW_SIZE` is acknowledged.
+    pub fn add_in_window(&mut self, delta: i32) {
+        for s in self.map.values_mut() {
+            s.in_window_size.add_saturating(delta);
+        }
+    }
+
     /// Remove locally initiated streams with id > given.
     pub fn remove_local_streams_with_id_gt(
         &mut self,
@@ -118,6 +135,7 @@ impl<T: Types> StreamMap<T> {
 
         let mut r = Vec::new();
         for r_id in stream_ids {
+            self.priority_tree.remove(r_id);
             r.push((r_id, self.map.remove(&r_id).unwrap()))
         }
         r
@@ -127,12 +145,60 @@ impl<T: Types> StreamMap<T> {
         self.map.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Number of locally initiated streams.
+    pub fn local_stream_count(&self) -> usize {
+        self.map
+            .keys()
+            .filter(|&&id| T::init_where(id) == InitWhere::Locally)
+            .count()
+    }
+
     pub fn _stream_ids(&self) -> Vec<StreamId> {
         self.map.keys().cloned().collect()
     }
 
-    pub fn writable_stream_ids(&mut self) -> HashSetShallowCloneItems<StreamId> {
-        self.writable_streams.items()
+    /// Apply RFC 7540 priority to a stream which is open or idle.
+    ///
+    /// `weight` is in `1..=256` range.
+    pub fn reprioritize(
+        &mut self,
+        stream_id: StreamId,
+        dep: StreamId,
+        weight: u16,
+        exclusive: bool,
+    ) {
+        if !self.priority_tree.contains(stream_id)
+            && self.priority_tree.len() >= self.map.len() + MAX_INACTIVE_NODES
+        {
+            warn!("too many idle streams in priority tree");
+            return;
+        }
+        self.priority_tree
+            .reprioritize(stream_id, dep, weight, exclusive);
+    }
+
+    /// Writable streams with share of resources according to RFC 7540 priority tree.
+    pub fn writable_stream_shares(&mut self) -> Vec<(StreamId, f64)> {
+        let writable_streams = &self.writable_streams;
+        self.priority_tree
+            .shares(|stream_id| writable_streams.get(&stream_id).is_some())
+    }
+
+    /// Writable streams ordered by urgency, then non-incremental first,
+    /// then by stream id.
+    pub fn writable_stream_ids_by_priority(&mut self) -> Vec<(Priority, StreamId)> {
+        let mut streams: Vec<(Priority, StreamId)> = self
+            .writable_streams
+            .items()
+            .into_iter()
+            .map(|&stream_id| (self.map[&stream_id].priority, stream_id))
+            .collect();
+        streams.sort_by_key(|&(p, stream_id)| (p.urgency, p.incremental, stream_id));
+        streams
     }
 
     pub fn snapshot(&self) -> HashMap<StreamId, HttpStreamStateSnapshot> {
@@ -170,6 +236,7 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
         let stream_id = self.id();
         debug!("removing stream {}", stream_id);
         self.writable_streams.remove(&stream_id);
+        self.priority_tree.remove(stream_id);
         self.entry.remove();
     }
 
@@ -200,6 +267,12 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
         self.mark_writable(writable);
     }
 
+    /// Hold or release outgoing `DATA` frames, see `HttpStreamCommon::out_held`.
+    pub fn set_out_held(&mut self, out_held: bool) {
+        self.stream().out_held = out_held;
+        self.sync_writable();
+    }
+
     pub fn remove_if_closed(mut self) -> Option<Self> {
         if self.stream().state == StreamState::Closed {
             self.remove();
@@ -209,18 +282,23 @@ impl<'m, T: Types + 'm> HttpStreamRef<'m, T> {
         }
     }
 
-    pub fn pop_outg_maybe_remove(
-        mut self,
+    /// Pop outgoing command, stream is not removed even if closed,
+    /// so the command can be accounted in stream traffic after it is written.
+    pub fn pop_outg(
+        &mut self,
         conn_out_window_size: &mut WindowSize,
-    ) -> (Option<HttpStreamCommand>, Option<Self>) {
+        max_data_len: usize,
+    ) -> Option<HttpStreamCommand> {
         self.check_state();
 
-        let r = self.stream().pop_outg(conn_out_window_size);
+        let r = self.stream().pop_outg(conn_out_window_size, max_data_len);
+        if let Some(command) = &r {
+            self.stream().specific.command_sent(command);
+        }
 
         self.sync_writable();
 
-        let stream = self.remove_if_closed();
-        (r, stream)
+        r
     }
 
     // Reset stream and remove it
@@ -77,6 +77,10 @@ impl ClientResponseStreamHandler for StreamQueueSyncSender<ClientTypes> {
         }))
     }
 
+    fn informational(&mut self, headers: Headers) -> result::Result<()> {
+        self.send(Ok(DataOrHeadersWithFlag::intermediate_headers(headers)))
+    }
+
     fn data_frame(&mut self, data: Bytes, end_stream: bool) -> result::Result<()> {
         self.send(Ok(DataOrHeadersWithFlag {
             content: DataOrHeaders::Data(data),
@@ -0,0 +1,157 @@
+use crate::solicit::frame::RawHttpFrameType;
+
+/// Traffic counters in one direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrafficStats {
+    /// Number of frames.
+    pub frames: u64,
+    /// Payload bytes of `HEADERS`, `PUSH_PROMISE` and `CONTINUATION` frames.
+    pub header_bytes: u64,
+    /// Payload bytes of `DATA` frames.
+    pub data_bytes: u64,
+    /// Number of frames by type, see `frame_type_index`.
+    pub(crate) frames_by_type: [u64; FRAME_TYPE_COUNT],
+}
+
+/// Known frame types plus one slot for unknown types.
+pub(crate) const FRAME_TYPE_COUNT: usize = 12;
+
+/// Index of frame type in `TrafficStats::frames_by_type`.
+fn frame_type_index(frame_type: RawHttpFrameType) -> usize {
+    match frame_type {
+        RawHttpFrameType(t @ 0..=9) => t as usize,
+        RawHttpFrameType::PRIORITY_UPDATE => 10,
+        _ => 11,
+    }
+}
+
+/// Name of frame type at index in `TrafficStats::frames_by_type`.
+pub(crate) fn frame_type_name(index: usize) -> &'static str {
+    const NAMES: [&str; FRAME_TYPE_COUNT] = [
+        "DATA",
+        "HEADERS",
+        "PRIORITY",
+        "RST_STREAM",
+        "SETTINGS",
+        "PUSH_PROMISE",
+        "PING",
+        "GOAWAY",
+        "WINDOW_UPDATE",
+        "CONTINUATION",
+        "PRIORITY_UPDATE",
+        "UNKNOWN",
+    ];
+    NAMES[index]
+}
+
+impl TrafficStats {
+    /// Account a frame of given raw type and payload length.
+    pub(crate) fn frame(&mut self, frame_type: RawHttpFrameType, payload_len: u32) {
+        self.frames += 1;
+        self.frames_by_type[frame_type_index(frame_type)] += 1;
+        match frame_type {
+            RawHttpFrameType::DATA => self.data_bytes += payload_len as u64,
+            RawHttpFrameType::HEADERS
+            | RawHttpFrameType::PUSH_PROMISE
+            | RawHttpFrameType::CONTINUATION => self.header_bytes += payload_len as u64,
+            _ => {}
+        }
+    }
+
+    /// Add counters of `other`.
+    pub(crate) fn add(&mut self, other: &TrafficStats) {
+        self.frames += other.frames;
+        self.header_bytes += other.header_bytes;
+        self.data_bytes += other.data_bytes;
+        for (n, other_n) in self.frames_by_type.iter_mut().zip(&other.frames_by_type) {
+            *n += other_n;
+        }
+    }
+
+    /// Counters accumulated since `before` snapshot of these counters.
+    pub(crate) fn since(&self, before: &TrafficStats) -> TrafficStats {
+        let mut frames_by_type = self.frames_by_type;
+        for (n, before_n) in frames_by_type.iter_mut().zip(&before.frames_by_type) {
+            *n -= before_n;
+        }
+        TrafficStats {
+            frames: self.frames - before.frames,
+            header_bytes: self.header_bytes - before.header_bytes,
+            data_bytes: self.data_bytes - before.data_bytes,
+            frames_by_type,
+        }
+    }
+}
+
+/// Traffic counters of a connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnTraffic {
+    /// Frames sent to peer.
+    pub sent: TrafficStats,
+    /// Frames received from peer.
+    pub received: TrafficStats,
+}
+
+/// Traffic counters of a stream.
+///
+/// Frames are attributed to a stream while it is open, `RST_STREAM`
+/// sent or received after the stream is removed is not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamTraffic {
+    /// Stream frames sent to peer.
+    pub sent: TrafficStats,
+    /// Stream frames received from peer.
+    pub received: TrafficStats,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_by_type() {
+        let mut stats = TrafficStats::default();
+        stats.frame(RawHttpFrameType::DATA, 10);
+        stats.frame(RawHttpFrameType::CONTINUATION, 3);
+        stats.frame(RawHttpFrameType::PRIORITY_UPDATE, 5);
+        stats.frame(RawHttpFrameType(0x20), 1);
+        assert_eq!(4, stats.frames);
+        assert_eq!(10, stats.data_bytes);
+        assert_eq!(3, stats.header_bytes);
+        let counted: Vec<(&str, u64)> = stats
+            .frames_by_type
+            .iter()
+            .enumerate()
+            .filter(|(_, &n)| n != 0)
+            .map(|(i, &n)| (frame_type_name(i), n))
+            .collect();
+        assert_eq!(
+            vec![
+                ("DATA", 1),
+                ("CONTINUATION", 1),
+                ("PRIORITY_UPDATE", 1),
+                ("UNKNOWN", 1)
+            ],
+            counted
+        );
+    }
+
+    #[test]
+    fn add_since() {
+        let mut stats = TrafficStats::default();
+        stats.frame(RawHttpFrameType::HEADERS, 7);
+        let before = stats;
+        stats.frame(RawHttpFrameType::DATA, 10);
+        stats.frame(RawHttpFrameType::DATA, 5);
+
+        let delta = stats.since(&before);
+        assert_eq!(2, delta.frames);
+        assert_eq!(15, delta.data_bytes);
+        assert_eq!(0, delta.header_bytes);
+        assert_eq!(2, delta.frames_by_type[0]);
+
+        let mut sum = before;
+        sum.add(&delta);
+        assert_eq!(stats, sum);
+    }
+}
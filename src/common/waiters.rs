@@ -68,6 +68,17 @@ impl WaiterShared {
 }
 
 impl Waiter {
+    /// Another waiter of the same waker.
+    pub fn new_waiter(&self) -> Waiter {
+        Waiter {
+            waker: self.waker.clone(),
+            shared: Arc::new(WaiterShared {
+                task: AtomicBoxOption::new(),
+                waker_knows: AtomicBool::new(false),
+            }),
+        }
+    }
+
     pub fn park(&self, context: &mut Context<'_>) {
         let mut lock = self.waker.waiters.lock().expect("lock");
 
@@ -142,6 +142,26 @@ impl From<ConnDead> for StreamDead {
 }
 
 impl StreamOutWindowReceiver {
+    /// Window of another stream of the same connection,
+    /// initially empty.
+    pub fn new_stream(&self) -> (StreamOutWindowSender, StreamOutWindowReceiver) {
+        let shared = Arc::new(StreamWindowShared {
+            conn: self.shared.conn.clone(),
+            window_size: AtomicIsize::new(0),
+            task: AtomicBoxOption::new(),
+            closed: AtomicBool::new(false),
+        });
+
+        let sender = StreamOutWindowSender {
+            shared: shared.clone(),
+        };
+        let receiver = StreamOutWindowReceiver {
+            conn_waiter: self.conn_waiter.new_waiter(),
+            shared,
+        };
+        (sender, receiver)
+    }
+
     pub fn decrease(&self, size: usize) {
         self.shared
             .conn
@@ -206,7 +226,85 @@ impl StreamOutWindowReceiver {
         self.poll_conn(cx).map_err(|e| e.into())
     }
 
+    /// Number of bytes which can be sent without exceeding
+    /// either stream or connection window; can be negative.
+    pub fn capacity(&self) -> isize {
+        let stream = self.shared.window_size.load(Ordering::SeqCst);
+        let conn = self.shared.conn.window_size.load(Ordering::SeqCst);
+        stream.min(conn)
+    }
+
+    /// Wait for both stream and connection windows to become positive,
+    /// and return at most `max` bytes available for sending.
+    pub fn poll_capacity(
+        &self,
+        cx: &mut Context<'_>,
+        max: usize,
+    ) -> Poll<Result<usize, StreamDead>> {
+        if max == 0 {
+            self.check_stream_closed()?;
+            return Poll::Ready(Ok(0));
+        }
+        loop {
+            match self.poll(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+            let capacity = self.capacity();
+            if capacity > 0 {
+                return Poll::Ready(Ok((capacity as usize).min(max)));
+            }
+            // connection window was taken by another stream
+            // after `poll` returned, so poll again to park the task
+            // until `WINDOW_UPDATE` increases the window
+        }
+    }
+
     pub async fn poll_f(&self) -> Result<(), StreamDead> {
         future::poll_fn(|cx| self.poll(cx)).await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::task::noop_waker;
+    use futures::task::ArcWake;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct CountWakes(AtomicUsize);
+
+    impl ArcWake for CountWakes {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn poll_capacity_waits_for_window_update() {
+        let conn = ConnOutWindowSender::new(10);
+        let (stream_tx, stream_rx) = conn.new_stream(0);
+
+        let wakes = Arc::new(CountWakes::default());
+        let waker = futures::task::waker(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert_eq!(Poll::Pending, stream_rx.poll_capacity(&mut cx, 100));
+        assert_eq!(0, wakes.0.load(Ordering::SeqCst));
+
+        stream_tx.increase(20);
+        assert_eq!(1, wakes.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(Ok(10)), stream_rx.poll_capacity(&mut cx, 100));
+
+        stream_rx.decrease(10);
+        assert_eq!(Poll::Pending, stream_rx.poll_capacity(&mut cx, 100));
+        conn.increase(5);
+        assert_eq!(2, wakes.0.load(Ordering::SeqCst));
+
+        let noop = noop_waker();
+        let mut cx = Context::from_waker(&noop);
+        assert_eq!(Poll::Ready(Ok(5)), stream_rx.poll_capacity(&mut cx, 100));
+    }
+}
@@ -0,0 +1,375 @@
+//! Propagation of request metadata (trace context, deadline, baggage)
+//! in request headers.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Headers;
+
+/// W3C trace context (`traceparent` header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    /// Id of the span which sent the request.
+    pub parent_id: u64,
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Start a new sampled trace.
+    pub fn new_root() -> TraceContext {
+        TraceContext {
+            trace_id: ((rand::random::<u64>() as u128) << 64) | rand::random::<u64>() as u128 | 1,
+            parent_id: rand::random::<u64>() | 1,
+            flags: 1,
+        }
+    }
+
+    /// Context of the same trace with new span id.
+    pub fn new_child(&self) -> TraceContext {
+        TraceContext {
+            parent_id: rand::random::<u64>() | 1,
+            ..*self
+        }
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.flags & 1 != 0
+    }
+}
+
+/// Metadata carried with a request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    pub trace: Option<TraceContext>,
+    /// Time by which the response is expected.
+    pub deadline: Option<Instant>,
+    /// Application key-value pairs (`baggage` header).
+    pub baggage: Vec<(String, String)>,
+}
+
+impl RequestContext {
+    pub fn new() -> RequestContext {
+        Default::default()
+    }
+
+    /// Time left until deadline.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn baggage_item(&self, key: &str) -> Option<&str> {
+        self.baggage
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| &v[..])
+    }
+}
+
+/// Maps part of `RequestContext` to and from request headers.
+pub trait ContextCodec: Send + Sync + 'static {
+    /// Name for debug output.
+    fn name(&self) -> &'static str;
+    /// Write context to outgoing request headers.
+    fn inject(&self, context: &RequestContext, headers: &mut Headers);
+    /// Read context from incoming request headers.
+    fn extract(&self, headers: &Headers, context: &mut RequestContext);
+}
+
+/// `traceparent` header codec.
+pub struct TraceparentCodec;
+
+impl ContextCodec for TraceparentCodec {
+    fn name(&self) -> &'static str {
+        "traceparent"
+    }
+
+    fn inject(&self, context: &RequestContext, headers: &mut Headers) {
+        if let Some(trace) = &context.trace {
+            headers.remove("traceparent");
+            headers.add(
+                "traceparent",
+                format!(
+                    "00-{:032x}-{:016x}-{:02x}",
+                    trace.trace_id, trace.parent_id, trace.flags
+                ),
+            );
+        }
+    }
+
+    fn extract(&self, headers: &Headers, context: &mut RequestContext) {
+        if let Some(value) = headers.get_opt("traceparent") {
+            context.trace = parse_traceparent(value);
+        }
+    }
+}
+
+fn parse_traceparent(value: &str) -> Option<TraceContext> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    if parts.len() < 4 || parts[0].len() != 2 || parts[0] == "ff" {
+        return None;
+    }
+    if parts[1].len() != 32 || parts[2].len() != 16 || parts[3].len() != 2 {
+        return None;
+    }
+    let trace = TraceContext {
+        trace_id: u128::from_str_radix(parts[1], 16).ok()?,
+        parent_id: u64::from_str_radix(parts[2], 16).ok()?,
+        flags: u8::from_str_radix(parts[3], 16).ok()?,
+    };
+    if trace.trace_id == 0 || trace.parent_id == 0 {
+        return None;
+    }
+    Some(trace)
+}
+
+/// `baggage` header codec.
+pub struct BaggageCodec;
+
+impl ContextCodec for BaggageCodec {
+    fn name(&self) -> &'static str {
+        "baggage"
+    }
+
+    fn inject(&self, context: &RequestContext, headers: &mut Headers) {
+        if context.baggage.is_empty() {
+            return;
+        }
+        let items: Vec<String> = context
+            .baggage
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, percent_encode(v)))
+            .collect();
+        headers.remove("baggage");
+        headers.add("baggage", items.join(","));
+    }
+
+    fn extract(&self, headers: &Headers, context: &mut RequestContext) {
+        for header in headers.iter().filter(|h| h.name() == "baggage") {
+            let value = String::from_utf8_lossy(header.value());
+            for item in value.split(',') {
+                // properties are ignored
+                let item = item.split(';').next().unwrap();
+                if let Some(eq) = item.find('=') {
+                    let key = item[..eq].trim();
+                    if !key.is_empty() {
+                        let value = percent_decode(item[eq + 1..].trim());
+                        context.baggage.push((key.to_owned(), value));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut r = String::new();
+    for b in s.bytes() {
+        match b {
+            b'!' | b'#'..=b'$' | b'&'..=b'+' | b'-'..=b':' | b'<'..=b'[' | b']'..=b'~' => {
+                r.push(b as char)
+            }
+            _ => r.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    r
+}
+
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut r = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                r.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        r.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&r).into_owned()
+}
+
+/// `grpc-timeout` header codec for request deadline.
+pub struct TimeoutCodec;
+
+impl ContextCodec for TimeoutCodec {
+    fn name(&self) -> &'static str {
+        "grpc-timeout"
+    }
+
+    fn inject(&self, context: &RequestContext, headers: &mut Headers) {
+        if let Some(timeout) = context.timeout() {
+            headers.remove("grpc-timeout");
+            headers.add("grpc-timeout", format_timeout(timeout));
+        }
+    }
+
+    fn extract(&self, headers: &Headers, context: &mut RequestContext) {
+        if let Some(timeout) = headers.get_opt("grpc-timeout").and_then(parse_timeout) {
+            context.deadline = Some(Instant::now() + timeout);
+        }
+    }
+}
+
+/// At most 8 digits are allowed.
+const MAX_TIMEOUT_VALUE: u128 = 99_999_999;
+
+fn format_timeout(timeout: Duration) -> String {
+    let units: [(char, u128); 6] = [
+        ('n', 1),
+        ('u', 1_000),
+        ('m', 1_000_000),
+        ('S', 1_000_000_000),
+        ('M', 60_000_000_000),
+        ('H', 3_600_000_000_000),
+    ];
+    let nanos = timeout.as_nanos();
+    for &(unit, unit_nanos) in &units {
+        // round up, so deadline is not shortened
+        let value = nanos.div_ceil(unit_nanos);
+        if value <= MAX_TIMEOUT_VALUE {
+            return format!("{}{}", value, unit);
+        }
+    }
+    format!("{}H", MAX_TIMEOUT_VALUE)
+}
+
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (number, unit) = value.split_at(value.len() - 1);
+    let number: u64 = number.parse().ok()?;
+    Some(match unit {
+        "H" => Duration::from_secs(number * 3600),
+        "M" => Duration::from_secs(number * 60),
+        "S" => Duration::from_secs(number),
+        "m" => Duration::from_millis(number),
+        "u" => Duration::from_micros(number),
+        "n" => Duration::from_nanos(number),
+        _ => return None,
+    })
+}
+
+/// Set of codecs mapping `RequestContext` to and from headers.
+///
+/// Default propagation uses `traceparent`, `baggage` and `grpc-timeout`.
+#[derive(Clone)]
+pub struct ContextPropagation {
+    codecs: Vec<Arc<dyn ContextCodec>>,
+}
+
+impl ContextPropagation {
+    /// Propagation without codecs.
+    pub fn empty() -> ContextPropagation {
+        ContextPropagation { codecs: Vec::new() }
+    }
+
+    pub fn add_codec<C: ContextCodec>(&mut self, codec: C) {
+        self.codecs.push(Arc::new(codec));
+    }
+
+    pub fn inject(&self, context: &RequestContext, headers: &mut Headers) {
+        for codec in &self.codecs {
+            codec.inject(context, headers);
+        }
+    }
+
+    pub fn extract(&self, headers: &Headers) -> RequestContext {
+        let mut context = RequestContext::new();
+        for codec in &self.codecs {
+            codec.extract(headers, &mut context);
+        }
+        context
+    }
+}
+
+impl Default for ContextPropagation {
+    fn default() -> ContextPropagation {
+        let mut propagation = ContextPropagation::empty();
+        propagation.add_codec(TraceparentCodec);
+        propagation.add_codec(BaggageCodec);
+        propagation.add_codec(TimeoutCodec);
+        propagation
+    }
+}
+
+impl fmt::Debug for ContextPropagation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codecs.iter().map(|c| c.name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn traceparent() {
+        let trace =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(0x4bf92f3577b34da6a3ce929d0e0e4736, trace.trace_id);
+        assert_eq!(0x00f067aa0ba902b7, trace.parent_id);
+        assert!(trace.sampled());
+
+        assert_eq!(
+            None,
+            parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01")
+        );
+        assert_eq!(None, parse_traceparent("00-4bf92f35-00f067aa0ba902b7-01"));
+        assert_eq!(
+            None,
+            parse_traceparent("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        assert_eq!("1500000u", format_timeout(Duration::from_millis(1500)));
+        assert_eq!("1n", format_timeout(Duration::from_nanos(1)));
+        assert_eq!("100000S", format_timeout(Duration::from_secs(100000)));
+        assert_eq!(Some(Duration::from_millis(1500)), parse_timeout("1500m"));
+        assert_eq!(Some(Duration::from_secs(7200)), parse_timeout("2H"));
+        assert_eq!(None, parse_timeout("123456789S"));
+        assert_eq!(None, parse_timeout("10x"));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let propagation = ContextPropagation::default();
+        let context = RequestContext {
+            trace: Some(TraceContext::new_root()),
+            deadline: Some(Instant::now() + Duration::from_secs(10)),
+            baggage: vec![
+                ("user".to_owned(), "a b,c%".to_owned()),
+                ("tenant".to_owned(), "x".to_owned()),
+            ],
+        };
+        let mut headers = Headers::new_get("/");
+        propagation.inject(&context, &mut headers);
+        assert_eq!("user=a%20b%2Cc%25,tenant=x", headers.get("baggage"));
+
+        let extracted = propagation.extract(&headers);
+        assert_eq!(context.trace, extracted.trace);
+        assert_eq!(context.baggage, extracted.baggage);
+        assert_eq!(Some("x"), extracted.baggage_item("tenant"));
+        let timeout = extracted.timeout().unwrap();
+        assert!(timeout > Duration::from_secs(9) && timeout <= Duration::from_secs(10));
+
+        assert_eq!(
+            RequestContext::new(),
+            ContextPropagation::empty().extract(&headers)
+        );
+    }
+}
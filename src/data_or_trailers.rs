@@ -1,9 +1,12 @@
+use std::convert::TryFrom;
 use std::panic;
 
 use futures::future;
 use futures::stream;
+use futures::stream::FusedStream;
 use futures::stream::Stream;
 use futures::stream::TryStreamExt;
+use futures::Future;
 use std::task::Poll;
 
 use bytes::Bytes;
@@ -15,13 +18,19 @@ use crate::solicit::header::Headers;
 use crate::data_or_headers::DataOrHeaders;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlag;
 use crate::data_or_headers_with_flag::DataOrHeadersWithFlagStream;
+use crate::message::SimpleHttpMessage;
 use crate::misc::any_to_string;
 use crate::solicit::end_stream::EndStream;
+use crate::ErrorCode;
 use futures::stream::StreamExt;
 use futures::task::Context;
 use std::pin::Pin;
+use tokio::time;
+use tokio::time::Delay;
+use tokio::time::Instant;
 
 /// Stream frame content after initial headers
+#[derive(Debug, PartialEq)]
 pub enum DataOrTrailers {
     /// DATA frame
     Data(Bytes, EndStream),
@@ -48,6 +57,16 @@ impl DataOrTrailers {
     }
 }
 
+impl DataOrTrailers {
+    /// This item is the last item of the stream (has `END_STREAM` flag).
+    pub fn is_end_of_stream(&self) -> bool {
+        match self {
+            DataOrTrailers::Data(_, end_stream) => *end_stream == EndStream::Yes,
+            DataOrTrailers::Trailers(..) => true,
+        }
+    }
+}
+
 /// Stream of DATA or HEADERS frames after initial HEADERS.
 ///
 /// Technically this class is just a simple wrapper over boxed `Stream`
@@ -55,9 +74,23 @@ impl DataOrTrailers {
 ///
 /// Most users won't need anything except data, so this type provides
 /// convenient constructors and accessors.
-pub struct HttpStreamAfterHeaders(
-    pub Pin<Box<dyn Stream<Item = result::Result<DataOrTrailers>> + Send + 'static>>,
-);
+///
+/// The stream is fused: it always ends with an item with `END_STREAM` flag
+/// (trailers or the last `DATA` frame, possibly empty), and after that
+/// (or after an error) returns `None` without polling the underlying stream.
+/// Empty `DATA` frames without `END_STREAM` flag are skipped.
+pub struct HttpStreamAfterHeaders {
+    stream: Pin<Box<dyn Stream<Item = result::Result<DataOrTrailers>> + Send + 'static>>,
+    // Remaining content-length, if known
+    rem_content_length: Option<u64>,
+    terminated: bool,
+    // Sends `RST_STREAM` when stream is received from network
+    reset: Option<ResetStreamFn>,
+    // Stream is reset and fails when it expires
+    deadline: Option<Delay>,
+}
+
+pub(crate) type ResetStreamFn = Box<dyn FnOnce(ErrorCode) + Send + 'static>;
 
 impl HttpStreamAfterHeaders {
     // constructors
@@ -67,7 +100,41 @@ impl HttpStreamAfterHeaders {
     where
         S: Stream<Item = result::Result<DataOrTrailers>> + Send + 'static,
     {
-        HttpStreamAfterHeaders(Box::pin(s))
+        HttpStreamAfterHeaders {
+            stream: Box::pin(s),
+            rem_content_length: None,
+            terminated: false,
+            reset: None,
+            deadline: None,
+        }
+    }
+
+    /// Set expected body length (usually from `content-length` header).
+    ///
+    /// Remaining length is then tracked and available via `rem_content_length`.
+    pub fn with_content_length(mut self, content_length: Option<u64>) -> HttpStreamAfterHeaders {
+        self.rem_content_length = content_length;
+        self
+    }
+
+    pub(crate) fn with_reset_fn(mut self, reset: ResetStreamFn) -> HttpStreamAfterHeaders {
+        self.reset = Some(reset);
+        self
+    }
+
+    /// Fail with `Error::DeadlineExceeded` and reset the stream with `CANCEL`
+    /// if the stream does not end before `deadline`.
+    pub(crate) fn with_deadline(mut self, deadline: Instant) -> HttpStreamAfterHeaders {
+        self.deadline = Some(time::delay_until(deadline));
+        self
+    }
+
+    /// Underlying stream, without content length tracking, deadline
+    /// and the final `END_STREAM` item if the underlying stream has none.
+    pub fn into_inner(
+        self,
+    ) -> Pin<Box<dyn Stream<Item = result::Result<DataOrTrailers>> + Send + 'static>> {
+        self.stream
     }
 
     pub(crate) fn from_parts<S>(s: S) -> HttpStreamAfterHeaders
@@ -112,12 +179,62 @@ impl HttpStreamAfterHeaders {
 
     // getters
 
-    /// Take only `DATA` frames from the stream
-    pub fn filter_data(self) -> impl Stream<Item = result::Result<Bytes>> + Send {
+    /// Remaining number of body bytes if content length is known.
+    pub fn rem_content_length(&self) -> Option<u64> {
+        self.rem_content_length
+    }
+
+    /// Stop receiving the stream.
+    ///
+    /// If the stream is received from network and is not finished yet,
+    /// `RST_STREAM` with given error code is sent to the peer.
+    pub fn reset(mut self, error_code: ErrorCode) {
+        self.reset_impl(error_code);
+    }
+
+    fn reset_impl(&mut self, error_code: ErrorCode) {
+        if let Some(reset) = self.reset.take() {
+            if !self.terminated {
+                reset(error_code);
+            }
+        }
+        self.terminated = true;
+    }
+
+    /// Collect remaining stream into given message,
+    /// fail and reset the stream if body exceeds `max_bytes`.
+    pub(crate) async fn collect_with_limit_into(
+        mut self,
+        mut message: SimpleHttpMessage,
+        max_bytes: usize,
+    ) -> result::Result<SimpleHttpMessage> {
+        if let Some(content_length) = self.rem_content_length {
+            if content_length > max_bytes as u64 {
+                self.reset_impl(ErrorCode::Cancel);
+                return Err(error::Error::BodyLimitExceeded(max_bytes));
+            }
+        }
+
+        let mut size = 0usize;
+        while let Some(part) = self.try_next().await? {
+            if let DataOrTrailers::Data(ref data, _) = part {
+                size = size.saturating_add(data.len());
+                if size > max_bytes {
+                    self.reset_impl(ErrorCode::Cancel);
+                    return Err(error::Error::BodyLimitExceeded(max_bytes));
+                }
+            }
+            message.add(part.into_part().content);
+        }
+        Ok(message)
+    }
+
+    /// Take only non-empty `DATA` frames from the stream
+    pub fn filter_data(self) -> impl FusedStream<Item = result::Result<Bytes>> + Send {
         self.try_filter_map(|p| {
             future::ok(match p {
-                DataOrTrailers::Data(data, ..) => Some(data),
-                DataOrTrailers::Trailers(..) => None,
+                DataOrTrailers::Data(data, ..) if !data.is_empty() => Some(data),
+                DataOrTrailers::Data(..) | DataOrTrailers::Trailers(..) => None,
             })
         })
     }
@@ -125,7 +242,7 @@ impl HttpStreamAfterHeaders {
     pub(crate) fn into_flag_stream(
         self,
     ) -> impl Stream<Item = result::Result<DataOrHeadersWithFlag>> + Send {
-        TryStreamExt::map_ok(self.0, |r| DataOrTrailers::into_part(r))
+        TryStreamExt::map_ok(self, DataOrTrailers::into_part)
     }
 
     // TODO: drop
@@ -133,20 +250,96 @@ impl HttpStreamAfterHeaders {
         DataOrHeadersWithFlagStream::new(self.into_flag_stream())
     }
 
+    /// Call `f` once when the stream ends or fails, or when it is dropped
+    /// before the end, then `f` is called with `Error::CallerDied`.
+    pub(crate) fn on_end<F>(mut self, f: F) -> HttpStreamAfterHeaders
+    where
+        F: FnOnce(Option<&error::Error>) + Send + 'static,
+    {
+        let rem_content_length = self.rem_content_length;
+        let reset = self.reset.take();
+        let stream = HttpStreamAfterHeaders::new(OnEnd {
+            stream: self,
+            f: Some(Box::new(f)),
+        })
+        .with_content_length(rem_content_length);
+        HttpStreamAfterHeaders { reset, ..stream }
+    }
+
+    /// Return `parts` before the remaining items of this stream.
+    #[cfg(feature = "cache")]
+    pub(crate) fn prepend(mut self, parts: Vec<DataOrTrailers>) -> HttpStreamAfterHeaders {
+        let prepended: u64 = parts
+            .iter()
+            .map(|p| match p {
+                DataOrTrailers::Data(data, _) => data.len() as u64,
+                DataOrTrailers::Trailers(..) => 0,
+            })
+            .sum();
+        let rem_content_length = self.rem_content_length.map(|l| l + prepended);
+        let reset = self.reset.take();
+        let stream =
+            HttpStreamAfterHeaders::new(stream::iter(parts.into_iter().map(Ok)).chain(self))
+                .with_content_length(rem_content_length);
+        HttpStreamAfterHeaders { reset, ..stream }
+    }
+
     /// Wrap a stream with `catch_unwind` combinator.
     /// Transform panic into `error::Error`
-    pub fn catch_unwind(self) -> HttpStreamAfterHeaders {
-        HttpStreamAfterHeaders::new(panic::AssertUnwindSafe(self.0).catch_unwind().then(|r| {
-            future::ready(match r {
-                Ok(r) => r,
-                Err(e) => {
-                    let e = any_to_string(e);
-                    // TODO: send plain text error if headers weren't sent yet
-                    warn!("handler panicked: {}", e);
-                    Err(error::Error::HandlerPanicked(e))
-                }
-            })
-        }))
+    pub fn catch_unwind(mut self) -> HttpStreamAfterHeaders {
+        let rem_content_length = self.rem_content_length;
+        let reset = self.reset.take();
+        let stream =
+            HttpStreamAfterHeaders::new(panic::AssertUnwindSafe(self).catch_unwind().then(|r| {
+                future::ready(match r {
+                    Ok(r) => r,
+                    Err(e) => {
+                        let e = any_to_string(e);
+                        // TODO: send plain text error if headers weren't sent yet
+                        warn!("handler panicked: {}", e);
+                        Err(error::Error::HandlerPanicked(e))
+                    }
+                })
+            }))
+            .with_content_length(rem_content_length);
+        HttpStreamAfterHeaders { reset, ..stream }
+    }
+}
+
+/// Stream calling a function when the stream ends, see `HttpStreamAfterHeaders::on_end`.
+struct OnEnd {
+    stream: HttpStreamAfterHeaders,
+    f: Option<OnEndFn>,
+}
+
+type OnEndFn = Box<dyn FnOnce(Option<&error::Error>) + Send + 'static>;
+
+impl Stream for OnEnd {
+    type Item = result::Result<DataOrTrailers>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = match self.stream.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        let end = match &item {
+            Some(Ok(part)) => part.is_end_of_stream(),
+            Some(Err(_)) | None => true,
+        };
+        if end {
+            if let Some(f) = self.f.take() {
+                f(item.as_ref().and_then(|r| r.as_ref().err()));
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for OnEnd {
+    fn drop(&mut self) {
+        if let Some(f) = self.f.take() {
+            f(Some(&error::Error::CallerDied));
+        }
     }
 }
 
@@ -154,6 +347,146 @@ impl Stream for HttpStreamAfterHeaders {
     type Item = result::Result<DataOrTrailers>;
 
     fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.0).poll_next(context)
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+
+        if let Some(deadline) = &mut self.deadline {
+            if Pin::new(deadline).poll(context).is_ready() {
+                self.reset_impl(ErrorCode::Cancel);
+                return Poll::Ready(Some(Err(error::Error::DeadlineExceeded)));
+            }
+        }
+
+        let item = loop {
+            match self.stream.as_mut().poll_next(context) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(DataOrTrailers::Data(ref data, EndStream::No))))
+                    if data.is_empty() =>
+                {
+                    continue
+                }
+                Poll::Ready(Some(item)) => break item,
+                Poll::Ready(None) => {
+                    // Underlying stream ended without `END_STREAM` item,
+                    // so finish it explicitly.
+                    self.terminated = true;
+                    return Poll::Ready(Some(Ok(DataOrTrailers::Data(
+                        Bytes::new(),
+                        EndStream::Yes,
+                    ))));
+                }
+            }
+        };
+
+        match &item {
+            Ok(part) => {
+                if let DataOrTrailers::Data(data, _) = part {
+                    if let Some(rem) = self.rem_content_length {
+                        self.rem_content_length = Some(rem.saturating_sub(data.len() as u64));
+                    }
+                }
+                if part.is_end_of_stream() {
+                    self.terminated = true;
+                }
+            }
+            Err(_) => self.terminated = true,
+        }
+
+        Poll::Ready(Some(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.terminated {
+            return (0, Some(0));
+        }
+        // At least the final `END_STREAM` item is expected.
+        // Each remaining non-empty `DATA` frame carries at least one byte
+        // of content-length, and it can be followed only by the final item.
+        let upper = self
+            .rem_content_length
+            .and_then(|rem| usize::try_from(rem).ok())
+            .and_then(|rem| rem.checked_add(1));
+        (1, upper)
+    }
+}
+
+impl FusedStream for HttpStreamAfterHeaders {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl From<Bytes> for HttpStreamAfterHeaders {
+    fn from(bytes: Bytes) -> HttpStreamAfterHeaders {
+        if bytes.is_empty() {
+            HttpStreamAfterHeaders::empty()
+        } else {
+            HttpStreamAfterHeaders::once_bytes(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor;
+
+    #[test]
+    fn bytes_stream_ends_with_end_of_stream() {
+        let mut s = HttpStreamAfterHeaders::bytes(stream::iter(vec![
+            Ok(Bytes::from_static(b"ab")),
+            Ok(Bytes::from_static(b"cd")),
+        ]))
+        .with_content_length(Some(4));
+
+        assert_eq!((1, Some(5)), s.size_hint());
+        let parts = executor::block_on((&mut s).try_collect::<Vec<_>>()).unwrap();
+        assert_eq!(3, parts.len());
+        assert!(!parts[1].is_end_of_stream());
+        assert!(parts[2].is_end_of_stream());
+        assert_eq!(Some(0), s.rem_content_length());
+        assert!(s.is_terminated());
+        assert_eq!((0, Some(0)), s.size_hint());
+    }
+
+    #[test]
+    fn not_polled_after_end_of_stream() {
+        let s = HttpStreamAfterHeaders::new(stream::iter(vec![
+            Ok(DataOrTrailers::Trailers(Headers::new())),
+            Err(error::Error::User("must not be polled".to_owned())),
+        ]));
+
+        let parts = executor::block_on(s.try_collect::<Vec<_>>()).unwrap();
+        assert_eq!(1, parts.len());
+    }
+
+    #[test]
+    fn filter_data_skips_empty() {
+        let s = HttpStreamAfterHeaders::bytes(stream::iter(vec![
+            Ok(Bytes::from_static(b"ab")),
+            Ok(Bytes::new()),
+            Ok(Bytes::from_static(b"cd")),
+        ]))
+        .with_content_length(Some(4));
+
+        let mut s = s.filter_data();
+        let data = executor::block_on((&mut s).try_collect::<Vec<_>>()).unwrap();
+        assert_eq!(
+            vec![Bytes::from_static(b"ab"), Bytes::from_static(b"cd")],
+            data
+        );
+        assert!(s.is_terminated());
+    }
+
+    #[test]
+    fn size_hint_after_content() {
+        let mut s =
+            HttpStreamAfterHeaders::bytes(stream::iter(vec![Ok(Bytes::from_static(b"ab"))]))
+                .with_content_length(Some(2));
+
+        assert_eq!((1, Some(3)), s.size_hint());
+        executor::block_on(s.next()).unwrap().unwrap();
+        assert_eq!((1, Some(1)), s.size_hint());
     }
 }
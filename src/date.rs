@@ -0,0 +1,179 @@
+//! HTTP-date formatting and parsing.
+
+/// Format IMF-fixdate, e. g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub(crate) fn format_http_date(unix_secs: u64) -> String {
+    let days = unix_secs / 86400;
+    let secs = unix_secs % 86400;
+
+    // civil from days (H. Hinnant)
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = (mp + 2) % 12;
+    let year = yoe + era * 400 + if month < 2 { 1 } else { 0 };
+
+    // 1970-01-01 is Thursday
+    let weekday = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"][(days % 7) as usize];
+    let month = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ][month as usize];
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        weekday,
+        day,
+        month,
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Days since 1970-01-01 of a date in proleptic Gregorian calendar,
+/// `month` is `1..=12`.
+#[cfg(any(feature = "cache", feature = "cookies", test))]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // days from civil (H. Hinnant)
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Parse date into seconds since Unix epoch, using cookie date
+/// algorithm (RFC 6265 section 5.1.1).
+///
+/// Accepts IMF-fixdate as well as obsolete RFC 850 and asctime formats,
+/// which HTTP-date recipients must accept (RFC 9110 section 5.6.7).
+#[cfg(any(feature = "cache", feature = "cookies", test))]
+pub(crate) fn parse_http_date(value: &str) -> Option<i64> {
+    fn is_delimiter(c: char) -> bool {
+        matches!(
+            c,
+            '\x09' | '\x20'..='\x2f' | '\x3b'..='\x40' | '\x5b'..='\x60' | '\x7b'..='\x7e'
+        )
+    }
+
+    /// Leading `min..=max` digits of a token.
+    fn digits(token: &str, min: usize, max: usize) -> Option<u32> {
+        let len = token.bytes().take_while(u8::is_ascii_digit).count();
+        if len < min || len > max {
+            return None;
+        }
+        token[..len].parse().ok()
+    }
+
+    fn time(token: &str) -> Option<(u32, u32, u32)> {
+        let mut fields = token.splitn(3, ':');
+        let hour = fields.next()?;
+        let minute = fields.next()?;
+        let second = fields.next()?;
+        // only the last field may have trailing non-digits
+        if !hour
+            .bytes()
+            .chain(minute.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        Some((
+            digits(hour, 1, 2)?,
+            digits(minute, 1, 2)?,
+            digits(second, 1, 2)?,
+        ))
+    }
+
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let mut hms = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in value.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if hms.is_none() {
+            if let Some(t) = time(token) {
+                hms = Some(t);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = digits(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() && token.len() >= 3 {
+            let prefix = token[..3].to_ascii_lowercase();
+            if let Some(m) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(m as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = hms?;
+    let year = match year? {
+        y @ 70..=99 => y + 1900,
+        y @ 0..=69 => y + 2000,
+        y => y,
+    };
+    let day = day?;
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year as i64, month?, day);
+    Some(days * 86400 + (hour * 3600 + minute * 60 + second) as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!("Thu, 01 Jan 1970 00:00:00 GMT", format_http_date(0));
+        assert_eq!("Sun, 06 Nov 1994 08:49:37 GMT", format_http_date(784111777));
+        assert_eq!("Tue, 29 Feb 2000 23:59:59 GMT", format_http_date(951868799));
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(0, days_from_civil(1970, 1, 1));
+        assert_eq!(784111777 / 86400, days_from_civil(1994, 11, 6));
+        assert_eq!(951868799 / 86400, days_from_civil(2000, 2, 29));
+        assert_eq!(-1, days_from_civil(1969, 12, 31));
+    }
+
+    #[test]
+    fn parse() {
+        let t = Some(784111777);
+        assert_eq!(t, parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"));
+        assert_eq!(t, parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+        assert_eq!(t, parse_http_date("Sun Nov  6 08:49:37 1994"));
+        assert_eq!(
+            Some(951782400),
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT")
+        );
+        assert_eq!(Some(-1), parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT"));
+        assert_eq!(None, parse_http_date("Sun, 06 Nov 1994"));
+        assert_eq!(None, parse_http_date("Sun, 32 Nov 1994 08:49:37 GMT"));
+        assert_eq!(None, parse_http_date("0"));
+        assert_eq!(None, parse_http_date("garbage"));
+    }
+}
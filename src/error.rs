@@ -6,16 +6,19 @@ use std::sync::Arc;
 
 use crate::assert_types::*;
 
-use crate::hpack::decoder::DecoderError;
+use httpbis_codec::hpack::decoder::DecoderError;
 
 use tls_api;
 
 use crate::common::sender::SendError;
 use crate::display_comma_separated::DisplayCommaSeparated;
+use crate::grpc::GrpcStatus;
 use crate::solicit::error_code::ErrorCode;
 use crate::solicit::frame::HttpFrameType;
+use crate::solicit::frame::HttpSetting;
 use crate::solicit::frame::ParseFrameError;
 use crate::solicit::frame::RawHttpFrameType;
+use crate::tls::ClientCertificateRejected;
 use crate::StreamDead;
 use crate::StreamId;
 use std::net::SocketAddr;
@@ -119,6 +122,10 @@ pub enum Error {
     ConnInWindowOverflow(i32, u32),
     /// Ping response wrong payload.
     PingAckOpaqueDataMismatch(u64, u64),
+    /// Header block size exceeds configured limit.
+    HeaderBlockTooLarge(usize, u32),
+    /// Upstream server did not respond in time.
+    UpstreamTimeout,
     /// Goaway after goaway.
     GoawayAfterGoaway,
     /// Got `SETTINGS` ack without `SETTINGS` sent.
@@ -137,6 +144,55 @@ pub enum Error {
     RequestIsMadeUsingHttp1,
     /// Listen address is not specified.
     ListenAddrNotSpecified,
+    /// Body is larger than the limit.
+    BodyLimitExceeded(usize),
+    /// Server responded to extended `CONNECT` with non-2xx status.
+    ExtendedConnectRejected(u32),
+    /// Server responded to `CONNECT` with non-2xx status.
+    ConnectRejected(u32),
+    /// Request is not a plain `CONNECT` request.
+    NotConnectRequest,
+    /// Server did not enable extended `CONNECT` (`SETTINGS_ENABLE_CONNECT_PROTOCOL`).
+    ExtendedConnectNotSupported,
+    /// Request is not a WebSocket extended `CONNECT` request.
+    NotWebSocketRequest,
+    /// Length-prefixed message is larger than the limit.
+    MessageTooLarge(u64, usize),
+    /// Body ended in the middle of a length-prefixed message.
+    TruncatedMessage,
+    /// Compression flag of a length-prefixed message is not `0` or `1`.
+    InvalidMessageFlag(u8),
+    /// Compressed message received, but compressor is not configured.
+    CompressedMessageNotSupported,
+    /// Malformed HTTP/1 request or response head.
+    InvalidHttp1Head(String),
+    /// Server did not switch to HTTP/2 in response to `h2c` upgrade request.
+    H2cUpgradeRejected(Option<u32>),
+    /// Request body cannot be streamed over HTTP/1 fallback connection.
+    StreamingRequestOverHttp1,
+    /// Keepalive `PING` was not acknowledged in time.
+    KeepaliveTimeout,
+    /// Received header list exceeds `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    HeaderListTooLarge(usize),
+    /// Too many requests wait for server `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    RequestQueueFull,
+    /// Setting value is out of range allowed by RFC 7540.
+    InvalidSetting(HttpSetting),
+    /// Server rejected client certificate during TLS handshake.
+    ClientCertificateRejected,
+    /// TLS ALPN did not select expected protocol, contains selected protocol if any.
+    AlpnMismatch(Option<Vec<u8>>),
+    /// Server certificate chain does not match `ClientConf::pinned_spki_sha256`.
+    PinMismatch,
+    /// Response was not received before request deadline,
+    /// see `Client::start_request_end_stream_with_deadline`.
+    DeadlineExceeded,
+    /// Request URI or `:scheme` and `:authority` do not identify an origin.
+    InvalidRequestTarget(String),
+    /// Message cannot be converted to or from `http` crate message.
+    InvalidHttpMessage(String),
+    /// gRPC call completed with status other than `OK`.
+    GrpcStatus(GrpcStatus),
 }
 
 fn _assert_error_sync_send() {
@@ -148,6 +204,12 @@ fn _assert_error_sync_send() {
 /// into an `HttpError` by wrapping the given `io::Error` into an `HttpError::IoError` variant.
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
+        if err
+            .get_ref()
+            .is_some_and(|e| e.is::<ClientCertificateRejected>())
+        {
+            return Error::ClientCertificateRejected;
+        }
         Error::IoError(err)
     }
 }
@@ -261,6 +323,10 @@ impl fmt::Display for Error {
             Error::PingAckOpaqueDataMismatch(_, _) => {
                 write!(f, "{} ack opaque data mismatch", HttpFrameType::Ping)
             }
+            Error::HeaderBlockTooLarge(size, limit) => {
+                write!(f, "Header block size {} exceeds limit {}", size, limit)
+            }
+            Error::UpstreamTimeout => write!(f, "Upstream timeout"),
             Error::GoawayAfterGoaway => write!(
                 f,
                 "{} after {}",
@@ -279,6 +345,54 @@ impl fmt::Display for Error {
             Error::PayloadTooLarge(_, _) => write!(f, "Payload too large"),
             Error::RequestIsMadeUsingHttp1 => write!(f, "Request is made using HTTP/1"),
             Error::ListenAddrNotSpecified => write!(f, "Listen addr not specified"),
+            Error::BodyLimitExceeded(limit) => write!(f, "Body exceeds limit of {} bytes", limit),
+            Error::ExtendedConnectRejected(status) => {
+                write!(f, "Extended CONNECT rejected with status {}", status)
+            }
+            Error::ConnectRejected(status) => write!(f, "CONNECT rejected with status {}", status),
+            Error::NotConnectRequest => write!(f, "Not a CONNECT request"),
+            Error::ExtendedConnectNotSupported => {
+                write!(f, "Server does not support extended CONNECT")
+            }
+            Error::NotWebSocketRequest => write!(f, "Not a WebSocket request"),
+            Error::MessageTooLarge(len, limit) => {
+                write!(
+                    f,
+                    "Message of {} bytes exceeds limit of {} bytes",
+                    len, limit
+                )
+            }
+            Error::TruncatedMessage => write!(f, "Body ended in the middle of a message"),
+            Error::InvalidMessageFlag(flag) => write!(f, "Invalid message flag: {}", flag),
+            Error::CompressedMessageNotSupported => {
+                write!(f, "Compressed message received without compressor")
+            }
+            Error::InvalidHttp1Head(e) => write!(f, "Invalid HTTP/1 head: {}", e),
+            Error::H2cUpgradeRejected(Some(status)) => {
+                write!(f, "h2c upgrade rejected with status {}", status)
+            }
+            Error::H2cUpgradeRejected(None) => write!(f, "h2c upgrade rejected"),
+            Error::HeaderListTooLarge(size) => {
+                write!(f, "Header list of {} bytes exceeds limit", size)
+            }
+            Error::RequestQueueFull => write!(f, "Request queue is full"),
+            Error::InvalidSetting(setting) => write!(f, "Invalid setting: {:?}", setting),
+            Error::ClientCertificateRejected => write!(f, "Server rejected client certificate"),
+            Error::AlpnMismatch(Some(protocol)) => write!(
+                f,
+                "ALPN selected unexpected protocol: {}",
+                String::from_utf8_lossy(protocol)
+            ),
+            Error::AlpnMismatch(None) => write!(f, "ALPN protocol was not negotiated"),
+            Error::PinMismatch => write!(f, "Server certificate does not match pinned keys"),
+            Error::DeadlineExceeded => write!(f, "Request deadline exceeded"),
+            Error::InvalidRequestTarget(target) => write!(f, "Invalid request target: {}", target),
+            Error::InvalidHttpMessage(message) => write!(f, "Invalid HTTP message: {}", message),
+            Error::GrpcStatus(status) => write!(f, "gRPC status {}", status),
+            Error::KeepaliveTimeout => write!(f, "Keepalive PING ack timeout"),
+            Error::StreamingRequestOverHttp1 => {
+                write!(f, "Streaming request body is not supported over HTTP/1")
+            }
         }
     }
 }
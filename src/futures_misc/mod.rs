@@ -1,5 +1,6 @@
 mod shutdown_signal;
 
+pub(crate) mod read_stream;
 pub(crate) mod select_lite;
 
 pub use self::shutdown_signal::*;
@@ -0,0 +1,73 @@
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::stream::Stream;
+use tokio::io::AsyncRead;
+
+use crate::result;
+
+/// Stream of chunks read from `AsyncRead` until EOF.
+pub(crate) struct ReadStream<R> {
+    read: R,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R> ReadStream<R> {
+    /// Chunks are at most `chunk_size` bytes.
+    pub fn new(read: R, chunk_size: usize) -> ReadStream<R> {
+        ReadStream {
+            read,
+            buf: vec![0; chunk_size],
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for ReadStream<R> {
+    type Item = result::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = &mut *self;
+        if me.done {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut me.read).poll_read(cx, &mut me.buf) {
+            Poll::Ready(Ok(0)) => {
+                me.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Ok(n)) => Poll::Ready(Some(Ok(Bytes::copy_from_slice(&me.buf[..n])))),
+            Poll::Ready(Err(e)) => {
+                me.done = true;
+                Poll::Ready(Some(Err(e.into())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+    use futures::stream::TryStreamExt;
+
+    #[test]
+    fn chunks() {
+        let data: &[u8] = b"abcdefg";
+        let chunks: Vec<Bytes> =
+            executor::block_on(ReadStream::new(data, 3).try_collect()).unwrap();
+        assert_eq!(
+            vec![
+                Bytes::from_static(b"abc"),
+                Bytes::from_static(b"def"),
+                Bytes::from_static(b"g")
+            ],
+            chunks
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! gRPC over HTTP/2 helpers: headers, `grpc-status` trailers
+//! and length-prefixed message bodies.
+//!
+//! Messages are framed with `LengthPrefixedCodec::grpc`,
+//! serialization of messages is left to the caller.
+
+use std::fmt;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use futures::stream;
+use futures::stream::Stream;
+use futures::stream::StreamExt;
+
+use crate::error;
+use crate::result;
+use crate::solicit::end_stream::EndStream;
+use crate::solicit::header::Headers;
+use crate::solicit::HttpScheme;
+use crate::solicit_async::HttpFutureStreamSend;
+use crate::DataOrTrailers;
+use crate::HttpStreamAfterHeaders;
+use crate::LengthPrefixedCodec;
+
+/// Content type of gRPC requests and responses.
+pub const CONTENT_TYPE: &str = "application/grpc";
+
+/// `application/grpc` optionally followed by `+format` or `;parameters`.
+pub fn is_grpc_content_type(content_type: &str) -> bool {
+    match content_type.get(..CONTENT_TYPE.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(CONTENT_TYPE) => {
+            match content_type.as_bytes().get(CONTENT_TYPE.len()) {
+                None | Some(b'+') | Some(b';') => true,
+                Some(_) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// gRPC status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcCode {
+    Ok,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+}
+
+const CODES: [GrpcCode; 17] = [
+    GrpcCode::Ok,
+    GrpcCode::Cancelled,
+    GrpcCode::Unknown,
+    GrpcCode::InvalidArgument,
+    GrpcCode::DeadlineExceeded,
+    GrpcCode::NotFound,
+    GrpcCode::AlreadyExists,
+    GrpcCode::PermissionDenied,
+    GrpcCode::ResourceExhausted,
+    GrpcCode::FailedPrecondition,
+    GrpcCode::Aborted,
+    GrpcCode::OutOfRange,
+    GrpcCode::Unimplemented,
+    GrpcCode::Internal,
+    GrpcCode::Unavailable,
+    GrpcCode::DataLoss,
+    GrpcCode::Unauthenticated,
+];
+
+impl GrpcCode {
+    /// Code by number, unknown numbers are `Unknown`.
+    pub fn from_u32(code: u32) -> GrpcCode {
+        CODES
+            .get(code as usize)
+            .copied()
+            .unwrap_or(GrpcCode::Unknown)
+    }
+
+    pub fn as_u32(self) -> u32 {
+        self as u32
+    }
+
+    /// Code of a call which received non-`200` HTTP status
+    /// (gRPC "HTTP to gRPC Status Code Mapping").
+    pub fn from_http_status(status: u32) -> GrpcCode {
+        match status {
+            400 => GrpcCode::Internal,
+            401 => GrpcCode::Unauthenticated,
+            403 => GrpcCode::PermissionDenied,
+            404 => GrpcCode::Unimplemented,
+            429 | 502 | 503 | 504 => GrpcCode::Unavailable,
+            _ => GrpcCode::Unknown,
+        }
+    }
+}
+
+/// Percent-encode `grpc-message` value.
+fn encode_message(message: &str) -> String {
+    let mut r = String::with_capacity(message.len());
+    for &b in message.as_bytes() {
+        if (0x20..0x7f).contains(&b) && b != b'%' {
+            r.push(b as char);
+        } else {
+            r.push_str(&format!("%{:02X}", b));
+        }
+    }
+    r
+}
+
+/// Decode percent-encoded `grpc-message` value, invalid
+/// sequences are left as is.
+fn decode_message(message: &str) -> String {
+    let bytes = message.as_bytes();
+    let mut r = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match hex {
+            Some(b) => {
+                r.push(b);
+                i += 3;
+            }
+            None => {
+                r.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&r).into_owned()
+}
+
+/// Status of a gRPC call, sent in `grpc-status` and `grpc-message` trailers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcStatus {
+    pub code: GrpcCode,
+    pub message: String,
+}
+
+impl GrpcStatus {
+    pub fn new(code: GrpcCode, message: impl Into<String>) -> GrpcStatus {
+        GrpcStatus {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn ok() -> GrpcStatus {
+        GrpcStatus::new(GrpcCode::Ok, "")
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.code == GrpcCode::Ok
+    }
+
+    /// Status of a failed call: `Error::GrpcStatus` is unwrapped,
+    /// other errors are `Internal`.
+    pub fn from_error(error: &error::Error) -> GrpcStatus {
+        match error {
+            error::Error::GrpcStatus(status) => status.clone(),
+            e => GrpcStatus::new(GrpcCode::Internal, e.to_string()),
+        }
+    }
+
+    /// Status from `grpc-status` and `grpc-message` headers or trailers.
+    pub fn from_headers(headers: &Headers) -> Option<GrpcStatus> {
+        let code = headers.get_opt_parse::<u32>("grpc-status")?;
+        Some(GrpcStatus::new(
+            GrpcCode::from_u32(code),
+            headers
+                .get_opt("grpc-message")
+                .map(decode_message)
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// Add `grpc-status` and `grpc-message` headers.
+    pub fn add_to_headers(&self, headers: &mut Headers) {
+        headers.add("grpc-status", self.code.as_u32().to_string());
+        if !self.message.is_empty() {
+            headers.add("grpc-message", encode_message(&self.message));
+        }
+    }
+
+    /// Trailers carrying this status.
+    pub fn to_trailers(&self) -> Headers {
+        let mut trailers = Headers::new();
+        self.add_to_headers(&mut trailers);
+        trailers
+    }
+}
+
+impl fmt::Display for GrpcStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.code)?;
+        if !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Headers of a gRPC request to `path` (`/package.Service/Method`).
+pub fn request_headers(scheme: HttpScheme, authority: &str, path: &str) -> Headers {
+    let mut headers = Headers::new_post(path.to_owned());
+    headers.add(":scheme", scheme.as_bytes());
+    headers.add(":authority", authority.to_owned());
+    headers.add("content-type", CONTENT_TYPE);
+    headers.add("te", "trailers");
+    headers
+}
+
+/// `true` if request headers are headers of a gRPC request.
+pub fn is_grpc_request(headers: &Headers) -> bool {
+    headers.method() == "POST" && headers.content_type().is_some_and(is_grpc_content_type)
+}
+
+/// Headers of a gRPC response, followed by messages and status trailers.
+pub fn response_headers() -> Headers {
+    let mut headers = Headers::ok_200();
+    headers.add("content-type", CONTENT_TYPE);
+    headers
+}
+
+/// Headers of a response without messages ("Trailers-Only"),
+/// usually sent for failed calls.
+pub fn trailers_only(status: &GrpcStatus) -> Headers {
+    let mut headers = response_headers();
+    status.add_to_headers(&mut headers);
+    headers
+}
+
+/// Response body with `messages` framed with `codec` followed by status trailers.
+///
+/// Status is `OK` if `messages` complete successfully, otherwise
+/// the status is made from the error with `GrpcStatus::from_error`.
+pub fn response_body<S>(codec: &LengthPrefixedCodec, messages: S) -> HttpStreamAfterHeaders
+where
+    S: Stream<Item = result::Result<Bytes>> + Send + 'static,
+{
+    let messages = codec.encode_stream(messages);
+    HttpStreamAfterHeaders::new(stream::unfold(Some(messages), |messages| async move {
+        let mut messages = messages?;
+        let part = match messages.next().await {
+            Some(Ok(message)) => {
+                return Some((
+                    Ok(DataOrTrailers::Data(message, EndStream::No)),
+                    Some(messages),
+                ))
+            }
+            Some(Err(e)) => DataOrTrailers::Trailers(GrpcStatus::from_error(&e).to_trailers()),
+            None => DataOrTrailers::Trailers(GrpcStatus::ok().to_trailers()),
+        };
+        Some((Ok(part), None))
+    }))
+}
+
+/// Messages of a gRPC response with given `headers` and `body`.
+///
+/// Stream fails with `Error::GrpcStatus` if call status is not `OK`,
+/// including when response has non-`200` HTTP status or is not a gRPC response.
+pub fn response_messages(
+    codec: &LengthPrefixedCodec,
+    headers: &Headers,
+    body: HttpStreamAfterHeaders,
+) -> HttpFutureStreamSend<Bytes> {
+    let status = headers.status();
+    let failed = |status: GrpcStatus| -> HttpFutureStreamSend<Bytes> {
+        Box::pin(stream::once(async {
+            Err(error::Error::GrpcStatus(status))
+        }))
+    };
+    if status != 200 {
+        return failed(GrpcStatus::new(
+            GrpcCode::from_http_status(status),
+            format!("HTTP status {}", status),
+        ));
+    }
+    if let Some(status) = GrpcStatus::from_headers(headers) {
+        return if status.is_ok() {
+            Box::pin(stream::empty())
+        } else {
+            failed(status)
+        };
+    }
+    if !headers.content_type().is_some_and(is_grpc_content_type) {
+        return failed(GrpcStatus::new(
+            GrpcCode::Unknown,
+            format!("content-type: {}", headers.content_type().unwrap_or("-")),
+        ));
+    }
+
+    let codec = codec.clone();
+    Box::pin(stream::unfold(
+        Some((body, BytesMut::new())),
+        move |state| {
+            let codec = codec.clone();
+            async move {
+                let (mut body, mut buf) = state?;
+                loop {
+                    match codec.decode(&mut buf) {
+                        Ok(Some(message)) => return Some((Ok(message), Some((body, buf)))),
+                        Ok(None) => {}
+                        Err(e) => return Some((Err(e), None)),
+                    }
+                    let trailers = match body.next().await {
+                        Some(Ok(DataOrTrailers::Data(data, _))) => {
+                            buf.extend_from_slice(&data);
+                            continue;
+                        }
+                        Some(Ok(DataOrTrailers::Trailers(trailers))) => trailers,
+                        Some(Err(e)) => return Some((Err(e), None)),
+                        None => Headers::new(),
+                    };
+                    if !buf.is_empty() {
+                        return Some((Err(error::Error::TruncatedMessage), None));
+                    }
+                    let status = GrpcStatus::from_headers(&trailers).unwrap_or_else(|| {
+                        GrpcStatus::new(GrpcCode::Internal, "missing grpc-status")
+                    });
+                    return if status.is_ok() {
+                        None
+                    } else {
+                        Some((Err(error::Error::GrpcStatus(status)), None))
+                    };
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+    use futures::stream::TryStreamExt;
+
+    #[test]
+    fn content_type() {
+        assert!(is_grpc_content_type("application/grpc"));
+        assert!(is_grpc_content_type("application/grpc+proto"));
+        assert!(is_grpc_content_type("Application/GRPC;charset=utf-8"));
+        assert!(!is_grpc_content_type("application/grpc-web"));
+        assert!(!is_grpc_content_type("application/json"));
+    }
+
+    #[test]
+    fn status_headers() {
+        let status = GrpcStatus::new(GrpcCode::NotFound, "no such user: 100% sure\n");
+        let trailers = status.to_trailers();
+        assert_eq!("5", trailers.get("grpc-status"));
+        assert_eq!("no such user: 100%25 sure%0A", trailers.get("grpc-message"));
+        assert_eq!(Some(status), GrpcStatus::from_headers(&trailers));
+
+        let mut headers = Headers::new();
+        headers.add("grpc-status", "42");
+        headers.add("grpc-message", "caf%C3%A9 %zz");
+        assert_eq!(
+            Some(GrpcStatus::new(GrpcCode::Unknown, "café %zz")),
+            GrpcStatus::from_headers(&headers)
+        );
+        assert_eq!(None, GrpcStatus::from_headers(&Headers::new()));
+    }
+
+    #[test]
+    fn round_trip() {
+        let codec = LengthPrefixedCodec::grpc();
+        let messages = stream::iter(vec![Ok(Bytes::from_static(b"a")), Ok(Bytes::new())]);
+        let body = response_body(&codec, messages);
+        let messages: Vec<Bytes> =
+            executor::block_on(response_messages(&codec, &response_headers(), body).try_collect())
+                .unwrap();
+        assert_eq!(vec![Bytes::from_static(b"a"), Bytes::new()], messages);
+    }
+
+    #[test]
+    fn failed() {
+        let codec = LengthPrefixedCodec::grpc();
+        let status = GrpcStatus::new(GrpcCode::Aborted, "conflict");
+        let messages = stream::iter(vec![
+            Ok(Bytes::from_static(b"a")),
+            Err(error::Error::GrpcStatus(status.clone())),
+        ]);
+        let body = response_body(&codec, messages);
+        let mut messages = response_messages(&codec, &response_headers(), body);
+        let first = executor::block_on(messages.next()).unwrap().unwrap();
+        assert_eq!(&b"a"[..], &first[..]);
+        match executor::block_on(messages.next()) {
+            Some(Err(error::Error::GrpcStatus(s))) => assert_eq!(status, s),
+            r => panic!("{:?}", r),
+        }
+        assert!(executor::block_on(messages.next()).is_none());
+
+        let messages = response_messages(
+            &codec,
+            &trailers_only(&status),
+            HttpStreamAfterHeaders::empty(),
+        );
+        match executor::block_on(messages.try_collect::<Vec<_>>()) {
+            Err(error::Error::GrpcStatus(s)) => assert_eq!(status, s),
+            r => panic!("{:?}", r),
+        }
+
+        let messages = response_messages(
+            &codec,
+            &Headers::new_status(503),
+            HttpStreamAfterHeaders::empty(),
+        );
+        match executor::block_on(messages.try_collect::<Vec<_>>()) {
+            Err(error::Error::GrpcStatus(s)) => assert_eq!(GrpcCode::Unavailable, s.code),
+            r => panic!("{:?}", r),
+        }
+    }
+}
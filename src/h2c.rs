@@ -0,0 +1,248 @@
+//! HTTP/2 over cleartext TCP started with HTTP/1.1 `Upgrade` (RFC 7540 section 3.2).
+
+use bytes::Bytes;
+
+use crate::http1::Http1Head;
+use crate::solicit::frame::FrameIR;
+use crate::solicit::frame::SettingsFrame;
+use crate::solicit::frame::FRAME_HEADER_LEN;
+use crate::solicit::header::name::PseudoHeaderName;
+use crate::solicit::header::Header;
+use crate::Headers;
+
+/// Header with base64url-encoded `SETTINGS` payload.
+pub(crate) const HTTP2_SETTINGS_HEADER: &str = "HTTP2-Settings";
+
+/// Response of a server accepting upgrade.
+pub(crate) const SWITCHING_PROTOCOLS_RESPONSE: &[u8] = b"\
+HTTP/1.1 101 Switching Protocols\r\n\
+Connection: Upgrade\r\n\
+Upgrade: h2c\r\n\
+\r\n\
+";
+
+fn base64url_decode(s: &str) -> Option<Vec<u8>> {
+    let mut r = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in s.trim_end_matches('=').bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            r.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(r)
+}
+
+pub(crate) fn base64url_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut r = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            r.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    r
+}
+
+/// Encode `SETTINGS` payload for `HTTP2-Settings` header (without padding).
+pub(crate) fn encode_settings(settings: &SettingsFrame) -> String {
+    let frame = settings.clone().serialize_into_vec();
+    base64url_encode(&frame[FRAME_HEADER_LEN..])
+}
+
+/// `OPTIONS *` request asking server to switch to HTTP/2.
+///
+/// Response to this request is received on stream 1 and discarded.
+pub(crate) fn upgrade_request(authority: &str, settings: &SettingsFrame) -> Vec<u8> {
+    format!(
+        "OPTIONS * HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Connection: Upgrade, {}\r\n\
+        Upgrade: h2c\r\n\
+        {}: {}\r\n\
+        \r\n",
+        authority,
+        HTTP2_SETTINGS_HEADER,
+        HTTP2_SETTINGS_HEADER,
+        encode_settings(settings)
+    )
+    .into_bytes()
+}
+
+/// Server accepted upgrade if response is `101` with `Upgrade: h2c`.
+pub(crate) fn is_upgrade_accepted(head: &Http1Head) -> bool {
+    head.status() == Some(101) && head.header_has_token("upgrade", "h2c")
+}
+
+/// Decode `HTTP2-Settings` header value.
+pub(crate) fn decode_settings(value: &str) -> Option<SettingsFrame> {
+    let payload = base64url_decode(value.trim())?;
+    let settings = SettingsFrame::parse_payload(&payload).ok()?;
+    Some(SettingsFrame::from_settings(settings))
+}
+
+/// HTTP/1.1 request accepted for upgrade.
+#[derive(Debug)]
+pub(crate) struct H2cUpgrade {
+    /// Request converted to HTTP/2 headers, it becomes stream 1
+    pub headers: Headers,
+    /// Client settings from `HTTP2-Settings` header
+    pub settings: SettingsFrame,
+}
+
+impl H2cUpgrade {
+    /// Upgrade of an HTTP/1.1 request, `None` if request does not ask for `h2c`
+    /// or cannot be upgraded.
+    ///
+    /// Requests with body are not upgraded.
+    pub fn from_request(head: &Http1Head) -> Option<H2cUpgrade> {
+        let (method, target, version) = head.request_line()?;
+        if version != "HTTP/1.1"
+            || !head.header_has_token("upgrade", "h2c")
+            || !head.header_has_token("connection", "upgrade")
+            || !head.header_has_token("connection", HTTP2_SETTINGS_HEADER)
+            || head.header("transfer-encoding").is_some()
+            || head.header("content-length").is_some_and(|l| l != "0")
+        {
+            return None;
+        }
+
+        let settings = decode_settings(head.header(HTTP2_SETTINGS_HEADER)?)?;
+
+        let mut headers = vec![
+            Header::new(PseudoHeaderName::Method, method.to_owned()),
+            Header::new(PseudoHeaderName::Scheme, "http"),
+            Header::new(PseudoHeaderName::Path, target.to_owned()),
+        ];
+        if let Some(host) = head.header("host") {
+            headers.push(Header::new(PseudoHeaderName::Authority, host.to_owned()));
+        }
+        for (name, value) in &head.headers {
+            let name = name.to_ascii_lowercase();
+            if head.is_connection_specific(&name)
+                || name == "host"
+                || name == "http2-settings"
+                || (name == "te" && value != "trailers")
+            {
+                continue;
+            }
+            let header =
+                Header::new_validate(Bytes::from(name), Bytes::from(value.clone())).ok()?;
+            headers.push(header);
+        }
+
+        Some(H2cUpgrade {
+            headers: Headers::from_vec(headers),
+            settings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::solicit::frame::HttpSetting;
+
+    #[test]
+    fn decode_settings_value() {
+        assert_eq!(Some(vec![]), decode_settings("").map(|f| f.settings));
+        // MAX_CONCURRENT_STREAMS 100, INITIAL_WINDOW_SIZE 65535
+        assert_eq!(
+            Some(vec![
+                HttpSetting::MaxConcurrentStreams(100),
+                HttpSetting::InitialWindowSize(65535),
+            ]),
+            decode_settings("AAMAAABkAAQAAP__").map(|f| f.settings)
+        );
+        assert!(decode_settings("AAMAAABk+").is_none());
+        assert!(decode_settings("AAMA").is_none());
+    }
+
+    #[test]
+    fn encode_settings_value() {
+        assert_eq!("", base64url_encode(b""));
+        assert_eq!("Zg", base64url_encode(b"f"));
+        assert_eq!("Zm8", base64url_encode(b"fo"));
+        assert_eq!("Zm9v", base64url_encode(b"foo"));
+        assert_eq!("-_8", base64url_encode(&[0xfb, 0xff]));
+        for len in 0..10 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 250) as u8).collect();
+            assert_eq!(
+                Some(bytes.clone()),
+                base64url_decode(&base64url_encode(&bytes))
+            );
+        }
+
+        let settings = SettingsFrame::from_settings(vec![
+            HttpSetting::MaxConcurrentStreams(100),
+            HttpSetting::InitialWindowSize(65535),
+        ]);
+        assert_eq!("AAMAAABkAAQAAP__", encode_settings(&settings));
+    }
+
+    #[test]
+    fn upgrade_request_is_upgradable() {
+        let settings = SettingsFrame::from_settings(vec![HttpSetting::EnablePush(false)]);
+        let request = upgrade_request("example.com:80", &settings);
+        let head = Http1Head::parse(&request).unwrap();
+        let upgrade = H2cUpgrade::from_request(&head).unwrap();
+        assert_eq!(settings.settings, upgrade.settings.settings);
+        assert_eq!("OPTIONS", upgrade.headers.method());
+        assert_eq!("*", upgrade.headers.path());
+    }
+
+    #[test]
+    fn from_request() {
+        let head = Http1Head::parse(
+            b"GET /foo?a=b HTTP/1.1\r\n\
+            Host: example.com\r\n\
+            Connection: Upgrade, HTTP2-Settings, X-Hop\r\n\
+            Upgrade: h2c\r\n\
+            HTTP2-Settings: AAMAAABkAAQAAP__\r\n\
+            X-Hop: 1\r\n\
+            Accept: */*\r\n\r\n",
+        )
+        .unwrap();
+        let upgrade = H2cUpgrade::from_request(&head).unwrap();
+        assert_eq!(2, upgrade.settings.settings.len());
+        let expected = Headers::from_vec(vec![
+            Header::new(":method", "GET"),
+            Header::new(":scheme", "http"),
+            Header::new(":path", "/foo?a=b"),
+            Header::new(":authority", "example.com"),
+            Header::new("accept", "*/*"),
+        ]);
+        assert_eq!(expected, upgrade.headers);
+    }
+
+    #[test]
+    fn from_request_not_upgraded() {
+        for head in &[
+            &b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"[..],
+            b"GET / HTTP/1.1\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\n\r\n",
+            b"GET / HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: h2c\r\nHTTP2-Settings: \r\n\r\n",
+            b"POST / HTTP/1.1\r\nConnection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\n\
+            HTTP2-Settings: \r\nContent-Length: 3\r\n\r\n",
+        ] {
+            let head = Http1Head::parse(head).unwrap();
+            assert!(H2cUpgrade::from_request(&head).is_none(), "{:?}", head);
+        }
+    }
+}
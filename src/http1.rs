@@ -0,0 +1,182 @@
+//! Minimal HTTP/1.1 message head parsing, enough for `Upgrade` handshakes.
+
+use std::io;
+use std::str;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
+use crate::error;
+use crate::result;
+
+/// Limit of request or response head size.
+pub(crate) const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// Hop-by-hop headers, not forwarded to HTTP/2 (RFC 7540 section 8.1.2.2).
+pub(crate) const CONNECTION_SPECIFIC_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Start line and header fields of an HTTP/1.1 message.
+#[derive(Debug, Clone)]
+pub(crate) struct Http1Head {
+    /// Request line or status line
+    pub first_line: String,
+    /// Header fields in order, names as sent
+    pub headers: Vec<(String, String)>,
+}
+
+fn invalid_head(message: &str) -> error::Error {
+    error::Error::InvalidHttp1Head(message.to_owned())
+}
+
+impl Http1Head {
+    /// Parse head, terminating empty line is optional.
+    pub fn parse(buf: &[u8]) -> result::Result<Http1Head> {
+        let buf = str::from_utf8(buf).map_err(|_| invalid_head("not UTF-8"))?;
+
+        let mut lines = buf
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
+
+        let first_line = match lines.next() {
+            Some(line) if !line.is_empty() => line.to_owned(),
+            _ => return Err(invalid_head("empty start line")),
+        };
+
+        let mut headers = Vec::new();
+        for line in lines {
+            if line.is_empty() {
+                break;
+            }
+            let colon = line.find(':').ok_or_else(|| invalid_head("no colon"))?;
+            let name = &line[..colon];
+            if name.is_empty() || name.bytes().any(|b| b <= b' ' || b >= 0x7f) {
+                return Err(invalid_head("invalid header name"));
+            }
+            headers.push((name.to_owned(), line[colon + 1..].trim().to_owned()));
+        }
+
+        Ok(Http1Head {
+            first_line,
+            headers,
+        })
+    }
+
+    /// Method, target and version of a request.
+    pub fn request_line(&self) -> Option<(&str, &str, &str)> {
+        let mut parts = self.first_line.split(' ');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version), None)
+                if !method.is_empty() && !target.is_empty() =>
+            {
+                Some((method, target, version))
+            }
+            _ => None,
+        }
+    }
+
+    /// Status code of a response.
+    pub fn status(&self) -> Option<u32> {
+        let mut parts = self.first_line.splitn(3, ' ');
+        match (parts.next(), parts.next()) {
+            (Some(version), Some(status)) if version.starts_with("HTTP/") && status.len() == 3 => {
+                status.parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// First header value with given name, names are case-insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Comma-separated header (e. g. `Connection`) contains given token
+    /// in any of its occurrences.
+    pub fn header_has_token(&self, name: &str, token: &str) -> bool {
+        self.headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .flat_map(|(_, v)| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+
+    /// Header is hop-by-hop or listed in `Connection` header.
+    pub fn is_connection_specific(&self, name: &str) -> bool {
+        CONNECTION_SPECIFIC_HEADERS
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name))
+            || self.header_has_token("connection", name)
+    }
+}
+
+/// Read until the empty line terminating the head, appending to `buf`.
+///
+/// Reads byte-by-byte, so bytes after the head are not consumed.
+pub(crate) async fn read_head<I>(conn: &mut I, buf: &mut Vec<u8>) -> result::Result<()>
+where
+    I: AsyncRead + Unpin,
+{
+    while !buf.ends_with(b"\r\n\r\n") && !buf.ends_with(b"\n\n") {
+        if buf.len() >= MAX_HEAD_LEN {
+            return Err(invalid_head("head is too large"));
+        }
+        let mut byte = [0];
+        if conn.read(&mut byte).await? == 0 {
+            let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected EOF");
+            return Err(error::Error::from(io_error));
+        }
+        buf.push(byte[0]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_request() {
+        let head = Http1Head::parse(
+            b"GET /foo HTTP/1.1\r\nHost: example.com\r\nConnection: Upgrade, HTTP2-Settings\r\n\
+            Connection: close\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(Some(("GET", "/foo", "HTTP/1.1")), head.request_line());
+        assert_eq!(Some("example.com"), head.header("host"));
+        assert_eq!(None, head.header("upgrade"));
+        assert!(head.header_has_token("connection", "http2-settings"));
+        assert!(head.header_has_token("CONNECTION", "close"));
+        assert!(!head.header_has_token("connection", "keep-alive"));
+        assert!(head.is_connection_specific("HTTP2-Settings"));
+        assert!(head.is_connection_specific("Transfer-Encoding"));
+        assert!(!head.is_connection_specific("host"));
+    }
+
+    #[test]
+    fn parse_response() {
+        let head =
+            Http1Head::parse(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: h2c\r\n\r\n").unwrap();
+        assert_eq!(Some(101), head.status());
+        assert_eq!(None, head.request_line());
+        let head = Http1Head::parse(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(None, head.status());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Http1Head::parse(b"\r\n\r\n").is_err());
+        assert!(Http1Head::parse(b"GET / HTTP/1.1\r\nno colon\r\n\r\n").is_err());
+        assert!(Http1Head::parse(b"GET / HTTP/1.1\r\n: value\r\n\r\n").is_err());
+        let head = Http1Head::parse(b"GET  HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(None, head.request_line());
+    }
+}
@@ -0,0 +1,232 @@
+//! Conversions between `Headers` and `http` crate messages,
+//! enabled with `http` feature.
+//!
+//! Pseudo-headers are mapped to request method and URI or response status.
+//! `:authority` is mapped to both URI authority and `host` header.
+//! Connection-specific headers of `http` messages are dropped.
+
+use std::convert::TryFrom;
+
+use bytes::Bytes;
+
+use crate::error;
+use crate::http1::CONNECTION_SPECIFIC_HEADERS;
+use crate::result;
+use crate::solicit::header::Header;
+use crate::solicit::header::Headers;
+use crate::SimpleHttpMessage;
+
+fn invalid_message(message: impl ToString) -> error::Error {
+    error::Error::InvalidHttpMessage(message.to_string())
+}
+
+/// Add regular headers from `map`, skipping headers not allowed in HTTP/2.
+fn add_header_map(headers: &mut Headers, map: &http::HeaderMap) {
+    for (name, value) in map {
+        if name == http::header::HOST || CONNECTION_SPECIFIC_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        headers.add(name.as_str(), Bytes::copy_from_slice(value.as_bytes()));
+    }
+}
+
+/// Regular headers of `headers` as `http` header map.
+fn header_map(headers: &Headers) -> result::Result<http::HeaderMap> {
+    let mut map = http::HeaderMap::new();
+    for header in headers.iter().filter(|h| !h.is_preudo_header()) {
+        let name = http::header::HeaderName::from_bytes(header.name().as_bytes())
+            .map_err(invalid_message)?;
+        let value =
+            http::header::HeaderValue::from_bytes(header.value()).map_err(invalid_message)?;
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+/// Request headers, `:scheme` is added only if URI is absolute,
+/// `:authority` is taken from `host` header if URI has no authority.
+impl From<&http::request::Parts> for Headers {
+    fn from(parts: &http::request::Parts) -> Headers {
+        let connect = parts.method == http::Method::CONNECT;
+        let mut headers = Headers::new();
+        headers.add_header(Header::new(":method", parts.method.as_str()));
+        if let (Some(scheme), false) = (parts.uri.scheme_str(), connect) {
+            headers.add_header(Header::new(":scheme", scheme));
+        }
+        let authority = match parts.uri.authority() {
+            Some(authority) => Some(authority.as_str().as_bytes()),
+            None => parts.headers.get(http::header::HOST).map(|h| h.as_bytes()),
+        };
+        if let Some(authority) = authority {
+            headers.add_header(Header::new(":authority", authority));
+        }
+        if !connect {
+            let path = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+            headers.add_header(Header::new(":path", path));
+        }
+        add_header_map(&mut headers, &parts.headers);
+        headers
+    }
+}
+
+impl From<&http::response::Parts> for Headers {
+    fn from(parts: &http::response::Parts) -> Headers {
+        let mut headers = Headers::new_status(parts.status.as_u16() as u32);
+        add_header_map(&mut headers, &parts.headers);
+        headers
+    }
+}
+
+/// Request method, URI and headers, URI is absolute
+/// if both `:scheme` and `:authority` are present.
+impl TryFrom<&Headers> for http::request::Parts {
+    type Error = error::Error;
+
+    fn try_from(headers: &Headers) -> result::Result<http::request::Parts> {
+        let authority = headers.get_opt(":authority");
+        let uri = match (
+            headers.get_opt(":scheme"),
+            authority,
+            headers.get_opt(":path"),
+        ) {
+            (Some(scheme), Some(authority), Some(path)) => http::Uri::builder()
+                .scheme(scheme)
+                .authority(authority)
+                .path_and_query(path)
+                .build(),
+            (_, _, Some(path)) => http::Uri::builder().path_and_query(path).build(),
+            (_, Some(authority), None) => http::Uri::builder().authority(authority).build(),
+            (_, None, None) => return Err(invalid_message("no :path or :authority")),
+        }
+        .map_err(invalid_message)?;
+        let method = headers
+            .get_opt(":method")
+            .ok_or_else(|| invalid_message("no :method"))?;
+
+        let (mut parts, ()) = http::Request::new(()).into_parts();
+        parts.method = http::Method::from_bytes(method.as_bytes()).map_err(invalid_message)?;
+        parts.uri = uri;
+        parts.headers = header_map(headers)?;
+        if let Some(authority) = authority {
+            let host = http::header::HeaderValue::from_str(authority).map_err(invalid_message)?;
+            parts.headers.insert(http::header::HOST, host);
+        }
+        Ok(parts)
+    }
+}
+
+impl TryFrom<&Headers> for http::response::Parts {
+    type Error = error::Error;
+
+    fn try_from(headers: &Headers) -> result::Result<http::response::Parts> {
+        let status = headers
+            .status_opt()
+            .ok_or_else(|| invalid_message("no :status"))?;
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        parts.status = http::StatusCode::from_u16(status as u16).map_err(invalid_message)?;
+        parts.headers = header_map(headers)?;
+        Ok(parts)
+    }
+}
+
+impl From<http::Request<Bytes>> for SimpleHttpMessage {
+    fn from(request: http::Request<Bytes>) -> SimpleHttpMessage {
+        let (parts, body) = request.into_parts();
+        SimpleHttpMessage {
+            headers: Headers::from(&parts),
+            body: body.into(),
+        }
+    }
+}
+
+impl From<http::Response<Bytes>> for SimpleHttpMessage {
+    fn from(response: http::Response<Bytes>) -> SimpleHttpMessage {
+        let (parts, body) = response.into_parts();
+        SimpleHttpMessage {
+            headers: Headers::from(&parts),
+            body: body.into(),
+        }
+    }
+}
+
+impl TryFrom<SimpleHttpMessage> for http::Request<Bytes> {
+    type Error = error::Error;
+
+    fn try_from(message: SimpleHttpMessage) -> result::Result<http::Request<Bytes>> {
+        let parts = http::request::Parts::try_from(&message.headers)?;
+        Ok(http::Request::from_parts(parts, message.body.into_bytes()))
+    }
+}
+
+impl TryFrom<SimpleHttpMessage> for http::Response<Bytes> {
+    type Error = error::Error;
+
+    fn try_from(message: SimpleHttpMessage) -> result::Result<http::Response<Bytes>> {
+        let parts = http::response::Parts::try_from(&message.headers)?;
+        Ok(http::Response::from_parts(parts, message.body.into_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request() {
+        let request = http::Request::put("/a?b")
+            .header("host", "example.com")
+            .header("connection", "close")
+            .header("x-a", "1")
+            .header("x-a", "2")
+            .body(Bytes::from_static(b"body"))
+            .unwrap();
+        let message = SimpleHttpMessage::from(request);
+        let headers = &message.headers;
+        assert_eq!("PUT", headers.method());
+        assert_eq!(None, headers.get_opt(":scheme"));
+        assert_eq!("example.com", headers.get(":authority"));
+        assert_eq!("/a?b", headers.path());
+        assert_eq!(None, headers.get_opt("host"));
+        assert_eq!(None, headers.get_opt("connection"));
+        assert_eq!(2, headers.iter().filter(|h| h.name() == "x-a").count());
+        assert_eq!(&b"body"[..], message.body.get_bytes());
+
+        let mut message = message;
+        message.headers.add(":scheme", "https");
+        let request = http::Request::<Bytes>::try_from(message).unwrap();
+        assert_eq!(http::Method::PUT, request.method());
+        assert_eq!("https://example.com/a?b", request.uri().to_string());
+        assert_eq!("example.com", request.headers()["host"]);
+        assert_eq!(2, request.headers().get_all("x-a").iter().count());
+        assert_eq!(&b"body"[..], &request.body()[..]);
+
+        let (parts, ()) = http::Request::get("http://other.com")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let headers = Headers::from(&parts);
+        assert_eq!("http", headers.get(":scheme"));
+        assert_eq!("other.com", headers.get(":authority"));
+        assert_eq!("/", headers.path());
+
+        assert!(http::request::Parts::try_from(&Headers::new_status(200)).is_err());
+    }
+
+    #[test]
+    fn response() {
+        let response = http::Response::builder()
+            .status(404)
+            .header("content-type", "text/plain")
+            .body(Bytes::new())
+            .unwrap();
+        let message = SimpleHttpMessage::from(response);
+        assert_eq!(404, message.headers.status());
+        assert_eq!(Some("text/plain"), message.headers.content_type());
+
+        let response = http::Response::<Bytes>::try_from(message).unwrap();
+        assert_eq!(http::StatusCode::NOT_FOUND, response.status());
+        assert_eq!("text/plain", response.headers()["content-type"]);
+
+        assert!(http::response::Parts::try_from(&Headers::new()).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Length-prefixed message framing of request and response bodies,
+//! used by RPC protocols like gRPC.
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use futures::stream::Stream;
+use futures::stream::StreamExt;
+
+use crate::error;
+use crate::result;
+use crate::solicit_async::HttpFutureStreamSend;
+
+/// Width of big-endian message length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixWidth {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl PrefixWidth {
+    fn len(self) -> usize {
+        match self {
+            PrefixWidth::U8 => 1,
+            PrefixWidth::U16 => 2,
+            PrefixWidth::U32 => 4,
+            PrefixWidth::U64 => 8,
+        }
+    }
+
+    fn max(self) -> u64 {
+        match self {
+            PrefixWidth::U64 => u64::MAX,
+            w => (1 << (w.len() * 8)) - 1,
+        }
+    }
+}
+
+/// Message compression for codecs with compression flag.
+pub trait MessageCompressor: Send + Sync + 'static {
+    fn compress(&self, message: &[u8]) -> result::Result<Bytes>;
+    fn decompress(&self, message: &[u8]) -> result::Result<Bytes>;
+}
+
+/// Splits body into length-prefixed messages and frames messages for sending.
+#[derive(Clone)]
+pub struct LengthPrefixedCodec {
+    pub width: PrefixWidth,
+    /// Length is preceded by a byte which is `1` for compressed messages (gRPC).
+    pub compression_flag: bool,
+    /// Larger incoming messages are rejected.
+    pub max_message_size: usize,
+    /// Required to receive compressed messages.
+    pub compressor: Option<Arc<dyn MessageCompressor>>,
+    /// Compress outgoing messages with `compressor`.
+    pub compress: bool,
+}
+
+impl fmt::Debug for LengthPrefixedCodec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LengthPrefixedCodec")
+            .field("width", &self.width)
+            .field("compression_flag", &self.compression_flag)
+            .field("max_message_size", &self.max_message_size)
+            .field("compress", &self.compress)
+            .finish()
+    }
+}
+
+impl LengthPrefixedCodec {
+    pub fn new(width: PrefixWidth) -> LengthPrefixedCodec {
+        LengthPrefixedCodec {
+            width,
+            compression_flag: false,
+            max_message_size: 4 << 20,
+            compressor: None,
+            compress: false,
+        }
+    }
+
+    /// gRPC framing: compression flag and 32-bit length.
+    pub fn grpc() -> LengthPrefixedCodec {
+        LengthPrefixedCodec {
+            compression_flag: true,
+            ..LengthPrefixedCodec::new(PrefixWidth::U32)
+        }
+    }
+
+    fn header_len(&self) -> usize {
+        self.compression_flag as usize + self.width.len()
+    }
+
+    /// Frame a message.
+    pub fn encode(&self, message: &[u8]) -> result::Result<Bytes> {
+        let (compressed, message) = match (&self.compressor, self.compress) {
+            (Some(compressor), true) if self.compression_flag => {
+                (true, compressor.compress(message)?)
+            }
+            _ => (false, Bytes::copy_from_slice(message)),
+        };
+        if message.len() as u64 > self.width.max() {
+            return Err(error::Error::MessageTooLarge(
+                message.len() as u64,
+                self.width.max() as usize,
+            ));
+        }
+
+        let mut buf = BytesMut::with_capacity(self.header_len() + message.len());
+        if self.compression_flag {
+            buf.put_u8(compressed as u8);
+        }
+        let len = (message.len() as u64).to_be_bytes();
+        buf.put_slice(&len[len.len() - self.width.len()..]);
+        buf.put_slice(&message);
+        Ok(buf.freeze())
+    }
+
+    /// Frame a stream of messages, e. g. for `ClientRequest::pull_bytes_from_stream`.
+    pub fn encode_stream<S>(&self, messages: S) -> HttpFutureStreamSend<Bytes>
+    where
+        S: Stream<Item = result::Result<Bytes>> + Send + 'static,
+    {
+        let codec = self.clone();
+        Box::pin(messages.map(move |m| m.and_then(|m| codec.encode(&m))))
+    }
+
+    /// Split a body stream into messages.
+    pub fn decode_stream<S>(&self, data: S) -> LengthPrefixedStream<S>
+    where
+        S: Stream<Item = result::Result<Bytes>> + Unpin,
+    {
+        LengthPrefixedStream {
+            codec: self.clone(),
+            data,
+            buf: BytesMut::new(),
+            done: false,
+        }
+    }
+
+    /// Decode a message from the beginning of the buffer if it is complete.
+    pub(crate) fn decode(&self, buf: &mut BytesMut) -> result::Result<Option<Bytes>> {
+        let header_len = self.header_len();
+        if buf.len() < header_len {
+            return Ok(None);
+        }
+        let compressed = match self.compression_flag {
+            true => match buf[0] {
+                0 => false,
+                1 => true,
+                flag => return Err(error::Error::InvalidMessageFlag(flag)),
+            },
+            false => false,
+        };
+        let len = buf[self.compression_flag as usize..header_len]
+            .iter()
+            .fold(0u64, |len, &b| (len << 8) | b as u64);
+        if len > self.max_message_size as u64 {
+            return Err(error::Error::MessageTooLarge(len, self.max_message_size));
+        }
+        let len = len as usize;
+        if buf.len() < header_len + len {
+            buf.reserve(header_len + len - buf.len());
+            return Ok(None);
+        }
+        buf.advance(header_len);
+        let message = buf.split_to(len).freeze();
+        if !compressed {
+            return Ok(Some(message));
+        }
+        match &self.compressor {
+            Some(compressor) => compressor.decompress(&message).map(Some),
+            None => Err(error::Error::CompressedMessageNotSupported),
+        }
+    }
+}
+
+/// Stream of whole messages decoded from a body stream.
+pub struct LengthPrefixedStream<S> {
+    codec: LengthPrefixedCodec,
+    data: S,
+    buf: BytesMut,
+    done: bool,
+}
+
+impl<S> Stream for LengthPrefixedStream<S>
+where
+    S: Stream<Item = result::Result<Bytes>> + Unpin,
+{
+    type Item = result::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let me = &mut *self;
+        loop {
+            if me.done {
+                return Poll::Ready(None);
+            }
+            match me.codec.decode(&mut me.buf) {
+                Ok(Some(message)) => return Poll::Ready(Some(Ok(message))),
+                Ok(None) => {}
+                Err(e) => {
+                    me.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            match Pin::new(&mut me.data).poll_next(cx) {
+                Poll::Ready(Some(Ok(data))) => me.buf.extend_from_slice(&data),
+                Poll::Ready(Some(Err(e))) => {
+                    me.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => {
+                    me.done = true;
+                    if !me.buf.is_empty() {
+                        return Poll::Ready(Some(Err(error::Error::TruncatedMessage)));
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor;
+    use futures::stream;
+    use futures::stream::TryStreamExt;
+
+    fn decode_chunks(codec: &LengthPrefixedCodec, data: &[u8], chunk: usize) -> Vec<Bytes> {
+        let chunks: Vec<result::Result<Bytes>> = data
+            .chunks(chunk)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        executor::block_on(codec.decode_stream(stream::iter(chunks)).try_collect()).unwrap()
+    }
+
+    #[test]
+    fn grpc() {
+        let codec = LengthPrefixedCodec::grpc();
+        assert_eq!(&b"\0\0\0\0\x03abc"[..], &codec.encode(b"abc").unwrap()[..]);
+
+        let mut data = Vec::new();
+        for message in &[&b"hello"[..], b"", b"world!"] {
+            data.extend_from_slice(&codec.encode(message).unwrap());
+        }
+        for &chunk in &[1, 3, 100] {
+            assert_eq!(
+                vec![
+                    Bytes::from_static(b"hello"),
+                    Bytes::new(),
+                    Bytes::from_static(b"world!")
+                ],
+                decode_chunks(&codec, &data, chunk)
+            );
+        }
+    }
+
+    #[test]
+    fn width() {
+        let codec = LengthPrefixedCodec::new(PrefixWidth::U16);
+        assert_eq!(&b"\0\x02ab"[..], &codec.encode(b"ab").unwrap()[..]);
+
+        let codec = LengthPrefixedCodec::new(PrefixWidth::U8);
+        assert!(codec.encode(&[0; 255]).is_ok());
+        match codec.encode(&[0; 256]) {
+            Err(error::Error::MessageTooLarge(256, 255)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut codec = LengthPrefixedCodec::grpc();
+        let decode = |codec: &LengthPrefixedCodec, data: &'static [u8]| {
+            executor::block_on(
+                codec
+                    .decode_stream(stream::iter(vec![Ok(Bytes::from_static(data))]))
+                    .try_collect::<Vec<_>>(),
+            )
+        };
+        match decode(&codec, b"\0\0\0\0\x05abc") {
+            Err(error::Error::TruncatedMessage) => {}
+            r => panic!("{:?}", r),
+        }
+        match decode(&codec, b"\x01\0\0\0\x01a") {
+            Err(error::Error::CompressedMessageNotSupported) => {}
+            r => panic!("{:?}", r),
+        }
+        codec.max_message_size = 2;
+        match decode(&codec, b"\0\0\0\0\x03abc") {
+            Err(error::Error::MessageTooLarge(3, 2)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn compression() {
+        struct Reverse;
+
+        impl MessageCompressor for Reverse {
+            fn compress(&self, message: &[u8]) -> result::Result<Bytes> {
+                Ok(message.iter().rev().cloned().collect::<Vec<_>>().into())
+            }
+
+            fn decompress(&self, message: &[u8]) -> result::Result<Bytes> {
+                self.compress(message)
+            }
+        }
+
+        let mut codec = LengthPrefixedCodec::grpc();
+        codec.compressor = Some(Arc::new(Reverse));
+        codec.compress = true;
+        let data = codec.encode(b"abc").unwrap();
+        assert_eq!(&b"\x01\0\0\0\x03cba"[..], &data[..]);
+        assert_eq!(
+            vec![Bytes::from_static(b"abc")],
+            decode_chunks(&codec, &data, 2)
+        );
+    }
+}
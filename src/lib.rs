@@ -13,7 +13,15 @@
 extern crate log;
 extern crate log_ndc;
 
+/// rustls TLS backend, enabled with `tls-rustls` feature.
+#[cfg(feature = "tls-rustls")]
+pub extern crate rustls;
 extern crate tls_api;
+/// Platform TLS backend, enabled with `tls-native` feature.
+#[cfg(feature = "tls-native")]
+pub extern crate tls_api_native_tls;
+#[cfg(feature = "tls-rustls")]
+pub extern crate tls_api_rustls;
 extern crate tls_api_stub;
 
 extern crate bytes;
@@ -30,6 +38,8 @@ mod client;
 mod codec;
 mod server;
 mod socket;
+mod socket_io;
+mod socket_named_pipe;
 mod socket_tcp;
 
 mod socket_unix;
@@ -46,64 +56,169 @@ mod message;
 
 mod futures_misc;
 
+mod h2c;
+mod http1;
+#[cfg(feature = "http")]
+mod http_compat;
+
 mod headers_place;
 mod req_resp;
 
 mod assert_types;
 
-mod hpack;
 mod solicit_async;
 mod solicit_misc;
 
+mod date;
 mod display_comma_separated;
 mod misc;
 
 mod resp;
 
 mod exec;
+mod length_prefixed;
 
+mod context;
+pub mod grpc;
 mod log_ndc_future;
+pub mod mem;
+mod proxy;
+#[cfg(feature = "static-files")]
+pub mod static_files;
+pub mod tls;
+#[cfg(feature = "tower")]
+mod tower;
+mod tunnel;
+mod websocket;
 
 pub(crate) mod bytes_ext;
 
+/// HTTP/2 wire layer: frame parsing/serialization and HPACK,
+/// see the `httpbis-codec` crate.
+pub use httpbis_codec as wire;
+
 pub use crate::socket::AnySocketAddr;
 
 pub use crate::solicit::error_code::ErrorCode;
+pub use crate::solicit::header::method::Method;
 pub use crate::solicit::header::name::HeaderName;
 pub use crate::solicit::header::name::PseudoHeaderName;
 pub use crate::solicit::header::value::HeaderValue;
 pub use crate::solicit::header::Header;
+pub use crate::solicit::header::HeaderError;
 pub use crate::solicit::header::Headers;
+pub use crate::solicit::priority::Priority;
+pub use crate::solicit::priority::MAX_URGENCY;
 pub use crate::solicit::stream_id::StreamId;
 pub use crate::solicit::HttpScheme;
 
+pub use crate::client::alt_svc::parse_alt_svc;
+pub use crate::client::alt_svc::AltSvc;
+pub use crate::client::alt_svc::AltSvcEvent;
+pub use crate::client::alt_svc::AltSvcHeader;
+pub use crate::client::alt_svc::AltSvcObserver;
+#[cfg(feature = "cache")]
+pub use crate::client::cache::CacheStorage;
+#[cfg(feature = "cache")]
+pub use crate::client::cache::CachedResponse;
+#[cfg(feature = "cache")]
+pub use crate::client::cache::HttpCache;
+#[cfg(feature = "cache")]
+pub use crate::client::cache::MemoryCacheStorage;
+pub use crate::client::coalesce::ConnCoverage;
+pub use crate::client::conf::ClientAlpn;
 pub use crate::client::conf::ClientConf;
+#[cfg(feature = "cookies")]
+pub use crate::client::cookie_jar::Cookie;
+#[cfg(feature = "cookies")]
+pub use crate::client::cookie_jar::CookieJar;
+pub use crate::client::headers_hook::RequestHeadersHook;
+pub use crate::client::http_proxy::basic_proxy_authorization;
+pub use crate::client::interceptor::ClientInterceptor;
+pub use crate::client::mirror::MirrorFilter;
+pub use crate::client::mirror::RequestMirror;
+pub use crate::client::multi::MultiClient;
+pub use crate::client::push::PushHandler;
 pub use crate::client::req::ClientRequest;
+pub use crate::client::request_builder::RequestBuilder;
+pub use crate::client::resolver::Resolver;
+pub use crate::client::resolver::SystemResolver;
+pub use crate::client::retry::is_unprocessed_error;
+pub use crate::client::retry::DefaultRetryPolicy;
+pub use crate::client::retry::RetryBudget;
+pub use crate::client::retry::RetryPolicy;
 pub use crate::client::tls::ClientTlsOption;
 pub use crate::client::Client;
 pub use crate::client::ClientBuilder;
 pub use crate::client::ClientInterface;
+pub use crate::common::conf::CommonConf;
+pub use crate::common::conn_id::ConnId;
+pub use crate::common::metrics::Metric;
+pub use crate::common::metrics::MetricLabel;
+pub use crate::common::metrics::Metrics;
+pub use crate::common::misbehavior::MisbehaviorLimits;
+pub use crate::common::padding::Padding;
 pub use crate::common::sender::SendError;
 pub use crate::common::sender::SenderState;
+pub use crate::common::traffic::ConnTraffic;
+pub use crate::common::traffic::StreamTraffic;
+pub use crate::common::traffic::TrafficStats;
 pub use crate::common::window_size::StreamDead;
-
+pub use crate::context::BaggageCodec;
+pub use crate::context::ContextCodec;
+pub use crate::context::ContextPropagation;
+pub use crate::context::RequestContext;
+pub use crate::context::TimeoutCodec;
+pub use crate::context::TraceContext;
+pub use crate::context::TraceparentCodec;
+
+pub use crate::proxy::ConnectAuthenticator;
+pub use crate::proxy::ConnectGateway;
+pub use crate::proxy::ProxyHandler;
+pub use crate::proxy::ProxyHeadersHook;
+pub use crate::tunnel::is_connect_request;
+pub use crate::tunnel::Tunnel;
+pub use crate::websocket::is_websocket_request;
+pub use crate::websocket::WebSocketStream;
+
+pub use crate::server::access_log::AccessLog;
+pub use crate::server::access_log::AccessLogEntry;
+pub use crate::server::admission::Admission;
+pub use crate::server::admission::AdmissionControl;
+pub use crate::server::admission::DefaultAdmissionControl;
+pub use crate::server::admission::ServerLoad;
 pub use crate::server::conf::ServerAlpn;
 pub use crate::server::conf::ServerConf;
 pub use crate::server::handler::ServerHandler;
 pub use crate::server::handler::ServerHandlerContext;
 pub use crate::server::handler_paths::ServerHandlerPaths;
 pub use crate::server::increase_in_window::ServerIncreaseInWindow;
+pub use crate::server::middleware::Next;
+pub use crate::server::middleware::ServerMiddleware;
+pub use crate::server::range::ByteRange;
+pub use crate::server::range::RangeNotSatisfiable;
 pub use crate::server::req::ServerRequest;
 pub use crate::server::resp::ServerResponse;
+pub use crate::server::router::PathParams;
+pub use crate::server::router::Router;
+pub use crate::server::shutdown::ShutdownHandle;
 pub use crate::server::stream_handler::ServerRequestStreamHandler;
 pub use crate::server::tls::ServerTlsOption;
 pub use crate::server::Server;
 pub use crate::server::ServerBuilder;
+#[cfg(feature = "tower")]
+pub use crate::tower::TowerHandler;
 
 pub use crate::data_or_trailers::DataOrTrailers;
 pub use crate::data_or_trailers::HttpStreamAfterHeaders;
 pub use crate::resp::Response;
+pub use crate::resp::ResponsePart;
+pub use crate::solicit::end_stream::EndStream;
 
+pub use crate::length_prefixed::LengthPrefixedCodec;
+pub use crate::length_prefixed::LengthPrefixedStream;
+pub use crate::length_prefixed::MessageCompressor;
+pub use crate::length_prefixed::PrefixWidth;
 pub use crate::message::SimpleHttpMessage;
 
 pub use crate::error::Error;
@@ -128,6 +243,6 @@ pub mod for_test {
         pub use crate::solicit::*;
     }
     pub mod hpack {
-        pub use crate::hpack::*;
+        pub use httpbis_codec::hpack::*;
     }
 }
@@ -0,0 +1,340 @@
+//! In-memory transport for testing clients and servers without sockets.
+//!
+//! `connected_pair` or `ClientBuilder::set_mem_server` connect a client
+//! to a server handler. A server alone can be tested with a `duplex` end
+//! passed to `Server::serve_connection`.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+use bytes::Buf;
+use bytes::BytesMut;
+use futures::future;
+use futures::future::FutureExt;
+use futures::Future;
+use tls_api::TlsConnector;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::runtime::Handle;
+
+use crate::result;
+use crate::server::conn::ServerConn;
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+use crate::Client;
+use crate::ClientBuilder;
+use crate::ClientConf;
+use crate::ServerConf;
+use crate::ServerHandler;
+use crate::ServerTlsOption;
+
+/// Bytes written but not yet read by the peer.
+const DEFAULT_MAX_BUF_SIZE: usize = 64 * 1024;
+
+/// One direction of a duplex stream.
+#[derive(Default)]
+struct Pipe {
+    buf: BytesMut,
+    /// Writer is shut down or dropped.
+    write_closed: bool,
+    /// Reader is dropped.
+    read_closed: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn close_write(&mut self) {
+        self.write_closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn close_read(&mut self) {
+        self.read_closed = true;
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// One end of an in-memory duplex stream created by `duplex`.
+///
+/// Dropping an end closes both directions.
+pub struct MemStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+    max_buf_size: usize,
+}
+
+/// Create a pair of connected in-memory streams.
+///
+/// Writes wait when `max_buf_size` bytes are not yet read by the peer.
+pub fn duplex(max_buf_size: usize) -> (MemStream, MemStream) {
+    assert!(max_buf_size > 0);
+    let a: Arc<Mutex<Pipe>> = Default::default();
+    let b: Arc<Mutex<Pipe>> = Default::default();
+    (
+        MemStream {
+            read: a.clone(),
+            write: b.clone(),
+            max_buf_size,
+        },
+        MemStream {
+            read: b,
+            write: a,
+            max_buf_size,
+        },
+    )
+}
+
+impl AsyncRead for MemStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.read.lock().unwrap();
+        if pipe.buf.is_empty() {
+            if pipe.write_closed || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let n = pipe.buf.len().min(buf.len());
+        buf[..n].copy_from_slice(&pipe.buf[..n]);
+        pipe.buf.advance(n);
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for MemStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.write.lock().unwrap();
+        if pipe.read_closed || pipe.write_closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        let n = (self.max_buf_size - pipe.buf.len().min(self.max_buf_size)).min(buf.len());
+        if n == 0 && !buf.is_empty() {
+            pipe.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        pipe.buf.extend_from_slice(&buf[..n]);
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write.lock().unwrap().close_write();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MemStream {
+    fn drop(&mut self) {
+        self.write.lock().unwrap().close_write();
+        self.read.lock().unwrap().close_read();
+    }
+}
+
+impl fmt::Debug for MemStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemStream").finish()
+    }
+}
+
+impl StreamItem for MemStream {
+    fn is_tcp(&self) -> bool {
+        false
+    }
+
+    fn set_nodelay(&self, _no_delay: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        Ok(AnySocketAddr::Mem)
+    }
+}
+
+/// Connects client to a server handler in the same process.
+///
+/// Each connection gets a new server connection,
+/// which is spawned on the client event loop.
+struct MemConnector<H: ServerHandler> {
+    conf: ServerConf,
+    handler: Arc<H>,
+}
+
+impl<H: ServerHandler> fmt::Display for MemConnector<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&AnySocketAddr::Mem, f)
+    }
+}
+
+impl<H: ServerHandler> ToClientStream for MemConnector<H> {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        let (client, server) = duplex(DEFAULT_MAX_BUF_SIZE);
+
+        let no_tls = ServerTlsOption::Plain;
+        let (conn, future) = ServerConn::new(
+            handle,
+            Box::pin(server),
+            AnySocketAddr::Mem,
+            no_tls,
+            self.conf.clone(),
+            self.handler.clone(),
+        );
+        handle.spawn(future.then(move |r| {
+            // connection is closed when handle is dropped
+            drop(conn);
+            if let Err(e) = r {
+                warn!("in-memory server connection end: {:?}", e);
+            }
+            future::ready(())
+        }));
+
+        Box::pin(future::ok(
+            Box::pin(client) as Pin<Box<dyn StreamItem + Send>>
+        ))
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        AnySocketAddr::Mem
+    }
+}
+
+impl<C: TlsConnector> ClientBuilder<C> {
+    /// Connect to `handler` over an in-memory transport instead of a socket.
+    ///
+    /// Each client connection gets a new server connection spawned
+    /// on the client event loop. Server does not use TLS.
+    pub fn set_mem_server<H: ServerHandler>(&mut self, server_conf: ServerConf, handler: H) {
+        self.connector = Some(Arc::new(MemConnector {
+            conf: server_conf,
+            handler: Arc::new(handler),
+        }));
+    }
+}
+
+/// Create a client connected to `handler` over an in-memory transport.
+///
+/// No sockets are opened. Server connections run on the client event loop
+/// and are closed together with the client.
+pub fn connected_pair<H: ServerHandler>(
+    client_conf: ClientConf,
+    server_conf: ServerConf,
+    handler: H,
+) -> result::Result<Client> {
+    let mut client = ClientBuilder::new_plain();
+    client.conf = client_conf;
+    client.set_mem_server(server_conf, handler);
+    client.build()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::runtime::Runtime;
+
+    use crate::ServerHandlerContext;
+    use crate::ServerRequest;
+    use crate::ServerResponse;
+
+    #[test]
+    fn duplex_read_write() {
+        let mut rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let (mut a, mut b) = duplex(4);
+            let write = tokio::spawn(async move {
+                a.write_all(b"hello world").await.unwrap();
+                a.shutdown().await.unwrap();
+                a
+            });
+            let mut read = Vec::new();
+            b.read_to_end(&mut read).await.unwrap();
+            assert_eq!(b"hello world", &read[..]);
+
+            let mut a = write.await.unwrap();
+            drop(b);
+            assert_eq!(
+                io::ErrorKind::BrokenPipe,
+                a.write(b"x").await.unwrap_err().kind()
+            );
+        });
+    }
+
+    struct Hello;
+
+    impl ServerHandler for Hello {
+        fn start_request(
+            &self,
+            _context: ServerHandlerContext,
+            req: ServerRequest,
+            mut resp: ServerResponse,
+        ) -> result::Result<()> {
+            let body = format!("hello {}", req.headers.path());
+            resp.send_found_200_plain_text(&body)?;
+            Ok(())
+        }
+    }
+
+    fn get_hello(client: &Client, path: &str) {
+        let r =
+            futures::executor::block_on(client.start_get(path, "mem").collect()).expect("response");
+        assert_eq!(200, r.headers.status());
+        assert_eq!(
+            format!("hello {}", path).as_bytes(),
+            &r.body.get_bytes()[..]
+        );
+    }
+
+    #[test]
+    fn pair() {
+        let client =
+            connected_pair(ClientConf::new(), ServerConf::new(), Hello).expect("connected_pair");
+        for path in &["/a", "/b"] {
+            get_hello(&client, path);
+        }
+    }
+
+    #[test]
+    fn builder() {
+        let mut client = ClientBuilder::new_plain();
+        client.set_mem_server(ServerConf::new(), Hello);
+        assert_eq!(
+            Some(AnySocketAddr::Mem),
+            client.connector.as_ref().map(|c| c.socket_addr())
+        );
+        let client = client.build().expect("build");
+        get_hello(&client, "/c");
+    }
+}
@@ -0,0 +1,325 @@
+//! Reverse and forward proxy building blocks.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::stream;
+use futures::stream::TryStreamExt;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use crate::error;
+use crate::result;
+use crate::AnySocketAddr;
+use crate::Client;
+use crate::DataOrTrailers;
+use crate::Headers;
+use crate::HttpStreamAfterHeaders;
+use crate::ServerHandler;
+use crate::ServerHandlerContext;
+use crate::ServerRequest;
+use crate::ServerResponse;
+use crate::SimpleHttpMessage;
+
+/// Hook to modify request or response headers.
+pub type ProxyHeadersHook = Arc<dyn Fn(&mut Headers) + Send + Sync>;
+
+/// Server handler which forwards requests to an upstream server.
+///
+/// Request and response bodies are streamed: data is read from one side
+/// only when the other side has window, so flow control is coupled.
+/// `RST_STREAM` received on either side is forwarded to the other side.
+/// Upstream failures before response headers are mapped to `502`,
+/// upstream timeout to `504`.
+pub struct ProxyHandler {
+    upstream: Arc<Client>,
+    /// Pseudonym of this proxy in `via` header, `via` is not added if `None`.
+    pub via: Option<String>,
+    /// Append client address to `x-forwarded-for` header.
+    pub x_forwarded_for: bool,
+    /// Max time to wait for upstream response headers.
+    pub upstream_timeout: Option<Duration>,
+    /// Called with request headers before sending them upstream.
+    pub request_hook: Option<ProxyHeadersHook>,
+    /// Called with response headers before sending them downstream.
+    pub response_hook: Option<ProxyHeadersHook>,
+}
+
+impl ProxyHandler {
+    /// Proxy requests to given client.
+    pub fn new(upstream: Arc<Client>) -> ProxyHandler {
+        ProxyHandler {
+            upstream,
+            via: None,
+            x_forwarded_for: true,
+            upstream_timeout: None,
+            request_hook: None,
+            response_hook: None,
+        }
+    }
+
+    fn rewrite_request(&self, headers: &mut Headers, peer_addr: &AnySocketAddr) {
+        if self.x_forwarded_for {
+            if let AnySocketAddr::Inet(addr) = peer_addr {
+                append_comma_separated(headers, "x-forwarded-for", &addr.ip().to_string());
+            }
+        }
+        if let Some(via) = &self.via {
+            append_comma_separated(headers, "via", &format!("2 {}", via));
+        }
+        if let Some(hook) = &self.request_hook {
+            hook(headers);
+        }
+    }
+
+    fn rewrite_response(
+        headers: &mut Headers,
+        via: &Option<String>,
+        hook: &Option<ProxyHeadersHook>,
+    ) {
+        if let Some(via) = via {
+            append_comma_separated(headers, "via", &format!("2 {}", via));
+        }
+        if let Some(hook) = hook {
+            hook(headers);
+        }
+    }
+}
+
+/// Append a value to a list header, merging all existing values into one.
+fn append_comma_separated(headers: &mut Headers, name: &'static str, value: &str) {
+    let mut values: Vec<String> = headers
+        .iter()
+        .filter(|h| h.name() == name)
+        .map(|h| String::from_utf8_lossy(h.value()).into_owned())
+        .collect();
+    values.push(value.to_owned());
+    headers.remove(name);
+    headers.add(name, values.join(", "));
+}
+
+async fn forward(
+    upstream: Arc<Client>,
+    headers: Headers,
+    end_stream: bool,
+    body: HttpStreamAfterHeaders,
+) -> result::Result<(Headers, HttpStreamAfterHeaders)> {
+    let (mut req, resp) = upstream
+        .start_request(headers, None, None, end_stream)
+        .await?;
+    if !end_stream {
+        req.pull_from_stream(body)?;
+    }
+    resp.0.await
+}
+
+impl ServerHandler for ProxyHandler {
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        mut resp: ServerResponse,
+    ) -> result::Result<()> {
+        let mut headers = req.headers.clone();
+        let end_stream = req.end_stream;
+        self.rewrite_request(&mut headers, context.peer_addr());
+        let body = req.make_stream();
+
+        let upstream = self.upstream.clone();
+        let upstream_timeout = self.upstream_timeout;
+        let via = self.via.clone();
+        let response_hook = self.response_hook.clone();
+
+        context.loop_remote().spawn(async move {
+            let forward = forward(upstream, headers, end_stream, body);
+            let r = match upstream_timeout {
+                Some(upstream_timeout) => {
+                    match tokio::time::timeout(upstream_timeout, forward).await {
+                        Ok(r) => r,
+                        Err(_) => Err(error::Error::UpstreamTimeout),
+                    }
+                }
+                None => forward.await,
+            };
+
+            let r = match r {
+                Ok((mut headers, body)) => {
+                    ProxyHandler::rewrite_response(&mut headers, &via, &response_hook);
+                    resp.send_headers(headers)
+                        .and_then(|()| resp.pull_from_stream(body))
+                }
+                Err(e) => {
+                    warn!("upstream request failed: {}", e);
+                    let status = match e {
+                        error::Error::UpstreamTimeout => 504,
+                        _ => 502,
+                    };
+                    let mut message = SimpleHttpMessage::new();
+                    message.headers = Headers::new_status(status);
+                    resp.send_message(message)
+                }
+            };
+            if let Err(e) = r {
+                debug!("failed to send proxied response: {:?}", e);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Hook to authorize `CONNECT` requests.
+///
+/// Receives request headers including `:authority` (the target),
+/// so it is also the place to restrict which hosts and ports
+/// can be reached through the gateway.
+pub type ConnectAuthenticator = Arc<dyn Fn(&Headers) -> bool + Send + Sync>;
+
+/// Server handler for `CONNECT` requests (forward proxy mode).
+///
+/// Opens a TCP connection to `:authority` and splices bytes between
+/// the HTTP/2 stream and the socket. Data is read from either side only
+/// when the other side accepted previous data, so backpressure works
+/// in both directions.
+///
+/// Gateway does not restrict targets itself: without a restrictive
+/// authenticator any client can reach any host reachable from the
+/// proxy, including internal services and loopback.
+///
+/// Register with `ServerHandlerPaths::set_connect_service`.
+pub struct ConnectGateway {
+    /// Reject request with `407` if returns `false`.
+    pub authenticate: ConnectAuthenticator,
+    /// Max time to establish TCP connection, `504` on timeout.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl ConnectGateway {
+    /// Gateway allowing requests accepted by the authenticator.
+    pub fn new(authenticate: ConnectAuthenticator) -> ConnectGateway {
+        ConnectGateway {
+            authenticate,
+            connect_timeout: None,
+        }
+    }
+
+    /// Gateway allowing all requests to any target.
+    ///
+    /// This is an open relay, use only when clients are trusted
+    /// or access to the proxy is restricted otherwise.
+    pub fn allow_all() -> ConnectGateway {
+        ConnectGateway::new(Arc::new(|_: &Headers| true))
+    }
+}
+
+const CONNECT_READ_BUF_SIZE: usize = 16 * 1024;
+
+async fn connect_target(
+    authority: String,
+    connect_timeout: Option<Duration>,
+) -> result::Result<TcpStream> {
+    let connect = TcpStream::connect(authority);
+    Ok(match connect_timeout {
+        Some(connect_timeout) => match tokio::time::timeout(connect_timeout, connect).await {
+            Ok(r) => r?,
+            Err(_) => return Err(error::Error::UpstreamTimeout),
+        },
+        None => connect.await?,
+    })
+}
+
+impl ServerHandler for ConnectGateway {
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        mut resp: ServerResponse,
+    ) -> result::Result<()> {
+        if !(self.authenticate)(&req.headers) {
+            resp.send_headers_end_of_stream(Headers::new_status(407))?;
+            return Ok(());
+        }
+
+        let authority = match req.headers.get_opt(":authority") {
+            Some(authority) => authority.to_owned(),
+            None => {
+                resp.send_headers_end_of_stream(Headers::new_status(400))?;
+                return Ok(());
+            }
+        };
+
+        let connect_timeout = self.connect_timeout;
+        let mut from_client = req.make_stream();
+
+        context.loop_remote().spawn(async move {
+            let socket = match connect_target(authority.clone(), connect_timeout).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("failed to connect to {}: {}", authority, e);
+                    let status = match e {
+                        error::Error::UpstreamTimeout => 504,
+                        _ => 502,
+                    };
+                    drop(resp.send_headers_end_of_stream(Headers::new_status(status)));
+                    return;
+                }
+            };
+
+            let (read, mut write) = tokio::io::split(socket);
+
+            let to_client = stream::try_unfold(
+                (read, vec![0; CONNECT_READ_BUF_SIZE]),
+                |(mut read, mut buf)| async move {
+                    let n = read.read(&mut buf).await?;
+                    if n == 0 {
+                        return Ok(None);
+                    }
+                    let data = Bytes::copy_from_slice(&buf[..n]);
+                    Ok(Some((data, (read, buf))))
+                },
+            );
+
+            let r = resp
+                .send_headers(Headers::ok_200())
+                .and_then(|()| resp.pull_bytes_from_stream(to_client));
+            if let Err(e) = r {
+                debug!("failed to send CONNECT response: {:?}", e);
+                return;
+            }
+
+            let r: result::Result<()> = async {
+                while let Some(part) = from_client.try_next().await? {
+                    if let DataOrTrailers::Data(data, _) = part {
+                        write.write_all(&data).await?;
+                    }
+                }
+                write.shutdown().await?;
+                Ok(())
+            }
+            .await;
+            if let Err(e) = r {
+                debug!("CONNECT tunnel to {} closed: {}", authority, e);
+            }
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn append_comma_separated() {
+        let mut headers = Headers::new_get("/");
+        super::append_comma_separated(&mut headers, "via", "2 a");
+        assert_eq!("2 a", headers.get("via"));
+        headers.add("via", "2 b");
+        super::append_comma_separated(&mut headers, "via", "2 c");
+        assert_eq!("2 a, 2 b, 2 c", headers.get("via"));
+        assert_eq!(1, headers.iter().filter(|h| h.name() == "via").count());
+    }
+}
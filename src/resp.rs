@@ -1,5 +1,6 @@
-use futures::{future, TryFutureExt, TryStreamExt};
+use futures::{future, FutureExt, TryFutureExt, TryStreamExt};
 
+use futures::channel::mpsc;
 use futures::stream;
 use futures::stream::Stream;
 use futures::stream::StreamExt;
@@ -7,6 +8,7 @@ use std::future::Future;
 
 use bytes::Bytes;
 
+use crate::bytes_ext::bytes_deque::BytesDeque;
 use crate::message::SimpleHttpMessage;
 use crate::solicit::header::Headers;
 use crate::solicit_async::*;
@@ -22,7 +24,18 @@ use std::pin::Pin;
 use std::task::Poll;
 
 /// Convenient wrapper around async HTTP response future/stream
-pub struct Response(pub HttpFutureSend<(Headers, HttpStreamAfterHeaders)>);
+pub struct Response(
+    pub HttpFutureSend<(Headers, HttpStreamAfterHeaders)>,
+    Option<mpsc::UnboundedReceiver<Headers>>,
+);
+
+/// Item of `Response::into_informational_stream`.
+pub enum ResponsePart {
+    /// Interim `1xx` response, e.g. `103 Early Hints`
+    Informational(Headers),
+    /// Final response headers followed by the rest of the response
+    Final(Headers, HttpStreamAfterHeaders),
+}
 
 impl Response {
     // constructors
@@ -31,7 +44,7 @@ impl Response {
     where
         F: Future<Output = result::Result<(Headers, HttpStreamAfterHeaders)>> + Send + 'static,
     {
-        Response(Box::pin(future))
+        Response(Box::pin(future), None)
     }
 
     pub fn headers_and_stream(headers: Headers, stream: HttpStreamAfterHeaders) -> Response {
@@ -73,35 +86,106 @@ impl Response {
         Response::headers(headers)
     }
 
-    pub fn from_stream<S>(mut stream: S) -> Response
+    pub fn from_stream<S>(stream: S) -> Response
     where
         S: Stream<Item = result::Result<DataOrHeadersWithFlag>> + Unpin + Send + 'static,
     {
-        Response::new(async move {
-            // Check that first frame is HEADERS
-            let (first, rem) = match stream.try_next().await? {
-                Some(part) => match part.content {
-                    DataOrHeaders::Headers(headers) => {
-                        (headers, HttpStreamAfterHeaders::from_parts(stream))
-                    }
-                    DataOrHeaders::Data(..) => {
-                        return Err(error::Error::InvalidFrame("data before headers".to_owned()))
+        Response::from_stream_impl(stream, None)
+    }
+
+    pub(crate) fn from_stream_impl<S>(mut stream: S, reset: Option<ResetStreamFn>) -> Response
+    where
+        S: Stream<Item = result::Result<DataOrHeadersWithFlag>> + Unpin + Send + 'static,
+    {
+        Response::new_with_informational(move |informational_tx| async move {
+            loop {
+                // Check that first frame is HEADERS
+                match stream.try_next().await? {
+                    Some(part) => match part.content {
+                        DataOrHeaders::Headers(headers) if headers.is_informational() => {
+                            // Receiver is dropped if caller is not interested
+                            drop(informational_tx.unbounded_send(headers));
+                        }
+                        DataOrHeaders::Headers(headers) => {
+                            let content_length = headers.content_length();
+                            let mut rem = HttpStreamAfterHeaders::from_parts(stream)
+                                .with_content_length(content_length);
+                            if let Some(reset) = reset {
+                                rem = rem.with_reset_fn(reset);
+                            }
+                            return Ok((headers, rem));
+                        }
+                        DataOrHeaders::Data(..) => {
+                            return Err(error::Error::InvalidFrame(
+                                "data before headers".to_owned(),
+                            ))
+                        }
+                    },
+                    None => {
+                        return Err(error::Error::InvalidFrame(
+                            "empty response, expecting headers".to_owned(),
+                        ))
                     }
-                },
-                None => {
-                    return Err(error::Error::InvalidFrame(
-                        "empty response, expecting headers".to_owned(),
-                    ))
                 }
-            };
-            Ok((first, rem))
+            }
         })
     }
 
+    /// Response which passes interim responses sent to the sender
+    /// to `into_informational_stream`.
+    pub(crate) fn new_with_informational<F, R>(f: F) -> Response
+    where
+        F: FnOnce(mpsc::UnboundedSender<Headers>) -> R,
+        R: Future<Output = result::Result<(Headers, HttpStreamAfterHeaders)>> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        Response(Box::pin(f(tx)), Some(rx))
+    }
+
     pub fn err(err: error::Error) -> Response {
         Response::new(future::err(err))
     }
 
+    /// Transform final response keeping interim responses.
+    pub(crate) fn map_ok<F>(self, f: F) -> Response
+    where
+        F: FnOnce((Headers, HttpStreamAfterHeaders)) -> (Headers, HttpStreamAfterHeaders)
+            + Send
+            + 'static,
+    {
+        Response(Box::pin(self.0.map_ok(f)), self.1)
+    }
+
+    /// Transform final response or error keeping interim responses.
+    pub(crate) fn map_result<F>(self, f: F) -> Response
+    where
+        F: FnOnce(
+                result::Result<(Headers, HttpStreamAfterHeaders)>,
+            ) -> result::Result<(Headers, HttpStreamAfterHeaders)>
+            + Send
+            + 'static,
+    {
+        Response(Box::pin(self.0.map(f)), self.1)
+    }
+
+    /// Wait for final response, passing interim responses to `tx`.
+    pub(crate) async fn forward_informational(
+        self,
+        tx: mpsc::UnboundedSender<Headers>,
+    ) -> result::Result<(Headers, HttpStreamAfterHeaders)> {
+        let mut parts = self.into_informational_stream();
+        loop {
+            match parts.try_next().await? {
+                Some(ResponsePart::Informational(headers)) => {
+                    // Receiver is dropped if caller is not interested
+                    drop(tx.unbounded_send(headers));
+                }
+                Some(ResponsePart::Final(headers, rem)) => return Ok((headers, rem)),
+                None => unreachable!("response stream ends after final response"),
+            }
+        }
+    }
+
     // getters
 
     pub fn into_stream_flag(self) -> HttpFutureStreamSend<DataOrHeadersWithFlag> {
@@ -119,6 +203,44 @@ impl Response {
         )
     }
 
+    /// Stream of interim `1xx` responses followed by the final response.
+    ///
+    /// Client passes interim responses to the response only
+    /// if `ClientConf::informational_responses` is enabled.
+    pub fn into_informational_stream(self) -> HttpFutureStreamSend<ResponsePart> {
+        let Response(future, mut informational) = self;
+        let mut future = Some(future);
+        let mut final_result = None;
+        Box::pin(stream::poll_fn(move |cx| loop {
+            // Interim responses are queued before final headers are resolved,
+            // so drain them before returning the final response
+            if let Some(rx) = &mut informational {
+                match rx.poll_next_unpin(cx) {
+                    Poll::Ready(Some(headers)) => {
+                        return Poll::Ready(Some(Ok(ResponsePart::Informational(headers))))
+                    }
+                    Poll::Ready(None) => informational = None,
+                    Poll::Pending => {}
+                }
+            }
+            if let Some(f) = &mut future {
+                match f.as_mut().poll(cx) {
+                    Poll::Ready(r) => {
+                        future = None;
+                        final_result = Some(r);
+                        continue;
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            return Poll::Ready(
+                final_result
+                    .take()
+                    .map(|r| r.map(|(headers, rem)| ResponsePart::Final(headers, rem))),
+            );
+        }))
+    }
+
     pub fn into_stream(self) -> HttpFutureStreamSend<DataOrHeaders> {
         Box::pin(TryStreamExt::map_ok(self.into_stream_flag(), |c| c.content))
     }
@@ -136,6 +258,20 @@ impl Response {
                 }),
         )
     }
+
+    /// Collect the response like `collect`, but fail with
+    /// `Error::BodyLimitExceeded` and reset the stream
+    /// if body is larger than `max_bytes`.
+    pub fn collect_with_limit(self, max_bytes: usize) -> HttpFutureSend<SimpleHttpMessage> {
+        Box::pin(async move {
+            let (headers, rem) = self.0.await?;
+            let message = SimpleHttpMessage {
+                headers,
+                body: BytesDeque::new(),
+            };
+            rem.collect_with_limit_into(message, max_bytes).await
+        })
+    }
 }
 
 impl Future for Response {
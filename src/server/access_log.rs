@@ -0,0 +1,146 @@
+//! Structured request logs reported when streams complete.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::common::stream::HttpStreamCommand;
+use crate::common::traffic::StreamTraffic;
+use crate::AnySocketAddr;
+use crate::Headers;
+
+/// Request completed by the server, see `AccessLog`.
+#[derive(Debug, Clone)]
+pub struct AccessLogEntry {
+    pub method: String,
+    /// Request path with query.
+    pub path: String,
+    /// Final response status, `None` if stream was closed
+    /// before response headers were sent.
+    pub status: Option<u32>,
+    /// Frames of the stream, request and response body sizes
+    /// are `received.data_bytes` and `sent.data_bytes`.
+    pub traffic: StreamTraffic,
+    /// Time from receiving request headers to stream completion.
+    pub duration: Duration,
+    pub peer_addr: AnySocketAddr,
+}
+
+/// Access log hook, see `ServerConf::access_log`.
+pub trait AccessLog: Send + Sync + 'static {
+    /// Called once for each request when its stream is closed,
+    /// reset, or the connection is terminated.
+    ///
+    /// Called on the connection event loop, so it must not block.
+    fn log(&self, entry: &AccessLogEntry);
+}
+
+impl fmt::Debug for dyn AccessLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AccessLog")
+    }
+}
+
+impl<F> AccessLog for F
+where
+    F: Fn(&AccessLogEntry) + Send + Sync + 'static,
+{
+    fn log(&self, entry: &AccessLogEntry) {
+        self(entry)
+    }
+}
+
+/// Accumulates entry of a stream, entry is logged when the stream is dropped.
+pub(crate) struct AccessLogRecorder {
+    log: Arc<dyn AccessLog>,
+    entry: AccessLogEntry,
+    start: Instant,
+}
+
+impl AccessLogRecorder {
+    pub fn new(
+        log: Arc<dyn AccessLog>,
+        headers: &Headers,
+        peer_addr: AnySocketAddr,
+    ) -> AccessLogRecorder {
+        AccessLogRecorder {
+            log,
+            entry: AccessLogEntry {
+                method: headers.get_opt(":method").unwrap_or_default().to_owned(),
+                path: headers.get_opt(":path").unwrap_or_default().to_owned(),
+                status: None,
+                traffic: StreamTraffic::default(),
+                duration: Duration::default(),
+                peer_addr,
+            },
+            start: Instant::now(),
+        }
+    }
+
+    pub fn command_sent(&mut self, command: &HttpStreamCommand) {
+        match command {
+            HttpStreamCommand::Headers(headers, _) => {
+                // informational responses are followed by final response,
+                // and trailers have no status
+                if self.entry.status.is_none_or(|s| s < 200) {
+                    if let Some(status) = headers.status_opt() {
+                        self.entry.status = Some(status);
+                    }
+                }
+            }
+            HttpStreamCommand::Data(..) | HttpStreamCommand::Rst(..) => {}
+        }
+    }
+
+    pub fn stream_dropped(&mut self, traffic: &StreamTraffic) {
+        self.entry.traffic = *traffic;
+        self.entry.duration = self.start.elapsed();
+        self.log.log(&self.entry);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::solicit::end_stream::EndStream;
+    use bytes::Bytes;
+    use std::sync::Mutex;
+
+    #[test]
+    fn record() {
+        let logged = Arc::new(Mutex::new(Vec::new()));
+        let logged_copy = logged.clone();
+        let log = move |entry: &AccessLogEntry| logged_copy.lock().unwrap().push(entry.clone());
+
+        let mut recorder = AccessLogRecorder::new(
+            Arc::new(log),
+            &Headers::new_post("/a?b"),
+            AnySocketAddr::Mem,
+        );
+        recorder.command_sent(&HttpStreamCommand::Headers(
+            Headers::new_status(100),
+            EndStream::No,
+        ));
+        recorder.command_sent(&HttpStreamCommand::Headers(
+            Headers::ok_200(),
+            EndStream::No,
+        ));
+        recorder.command_sent(&HttpStreamCommand::Data(
+            Bytes::from_static(b"hello"),
+            EndStream::No,
+        ));
+        recorder.command_sent(&HttpStreamCommand::Headers(Headers::new(), EndStream::Yes));
+        assert!(logged.lock().unwrap().is_empty());
+        let mut traffic = StreamTraffic::default();
+        traffic.received.data_bytes = 3;
+        recorder.stream_dropped(&traffic);
+
+        let logged = logged.lock().unwrap();
+        assert_eq!(1, logged.len());
+        assert_eq!("POST", logged[0].method);
+        assert_eq!("/a?b", logged[0].path);
+        assert_eq!(Some(200), logged[0].status);
+        assert_eq!(traffic, logged[0].traffic);
+    }
+}
@@ -0,0 +1,120 @@
+//! Load shedding: rejecting requests with `503` before handlers are invoked.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::Headers;
+
+/// Connection load at the time a new request is received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerLoad {
+    /// Streams open on the connection, not counting the new one.
+    pub active_streams: usize,
+    /// Bytes queued for writing to the connection.
+    pub write_queue_bytes: usize,
+}
+
+/// Decision of `AdmissionControl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Accept,
+    /// Respond with `503`, and `retry-after` header if specified.
+    Reject(Option<Duration>),
+}
+
+/// Admission control hook, see `ServerConf::admission_control`.
+pub trait AdmissionControl: Send + Sync + 'static {
+    /// Called for each new request before the handler.
+    fn admit(&self, req: &Headers, load: &ServerLoad) -> Admission;
+}
+
+impl fmt::Debug for dyn AdmissionControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AdmissionControl")
+    }
+}
+
+/// Rejects requests when a connection has too many active streams
+/// or too much data waiting to be written.
+#[derive(Debug, Clone)]
+pub struct DefaultAdmissionControl {
+    pub max_active_streams: usize,
+    pub max_write_queue_bytes: usize,
+    /// `retry-after` value sent with `503`.
+    pub retry_after: Option<Duration>,
+}
+
+impl DefaultAdmissionControl {
+    pub fn new() -> DefaultAdmissionControl {
+        DefaultAdmissionControl {
+            max_active_streams: 100,
+            max_write_queue_bytes: 1 << 20,
+            retry_after: Some(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Default for DefaultAdmissionControl {
+    fn default() -> DefaultAdmissionControl {
+        DefaultAdmissionControl::new()
+    }
+}
+
+impl AdmissionControl for DefaultAdmissionControl {
+    fn admit(&self, _req: &Headers, load: &ServerLoad) -> Admission {
+        if load.active_streams >= self.max_active_streams
+            || load.write_queue_bytes >= self.max_write_queue_bytes
+        {
+            Admission::Reject(self.retry_after)
+        } else {
+            Admission::Accept
+        }
+    }
+}
+
+/// Headers of `503` response sent to rejected requests.
+pub(crate) fn rejected_headers(retry_after: Option<Duration>) -> Headers {
+    let mut headers = Headers::new_status(503);
+    if let Some(retry_after) = retry_after {
+        // round up, so client does not retry too early
+        let secs = retry_after.as_secs() + (retry_after.subsec_nanos() > 0) as u64;
+        headers.add("retry-after", format!("{}", secs));
+    }
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_admission_control() {
+        let control = DefaultAdmissionControl {
+            max_active_streams: 2,
+            max_write_queue_bytes: 100,
+            retry_after: Some(Duration::from_millis(1500)),
+        };
+        let req = Headers::new_get("/");
+        let load = |active_streams, write_queue_bytes| ServerLoad {
+            active_streams,
+            write_queue_bytes,
+        };
+        assert_eq!(Admission::Accept, control.admit(&req, &load(1, 99)));
+        assert_eq!(
+            Admission::Reject(Some(Duration::from_millis(1500))),
+            control.admit(&req, &load(2, 0))
+        );
+        assert_eq!(
+            Admission::Reject(Some(Duration::from_millis(1500))),
+            control.admit(&req, &load(0, 100))
+        );
+    }
+
+    #[test]
+    fn rejected() {
+        let headers = rejected_headers(Some(Duration::from_millis(1500)));
+        assert_eq!(503, headers.status());
+        assert_eq!("2", headers.get("retry-after"));
+        assert_eq!(None, rejected_headers(None).get_opt("retry-after"));
+    }
+}
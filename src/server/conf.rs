@@ -1,10 +1,16 @@
 use crate::common::conf::CommonConf;
+use crate::AccessLog;
+use crate::AdmissionControl;
+use crate::Headers;
+use std::sync::Arc;
+use std::time::Duration;
 
+/// Server reaction to protocol negotiated with TLS ALPN.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServerAlpn {
-    // Ignore negotiated ALPN
+    /// Ignore negotiated ALPN
     Ignore,
-    // Return error is ALPN is not "h2"
+    /// Close connection with `Error::AlpnMismatch` if ALPN did not select `h2`
     Require,
 }
 
@@ -14,6 +20,8 @@ pub struct ServerConf {
     pub no_delay: Option<bool>,
     pub thread_name: Option<String>,
 
+    /// Whether TLS connections must negotiate `h2` with ALPN,
+    /// default is `ServerAlpn::Ignore`.
     pub alpn: Option<ServerAlpn>,
 
     // Bind on both IPv4 and IPv6 addresses when addr is IPv6
@@ -23,6 +31,47 @@ pub struct ServerConf {
     pub reuse_port: Option<bool>,
     pub backlog: Option<i32>,
 
+    /// Send `PING` to each connection with this interval
+    /// to measure round-trip time (see `ConnStateSnapshot::rtt`).
+    pub ping_interval: Option<Duration>,
+    /// Grow connection receive window to bandwidth-delay product
+    /// measured with `PING` frames sent while receiving `DATA`,
+    /// default `false`.
+    pub adaptive_window: Option<bool>,
+
+    /// Send `PING` when nothing is received from client during this interval.
+    pub keepalive_interval: Option<Duration>,
+    /// Close connection with `Error::KeepaliveTimeout` if keepalive `PING`
+    /// is not acknowledged within this time, default is 20 seconds.
+    pub keepalive_timeout: Option<Duration>,
+    /// Gracefully close connection with `GOAWAY` when it has no open streams
+    /// and nothing is received from client during this time. Not limited by default.
+    pub idle_timeout: Option<Duration>,
+
+    /// Headers added to every response unless the handler
+    /// sets headers with the same name.
+    ///
+    /// Can be overridden per route with `ServerHandlerPaths::set_default_headers`.
+    pub default_headers: Option<Headers>,
+
+    /// Advertise `SETTINGS_ENABLE_CONNECT_PROTOCOL` to accept
+    /// extended `CONNECT` requests (RFC 8441), e. g. WebSockets.
+    pub enable_connect_protocol: Option<bool>,
+
+    /// Add `date` header to responses which do not have one, default `true`.
+    pub send_date: Option<bool>,
+
+    /// Consulted before invoking the handler for each new request,
+    /// rejected requests get `503` response (see `DefaultAdmissionControl`).
+    pub admission_control: Option<Arc<dyn AdmissionControl>>,
+
+    /// Called when each request stream completes, see `AccessLog`.
+    pub access_log: Option<Arc<dyn AccessLog>>,
+
+    /// Accept HTTP/1.1 requests with `Upgrade: h2c` on plain connections,
+    /// default `true`.
+    pub h2c_upgrade: Option<bool>,
+
     pub common: CommonConf,
 }
 
@@ -1,16 +1,25 @@
 use std::panic;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
 use crate::error;
 use crate::result;
 use crate::AnySocketAddr;
 
 use crate::solicit::end_stream::EndStream;
+use crate::solicit::frame::ContinuationFlag;
+use crate::solicit::frame::ContinuationFrame;
+use crate::solicit::frame::HttpFrame;
 use crate::solicit::frame::HttpSetting;
-use crate::solicit::frame::SettingsFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
+use crate::solicit::frame::PushPromiseFlag;
+use crate::solicit::frame::PushPromiseFrame;
 use crate::solicit::header::*;
+use crate::solicit::priority::Priority;
 use crate::solicit::DEFAULT_SETTINGS;
 
+use bytes::Bytes;
 use futures::channel::oneshot;
 use futures::future;
 use futures::FutureExt;
@@ -22,8 +31,6 @@ use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 use tokio::net::TcpStream;
 
-use tls_api::TlsAcceptor;
-
 use crate::solicit_async::*;
 
 use crate::socket::StreamItem;
@@ -33,29 +40,48 @@ use crate::common::init_where::InitWhere;
 use crate::assert_types::assert_send_future;
 use crate::client_died_error_holder::SomethingDiedErrorHolder;
 use crate::common::conn::Conn;
+use crate::common::conn::ConnIo;
 use crate::common::conn::ConnSpecific;
 use crate::common::conn::ConnStateSnapshot;
 use crate::common::conn_command_channel::conn_command_channel;
 use crate::common::conn_command_channel::ConnCommandSender;
+use crate::common::conn_id::ConnId;
 use crate::common::conn_read::ConnReadSideCustom;
 use crate::common::conn_write::CommonToWriteMessage;
 use crate::common::conn_write::ConnWriteSideCustom;
 use crate::common::sender::CommonSender;
+use crate::common::stream::HttpStreamCommand;
 use crate::common::stream::HttpStreamCommon;
 use crate::common::stream::HttpStreamData;
 use crate::common::stream::HttpStreamDataSpecific;
 use crate::common::stream::InMessageStage;
 use crate::common::stream_handler::StreamHandlerInternal;
 use crate::common::stream_map::HttpStreamRef;
+use crate::common::traffic::StreamTraffic;
+use crate::context::ContextPropagation;
+use crate::context::RequestContext;
+use crate::h2c::H2cUpgrade;
 use crate::headers_place::HeadersPlace;
 use crate::misc::any_to_string;
 use crate::req_resp::RequestOrResponse;
+use crate::server::access_log::AccessLog;
+use crate::server::access_log::AccessLogRecorder;
+use crate::server::admission::rejected_headers;
+use crate::server::admission::Admission;
+use crate::server::admission::AdmissionControl;
+use crate::server::admission::ServerLoad;
 use crate::server::handler::ServerHandler;
 use crate::server::handler::ServerHandlerContext;
+use crate::server::push::PushContext;
+use crate::server::push::PushPromise;
 use crate::server::req::ServerRequest;
+use crate::server::router::PathParams;
 use crate::server::types::ServerTypes;
 use crate::solicit::stream_id::StreamId;
+use crate::tls::PeerCertificates;
+use crate::tls::TlsSessionInfo;
 use crate::ErrorCode;
+use crate::ServerAlpn;
 use crate::ServerConf;
 use crate::ServerResponse;
 use crate::ServerTlsOption;
@@ -63,9 +89,23 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use tokio::runtime::Handle;
 
-pub struct ServerStreamData {}
+pub struct ServerStreamData {
+    access_log: Option<AccessLogRecorder>,
+}
 
-impl HttpStreamDataSpecific for ServerStreamData {}
+impl HttpStreamDataSpecific for ServerStreamData {
+    fn command_sent(&mut self, command: &HttpStreamCommand) {
+        if let Some(access_log) = &mut self.access_log {
+            access_log.command_sent(command);
+        }
+    }
+
+    fn stream_dropped(&mut self, traffic: &StreamTraffic) {
+        if let Some(access_log) = &mut self.access_log {
+            access_log.stream_dropped(traffic);
+        }
+    }
+}
 
 pub(crate) type ServerStream = HttpStreamCommon<ServerTypes>;
 
@@ -84,6 +124,15 @@ impl HttpStreamData for ServerStream {
 
 pub(crate) struct ServerConnData {
     factory: Arc<dyn ServerHandler>,
+    context_propagation: Option<ContextPropagation>,
+    default_headers: Option<Arc<Headers>>,
+    send_date: bool,
+    admission_control: Option<Arc<dyn AdmissionControl>>,
+    access_log: Option<Arc<dyn AccessLog>>,
+    next_push_stream_id: Arc<Mutex<StreamId>>,
+    peer_certificates: Option<Arc<PeerCertificates>>,
+    tls_session: Option<Arc<TlsSessionInfo>>,
+    local_addr: AnySocketAddr,
 }
 
 impl ConnSpecific for ServerConnData {}
@@ -118,12 +167,36 @@ where
 
         debug!("new stream: {}", stream_id);
 
-        let (_, out_window) = self.new_stream_data(
+        let admission = match &self.specific.admission_control {
+            Some(admission_control) => {
+                let load = ServerLoad {
+                    active_streams: self.streams.len(),
+                    write_queue_bytes: self.queued_write.queued_bytes_len(),
+                };
+                admission_control.admit(&headers, &load)
+            }
+            None => Admission::Accept,
+        };
+
+        // `PRIORITY_UPDATE` received before the request overrides the header
+        let priority = match self.pending_priorities.remove(&stream_id) {
+            Some(priority) => priority,
+            None => Priority::from_headers(&headers),
+        };
+
+        let access_log = self
+            .specific
+            .access_log
+            .clone()
+            .map(|log| AccessLogRecorder::new(log, &headers, self.peer_addr.clone()));
+
+        let (mut stream, out_window) = self.new_stream_data(
             stream_id,
             headers.content_length(),
             InMessageStage::AfterInitialHeaders,
-            ServerStreamData {},
+            ServerStreamData { access_log },
         );
+        stream.stream().priority = priority;
 
         let in_window_size = self
             .streams
@@ -135,20 +208,60 @@ where
 
         let factory = self.specific.factory.clone();
 
+        let push = match (
+            headers.get_opt(":scheme"),
+            headers
+                .get_opt(":authority")
+                .or_else(|| headers.get_opt("host")),
+        ) {
+            (Some(scheme), Some(authority)) => Some(PushContext {
+                next_stream_id: self.specific.next_push_stream_id.clone(),
+                scheme: scheme.to_owned(),
+                authority: authority.to_owned(),
+            }),
+            _ => None,
+        };
+
         let sender = ServerResponse {
             common: CommonSender::new(stream_id, self.to_write_tx.clone(), out_window, false),
             drop_callback: None,
+            default_headers: self.specific.default_headers.clone(),
+            send_date: self.specific.send_date,
+            headers_filters: Vec::new(),
+            push,
+            peer_max_frame_size: self.peer_settings.max_frame_size,
         };
 
         let context = ServerHandlerContext {
             loop_handle: self.loop_handle.clone(),
+            conn_id: self.conn_id,
+            peer_addr: self.peer_addr.clone(),
+            local_addr: self.specific.local_addr.clone(),
+            request_context: match &self.specific.context_propagation {
+                Some(context_propagation) => context_propagation.extract(&headers),
+                None => RequestContext::new(),
+            },
+            peer_certificates: self.specific.peer_certificates.clone(),
+            tls_session: self.specific.tls_session.clone(),
         };
 
+        if let Admission::Reject(retry_after) = admission {
+            debug!("rejecting stream {} by admission control", stream_id);
+            let mut sender = sender;
+            // ignore error, the stream is not processed anyway
+            drop(sender.send_headers_end_of_stream(rejected_headers(retry_after)));
+            return Ok(self.streams.get_mut(stream_id).expect("get stream"));
+        }
+
         let mut stream_handler = None;
         let invoke_result = {
             let req = ServerRequest {
                 headers,
                 end_stream: end_stream == EndStream::Yes,
+                params: PathParams::default(),
+                peer_addr: self.peer_addr.clone(),
+                local_addr: self.specific.local_addr.clone(),
+                tls_session: self.specific.tls_session.clone(),
                 stream_id,
                 in_window_size,
                 stream_handler: &mut stream_handler,
@@ -181,8 +294,29 @@ where
     }
 }
 
+impl<I> ServerInner<I>
+where
+    I: AsyncWrite + AsyncRead + Send + 'static,
+{
+    /// Open stream 1 for request upgraded from HTTP/1.1 (RFC 7540 section 3.2).
+    fn process_h2c_upgrade(&mut self, upgrade: H2cUpgrade) -> result::Result<()> {
+        // `101` response acknowledges settings from `HTTP2-Settings`
+        for setting in upgrade.settings.settings {
+            self.peer_settings.apply(setting);
+        }
+
+        // Upgraded request is half-closed (remote)
+        if let Some(stream) = self.process_headers(1, EndStream::Yes, upgrade.headers)? {
+            stream.close_remote();
+        }
+        self.peer_closed_streams.add(1);
+        Ok(())
+    }
+}
+
 pub enum ServerToWriteMessage {
     Common(CommonToWriteMessage),
+    Push(PushPromise),
 }
 
 impl From<CommonToWriteMessage> for ServerToWriteMessage {
@@ -200,6 +334,88 @@ where
     fn process_message(&mut self, message: ServerToWriteMessage) -> result::Result<()> {
         match message {
             ServerToWriteMessage::Common(common) => self.process_common_message(common),
+            ServerToWriteMessage::Push(push) => self.process_push(push),
+        }
+    }
+}
+
+impl<I> ServerInner<I>
+where
+    I: AsyncWrite + AsyncRead + Send + 'static,
+{
+    fn pushed_stream_count(&self) -> usize {
+        self.streams.local_stream_count()
+    }
+
+    fn process_push(&mut self, push: PushPromise) -> result::Result<()> {
+        let PushPromise {
+            stream_id,
+            promised_stream_id,
+            headers,
+            out_window,
+        } = push;
+
+        // Dropped window sender makes the pushed response fail
+        if !self.peer_settings.enable_push
+            || self.goaway_sent.is_some()
+            || self.goaway_received.is_some()
+            || self.streams.get_mut(stream_id).is_none()
+            || self.pushed_stream_count() >= self.peer_settings.max_concurrent_streams as usize
+        {
+            debug!(
+                "push promise {} on {} discarded",
+                promised_stream_id, stream_id
+            );
+            return Ok(());
+        }
+
+        self.write_push_promise(stream_id, promised_stream_id, &headers);
+
+        let initial_window_size = self.peer_settings.initial_window_size;
+        out_window.increase(initial_window_size as isize);
+        let mut stream = HttpStreamCommon::new(
+            self.our_settings_ack.initial_window_size,
+            initial_window_size,
+            out_window,
+            None,
+            InMessageStage::AfterTrailingHeaders,
+            ServerStreamData { access_log: None },
+        );
+        // Pushed stream is reserved (local) until headers are sent,
+        // which is equivalent to half-closed (remote) for us.
+        stream.close_remote();
+        stream.metrics = self.stream_metrics();
+        self.streams.insert(promised_stream_id, stream);
+        Ok(())
+    }
+
+    fn write_push_promise(
+        &mut self,
+        stream_id: StreamId,
+        promised_stream_id: StreamId,
+        headers: &Headers,
+    ) {
+        let block = self.encoder.encode_headers(headers.iter_for_hpack());
+
+        // PUSH_PROMISE payload also contains promised stream id
+        let max_frame_size = self.peer_settings.max_frame_size as usize;
+        let first_len = block.len().min(max_frame_size - 4);
+        let mut frame =
+            PushPromiseFrame::new(stream_id, promised_stream_id, block.slice(..first_len));
+        if first_len < block.len() {
+            frame.flags.clear(PushPromiseFlag::EndHeaders);
+        }
+        self.queued_write.queue_not_goaway(frame);
+
+        let mut pos = first_len;
+        while pos < block.len() {
+            let end = block.len().min(pos + max_frame_size);
+            let mut frame = ContinuationFrame::new(block.slice(pos..end), stream_id);
+            if end == block.len() {
+                frame.flags.set(ContinuationFlag::EndHeaders);
+            }
+            self.queued_write.queue_not_goaway(frame);
+            pos = end;
         }
     }
 }
@@ -231,6 +447,14 @@ where
             return Ok(None);
         }
 
+        if headers.get_opt(":protocol").is_some()
+            && !self.our_settings_sent().enable_connect_protocol
+        {
+            warn!("extended CONNECT is not enabled: {:?}", headers);
+            self.send_rst_stream(stream_id, ErrorCode::ProtocolError)?;
+            return Ok(None);
+        }
+
         if !existing_stream {
             return self
                 .new_stream_from_client(stream_id, headers, end_stream)
@@ -247,18 +471,31 @@ where
         stream.stream().trailers_recvd(headers);
         Ok(Some(stream))
     }
+
+    fn process_push_promise(
+        &mut self,
+        frame: PushPromiseDecodedFrame,
+    ) -> result::Result<Option<HttpStreamRef<'_, ServerTypes>>> {
+        // 8.2: A client cannot push
+        warn!("PUSH_PROMISE received from client: {:?}", frame);
+        self.send_goaway(ErrorCode::ProtocolError)?;
+        Ok(None)
+    }
 }
 
 pub struct ServerConn {
     write_tx: ConnCommandSender<ServerTypes>,
+    conn_id: ConnId,
 }
 
 impl ServerConn {
     fn connected<F, I>(
         lh: &Handle,
-        socket: HttpFutureSend<I>,
+        socket: HttpFutureSend<(I, Option<PeerCertificates>, Option<TlsSessionInfo>)>,
         peer_addr: AnySocketAddr,
+        local_addr: AnySocketAddr,
         conf: ServerConf,
+        h2c_upgrade: bool,
         service: Arc<F>,
     ) -> (ServerConn, HttpFutureSend<()>)
     where
@@ -271,29 +508,69 @@ impl ServerConn {
 
         let (write_tx, write_rx) = conn_command_channel(conn_died_error_holder.clone());
 
-        let settings_frame = SettingsFrame::from_settings(vec![HttpSetting::EnablePush(false)]);
+        let mut settings_frame = conf.common.settings_frame();
+        if conf.enable_connect_protocol == Some(true) {
+            settings_frame
+                .settings
+                .push(HttpSetting::EnableConnectProtocol(true));
+        }
         let mut settings = DEFAULT_SETTINGS;
         settings.apply_from_frame(&settings_frame);
 
         let write_tx_copy = write_tx.clone();
 
-        let run = socket.and_then(move |mut conn| async move {
-            server_handshake(&mut conn, settings_frame).await?;
-
-            let conn_data = Conn::<ServerTypes, I>::new(
-                lh,
-                ServerConnData { factory: service },
-                conf.common,
-                settings,
-                write_tx_copy,
-                write_rx,
-                conn,
-                peer_addr,
-                conn_died_error_holder,
-            );
-
-            conn_data.run().await
-        });
+        let conn_id = ConnId::next();
+
+        let context_propagation = conf.common.context_propagation.clone();
+        let default_headers = conf.default_headers.clone().map(Arc::new);
+        let send_date = conf.send_date.unwrap_or(true);
+        let admission_control = conf.admission_control.clone();
+        let access_log = conf.access_log.clone();
+
+        let handshake_started = Instant::now();
+
+        let run = socket.and_then(
+            move |(mut conn, peer_certificates, tls_session)| async move {
+                let upgrade = server_handshake(&mut conn, settings_frame, h2c_upgrade).await?;
+
+                let io = ConnIo {
+                    loop_handle: lh,
+                    socket: conn,
+                    conn_id,
+                    peer_addr,
+                    to_write_tx: write_tx_copy,
+                    write_rx,
+                    conn_died_error_holder,
+                };
+                let mut conn_data = Conn::<ServerTypes, I>::new(
+                    io,
+                    ServerConnData {
+                        factory: service,
+                        context_propagation,
+                        default_headers,
+                        send_date,
+                        admission_control,
+                        access_log,
+                        next_push_stream_id: Arc::new(Mutex::new(2)),
+                        peer_certificates: peer_certificates.map(Arc::new),
+                        tls_session: tls_session.map(Arc::new),
+                        local_addr,
+                    },
+                    conf.common,
+                    settings,
+                );
+                conn_data.handshake_done(handshake_started);
+                conn_data.set_ping_interval(conf.ping_interval);
+                conn_data.set_adaptive_window(conf.adaptive_window.unwrap_or(false));
+                conn_data.set_keepalive(conf.keepalive_interval, conf.keepalive_timeout);
+                conn_data.set_idle_timeout(conf.idle_timeout);
+                if let Some(upgrade) = upgrade {
+                    conn_data.process_h2c_upgrade(upgrade)?;
+                }
+
+                conn_data.run().await
+            },
+        );
 
         let run = assert_send_future(run);
 
@@ -302,29 +579,63 @@ impl ServerConn {
             future::ready(x)
         }));
 
-        (ServerConn { write_tx }, future)
+        (ServerConn { write_tx, conn_id }, future)
     }
 
-    pub fn new<S, A>(
+    /// Id of this connection.
+    pub fn conn_id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    pub fn new<S>(
         lh: &Handle,
         socket: Pin<Box<dyn StreamItem>>,
         peer_addr: AnySocketAddr,
-        tls: ServerTlsOption<A>,
+        tls: ServerTlsOption,
         conf: ServerConf,
         service: Arc<S>,
     ) -> (ServerConn, HttpFutureSend<()>)
     where
         S: ServerHandler,
-        A: TlsAcceptor,
     {
+        let local_addr = match socket.local_addr() {
+            Ok(local_addr) => local_addr,
+            Err(e) => {
+                warn!("failed to get local address of connection: {}", e);
+                AnySocketAddr::Io
+            }
+        };
         match tls {
             ServerTlsOption::Plain => {
-                let socket = Box::pin(future::ok(socket));
-                ServerConn::connected(lh, socket, peer_addr, conf, service)
+                let socket = Box::pin(future::ok((socket, None, None)));
+                let h2c_upgrade = conf.h2c_upgrade.unwrap_or(true);
+                ServerConn::connected(
+                    lh,
+                    socket,
+                    peer_addr,
+                    local_addr,
+                    conf,
+                    h2c_upgrade,
+                    service,
+                )
             }
             ServerTlsOption::Tls(acceptor) => {
-                let socket = Box::pin(async move { Ok(acceptor.accept(socket).await?) });
-                ServerConn::connected(lh, socket, peer_addr, conf, service)
+                let alpn = conf.alpn.clone().unwrap_or(ServerAlpn::Ignore);
+                let socket = Box::pin(async move {
+                    let handshake = acceptor.accept(Box::pin(socket)).await?;
+                    if alpn == ServerAlpn::Require
+                        && handshake.alpn_protocol.as_deref() != Some(b"h2")
+                    {
+                        return Err(error::Error::AlpnMismatch(handshake.alpn_protocol));
+                    }
+                    let tls_session = handshake.session_info();
+                    Ok((
+                        handshake.stream,
+                        handshake.peer_certificates,
+                        Some(tls_session),
+                    ))
+                });
+                ServerConn::connected(lh, socket, peer_addr, local_addr, conf, false, service)
             }
         }
     }
@@ -339,7 +650,7 @@ impl ServerConn {
     where
         S: ServerHandler,
     {
-        let no_tls: ServerTlsOption<tls_api_stub::TlsAcceptor> = ServerTlsOption::Plain;
+        let no_tls = ServerTlsOption::Plain;
         ServerConn::new(
             lh,
             Box::pin(socket),
@@ -385,6 +696,22 @@ impl ServerConn {
         ServerConn::new_plain_single_thread(lh, socket, peer_addr, conf, Arc::new(HttpServiceFn(f)))
     }
 
+    /// Write a pre-built frame, see `Server::send_raw_frame`.
+    pub fn send_raw_frame(&self, frame: HttpFrame) -> result::Result<()> {
+        let message = CommonToWriteMessage::RawFrame(frame);
+        self.write_tx
+            .unbounded_send(message.into())
+            .map_err(|_| error::Error::ConnDied)
+    }
+
+    /// Send `GOAWAY`, see `ShutdownHandle`.
+    pub(crate) fn send_goaway(&self, error_code: ErrorCode, graceful: bool) -> result::Result<()> {
+        let message = CommonToWriteMessage::Goaway(error_code, Bytes::new(), graceful);
+        self.write_tx
+            .unbounded_send(message.into())
+            .map_err(|_| error::Error::ConnDied)
+    }
+
     /// For tests
     pub fn dump_state(&self) -> HttpFutureSend<ConnStateSnapshot> {
         let (tx, rx) = oneshot::channel();
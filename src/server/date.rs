@@ -0,0 +1,55 @@
+//! Cached `date` response header value.
+
+use std::cell::RefCell;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::date::format_http_date;
+use crate::HeaderValue;
+
+struct CachedDate {
+    unix_secs: u64,
+    value: HeaderValue,
+}
+
+thread_local! {
+    static CACHED_DATE: RefCell<Option<CachedDate>> = const { RefCell::new(None) };
+}
+
+/// Current time as `date` header value.
+///
+/// Value is formatted at most once per second per thread.
+pub(crate) fn date_header_value() -> HeaderValue {
+    let unix_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    CACHED_DATE.with(|cached| {
+        let mut cached = cached.borrow_mut();
+        match &*cached {
+            Some(date) if date.unix_secs == unix_secs => date.value.clone(),
+            _ => {
+                let value = HeaderValue::from(format_http_date(unix_secs));
+                *cached = Some(CachedDate {
+                    unix_secs,
+                    value: value.clone(),
+                });
+                value
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cached() {
+        let a = date_header_value();
+        let b = date_header_value();
+        assert_eq!(29, a.as_slice().len());
+        // may differ if second changed between calls
+        assert!(a == b || b == date_header_value());
+    }
+}
@@ -1,13 +1,59 @@
+use crate::common::conn_id::ConnId;
+use crate::context::RequestContext;
 use crate::result;
 use crate::server::req::ServerRequest;
+use crate::tls::PeerCertificates;
+use crate::tls::TlsSessionInfo;
+use crate::AnySocketAddr;
 use crate::ServerResponse;
+use std::sync::Arc;
 use tokio::runtime::Handle;
 
 pub struct ServerHandlerContext {
     pub(crate) loop_handle: Handle,
+    pub(crate) conn_id: ConnId,
+    pub(crate) peer_addr: AnySocketAddr,
+    pub(crate) local_addr: AnySocketAddr,
+    pub(crate) request_context: RequestContext,
+    pub(crate) peer_certificates: Option<Arc<PeerCertificates>>,
+    pub(crate) tls_session: Option<Arc<TlsSessionInfo>>,
 }
 
 impl ServerHandlerContext {
+    /// Id of the connection request is received on.
+    pub fn conn_id(&self) -> ConnId {
+        self.conn_id
+    }
+
+    /// Address of the peer which sent the request.
+    pub fn peer_addr(&self) -> &AnySocketAddr {
+        &self.peer_addr
+    }
+
+    /// Local address of the connection request is received on.
+    pub fn local_addr(&self) -> &AnySocketAddr {
+        &self.local_addr
+    }
+
+    /// Context extracted from request headers with
+    /// `CommonConf::context_propagation`, empty if not configured.
+    pub fn request_context(&self) -> &RequestContext {
+        &self.request_context
+    }
+
+    /// Certificates presented by the client during TLS handshake.
+    ///
+    /// `None` for plain connections, if client did not present
+    /// a certificate, or if TLS stack does not expose certificates.
+    pub fn peer_certificates(&self) -> Option<&PeerCertificates> {
+        self.peer_certificates.as_deref()
+    }
+
+    /// Parameters negotiated during TLS handshake, `None` for plain connections.
+    pub fn tls_session(&self) -> Option<&TlsSessionInfo> {
+        self.tls_session.as_deref()
+    }
+
     // TODO: provide access to executor if there's any
     pub fn loop_remote(&self) -> Handle {
         self.loop_handle.clone()
@@ -12,24 +12,36 @@ use crate::ServerResponse;
 #[derive(Default)]
 struct Node {
     service: Option<Arc<dyn ServerHandler>>,
+    default_headers: Option<Headers>,
     children: HashMap<String, Node>,
 }
 
 impl Node {
-    fn add_service(&mut self, path: &str, service: Arc<dyn ServerHandler>) {
+    fn node_mut(&mut self, path: &str) -> &mut Node {
         match split_path(path) {
-            None => {
-                self.service = Some(service);
-            }
+            None => self,
             Some((first, rem)) => {
                 let node = match self.children.entry(first.to_owned()) {
                     hash_map::Entry::Occupied(e) => e.into_mut(),
-                    hash_map::Entry::Vacant(e) => e.insert(Node {
-                        service: None,
-                        children: HashMap::new(),
-                    }),
+                    hash_map::Entry::Vacant(e) => e.insert(Node::default()),
                 };
-                node.add_service(rem, service);
+                node.node_mut(rem)
+            }
+        }
+    }
+
+    fn add_service(&mut self, path: &str, service: Arc<dyn ServerHandler>) {
+        self.node_mut(path).service = Some(service);
+    }
+
+    /// Default headers of nodes on the path, outermost first.
+    fn find_default_headers<'a>(&'a self, path: &str, result: &mut Vec<&'a Headers>) {
+        if let Some(headers) = &self.default_headers {
+            result.push(headers);
+        }
+        if let Some((first, rem)) = split_path(path) {
+            if let Some(node) = self.children.get(first) {
+                node.find_default_headers(rem, result);
             }
         }
     }
@@ -96,6 +108,7 @@ fn test_split_path() {
 #[derive(Default)]
 pub struct ServerHandlerPaths {
     root: Node,
+    connect: Option<Arc<dyn ServerHandler>>,
 }
 
 impl ServerHandlerPaths {
@@ -170,6 +183,24 @@ impl ServerHandlerPaths {
         self.root.remove_service(path)
     }
 
+    /// Register a service for `CONNECT` requests, which have no path.
+    ///
+    /// Extended `CONNECT` requests (e. g. WebSockets) are routed by path.
+    ///
+    /// `CONNECT` requests are rejected with `405` if not set.
+    pub fn set_connect_service(&mut self, service: Arc<dyn ServerHandler>) {
+        self.connect = Some(service);
+    }
+
+    /// Set response default headers for given path and paths below it.
+    ///
+    /// These headers replace headers with the same names
+    /// from `ServerConf::default_headers` or from outer paths.
+    pub fn set_default_headers(&mut self, path: &str, headers: Headers) {
+        assert!(path.starts_with("/"));
+        self.root.node_mut(path).default_headers = Some(headers);
+    }
+
     fn find_service(&self, path: &str) -> Option<&dyn ServerHandler> {
         self.root.find_service(path)
     }
@@ -182,6 +213,26 @@ impl ServerHandler for ServerHandlerPaths {
         req: ServerRequest,
         mut resp: ServerResponse,
     ) -> result::Result<()> {
+        // extended `CONNECT` requests have a path and are routed by it
+        if req.headers.is_connect() && req.headers.get_opt(":protocol").is_none() {
+            return match &self.connect {
+                Some(service) => service.start_request(context, req, resp),
+                None => {
+                    info!("serving 405 for CONNECT");
+                    drop(resp.send_headers(Headers::new_status(405)));
+                    drop(resp.close());
+                    Ok(())
+                }
+            };
+        }
+
+        let mut default_headers = Vec::new();
+        self.root
+            .find_default_headers(req.headers.path(), &mut default_headers);
+        for headers in default_headers {
+            resp.add_default_headers(headers);
+        }
+
         if let Some(service) = self.find_service(req.headers.path()) {
             info!("invoking user callback for path {}", req.headers.path());
             service.start_request(context, req, resp)
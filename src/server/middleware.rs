@@ -0,0 +1,79 @@
+//! Layers around server handler.
+
+use std::sync::Arc;
+
+use crate::result;
+use crate::server::handler::ServerHandler;
+use crate::server::handler::ServerHandlerContext;
+use crate::server::req::ServerRequest;
+use crate::ServerResponse;
+
+/// Layer around `ServerHandler`, e. g. for authentication,
+/// logging or adding security headers.
+///
+/// Middleware is registered with `ServerBuilder::add_middleware`,
+/// first registered middleware is the outermost layer.
+pub trait ServerMiddleware: Send + Sync + 'static {
+    /// Handle request, usually by passing it to `next`.
+    ///
+    /// Middleware may modify `req.headers` before passing the request,
+    /// post-process response headers with `ServerResponse::add_headers_filter`,
+    /// or short-circuit the chain by responding with `resp` itself.
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        resp: ServerResponse,
+        next: Next,
+    ) -> result::Result<()>;
+}
+
+/// Remaining part of the middleware chain, ending with the handler.
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn ServerMiddleware>],
+    handler: &'a dyn ServerHandler,
+}
+
+impl<'a> Next<'a> {
+    /// Pass request to the next middleware or to the handler.
+    pub fn start_request(
+        self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        resp: ServerResponse,
+    ) -> result::Result<()> {
+        match self.middleware.split_first() {
+            Some((first, middleware)) => first.start_request(
+                context,
+                req,
+                resp,
+                Next {
+                    middleware,
+                    handler: self.handler,
+                },
+            ),
+            None => self.handler.start_request(context, req, resp),
+        }
+    }
+}
+
+/// Handler wrapped in middleware chain.
+pub(crate) struct WithMiddleware<H> {
+    pub middleware: Vec<Arc<dyn ServerMiddleware>>,
+    pub handler: H,
+}
+
+impl<H: ServerHandler> ServerHandler for WithMiddleware<H> {
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        resp: ServerResponse,
+    ) -> result::Result<()> {
+        Next {
+            middleware: &self.middleware,
+            handler: &self.handler,
+        }
+        .start_request(context, req, resp)
+    }
+}
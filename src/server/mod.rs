@@ -1,10 +1,18 @@
+pub mod access_log;
+pub mod admission;
 pub mod conf;
 pub mod conn;
+pub(crate) mod date;
 pub mod handler;
 pub mod handler_paths;
 pub(crate) mod increase_in_window;
+pub mod middleware;
+pub(crate) mod push;
+pub(crate) mod range;
 pub mod req;
 pub mod resp;
+pub mod router;
+pub mod shutdown;
 pub(crate) mod stream_handler;
 pub mod tls;
 pub(crate) mod types;
@@ -12,7 +20,9 @@ pub(crate) mod types;
 use futures::future::try_join_all;
 use std::collections::HashMap;
 
+use std::marker::PhantomData;
 use std::net::ToSocketAddrs;
+use std::path::Path;
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -31,35 +41,52 @@ use futures::stream::TryStreamExt;
 
 use crate::error::Error;
 use crate::result::Result;
+use crate::tls::acceptor_like;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::rustls::sni_cert_resolver;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::rustls::PeerCertificatesAcceptor;
+#[cfg(feature = "tls-rustls")]
+use crate::tls::SniCertificate;
+use crate::tls::TlsAcceptorLike;
 
 use crate::solicit_async::*;
 
 use crate::futures_misc::*;
 
-use tls_api::TlsAcceptor;
 use tls_api_stub;
 
 use crate::socket::AnySocketAddr;
 use crate::socket::ToSocketListener;
 use crate::socket::ToTokioListener;
+use crate::socket_io::IoStream;
 
 pub use self::tls::ServerTlsOption;
 use crate::assert_types::assert_send_future;
 use crate::common::conn::ConnStateSnapshot;
+use crate::common::conn_id::ConnId;
 use crate::result;
+use crate::server::access_log::AccessLog;
 pub use crate::server::conf::ServerConf;
 pub use crate::server::conn::ServerConn;
 use crate::server::handler::ServerHandler;
 use crate::server::handler_paths::ServerHandlerPaths;
+use crate::server::middleware::ServerMiddleware;
+use crate::server::middleware::WithMiddleware;
+use crate::server::shutdown::ShutdownHandle;
+use crate::socket_named_pipe::SocketAddrNamedPipe;
 use crate::socket_unix::SocketAddrUnix;
+use crate::solicit::frame::HttpFrame;
 use rand::thread_rng;
 use rand::Rng;
 use std::fmt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
 use tokio::runtime::{Handle, Runtime};
 
 pub struct ServerBuilder<A: tls_api::TlsAcceptor = tls_api_stub::TlsAcceptor> {
     pub conf: ServerConf,
-    pub tls: ServerTlsOption<A>,
+    pub tls: ServerTlsOption,
     pub addr: Option<AnySocketAddr>,
     /// Event loop to spawn server.
     /// If not specified, builder will create new event loop in a new thread.
@@ -69,6 +96,10 @@ pub struct ServerBuilder<A: tls_api::TlsAcceptor = tls_api_stub::TlsAcceptor> {
     // TODO: test it
     pub conn_event_loops: Vec<Handle>,
     pub service: ServerHandlerPaths,
+    /// Layers around `service`, see `add_middleware`.
+    pub middleware: Vec<Arc<dyn ServerMiddleware>>,
+    /// `tls_api` acceptor accepted by `set_tls`.
+    _tls_acceptor: PhantomData<fn() -> A>,
 }
 
 impl ServerBuilder<tls_api_stub::TlsAcceptor> {
@@ -90,6 +121,60 @@ impl ServerBuilder<tls_api_stub::TlsAcceptor> {
     }
 }
 
+#[cfg(feature = "tls-rustls")]
+impl ServerBuilder<tls_api_rustls::TlsAcceptor> {
+    /// New server builder using rustls with given certificate chain
+    /// and private key (PKCS#8 or PKCS#1), all DER-encoded.
+    ///
+    /// `h2` protocol is offered in ALPN.
+    pub fn new_rustls(
+        cert_chain: &[&[u8]],
+        key: &[u8],
+    ) -> Result<ServerBuilder<tls_api_rustls::TlsAcceptor>> {
+        let mut acceptor = tls_api_rustls::TlsAcceptorBuilder::from_certs_and_key(cert_chain, key)?;
+        tls_api::TlsAcceptorBuilder::set_alpn_protocols(&mut acceptor, &[b"h2"])?;
+        let mut server = ServerBuilder::new();
+        server.set_tls(tls_api::TlsAcceptorBuilder::build(acceptor)?);
+        Ok(server)
+    }
+
+    /// New server builder using rustls serving several domains.
+    ///
+    /// Certificate is selected by hostname client sends in SNI extension,
+    /// connections without SNI or with unknown hostname fail handshake.
+    ///
+    /// `h2` protocol is offered in ALPN.
+    pub fn new_rustls_sni(
+        certs: &[SniCertificate],
+    ) -> Result<ServerBuilder<tls_api_rustls::TlsAcceptor>> {
+        let mut config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+        config.cert_resolver = Arc::new(sni_cert_resolver(certs)?);
+        let mut acceptor = tls_api_rustls::TlsAcceptorBuilder(config);
+        tls_api::TlsAcceptorBuilder::set_alpn_protocols(&mut acceptor, &[b"h2"])?;
+        let mut server = ServerBuilder::new();
+        server.set_tls(tls_api::TlsAcceptorBuilder::build(acceptor)?);
+        Ok(server)
+    }
+}
+
+#[cfg(feature = "tls-native")]
+impl ServerBuilder<tls_api_native_tls::TlsAcceptor> {
+    /// New server builder using platform TLS library
+    /// with identity from PKCS#12 archive.
+    ///
+    /// Platform TLS does not negotiate ALPN, so `ServerConf::alpn`
+    /// must not be `ServerAlpn::Require`.
+    pub fn new_native_tls(
+        pkcs12: &[u8],
+        password: &str,
+    ) -> Result<ServerBuilder<tls_api_native_tls::TlsAcceptor>> {
+        let acceptor = tls_api_native_tls::TlsAcceptorBuilder::from_pkcs12(pkcs12, password)?;
+        let mut server = ServerBuilder::new();
+        server.set_tls(tls_api::TlsAcceptorBuilder::build(acceptor)?);
+        Ok(server)
+    }
+}
+
 impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
     /// Set port server listens on.
     /// Can be zero to bind on any available port,
@@ -112,9 +197,25 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
 }
 
 impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
-    // Set name of unix domain socket
+    /// Listen on unix domain socket at given path.
     pub fn set_unix_addr<S: Into<SocketAddrUnix>>(&mut self, addr: S) -> Result<()> {
-        self.addr = Some(AnySocketAddr::Unix(addr.into()));
+        self.bind_unix(addr.into().0);
+        Ok(())
+    }
+
+    /// Listen on unix domain socket at given path.
+    ///
+    /// Socket file is created when server is built
+    /// and removed when `Server` is dropped.
+    pub fn bind_unix<P: AsRef<Path>>(&mut self, path: P) {
+        self.addr = Some(AnySocketAddr::Unix(SocketAddrUnix::from(path.as_ref())));
+    }
+
+    /// Listen on Windows named pipe, e. g. `\\.\pipe\name`.
+    ///
+    /// Fails to start on other platforms.
+    pub fn set_named_pipe_addr<S: Into<SocketAddrNamedPipe>>(&mut self, addr: S) -> Result<()> {
+        self.addr = Some(AnySocketAddr::NamedPipe(addr.into()));
         Ok(())
     }
 }
@@ -134,11 +235,42 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
             event_loop: None,
             conn_event_loops: Vec::new(),
             service: ServerHandlerPaths::new(),
+            middleware: Vec::new(),
+            _tls_acceptor: PhantomData,
         }
     }
 
     pub fn set_tls(&mut self, acceptor: A) {
-        self.tls = ServerTlsOption::Tls(Arc::new(acceptor));
+        self.tls = ServerTlsOption::Tls(acceptor_like(acceptor));
+    }
+
+    /// Accept TLS connections with any TLS stack.
+    pub fn set_tls_acceptor<T: TlsAcceptorLike>(&mut self, acceptor: T) {
+        self.tls = ServerTlsOption::Tls(acceptor_like(acceptor));
+    }
+
+    /// Accept TLS connections with rustls configured by caller,
+    /// for example with client certificate verifier.
+    ///
+    /// Certificates presented by clients are available to handlers
+    /// with `ServerHandlerContext::peer_certificates`.
+    #[cfg(feature = "tls-rustls")]
+    pub fn set_rustls_config(&mut self, config: rustls::ServerConfig) {
+        self.set_tls_acceptor(PeerCertificatesAcceptor(Arc::new(config)));
+    }
+
+    /// Report each completed request to `access_log`,
+    /// sets `ServerConf::access_log`.
+    pub fn set_access_log<L: AccessLog>(&mut self, access_log: L) {
+        self.conf.access_log = Some(Arc::new(access_log));
+    }
+
+    /// Add a layer around `service`.
+    ///
+    /// Middleware added first is the outermost layer,
+    /// it sees requests first and responses last.
+    pub fn add_middleware<M: ServerMiddleware>(&mut self, middleware: M) {
+        self.middleware.push(Arc::new(middleware));
     }
 
     pub fn build(self) -> Result<Server> {
@@ -164,26 +296,35 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
         let join = if let Some(remote) = self.event_loop {
             let tls = self.tls;
             let conf = self.conf;
-            let service = self.service;
+            let service = WithMiddleware {
+                middleware: self.middleware,
+                handler: self.service,
+            };
             let conn_event_loops = self.conn_event_loops;
             let handle = remote.clone();
             remote.spawn(spawn_server_event_loop(
                 handle.clone(),
                 conn_event_loops,
                 state_copy,
-                tls,
-                listen,
+                ServerLoopListen {
+                    listen,
+                    tls,
+                    conf,
+                    service,
+                },
                 shutdown_future,
-                conf,
-                service,
                 alive_tx,
             ));
             Completion::Rx(done_rx)
         } else {
             let tls = self.tls;
             let conf = self.conf;
-            let service = self.service;
+            let service = WithMiddleware {
+                middleware: self.middleware,
+                handler: self.service,
+            };
             let conn_event_loops = self.conn_event_loops;
+            let (exit_tx, exit_rx) = oneshot::channel();
             let join_handle = thread::Builder::new()
                 .name(
                     conf.thread_name
@@ -193,27 +334,37 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
                 )
                 .spawn(move || {
                     let mut lp = Runtime::new().expect("http2server");
-                    lp.block_on(
-                        spawn_server_event_loop(
-                            lp.handle().clone(),
-                            conn_event_loops,
-                            state_copy,
-                            tls,
+                    let accept_done = spawn_server_event_loop(
+                        lp.handle().clone(),
+                        conn_event_loops,
+                        state_copy.clone(),
+                        ServerLoopListen {
                             listen,
-                            shutdown_future,
+                            tls,
                             conf,
                             service,
-                            alive_tx,
-                        )
-                        .map(|_| ()),
+                        },
+                        shutdown_future,
+                        alive_tx,
                     );
+                    let state = state_copy.clone();
+                    let run = async move {
+                        let _ = accept_done.await;
+                        // Keep serving accepted connections, see `ShutdownHandle`
+                        let closed = state.lock().expect("lock").wait_closed();
+                        let _ = closed.await;
+                    };
+                    lp.block_on(future::select(Box::pin(run), exit_rx));
+                    drop(lp);
+                    // Connections are dropped with the event loop
+                    state_copy.lock().expect("lock").clear();
                 })?;
-            Completion::Thread(join_handle)
+            Completion::Thread(join_handle, exit_tx)
         };
 
         Ok(Server {
             state: state,
-            shutdown: shutdown_signal,
+            shutdown: Arc::new(shutdown_signal),
             local_addr: local_addr,
             join: Some(join),
             alive_rx: alive_rx,
@@ -222,14 +373,15 @@ impl<A: tls_api::TlsAcceptor> ServerBuilder<A> {
 }
 
 enum Completion {
-    Thread(thread::JoinHandle<()>),
+    /// Server thread exits when connections are closed or when signalled.
+    Thread(thread::JoinHandle<()>, oneshot::Sender<()>),
     Rx(oneshot::Receiver<()>),
 }
 
 pub struct Server {
     state: Arc<Mutex<ServerState>>,
     local_addr: AnySocketAddr,
-    shutdown: ShutdownSignal,
+    shutdown: Arc<ShutdownSignal>,
     alive_rx: mpsc::Receiver<()>,
     join: Option<Completion>,
 }
@@ -243,19 +395,42 @@ impl fmt::Debug for Server {
 }
 
 #[derive(Default)]
-struct ServerState {
-    last_conn_id: u64,
-    conns: HashMap<u64, ServerConn>,
+pub(crate) struct ServerState {
+    conns: HashMap<ConnId, ServerConn>,
+    /// Notified when there are no connections, see `ShutdownHandle::drain`.
+    closed_waiters: Vec<oneshot::Sender<()>>,
 }
 
 impl ServerState {
+    /// Future resolved when there are no connections.
+    fn wait_closed(&mut self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.closed_waiters.push(tx);
+        self.notify_if_closed();
+        rx
+    }
+
+    fn notify_if_closed(&mut self) {
+        if self.conns.is_empty() {
+            for tx in self.closed_waiters.drain(..) {
+                let _ = tx.send(());
+            }
+        }
+    }
+
+    /// Forget connections which are no longer running.
+    fn clear(&mut self) {
+        self.conns.clear();
+        self.notify_if_closed();
+    }
+
     fn snapshot(&self) -> HttpFutureSend<ServerStateSnapshot> {
         let futures: Vec<_> = self
             .conns
             .iter()
             .map(|(&id, conn)| {
                 assert_send_future::<result::Result<_>, _>(
-                    conn.dump_state().map_ok(move |state| (id, state)),
+                    conn.dump_state().map_ok(move |state| (id.get(), state)),
                 )
             })
             .collect();
@@ -271,6 +446,7 @@ impl ServerState {
 
 #[derive(Debug)]
 pub struct ServerStateSnapshot {
+    /// Connections by `ConnId::get`.
     pub conns: HashMap<u64, ConnStateSnapshot>,
 }
 
@@ -283,21 +459,33 @@ impl ServerStateSnapshot {
     }
 }
 
-fn spawn_server_event_loop<S, A>(
+/// Listener and settings of accepted connections,
+/// see `spawn_server_event_loop`.
+struct ServerLoopListen<S> {
+    listen: Box<dyn ToTokioListener + Send>,
+    tls: ServerTlsOption,
+    conf: ServerConf,
+    service: S,
+}
+
+fn spawn_server_event_loop<S>(
     handle: Handle,
     mut conn_handles: Vec<Handle>,
     state: Arc<Mutex<ServerState>>,
-    tls: ServerTlsOption<A>,
-    listen: Box<dyn ToTokioListener + Send>,
+    listen: ServerLoopListen<S>,
     shutdown_future: ShutdownFuture,
-    conf: ServerConf,
-    service: S,
     _alive_tx: mpsc::Sender<()>,
 ) -> oneshot::Receiver<()>
 where
     S: ServerHandler,
-    A: TlsAcceptor,
 {
+    let ServerLoopListen {
+        listen,
+        tls,
+        conf,
+        service,
+    } = listen;
+
     let service = Arc::new(service);
 
     let tokio_listener = listen.to_tokio_listener(&handle);
@@ -334,14 +522,13 @@ where
                     let (conn, future) =
                         ServerConn::new(&handle_clone, socket, peer_addr, tls, conf, service);
 
-                    let conn_id = {
+                    let conn_id = conn.conn_id();
+
+                    {
                         let mut g = state.lock().expect("lock");
-                        g.last_conn_id += 1;
-                        let conn_id = g.last_conn_id;
                         let prev = g.conns.insert(conn_id, conn);
                         assert!(prev.is_none());
-                        conn_id
-                    };
+                    }
 
                     let future = assert_send_future::<result::Result<()>, _>(future);
 
@@ -349,10 +536,11 @@ where
                         let mut g = state.lock().expect("lock");
                         let removed = g.conns.remove(&conn_id);
                         assert!(removed.is_some());
+                        g.notify_if_closed();
                         future::ready(r)
                     })
-                    .map_err(|e| {
-                        warn!("connection end: {:?}", e);
+                    .map_err(move |e| {
+                        warn!("connection {} end: {:?}", conn_id, e);
                         ()
                     })
                 });
@@ -389,15 +577,64 @@ impl Server {
         &self.local_addr
     }
 
+    /// Handle to stop accepting connections and close existing ones,
+    /// usable after the server is dropped.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            shutdown: self.shutdown.clone(),
+            state: self.state.clone(),
+        }
+    }
+
     pub fn is_alive(&self) -> bool {
         self.alive_rx.try_recv() != Err(mpsc::TryRecvError::Disconnected)
     }
 
+    /// Write a pre-built frame to connection `conn_id`.
+    ///
+    /// This is an escape hatch for protocol experiments and conformance
+    /// testing: the frame bypasses stream state, flow control and HPACK
+    /// encoder, so peer may treat it as a protocol error.
+    pub fn send_raw_frame(&self, conn_id: ConnId, frame: HttpFrame) -> Result<()> {
+        let g = self.state.lock().expect("lock");
+        match g.conns.get(&conn_id) {
+            Some(conn) => conn.send_raw_frame(frame),
+            None => Err(Error::ConnDied),
+        }
+    }
+
     // for tests
     pub fn dump_state(&self) -> HttpFutureSend<ServerStateSnapshot> {
         let g = self.state.lock().expect("lock");
         g.snapshot()
     }
+
+    /// Serve a single HTTP/2 connection over an already established stream,
+    /// for example QUIC stream, SSH channel or custom tunnel.
+    ///
+    /// No TLS or protocol negotiation is performed. Returned future must be
+    /// polled within tokio runtime and resolves when the connection is closed,
+    /// with an error if it was closed by the peer or because of protocol error.
+    pub fn serve_connection<I, S>(io: I, conf: ServerConf, service: S) -> HttpFutureSend<()>
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+        S: ServerHandler,
+    {
+        Box::pin(async move {
+            let (conn, future) = ServerConn::new(
+                &Handle::current(),
+                Box::pin(IoStream(io)),
+                AnySocketAddr::Io,
+                ServerTlsOption::Plain,
+                conf,
+                Arc::new(service),
+            );
+            let r = future.await;
+            // connection is closed when handle is dropped
+            drop(conn);
+            r
+        })
+    }
 }
 
 // We shutdown the server in the destructor.
@@ -408,7 +645,10 @@ impl Drop for Server {
         // do not ignore errors of take
         // ignore errors of join, it means that server event loop crashed
         match self.join.take().unwrap() {
-            Completion::Thread(join) => drop(join.join()),
+            Completion::Thread(join, exit) => {
+                let _ = exit.send(());
+                drop(join.join());
+            }
             Completion::Rx(_rx) => {
                 // cannot wait on _rx, because Core might not be running
             }
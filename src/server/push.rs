@@ -0,0 +1,41 @@
+//! Server push (RFC 7540 section 8.2).
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::common::window_size::StreamOutWindowSender;
+use crate::solicit::stream_id::StreamId;
+use crate::Headers;
+
+/// Allows a response to promise pushed streams.
+#[derive(Clone)]
+pub(crate) struct PushContext {
+    /// Id of the next pushed stream of the connection.
+    ///
+    /// Locked until `PUSH_PROMISE` is enqueued, so promises
+    /// are sent in stream id order.
+    pub next_stream_id: Arc<Mutex<StreamId>>,
+    /// `:scheme` of the request
+    pub scheme: String,
+    /// `:authority` of the request
+    pub authority: String,
+}
+
+impl PushContext {
+    /// Headers of a promised `GET` request.
+    pub fn promised_request(&self, path: &str, headers: Headers) -> Headers {
+        let mut promised = Headers::new_get(path.to_owned());
+        promised.add(":scheme", self.scheme.clone());
+        promised.add(":authority", self.authority.clone());
+        promised.extend(headers);
+        promised
+    }
+}
+
+/// Request to send `PUSH_PROMISE` and open the promised stream.
+pub(crate) struct PushPromise {
+    pub stream_id: StreamId,
+    pub promised_stream_id: StreamId,
+    pub headers: Headers,
+    pub out_window: StreamOutWindowSender,
+}
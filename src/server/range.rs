@@ -0,0 +1,189 @@
+//! `Range` request header support (RFC 7233).
+
+use crate::Headers;
+
+/// Satisfiable byte range, both bounds inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// First byte position.
+    pub start: u64,
+    /// Last byte position (inclusive).
+    pub end: u64,
+}
+
+/// `Range` header cannot be satisfied, response must be `416`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeNotSatisfiable;
+
+impl ByteRange {
+    /// Parse `Range` header value for content of given length.
+    ///
+    /// Return `Ok(None)` if the whole content should be served: header
+    /// uses unknown unit, is malformed or requests multiple ranges
+    /// (which are not supported).
+    pub fn parse(range: &str, content_len: u64) -> Result<Option<ByteRange>, RangeNotSatisfiable> {
+        let spec = match range.trim().strip_prefix("bytes=") {
+            Some(spec) => spec.trim(),
+            None => return Ok(None),
+        };
+        if spec.contains(',') {
+            return Ok(None);
+        }
+        let (first, last) = match spec.find('-') {
+            Some(pos) => (spec[..pos].trim(), spec[pos + 1..].trim()),
+            None => return Ok(None),
+        };
+
+        let (start, end) = if first.is_empty() {
+            // suffix-byte-range-spec
+            let suffix_len: u64 = match last.parse() {
+                Ok(suffix_len) => suffix_len,
+                Err(_) => return Ok(None),
+            };
+            if suffix_len == 0 || content_len == 0 {
+                return Err(RangeNotSatisfiable);
+            }
+            (content_len.saturating_sub(suffix_len), content_len - 1)
+        } else {
+            let start: u64 = match first.parse() {
+                Ok(start) => start,
+                Err(_) => return Ok(None),
+            };
+            let end = if last.is_empty() {
+                u64::MAX
+            } else {
+                match last.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return Ok(None),
+                }
+            };
+            if start >= content_len {
+                return Err(RangeNotSatisfiable);
+            }
+            (start, end.min(content_len - 1))
+        };
+
+        Ok(Some(ByteRange { start, end }))
+    }
+
+    /// Evaluate `Range` and `If-Range` request headers.
+    ///
+    /// `etag` and `last_modified` are validators of the current
+    /// representation; if `If-Range` does not match them, the whole
+    /// content is served.
+    pub fn from_request(
+        req: &Headers,
+        content_len: u64,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<Option<ByteRange>, RangeNotSatisfiable> {
+        let range = match req.get_opt("range") {
+            Some(range) => range,
+            None => return Ok(None),
+        };
+        if let Some(if_range) = req.get_opt("if-range") {
+            if !if_range_matches(if_range.trim(), etag, last_modified) {
+                return Ok(None);
+            }
+        }
+        ByteRange::parse(range, content_len)
+    }
+
+    /// Number of bytes in the range.
+    pub fn byte_count(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// `content-range` header value for this range.
+    pub fn content_range(&self, content_len: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, content_len)
+    }
+
+    /// `:status 206` headers with `content-range` and `content-length`.
+    pub fn partial_content_206(&self, content_len: u64) -> Headers {
+        let mut headers = Headers::new_status(206);
+        headers.add("content-range", self.content_range(content_len));
+        headers.add("content-length", format!("{}", self.byte_count()));
+        headers
+    }
+}
+
+impl RangeNotSatisfiable {
+    /// `:status 416` headers with `content-range` of unsatisfied range.
+    pub fn range_not_satisfiable_416(&self, content_len: u64) -> Headers {
+        let mut headers = Headers::new_status(416);
+        headers.add("content-range", format!("bytes */{}", content_len));
+        headers
+    }
+}
+
+/// `If-Range` matches if it is a strong entity tag equal to `etag`,
+/// or a date equal to `last_modified`.
+fn if_range_matches(if_range: &str, etag: Option<&str>, last_modified: Option<&str>) -> bool {
+    if if_range.starts_with('"') {
+        match etag {
+            Some(etag) => etag == if_range,
+            None => false,
+        }
+    } else if if_range.starts_with("W/") {
+        // weak validators must not be used in `If-Range`
+        false
+    } else {
+        match last_modified {
+            Some(last_modified) => last_modified == if_range,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let r = |start, end| Ok(Some(ByteRange { start, end }));
+        assert_eq!(r(0, 9), ByteRange::parse("bytes=0-9", 100));
+        assert_eq!(r(10, 99), ByteRange::parse("bytes=10-", 100));
+        assert_eq!(r(10, 99), ByteRange::parse("bytes=10-1000", 100));
+        assert_eq!(r(90, 99), ByteRange::parse("bytes=-10", 100));
+        assert_eq!(r(0, 99), ByteRange::parse("bytes=-1000", 100));
+        assert_eq!(Ok(None), ByteRange::parse("items=0-9", 100));
+        assert_eq!(Ok(None), ByteRange::parse("bytes=0-9,20-29", 100));
+        assert_eq!(Ok(None), ByteRange::parse("bytes=9-0", 100));
+        assert_eq!(Ok(None), ByteRange::parse("bytes=x-1", 100));
+        assert_eq!(
+            Err(RangeNotSatisfiable),
+            ByteRange::parse("bytes=100-", 100)
+        );
+        assert_eq!(Err(RangeNotSatisfiable), ByteRange::parse("bytes=-0", 100));
+        assert_eq!(Err(RangeNotSatisfiable), ByteRange::parse("bytes=0-", 0));
+    }
+
+    #[test]
+    fn if_range() {
+        let mut req = Headers::new_get("/");
+        req.add("range", "bytes=0-9");
+        req.add("if-range", "\"abc\"");
+        assert_eq!(
+            Ok(Some(ByteRange { start: 0, end: 9 })),
+            ByteRange::from_request(&req, 100, Some("\"abc\""), None)
+        );
+        assert_eq!(
+            Ok(None),
+            ByteRange::from_request(&req, 100, Some("\"def\""), None)
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let range = ByteRange { start: 10, end: 19 };
+        let headers = range.partial_content_206(100);
+        assert_eq!(206, headers.status());
+        assert_eq!("bytes 10-19/100", headers.get("content-range"));
+        assert_eq!(Some(10), headers.content_length());
+        let headers = RangeNotSatisfiable.range_not_satisfiable_416(100);
+        assert_eq!(416, headers.status());
+        assert_eq!("bytes */100", headers.get("content-range"));
+    }
+}
@@ -1,13 +1,21 @@
+use std::sync::Arc;
+
+use crate::bytes_ext::bytes_deque::BytesDeque;
 use crate::common::conn_command_channel::ConnCommandSender;
 use crate::common::increase_in_window::IncreaseInWindow;
 use crate::common::stream_from_network::StreamFromNetwork;
 use crate::common::stream_queue_sync::stream_queue_sync;
 use crate::server::increase_in_window::ServerIncreaseInWindow;
+use crate::server::router::PathParams;
 use crate::server::stream_handler::ServerRequestStreamHandler;
 use crate::server::stream_handler::ServerRequestStreamHandlerHolder;
 use crate::server::types::ServerTypes;
+use crate::solicit_async::HttpFutureSend;
+use crate::tls::TlsSessionInfo;
+use crate::AnySocketAddr;
 use crate::Headers;
 use crate::HttpStreamAfterHeaders;
+use crate::SimpleHttpMessage;
 use crate::StreamId;
 
 pub struct ServerRequest<'a> {
@@ -15,6 +23,11 @@ pub struct ServerRequest<'a> {
     pub headers: Headers,
     /// True if requests ends with headers
     pub end_stream: bool,
+    /// Path parameters captured by `Router`, empty if request is not routed by it
+    pub params: PathParams,
+    pub(crate) peer_addr: AnySocketAddr,
+    pub(crate) local_addr: AnySocketAddr,
+    pub(crate) tls_session: Option<Arc<TlsSessionInfo>>,
     pub(crate) stream_id: StreamId,
     /// Stream in window size at the moment of request start
     pub(crate) in_window_size: u32,
@@ -23,10 +36,26 @@ pub struct ServerRequest<'a> {
 }
 
 impl<'a> ServerRequest<'a> {
+    /// Address of the peer which sent the request.
+    pub fn peer_addr(&self) -> &AnySocketAddr {
+        &self.peer_addr
+    }
+
+    /// Local address of the connection request is received on.
+    pub fn local_addr(&self) -> &AnySocketAddr {
+        &self.local_addr
+    }
+
+    /// Parameters negotiated during TLS handshake, `None` for plain connections.
+    pub fn tls_session(&self) -> Option<&TlsSessionInfo> {
+        self.tls_session.as_deref()
+    }
+
     pub fn make_stream(self) -> HttpStreamAfterHeaders {
         if self.end_stream {
             HttpStreamAfterHeaders::empty()
         } else {
+            let content_length = self.headers.content_length();
             self.register_stream_handler(|increase_in_window| {
                 let (inc_tx, inc_rx) = stream_queue_sync();
                 let stream_from_network = StreamFromNetwork {
@@ -34,14 +63,30 @@ impl<'a> ServerRequest<'a> {
                     increase_in_window: increase_in_window.0,
                 };
 
+                let reset = stream_from_network.reset_fn();
                 (
                     inc_tx,
-                    HttpStreamAfterHeaders::from_parts(stream_from_network),
+                    HttpStreamAfterHeaders::from_parts(stream_from_network)
+                        .with_content_length(content_length)
+                        .with_reset_fn(reset),
                 )
             })
         }
     }
 
+    /// Collect request headers, body and trailers.
+    ///
+    /// Fail with `Error::BodyLimitExceeded` and reset the stream
+    /// if request body is larger than `max_bytes`.
+    pub fn collect_with_limit(self, max_bytes: usize) -> HttpFutureSend<SimpleHttpMessage> {
+        let message = SimpleHttpMessage {
+            headers: self.headers.clone(),
+            body: BytesDeque::new(),
+        };
+        let stream = self.make_stream();
+        Box::pin(stream.collect_with_limit_into(message, max_bytes))
+    }
+
     /// Register synchnous stream handler (callback will be called immediately
     /// when new data arrives). Note that increasing in window size is the handler
     /// responsibility.
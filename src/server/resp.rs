@@ -1,8 +1,17 @@
 use crate::assert_types::assert_send;
 use crate::common::sender::CommonSender;
 use crate::common::sender::SendError;
+use crate::error;
+use crate::futures_misc::read_stream::ReadStream;
 
+use crate::headers_place::HeadersPlace;
+use crate::req_resp::RequestOrResponse;
 use crate::result;
+use crate::server::conn::ServerToWriteMessage;
+use crate::server::date::date_header_value;
+use crate::server::push::PushContext;
+use crate::server::push::PushPromise;
+use crate::server::range::ByteRange;
 use crate::server::types::ServerTypes;
 use crate::ErrorCode;
 use crate::Headers;
@@ -11,10 +20,18 @@ use crate::SenderState;
 use crate::SimpleHttpMessage;
 use crate::StreamDead;
 use bytes::Bytes;
+use futures::future::TryFutureExt;
 use futures::stream::Stream;
 use futures::task::Context;
+use std::io::SeekFrom;
 use std::mem;
+use std::path::Path;
+use std::sync::Arc;
 use std::task::Poll;
+use tokio::io::AsyncReadExt;
+
+/// See `ServerResponse::add_headers_filter`.
+pub(crate) type HeadersFilter = Arc<dyn Fn(&mut Headers) + Send + Sync>;
 
 // NOTE: Keep in sync with ClientRequest
 pub struct ServerResponse {
@@ -22,6 +39,14 @@ pub struct ServerResponse {
     // need to replace with FnOnce when rust allows it
     pub(crate) drop_callback:
         Option<Box<dyn FnMut(&mut ServerResponse) -> result::Result<()> + Send>>,
+    pub(crate) default_headers: Option<Arc<Headers>>,
+    pub(crate) send_date: bool,
+    /// Applied to final response headers, last added first.
+    pub(crate) headers_filters: Vec<HeadersFilter>,
+    /// `None` for pushed responses.
+    pub(crate) push: Option<PushContext>,
+    /// Peer `SETTINGS_MAX_FRAME_SIZE` when the stream was opened.
+    pub(crate) peer_max_frame_size: u32,
 }
 
 impl Drop for ServerResponse {
@@ -64,11 +89,140 @@ impl ServerResponse {
         self.common.poll(cx)
     }
 
+    /// Declare how many bytes the caller wants to send.
+    ///
+    /// Capacity is reduced by the amount of data sent.
+    pub fn reserve_capacity(&mut self, capacity: usize) {
+        self.common.reserve_capacity(capacity)
+    }
+
+    /// Number of bytes which can be sent now without exceeding
+    /// stream or connection window, at most reserved capacity.
+    pub fn capacity(&self) -> usize {
+        self.common.capacity()
+    }
+
+    /// Wait until some capacity is available.
+    ///
+    /// Returns the number of bytes (at most reserved capacity)
+    /// which can be sent without exceeding stream or connection window.
+    /// Returns zero if no capacity is reserved.
+    /// Fails if the stream is ended, reset or closed by the peer.
+    ///
+    /// Connection window is shared between streams, so the capacity
+    /// is only a hint when several streams send data concurrently.
+    pub fn poll_capacity(&mut self, cx: &mut Context<'_>) -> Poll<Result<usize, StreamDead>> {
+        self.common.poll_capacity(cx)
+    }
+
+    /// Add headers to the response, replacing default headers with the same names.
+    ///
+    /// Default headers are initialized from `ServerConf::default_headers`
+    /// and added to response headers unless the handler sets
+    /// headers with the same name.
+    pub fn add_default_headers(&mut self, headers: &Headers) {
+        let defaults = Arc::make_mut(self.default_headers.get_or_insert_with(Default::default));
+        for header in headers.iter() {
+            defaults.remove(header.name());
+        }
+        for header in headers.iter() {
+            defaults.add_header(header.clone());
+        }
+    }
+
+    /// Modify final response headers before they are sent,
+    /// e. g. by middleware to add headers to every response.
+    ///
+    /// Filters are applied after default headers are added,
+    /// filter added last is applied first. Filters are inherited
+    /// by pushed responses created after the filter is added.
+    pub fn add_headers_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&mut Headers) + Send + Sync + 'static,
+    {
+        self.headers_filters.push(Arc::new(filter));
+    }
+
+    fn with_default_headers(&self, mut headers: Headers) -> Headers {
+        if headers.is_informational() {
+            return headers;
+        }
+        if let Some(defaults) = &self.default_headers {
+            headers.add_missing(defaults);
+        }
+        if self.send_date && headers.get_opt("date").is_none() {
+            headers.add("date", date_header_value());
+        }
+        for filter in self.headers_filters.iter().rev() {
+            filter(&mut headers);
+        }
+        headers
+    }
+
+    /// Promise a response to `GET path` request (server push)
+    /// and return the sender of the pushed response.
+    ///
+    /// `:scheme` and `:authority` of the promised request are copied from
+    /// the request this response is for, `headers` are added to it.
+    /// Push must be made before this response is finished.
+    ///
+    /// The promise is not sent if the client disabled push or has
+    /// too many pushed streams, then the pushed response is discarded.
+    pub fn push(&mut self, path: &str, headers: Headers) -> Result<ServerResponse, SendError> {
+        let push = match &self.push {
+            Some(push) => push.clone(),
+            None => return Err(SendError::PushNotAllowed),
+        };
+        let headers = push.promised_request(path, headers);
+
+        let mut next_stream_id = push.next_stream_id.lock().unwrap();
+        let promised_stream_id = *next_stream_id;
+        let (common, out_window) = self.common.new_stream(promised_stream_id)?;
+        self.common
+            .send_message(ServerToWriteMessage::Push(PushPromise {
+                stream_id: self.common.stream_id(),
+                promised_stream_id,
+                headers,
+                out_window,
+            }))?;
+        *next_stream_id += 2;
+        drop(next_stream_id);
+
+        Ok(ServerResponse {
+            common,
+            drop_callback: None,
+            default_headers: self.default_headers.clone(),
+            send_date: self.send_date,
+            headers_filters: self.headers_filters.clone(),
+            push: None,
+            peer_max_frame_size: self.peer_max_frame_size,
+        })
+    }
+
+    /// Send informational (`1xx`) response, e. g. `103 Early Hints`,
+    /// before final response headers.
+    ///
+    /// Can be called several times. `:status` header is added to `headers`,
+    /// default headers are not. Status `101` is not allowed in HTTP/2.
+    pub fn send_informational(&mut self, status: u32, headers: Headers) -> Result<(), SendError> {
+        if !(100..=199).contains(&status) || status == 101 {
+            return Err(SendError::NotInformationalStatus(status));
+        }
+        let mut informational = Headers::new_status(status);
+        informational.extend(headers);
+        informational
+            .validate(RequestOrResponse::Response, HeadersPlace::Initial)
+            .map_err(SendError::InvalidHeaders)?;
+        self.common.send_informational_headers(informational)
+    }
+
     pub fn send_headers(&mut self, headers: Headers) -> Result<(), SendError> {
+        let headers = self.with_default_headers(headers);
         self.common.send_headers(headers)
     }
 
     pub fn send_headers_end_of_stream(&mut self, headers: Headers) -> Result<(), SendError> {
+        let headers = self.with_default_headers(headers);
         self.common.send_headers_end_of_stream(headers)
     }
 
@@ -76,10 +230,20 @@ impl ServerResponse {
         self.common.send_data(data)
     }
 
+    /// Send last `DATA` frame
+    ///
+    /// `END_STREAM` flag is set on the frame carrying the last chunk of `data`,
+    /// no separate empty `DATA` frame is sent.
     pub fn send_data_end_of_stream(&mut self, data: Bytes) -> Result<(), SendError> {
         self.common.send_data_end_of_stream(data)
     }
 
+    /// Send trailing headers and finish the response.
+    ///
+    /// Trailers are sent in a `HEADERS` frame with `END_STREAM` flag
+    /// after all data enqueued before, even if the data waits for
+    /// flow control window. Client receives them as the last
+    /// `DataOrTrailers::Trailers` item of the response stream.
     pub fn send_trailers(&mut self, trailers: Headers) -> Result<(), SendError> {
         self.common.send_trailers(trailers)
     }
@@ -95,9 +259,48 @@ impl ServerResponse {
         self.common.pull_bytes_from_stream(stream)
     }
 
+    /// Send `range` of file at `path` (whole file if `None`) as response body
+    /// and finish the response; headers must be sent before.
+    ///
+    /// The file is opened asynchronously, so this function does not block,
+    /// and the stream is reset if the file cannot be opened or read.
+    /// File is read in chunks not larger than peer max frame size,
+    /// and only when flow control windows allow to send data.
+    ///
+    /// Data is copied through userspace: `sendfile` and `copy_file_range`
+    /// cannot be used, because `DATA` frames are framed and possibly encrypted
+    /// by the connection writer.
+    pub fn send_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        range: Option<ByteRange>,
+    ) -> result::Result<()> {
+        let path = path.as_ref().to_owned();
+        let chunk_size = self.peer_max_frame_size as usize;
+        let file = async move {
+            let mut file = tokio::fs::File::open(path).await?;
+            let len = match range {
+                Some(range) => {
+                    file.seek(SeekFrom::Start(range.start)).await?;
+                    range.byte_count()
+                }
+                None => file.metadata().await?.len(),
+            };
+            Ok::<_, error::Error>(ReadStream::new(file.take(len), chunk_size))
+        };
+        self.pull_bytes_from_stream(file.try_flatten_stream())?;
+        Ok(())
+    }
+
     pub fn send_message(&mut self, message: SimpleHttpMessage) -> Result<(), SendError> {
-        self.send_headers(message.headers)?;
-        self.send_data_end_of_stream(message.body.into_bytes())?;
+        let body = message.body.into_bytes();
+        if body.is_empty() {
+            // Do not send empty `DATA` frame just to close the stream
+            self.send_headers_end_of_stream(message.headers)?;
+        } else {
+            self.send_headers(message.headers)?;
+            self.send_data_end_of_stream(body)?;
+        }
         Ok(())
     }
 
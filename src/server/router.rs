@@ -0,0 +1,281 @@
+//! Dispatching requests by method and path pattern.
+
+use std::sync::Arc;
+
+use crate::context::percent_decode;
+use crate::result;
+use crate::server::handler::ServerHandler;
+use crate::server::handler::ServerHandlerContext;
+use crate::server::req::ServerRequest;
+use crate::Headers;
+use crate::Method;
+use crate::ServerResponse;
+
+/// Path parameters captured by `Router`, available as `ServerRequest::params`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathParams {
+    params: Vec<(String, String)>,
+}
+
+impl PathParams {
+    /// Percent-decoded value of parameter `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    /// Parameters in path order, outer mounts first.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(n, v)| (&n[..], &v[..]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    /// `{name}`, matches one segment.
+    Param(String),
+    /// `{*name}`, matches remaining segments, possibly none.
+    Rest(String),
+}
+
+#[derive(Debug)]
+struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Pattern {
+        assert!(pattern.starts_with('/'), "pattern must start with /");
+        let segments: Vec<Segment> = path_segments(pattern)
+            .map(|s| {
+                if s.starts_with('{') && s.ends_with('}') {
+                    let name = &s[1..s.len() - 1];
+                    match name.strip_prefix('*') {
+                        Some(name) => Segment::Rest(name.to_owned()),
+                        None => Segment::Param(name.to_owned()),
+                    }
+                } else {
+                    Segment::Literal(s.to_owned())
+                }
+            })
+            .collect();
+        let rest = segments.iter().position(|s| matches!(s, Segment::Rest(..)));
+        assert!(
+            rest.is_none_or(|i| i == segments.len() - 1),
+            "{{*..}} must be the last segment of pattern {}",
+            pattern
+        );
+        Pattern { segments }
+    }
+
+    /// Match pattern against the start of `path` segments,
+    /// return the number of matched segments.
+    fn match_prefix(&self, path: &[&str], params: &mut Vec<(String, String)>) -> Option<usize> {
+        let mut i = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => {
+                    if path.get(i) != Some(&&literal[..]) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.push((name.clone(), percent_decode(path.get(i)?)));
+                }
+                Segment::Rest(name) => {
+                    params.push((name.clone(), percent_decode(&path[i..].join("/"))));
+                    return Some(path.len());
+                }
+            }
+            i += 1;
+        }
+        Some(i)
+    }
+}
+
+/// Non-empty segments of path without query.
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    let path = path.split('?').next().unwrap();
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+enum Entry {
+    Route(Method, Pattern, Arc<dyn ServerHandler>),
+    Mount(Pattern, Router),
+}
+
+/// `ServerHandler` dispatching requests by method and path pattern.
+///
+/// Pattern segments are literals, `{name}` matching any single segment,
+/// or `{*name}` as the last segment matching the rest of the path.
+/// Captured values are available to the handler as `ServerRequest::params`.
+///
+/// Routes and mounts are tried in the order they were added.
+/// Requests not matching any pattern are answered with `404`,
+/// and requests matching only patterns with other methods with `405`.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use httpbis::*;
+/// let mut users = Router::new();
+/// users.add_route_fn(Method::GET, "/{id}", |_context, req, mut resp| {
+///     let id = req.params.get("id").unwrap().to_owned();
+///     resp.send_found_200_plain_text(&format!("user {}", id))?;
+///     Ok(())
+/// });
+///
+/// let mut router = Router::new();
+/// router.mount("/users", users);
+///
+/// let mut server = ServerBuilder::new_plain();
+/// server.service.set_service("/", Arc::new(router));
+/// ```
+#[derive(Default)]
+pub struct Router {
+    entries: Vec<Entry>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// Register handler for requests with `method` and path matching `pattern`.
+    pub fn add_route(&mut self, method: Method, pattern: &str, handler: Arc<dyn ServerHandler>) {
+        self.entries
+            .push(Entry::Route(method, Pattern::parse(pattern), handler));
+    }
+
+    pub fn add_route_fn<F>(&mut self, method: Method, pattern: &str, handler: F)
+    where
+        F: Fn(ServerHandlerContext, ServerRequest, ServerResponse) -> result::Result<()>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.add_route(method, pattern, Arc::new(handler));
+    }
+
+    /// Dispatch requests with path starting with `prefix` to `router`,
+    /// which matches its patterns against the rest of the path.
+    ///
+    /// `prefix` may capture parameters too.
+    pub fn mount(&mut self, prefix: &str, router: Router) {
+        self.entries
+            .push(Entry::Mount(Pattern::parse(prefix), router));
+    }
+
+    /// Find handler for `path` segments, `allowed` collects methods
+    /// of routes matching the path.
+    fn find<'a>(
+        &'a self,
+        method: &str,
+        path: &[&str],
+        params: &mut Vec<(String, String)>,
+        allowed: &mut Vec<&'a Method>,
+    ) -> Option<&'a dyn ServerHandler> {
+        for entry in &self.entries {
+            let params_len = params.len();
+            match entry {
+                Entry::Route(route_method, pattern, handler) => {
+                    if pattern.match_prefix(path, params) == Some(path.len()) {
+                        if route_method.as_str() == method {
+                            return Some(&**handler);
+                        }
+                        allowed.push(route_method);
+                    }
+                }
+                Entry::Mount(prefix, router) => {
+                    if let Some(matched) = prefix.match_prefix(path, params) {
+                        if let Some(handler) =
+                            router.find(method, &path[matched..], params, allowed)
+                        {
+                            return Some(handler);
+                        }
+                    }
+                }
+            }
+            params.truncate(params_len);
+        }
+        None
+    }
+}
+
+impl ServerHandler for Router {
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        mut req: ServerRequest,
+        mut resp: ServerResponse,
+    ) -> result::Result<()> {
+        let path: Vec<&str> = path_segments(req.headers.path()).collect();
+        let mut params = Vec::new();
+        let mut allowed = Vec::new();
+        match self.find(req.headers.method(), &path, &mut params, &mut allowed) {
+            Some(handler) => {
+                req.params.params.extend(params);
+                handler.start_request(context, req, resp)
+            }
+            None if !allowed.is_empty() => {
+                let allowed: Vec<&str> = allowed.iter().map(|m| m.as_str()).collect();
+                let mut headers = Headers::new_status(405);
+                headers.add("allow", allowed.join(", "));
+                resp.send_headers_end_of_stream(headers)?;
+                Ok(())
+            }
+            None => {
+                resp.send_not_found_404("not found")?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn match_path(pattern: &str, path: &str) -> Option<(usize, Vec<(String, String)>)> {
+        let path: Vec<&str> = path_segments(path).collect();
+        let mut params = Vec::new();
+        let matched = Pattern::parse(pattern).match_prefix(&path, &mut params)?;
+        Some((matched, params))
+    }
+
+    fn param(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn pattern() {
+        assert_eq!(Some((0, vec![])), match_path("/", "/a"));
+        assert_eq!(Some((1, vec![])), match_path("/a", "/a/b?c=d"));
+        assert_eq!(None, match_path("/a/b", "/a"));
+        assert_eq!(
+            Some((2, vec![param("id", "4 2")])),
+            match_path("/users/{id}", "/users/4%202")
+        );
+        assert_eq!(
+            Some((3, vec![param("path", "b/c")])),
+            match_path("/a/{*path}", "/a/b/c")
+        );
+        assert_eq!(
+            Some((1, vec![param("path", "")])),
+            match_path("/a/{*path}", "/a")
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rest_not_last() {
+        Pattern::parse("/{*a}/b");
+    }
+}
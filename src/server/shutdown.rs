@@ -0,0 +1,73 @@
+//! Stopping the server independently of dropping `Server`.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::channel::oneshot;
+use tokio::time;
+
+use crate::futures_misc::ShutdownSignal;
+use crate::server::ServerState;
+use crate::solicit_async::HttpFutureSend;
+use crate::ErrorCode;
+
+/// Handle to stop the server, obtained with `Server::shutdown_handle`.
+///
+/// Dropping `Server` stops accepting new connections too, and aborts
+/// accepted connections if the server runs its own event loop
+/// (i. e. `ServerBuilder::event_loop` is not set).
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    pub(crate) shutdown: Arc<ShutdownSignal>,
+    pub(crate) state: Arc<Mutex<ServerState>>,
+}
+
+impl ShutdownHandle {
+    /// Stop accepting new connections, existing connections are not affected.
+    pub fn stop_accepting(&self) {
+        self.shutdown.shutdown();
+    }
+
+    /// Stop accepting new connections and close existing connections
+    /// right away, requests in progress are aborted.
+    pub fn close(&self) {
+        self.stop_accepting();
+        self.send_goaway(false);
+    }
+
+    /// Stop accepting new connections and drain existing ones.
+    ///
+    /// Each connection is sent graceful `GOAWAY` and closed when its streams
+    /// complete. Connections still alive after `deadline` are closed as with
+    /// `close`. Returned future resolves when all connections are closed.
+    pub fn drain(&self, deadline: Duration) -> HttpFutureSend<()> {
+        self.stop_accepting();
+        self.send_goaway(true);
+        let closed = self.wait_closed();
+
+        let handle = self.clone();
+        Box::pin(async move {
+            if time::timeout(deadline, closed).await.is_err() {
+                info!("closing connections not drained in {:?}", deadline);
+                let closed = handle.wait_closed();
+                handle.send_goaway(false);
+                let _ = closed.await;
+            }
+            Ok(())
+        })
+    }
+
+    fn send_goaway(&self, graceful: bool) {
+        let state = self.state.lock().expect("lock");
+        for (conn_id, conn) in &state.conns {
+            if let Err(e) = conn.send_goaway(ErrorCode::NoError, graceful) {
+                debug!("failed to send GOAWAY to conn {}: {:?}", conn_id, e);
+            }
+        }
+    }
+
+    fn wait_closed(&self) -> oneshot::Receiver<()> {
+        self.state.lock().expect("lock").wait_closed()
+    }
+}
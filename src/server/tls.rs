@@ -1,17 +1,9 @@
 use std::sync::Arc;
 
-use tls_api::TlsAcceptor;
+use crate::tls::TlsAcceptorLike;
 
-pub enum ServerTlsOption<A: TlsAcceptor> {
+#[derive(Clone)]
+pub enum ServerTlsOption {
     Plain,
-    Tls(Arc<A>),
-}
-
-impl<A: TlsAcceptor> Clone for ServerTlsOption<A> {
-    fn clone(&self) -> Self {
-        match self {
-            &ServerTlsOption::Plain => ServerTlsOption::Plain,
-            &ServerTlsOption::Tls(ref a) => ServerTlsOption::Tls(a.clone()),
-        }
-    }
+    Tls(Arc<dyn TlsAcceptorLike>),
 }
@@ -7,11 +7,14 @@ use std::net::SocketAddr;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncWrite;
 
+use crate::client::resolver::ResolvingConnector;
+use crate::socket_named_pipe::SocketAddrNamedPipe;
 use crate::socket_unix::SocketAddrUnix;
 use crate::ServerConf;
 use futures::stream::Stream;
 use futures::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use tokio::runtime::Handle;
 
 pub trait ToSocketListener {
@@ -20,10 +23,21 @@ pub trait ToSocketListener {
     fn cleanup(&self);
 }
 
+/// Address client connects to or server listens on.
 #[derive(Clone, Debug, PartialEq)]
 pub enum AnySocketAddr {
     Inet(SocketAddr),
     Unix(SocketAddrUnix),
+    /// Windows named pipe.
+    NamedPipe(SocketAddrNamedPipe),
+    /// In-memory transport, see `mem` module.
+    Mem,
+    /// Stream provided by user, see `Client::new_from_io`
+    /// and `Server::serve_connection`.
+    Io,
+    /// Host name and port, resolved before each connection,
+    /// see `ClientBuilder::set_host`.
+    Host(String, u16),
 }
 
 impl Display for AnySocketAddr {
@@ -31,6 +45,10 @@ impl Display for AnySocketAddr {
         match *self {
             AnySocketAddr::Inet(ref inet_addr) => Display::fmt(inet_addr, f),
             AnySocketAddr::Unix(ref unix_addr) => Display::fmt(unix_addr, f),
+            AnySocketAddr::NamedPipe(ref pipe_addr) => Display::fmt(pipe_addr, f),
+            AnySocketAddr::Mem => write!(f, "mem"),
+            AnySocketAddr::Io => write!(f, "io"),
+            AnySocketAddr::Host(ref host, port) => write!(f, "{}:{}", host, port),
         }
     }
 }
@@ -43,6 +61,14 @@ impl AnySocketAddr {
                 io::ErrorKind::Other,
                 "Cannot get port from unix domain socket",
             )),
+            &AnySocketAddr::NamedPipe(_) => {
+                Err(io::Error::other("Cannot get port from named pipe"))
+            }
+            &AnySocketAddr::Mem => Err(io::Error::other("Cannot get port from in-memory address")),
+            &AnySocketAddr::Io => Err(io::Error::other(
+                "Cannot get port from user-provided stream",
+            )),
+            &AnySocketAddr::Host(_, port) => Ok(port),
         }
     }
 }
@@ -52,6 +78,10 @@ impl ToSocketListener for AnySocketAddr {
         match self {
             &AnySocketAddr::Inet(ref inet_addr) => inet_addr.to_listener(conf),
             &AnySocketAddr::Unix(ref unix_addr) => unix_addr.to_listener(conf),
+            &AnySocketAddr::NamedPipe(ref pipe_addr) => pipe_addr.to_listener(conf),
+            &AnySocketAddr::Mem => Err(io::Error::other("Cannot listen on in-memory address")),
+            &AnySocketAddr::Io => Err(io::Error::other("Cannot listen on user-provided stream")),
+            &AnySocketAddr::Host(..) => Err(io::Error::other("Cannot listen on host name")),
         }
     }
 
@@ -62,6 +92,10 @@ impl ToSocketListener for AnySocketAddr {
             &AnySocketAddr::Unix(ref unix_addr) => unix_addr.cleanup(),
             #[cfg(not(unix))]
             &AnySocketAddr::Unix(..) => {}
+            &AnySocketAddr::NamedPipe(ref pipe_addr) => pipe_addr.cleanup(),
+            &AnySocketAddr::Mem => {}
+            &AnySocketAddr::Io => {}
+            &AnySocketAddr::Host(..) => {}
         }
     }
 }
@@ -74,6 +108,16 @@ impl ToClientStream for AnySocketAddr {
         match self {
             &AnySocketAddr::Inet(ref inet_addr) => inet_addr.connect(handle),
             &AnySocketAddr::Unix(ref unix_addr) => unix_addr.connect(handle),
+            &AnySocketAddr::NamedPipe(ref pipe_addr) => pipe_addr.connect(handle),
+            &AnySocketAddr::Mem => Box::pin(futures::future::err(io::Error::other(
+                "Cannot connect to in-memory address",
+            ))),
+            &AnySocketAddr::Io => Box::pin(futures::future::err(io::Error::other(
+                "Cannot connect to user-provided stream address",
+            ))),
+            &AnySocketAddr::Host(ref host, port) => {
+                ResolvingConnector::system(host, port).connect(handle)
+            }
         }
     }
 
@@ -105,8 +149,24 @@ pub trait ToClientStream: Display + Send + Sync {
     fn socket_addr(&self) -> AnySocketAddr;
 }
 
+impl<T: ToClientStream + ?Sized> ToClientStream for Arc<T> {
+    fn connect(
+        &self,
+        handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        (**self).connect(handle)
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        (**self).socket_addr()
+    }
+}
+
 pub trait StreamItem: AsyncRead + AsyncWrite + Debug + Send + Sync {
     fn is_tcp(&self) -> bool;
 
     fn set_nodelay(&self, no_delay: bool) -> io::Result<()>;
+
+    /// Local address of the connection.
+    fn local_addr(&self) -> io::Result<AnySocketAddr>;
 }
@@ -0,0 +1,115 @@
+//! Transport over user-provided streams, for example QUIC streams,
+//! SSH channels or custom tunnels.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future;
+use futures::Future;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+use tokio::runtime::Handle;
+
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+use crate::AnySocketAddr;
+
+/// User-provided duplex stream.
+pub(crate) struct IoStream<I>(pub I);
+
+impl<I: AsyncRead + Unpin> AsyncRead for IoStream<I> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl<I: AsyncWrite + Unpin> AsyncWrite for IoStream<I> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+impl<I> fmt::Debug for IoStream<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IoStream").finish()
+    }
+}
+
+impl<I> StreamItem for IoStream<I>
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+{
+    fn is_tcp(&self) -> bool {
+        false
+    }
+
+    fn set_nodelay(&self, _no_delay: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        Ok(AnySocketAddr::Io)
+    }
+}
+
+/// Connector returning a user-provided stream once.
+///
+/// The stream cannot be reopened, so reconnects fail.
+pub(crate) struct IoConnector {
+    stream: Mutex<Option<Pin<Box<dyn StreamItem + Send>>>>,
+}
+
+impl IoConnector {
+    pub fn new<I>(io: I) -> IoConnector
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    {
+        IoConnector {
+            stream: Mutex::new(Some(Box::pin(IoStream(io)))),
+        }
+    }
+}
+
+impl fmt::Display for IoConnector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&AnySocketAddr::Io, f)
+    }
+}
+
+impl ToClientStream for IoConnector {
+    fn connect(
+        &self,
+        _handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        match self.stream.lock().unwrap().take() {
+            Some(stream) => Box::pin(future::ok(stream)),
+            None => Box::pin(future::err(io::Error::other(
+                "user-provided stream is already used",
+            ))),
+        }
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        AnySocketAddr::Io
+    }
+}
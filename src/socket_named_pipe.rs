@@ -0,0 +1,271 @@
+//! Windows named pipe transport, e. g. `\\.\pipe\httpbis`.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::pin::Pin;
+#[cfg(windows)]
+use std::task::Context;
+#[cfg(windows)]
+use std::task::Poll;
+
+#[cfg(windows)]
+use futures::future;
+#[cfg(windows)]
+use futures::stream;
+#[cfg(windows)]
+use futures::stream::Stream;
+use futures::Future;
+#[cfg(windows)]
+use mio_named_pipes::NamedPipe;
+#[cfg(windows)]
+use tokio::io::AsyncRead;
+#[cfg(windows)]
+use tokio::io::AsyncWrite;
+#[cfg(windows)]
+use tokio::io::PollEvented;
+use tokio::runtime::Handle;
+
+#[cfg(windows)]
+use crate::assert_types::assert_send_stream;
+use crate::socket::AnySocketAddr;
+use crate::socket::StreamItem;
+use crate::socket::ToClientStream;
+#[cfg(windows)]
+use crate::socket::ToServerStream;
+use crate::socket::ToSocketListener;
+use crate::socket::ToTokioListener;
+use crate::ServerConf;
+
+/// Windows named pipe path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketAddrNamedPipe(pub(crate) PathBuf);
+
+impl From<PathBuf> for SocketAddrNamedPipe {
+    fn from(p: PathBuf) -> Self {
+        SocketAddrNamedPipe(p)
+    }
+}
+
+impl From<&Path> for SocketAddrNamedPipe {
+    fn from(p: &Path) -> Self {
+        SocketAddrNamedPipe(p.into())
+    }
+}
+
+impl From<&str> for SocketAddrNamedPipe {
+    fn from(p: &str) -> Self {
+        SocketAddrNamedPipe(p.into())
+    }
+}
+
+impl From<String> for SocketAddrNamedPipe {
+    fn from(p: String) -> Self {
+        SocketAddrNamedPipe(p.into())
+    }
+}
+
+impl fmt::Display for SocketAddrNamedPipe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0.display(), f)
+    }
+}
+
+/// Server end of the pipe waiting for the next client.
+#[cfg(windows)]
+struct NamedPipeListener {
+    addr: SocketAddrNamedPipe,
+    pipe: NamedPipe,
+}
+
+impl ToSocketListener for SocketAddrNamedPipe {
+    #[cfg(windows)]
+    fn to_listener(&self, _conf: &ServerConf) -> io::Result<Box<dyn ToTokioListener + Send>> {
+        debug!("creating named pipe {}", self);
+        Ok(Box::new(NamedPipeListener {
+            addr: self.clone(),
+            pipe: NamedPipe::new(&self.0)?,
+        }))
+    }
+
+    #[cfg(not(windows))]
+    fn to_listener(&self, _conf: &ServerConf) -> io::Result<Box<dyn ToTokioListener + Send>> {
+        Err(io::Error::other("cannot use named pipes on non-windows"))
+    }
+
+    fn cleanup(&self) {
+        // pipe is removed when its last handle is closed
+    }
+}
+
+#[cfg(windows)]
+impl ToTokioListener for NamedPipeListener {
+    fn to_tokio_listener(self: Box<Self>, handle: &Handle) -> Box<dyn ToServerStream> {
+        let NamedPipeListener { addr, pipe } = *self;
+        let pipe = handle.enter(|| PollEvented::new(pipe).unwrap());
+        Box::new(NamedPipeServer { addr, pipe })
+    }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        Ok(AnySocketAddr::NamedPipe(self.addr.clone()))
+    }
+}
+
+#[cfg(windows)]
+struct NamedPipeServer {
+    addr: SocketAddrNamedPipe,
+    pipe: PollEvented<NamedPipe>,
+}
+
+#[cfg(windows)]
+impl NamedPipeServer {
+    /// Wait for a client to connect to the current pipe instance,
+    /// and create a new instance for the next client.
+    async fn accept(&mut self) -> io::Result<NamedPipeStream> {
+        let pipe = &self.pipe;
+        future::poll_fn(|cx| loop {
+            match pipe.get_ref().connect() {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // pipe is flagged writable when pending connect finishes
+                    futures::ready!(pipe.poll_write_ready(cx))?;
+                    pipe.clear_write_ready(cx)?;
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        })
+        .await?;
+        if let Some(e) = self.pipe.get_ref().take_error()? {
+            return Err(e);
+        }
+
+        let next = PollEvented::new(NamedPipe::new(&self.addr.0)?)?;
+        Ok(NamedPipeStream {
+            addr: self.addr.clone(),
+            pipe: std::mem::replace(&mut self.pipe, next),
+        })
+    }
+}
+
+#[cfg(windows)]
+impl ToServerStream for NamedPipeServer {
+    fn incoming(
+        self: Box<Self>,
+    ) -> Pin<
+        Box<dyn Stream<Item = io::Result<(Pin<Box<dyn StreamItem + Send>>, AnySocketAddr)>> + Send>,
+    > {
+        let stream = stream::unfold(*self, |mut server| async {
+            let r = match server.accept().await {
+                Ok(stream) => Ok((
+                    Box::pin(stream) as Pin<Box<dyn StreamItem + Send>>,
+                    AnySocketAddr::NamedPipe(server.addr.clone()),
+                )),
+                Err(e) => Err(e),
+            };
+            Some((r, server))
+        });
+
+        let stream = assert_send_stream::<
+            io::Result<(Pin<Box<dyn StreamItem + Send>>, AnySocketAddr)>,
+            _,
+        >(stream);
+
+        Box::pin(stream)
+    }
+}
+
+impl ToClientStream for SocketAddrNamedPipe {
+    #[cfg(windows)]
+    fn connect(
+        &self,
+        _handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        use std::fs::OpenOptions;
+        use std::os::windows::fs::OpenOptionsExt;
+        use std::os::windows::io::FromRawHandle;
+        use std::os::windows::io::IntoRawHandle;
+
+        // `winbase.h`, required for handles used with I/O completion ports
+        const FILE_FLAG_OVERLAPPED: u32 = 0x4000_0000;
+
+        let addr = self.clone();
+        Box::pin(async move {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(FILE_FLAG_OVERLAPPED)
+                .open(&addr.0)?;
+            let pipe = unsafe { NamedPipe::from_raw_handle(file.into_raw_handle()) };
+            let pipe = PollEvented::new(pipe)?;
+            Ok(Box::pin(NamedPipeStream { addr, pipe }) as Pin<Box<dyn StreamItem + Send>>)
+        })
+    }
+
+    #[cfg(not(windows))]
+    fn connect(
+        &self,
+        _handle: &Handle,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
+        Box::pin(futures::future::err(io::Error::other(
+            "cannot use named pipes on non-windows",
+        )))
+    }
+
+    fn socket_addr(&self) -> AnySocketAddr {
+        AnySocketAddr::NamedPipe(self.clone())
+    }
+}
+
+/// Connected named pipe, client or server end.
+#[cfg(windows)]
+#[derive(Debug)]
+struct NamedPipeStream {
+    addr: SocketAddrNamedPipe,
+    pipe: PollEvented<NamedPipe>,
+}
+
+#[cfg(windows)]
+impl AsyncRead for NamedPipeStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.pipe).poll_read(cx, buf)
+    }
+}
+
+#[cfg(windows)]
+impl AsyncWrite for NamedPipeStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.pipe).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_shutdown(cx)
+    }
+}
+
+#[cfg(windows)]
+impl StreamItem for NamedPipeStream {
+    fn is_tcp(&self) -> bool {
+        false
+    }
+
+    fn set_nodelay(&self, _no_delay: bool) -> io::Result<()> {
+        Err(io::Error::other("Cannot set nodelay on named pipe"))
+    }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        Ok(AnySocketAddr::NamedPipe(self.addr.clone()))
+    }
+}
@@ -126,4 +126,8 @@ impl StreamItem for TcpStream {
     fn set_nodelay(&self, no_delay: bool) -> io::Result<()> {
         self.set_nodelay(no_delay)
     }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        TcpStream::local_addr(self).map(AnySocketAddr::Inet)
+    }
 }
@@ -136,19 +136,13 @@ impl ToClientStream for SocketAddrUnix {
     #[cfg(unix)]
     fn connect(
         &self,
-        handle: &Handle,
+        _handle: &Handle,
     ) -> Pin<Box<dyn Future<Output = io::Result<Pin<Box<dyn StreamItem + Send>>>> + Send>> {
-        // TODO: async connect
-        let stream = match std::os::unix::net::UnixStream::connect(&self.0) {
-            Ok(stream) => stream,
-            Err(e) => return Box::pin(async { Err(e) }),
-        };
-        match handle.enter(|| UnixStream::from_std(stream)) {
-            Ok(stream) => {
-                Box::pin(async { Ok(Box::pin(stream) as Pin<Box<dyn StreamItem + Send>>) })
-            }
-            Err(e) => return Box::pin(async { Err(e) }),
-        }
+        let path = self.0.clone();
+        Box::pin(async move {
+            let stream = UnixStream::connect(path).await?;
+            Ok(Box::pin(stream) as Pin<Box<dyn StreamItem + Send>>)
+        })
     }
 
     #[cfg(not(unix))]
@@ -180,4 +174,8 @@ impl StreamItem for UnixStream {
             "Cannot set nodelay on unix domain socket",
         ))
     }
+
+    fn local_addr(&self) -> io::Result<AnySocketAddr> {
+        UnixStream::local_addr(self).map(|addr| AnySocketAddr::Unix(addr.into()))
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use bytes::Bytes;
 
 use crate::HeaderValue;
@@ -8,3 +10,54 @@ pub const METHOD_GET: HeaderValue =
 /// String `"POST"`.
 pub const METHOD_POST: HeaderValue =
     unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(b"POST")) };
+
+/// Request method, value of `:method` pseudo-header.
+///
+/// Constants are provided for standard methods,
+/// other methods can be created from strings.
+#[derive(Eq, PartialEq, Hash, Clone)]
+pub struct Method(HeaderValue);
+
+const fn method(name: &'static [u8]) -> Method {
+    Method(unsafe { HeaderValue::from_bytes_unchecked(Bytes::from_static(name)) })
+}
+
+impl Method {
+    pub const GET: Method = Method(METHOD_GET);
+    pub const HEAD: Method = method(b"HEAD");
+    pub const POST: Method = Method(METHOD_POST);
+    pub const PUT: Method = method(b"PUT");
+    pub const DELETE: Method = method(b"DELETE");
+    pub const CONNECT: Method = method(b"CONNECT");
+    pub const OPTIONS: Method = method(b"OPTIONS");
+    pub const TRACE: Method = method(b"TRACE");
+    pub const PATCH: Method = method(b"PATCH");
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'a> From<&'a str> for Method {
+    fn from(s: &'a str) -> Method {
+        Method(HeaderValue::from(s))
+    }
+}
+
+impl From<Method> for HeaderValue {
+    fn from(method: Method) -> HeaderValue {
+        method.0
+    }
+}
@@ -32,6 +32,8 @@ pub struct Header {
     name: HeaderName,
     /// Header value.
     pub value: HeaderValue,
+    /// Encode as HPACK never-indexed literal.
+    sensitive: bool,
 }
 
 impl fmt::Debug for Header {
@@ -94,6 +96,7 @@ impl Header {
         Ok(Header {
             name,
             value: HeaderValue::from(value),
+            sensitive: false,
         })
     }
 
@@ -107,6 +110,15 @@ impl Header {
         Header {
             name: name.into(),
             value: value.into(),
+            sensitive: false,
+        }
+    }
+
+    /// Creates a new sensitive `Header`, see `set_sensitive`.
+    pub fn new_sensitive<N: Into<HeaderName>, V: Into<HeaderValue>>(name: N, value: V) -> Header {
+        Header {
+            sensitive: true,
+            ..Header::new(name, value)
         }
     }
 
@@ -139,6 +151,18 @@ impl Header {
         self.value.as_slice()
     }
 
+    /// Header is encoded as HPACK never-indexed literal?
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Encode header as HPACK never-indexed literal (RFC 7541 section 6.2.3),
+    /// so the value is never added to the dynamic table of either peer
+    /// nor of intermediaries. Use for credentials like `authorization`.
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
     /// name: value
     pub fn format(&self) -> String {
         format!(
@@ -185,12 +209,42 @@ impl<N: Into<HeaderName>, V: Into<HeaderValue>> From<(N, V)> for Header {
     }
 }
 
+/// Values of frequently used headers parsed when headers are modified.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+struct ParsedHeaders {
+    status: Option<u32>,
+    content_length: Option<u64>,
+}
+
+impl ParsedHeaders {
+    fn is_parsed(name: &str) -> bool {
+        name == ":status" || name == "content-length"
+    }
+
+    fn parse(headers: &[Header]) -> ParsedHeaders {
+        ParsedHeaders {
+            status: ParsedHeaders::parse_first(headers, ":status"),
+            content_length: ParsedHeaders::parse_first(headers, "content-length"),
+        }
+    }
+
+    // First header with given name is used like in `Headers::get_opt`
+    fn parse_first<I: FromStr>(headers: &[Header], name: &str) -> Option<I> {
+        headers
+            .iter()
+            .find(|h| h.name() == name)
+            .and_then(|h| str::from_utf8(h.value()).ok())
+            .and_then(|v| v.parse().ok())
+    }
+}
+
 /// HTTP message headers (or trailers)
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
 pub struct Headers {
     // Pseudo-headers stored before regular headers
     headers: Vec<Header>,
     pseudo_count: usize,
+    parsed: ParsedHeaders,
 }
 
 impl Headers {
@@ -203,9 +257,11 @@ impl Headers {
     pub fn from_vec(mut headers: Vec<Header>) -> Headers {
         headers.sort_by_key(|h| !h.is_preudo_header());
         let pseudo_count = headers.iter().take_while(|h| h.is_preudo_header()).count();
+        let parsed = ParsedHeaders::parse(&headers);
         Headers {
             headers,
             pseudo_count,
+            parsed,
         }
     }
 
@@ -222,9 +278,11 @@ impl Headers {
                 saw_regular_header = true;
             }
         }
+        let parsed = ParsedHeaders::parse(&headers);
         return Ok(Headers {
             headers,
             pseudo_count,
+            parsed,
         });
     }
 
@@ -235,6 +293,12 @@ impl Headers {
         self.headers.iter()
     }
 
+    /// Headers as `(name, value, sensitive)` triples for the HPACK encoder.
+    pub(crate) fn iter_for_hpack(&self) -> impl Iterator<Item = (&[u8], &[u8], bool)> {
+        self.iter()
+            .map(|h| (h.name().as_bytes(), h.value(), h.is_sensitive()))
+    }
+
     fn pseudo_headers(&self) -> &[Header] {
         &self.headers[..self.pseudo_count]
     }
@@ -324,8 +388,36 @@ impl Headers {
             debug_assert!(!header.is_preudo_header());
         }
 
+        if pseudo_headers_met.contains(PseudoHeaderName::Protocol) && !self.is_connect() {
+            return Err(HeaderError::UnexpectedPseudoHeader(
+                PseudoHeaderName::Protocol,
+            ));
+        }
+
         if headers_place == HeadersPlace::Initial {
             let required_headers = match req_or_resp {
+                // Extended CONNECT request (RFC 8441 section 4) is a regular
+                // request with `:protocol` pseudo-header.
+                RequestOrResponse::Request
+                    if pseudo_headers_met.contains(PseudoHeaderName::Protocol) =>
+                {
+                    &[
+                        PseudoHeaderName::Method,
+                        PseudoHeaderName::Scheme,
+                        PseudoHeaderName::Path,
+                        PseudoHeaderName::Authority,
+                    ][..]
+                }
+                // The ":scheme" and ":path" pseudo-header fields MUST be omitted
+                // (in CONNECT request).
+                RequestOrResponse::Request if self.is_connect() => {
+                    for &unexpected in &[PseudoHeaderName::Scheme, PseudoHeaderName::Path] {
+                        if pseudo_headers_met.contains(unexpected) {
+                            return Err(HeaderError::UnexpectedPseudoHeader(unexpected));
+                        }
+                    }
+                    &[PseudoHeaderName::Method, PseudoHeaderName::Authority][..]
+                }
                 // All HTTP/2 requests MUST include exactly one valid value for the
                 // ":method", ":scheme", and ":path" pseudo-header fields, unless it is
                 // a CONNECT request (Section 8.3).  An HTTP request that omits
@@ -381,7 +473,7 @@ impl Headers {
 
     /// Status header value.
     pub fn status(&self) -> u32 {
-        self.get_opt_parse(":status").unwrap()
+        self.status_opt().unwrap()
     }
 
     /// Path header.
@@ -395,12 +487,67 @@ impl Headers {
         self.get(":method")
     }
 
+    /// Is this a `CONNECT` request.
+    pub fn is_connect(&self) -> bool {
+        self.get_opt(":method") == Some("CONNECT")
+    }
+
     /// Content-length header.
     pub fn content_length(&self) -> Option<u64> {
-        match self.get_opt("content-length") {
-            Some(v) => v.parse().ok(),
-            None => None,
-        }
+        self.parsed.content_length
+    }
+
+    /// Content-type header.
+    pub fn content_type(&self) -> Option<&str> {
+        self.get_opt("content-type")
+    }
+
+    /// `:authority` pseudo-header, or `host` header if `:authority` is absent.
+    pub fn host_or_authority(&self) -> Option<&str> {
+        self.get_opt(":authority").or_else(|| self.get_opt("host"))
+    }
+
+    /// `:path` header split into path and optional query (without `?`).
+    pub fn path_and_query(&self) -> Option<(&str, Option<&str>)> {
+        let path = self.get_opt(":path")?;
+        Some(match path.find('?') {
+            Some(pos) => (&path[..pos], Some(&path[pos + 1..])),
+            None => (path, None),
+        })
+    }
+
+    /// Status header value, `None` if absent or malformed.
+    pub fn status_opt(&self) -> Option<u32> {
+        self.parsed.status
+    }
+
+    /// Status is `1xx`.
+    pub fn is_informational(&self) -> bool {
+        self.status_class() == Some(1)
+    }
+
+    /// Status is `2xx`.
+    pub fn is_success(&self) -> bool {
+        self.status_class() == Some(2)
+    }
+
+    /// Status is `3xx`.
+    pub fn is_redirection(&self) -> bool {
+        self.status_class() == Some(3)
+    }
+
+    /// Status is `4xx`.
+    pub fn is_client_error(&self) -> bool {
+        self.status_class() == Some(4)
+    }
+
+    /// Status is `5xx`.
+    pub fn is_server_error(&self) -> bool {
+        self.status_class() == Some(5)
+    }
+
+    fn status_class(&self) -> Option<u32> {
+        self.status_opt().map(|s| s / 100)
     }
 
     /// Add a header
@@ -408,8 +555,14 @@ impl Headers {
         self.add_header(Header::new(name, value));
     }
 
+    /// Add a sensitive header, see `Header::set_sensitive`
+    pub fn add_sensitive(&mut self, name: impl Into<HeaderName>, value: impl Into<HeaderValue>) {
+        self.add_header(Header::new_sensitive(name, value));
+    }
+
     /// Add a header
     pub fn add_header(&mut self, header: Header) {
+        let parsed = ParsedHeaders::is_parsed(header.name());
         if header.is_preudo_header() {
             let pseudo_count = self.pseudo_count;
             self.headers.insert(pseudo_count, header);
@@ -417,6 +570,22 @@ impl Headers {
         } else {
             self.headers.push(header);
         }
+        if parsed {
+            self.parsed = ParsedHeaders::parse(&self.headers);
+        }
+    }
+
+    /// Remove all headers with given name
+    pub fn remove(&mut self, name: &str) {
+        self.headers.retain(|h| h.name() != name);
+        self.pseudo_count = self
+            .headers
+            .iter()
+            .take_while(|h| h.is_preudo_header())
+            .count();
+        if ParsedHeaders::is_parsed(name) {
+            self.parsed = ParsedHeaders::parse(&self.headers);
+        }
     }
 
     /// Add all headers
@@ -426,6 +595,24 @@ impl Headers {
             self.add_header(h);
         }
     }
+
+    /// Add headers from `defaults` with names not present in these headers.
+    ///
+    /// Pseudo-headers are not copied, `cookie` headers are always added
+    /// (cookies are concatenated rather than overridden).
+    pub fn add_missing(&mut self, defaults: &Headers) {
+        let present: Vec<String> = self
+            .iter()
+            .filter(|h| h.name() != "cookie")
+            .map(|h| h.name().to_owned())
+            .collect();
+        for header in defaults.iter() {
+            if header.is_preudo_header() || present.iter().any(|n| n == header.name()) {
+                continue;
+            }
+            self.add_header(header.clone());
+        }
+    }
 }
 
 impl FromIterator<Header> for Headers {
@@ -437,7 +624,12 @@ impl FromIterator<Header> for Headers {
 #[cfg(test)]
 mod test {
 
+    use crate::headers_place::HeadersPlace;
+    use crate::req_resp::RequestOrResponse;
     use crate::solicit::header::Header;
+    use crate::solicit::header::HeaderError;
+    use crate::solicit::header::Headers;
+    use crate::solicit::header::PseudoHeaderName;
 
     #[test]
     fn test_partial_eq_of_headers() {
@@ -461,4 +653,147 @@ mod test {
             format!("{:?}", Header::new(&b":method"[..], &b"\t"[..]))
         );
     }
+
+    #[test]
+    fn typed_accessors() {
+        let mut headers = Headers::new_get("/foo/bar?a=1&b=2");
+        headers.add("host", "example.com");
+        headers.add("content-type", "text/plain");
+        headers.add("content-length", "17");
+        assert_eq!(
+            Some(("/foo/bar", Some("a=1&b=2"))),
+            headers.path_and_query()
+        );
+        assert_eq!(Some("example.com"), headers.host_or_authority());
+        assert_eq!(Some("text/plain"), headers.content_type());
+        assert_eq!(Some(17), headers.content_length());
+
+        headers.add(":authority", "example.org");
+        assert_eq!(Some("example.org"), headers.host_or_authority());
+
+        assert_eq!(Some(("/", None)), Headers::new_get("/").path_and_query());
+        assert_eq!(None, Headers::new().path_and_query());
+    }
+
+    #[test]
+    fn validate_connect() {
+        let connect = Headers::from_vec(vec![
+            Header::new(":method", "CONNECT"),
+            Header::new(":authority", "example.com:443"),
+        ]);
+        assert!(connect.is_connect());
+        assert!(connect
+            .validate(RequestOrResponse::Request, HeadersPlace::Initial)
+            .is_ok());
+
+        let mut with_path = connect.clone();
+        with_path.add(":path", "/");
+        match with_path.validate(RequestOrResponse::Request, HeadersPlace::Initial) {
+            Err(HeaderError::UnexpectedPseudoHeader(PseudoHeaderName::Path)) => {}
+            r => panic!("{:?}", r),
+        }
+
+        let no_authority = Headers::from_vec(vec![Header::new(":method", "CONNECT")]);
+        match no_authority.validate(RequestOrResponse::Request, HeadersPlace::Initial) {
+            Err(HeaderError::MissingPseudoHeader(PseudoHeaderName::Authority)) => {}
+            r => panic!("{:?}", r),
+        }
+
+        let mut extended = with_path.clone();
+        extended.add(":scheme", "https");
+        extended.add(":protocol", "websocket");
+        assert!(extended
+            .validate(RequestOrResponse::Request, HeadersPlace::Initial)
+            .is_ok());
+
+        let mut get_with_protocol = Headers::new_get("/");
+        get_with_protocol.add(":scheme", "https");
+        get_with_protocol.add(":protocol", "websocket");
+        match get_with_protocol.validate(RequestOrResponse::Request, HeadersPlace::Initial) {
+            Err(HeaderError::UnexpectedPseudoHeader(PseudoHeaderName::Protocol)) => {}
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn remove() {
+        let mut headers = Headers::new_get("/");
+        headers.add("via", "a");
+        headers.add("x", "1");
+        headers.add("via", "b");
+        headers.remove("via");
+        headers.remove(":path");
+        assert_eq!(None, headers.get_opt("via"));
+        assert_eq!(None, headers.get_opt(":path"));
+        assert_eq!("GET", headers.method());
+        assert_eq!("1", headers.get("x"));
+        headers.add(":path", "/a");
+        assert_eq!("/a", headers.path());
+    }
+
+    #[test]
+    fn status_class() {
+        assert!(Headers::ok_200().is_success());
+        assert!(!Headers::ok_200().is_client_error());
+        assert!(Headers::new_status(100).is_informational());
+        assert!(Headers::redirect_302("/").is_redirection());
+        assert!(Headers::not_found_404().is_client_error());
+        assert!(Headers::internal_error_500().is_server_error());
+        assert!(!Headers::new_get("/").is_success());
+        assert_eq!(None, Headers::new_get("/").status_opt());
+    }
+
+    #[test]
+    fn parsed_updated() {
+        let mut headers = Headers::new();
+        headers.add("content-length", "x");
+        assert_eq!(None, headers.content_length());
+        // first header is used
+        headers.add("content-length", "10");
+        assert_eq!(None, headers.content_length());
+        headers.remove("content-length");
+        headers.add("content-length", "10");
+        assert_eq!(Some(10), headers.content_length());
+
+        headers.add(":status", "204");
+        assert_eq!(Some(204), headers.status_opt());
+        headers.remove(":status");
+        assert_eq!(None, headers.status_opt());
+        assert_eq!(Some(10), headers.content_length());
+
+        let mut extended = Headers::ok_200();
+        extended.extend(headers);
+        assert_eq!(Some(200), extended.status_opt());
+        assert_eq!(Some(10), extended.content_length());
+        assert_eq!(
+            extended,
+            Headers::from_vec(extended.iter().cloned().collect())
+        );
+    }
+
+    #[test]
+    fn add_missing() {
+        let mut defaults = Headers::new();
+        defaults.add("user-agent", "httpbis");
+        defaults.add("accept", "text/html");
+        defaults.add("accept", "application/json");
+        defaults.add("cookie", "a=1");
+
+        let mut headers = Headers::new_get("/");
+        headers.add("accept", "text/plain");
+        headers.add("cookie", "b=2");
+        headers.add_missing(&defaults);
+
+        let values = |name| {
+            headers
+                .iter()
+                .filter(|h| h.name() == name)
+                .map(|h| String::from_utf8_lossy(h.value()).into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["httpbis"], values("user-agent"));
+        assert_eq!(vec!["text/plain"], values("accept"));
+        assert_eq!(vec!["b=2", "a=1"], values("cookie"));
+        assert_eq!("/", headers.path());
+    }
 }
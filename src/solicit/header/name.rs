@@ -21,6 +21,10 @@ pub enum PseudoHeaderName {
     // 8.1.2.4 Response Pseudo-Header Fields
     /// `:status`
     Status = 4,
+
+    // RFC 8441 extended CONNECT
+    /// `:protocol`
+    Protocol = 5,
 }
 
 impl PseudoHeaderName {
@@ -32,6 +36,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority => ":authority",
             PseudoHeaderName::Path => ":path",
             PseudoHeaderName::Status => ":status",
+            PseudoHeaderName::Protocol => ":protocol",
         }
     }
 
@@ -43,6 +48,7 @@ impl PseudoHeaderName {
             b":authority" => Ok(PseudoHeaderName::Authority),
             b":path" => Ok(PseudoHeaderName::Path),
             b":status" => Ok(PseudoHeaderName::Status),
+            b":protocol" => Ok(PseudoHeaderName::Protocol),
             _ => Err(HeaderError::UnknownPseudoHeader),
         }
     }
@@ -55,6 +61,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority => RequestOrResponse::Request,
             PseudoHeaderName::Path => RequestOrResponse::Request,
             PseudoHeaderName::Status => RequestOrResponse::Response,
+            PseudoHeaderName::Protocol => RequestOrResponse::Request,
         }
     }
 
@@ -70,6 +77,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Scheme,
             PseudoHeaderName::Authority,
             PseudoHeaderName::Path,
+            PseudoHeaderName::Protocol,
         ];
         static RESPONSE_HEADERS: &[PseudoHeaderName] = &[PseudoHeaderName::Status];
         match request_or_response {
@@ -86,6 +94,7 @@ impl PseudoHeaderName {
             PseudoHeaderName::Authority,
             PseudoHeaderName::Path,
             PseudoHeaderName::Status,
+            PseudoHeaderName::Protocol,
         ];
         ALL_HEADERS
     }
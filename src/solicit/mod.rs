@@ -7,6 +7,7 @@ pub(crate) mod end_stream;
 pub(crate) mod error_code;
 pub mod frame;
 pub mod header;
+pub(crate) mod priority;
 pub mod session;
 pub(crate) mod stream_id;
 pub(crate) mod window_size;
@@ -20,6 +21,7 @@ pub const DEFAULT_SETTINGS: HttpSettings = HttpSettings {
     initial_window_size: 65_535,
     max_frame_size: 16_384,
     max_header_list_size: u32::MAX,
+    enable_connect_protocol: false,
 };
 
 /// A set of protocol names that the library should use to indicate that HTTP/2
@@ -31,7 +33,7 @@ pub const DEFAULT_SETTINGS: HttpSettings = HttpSettings {
 pub const ALPN_PROTOCOLS: &'static [&'static [u8]] = &[b"h2", b"h2-16", b"h2-15", b"h2-14"];
 
 /// An enum representing the two possible HTTP schemes.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum HttpScheme {
     /// The variant corresponding to `http://`
     Http,
@@ -49,6 +51,3 @@ impl HttpScheme {
         }
     }
 }
-
-#[cfg(test)]
-pub mod tests;
@@ -0,0 +1,152 @@
+//! Extensible priorities (RFC 9218).
+
+use std::fmt;
+use std::str;
+
+use crate::Headers;
+
+/// Name of the request header carrying priority.
+pub(crate) const PRIORITY_HEADER: &str = "priority";
+
+/// Highest urgency value, lowest priority.
+pub const MAX_URGENCY: u8 = 7;
+
+/// Priority of a request, sent in the `priority` header
+/// or `PRIORITY_UPDATE` frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    /// `0` is the highest priority, `7` is the lowest.
+    pub urgency: u8,
+    /// Response can be processed incrementally,
+    /// so it is interleaved with other incremental responses.
+    pub incremental: bool,
+}
+
+impl Default for Priority {
+    fn default() -> Priority {
+        Priority {
+            urgency: 3,
+            incremental: false,
+        }
+    }
+}
+
+impl Priority {
+    /// Create priority, urgency is clamped to `MAX_URGENCY`.
+    pub fn new(urgency: u8, incremental: bool) -> Priority {
+        Priority {
+            urgency: urgency.min(MAX_URGENCY),
+            incremental,
+        }
+    }
+
+    /// Parse a priority field value (a structured field dictionary).
+    ///
+    /// Unknown or invalid members are ignored, so the result
+    /// is default priority for malformed values.
+    pub fn parse(value: &str) -> Priority {
+        let mut priority = Priority::default();
+        for member in value.split(',') {
+            // parameters are ignored
+            let member = member.split(';').next().unwrap().trim();
+            let (key, value) = match member.find('=') {
+                Some(pos) => (&member[..pos], Some(&member[pos + 1..])),
+                None => (member, None),
+            };
+            match (key, value) {
+                ("u", Some(value)) => match value.parse::<u8>() {
+                    Ok(urgency) if urgency <= MAX_URGENCY => priority.urgency = urgency,
+                    _ => {}
+                },
+                ("i", None) | ("i", Some("?1")) => priority.incremental = true,
+                ("i", Some("?0")) => priority.incremental = false,
+                _ => {}
+            }
+        }
+        priority
+    }
+
+    /// Parse a `PRIORITY_UPDATE` frame field value.
+    pub(crate) fn from_field_value(value: &[u8]) -> Priority {
+        match str::from_utf8(value) {
+            Ok(value) => Priority::parse(value),
+            Err(_) => Priority::default(),
+        }
+    }
+
+    /// Priority from `priority` header, default if header is absent.
+    pub fn from_headers(headers: &Headers) -> Priority {
+        match headers.get_opt(PRIORITY_HEADER) {
+            Some(value) => Priority::parse(value),
+            None => Priority::default(),
+        }
+    }
+
+    /// Set `priority` header, removing it if priority is default.
+    pub fn set_header(&self, headers: &mut Headers) {
+        headers.remove(PRIORITY_HEADER);
+        if *self != Priority::default() {
+            headers.add(PRIORITY_HEADER, self.to_string());
+        }
+    }
+}
+
+/// Field value, empty for default priority.
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default = Priority::default();
+        if self.urgency != default.urgency {
+            write!(f, "u={}", self.urgency)?;
+            if self.incremental {
+                write!(f, ", ")?;
+            }
+        }
+        if self.incremental {
+            write!(f, "i")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Priority::default(), Priority::parse(""));
+        assert_eq!(Priority::new(5, false), Priority::parse("u=5"));
+        assert_eq!(Priority::new(3, true), Priority::parse("i"));
+        assert_eq!(Priority::new(0, true), Priority::parse("u=0, i"));
+        assert_eq!(Priority::new(1, true), Priority::parse("i=?1,u=1"));
+        assert_eq!(Priority::new(3, false), Priority::parse("i=?0"));
+        // invalid and unknown members are ignored
+        assert_eq!(Priority::new(3, true), Priority::parse("u=8, i, x=1"));
+        assert_eq!(Priority::new(2, false), Priority::parse("u=2;p=1, i=5"));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("", Priority::default().to_string());
+        assert_eq!("u=5", Priority::new(5, false).to_string());
+        assert_eq!("i", Priority::new(3, true).to_string());
+        assert_eq!("u=0, i", Priority::new(0, true).to_string());
+        for urgency in 0..=MAX_URGENCY {
+            for &incremental in &[false, true] {
+                let p = Priority::new(urgency, incremental);
+                assert_eq!(p, Priority::parse(&p.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn header() {
+        let mut headers = Headers::new_get("/");
+        assert_eq!(Priority::default(), Priority::from_headers(&headers));
+        Priority::new(1, true).set_header(&mut headers);
+        assert_eq!("u=1, i", headers.get("priority"));
+        assert_eq!(Priority::new(1, true), Priority::from_headers(&headers));
+        Priority::default().set_header(&mut headers);
+        assert_eq!(None, headers.get_opt("priority"));
+    }
+}
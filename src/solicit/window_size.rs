@@ -2,18 +2,7 @@
 
 use std::fmt;
 
-/// A sender MUST NOT allow a flow-control window to exceed 231-1 octets. If a sender receives
-/// a WINDOW_UPDATE that causes a flow-control window to exceed this maximum,
-/// it MUST terminate either the stream or the connection, as appropriate. For streams,
-/// the sender sends a RST_STREAM with an error code of FLOW_CONTROL_ERROR; for the connection,
-/// a GOAWAY frame with an error code of FLOW_CONTROL_ERROR is sent.
-pub const MAX_WINDOW_SIZE: u32 = 0x7fffffff;
-
-#[test]
-fn test_max_window_size_is_i32_max() {
-    use std::i32;
-    assert_eq!(i32::max_value(), MAX_WINDOW_SIZE as i32);
-}
+pub use httpbis_codec::window_size::MAX_WINDOW_SIZE;
 
 /// Effective reachable min window size.
 pub const MIN_WINDOW_SIZE: i32 = 0 - MAX_WINDOW_SIZE as i32;
@@ -128,4 +117,10 @@ impl NonNegativeWindowSize {
     pub fn try_increase(&mut self, delta: u32) -> Result<(), ()> {
         self.0.try_increase(delta)
     }
+
+    /// Add positive or negative delta, negative result is truncated to zero.
+    pub fn add_saturating(&mut self, delta: i32) {
+        let size = self.size().saturating_add(delta).max(0);
+        self.0 = WindowSize::new(size);
+    }
 }
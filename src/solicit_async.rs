@@ -9,11 +9,18 @@ use futures::stream::Stream;
 use std::future::Future;
 
 use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
 
 use crate::error;
 use crate::error::Error;
+use crate::h2c::is_upgrade_accepted;
+use crate::h2c::upgrade_request;
+use crate::h2c::H2cUpgrade;
+use crate::h2c::SWITCHING_PROTOCOLS_RESPONSE;
+use crate::http1::read_head;
+use crate::http1::Http1Head;
 use crate::result;
 use crate::result::Result;
 
@@ -100,6 +107,29 @@ pub async fn client_handshake<I: AsyncWrite + AsyncRead + Unpin + Send + 'static
     Ok(())
 }
 
+/// Switch to HTTP/2 with HTTP/1.1 `Upgrade: h2c`, then send preface (RFC 7540 section 3.2).
+pub(crate) async fn client_h2c_handshake<I>(
+    conn: &mut I,
+    settings: SettingsFrame,
+    authority: &str,
+) -> result::Result<()>
+where
+    I: AsyncWrite + AsyncRead + Unpin + Send + 'static,
+{
+    debug!("send h2c upgrade request");
+    conn.write_all(&upgrade_request(authority, &settings))
+        .await?;
+
+    let mut head = Vec::new();
+    read_head(conn, &mut head).await?;
+    let head = Http1Head::parse(&head)?;
+    if !is_upgrade_accepted(&head) {
+        return Err(error::Error::H2cUpgradeRejected(head.status()));
+    }
+
+    client_handshake(conn, settings).await
+}
+
 /// Response to be sent when request is sent over HTTP/1
 const HTTP_1_500_RESPONSE: &'static [u8] = b"\
 HTTP/1.1 500 Internal Server Error\r\n\
@@ -110,11 +140,33 @@ Request is made using HTTP/1, server only supports HTTP/2\r\n\
 
 /// Buf content looks like a start of HTTP/1 request
 fn looks_like_http_1(buf: &[u8]) -> bool {
-    buf.starts_with(b"GET ") || buf.starts_with(b"POST ") || buf.starts_with(b"HEAD ")
+    buf.starts_with(b"GET ")
+        || buf.starts_with(b"POST ")
+        || buf.starts_with(b"HEAD ")
+        || buf.starts_with(b"OPTIONS ")
+}
+
+async fn recv_preface<I>(conn: &mut I) -> result::Result<()>
+where
+    I: AsyncRead + Unpin,
+{
+    let mut preface = [0; PREFACE.len()];
+    conn.read_exact(&mut preface).await?;
+    if &preface[..] != PREFACE {
+        return Err(error::Error::InvalidFrame(format!(
+            "wrong preface: {:?}",
+            BsDebug(&preface)
+        )));
+    }
+    Ok(())
 }
 
-/// Recv HTTP/2 preface, or sent HTTP/1 500 and return error is input looks like HTTP/1 request
-async fn recv_preface_or_handle_http_1<I>(conn: &mut I) -> result::Result<()>
+/// Recv HTTP/2 preface, or handle HTTP/1 request: accept `h2c` upgrade if allowed,
+/// otherwise send HTTP/1 500 and return error
+async fn recv_preface_or_handle_http_1<I>(
+    conn: &mut I,
+    h2c_upgrade: bool,
+) -> result::Result<Option<H2cUpgrade>>
 where
     I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
@@ -127,7 +179,8 @@ where
     where
         I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
     {
-        type Output = result::Result<bool>;
+        /// First line of HTTP/1 request
+        type Output = result::Result<Option<Vec<u8>>>;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
             loop {
@@ -156,13 +209,13 @@ where
                 self.collected.push(c);
 
                 if self.collected == PREFACE {
-                    return Poll::Ready(Ok(false));
+                    return Poll::Ready(Ok(None));
                 }
 
                 // TODO: check only for first \n
                 if c == b'\n' {
                     if looks_like_http_1(&self.collected) {
-                        return Poll::Ready(Ok(true));
+                        return Poll::Ready(Ok(Some(std::mem::take(&mut self.collected))));
                     }
                 }
 
@@ -176,30 +229,51 @@ where
         }
     }
 
-    let need_500 = Intermediate {
+    let first_line = Intermediate {
         conn,
         collected: Vec::new(),
     }
     .await?;
 
-    if need_500 {
-        conn.write_all(HTTP_1_500_RESPONSE).await?;
-
-        return Err(error::Error::RequestIsMadeUsingHttp1);
+    let mut head = match first_line {
+        Some(head) => head,
+        None => return Ok(None),
+    };
+
+    // Only well-formed HTTP/1.1 requests are read further,
+    // others are answered right after request line
+    if h2c_upgrade && head.ends_with(b" HTTP/1.1\r\n") {
+        read_head(conn, &mut head).await?;
+        let upgrade = Http1Head::parse(&head)
+            .ok()
+            .and_then(|head| H2cUpgrade::from_request(&head));
+        if let Some(upgrade) = upgrade {
+            debug!("upgrading to h2c: {:?}", upgrade.headers);
+            conn.write_all(SWITCHING_PROTOCOLS_RESPONSE).await?;
+            return Ok(Some(upgrade));
+        }
     }
 
-    Ok(())
+    conn.write_all(HTTP_1_500_RESPONSE).await?;
+
+    Err(error::Error::RequestIsMadeUsingHttp1)
 }
 
-pub async fn server_handshake<I>(conn: &mut I, settings: SettingsFrame) -> result::Result<()>
+/// Returns upgraded request if connection started with HTTP/1.1 `h2c` upgrade.
+pub(crate) async fn server_handshake<I>(
+    conn: &mut I,
+    settings: SettingsFrame,
+    h2c_upgrade: bool,
+) -> result::Result<Option<H2cUpgrade>>
 where
     I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 {
-    let mut preface_buf = Vec::with_capacity(PREFACE.len());
-    preface_buf.resize(PREFACE.len(), 0);
-
-    recv_preface_or_handle_http_1(conn).await?;
+    let upgrade = recv_preface_or_handle_http_1(conn, h2c_upgrade).await?;
     send_settings(conn, settings).await?;
+    if upgrade.is_some() {
+        // 3.2: client sends preface after receiving `101`
+        recv_preface(conn).await?;
+    }
 
-    Ok(())
+    Ok(upgrade)
 }
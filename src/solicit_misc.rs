@@ -6,7 +6,8 @@ use crate::solicit::frame::HttpFrame;
 use crate::solicit::frame::HttpFrameDecoded;
 use crate::solicit::frame::PingFrame;
 use crate::solicit::frame::PriorityFrame;
-use crate::solicit::frame::PushPromiseFrame;
+use crate::solicit::frame::PriorityUpdateFrame;
+use crate::solicit::frame::PushPromiseDecodedFrame;
 use crate::solicit::frame::RawFrame;
 use crate::solicit::frame::RstStreamFrame;
 use crate::solicit::frame::SettingsFrame;
@@ -20,7 +21,7 @@ pub enum HttpFrameStream {
     Headers(HeadersDecodedFrame),
     Priority(PriorityFrame),
     RstStream(RstStreamFrame),
-    PushPromise(PushPromiseFrame),
+    PushPromise(PushPromiseDecodedFrame),
     WindowUpdate(WindowUpdateFrame),
 }
 
@@ -69,6 +70,7 @@ pub enum HttpFrameConn {
     Ping(PingFrame),
     Goaway(GoawayFrame),
     WindowUpdate(WindowUpdateFrame),
+    PriorityUpdate(PriorityUpdateFrame),
 }
 
 impl HttpFrameConn {
@@ -79,6 +81,7 @@ impl HttpFrameConn {
             HttpFrameConn::Ping(f) => HttpFrame::Ping(f),
             HttpFrameConn::Goaway(f) => HttpFrame::Goaway(f),
             HttpFrameConn::WindowUpdate(f) => HttpFrame::WindowUpdate(f),
+            HttpFrameConn::PriorityUpdate(f) => HttpFrame::PriorityUpdate(f),
         }
     }
 }
@@ -116,6 +119,9 @@ impl HttpFrameClassified {
                     HttpFrameClassified::Conn(HttpFrameConn::WindowUpdate(f))
                 }
             }
+            HttpFrameDecoded::PriorityUpdate(f) => {
+                HttpFrameClassified::Conn(HttpFrameConn::PriorityUpdate(f))
+            }
             HttpFrameDecoded::Unknown(f) => HttpFrameClassified::Unknown(f),
         }
     }
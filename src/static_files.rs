@@ -0,0 +1,270 @@
+//! Serving files from a directory, enabled with `static-files` feature.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use httpbis::*;
+//! # use httpbis::static_files::StaticFiles;
+//! let mut server = ServerBuilder::new_plain();
+//! let mut files = StaticFiles::new("/var/www");
+//! files.set_prefix("/static");
+//! server.service.set_service("/static", Arc::new(files));
+//! ```
+
+use std::fs;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::context::percent_decode;
+use crate::date::format_http_date;
+use crate::result;
+use crate::server::handler::ServerHandler;
+use crate::server::handler::ServerHandlerContext;
+use crate::server::range::ByteRange;
+use crate::server::req::ServerRequest;
+use crate::ErrorCode;
+use crate::Headers;
+use crate::ServerResponse;
+
+/// `content-type` for file name extension, `application/octet-stream`
+/// if extension is unknown.
+pub fn guess_content_type(path: &Path) -> &'static str {
+    let ext = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.to_ascii_lowercase(),
+        None => return "application/octet-stream",
+    };
+    match &ext[..] {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Relative file path for request path, `None` if path escapes the root.
+fn relative_path(path: &str) -> Option<PathBuf> {
+    let path = path.split(['?', '#']).next().unwrap();
+    let path = percent_decode(path);
+    let mut r = PathBuf::new();
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(c) => r.push(c),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(..) => return None,
+        }
+    }
+    Some(r)
+}
+
+/// `If-None-Match` matches `etag` using weak comparison.
+fn if_none_match(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+/// `ServerHandler` serving files from a directory.
+///
+/// Responds to `GET` and `HEAD` with `etag`, `last-modified` and `content-type`,
+/// answers conditional requests with `304` and `Range` requests with `206`.
+/// Requests for directories are served with index file.
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    root: PathBuf,
+    prefix: String,
+    index: Option<String>,
+}
+
+impl StaticFiles {
+    /// Serve files from `root` directory.
+    pub fn new(root: impl Into<PathBuf>) -> StaticFiles {
+        StaticFiles {
+            root: root.into(),
+            prefix: String::new(),
+            index: Some("index.html".to_owned()),
+        }
+    }
+
+    /// Strip `prefix` from request paths, e. g. path handler is registered with.
+    /// By default request path is used as is.
+    pub fn set_prefix(&mut self, prefix: &str) {
+        self.prefix = prefix.trim_end_matches('/').to_owned();
+    }
+
+    /// File served for directory requests, default is `index.html`.
+    /// Directories are not served if `None`.
+    pub fn set_index(&mut self, index: Option<&str>) {
+        self.index = index.map(|i| i.to_owned());
+    }
+
+    /// File for request path, with its metadata.
+    async fn resolve(&self, path: &str) -> Option<(PathBuf, fs::Metadata)> {
+        let path = match path.strip_prefix(&self.prefix[..]) {
+            Some(path) if path.is_empty() || path.starts_with('/') => path,
+            _ => return None,
+        };
+        let mut file_path = self.root.join(relative_path(path)?);
+        let mut metadata = tokio::fs::metadata(&file_path).await.ok()?;
+        if metadata.is_dir() {
+            file_path.push(self.index.as_ref()?);
+            metadata = tokio::fs::metadata(&file_path).await.ok()?;
+        }
+        if !metadata.is_file() {
+            return None;
+        }
+        Some((file_path, metadata))
+    }
+
+    fn serve(
+        &self,
+        req: &Headers,
+        resp: &mut ServerResponse,
+        file_path: &Path,
+        metadata: &fs::Metadata,
+    ) -> result::Result<()> {
+        let len = metadata.len();
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        let etag = format!("\"{:x}-{:x}\"", mtime.unwrap_or(0), len);
+        let last_modified = mtime.map(format_http_date);
+
+        let mut headers = Headers::new();
+        headers.add("etag", etag.clone());
+        if let Some(last_modified) = &last_modified {
+            headers.add("last-modified", last_modified.clone());
+        }
+
+        let not_modified = match req.get_opt("if-none-match") {
+            Some(inm) => if_none_match(inm, &etag),
+            // exact match, because `last-modified` sent is the only value clients know
+            None => {
+                last_modified.is_some()
+                    && req.get_opt("if-modified-since") == last_modified.as_deref()
+            }
+        };
+        if not_modified {
+            let mut not_modified = Headers::new_status(304);
+            not_modified.extend(headers);
+            resp.send_headers_end_of_stream(not_modified)?;
+            return Ok(());
+        }
+
+        headers.add("content-type", guess_content_type(file_path));
+        headers.add("accept-ranges", "bytes");
+        let range = match ByteRange::from_request(req, len, Some(&etag), last_modified.as_deref()) {
+            Ok(range) => range,
+            Err(e) => {
+                resp.send_headers_end_of_stream(e.range_not_satisfiable_416(len))?;
+                return Ok(());
+            }
+        };
+        let mut resp_headers = match range {
+            Some(range) => range.partial_content_206(len),
+            None => {
+                let mut ok = Headers::ok_200();
+                ok.add("content-length", format!("{}", len));
+                ok
+            }
+        };
+        resp_headers.extend(headers);
+
+        if req.method() == "HEAD" || range.map_or(len, |r| r.byte_count()) == 0 {
+            resp.send_headers_end_of_stream(resp_headers)?;
+            return Ok(());
+        }
+        resp.send_headers(resp_headers)?;
+        if let Err(e) = resp.send_file(file_path, range) {
+            warn!("failed to send file {}: {}", file_path.display(), e);
+            resp.reset(ErrorCode::InternalError)?;
+        }
+        Ok(())
+    }
+}
+
+impl ServerHandler for StaticFiles {
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        mut resp: ServerResponse,
+    ) -> result::Result<()> {
+        match req.headers.method() {
+            "GET" | "HEAD" => {}
+            _ => {
+                let mut headers = Headers::new_status(405);
+                headers.add("allow", "GET, HEAD");
+                resp.send_headers_end_of_stream(headers)?;
+                return Ok(());
+            }
+        }
+        // do not block connection event loop on file system access
+        let files = self.clone();
+        let headers = req.headers;
+        context.loop_remote().spawn(async move {
+            let r = match files.resolve(headers.path()).await {
+                Some((file_path, metadata)) => {
+                    files.serve(&headers, &mut resp, &file_path, &metadata)
+                }
+                None => resp.send_not_found_404("not found").map_err(From::from),
+            };
+            if let Err(e) = r {
+                warn!("failed to serve {}: {}", headers.path(), e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relative() {
+        assert_eq!(Some(PathBuf::from("a/b")), relative_path("/a/./b?c"));
+        assert_eq!(Some(PathBuf::from("a b")), relative_path("/a%20b"));
+        assert_eq!(Some(PathBuf::new()), relative_path("/"));
+        assert_eq!(None, relative_path("/a/../../b"));
+        assert_eq!(None, relative_path("/%2E%2E/b"));
+    }
+
+    #[test]
+    fn content_type() {
+        assert_eq!("image/png", guess_content_type(Path::new("a/b.PNG")));
+        assert_eq!(
+            "application/octet-stream",
+            guess_content_type(Path::new("a"))
+        );
+    }
+
+    #[test]
+    fn none_match() {
+        assert!(if_none_match("\"a\", W/\"b\"", "\"b\""));
+        assert!(if_none_match("*", "\"b\""));
+        assert!(!if_none_match("\"a\"", "\"b\""));
+    }
+}
@@ -0,0 +1,260 @@
+//! TLS stack abstraction.
+//!
+//! Client and server perform TLS handshake through `TlsConnectorLike`
+//! and `TlsAcceptorLike` traits, so any TLS implementation can be plugged in.
+//! These traits are implemented for all `tls_api` connectors and acceptors.
+
+#[cfg(feature = "tls-rustls")]
+use std::any::Any;
+use std::error::Error as std_Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+use crate::result;
+
+#[cfg(feature = "tls-rustls")]
+mod pin;
+#[cfg(feature = "tls-rustls")]
+pub(crate) mod rustls;
+mod x509;
+
+#[cfg(feature = "tls-rustls")]
+pub(crate) use self::pin::check_pins;
+#[cfg(feature = "tls-rustls")]
+pub use self::pin::spki_sha256;
+pub use self::x509::certificate_dns_names;
+
+/// Byte stream TLS runs over, or encrypted stream after TLS handshake.
+pub trait TlsIo: AsyncRead + AsyncWrite + fmt::Debug + Unpin + Send + Sync + 'static {}
+
+impl<S: AsyncRead + AsyncWrite + fmt::Debug + Unpin + Send + Sync + 'static> TlsIo for S {}
+
+/// Future of TLS handshake.
+pub type TlsHandshakeFuture<'a> =
+    Pin<Box<dyn Future<Output = result::Result<TlsHandshake>> + Send + 'a>>;
+
+/// Result of successful TLS handshake.
+pub struct TlsHandshake {
+    /// Encrypted stream.
+    pub stream: Pin<Box<dyn TlsIo>>,
+    /// Protocol selected with ALPN, `None` if ALPN was not negotiated.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Certificates presented by the peer, `None` if the peer did not
+    /// present any or TLS stack does not expose them.
+    pub peer_certificates: Option<PeerCertificates>,
+    /// Negotiated protocol version, `None` if TLS stack does not expose it.
+    pub version: Option<String>,
+    /// Negotiated cipher suite, `None` if TLS stack does not expose it.
+    pub cipher_suite: Option<String>,
+    /// Server name sent by the client with SNI.
+    pub sni: Option<String>,
+}
+
+impl TlsHandshake {
+    /// Parameters negotiated during the handshake.
+    pub fn session_info(&self) -> TlsSessionInfo {
+        TlsSessionInfo {
+            alpn_protocol: self.alpn_protocol.clone(),
+            version: self.version.clone(),
+            cipher_suite: self.cipher_suite.clone(),
+            sni: self.sni.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for TlsHandshake {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TlsHandshake")
+            .field("alpn_protocol", &self.alpn_protocol)
+            .field("peer_certificates", &self.peer_certificates)
+            .field("version", &self.version)
+            .field("cipher_suite", &self.cipher_suite)
+            .field("sni", &self.sni)
+            .finish()
+    }
+}
+
+/// Parameters negotiated during TLS handshake of a connection.
+///
+/// Only ALPN is reported by all TLS stacks, other fields
+/// are filled by rustls only.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsSessionInfo {
+    /// Protocol selected with ALPN, `None` if ALPN was not negotiated.
+    pub alpn_protocol: Option<Vec<u8>>,
+    /// Protocol version, e. g. `TLSv1_3`.
+    pub version: Option<String>,
+    /// Cipher suite name, e. g. `TLS13_AES_128_GCM_SHA256`.
+    pub cipher_suite: Option<String>,
+    /// Server name requested by the client with SNI.
+    pub sni: Option<String>,
+}
+
+/// Hostname, certificate chain and private key (PKCS#8 or PKCS#1),
+/// all DER-encoded, served to clients requesting that hostname with SNI.
+pub type SniCertificate<'a> = (&'a str, &'a [&'a [u8]], &'a [u8]);
+
+/// Certificate chain presented by TLS peer.
+#[derive(Clone)]
+pub struct PeerCertificates {
+    /// DER-encoded certificates, end-entity certificate first.
+    pub cert_chain: Vec<Vec<u8>>,
+    /// Whether the chain was verified against trusted roots during handshake.
+    pub verified: bool,
+}
+
+impl PeerCertificates {
+    /// End-entity certificate of the peer.
+    pub fn end_entity(&self) -> Option<&[u8]> {
+        self.cert_chain.first().map(Vec::as_slice)
+    }
+}
+
+impl fmt::Debug for PeerCertificates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PeerCertificates")
+            .field("cert_chain", &self.cert_chain.len())
+            .field("verified", &self.verified)
+            .finish()
+    }
+}
+
+/// Client side of TLS stack.
+pub trait TlsConnectorLike: Send + Sync + 'static {
+    /// Perform client handshake with server `domain` over `stream`.
+    fn connect<'a>(
+        &'a self,
+        domain: &'a str,
+        stream: Pin<Box<dyn TlsIo>>,
+    ) -> TlsHandshakeFuture<'a>;
+}
+
+/// Server side of TLS stack.
+pub trait TlsAcceptorLike: Send + Sync + 'static {
+    /// Perform server handshake over `stream`.
+    fn accept<'a>(&'a self, stream: Pin<Box<dyn TlsIo>>) -> TlsHandshakeFuture<'a>;
+}
+
+/// Erase connector type.
+///
+/// `tls_api_rustls` connector is replaced with equivalent connector
+/// which reports server certificates.
+pub(crate) fn connector_like<C: TlsConnectorLike>(connector: C) -> Arc<dyn TlsConnectorLike> {
+    #[cfg(feature = "tls-rustls")]
+    {
+        let any: &dyn Any = &connector;
+        if let Some(connector) = any.downcast_ref::<tls_api_rustls::TlsConnector>() {
+            return Arc::new(self::rustls::RustlsConnector(connector.config.clone()));
+        }
+    }
+    Arc::new(connector)
+}
+
+/// Erase acceptor type.
+///
+/// `tls_api_rustls` acceptor is replaced with equivalent acceptor
+/// which reports client certificates and session parameters.
+pub(crate) fn acceptor_like<A: TlsAcceptorLike>(acceptor: A) -> Arc<dyn TlsAcceptorLike> {
+    #[cfg(feature = "tls-rustls")]
+    {
+        let any: &dyn Any = &acceptor;
+        if let Some(acceptor) = any.downcast_ref::<tls_api_rustls::TlsAcceptor>() {
+            return Arc::new(self::rustls::PeerCertificatesAcceptor(acceptor.0.clone()));
+        }
+    }
+    Arc::new(acceptor)
+}
+
+impl<C: tls_api::TlsConnector + Sync> TlsConnectorLike for C {
+    fn connect<'a>(
+        &'a self,
+        domain: &'a str,
+        stream: Pin<Box<dyn TlsIo>>,
+    ) -> TlsHandshakeFuture<'a> {
+        Box::pin(async move {
+            let tls_stream = tls_api::TlsConnector::connect(self, domain, stream).await?;
+            Ok(TlsHandshake {
+                alpn_protocol: tls_stream.get_alpn_protocol(),
+                peer_certificates: None,
+                version: None,
+                cipher_suite: None,
+                sni: Some(domain.to_owned()),
+                stream: Box::pin(tls_stream),
+            })
+        })
+    }
+}
+
+impl<A: tls_api::TlsAcceptor + Sync> TlsAcceptorLike for A {
+    fn accept<'a>(&'a self, stream: Pin<Box<dyn TlsIo>>) -> TlsHandshakeFuture<'a> {
+        Box::pin(async move {
+            let tls_stream = tls_api::TlsAcceptor::accept(self, stream).await?;
+            Ok(TlsHandshake {
+                alpn_protocol: tls_stream.get_alpn_protocol(),
+                peer_certificates: None,
+                version: None,
+                cipher_suite: None,
+                sni: None,
+                stream: Box::pin(tls_stream),
+            })
+        })
+    }
+}
+
+/// Client certificate chain and private key (PKCS#8 or PKCS#1), all DER-encoded.
+#[derive(Clone)]
+pub struct ClientCertificate {
+    pub cert_chain: Vec<Vec<u8>>,
+    pub key: Vec<u8>,
+}
+
+impl fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field("cert_chain", &self.cert_chain.len())
+            .finish()
+    }
+}
+
+/// Selects client certificate when server requests client authentication.
+///
+/// Called during each TLS handshake, so different connections
+/// may present different certificates.
+pub trait ClientCertificateResolver: Send + Sync + 'static {
+    /// `acceptable_issuers` are DER-encoded distinguished names of CAs
+    /// accepted by the server. `None` continues handshake without
+    /// client certificate.
+    fn resolve(&self, acceptable_issuers: &[&[u8]]) -> Option<ClientCertificate>;
+}
+
+impl ClientCertificateResolver for ClientCertificate {
+    fn resolve(&self, _acceptable_issuers: &[&[u8]]) -> Option<ClientCertificate> {
+        Some(self.clone())
+    }
+}
+
+impl<F> ClientCertificateResolver for F
+where
+    F: Fn(&[&[u8]]) -> Option<ClientCertificate> + Send + Sync + 'static,
+{
+    fn resolve(&self, acceptable_issuers: &[&[u8]]) -> Option<ClientCertificate> {
+        self(acceptable_issuers)
+    }
+}
+
+/// Payload of `io::Error` converted to `Error::ClientCertificateRejected`.
+#[derive(Debug)]
+pub(crate) struct ClientCertificateRejected;
+
+impl fmt::Display for ClientCertificateRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "server rejected client certificate")
+    }
+}
+
+impl std_Error for ClientCertificateRejected {}
@@ -0,0 +1,56 @@
+//! Server certificate pinning.
+
+use ring::digest;
+
+use crate::error::Error;
+use crate::result;
+use crate::tls::x509::subject_public_key_info;
+use crate::tls::PeerCertificates;
+
+/// SHA-256 hash of `SubjectPublicKeyInfo` of DER-encoded X.509 certificate,
+/// as used in `ClientConf::pinned_spki_sha256`.
+///
+/// `None` if certificate cannot be parsed.
+pub fn spki_sha256(cert: &[u8]) -> Option<[u8; 32]> {
+    let spki = subject_public_key_info(cert)?;
+    let mut hash = [0; 32];
+    hash.copy_from_slice(digest::digest(&digest::SHA256, spki).as_ref());
+    Some(hash)
+}
+
+/// Succeeds if any certificate in server chain matches any of `pins`.
+pub(crate) fn check_pins(
+    pins: &[[u8; 32]],
+    peer_certificates: Option<&PeerCertificates>,
+) -> result::Result<()> {
+    let matched = peer_certificates.is_some_and(|peer| {
+        peer.cert_chain
+            .iter()
+            .filter_map(|cert| spki_sha256(cert))
+            .any(|hash| pins.contains(&hash))
+    });
+    if matched {
+        Ok(())
+    } else {
+        Err(Error::PinMismatch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::tls::x509::test::CERT;
+
+    #[test]
+    fn pins() {
+        let pin = spki_sha256(CERT).unwrap();
+        let peer = PeerCertificates {
+            cert_chain: vec![b"garbage".to_vec(), CERT.to_vec()],
+            verified: true,
+        };
+        assert!(check_pins(&[[0; 32], pin], Some(&peer)).is_ok());
+        assert!(check_pins(&[[0; 32]], Some(&peer)).is_err());
+        assert!(check_pins(&[pin], None).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! Certificate handling with rustls.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future;
+
+use rustls::internal::msgs::enums::AlertDescription;
+use rustls::sign;
+use rustls::SignatureScheme;
+use rustls::TLSError;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+use crate::error::Error;
+use crate::result;
+use crate::tls::ClientCertificateRejected;
+use crate::tls::ClientCertificateResolver;
+use crate::tls::PeerCertificates;
+use crate::tls::SniCertificate;
+use crate::tls::TlsAcceptorLike;
+use crate::tls::TlsConnectorLike;
+use crate::tls::TlsHandshake;
+use crate::tls::TlsHandshakeFuture;
+use crate::tls::TlsIo;
+
+/// `ClientCertificateResolver` as rustls resolver.
+pub(crate) struct RustlsClientCertResolver<R: ClientCertificateResolver>(pub R);
+
+impl<R: ClientCertificateResolver> rustls::ResolvesClientCert for RustlsClientCertResolver<R> {
+    fn resolve(
+        &self,
+        acceptable_issuers: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<sign::CertifiedKey> {
+        let cert = self.0.resolve(acceptable_issuers)?;
+        let key = match sign::any_supported_type(&rustls::PrivateKey(cert.key)) {
+            Ok(key) => key,
+            Err(()) => {
+                warn!("unsupported client certificate private key");
+                return None;
+            }
+        };
+        let cert_chain = cert
+            .cert_chain
+            .into_iter()
+            .map(rustls::Certificate)
+            .collect();
+        Some(sign::CertifiedKey::new(cert_chain, Arc::new(key)))
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// Server certificate resolver selecting certificate by SNI hostname.
+pub(crate) fn sni_cert_resolver(
+    certs: &[SniCertificate],
+) -> result::Result<rustls::ResolvesServerCertUsingSNI> {
+    let mut resolver = rustls::ResolvesServerCertUsingSNI::new();
+    for &(hostname, cert_chain, key) in certs {
+        let key = sign::any_supported_type(&rustls::PrivateKey(key.to_vec())).map_err(|()| {
+            Error::TlsError(tls_api::Error::new_other(&format!(
+                "unsupported private key for {}",
+                hostname
+            )))
+        })?;
+        let cert_chain = cert_chain
+            .iter()
+            .map(|c| rustls::Certificate(c.to_vec()))
+            .collect();
+        resolver
+            .add(hostname, sign::CertifiedKey::new(cert_chain, Arc::new(key)))
+            .map_err(|e| Error::TlsError(tls_api::Error::new(e)))?;
+    }
+    Ok(resolver)
+}
+
+/// Alert sent by server which did not accept client certificate.
+fn is_client_certificate_rejection(error: &io::Error) -> bool {
+    matches!(
+        error.get_ref().and_then(|e| e.downcast_ref::<TLSError>()),
+        Some(TLSError::AlertReceived(
+            AlertDescription::BadCertificate
+                | AlertDescription::UnsupportedCertificate
+                | AlertDescription::CertificateRevoked
+                | AlertDescription::CertificateExpired
+                | AlertDescription::CertificateUnknown
+                | AlertDescription::UnknownCA
+                | AlertDescription::AccessDenied
+                | AlertDescription::CertificateRequired
+        ))
+    )
+}
+
+fn map_client_certificate_rejection(error: io::Error) -> io::Error {
+    if is_client_certificate_rejection(&error) {
+        io::Error::new(io::ErrorKind::PermissionDenied, ClientCertificateRejected)
+    } else {
+        error
+    }
+}
+
+fn tls_error(error: io::Error) -> Error {
+    Error::TlsError(tls_api::Error::new(error))
+}
+
+/// rustls connector reporting certificates presented by servers.
+pub(crate) struct RustlsConnector(pub Arc<rustls::ClientConfig>);
+
+impl RustlsConnector {
+    async fn handshake(
+        &self,
+        domain: &str,
+        stream: Pin<Box<dyn TlsIo>>,
+    ) -> io::Result<RustlsStream<rustls::ClientSession>> {
+        let dns_name = webpki::DNSNameRef::try_from_ascii_str(domain)
+            .map_err(|()| io::Error::new(io::ErrorKind::InvalidInput, "invalid domain name"))?;
+        RustlsStream {
+            io: stream,
+            session: rustls::ClientSession::new(&self.0, dns_name),
+        }
+        .handshake()
+        .await
+    }
+}
+
+impl TlsConnectorLike for RustlsConnector {
+    fn connect<'a>(
+        &'a self,
+        domain: &'a str,
+        stream: Pin<Box<dyn TlsIo>>,
+    ) -> TlsHandshakeFuture<'a> {
+        Box::pin(async move {
+            let stream = self.handshake(domain, stream).await.map_err(tls_error)?;
+            Ok(stream.into_handshake(Some(domain.to_owned()), |s| s))
+        })
+    }
+}
+
+/// rustls connector presenting client certificate.
+///
+/// With TLS 1.3 server verifies client certificate after client
+/// considers handshake complete, so rejection is also detected
+/// when reading from the connection.
+pub(crate) struct ClientAuthConnector(pub RustlsConnector);
+
+impl TlsConnectorLike for ClientAuthConnector {
+    fn connect<'a>(
+        &'a self,
+        domain: &'a str,
+        stream: Pin<Box<dyn TlsIo>>,
+    ) -> TlsHandshakeFuture<'a> {
+        Box::pin(async move {
+            match self.0.handshake(domain, stream).await {
+                Ok(stream) => Ok(stream.into_handshake(Some(domain.to_owned()), ClientAuthStream)),
+                Err(e) if is_client_certificate_rejection(&e) => {
+                    Err(Error::ClientCertificateRejected)
+                }
+                Err(e) => Err(tls_error(e)),
+            }
+        })
+    }
+}
+
+struct ClientAuthStream<S>(S);
+
+impl<S: fmt::Debug> fmt::Debug for ClientAuthStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ClientAuthStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0)
+            .poll_read(cx, buf)
+            .map_err(map_client_certificate_rejection)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ClientAuthStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0)
+            .poll_write(cx, buf)
+            .map_err(map_client_certificate_rejection)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0)
+            .poll_flush(cx)
+            .map_err(map_client_certificate_rejection)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+/// Adapts async IO to `io::Read` and `io::Write` expected by rustls,
+/// `Poll::Pending` is reported as `io::ErrorKind::WouldBlock`.
+struct SyncIo<'a, 'b> {
+    io: &'a mut Pin<Box<dyn TlsIo>>,
+    cx: &'a mut Context<'b>,
+}
+
+impl<'a, 'b> io::Read for SyncIo<'a, 'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.io.as_mut().poll_read(self.cx, buf) {
+            Poll::Ready(r) => r,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<'a, 'b> io::Write for SyncIo<'a, 'b> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.io.as_mut().poll_write(self.cx, buf) {
+            Poll::Ready(r) => r,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.io.as_mut().poll_flush(self.cx) {
+            Poll::Ready(r) => r,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+fn would_block_to_pending<T>(r: io::Result<T>) -> Poll<io::Result<T>> {
+    match r {
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+        r => Poll::Ready(r),
+    }
+}
+
+/// rustls session over async IO.
+///
+/// Unlike `tls_api_rustls::TlsStream` keeps the session accessible
+/// after handshake, so peer certificates can be extracted.
+struct RustlsStream<S: rustls::Session + 'static> {
+    io: Pin<Box<dyn TlsIo>>,
+    session: S,
+}
+
+impl<S: rustls::Session + 'static> RustlsStream<S> {
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.session.is_handshaking() {
+            let mut io = SyncIo {
+                io: &mut self.io,
+                cx,
+            };
+            match self.session.complete_io(&mut io) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    async fn handshake(mut self) -> io::Result<Self> {
+        future::poll_fn(|cx| self.poll_handshake(cx)).await?;
+        Ok(self)
+    }
+
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.session.get_alpn_protocol().map(Vec::from)
+    }
+
+    /// rustls fails handshake if peer certificate is not verified
+    /// by configured verifier.
+    fn peer_certificates(&self) -> Option<PeerCertificates> {
+        self.session
+            .get_peer_certificates()
+            .map(|chain| PeerCertificates {
+                cert_chain: chain.into_iter().map(|c| c.0).collect(),
+                verified: true,
+            })
+    }
+
+    /// Handshake result, encrypted stream is `wrap`ped.
+    fn into_handshake<T: TlsIo>(
+        self,
+        sni: Option<String>,
+        wrap: impl FnOnce(Self) -> T,
+    ) -> TlsHandshake {
+        TlsHandshake {
+            alpn_protocol: self.alpn_protocol(),
+            peer_certificates: self.peer_certificates(),
+            version: self
+                .session
+                .get_protocol_version()
+                .map(|v| format!("{:?}", v)),
+            cipher_suite: self
+                .session
+                .get_negotiated_ciphersuite()
+                .map(|s| format!("{:?}", s.suite)),
+            sni,
+            stream: Box::pin(wrap(self)),
+        }
+    }
+
+    fn with_stream<R>(
+        &mut self,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(&mut rustls::Stream<S, SyncIo>) -> io::Result<R>,
+    ) -> Poll<io::Result<R>> {
+        let mut io = SyncIo {
+            io: &mut self.io,
+            cx,
+        };
+        would_block_to_pending(f(&mut rustls::Stream::new(&mut self.session, &mut io)))
+    }
+}
+
+impl<S: rustls::Session + 'static> fmt::Debug for RustlsStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RustlsStream")
+            .field("io", &self.io)
+            .finish()
+    }
+}
+
+impl<S: rustls::Session + Unpin + 'static> AsyncRead for RustlsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.with_stream(cx, |stream| io::Read::read(stream, buf))
+    }
+}
+
+impl<S: rustls::Session + Unpin + 'static> AsyncWrite for RustlsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.with_stream(cx, |stream| io::Write::write(stream, buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.with_stream(cx, |stream| io::Write::flush(stream))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// rustls acceptor reporting certificates presented by clients.
+pub(crate) struct PeerCertificatesAcceptor(pub Arc<rustls::ServerConfig>);
+
+impl TlsAcceptorLike for PeerCertificatesAcceptor {
+    fn accept<'a>(&'a self, stream: Pin<Box<dyn TlsIo>>) -> TlsHandshakeFuture<'a> {
+        Box::pin(async move {
+            let stream = RustlsStream {
+                io: stream,
+                session: rustls::ServerSession::new(&self.0),
+            }
+            .handshake()
+            .await
+            .map_err(tls_error)?;
+            let sni = stream.session.get_sni_hostname().map(str::to_owned);
+            Ok(stream.into_handshake(sni, |s| s))
+        })
+    }
+}
@@ -0,0 +1,179 @@
+//! Minimal parsing of DER-encoded X.509 certificates.
+
+const DER_BOOLEAN: u8 = 0x01;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_OID: u8 = 0x06;
+const DER_SEQUENCE: u8 = 0x30;
+/// `[0]` tag of X.509 `version` field.
+const DER_CONTEXT_0: u8 = 0xa0;
+/// `[3]` tag of X.509 `extensions` field.
+const DER_CONTEXT_3: u8 = 0xa3;
+/// `[2]` tag of `dNSName` choice of `GeneralName`.
+const DER_CONTEXT_PRIMITIVE_2: u8 = 0x82;
+
+/// Encoded OID 2.5.29.17 of subject alternative name extension.
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Split DER value into tag, contents and the remaining input.
+fn der_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+    let len = if len < 0x80 {
+        len as usize
+    } else {
+        let octets = (len & 0x7f) as usize;
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let len = input[..octets]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        input = &input[octets..];
+        len
+    };
+    if input.len() < len {
+        return None;
+    }
+    Some((tag, &input[..len], &input[len..]))
+}
+
+/// Value with expected tag, returns contents and the remaining input.
+fn der_expect(tag: u8, input: &[u8]) -> Option<(&[u8], &[u8])> {
+    match der_tlv(input)? {
+        (t, contents, rest) if t == tag => Some((contents, rest)),
+        _ => None,
+    }
+}
+
+/// Fields of `TBSCertificate` after `subject`, starting with `subjectPublicKeyInfo`.
+fn tbs_certificate_from_spki(cert: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = der_expect(DER_SEQUENCE, cert)?;
+    let (mut rest, _) = der_expect(DER_SEQUENCE, cert)?;
+    if let (DER_CONTEXT_0, _, after_version) = der_tlv(rest)? {
+        rest = after_version;
+    }
+    // serialNumber, signature, issuer, validity, subject
+    for _ in 0..5 {
+        rest = der_tlv(rest)?.2;
+    }
+    Some(rest)
+}
+
+/// DER-encoded `SubjectPublicKeyInfo` of DER-encoded X.509 certificate.
+#[cfg_attr(not(feature = "tls-rustls"), allow(dead_code))]
+pub(crate) fn subject_public_key_info(cert: &[u8]) -> Option<&[u8]> {
+    let rest = tbs_certificate_from_spki(cert)?;
+    let (_, after_spki) = der_expect(DER_SEQUENCE, rest)?;
+    Some(&rest[..rest.len() - after_spki.len()])
+}
+
+/// DNS names from subject alternative name extension.
+fn subject_alt_dns_names(cert: &[u8]) -> Option<Vec<String>> {
+    let rest = tbs_certificate_from_spki(cert)?;
+    // skip subjectPublicKeyInfo, then optional issuerUniqueID and subjectUniqueID
+    let mut rest = der_tlv(rest)?.2;
+    let extensions = loop {
+        match der_tlv(rest)? {
+            (DER_CONTEXT_3, extensions, _) => break extensions,
+            (_, _, next) => rest = next,
+        }
+    };
+    let (mut extensions, _) = der_expect(DER_SEQUENCE, extensions)?;
+    while !extensions.is_empty() {
+        let (extension, next) = der_expect(DER_SEQUENCE, extensions)?;
+        extensions = next;
+        let (oid, mut value) = der_expect(DER_OID, extension)?;
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        if let (DER_BOOLEAN, _, after_critical) = der_tlv(value)? {
+            value = after_critical;
+        }
+        let (general_names, _) = der_expect(DER_OCTET_STRING, value)?;
+        let (mut general_names, _) = der_expect(DER_SEQUENCE, general_names)?;
+        let mut dns_names = Vec::new();
+        while !general_names.is_empty() {
+            let (tag, name, next) = der_tlv(general_names)?;
+            general_names = next;
+            if tag == DER_CONTEXT_PRIMITIVE_2 {
+                dns_names.push(String::from_utf8(name.to_vec()).ok()?);
+            }
+        }
+        return Some(dns_names);
+    }
+    Some(Vec::new())
+}
+
+/// DNS names from subject alternative name extension of DER-encoded
+/// X.509 certificate, as used in `ConnCoverage`.
+///
+/// Empty if certificate has no such names or cannot be parsed.
+pub fn certificate_dns_names(cert: &[u8]) -> Vec<String> {
+    subject_alt_dns_names(cert).unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    // Skeleton of a certificate with empty fields
+    const SPKI: &[u8] = &[0x30, 0x03, 0x02, 0x01, 0x07];
+    pub(crate) const CERT: &[u8] = &[
+        0x30, 0x17, // Certificate
+        0x30, 0x15, // TBSCertificate
+        0xa0, 0x03, 0x02, 0x01, 0x02, // version
+        0x02, 0x01, 0x01, // serialNumber
+        0x30, 0x00, // signature
+        0x30, 0x00, // issuer
+        0x30, 0x00, // validity
+        0x30, 0x00, // subject
+        0x30, 0x03, 0x02, 0x01, 0x07, // subjectPublicKeyInfo
+    ];
+
+    #[test]
+    fn parse_spki() {
+        assert_eq!(Some(SPKI), subject_public_key_info(CERT));
+        // Certificate without version field
+        let mut v1 = vec![0x30, 0x12, 0x30, 0x10];
+        v1.extend_from_slice(&CERT[9..]);
+        assert_eq!(Some(SPKI), subject_public_key_info(&v1));
+        assert_eq!(None, subject_public_key_info(&CERT[..CERT.len() - 1]));
+        assert_eq!(None, subject_public_key_info(b"garbage"));
+    }
+
+    #[test]
+    fn long_form_length() {
+        let mut value = vec![0x04, 0x81, 0x80];
+        value.extend_from_slice(&[0; 0x80]);
+        value.push(0xff);
+        let (tag, contents, rest) = der_tlv(&value).unwrap();
+        assert_eq!(0x04, tag);
+        assert_eq!(0x80, contents.len());
+        assert_eq!(&[0xff], rest);
+    }
+
+    #[test]
+    fn dns_names() {
+        let mut cert = CERT[4..].to_vec();
+        cert.extend_from_slice(&[
+            0xa3, 0x23, // extensions
+            0x30, 0x21, // Extensions
+            0x30, 0x1f, // Extension
+            0x06, 0x03, 0x55, 0x1d, 0x11, // subjectAltName
+            0x01, 0x01, 0xff, // critical
+            0x04, 0x15, // extnValue
+            0x30, 0x13, // GeneralNames
+            0x82, 0x05, b'a', b'.', b'c', b'o', b'm', // dNSName
+            0x87, 0x04, 0x7f, 0x00, 0x00, 0x01, // iPAddress
+            0x82, 0x04, b'*', b'.', b'b', b'c', // dNSName
+        ]);
+        let mut tbs = vec![0x30, cert.len() as u8];
+        tbs.extend_from_slice(&cert);
+        let mut cert = vec![0x30, tbs.len() as u8];
+        cert.extend_from_slice(&tbs);
+
+        assert_eq!(vec!["a.com", "*.bc"], certificate_dns_names(&cert));
+        assert_eq!(Some(SPKI), subject_public_key_info(&cert));
+        assert!(certificate_dns_names(CERT).is_empty());
+    }
+}
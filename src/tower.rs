@@ -0,0 +1,112 @@
+//! `tower::Service` adapters, enabled with `tower` feature.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::future;
+use tower_service::Service;
+
+use crate::error;
+use crate::result;
+use crate::server::handler::ServerHandler;
+use crate::server::handler::ServerHandlerContext;
+use crate::server::req::ServerRequest;
+use crate::Client;
+use crate::Headers;
+use crate::HttpStreamAfterHeaders;
+use crate::ServerResponse;
+
+/// Client as a `tower::Service`.
+///
+/// Request URI may be absolute or contain only path, then `:authority`
+/// is taken from `host` header and `:scheme` is the client scheme.
+/// The client is always ready, requests are queued by the client itself.
+impl Service<http::Request<Bytes>> for Client {
+    type Response = http::Response<HttpStreamAfterHeaders>;
+    type Error = error::Error;
+    type Future = Pin<
+        Box<dyn Future<Output = result::Result<http::Response<HttpStreamAfterHeaders>>> + Send>,
+    >;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<result::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Bytes>) -> Self::Future {
+        let response = self.start_http_request(request);
+        Box::pin(async move {
+            let (headers, body) = response.await?;
+            let parts = http::response::Parts::try_from(&headers)?;
+            Ok(http::Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// `ServerHandler` serving requests with a `tower::Service`.
+///
+/// The service is cloned for each request, response body is any type
+/// convertible to `HttpStreamAfterHeaders`, e. g. `Bytes`.
+/// Service errors are logged and answered with `500`.
+pub struct TowerHandler<S> {
+    service: S,
+}
+
+impl<S> TowerHandler<S> {
+    pub fn new(service: S) -> TowerHandler<S> {
+        TowerHandler { service }
+    }
+}
+
+impl<S, B> ServerHandler for TowerHandler<S>
+where
+    S: Service<http::Request<HttpStreamAfterHeaders>, Response = http::Response<B>>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+    S::Error: fmt::Display + Send,
+    B: Into<HttpStreamAfterHeaders> + Send + 'static,
+{
+    fn start_request(
+        &self,
+        context: ServerHandlerContext,
+        req: ServerRequest,
+        mut resp: ServerResponse,
+    ) -> result::Result<()> {
+        let parts = match http::request::Parts::try_from(&req.headers) {
+            Ok(parts) => parts,
+            Err(e) => {
+                info!("serving 400 for {}", e);
+                drop(resp.send_headers_end_of_stream(Headers::new_status(400)));
+                return Ok(());
+            }
+        };
+        let request = http::Request::from_parts(parts, req.make_stream());
+
+        let mut service = self.service.clone();
+        context.loop_remote().spawn(async move {
+            let response = match future::poll_fn(|cx| service.poll_ready(cx)).await {
+                Ok(()) => service.call(request).await,
+                Err(e) => Err(e),
+            };
+            match response {
+                Ok(response) => {
+                    let (parts, body) = response.into_parts();
+                    drop(resp.send_headers(Headers::from(&parts)));
+                    drop(resp.pull_from_stream(body.into()));
+                }
+                Err(e) => {
+                    warn!("service failed: {}", e);
+                    drop(resp.send_internal_error_500("service failed"));
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Byte streams over `CONNECT` requests (RFC 7540 section 8.3).
+
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::stream::Stream;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+use crate::error;
+use crate::result;
+use crate::ClientRequest;
+use crate::DataOrTrailers;
+use crate::Headers;
+use crate::HttpStreamAfterHeaders;
+use crate::ServerRequest;
+use crate::ServerResponse;
+
+/// Request is a plain (not extended) `CONNECT`.
+pub fn is_connect_request(headers: &Headers) -> bool {
+    headers.is_connect() && headers.get_opt(":protocol").is_none()
+}
+
+enum TunnelSender {
+    Client(ClientRequest),
+    Server(ServerResponse),
+}
+
+impl TunnelSender {
+    fn reserve_capacity(&mut self, capacity: usize) {
+        match self {
+            TunnelSender::Client(s) => s.reserve_capacity(capacity),
+            TunnelSender::Server(s) => s.reserve_capacity(capacity),
+        }
+    }
+
+    fn poll_capacity(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let r = match self {
+            TunnelSender::Client(s) => s.poll_capacity(cx),
+            TunnelSender::Server(s) => s.poll_capacity(cx),
+        };
+        r.map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, format!("{:?}", e)))
+    }
+
+    fn send_data(&mut self, data: Bytes, end_stream: bool) -> io::Result<()> {
+        let r = match (self, end_stream) {
+            (TunnelSender::Client(s), false) => s.send_data(data),
+            (TunnelSender::Client(s), true) => s.send_data_end_of_stream(data),
+            (TunnelSender::Server(s), false) => s.send_data(data),
+            (TunnelSender::Server(s), true) => s.send_data_end_of_stream(data),
+        };
+        r.map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, format!("{:?}", e)))
+    }
+}
+
+/// Bidirectional byte stream carried in `DATA` frames of a `CONNECT` stream.
+///
+/// Shutdown sends `END_STREAM`, reading returns EOF after peer
+/// sent `END_STREAM`.
+pub struct Tunnel {
+    sender: TunnelSender,
+    recv: HttpStreamAfterHeaders,
+    recv_buf: Bytes,
+    shutdown: bool,
+}
+
+impl Tunnel {
+    pub(crate) fn new_client(sender: ClientRequest, recv: HttpStreamAfterHeaders) -> Tunnel {
+        Tunnel {
+            sender: TunnelSender::Client(sender),
+            recv,
+            recv_buf: Bytes::new(),
+            shutdown: false,
+        }
+    }
+
+    pub(crate) fn new_server(sender: ServerResponse, recv: HttpStreamAfterHeaders) -> Tunnel {
+        Tunnel {
+            sender: TunnelSender::Server(sender),
+            recv,
+            recv_buf: Bytes::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Accept a `CONNECT` request, responding with `200`.
+    ///
+    /// Request which is not a plain `CONNECT` request
+    /// is answered with `400` and error is returned.
+    pub fn accept(req: ServerRequest, mut resp: ServerResponse) -> result::Result<Tunnel> {
+        if !is_connect_request(&req.headers) {
+            resp.send_headers_end_of_stream(Headers::new_status(400))?;
+            return Err(error::Error::NotConnectRequest);
+        }
+
+        resp.send_headers(Headers::ok_200())?;
+        Ok(Tunnel::new_server(resp, req.make_stream()))
+    }
+}
+
+impl AsyncRead for Tunnel {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        while self.recv_buf.is_empty() {
+            match Pin::new(&mut self.recv).poll_next(cx) {
+                Poll::Ready(Some(Ok(DataOrTrailers::Data(data, _)))) => self.recv_buf = data,
+                Poll::Ready(Some(Ok(DataOrTrailers::Trailers(_)))) | Poll::Ready(None) => {
+                    return Poll::Ready(Ok(0))
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(io::Error::other(e))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let n = buf.len().min(self.recv_buf.len());
+        buf[..n].copy_from_slice(&self.recv_buf.split_to(n));
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for Tunnel {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        self.sender.reserve_capacity(buf.len());
+        let n = match self.sender.poll_capacity(cx) {
+            Poll::Ready(Ok(0)) => {
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            Poll::Ready(Ok(n)) => n,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        self.sender
+            .send_data(Bytes::copy_from_slice(&buf[..n]), false)?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // data is queued to the connection when written
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.shutdown {
+            self.shutdown = true;
+            self.sender.send_data(Bytes::new(), true)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
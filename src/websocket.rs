@@ -0,0 +1,164 @@
+//! WebSockets over HTTP/2 extended `CONNECT` (RFC 8441).
+
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncWrite;
+
+use crate::error;
+use crate::result;
+use crate::solicit_async::HttpFutureSend;
+use crate::tunnel::Tunnel;
+use crate::Client;
+use crate::Header;
+use crate::Headers;
+use crate::ServerRequest;
+use crate::ServerResponse;
+
+/// WebSocket protocol version sent in `sec-websocket-version`.
+const WEBSOCKET_VERSION: &str = "13";
+
+/// Request is extended `CONNECT` for a WebSocket.
+pub fn is_websocket_request(headers: &Headers) -> bool {
+    headers.is_connect() && headers.get_opt(":protocol") == Some("websocket")
+}
+
+/// `sec-websocket-protocol` values.
+fn subprotocols(headers: &Headers) -> Vec<String> {
+    headers
+        .iter()
+        .filter(|h| h.name() == "sec-websocket-protocol")
+        .flat_map(|h| {
+            String::from_utf8_lossy(h.value())
+                .split(',')
+                .map(|p| p.trim().to_owned())
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Byte stream of a WebSocket established with extended `CONNECT`.
+///
+/// The stream carries WebSocket frames (RFC 6455 section 5) and
+/// can be passed to a WebSocket protocol implementation as an
+/// already connected socket, e. g. `tungstenite::WebSocket::from_raw_socket`.
+///
+/// Shutdown sends `END_STREAM`, reading returns EOF after peer
+/// sent `END_STREAM`.
+pub struct WebSocketStream {
+    tunnel: Tunnel,
+    protocol: Option<String>,
+}
+
+impl WebSocketStream {
+    /// Open a WebSocket to given path.
+    ///
+    /// `protocols` are offered in `sec-websocket-protocol`.
+    /// Server must enable extended `CONNECT`
+    /// (`ServerConf::enable_connect_protocol`),
+    /// otherwise `Error::ExtendedConnectNotSupported` is returned.
+    pub fn connect(
+        client: &Client,
+        path: &str,
+        authority: &str,
+        protocols: &[&str],
+    ) -> HttpFutureSend<WebSocketStream> {
+        let mut headers = Headers::from_vec(vec![
+            Header::new(":method", "CONNECT"),
+            Header::new(":protocol", "websocket"),
+            Header::new(":scheme", client.http_scheme().as_bytes()),
+            Header::new(":path", path.to_owned()),
+            Header::new(":authority", authority.to_owned()),
+        ]);
+        headers.add("sec-websocket-version", WEBSOCKET_VERSION);
+        if !protocols.is_empty() {
+            headers.add("sec-websocket-protocol", protocols.join(", "));
+        }
+
+        let start = client.start_request(headers, None, None, false);
+        Box::pin(async move {
+            let (sender, response) = start.await?;
+            let (headers, recv) = response.0.await?;
+            let status = headers.status();
+            if status / 100 != 2 {
+                return Err(error::Error::ExtendedConnectRejected(status));
+            }
+            Ok(WebSocketStream {
+                tunnel: Tunnel::new_client(sender, recv),
+                protocol: headers
+                    .get_opt("sec-websocket-protocol")
+                    .map(|p| p.to_owned()),
+            })
+        })
+    }
+
+    /// Accept a WebSocket request, responding with `200`.
+    ///
+    /// `select_protocol` picks a subprotocol from protocols offered
+    /// by the client. Request which is not a WebSocket request
+    /// is answered with `400` and error is returned.
+    pub fn accept<F>(
+        req: ServerRequest,
+        mut resp: ServerResponse,
+        select_protocol: F,
+    ) -> result::Result<WebSocketStream>
+    where
+        F: FnOnce(&[String]) -> Option<String>,
+    {
+        if !is_websocket_request(&req.headers)
+            || req.headers.get_opt("sec-websocket-version") != Some(WEBSOCKET_VERSION)
+        {
+            resp.send_headers_end_of_stream(Headers::new_status(400))?;
+            return Err(error::Error::NotWebSocketRequest);
+        }
+
+        let protocol = select_protocol(&subprotocols(&req.headers));
+        let mut headers = Headers::ok_200();
+        if let Some(protocol) = &protocol {
+            headers.add("sec-websocket-protocol", protocol.clone());
+        }
+        resp.send_headers(headers)?;
+
+        Ok(WebSocketStream {
+            tunnel: Tunnel::new_server(resp, req.make_stream()),
+            protocol,
+        })
+    }
+
+    /// Subprotocol selected by the server.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+}
+
+impl AsyncRead for WebSocketStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.tunnel).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for WebSocketStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.tunnel).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tunnel).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.tunnel).poll_shutdown(cx)
+    }
+}